@@ -0,0 +1,203 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::aliases::U40;
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::Log;
+use alloy::sol_types::SolCall;
+use alloy::transports::Transport;
+use dria_oracle_contracts::Multicall3::{self, Call3};
+use dria_oracle_contracts::OracleCoordinator::{
+    self, getFeeReturn, getResponsesCall, getValidationsCall, nextTaskIdCall, requestsCall,
+    LLMOracleTaskParameters, OracleCoordinatorInstance, StatusUpdate,
+};
+use dria_oracle_contracts::MULTICALL3_ADDRESS;
+use eyre::{eyre, Context, Result};
+
+use crate::{TaskResponse, TaskView};
+
+/// Maximum number of tasks to read within a single multicall, each task takes three calls.
+pub const TASKS_PER_MULTICALL: usize = 50;
+
+/// A read-only client for the coordinator, which does not need a wallet.
+///
+/// It is generic over the provider, so that any transport can be used, e.g. HTTP within a browser.
+#[derive(Debug, Clone)]
+pub struct OracleClient<T, P> {
+    pub coordinator: OracleCoordinatorInstance<T, P>,
+}
+
+impl<T, P> OracleClient<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T> + Clone,
+{
+    /// Creates a client for the coordinator at the given address.
+    pub fn new(coordinator_address: Address, provider: P) -> Self {
+        Self {
+            coordinator: OracleCoordinator::new(coordinator_address, provider),
+        }
+    }
+
+    /// Get task info for the given task id.
+    pub async fn get_task(&self, task_id: U256) -> Result<TaskView> {
+        self.get_tasks(&[task_id])
+            .await?
+            .pop()
+            .ok_or_else(|| eyre!("Task {} could not be read.", task_id))
+    }
+
+    /// Get task info for the given task ids, in the same order.
+    ///
+    /// The request, responses and validations of many tasks are read within a single RPC call
+    /// using [`Multicall3`], falling back to reading each task separately if that is not available.
+    pub async fn get_tasks(&self, task_ids: &[U256]) -> Result<Vec<TaskView>> {
+        if let Some(task_id) = task_ids.iter().find(|id| id.is_zero()) {
+            return Err(eyre!("Task ID must be non-zero, got {}.", task_id));
+        }
+
+        let mut tasks = Vec::with_capacity(task_ids.len());
+        for chunk in task_ids.chunks(TASKS_PER_MULTICALL) {
+            match self.get_tasks_multicall(chunk).await {
+                Ok(chunk_tasks) => tasks.extend(chunk_tasks),
+                Err(e) => {
+                    log::debug!("Multicall failed, reading tasks one by one: {:#}", e);
+                    tasks.extend(self.get_tasks_sequential(chunk).await?);
+                }
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    /// Reads the given tasks within a single multicall, along with the next task id to validate them.
+    async fn get_tasks_multicall(&self, task_ids: &[U256]) -> Result<Vec<TaskView>> {
+        let coordinator = *self.coordinator.address();
+        let call = |call_data: Vec<u8>| Call3 {
+            target: coordinator,
+            allowFailure: false,
+            callData: Bytes::from(call_data),
+        };
+
+        let mut calls = vec![call(nextTaskIdCall {}.abi_encode())];
+        for task_id in task_ids {
+            calls.push(call(requestsCall { taskId: *task_id }.abi_encode()));
+            calls.push(call(getResponsesCall { taskId: *task_id }.abi_encode()));
+            calls.push(call(getValidationsCall { taskId: *task_id }.abi_encode()));
+        }
+
+        let results = Multicall3::new(MULTICALL3_ADDRESS, self.coordinator.provider())
+            .aggregate3(calls)
+            .call()
+            .await
+            .wrap_err("could not call multicall")?
+            .returnData;
+        if results.len() != 1 + 3 * task_ids.len() {
+            return Err(eyre!("unexpected number of multicall results"));
+        }
+
+        let next_task_id = nextTaskIdCall::abi_decode_returns(&results[0].returnData, true)?._0;
+        let mut tasks = Vec::with_capacity(task_ids.len());
+        for (task_id, results) in task_ids.iter().zip(results[1..].chunks(3)) {
+            if *task_id >= next_task_id {
+                return Err(eyre!("Task with id {} has not been created yet.", task_id));
+            }
+
+            let request = requestsCall::abi_decode_returns(&results[0].returnData, true)?;
+            let responses = getResponsesCall::abi_decode_returns(&results[1].returnData, true)?._0;
+            let validations =
+                getValidationsCall::abi_decode_returns(&results[2].returnData, true)?._0;
+            tasks.push(TaskView::from_contract(
+                *task_id,
+                request,
+                responses,
+                validations,
+            )?);
+        }
+
+        Ok(tasks)
+    }
+
+    /// Reads the given tasks one by one, with three calls for each task.
+    async fn get_tasks_sequential(&self, task_ids: &[U256]) -> Result<Vec<TaskView>> {
+        let next_task_id = self.coordinator.nextTaskId().call().await?._0;
+
+        let mut tasks = Vec::with_capacity(task_ids.len());
+        for task_id in task_ids {
+            if *task_id >= next_task_id {
+                return Err(eyre!("Task with id {} has not been created yet.", task_id));
+            }
+
+            let request = self.coordinator.requests(*task_id).call().await?;
+            let responses = self.coordinator.getResponses(*task_id).call().await?._0;
+            let validations = self.coordinator.getValidations(*task_id).call().await?._0;
+            tasks.push(TaskView::from_contract(
+                *task_id,
+                request,
+                responses,
+                validations,
+            )?);
+        }
+
+        Ok(tasks)
+    }
+
+    /// Get previous tasks within the range of blocks.
+    pub async fn get_tasks_in_range(
+        &self,
+        from_block: impl Into<BlockNumberOrTag>,
+        to_block: impl Into<BlockNumberOrTag>,
+    ) -> Result<Vec<(StatusUpdate, Log)>> {
+        let tasks = self
+            .coordinator
+            .StatusUpdate_filter()
+            .from_block(from_block)
+            .to_block(to_block)
+            .query()
+            .await?;
+
+        Ok(tasks)
+    }
+
+    /// Get the best response of a task.
+    ///
+    /// Uses the coordinator's `getBestResponse`, but if that call reverts (e.g. the task is not
+    /// finalized yet) the highest-scored response is selected locally, preferring the earliest one on ties.
+    pub async fn get_task_best_response(&self, task_id: U256) -> Result<TaskResponse> {
+        match self.coordinator.getBestResponse(task_id).call().await {
+            Ok(best) => Ok(TaskResponse::from(best._0)),
+            Err(e) => {
+                log::debug!(
+                    "Could not get best response of task {} from the coordinator: {}",
+                    task_id,
+                    e
+                );
+
+                let responses = self.coordinator.getResponses(task_id).call().await?._0;
+                responses
+                    .into_iter()
+                    .rev()
+                    .max_by_key(|response| response.score)
+                    .map(TaskResponse::from)
+                    .ok_or_else(|| eyre!("Task {} has no responses.", task_id))
+            }
+        }
+    }
+
+    /// Returns the fees of a request with the given parameters.
+    pub async fn get_request_fee(
+        &self,
+        difficulty: u8,
+        num_gens: u64,
+        num_vals: u64,
+    ) -> Result<getFeeReturn> {
+        let parameters = LLMOracleTaskParameters {
+            difficulty,
+            numGenerations: U40::from(num_gens),
+            numValidations: U40::from(num_vals),
+        };
+
+        let fees = self.coordinator.getFee(parameters).call().await?;
+
+        Ok(fees)
+    }
+}
@@ -0,0 +1,130 @@
+use alloy::primitives::Bytes;
+use dria_oracle_storage::ArweaveKey;
+use eyre::{eyre, Context, Result};
+
+/// Base URL to download the values stored on Arweave.
+const ARWEAVE_DOWNLOAD_BASE_URL: &str = "https://arweave.net";
+
+/// Downloads the value of the given key, which is decoded like the storage does.
+///
+/// This only needs a plain HTTP request, so unlike the storage itself it works on `wasm32` as well.
+async fn download(key: &ArweaveKey) -> Result<String> {
+    if key.version > ArweaveKey::VERSION {
+        return Err(eyre!(
+            "Key has version {}, while at most {} is supported",
+            key.version,
+            ArweaveKey::VERSION
+        ));
+    }
+    if let Some(enc) = &key.enc {
+        return Err(eyre!("Value is encrypted with {:?}", enc));
+    }
+
+    let url = format!("{}/{}", ARWEAVE_DOWNLOAD_BASE_URL, key.arweave);
+    log::debug!("Fetching from Arweave: {}", url);
+
+    let response = reqwest::get(&url)
+        .await
+        .wrap_err("failed to fetch from Arweave")?;
+    if !response.status().is_success() {
+        return Err(eyre!("Failed to fetch from Arweave: {}", response.status()));
+    }
+
+    let value = key.decode(&response.bytes().await?)?;
+    Ok(String::from_utf8_lossy(&value).into_owned())
+}
+
+/// Content of a task as it appears on-chain, which is either the value itself
+/// or a key to the value within the external storage.
+#[derive(Debug, Clone, Default)]
+pub struct StoredContent {
+    /// The content as it is on-chain.
+    pub raw: String,
+    /// The content downloaded from the storage, `None` if it is not resolved
+    /// or if the content is not a storage key.
+    pub resolved: Option<String>,
+}
+
+impl StoredContent {
+    /// Returns `true` if the raw content is a storage key.
+    pub fn is_storage_key(&self) -> bool {
+        ArweaveKey::parse(&self.raw).is_some()
+    }
+
+    /// Returns the resolved content if it exists, otherwise the raw content.
+    pub fn content(&self) -> &str {
+        self.resolved.as_deref().unwrap_or(&self.raw)
+    }
+
+    /// Returns the content, downloading it from the storage if it is a storage key.
+    pub async fn fetch(&self) -> Result<String> {
+        if let Some(resolved) = &self.resolved {
+            return Ok(resolved.clone());
+        }
+
+        match ArweaveKey::parse(&self.raw) {
+            Some(key) => download(&key)
+                .await
+                .wrap_err("could not download from Arweave"),
+            None => Ok(self.raw.clone()),
+        }
+    }
+
+    /// Downloads the content from the storage if it is a storage key.
+    ///
+    /// Failed downloads are logged and leave the content unresolved.
+    pub async fn resolve(&mut self) {
+        if !self.is_storage_key() || self.resolved.is_some() {
+            return;
+        }
+
+        match self.fetch().await {
+            Ok(value) => self.resolved = Some(value),
+            Err(e) => log::warn!("Could not download {} from storage: {}", self.raw, e),
+        }
+    }
+}
+
+impl From<&Bytes> for StoredContent {
+    fn from(bytes: &Bytes) -> Self {
+        Self {
+            raw: String::from_utf8_lossy(bytes).to_string(),
+            resolved: None,
+        }
+    }
+}
+
+impl std::fmt::Display for StoredContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.content())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_key() {
+        let key = StoredContent::from(&Bytes::from_static(
+            br#"{"arweave":"Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA"}"#,
+        ));
+        assert!(key.is_storage_key());
+
+        let key = ArweaveKey::parse(
+            r#"{"arweave":"Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA","v":2,"item":"output"}"#,
+        )
+        .unwrap();
+        assert_eq!(key.item.as_deref(), Some("output"));
+
+        // keys of a newer version are still keys, but are not downloaded
+        let key = StoredContent::from(&Bytes::from_static(
+            br#"{"arweave":"Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA","v":3}"#,
+        ));
+        assert!(key.is_storage_key());
+
+        let value = StoredContent::from(&Bytes::from_static(b"hello"));
+        assert!(!value.is_storage_key());
+        assert_eq!(value.content(), "hello");
+    }
+}
@@ -0,0 +1,13 @@
+//! A read-only client for the Dria Oracle, to view tasks & fees without a wallet.
+//!
+//! It only depends on crates that compile to `wasm32-unknown-unknown`, so that it can be used within
+//! browser dashboards, with any provider such as the HTTP provider of `alloy`.
+
+mod client;
+pub use client::{OracleClient, TASKS_PER_MULTICALL};
+
+mod content;
+pub use content::StoredContent;
+
+mod task;
+pub use task::{TaskRequest, TaskResponse, TaskValidation, TaskView};
@@ -0,0 +1,138 @@
+use alloy::primitives::{Address, U256};
+use dria_oracle_contracts::OracleCoordinator::{
+    requestsReturn, LLMOracleTaskResponse, LLMOracleTaskValidation,
+};
+use dria_oracle_contracts::{bytes32_to_string, bytes_to_string, TaskStatus};
+use eyre::Result;
+
+use crate::StoredContent;
+
+/// A task request as stored within the coordinator.
+#[derive(Debug, Clone)]
+pub struct TaskRequest {
+    /// Address of the requester.
+    pub requester: Address,
+    /// Protocol name of the task.
+    pub protocol: String,
+    /// Current status of the task.
+    pub status: TaskStatus,
+    /// Difficulty of the proof-of-work for the responses.
+    pub difficulty: u8,
+    /// Number of generations requested.
+    pub num_generations: u64,
+    /// Number of validations requested per generation.
+    pub num_validations: u64,
+    /// Fee for each generation.
+    pub generator_fee: U256,
+    /// Fee for each validation.
+    pub validator_fee: U256,
+    /// Fee for the platform.
+    pub platform_fee: U256,
+    /// Task input.
+    pub input: StoredContent,
+    /// Models accepted for the task.
+    pub models: Vec<String>,
+}
+
+/// A generation response to a task.
+#[derive(Debug, Clone)]
+pub struct TaskResponse {
+    /// Address of the generator.
+    pub responder: Address,
+    /// Proof-of-work nonce.
+    pub nonce: U256,
+    /// Final score of the response, given by validations.
+    pub score: U256,
+    /// Output of the generation.
+    pub output: StoredContent,
+    /// Metadata of the generation.
+    pub metadata: StoredContent,
+}
+
+impl From<LLMOracleTaskResponse> for TaskResponse {
+    fn from(response: LLMOracleTaskResponse) -> Self {
+        Self {
+            responder: response.responder,
+            nonce: response.nonce,
+            score: response.score,
+            output: StoredContent::from(&response.output),
+            metadata: StoredContent::from(&response.metadata),
+        }
+    }
+}
+
+/// A validation of the responses to a task.
+#[derive(Debug, Clone)]
+pub struct TaskValidation {
+    /// Address of the validator.
+    pub validator: Address,
+    /// Proof-of-work nonce.
+    pub nonce: U256,
+    /// Scores for each response, in the same order as the responses.
+    pub scores: Vec<U256>,
+    /// Metadata of the validation.
+    pub metadata: StoredContent,
+}
+
+/// A task along with its responses and validations.
+#[derive(Debug, Clone)]
+pub struct TaskView {
+    /// Task id.
+    pub task_id: U256,
+    /// Request of the task.
+    pub request: TaskRequest,
+    /// Responses to the task.
+    pub responses: Vec<TaskResponse>,
+    /// Validations of the task.
+    pub validations: Vec<TaskValidation>,
+}
+
+impl TaskView {
+    /// Creates a task view from the values returned by the coordinator.
+    pub fn from_contract(
+        task_id: U256,
+        request: requestsReturn,
+        responses: Vec<LLMOracleTaskResponse>,
+        validations: Vec<LLMOracleTaskValidation>,
+    ) -> Result<Self> {
+        let models = bytes_to_string(&request.models)?;
+        Ok(Self {
+            task_id,
+            request: TaskRequest {
+                requester: request.requester,
+                protocol: bytes32_to_string(&request.protocol)?,
+                status: TaskStatus::try_from(request.status)?,
+                difficulty: request.parameters.difficulty,
+                num_generations: request.parameters.numGenerations.to(),
+                num_validations: request.parameters.numValidations.to(),
+                generator_fee: request.generatorFee,
+                validator_fee: request.validatorFee,
+                platform_fee: request.platformFee,
+                input: StoredContent::from(&request.input),
+                models: models.split(',').map(|m| m.trim().to_string()).collect(),
+            },
+            responses: responses.into_iter().map(TaskResponse::from).collect(),
+            validations: validations
+                .into_iter()
+                .map(|validation| TaskValidation {
+                    validator: validation.validator,
+                    nonce: validation.nonce,
+                    scores: validation.scores,
+                    metadata: StoredContent::from(&validation.metadata),
+                })
+                .collect(),
+        })
+    }
+
+    /// Downloads all contents of the task that are stored externally.
+    pub async fn resolve_storage(&mut self) {
+        self.request.input.resolve().await;
+        for response in &mut self.responses {
+            response.output.resolve().await;
+            response.metadata.resolve().await;
+        }
+        for validation in &mut self.validations {
+            validation.metadata.resolve().await;
+        }
+    }
+}
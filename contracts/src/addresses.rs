@@ -1,6 +1,8 @@
-use alloy::primitives::{address, Address};
+use alloy::primitives::Address;
 use alloy_chains::NamedChain;
 
+use super::get_deployment;
+
 /// Contract addresses.
 ///
 /// All contracts can be derived from the `coordinator` contract.
@@ -29,12 +31,7 @@ impl std::fmt::Display for ContractAddresses {
 /// Will return an error if the chain is not supported, i.e. a coordinator address
 /// is not deployed there.
 pub fn get_coordinator_address(chain: NamedChain) -> eyre::Result<Address> {
-    let addresses = match chain {
-        NamedChain::AnvilHardhat => address!("9fE46736679d2D9a65F0992F2272dE9f3c7fa6e0"),
-        NamedChain::BaseSepolia => address!("13f977bde221b470d3ae055cde7e1f84debfe202"),
-        NamedChain::Base => address!("17b6d1eddcd5f9ca19bb2ffed2f3deb6bd74bd20"),
-        _ => return Err(eyre::eyre!("Chain {} is not supported", chain)),
-    };
-
-    Ok(addresses)
+    get_deployment(chain)
+        .map(|deployment| deployment.coordinator)
+        .ok_or_else(|| eyre::eyre!("Chain {} is not supported", chain))
 }
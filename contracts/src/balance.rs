@@ -1,6 +1,12 @@
-use alloy::primitives::{utils::format_ether, Address, U256};
+use alloy::primitives::{
+    utils::{format_units, parse_units},
+    Address, U256,
+};
 use std::fmt::Display;
 
+/// Number of decimals of the native token (ETH), also the default for ERC20 tokens.
+const DEFAULT_DECIMALS: u8 = 18;
+
 /// A token balance contains amount, token symbol and the token address if its non-native token.
 #[derive(Debug)]
 pub struct TokenBalance {
@@ -10,16 +16,45 @@ pub struct TokenBalance {
     pub symbol: String,
     /// Token contract address, `None` if its ETH (native token).
     pub address: Option<Address>,
+    /// Token decimals, used when formatting the amount.
+    pub decimals: u8,
 }
 
 impl TokenBalance {
-    /// Create a new token result.
+    /// Create a new token result, assuming 18 decimals.
     pub fn new(amount: U256, symbol: impl ToString, address: Option<Address>) -> Self {
         Self {
             amount,
             symbol: symbol.to_string(),
             address,
+            decimals: DEFAULT_DECIMALS,
+        }
+    }
+
+    /// Change the decimals of the token.
+    pub fn with_decimals(mut self, decimals: u8) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Returns the amount formatted w.r.t token decimals, e.g. `1.5` for `1500000` with 6 decimals.
+    pub fn formatted(&self) -> String {
+        Self::format_amount(self.amount, self.decimals)
+    }
+
+    /// Formats an amount w.r.t given decimals.
+    pub fn format_amount(amount: U256, decimals: u8) -> String {
+        format_units(amount, decimals).unwrap_or_else(|_| amount.to_string())
+    }
+
+    /// Parses an amount given in token units w.r.t given decimals, e.g. `1500000` for `1.5` with 6 decimals.
+    pub fn parse_amount(value: &str, decimals: u8) -> eyre::Result<U256> {
+        let amount = parse_units(value, decimals)?;
+        if amount.is_negative() {
+            return Err(eyre::eyre!("Amount must not be negative, got {}.", value));
         }
+
+        Ok(amount.get_absolute())
     }
 }
 
@@ -28,9 +63,38 @@ impl Display for TokenBalance {
         write!(
             f,
             "{} {} {}",
-            format_ether(self.amount),
+            self.formatted(),
             self.symbol,
             self.address.map(|s| s.to_string()).unwrap_or_default() // empty-string if `None`
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatted_balance() {
+        let balance = TokenBalance::new(U256::from(1_500_000u64), "USDC", None).with_decimals(6);
+        assert_eq!(balance.formatted(), "1.500000");
+
+        let balance = TokenBalance::new(U256::from(10u64).pow(U256::from(18)), "WETH", None);
+        assert_eq!(balance.formatted(), "1.000000000000000000");
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(
+            TokenBalance::parse_amount("1.5", 6).unwrap(),
+            U256::from(1_500_000u64)
+        );
+        assert_eq!(
+            TokenBalance::parse_amount("2", 18).unwrap(),
+            U256::from(2u64) * U256::from(10u64).pow(U256::from(18))
+        );
+        assert!(TokenBalance::parse_amount("-1", 18).is_err());
+        assert!(TokenBalance::parse_amount("0.0000001", 6).is_err());
+        assert!(TokenBalance::parse_amount("abc", 18).is_err());
+    }
+}
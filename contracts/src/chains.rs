@@ -0,0 +1,78 @@
+use alloy::primitives::{address, Address};
+use alloy_chains::NamedChain;
+
+/// A known deployment of the oracle contracts on a chain.
+#[derive(Debug, Clone)]
+pub struct ChainDeployment {
+    /// The chain that the contracts are deployed at.
+    pub chain: NamedChain,
+    /// Oracle coordinator address.
+    pub coordinator: Address,
+    /// Fee token address, `None` if it is not fixed for the chain (e.g. local networks).
+    pub token: Option<Address>,
+    /// Base URL of the block explorer, `None` if there is no explorer (e.g. local networks).
+    pub explorer_url: Option<&'static str>,
+}
+
+impl std::fmt::Display for ChainDeployment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({})\n  Coordinator: {}\n  Token:       {}\n  Explorer:    {}",
+            self.chain,
+            self.chain as u64,
+            self.coordinator,
+            self.token.map(|t| t.to_string()).unwrap_or("-".to_string()),
+            self.explorer_url.unwrap_or("-")
+        )
+    }
+}
+
+/// All known deployments of the oracle contracts.
+pub const KNOWN_DEPLOYMENTS: [ChainDeployment; 3] = [
+    ChainDeployment {
+        chain: NamedChain::Base,
+        coordinator: address!("17b6d1eddcd5f9ca19bb2ffed2f3deb6bd74bd20"),
+        token: Some(address!("4200000000000000000000000000000000000006")),
+        explorer_url: Some("https://basescan.org"),
+    },
+    ChainDeployment {
+        chain: NamedChain::BaseSepolia,
+        coordinator: address!("13f977bde221b470d3ae055cde7e1f84debfe202"),
+        token: Some(address!("4200000000000000000000000000000000000006")),
+        explorer_url: Some("https://sepolia.basescan.org"),
+    },
+    ChainDeployment {
+        chain: NamedChain::AnvilHardhat,
+        coordinator: address!("9fE46736679d2D9a65F0992F2272dE9f3c7fa6e0"),
+        token: None,
+        explorer_url: None,
+    },
+];
+
+/// Returns the known deployment for a given chain, if any.
+pub fn get_deployment(chain: NamedChain) -> Option<&'static ChainDeployment> {
+    KNOWN_DEPLOYMENTS.iter().find(|d| d.chain == chain)
+}
+
+/// Returns the known deployment for a given chain id, if any.
+pub fn get_deployment_by_id(chain_id: u64) -> Option<&'static ChainDeployment> {
+    KNOWN_DEPLOYMENTS
+        .iter()
+        .find(|d| d.chain as u64 == chain_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_deployments() {
+        let base = get_deployment(NamedChain::Base).unwrap();
+        assert_eq!(base.chain as u64, 8453);
+        assert!(base.explorer_url.is_some());
+
+        assert!(get_deployment_by_id(84532).is_some());
+        assert!(get_deployment(NamedChain::Mainnet).is_none());
+    }
+}
@@ -0,0 +1,297 @@
+use alloy::contract::Error;
+use alloy::primitives::{b256, B256};
+use alloy::sol_types::{SolCall, SolError, SolEvent};
+
+use crate::{OracleCoordinator, OracleKind};
+
+/// Storage slot of the implementation address within an ERC1967 proxy, such as the coordinator,
+/// i.e. `keccak256("eip1967.proxy.implementation") - 1`.
+pub const ERC1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+/// Functions of the coordinator that are called by all kinds of oracles.
+static COMMON_FUNCTIONS: [(&str, [u8; 4]); 6] = [
+    ("requests", OracleCoordinator::requestsCall::SELECTOR),
+    (
+        "getResponses",
+        OracleCoordinator::getResponsesCall::SELECTOR,
+    ),
+    (
+        "getValidations",
+        OracleCoordinator::getValidationsCall::SELECTOR,
+    ),
+    ("nextTaskId", OracleCoordinator::nextTaskIdCall::SELECTOR),
+    ("feeToken", OracleCoordinator::feeTokenCall::SELECTOR),
+    ("registry", OracleCoordinator::registryCall::SELECTOR),
+];
+
+/// Events of the coordinator that are decoded by all kinds of oracles.
+static EVENTS: [(&str, B256); 3] = [
+    (
+        "StatusUpdate",
+        OracleCoordinator::StatusUpdate::SIGNATURE_HASH,
+    ),
+    ("Response", OracleCoordinator::Response::SIGNATURE_HASH),
+    ("Validation", OracleCoordinator::Validation::SIGNATURE_HASH),
+];
+
+/// Errors of the coordinator that are decoded by all kinds of oracles, see [`crate::contract_error_report`].
+static ERRORS: [(&str, [u8; 4]); 6] = [
+    (
+        "AlreadyResponded",
+        OracleCoordinator::AlreadyResponded::SELECTOR,
+    ),
+    (
+        "InsufficientFees",
+        OracleCoordinator::InsufficientFees::SELECTOR,
+    ),
+    ("InvalidNonce", OracleCoordinator::InvalidNonce::SELECTOR),
+    (
+        "InvalidTaskStatus",
+        OracleCoordinator::InvalidTaskStatus::SELECTOR,
+    ),
+    (
+        "InvalidValidation",
+        OracleCoordinator::InvalidValidation::SELECTOR,
+    ),
+    ("NotRegistered", OracleCoordinator::NotRegistered::SELECTOR),
+];
+
+/// The parts of the coordinator interface that are missing from a deployed implementation or have
+/// changed within it, found by probing its bytecode (see [`CoordinatorInterface::probe`]) and by
+/// calling its views (see [`CoordinatorInterface::check_return`]).
+///
+/// The coordinator is upgradable, so its ABI may drift from the one that this node is built with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoordinatorInterface {
+    /// Functions that the node calls, which are not found within the implementation.
+    pub missing_functions: Vec<&'static str>,
+    /// Functions whose return values could not be decoded, i.e. their return types have changed.
+    pub mismatched_functions: Vec<&'static str>,
+    /// Events that the node decodes, which are not found within the implementation.
+    pub missing_events: Vec<&'static str>,
+    /// Errors that the node decodes, which are not found within the implementation.
+    ///
+    /// The reverts are still caught, only their reasons can not be told apart.
+    pub missing_errors: Vec<&'static str>,
+}
+
+impl CoordinatorInterface {
+    /// Probes the runtime bytecode of a coordinator implementation for the functions & events that
+    /// the node uses.
+    ///
+    /// The function selectors are pushed by the dispatcher of the contract, the event topics
+    /// are pushed when they are emitted and the error selectors are pushed when they are reverted
+    /// with, so a missing push means that the ABI has changed.
+    ///
+    /// The return types are not within the bytecode, see [`Self::check_return`] for them.
+    pub fn probe(code: &[u8]) -> Self {
+        let respond = ("respond", OracleCoordinator::respondCall::SELECTOR);
+        let validate = ("validate", OracleCoordinator::validateCall::SELECTOR);
+
+        Self {
+            missing_functions: COMMON_FUNCTIONS
+                .into_iter()
+                .chain([respond, validate])
+                .filter(|(_, selector)| !contains_push(code, selector))
+                .map(|(name, _)| name)
+                .collect(),
+            mismatched_functions: Vec::new(),
+            missing_events: EVENTS
+                .into_iter()
+                .filter(|(_, topic)| !contains_push(code, topic.as_slice()))
+                .map(|(name, _)| name)
+                .collect(),
+            missing_errors: ERRORS
+                .into_iter()
+                .filter(|(_, selector)| !contains_push(code, selector))
+                .map(|(name, _)| name)
+                .collect(),
+        }
+    }
+
+    /// Checks the result of a call to the function with the given name, which is marked as
+    /// mismatched if its return value could not be decoded.
+    ///
+    /// Returns the value if it is decoded, `None` if it is mismatched, and the other errors as is,
+    /// e.g. if the RPC has failed.
+    pub fn check_return<T>(
+        &mut self,
+        name: &'static str,
+        result: Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::AbiError(_)) => {
+                self.mismatched_functions.push(name);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns `true` if all functions, events & errors that the node uses are found and match.
+    pub fn is_compatible(&self) -> bool {
+        self.missing_functions.is_empty()
+            && self.mismatched_functions.is_empty()
+            && self.missing_events.is_empty()
+            && self.missing_errors.is_empty()
+    }
+
+    /// Returns `true` if the tasks of the given kind can still be handled, i.e. the common
+    /// functions & events along with the response function of the kind are found and match.
+    ///
+    /// Missing errors do not prevent any kind, as the reverts are still caught.
+    pub fn supports(&self, kind: OracleKind) -> bool {
+        let function = match kind {
+            OracleKind::Generator => "respond",
+            OracleKind::Validator => "validate",
+        };
+
+        self.missing_events.is_empty()
+            && self.mismatched_functions.is_empty()
+            && self
+                .missing_functions
+                .iter()
+                .all(|name| *name == "respond" || *name == "validate")
+            && !self.missing_functions.contains(&function)
+    }
+}
+
+impl std::fmt::Display for CoordinatorInterface {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_compatible() {
+            return write!(f, "all functions, events & errors match");
+        }
+
+        let mut parts = Vec::new();
+        if !self.missing_functions.is_empty() {
+            parts.push(format!(
+                "missing functions: {}",
+                self.missing_functions.join(", ")
+            ));
+        }
+        if !self.mismatched_functions.is_empty() {
+            parts.push(format!(
+                "mismatched returns: {}",
+                self.mismatched_functions.join(", ")
+            ));
+        }
+        if !self.missing_events.is_empty() {
+            parts.push(format!(
+                "missing events: {}",
+                self.missing_events.join(", ")
+            ));
+        }
+        if !self.missing_errors.is_empty() {
+            parts.push(format!(
+                "missing errors: {}",
+                self.missing_errors.join(", ")
+            ));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// Returns `true` if the bytecode pushes the given value, where the leading zeros are
+/// not pushed as the compiler uses the shortest push for a constant.
+fn contains_push(code: &[u8], value: &[u8]) -> bool {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    // PUSH1 is 0x60, and PUSH32 is 0x7f
+    let opcode = 0x5f + value.len() as u8;
+
+    code.windows(value.len() + 1)
+        .any(|window| window[0] == opcode && &window[1..] == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a bytecode that pushes the given values, along with some other opcodes.
+    fn bytecode(values: &[&[u8]]) -> Vec<u8> {
+        let mut code = vec![0x60, 0x80, 0x60, 0x40, 0x52];
+        for value in values {
+            let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+            code.push(0x5f + (value.len() - start) as u8);
+            code.extend_from_slice(&value[start..]);
+            code.push(0x14); // EQ
+        }
+        code
+    }
+
+    #[test]
+    fn test_probe_coordinator_interface() {
+        let mut values = COMMON_FUNCTIONS
+            .iter()
+            .map(|(_, selector)| selector.as_slice())
+            .chain(EVENTS.iter().map(|(_, topic)| topic.as_slice()))
+            .chain(ERRORS.iter().map(|(_, selector)| selector.as_slice()))
+            .collect::<Vec<_>>();
+        values.push(&OracleCoordinator::respondCall::SELECTOR);
+
+        // validate is missing, so only generators are supported
+        let interface = CoordinatorInterface::probe(&bytecode(&values));
+        assert_eq!(interface.missing_functions, vec!["validate"]);
+        assert!(!interface.is_compatible());
+        assert!(interface.supports(OracleKind::Generator));
+        assert!(!interface.supports(OracleKind::Validator));
+        assert_eq!(interface.to_string(), "missing functions: validate");
+
+        values.push(&OracleCoordinator::validateCall::SELECTOR);
+        let interface = CoordinatorInterface::probe(&bytecode(&values));
+        assert!(interface.is_compatible());
+
+        // a changed return type breaks all kinds
+        let mut mismatched = interface.clone();
+        let decoded = mismatched
+            .check_return::<()>(
+                "requests",
+                Err(Error::AbiError(alloy::sol_types::Error::Overrun.into())),
+            )
+            .unwrap();
+        assert!(decoded.is_none());
+        assert_eq!(mismatched.mismatched_functions, vec!["requests"]);
+        assert!(!mismatched.supports(OracleKind::Generator));
+        assert_eq!(mismatched.to_string(), "mismatched returns: requests");
+        assert!(mismatched
+            .check_return::<()>("nextTaskId", Err(Error::ContractNotDeployed))
+            .is_err());
+        assert_eq!(mismatched.check_return("feeToken", Ok(1)).unwrap(), Some(1));
+
+        // a missing error is reported, but does not prevent any kind
+        values.retain(|value| *value != OracleCoordinator::InvalidNonce::SELECTOR.as_slice());
+        let interface = CoordinatorInterface::probe(&bytecode(&values));
+        assert_eq!(interface.missing_errors, vec!["InvalidNonce"]);
+        assert!(!interface.is_compatible());
+        assert!(interface.supports(OracleKind::Validator));
+
+        // an event that does not decode breaks all kinds
+        values.retain(|value| *value != OracleCoordinator::StatusUpdate::SIGNATURE_HASH.as_slice());
+        let interface = CoordinatorInterface::probe(&bytecode(&values));
+        assert_eq!(interface.missing_events, vec!["StatusUpdate"]);
+        assert!(!interface.supports(OracleKind::Generator));
+    }
+
+    #[test]
+    fn test_contains_push() {
+        // leading zeros are not pushed
+        assert!(contains_push(
+            &[0x62, 0x12, 0x34, 0x56],
+            &[0x00, 0x12, 0x34, 0x56]
+        ));
+        assert!(!contains_push(
+            &[0x63, 0x00, 0x12, 0x34, 0x56],
+            &[0x00, 0x12, 0x34, 0x56]
+        ));
+        assert!(contains_push(
+            &[0x63, 0x12, 0x34, 0x56, 0x78],
+            &[0x12, 0x34, 0x56, 0x78]
+        ));
+        assert!(!contains_push(
+            &[0x12, 0x34, 0x56, 0x78],
+            &[0x12, 0x34, 0x56, 0x78]
+        ));
+    }
+}
@@ -1,8 +1,10 @@
 use alloy::contract::Error;
 use alloy::primitives::utils::format_ether;
+use alloy::primitives::{keccak256, Address, Bytes, U256};
+use alloy::sol_types::SolValue;
 use eyre::{eyre, ErrReport};
 
-use super::OracleCoordinator::OracleCoordinatorErrors;
+use super::OracleCoordinator::{self, OracleCoordinatorErrors};
 use super::OracleRegistry::OracleRegistryErrors;
 use super::ERC20::ERC20Errors;
 
@@ -202,3 +204,213 @@ impl From<OracleCoordinatorErrors> for ErrReport {
         }
     }
 }
+
+/// Maximum size of a transaction that is accepted to the mempool by most nodes (128KB),
+/// responses that are larger than this should be uploaded to the storage instead.
+pub const MAX_RESPONSE_BYTES: usize = 128 * 1024;
+
+/// Returns the proof-of-work candidate of a nonce, computed the same way as the coordinator:
+///
+/// ```solidity
+/// keccak256(abi.encodePacked(taskId, input, requester, responder, nonce))
+/// ```
+pub fn nonce_candidate(
+    task_id: &U256,
+    input: &Bytes,
+    requester: &Address,
+    responder: &Address,
+    nonce: &U256,
+) -> U256 {
+    let mut message = Vec::new();
+    task_id.abi_encode_packed_to(&mut message);
+    input.abi_encode_packed_to(&mut message);
+    requester.abi_encode_packed_to(&mut message);
+    responder.abi_encode_packed_to(&mut message);
+    nonce.abi_encode_packed_to(&mut message);
+
+    U256::from_be_bytes(*keccak256(message)) // big-endian!
+}
+
+/// Checks the nonce of a response the same way as the coordinator, i.e. its candidate must be
+/// at most `(2^256-1) / 2^difficulty`.
+///
+/// Returns the `InvalidNonce` error that the coordinator would revert with otherwise.
+pub fn check_nonce(
+    task_id: &U256,
+    difficulty: u8,
+    input: &Bytes,
+    requester: &Address,
+    responder: &Address,
+    nonce: &U256,
+) -> Result<(), OracleCoordinatorErrors> {
+    let target = U256::MAX >> difficulty;
+    if nonce_candidate(task_id, input, requester, responder, nonce) > target {
+        return Err(OracleCoordinatorErrors::InvalidNonce(
+            OracleCoordinator::InvalidNonce {
+                taskId: *task_id,
+                nonce: *nonce,
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that there is a score for each generation of the task.
+///
+/// Returns the `InvalidValidation` error that the coordinator would revert with otherwise.
+pub fn check_validation_scores(
+    task_id: &U256,
+    validator: &Address,
+    scores: &[U256],
+    num_generations: u64,
+) -> Result<(), OracleCoordinatorErrors> {
+    if scores.len() as u64 != num_generations {
+        return Err(OracleCoordinatorErrors::InvalidValidation(
+            OracleCoordinator::InvalidValidation {
+                taskId: *task_id,
+                validator: *validator,
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that the total size of the given payloads fits in a transaction, see [`MAX_RESPONSE_BYTES`].
+///
+/// This is not checked by the coordinator, but such a transaction would be dropped by the nodes anyways.
+pub fn check_response_size(task_id: &U256, payloads: &[&Bytes]) -> Result<(), ErrReport> {
+    let size = payloads.iter().map(|p| p.len()).sum::<usize>();
+    if size > MAX_RESPONSE_BYTES {
+        return Err(eyre!(
+            "Response for task {} is too large ({} bytes, max {} bytes)",
+            task_id,
+            size,
+            MAX_RESPONSE_BYTES
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns a rough estimate of the gas that the given payloads add to a response transaction.
+///
+/// The payloads are both sent as calldata (16 gas per non-zero byte & 4 gas per zero byte) and stored by
+/// the coordinator (about 20k gas per new 32-byte slot), where the latter is the dominant cost.
+pub fn estimate_response_gas(payloads: &[&Bytes]) -> u64 {
+    const NONZERO_BYTE_GAS: u64 = 16;
+    const ZERO_BYTE_GAS: u64 = 4;
+    const STORAGE_SLOT_GAS: u64 = 20_000;
+
+    payloads
+        .iter()
+        .map(|payload| {
+            let calldata_gas = payload
+                .iter()
+                .map(|b| {
+                    if *b == 0 {
+                        ZERO_BYTE_GAS
+                    } else {
+                        NONZERO_BYTE_GAS
+                    }
+                })
+                .sum::<u64>();
+            let storage_gas = (payload.len() as u64).div_ceil(32) * STORAGE_SLOT_GAS;
+
+            calldata_gas + storage_gas
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    const REQUESTER: Address = address!("0877022A137b8E8CE1C3020B9f047651dD02E37B");
+    const RESPONDER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+    #[test]
+    fn test_check_nonce() {
+        let task_id = U256::from(1);
+        let input = Bytes::from_static(b"hello");
+        let difficulty = 8;
+
+        let check = |responder: &Address, difficulty: u8, nonce: u64| {
+            check_nonce(
+                &task_id,
+                difficulty,
+                &input,
+                &REQUESTER,
+                responder,
+                &U256::from(nonce),
+            )
+        };
+
+        // the check agrees with the target of the coordinator
+        for nonce in 0u64..512 {
+            let candidate =
+                nonce_candidate(&task_id, &input, &REQUESTER, &RESPONDER, &U256::from(nonce));
+            assert_eq!(
+                check(&RESPONDER, difficulty, nonce).is_ok(),
+                candidate <= U256::MAX >> difficulty
+            );
+        }
+
+        // a nonce that is valid for its responder is not valid for another one
+        let nonce = (0u64..)
+            .find(|n| {
+                check(&RESPONDER, difficulty, *n).is_ok()
+                    && check(&REQUESTER, difficulty, *n).is_err()
+            })
+            .unwrap();
+        assert!(matches!(
+            check(&REQUESTER, difficulty, nonce),
+            Err(OracleCoordinatorErrors::InvalidNonce(e)) if e.nonce == U256::from(nonce) && e.taskId == task_id
+        ));
+
+        // difficulty 0 accepts all nonces
+        assert!(check(&REQUESTER, 0, nonce).is_ok());
+    }
+
+    #[test]
+    fn test_check_validation_scores() {
+        let task_id = U256::from(1);
+        let scores = vec![U256::from(5); 3];
+        assert!(check_validation_scores(&task_id, &RESPONDER, &scores, 3).is_ok());
+
+        let err = check_validation_scores(&task_id, &RESPONDER, &scores, 2);
+        assert!(matches!(
+            err,
+            Err(OracleCoordinatorErrors::InvalidValidation(e)) if e.validator == RESPONDER
+        ));
+    }
+
+    #[test]
+    fn test_check_response_size() {
+        let task_id = U256::from(1);
+        let small = Bytes::from(vec![0u8; 1024]);
+        let large = Bytes::from(vec![0u8; MAX_RESPONSE_BYTES]);
+        assert!(check_response_size(&task_id, &[&small]).is_ok());
+        assert!(check_response_size(&task_id, &[&large]).is_ok());
+        assert!(check_response_size(&task_id, &[&large, &small]).is_err());
+    }
+
+    #[test]
+    fn test_estimate_response_gas() {
+        assert_eq!(estimate_response_gas(&[]), 0);
+
+        // 32 zero bytes in a single slot
+        let zeros = Bytes::from(vec![0u8; 32]);
+        assert_eq!(estimate_response_gas(&[&zeros]), 32 * 4 + 20_000);
+
+        // 33 non-zero bytes in two slots
+        let ones = Bytes::from(vec![1u8; 33]);
+        assert_eq!(estimate_response_gas(&[&ones]), 33 * 16 + 2 * 20_000);
+        assert_eq!(
+            estimate_response_gas(&[&zeros, &ones]),
+            32 * 4 + 33 * 16 + 3 * 20_000
+        );
+    }
+}
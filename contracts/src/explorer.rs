@@ -0,0 +1,69 @@
+use alloy::primitives::{Address, TxHash};
+
+use super::get_deployment_by_id;
+
+/// Creates block explorer links for transactions & addresses of a chain.
+///
+/// If the chain has no known explorer, the links fall back to the plain hashes & addresses.
+#[derive(Debug, Clone, Default)]
+pub struct Explorer {
+    /// Base URL of the explorer, without a trailing slash.
+    base_url: Option<String>,
+}
+
+impl Explorer {
+    /// Creates an explorer with the given base URL, e.g. `https://basescan.org`.
+    ///
+    /// Both Etherscan-like (Basescan) and Blockscout explorers share the same URL paths.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: Some(base_url.into().trim_end_matches('/').to_string()),
+        }
+    }
+
+    /// Creates the explorer of the chain with the given id, using the known deployments.
+    pub fn from_chain_id(chain_id: u64) -> Self {
+        get_deployment_by_id(chain_id)
+            .and_then(|deployment| deployment.explorer_url)
+            .map(Self::new)
+            .unwrap_or_default()
+    }
+
+    /// Returns a link to the given transaction.
+    pub fn tx(&self, tx_hash: TxHash) -> String {
+        match &self.base_url {
+            Some(base_url) => format!("{}/tx/{}", base_url, tx_hash),
+            None => tx_hash.to_string(),
+        }
+    }
+
+    /// Returns a link to the given address.
+    pub fn address(&self, address: Address) -> String {
+        match &self.base_url {
+            Some(base_url) => format!("{}/address/{}", base_url, address),
+            None => address.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explorer_links() {
+        let explorer = Explorer::new("https://basescan.org/");
+        assert_eq!(
+            explorer.tx(TxHash::ZERO),
+            format!("https://basescan.org/tx/{}", TxHash::ZERO)
+        );
+        assert_eq!(
+            explorer.address(Address::ZERO),
+            format!("https://basescan.org/address/{}", Address::ZERO)
+        );
+
+        // unknown chains fall back to plain hashes
+        let explorer = Explorer::from_chain_id(31337);
+        assert_eq!(explorer.tx(TxHash::ZERO), TxHash::ZERO.to_string());
+    }
+}
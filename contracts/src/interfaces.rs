@@ -36,6 +36,41 @@ sol!(
     "./abi/LLMOracleCoordinator.json"
 );
 
+// Multicall3, deployed at the same address on most chains
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface Multicall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Call3Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Call3Result[] memory returnData);
+    }
+);
+
+// Chainlink price feed, e.g. ETH / USD
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface AggregatorV3 {
+        function decimals() external view returns (uint8);
+
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+);
+
+/// Address of the [`Multicall3`] contract, which is the same for all chains that it is deployed at.
+pub const MULTICALL3_ADDRESS: alloy::primitives::Address =
+    alloy::primitives::address!("cA11bde05977b3631167028862bE2a173976CA11");
+
 /// `OracleKind` as it appears within the registry.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OracleKind {
@@ -4,8 +4,17 @@ pub use balance::*;
 mod addresses;
 pub use addresses::*;
 
+mod chains;
+pub use chains::*;
+
+mod explorer;
+pub use explorer::*;
+
 mod interfaces;
 pub use interfaces::*;
 
 mod errors;
 pub use errors::*;
+
+mod compat;
+pub use compat::*;
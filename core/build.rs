@@ -0,0 +1,7 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // gRPC service is generated only if its feature is enabled, which requires `protoc`
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/oracle.proto")?;
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use eyre::{Context, Result};
+use reqwest::Url;
+use rskafka::client::partition::{Compression, UnknownTopicHandling};
+use rskafka::client::ClientBuilder;
+use rskafka::record::Record;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use super::{address_and_topic, spawn_publisher, BridgeEvent};
+
+/// Connects to the Kafka broker at the given URL, and publishes the events to partition 0 of its topic.
+pub(super) async fn connect(url: &Url) -> Result<mpsc::Sender<BridgeEvent>> {
+    let (address, topic) = address_and_topic(url)?;
+    let client = ClientBuilder::new(vec![address])
+        .build()
+        .await
+        .wrap_err("could not connect to Kafka")?
+        .partition_client(topic, 0, UnknownTopicHandling::Retry)
+        .await
+        .wrap_err("could not connect to Kafka topic")?;
+    let client = Arc::new(client);
+
+    Ok(spawn_publisher(move |payload| {
+        let client = client.clone();
+        async move {
+            let record = Record {
+                key: None,
+                value: Some(payload),
+                headers: Default::default(),
+                timestamp: chrono::Utc::now(),
+            };
+            client
+                .produce(vec![record], Compression::NoCompression)
+                .await
+                .wrap_err("could not publish to Kafka")?;
+            Ok(())
+        }
+    }))
+}
@@ -0,0 +1,255 @@
+//! An optional bridge that publishes the activity of the node to a message broker as JSON,
+//! so that analytics & alerting pipelines can follow it without querying the RPC themselves.
+//!
+//! The broker is given as a URL with the topic (or subject) as its path:
+//!
+//! - `nats://<host>:<port>/<subject>`, requires the `nats` feature.
+//! - `kafka://<host>:<port>/<topic>`, requires the `kafka` feature, published to partition 0.
+
+use alloy::primitives::{Address, TxHash, U256};
+use alloy::rpc::types::Log;
+use dria_oracle_contracts::{bytes32_to_string, OracleCoordinator::StatusUpdate, OracleKind};
+use eyre::{eyre, Result};
+use reqwest::Url;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::TraceId;
+
+/// Number of events that can wait to be published, after which the new ones are dropped
+/// so that a broker that is down does not grow the memory of the node.
+const BRIDGE_CAPACITY: usize = 1024;
+
+/// An event published to the bridge.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeEvent {
+    /// A `StatusUpdate` event observed from the coordinator.
+    StatusUpdate {
+        coordinator: Address,
+        task_id: U256,
+        protocol: String,
+        status_before: u8,
+        status_after: u8,
+        block_number: Option<u64>,
+        tx_hash: Option<TxHash>,
+    },
+    /// The outcome of a response that we have made to a task.
+    Response {
+        coordinator: Address,
+        task_id: U256,
+        kind: String,
+        /// Transaction of the response, if it has succeeded.
+        tx_hash: Option<TxHash>,
+        /// Error of the response, if it has failed.
+        error: Option<String>,
+        /// Trace id of the attempt that made the response.
+        trace_id: Option<TraceId>,
+    },
+}
+
+impl BridgeEvent {
+    /// Creates the event of an observed `StatusUpdate`.
+    pub fn status_update(coordinator: Address, event: &StatusUpdate, log: &Log) -> Self {
+        Self::StatusUpdate {
+            coordinator,
+            task_id: event.taskId,
+            protocol: bytes32_to_string(&event.protocol).unwrap_or_default(),
+            status_before: event.statusBefore,
+            status_after: event.statusAfter,
+            block_number: log.block_number,
+            tx_hash: log.transaction_hash,
+        }
+    }
+
+    /// Creates the event of a response outcome, where `Ok(None)` is a skipped task and is not published.
+    pub fn response<T>(
+        coordinator: Address,
+        task_id: U256,
+        kind: OracleKind,
+        result: &Result<Option<T>>,
+        tx_hash: impl FnOnce(&T) -> TxHash,
+    ) -> Option<Self> {
+        let (tx_hash, error) = match result {
+            Ok(Some(receipt)) => (Some(tx_hash(receipt)), None),
+            Ok(None) => return None,
+            Err(e) => (None, Some(format!("{:#}", e))),
+        };
+
+        Some(Self::Response {
+            coordinator,
+            task_id,
+            kind: kind.to_string(),
+            tx_hash,
+            error,
+            trace_id: TraceId::current(),
+        })
+    }
+}
+
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+/// Returns the `<host>:<port>` address and the topic of the given bridge URL.
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(dead_code))]
+fn address_and_topic(url: &Url) -> Result<(String, String)> {
+    let address = format!(
+        "{}:{}",
+        url.host_str()
+            .ok_or_else(|| eyre!("bridge URL has no host"))?,
+        url.port().ok_or_else(|| eyre!("bridge URL has no port"))?
+    );
+    let topic = url.path().trim_start_matches('/').to_string();
+    if topic.is_empty() {
+        return Err(eyre!("bridge URL has no topic"));
+    }
+
+    Ok((address, topic))
+}
+
+/// Spawns a task that publishes the events sent to the returned channel with the given function.
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(dead_code))]
+fn spawn_publisher<F, Fut>(publish: F) -> mpsc::Sender<BridgeEvent>
+where
+    F: Fn(Vec<u8>) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<()>> + Send,
+{
+    let (sender, mut receiver) = mpsc::channel::<BridgeEvent>(BRIDGE_CAPACITY);
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            let result = match serde_json::to_vec(&event) {
+                Ok(payload) => publish(payload).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                log::warn!("Could not publish event to the bridge: {:#}", e);
+            }
+        }
+    });
+
+    sender
+}
+
+/// Publishes events to a message broker in the background, so that a slow broker does not
+/// hold back the node. Events that can not be published, or that do not fit into the queue
+/// of [`BRIDGE_CAPACITY`] events, are logged and dropped.
+///
+/// Cloning the bridge shares the same connection.
+#[derive(Debug, Clone)]
+pub struct EventBridge {
+    sender: mpsc::Sender<BridgeEvent>,
+}
+
+impl EventBridge {
+    /// Connects to the broker at the given URL, see the [module documentation](self).
+    pub async fn connect(url: &Url) -> Result<Self> {
+        let sender = match url.scheme() {
+            #[cfg(feature = "nats")]
+            "nats" => nats::connect(url).await,
+            #[cfg(feature = "kafka")]
+            "kafka" => kafka::connect(url).await,
+            #[allow(unreachable_patterns)]
+            "nats" | "kafka" => Err(eyre!(
+                "{} bridge requires the `{}` feature",
+                url.scheme(),
+                url.scheme()
+            )),
+            scheme => Err(eyre!("Unknown bridge scheme: {}", scheme)),
+        }?;
+
+        log::info!("Publishing events to {}", url);
+        Ok(Self { sender })
+    }
+
+    /// Queues the given event to be published, drops it if the queue is full.
+    pub fn publish(&self, event: BridgeEvent) {
+        match self.sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                log::warn!("Bridge is falling behind, dropping event: {:?}", event);
+            }
+            // the publisher lives as long as the runtime, so this only fails on shutdown
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_event() {
+        let result: Result<Option<TxHash>> = Ok(Some(TxHash::repeat_byte(1)));
+        let event = BridgeEvent::response(
+            Address::ZERO,
+            U256::from(1),
+            OracleKind::Generator,
+            &result,
+            |tx| *tx,
+        )
+        .unwrap();
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "response");
+        assert_eq!(json["kind"], "generator");
+        assert!(json["error"].is_null());
+
+        let result: Result<Option<TxHash>> = Err(eyre!("out of gas"));
+        let event = BridgeEvent::response(
+            Address::ZERO,
+            U256::from(1),
+            OracleKind::Validator,
+            &result,
+            |tx| *tx,
+        )
+        .unwrap();
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["error"], "out of gas");
+
+        let result: Result<Option<TxHash>> = Ok(None);
+        assert!(BridgeEvent::response(
+            Address::ZERO,
+            U256::from(1),
+            OracleKind::Generator,
+            &result,
+            |tx| *tx
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_bridge_full() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        let bridge = EventBridge { sender };
+        for task_id in 1..=2 {
+            bridge.publish(BridgeEvent::Response {
+                coordinator: Address::ZERO,
+                task_id: U256::from(task_id),
+                kind: OracleKind::Generator.to_string(),
+                tx_hash: None,
+                error: None,
+                trace_id: None,
+            });
+        }
+
+        // the event that does not fit is dropped instead of blocking
+        let Ok(BridgeEvent::Response { task_id, .. }) = receiver.try_recv() else {
+            panic!("expected the first event");
+        };
+        assert_eq!(task_id, U256::from(1));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bridge_url() {
+        let url = Url::parse("amqp://localhost:5672/tasks").unwrap();
+        assert!(EventBridge::connect(&url).await.is_err());
+        let url = Url::parse("nats://localhost:4222").unwrap();
+        assert!(address_and_topic(&url).is_err());
+        let url = Url::parse("kafka://localhost:9092/oracle-events").unwrap();
+        let (address, topic) = address_and_topic(&url).unwrap();
+        assert_eq!(address, "localhost:9092");
+        assert_eq!(topic, "oracle-events");
+    }
+}
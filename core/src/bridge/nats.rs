@@ -0,0 +1,24 @@
+use eyre::{Context, Result};
+use reqwest::Url;
+use tokio::sync::mpsc;
+
+use super::{address_and_topic, spawn_publisher, BridgeEvent};
+
+/// Connects to the NATS server at the given URL, and publishes the events to its subject.
+pub(super) async fn connect(url: &Url) -> Result<mpsc::Sender<BridgeEvent>> {
+    let (address, subject) = address_and_topic(url)?;
+    let client = async_nats::connect(address)
+        .await
+        .wrap_err("could not connect to NATS")?;
+
+    Ok(spawn_publisher(move |payload| {
+        let client = client.clone();
+        let subject = subject.clone();
+        async move {
+            client
+                .publish(subject, payload.into())
+                .await
+                .wrap_err("could not publish to NATS")
+        }
+    }))
+}
@@ -0,0 +1,41 @@
+use dkn_workflows::{DriaWorkflowsConfig, Model};
+use eyre::{Context, Result};
+
+use crate::{benchmark_model, RetryPolicy};
+
+/// Benchmarks each of the given models at each of the given concurrencies, and displays a report for each.
+///
+/// The outputs are scored with the `judge` model if given, otherwise with the benchmarked model itself.
+pub(in crate::cli) async fn benchmark_models(
+    models: Vec<Model>,
+    judge: Option<Model>,
+    rounds: usize,
+    concurrencies: Vec<usize>,
+) -> Result<()> {
+    let mut required = models.clone();
+    required.extend(judge.clone());
+    DriaWorkflowsConfig::new(required)
+        .check_services()
+        .await
+        .wrap_err("models are not available, check the API keys or the Ollama setup")?;
+
+    let policy = RetryPolicy::default();
+    for model in models {
+        for &concurrency in &concurrencies {
+            log::info!(
+                "Benchmarking {} with {} generations at a time.",
+                model,
+                concurrency
+            );
+            let judge = judge.clone().unwrap_or_else(|| model.clone());
+            let report = benchmark_model(model.clone(), judge, rounds, concurrency, &policy).await;
+            log::info!("{}", report);
+        }
+    }
+
+    log::info!(
+        "Compare the throughput & latency at each concurrency to choose --max-concurrent-tasks."
+    );
+
+    Ok(())
+}
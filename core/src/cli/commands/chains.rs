@@ -0,0 +1,12 @@
+use dria_oracle_contracts::KNOWN_DEPLOYMENTS;
+
+/// Displays the chains with known deployments of the oracle contracts.
+pub(in crate::cli) fn display_chains() {
+    let deployments = KNOWN_DEPLOYMENTS
+        .iter()
+        .map(|deployment| deployment.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    log::info!("Supported chains:\n{}", deployments);
+}
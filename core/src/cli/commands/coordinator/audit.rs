@@ -0,0 +1,145 @@
+use alloy::primitives::U256;
+use dria_oracle_contracts::TaskStatus;
+use eyre::{eyre, Result};
+
+use crate::compute::execute_validations;
+
+/// Mean absolute deviation of a validator from the local scores, above which the validator is flagged.
+///
+/// Scores are within `[51, 255]` with steps of 51 by default, so this corresponds to a full step on average.
+const DISAGREEMENT_THRESHOLD: f64 = 51.0;
+
+impl crate::DriaOracle {
+    /// Recomputes the validation scores of a completed task locally,
+    /// and compares them against the scores of each validator on-chain.
+    pub(in crate::cli) async fn audit_task(&self, task_id: U256) -> Result<()> {
+        let task = self.get_task(task_id).await?;
+        if !matches!(task.request.status, TaskStatus::Completed) {
+            return Err(eyre!(
+                "Task {} is not completed yet, it is {}.",
+                task_id,
+                task.request.status
+            ));
+        }
+
+        // download the input & generations, validators work on the generation metadata
+        log::info!("Downloading the input & generations of task {}.", task_id);
+        let input = task.request.input.fetch().await?;
+        let mut generations = Vec::new();
+        for response in &task.responses {
+            generations.push(response.metadata.fetch().await?);
+        }
+        for validation in &task.validations {
+            match validation.metadata.fetch().await {
+                Ok(metadata) => log::debug!(
+                    "Validation metadata of {}:\n{}",
+                    validation.validator,
+                    metadata
+                ),
+                Err(e) => log::warn!(
+                    "Could not download validation metadata of {}: {}",
+                    validation.validator,
+                    e
+                ),
+            }
+        }
+
+        // validations always use the same model, see the validation handler
+        log::info!("Computing validation scores locally.");
+        let local_scores = execute_validations(
+            input,
+            generations,
+            Self::VALIDATION_MODEL,
+            &self.config.retry_policy,
+        )
+        .await?
+        .iter()
+        .map(|v| {
+            v.final_score_as_solidity_type(
+                self.config.score_maps.for_protocol(&task.request.protocol),
+            )
+        })
+        .collect::<Vec<_>>();
+
+        log::info!("Scores per response (local / final / validators):");
+        for (idx, response) in task.responses.iter().enumerate() {
+            let validator_scores = task
+                .validations
+                .iter()
+                .map(|v| {
+                    v.scores
+                        .get(idx)
+                        .map(|s| s.to_string())
+                        .unwrap_or("-".to_string())
+                })
+                .collect::<Vec<_>>();
+            log::info!(
+                "Response #{} by {}: {} / {} / [{}]",
+                idx,
+                self.explorer.address(response.responder),
+                local_scores
+                    .get(idx)
+                    .map(|s| s.to_string())
+                    .unwrap_or("-".to_string()),
+                response.score,
+                validator_scores.join(", ")
+            );
+        }
+
+        log::info!("Disagreement per validator (mean absolute deviation from local scores):");
+        for validation in &task.validations {
+            let deviation = mean_absolute_deviation(&local_scores, &validation.scores);
+            if deviation > DISAGREEMENT_THRESHOLD {
+                log::warn!(
+                    "{}: {:.2} (exceeds {:.2})",
+                    self.explorer.address(validation.validator),
+                    deviation,
+                    DISAGREEMENT_THRESHOLD
+                );
+            } else {
+                log::info!(
+                    "{}: {:.2}",
+                    self.explorer.address(validation.validator),
+                    deviation
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Mean absolute deviation between two score lists, compared element-wise up to the shorter one.
+fn mean_absolute_deviation(expected: &[U256], actual: &[U256]) -> f64 {
+    let deviations = expected
+        .iter()
+        .zip(actual)
+        .map(|(e, a)| (e.saturating_to::<u64>() as f64 - a.saturating_to::<u64>() as f64).abs())
+        .collect::<Vec<_>>();
+
+    if deviations.is_empty() {
+        0.0
+    } else {
+        deviations.iter().sum::<f64>() / deviations.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_absolute_deviation() {
+        let scores = |s: &[u64]| s.iter().map(|s| U256::from(*s)).collect::<Vec<_>>();
+
+        assert_eq!(
+            mean_absolute_deviation(&scores(&[255, 51]), &scores(&[255, 51])),
+            0.0
+        );
+        assert_eq!(
+            mean_absolute_deviation(&scores(&[255, 51]), &scores(&[204, 153])),
+            76.5
+        );
+        assert_eq!(mean_absolute_deviation(&scores(&[255]), &[]), 0.0);
+    }
+}
@@ -0,0 +1,233 @@
+use alloy::primitives::{aliases::U40, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionReceipt;
+use dkn_workflows::Model;
+use dria_oracle_contracts::{string_to_bytes, string_to_bytes32, OracleCoordinator, TokenBalance};
+use eyre::{eyre, Context, Result};
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use std::path::Path;
+
+use super::request::{input_to_bytes, task_id_from_receipt};
+
+/// A single request within a batch file, where each line is a JSON object.
+///
+/// Omitted fields take the same defaults as the `request` command.
+#[derive(Debug, Clone, serde::Deserialize)]
+struct BatchRequest {
+    input: String,
+    models: Vec<String>,
+    #[serde(default = "default_difficulty")]
+    difficulty: u8,
+    #[serde(default = "default_count")]
+    num_gens: u64,
+    #[serde(default = "default_count")]
+    num_vals: u64,
+    #[serde(default = "default_protocol")]
+    protocol: String,
+}
+
+fn default_difficulty() -> u8 {
+    2
+}
+
+fn default_count() -> u64 {
+    1
+}
+
+fn default_protocol() -> String {
+    crate::DEFAULT_PROTOCOL.to_string()
+}
+
+impl crate::DriaOracle {
+    /// Requests all tasks within the given JSON-lines file.
+    ///
+    /// The total fee is approved once upfront, and the requests are sent one by one with
+    /// explicit nonces, while at most `concurrency` of them are waited for at the same time.
+    pub(in crate::cli) async fn request_batch(
+        &self,
+        path: &Path,
+        concurrency: usize,
+    ) -> Result<()> {
+        let requests = read_batch_file(path)?;
+        if requests.is_empty() {
+            log::warn!("There are no requests in {}.", path.display());
+            return Ok(());
+        }
+        log::info!(
+            "Requesting {} tasks from {}.",
+            requests.len(),
+            path.display()
+        );
+
+        // get total fee for all requests
+        let mut total_fee = U256::ZERO;
+        for request in &requests {
+            total_fee += self
+                .get_request_fee(request.difficulty, request.num_gens, request.num_vals)
+                .await?
+                .totalFee;
+        }
+
+        let mut nonce = self
+            .prepay_requests(requests.len(), total_fee, "task requests")
+            .await?;
+        let mut pending = FuturesUnordered::new();
+        let (mut succeeded, mut failed) = (0usize, 0usize);
+        for (idx, request) in requests.into_iter().enumerate() {
+            // wait for a slot before sending the next request
+            if pending.len() >= concurrency.max(1) {
+                if let Some((idx, result)) = pending.next().await {
+                    self.log_batch_result(idx, result, &mut succeeded, &mut failed);
+                }
+            }
+
+            let parameters = OracleCoordinator::LLMOracleTaskParameters {
+                difficulty: request.difficulty,
+                numGenerations: U40::from(request.num_gens),
+                numValidations: U40::from(request.num_vals),
+            };
+            let req = self
+                .coordinator
+                .request(
+                    string_to_bytes32(request.protocol)?,
+                    input_to_bytes(request.input).await?,
+                    string_to_bytes(request.models.join(",")),
+                    parameters,
+                )
+                .nonce(nonce);
+
+            match self.send_with_gas_hikes(req).await {
+                Ok(tx) => {
+                    nonce += 1;
+                    pending
+                        .push(async move { (idx, self.wait_for_tx(tx).await.map_err(Into::into)) });
+                }
+                Err(e) => {
+                    log::error!("Could not send request #{}: {:?}", idx, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        // wait for the remaining requests
+        while let Some((idx, result)) = pending.next().await {
+            self.log_batch_result(idx, result, &mut succeeded, &mut failed);
+        }
+
+        log::info!(
+            "Batch completed: {} requested, {} failed.",
+            succeeded,
+            failed
+        );
+
+        Ok(())
+    }
+
+    /// Prepares the wallet to send the given number of requests with the given total fee, i.e. checks the
+    /// balance, confirms the spending and makes a single approval for all of them.
+    ///
+    /// Returns the pending nonce of the wallet, so that the requests can be sent one by one with explicit
+    /// nonces, without the pending ones colliding.
+    pub(in crate::cli) async fn prepay_requests(
+        &self,
+        count: usize,
+        total_fee: U256,
+        description: &str,
+    ) -> Result<u64> {
+        let balance = self.get_token_balance(self.address()).await?;
+        if balance.amount < total_fee {
+            return Err(eyre!(
+                "Insufficient balance for {} tasks, need {}. Please fund your wallet.",
+                count,
+                TokenBalance::format_amount(total_fee, balance.decimals)
+            ));
+        }
+
+        self.confirm_spending(
+            &format!(
+                "Pay the fees of {} {} to the coordinator.",
+                count, description
+            ),
+            total_fee,
+            *self.coordinator.address(),
+        )
+        .await?;
+
+        let allowance = self
+            .allowance(self.address(), *self.coordinator.address())
+            .await?;
+        if allowance.amount < total_fee {
+            let approval_amount = total_fee - allowance.amount;
+            log::info!(
+                "Insufficient allowance. Approving the required amount: {}.",
+                TokenBalance::format_amount(approval_amount, allowance.decimals)
+            );
+
+            self.approve(*self.coordinator.address(), approval_amount)
+                .await?;
+            log::info!("Token approval successful.");
+        }
+
+        let nonce = self
+            .provider
+            .get_transaction_count(self.address())
+            .pending()
+            .await?;
+
+        Ok(nonce)
+    }
+
+    /// Logs the result of a single request within a batch, and counts it.
+    fn log_batch_result(
+        &self,
+        idx: usize,
+        result: Result<TransactionReceipt>,
+        succeeded: &mut usize,
+        failed: &mut usize,
+    ) {
+        match result.and_then(|receipt| {
+            task_id_from_receipt(&receipt).map(|task_id| (task_id, receipt.transaction_hash))
+        }) {
+            Ok((task_id, tx_hash)) => {
+                log::info!(
+                    "Request #{} created task {}. tx: {}",
+                    idx,
+                    task_id,
+                    self.explorer.tx(tx_hash)
+                );
+                *succeeded += 1;
+            }
+            Err(e) => {
+                log::error!("Request #{} failed: {:?}", idx, e);
+                *failed += 1;
+            }
+        }
+    }
+}
+
+/// Reads & validates the requests within a JSON-lines file, skipping empty lines.
+fn read_batch_file(path: &Path) -> Result<Vec<BatchRequest>> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("could not read {}", path.display()))?;
+
+    let mut requests = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = serde_json::from_str::<BatchRequest>(line)
+            .wrap_err_with(|| format!("invalid request at line {}", line_no + 1))?;
+        if request.models.is_empty() {
+            return Err(eyre!("no models given at line {}", line_no + 1));
+        }
+        for model in &request.models {
+            Model::try_from(model.clone())
+                .map_err(|e| eyre!("invalid model at line {}: {}", line_no + 1, e))?;
+        }
+
+        requests.push(request);
+    }
+
+    Ok(requests)
+}
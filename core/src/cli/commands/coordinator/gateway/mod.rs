@@ -0,0 +1,164 @@
+use bytes::Bytes;
+use dkn_workflows::Model;
+use eyre::{eyre, Context, Result};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, Method, StatusCode};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{net::TcpListener, sync::Mutex};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::commands::http::{json_response, serve_connections};
+use crate::DriaOracle;
+
+mod openai;
+use openai::{ChatCompletionRequest, ChatCompletionResponse, ErrorResponse};
+
+/// Parameters of the oracle requests made by the gateway.
+#[derive(Debug, Clone)]
+pub struct GatewayOptions {
+    pub difficulty: u8,
+    pub num_gens: u64,
+    pub num_vals: u64,
+    pub protocol: String,
+    /// Maximum time to wait for a task to complete.
+    pub timeout: Duration,
+}
+
+impl DriaOracle {
+    /// Serves an OpenAI-compatible chat completions API at the given address.
+    ///
+    /// Each chat completion request creates an oracle request with the wallet of this node,
+    /// waits for the task to complete, and returns its best response.
+    pub(in crate::cli) async fn serve_gateway(
+        &self,
+        address: SocketAddr,
+        options: GatewayOptions,
+        cancellation: CancellationToken,
+    ) -> Result<()> {
+        let listener = TcpListener::bind(address)
+            .await
+            .wrap_err_with(|| format!("could not bind to {}", address))?;
+        log::info!(
+            "Serving OpenAI-compatible API at http://{}/v1/chat/completions",
+            address
+        );
+
+        // requests are sent one at a time to avoid nonce conflicts, while the waiting is concurrent
+        let request_lock = Mutex::new(());
+        serve_connections(listener, cancellation, |req| {
+            self.handle_gateway_request(req, &options, &request_lock)
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Routes a request to the gateway, and converts errors to OpenAI-style error responses.
+    async fn handle_gateway_request(
+        &self,
+        req: hyper::Request<Incoming>,
+        options: &GatewayOptions,
+        request_lock: &Mutex<()>,
+    ) -> hyper::Response<Full<Bytes>> {
+        let (method, path) = (req.method().clone(), req.uri().path().to_string());
+        match (method, path.as_str()) {
+            (Method::POST, "/v1/chat/completions") => {
+                let (request, model) = match parse_chat_request(req).await {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        return json_response(
+                            StatusCode::BAD_REQUEST,
+                            &ErrorResponse::new("invalid_request_error", format!("{:#}", e)),
+                        )
+                    }
+                };
+
+                match self
+                    .chat_completion(request, model, options, request_lock)
+                    .await
+                {
+                    Ok(response) => json_response(StatusCode::OK, &response),
+                    Err(e) => {
+                        log::error!("Could not complete chat request: {:#}", e);
+                        json_response(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            &ErrorResponse::new("server_error", format!("{:#}", e)),
+                        )
+                    }
+                }
+            }
+            (_, path) => json_response(
+                StatusCode::NOT_FOUND,
+                &ErrorResponse::new("invalid_request_error", format!("unknown path {}", path)),
+            ),
+        }
+    }
+
+    /// Requests a task for the given chat completion request, and returns its best response.
+    async fn chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+        model: Model,
+        options: &GatewayOptions,
+        request_lock: &Mutex<()>,
+    ) -> Result<ChatCompletionResponse> {
+        let input = request.to_input();
+
+        let task_id = {
+            let _guard = request_lock.lock().await;
+            self.request_task(
+                &input,
+                vec![model],
+                options.difficulty,
+                options.num_gens,
+                options.num_vals,
+                options.protocol.clone(),
+            )
+            .await?
+        };
+
+        tokio::time::timeout(
+            options.timeout,
+            self.follow_task(task_id, CancellationToken::new()),
+        )
+        .await
+        .map_err(|_| {
+            eyre!(
+                "task {} did not complete within {}s",
+                task_id,
+                options.timeout.as_secs()
+            )
+        })??;
+        let output = self
+            .get_task_best_response(task_id)
+            .await?
+            .output
+            .fetch()
+            .await?;
+
+        let created = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        Ok(ChatCompletionResponse::new(
+            task_id,
+            created,
+            request.model,
+            &input,
+            output,
+        ))
+    }
+}
+
+/// Parses the body of a chat completion request, along with its model.
+async fn parse_chat_request(
+    req: hyper::Request<Incoming>,
+) -> Result<(ChatCompletionRequest, Model)> {
+    let body = req.into_body().collect().await?.to_bytes();
+    let request: ChatCompletionRequest =
+        serde_json::from_slice(&body).wrap_err("could not parse chat completion request")?;
+    if request.messages.is_empty() {
+        return Err(eyre!("messages must not be empty"));
+    }
+    let model = Model::try_from(request.model.clone()).map_err(|e| eyre!(e))?;
+
+    Ok((request, model))
+}
@@ -0,0 +1,149 @@
+//! A subset of the OpenAI chat completions API, enough for the usual clients to talk to the gateway.
+//!
+//! See <https://platform.openai.com/docs/api-reference/chat>.
+
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::compute::estimate_tokens;
+
+/// A message within a chat completion request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// Body of a `POST /v1/chat/completions` request.
+///
+/// Other fields such as `temperature` are accepted but ignored, as they are decided by the generators.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+}
+
+impl ChatCompletionRequest {
+    /// Returns the input of the oracle request for these messages.
+    ///
+    /// A single user message is used as is, otherwise the conversation is written as a transcript
+    /// with the role of each message, as the oracle accepts a single input.
+    pub fn to_input(&self) -> String {
+        match self.messages.as_slice() {
+            [message] if message.role == "user" => message.content.clone(),
+            messages => messages
+                .iter()
+                .map(|m| format!("{}: {}", m.role, m.content))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatCompletionChoice {
+    pub index: u32,
+    pub message: ChatMessage,
+    pub finish_reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatCompletionUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+/// Body of a `POST /v1/chat/completions` response.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatCompletionResponse {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChatCompletionChoice>,
+    pub usage: ChatCompletionUsage,
+}
+
+impl ChatCompletionResponse {
+    /// Creates a response with the given output of a completed task.
+    ///
+    /// Token counts are estimated, as the generators do not report them.
+    pub fn new(task_id: U256, created: u64, model: String, input: &str, output: String) -> Self {
+        let prompt_tokens = estimate_tokens(input);
+        let completion_tokens = estimate_tokens(&output);
+
+        Self {
+            id: format!("chatcmpl-{}", task_id),
+            object: "chat.completion".to_string(),
+            created,
+            model,
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatMessage {
+                    role: "assistant".to_string(),
+                    content: output,
+                },
+                finish_reason: "stop".to_string(),
+            }],
+            usage: ChatCompletionUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+        }
+    }
+}
+
+/// Body of an error response.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorResponse {
+    pub error: ErrorDetails,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorDetails {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl ErrorResponse {
+    pub fn new(kind: &str, message: impl ToString) -> Self {
+        Self {
+            error: ErrorDetails {
+                message: message.to_string(),
+                kind: kind.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_to_input() {
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o-mini",
+            "temperature": 0.5,
+            "messages": [{ "role": "user", "content": "What is 2 + 2?" }]
+        }))
+        .unwrap();
+        assert_eq!(request.to_input(), "What is 2 + 2?");
+
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o-mini",
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "What is 2 + 2?" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            request.to_input(),
+            "system: Be brief.\n\nuser: What is 2 + 2?"
+        );
+    }
+}
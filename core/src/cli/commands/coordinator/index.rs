@@ -0,0 +1,176 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::rpc::types::Log;
+use dria_oracle_contracts::OracleCoordinator::StatusUpdate;
+use dria_oracle_contracts::TaskStatus;
+use eyre::Result;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::{DriaOracle, IndexFilter, IndexedTask};
+
+/// Interval to check for new blocks once the index has caught up.
+const INDEX_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+impl DriaOracle {
+    /// Indexes the tasks of the coordinator into the local database, along with their responses &
+    /// validations and the contents stored externally, until cancelled.
+    ///
+    /// The other events of the coordinator & its registry are indexed as well. All tasks are indexed,
+    /// and they are filtered when they are listed, see [`Self::display_indexed_tasks`].
+    ///
+    /// Indexing continues from the last indexed block, unless `from_block` is given.
+    pub(in crate::cli) async fn index_tasks(
+        &self,
+        from_block: Option<BlockNumberOrTag>,
+        cancellation: CancellationToken,
+    ) -> Result<()> {
+        let coordinator = *self.coordinator.address();
+        let mut next_block = match from_block {
+            Some(from_block) => self.resolve_block_number(from_block).await?,
+            None => match self.db.get_index_checkpoint(coordinator)? {
+                Some(checkpoint) => checkpoint + 1,
+                None => self.provider.get_block_number().await?,
+            },
+        };
+        log::info!(
+            "Indexing tasks of coordinator {} from block {}",
+            coordinator,
+            next_block
+        );
+
+        loop {
+            // failures are retried from the same block at the next poll
+            match self.index_new_blocks(next_block).await {
+                Ok(Some(latest_block)) => next_block = latest_block + 1,
+                Ok(None) => {}
+                Err(e) => log::error!("Could not index tasks from block {}: {:#}", next_block, e),
+            }
+
+            tokio::select! {
+                _ = cancellation.cancelled() => {
+                    log::info!("Indexed up to block {}.", next_block.saturating_sub(1));
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(INDEX_POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    /// Indexes the tasks from the given block up to the latest block, and saves the latest block
+    /// as the checkpoint of the coordinator.
+    ///
+    /// Returns the latest block, or `None` if there are no new blocks.
+    async fn index_new_blocks(&self, from_block: u64) -> Result<Option<u64>> {
+        let latest_block = self.provider.get_block_number().await?;
+        if from_block > latest_block {
+            return Ok(None);
+        }
+
+        // the range is scanned once, for both the status updates & the other events
+        let logs = self
+            .get_contract_logs_in_range(from_block, latest_block)
+            .await?;
+        let indexed = self
+            .index_tasks_of(&self.decode_status_updates(&logs), latest_block)
+            .await?;
+        let indexed_events = self
+            .db
+            .upsert_indexed_events(self.decode_contract_events(&logs))?;
+        self.db
+            .set_index_checkpoint(*self.coordinator.address(), latest_block)?;
+        if indexed > 0 || indexed_events > 0 {
+            log::info!(
+                "Indexed {} tasks & {} other events between blocks {} - {}",
+                indexed,
+                indexed_events,
+                from_block,
+                latest_block
+            );
+        }
+
+        Ok(Some(latest_block))
+    }
+
+    /// Indexes the tasks of the given status updates up to `to_block`, returns the number of indexed tasks.
+    async fn index_tasks_of(&self, events: &[(StatusUpdate, Log)], to_block: u64) -> Result<usize> {
+        // the latest event block of each task
+        let task_blocks = events
+            .iter()
+            .map(|(event, log)| (event.taskId, log.block_number.unwrap_or(to_block)))
+            .collect::<BTreeMap<U256, u64>>();
+        if task_blocks.is_empty() {
+            return Ok(0);
+        }
+
+        let task_ids = task_blocks.keys().copied().collect::<Vec<_>>();
+        let mut tasks = Vec::new();
+        for mut task in self.get_tasks(&task_ids).await? {
+            task.resolve_storage().await;
+            tasks.push(IndexedTask::from_view(
+                *self.coordinator.address(),
+                task_blocks[&task.task_id],
+                &task,
+            ));
+        }
+
+        let indexed = tasks.len();
+        self.db.upsert_indexed_tasks(tasks)?;
+        Ok(indexed)
+    }
+
+    /// Displays the indexed tasks that match the filter.
+    pub(in crate::cli) fn display_indexed_tasks(&self, filter: &IndexFilter) -> Result<()> {
+        let tasks = self
+            .db
+            .get_indexed_tasks(*self.coordinator.address(), filter)?;
+        if tasks.is_empty() {
+            log::warn!("There are no indexed tasks that match the filters.");
+            return Ok(());
+        }
+
+        for task in &tasks {
+            log::info!(
+                "Task {} ({}) by {} at block {}: {}, {} responses, {} validations",
+                task.task_id,
+                task.protocol,
+                task.requester,
+                task.block_number,
+                TaskStatus::try_from(task.status).unwrap_or_default(),
+                task.responses.len(),
+                task.validations.len()
+            );
+        }
+        log::info!("Found {} indexed tasks.", tasks.len());
+
+        Ok(())
+    }
+
+    /// Displays the indexed events of the coordinator & its registry, in the order they are emitted.
+    pub(in crate::cli) fn display_indexed_events(&self) -> Result<()> {
+        let events = self.db.get_indexed_events(*self.coordinator.address())?;
+        if events.is_empty() {
+            log::warn!("There are no indexed events, index them with the `index` command.");
+            return Ok(());
+        }
+
+        for event in &events {
+            log::info!(
+                "{}{} at block {}, tx: {}",
+                if event.contract == *self.registry.address() {
+                    "Registry: "
+                } else {
+                    ""
+                },
+                event.event,
+                event.block_number,
+                self.explorer.tx(event.tx_hash)
+            );
+        }
+        log::info!("Found {} indexed events.", events.len());
+
+        Ok(())
+    }
+}
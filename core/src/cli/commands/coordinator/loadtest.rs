@@ -0,0 +1,247 @@
+use alloy::primitives::{aliases::U40, U256};
+use alloy::rpc::types::TransactionReceipt;
+use dkn_workflows::Model;
+use dria_oracle_contracts::{string_to_bytes, string_to_bytes32, OracleCoordinator};
+use eyre::Result;
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use super::request::task_id_from_receipt;
+
+/// Outcome of a single task within a load test.
+enum LoadTestOutcome {
+    /// Completed after the given time since it was requested.
+    Completed(Duration),
+    /// Not completed within the timeout.
+    TimedOut,
+    /// Could not be requested or followed.
+    Failed,
+}
+
+/// Latencies & throughput of a load test.
+#[derive(Debug, Default)]
+struct LoadTestReport {
+    requested: usize,
+    timed_out: usize,
+    failed: usize,
+    /// Request to completion latencies of the completed tasks.
+    latencies: Vec<Duration>,
+    /// Time from the first request until the last task is done.
+    elapsed: Duration,
+}
+
+impl LoadTestReport {
+    fn add(&mut self, outcome: LoadTestOutcome) {
+        match outcome {
+            LoadTestOutcome::Completed(latency) => self.latencies.push(latency),
+            LoadTestOutcome::TimedOut => self.timed_out += 1,
+            LoadTestOutcome::Failed => self.failed += 1,
+        }
+    }
+
+    /// Returns the latency at the given percentile (within 0-100) of the completed tasks, nearest-rank.
+    fn percentile(&self, p: f64) -> Option<Duration> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        let rank = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+        latencies
+            .get(rank.clamp(1, latencies.len().max(1)) - 1)
+            .copied()
+    }
+
+    /// Returns the completed tasks per minute.
+    fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+
+        self.latencies.len() as f64 * 60.0 / secs
+    }
+
+    fn log(&self) {
+        log::info!(
+            "Load test finished in {:.1}s: {} requested, {} completed, {} timed out, {} failed.",
+            self.elapsed.as_secs_f64(),
+            self.requested,
+            self.latencies.len(),
+            self.timed_out,
+            self.failed
+        );
+        if self.latencies.is_empty() {
+            log::warn!("No tasks were completed, are there nodes serving the coordinator?");
+            return;
+        }
+
+        let secs = |latency: Option<Duration>| latency.unwrap_or_default().as_secs_f64();
+        let mean = self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32;
+        log::info!(
+            "Latency: min {:.1}s, mean {:.1}s, p50 {:.1}s, p90 {:.1}s, p99 {:.1}s, max {:.1}s",
+            secs(self.percentile(0.0)),
+            mean.as_secs_f64(),
+            secs(self.percentile(50.0)),
+            secs(self.percentile(90.0)),
+            secs(self.percentile(99.0)),
+            secs(self.percentile(100.0))
+        );
+        log::info!("Throughput: {:.2} tasks/min", self.throughput());
+    }
+}
+
+impl crate::DriaOracle {
+    /// Requests the given number of synthetic tasks with at most `concurrency` of them in progress at the same time,
+    /// waits for each of them to complete and logs the latency distribution & throughput.
+    ///
+    /// Like a batch, the total fee is approved once upfront and the requests are sent one by one with explicit nonces.
+    pub(in crate::cli) async fn load_test(
+        &self,
+        tasks: usize,
+        concurrency: usize,
+        models: Vec<Model>,
+        difficulty: u8,
+        protocol: String,
+        timeout: Duration,
+    ) -> Result<()> {
+        let fee = self.get_request_fee(difficulty, 1, 1).await?.totalFee;
+        let mut nonce = self
+            .prepay_requests(tasks, fee * U256::from(tasks), "load test requests")
+            .await?;
+
+        log::info!(
+            "Requesting {} tasks with concurrency {}, waiting up to {}s for each.",
+            tasks,
+            concurrency,
+            timeout.as_secs()
+        );
+        let models = string_to_bytes(
+            models
+                .iter()
+                .map(|model| model.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        let protocol = string_to_bytes32(protocol)?;
+        let parameters = OracleCoordinator::LLMOracleTaskParameters {
+            difficulty,
+            numGenerations: U40::from(1),
+            numValidations: U40::from(1),
+        };
+
+        let started = Instant::now();
+        let mut report = LoadTestReport::default();
+        let mut pending = FuturesUnordered::new();
+        for idx in 0..tasks {
+            // wait for a slot before sending the next request
+            if pending.len() >= concurrency.max(1) {
+                if let Some(outcome) = pending.next().await {
+                    report.add(outcome);
+                }
+            }
+
+            // inputs are unique, so that no cache is hit along the way
+            let input = format!("Load test task #{}: what is {} + {}?", idx, idx, idx + 1);
+            let req = self
+                .coordinator
+                .request(
+                    protocol,
+                    string_to_bytes(input),
+                    models.clone(),
+                    parameters.clone(),
+                )
+                .nonce(nonce);
+            let requested_at = Instant::now();
+            match self.send_with_gas_hikes(req).await {
+                Ok(tx) => {
+                    nonce += 1;
+                    report.requested += 1;
+                    pending.push(async move {
+                        let receipt = self.wait_for_tx(tx).await.map_err(Into::into);
+                        self.follow_load_test_task(idx, receipt, requested_at, timeout)
+                            .await
+                    });
+                }
+                Err(e) => {
+                    log::error!("Could not send load test request #{}: {:#}", idx, e);
+                    report.add(LoadTestOutcome::Failed);
+                }
+            }
+        }
+
+        while let Some(outcome) = pending.next().await {
+            report.add(outcome);
+        }
+        report.elapsed = started.elapsed();
+        report.log();
+
+        Ok(())
+    }
+
+    /// Waits for the task of the given request receipt to complete.
+    async fn follow_load_test_task(
+        &self,
+        idx: usize,
+        receipt: Result<TransactionReceipt>,
+        requested_at: Instant,
+        timeout: Duration,
+    ) -> LoadTestOutcome {
+        let task_id = match receipt.and_then(|receipt| task_id_from_receipt(&receipt)) {
+            Ok(task_id) => task_id,
+            Err(e) => {
+                log::error!("Load test request #{} failed: {:#}", idx, e);
+                return LoadTestOutcome::Failed;
+            }
+        };
+
+        let remaining = timeout.saturating_sub(requested_at.elapsed());
+        match tokio::time::timeout(
+            remaining,
+            self.follow_task(task_id, CancellationToken::new()),
+        )
+        .await
+        {
+            Ok(Ok(_)) => {
+                let latency = requested_at.elapsed();
+                log::info!(
+                    "Task {} is completed in {:.1}s.",
+                    task_id,
+                    latency.as_secs_f64()
+                );
+                LoadTestOutcome::Completed(latency)
+            }
+            Ok(Err(e)) => {
+                log::error!("Could not follow task {}: {:#}", task_id, e);
+                LoadTestOutcome::Failed
+            }
+            Err(_) => {
+                log::warn!("Task {} is not completed within the timeout.", task_id);
+                LoadTestOutcome::TimedOut
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_test_report() {
+        let mut report = LoadTestReport::default();
+        assert_eq!(report.percentile(50.0), None);
+        assert_eq!(report.throughput(), 0.0);
+
+        for secs in [5, 1, 3, 2, 4] {
+            report.add(LoadTestOutcome::Completed(Duration::from_secs(secs)));
+        }
+        report.add(LoadTestOutcome::TimedOut);
+        report.elapsed = Duration::from_secs(30);
+
+        assert_eq!(report.percentile(0.0), Some(Duration::from_secs(1)));
+        assert_eq!(report.percentile(50.0), Some(Duration::from_secs(3)));
+        assert_eq!(report.percentile(90.0), Some(Duration::from_secs(5)));
+        assert_eq!(report.percentile(100.0), Some(Duration::from_secs(5)));
+        assert_eq!(report.throughput(), 10.0);
+        assert_eq!(report.timed_out, 1);
+    }
+}
@@ -1,14 +1,27 @@
 use alloy::eips::BlockNumberOrTag;
-use eyre::{Context, Result};
-use futures_util::StreamExt;
+use dria_oracle_contracts::TaskStatus;
+use eyre::{eyre, Context, Result};
+use futures_util::future::{BoxFuture, FutureExt};
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
+use tokio_util::task::LocalPoolHandle;
 
-use crate::DriaOracle;
+use super::configured_storage;
+use crate::{BridgeEvent, DriaOracle, QueuedTask, TaskQueue};
 
+mod audit;
+mod batch;
+mod gateway;
+pub(in crate::cli) use gateway::GatewayOptions;
+mod index;
+mod loadtest;
 mod request;
 mod serve;
 mod view;
+mod watch;
 
 impl DriaOracle {
     /// Starts the oracle node.
@@ -16,10 +29,12 @@ impl DriaOracle {
         &self,
         from_block: Option<BlockNumberOrTag>,
         to_block: Option<BlockNumberOrTag>,
+        catch_up_workers: Option<usize>,
         cancellation: CancellationToken,
     ) -> Result<()> {
         log::info!(
-            "Started oracle as {} using models: {}",
+            "Started oracle for coordinator {} as {} using models: {}",
+            self.explorer.address(*self.coordinator.address()),
             self.kinds
                 .iter()
                 .map(|k| k.to_string())
@@ -33,6 +48,15 @@ impl DriaOracle {
                 .join(", ")
         );
 
+        // tasks that were interrupted by a crash or a restart are handled before the new ones
+        tokio::select! {
+            _ = cancellation.cancelled() => {
+                log::debug!("Cancellation signal received. Stopping...");
+                return Ok(());
+            }
+            _ = self.resume_interrupted_tasks() => {}
+        }
+
         // check previous tasks if `from_block` is given
         if let Some(from_block) = from_block {
             tokio::select! {
@@ -40,7 +64,7 @@ impl DriaOracle {
                     log::debug!("Cancellation signal received. Stopping...");
                     return Ok(());
                 }
-                result = self.process_tasks_within_range(from_block, to_block.unwrap_or(BlockNumberOrTag::Latest)) => {
+                result = self.catch_up(from_block, to_block.unwrap_or(BlockNumberOrTag::Latest), catch_up_workers) => {
                     if let Err(e) = result {
                         log::error!("Could not handle previous tasks: {:?}", e);
                         log::warn!("Continuing anyways...");
@@ -54,10 +78,54 @@ impl DriaOracle {
             return Ok(());
         }
 
-        // otherwise, we can continue with the event loop
+        // otherwise, we can continue with the event loop, where the tasks are queued as they
+        // arrive and dispatched in the order of priority as long as there is capacity
+        let mut queue = TaskQueue::new(self.config.queue_policy());
+        // dispatched tasks are spawned so that they run in parallel with each other & the event loop, with
+        // a node of their own that shares the state of this one; they are pinned to the threads of a pool,
+        // as the uploads to storage can not be moved across threads
+        let task_node = Arc::new(self.connect(self.config.wallet.clone()));
+        let task_pool = LocalPoolHandle::new(self.config.max_concurrent_tasks.max(1));
+        let mut in_flight = JoinSet::new();
+        // new tasks are read from the chain alongside the event loop before they are queued, and the
+        // tasks of other shards are queued after their failover duration, if they are still pending
+        let mut enqueuing = FuturesUnordered::<BoxFuture<'_, Option<QueuedTask>>>::new();
+        // responses whose transactions have failed are retried periodically, starting right away,
+        // alongside the event loop so that the new tasks are still handled while the outbox drains
+        let mut outbox_retry = tokio::time::interval(Self::OUTBOX_RETRY_INTERVAL);
+        let mut outbox_retrying = FuturesUnordered::new();
+        // the responses of the completed tasks are recorded for the reputation of their generators
+        let mut peer_recording = FuturesUnordered::new();
+        // new tasks are not dispatched while the storage is unreachable, as their responses could not be
+        // uploaded; this is only checked if there is a wallet to upload with, and the balance is left
+        // to the balance monitor as the responses that fit on-chain need no balance at all
+        let storage = configured_storage()?;
+        let mut storage_check = tokio::time::interval(Self::STORAGE_CHECK_INTERVAL);
+        let mut storage_checking = FuturesUnordered::new();
+        let mut is_storage_healthy = true;
+        // new tasks are deferred while the gas is above the ceiling or the gas budget is exhausted
+        let mut gas_check = tokio::time::interval(Self::GAS_CHECK_INTERVAL);
+        let mut gas_checking = FuturesUnordered::new();
+        let mut is_gas_affordable = true;
+        // upgrades of the coordinator are alerted about, as they may break the decoding of events & errors
+        let mut interface_check = tokio::time::interval(Self::INTERFACE_CHECK_INTERVAL);
+        let mut interface_checking = FuturesUnordered::new();
+        let mut implementation = None;
+        // the kinds that the implementation no longer supports are not served until it is upgraded again
+        let mut supported_kinds = self.kinds.clone();
+        // the local database is kept within its retention policy, so that it does not grow unbounded
+        let mut db_compaction = tokio::time::interval(Self::DB_COMPACTION_INTERVAL);
+        // new tasks are kept in the queue out of the serve windows or in maintenance mode, which is
+        // checked periodically so that the queued tasks are dispatched once the gate opens again
+        let mut gate_check = tokio::time::interval(Self::SERVE_GATE_CHECK_INTERVAL);
+        // whether the node is drained in maintenance mode, so that it is alerted once when it is
+        let mut is_drained = false;
         loop {
             // subscribe to new tasks
-            log::info!("Subscribing to task events");
+            log::info!(
+                "Subscribing to task events of coordinator {}",
+                self.coordinator.address()
+            );
             let mut event_stream = self
                 .coordinator
                 .StatusUpdate_filter()
@@ -69,20 +137,215 @@ impl DriaOracle {
             // start the event loop
             log::info!("Listening for events...");
             loop {
+                // the policy may have been changed while serving, e.g. on SIGHUP
+                queue.set_policy(self.config.queue_policy());
+                while is_storage_healthy
+                    && is_gas_affordable
+                    && in_flight.len() < self.config.max_concurrent_tasks
+                    && self.config.serve_gate.is_open()
+                {
+                    let Some(task) = queue.pop() else {
+                        break;
+                    };
+                    if !supported_kinds.contains(&task.kind()) {
+                        log::warn!(
+                            "Dropping task {} as the coordinator no longer supports {} responses.",
+                            task.event.taskId,
+                            task.kind()
+                        );
+                        continue;
+                    }
+                    log::debug!(
+                        "Dispatching task {} after {}ms in queue ({})",
+                        task.event.taskId,
+                        task.queued_at.elapsed().as_millis(),
+                        queue
+                    );
+                    let node = task_node.clone();
+                    in_flight.spawn(task_pool.spawn_pinned(move || async move {
+                        node.process_task_by_event(task.event, &task.log).await
+                    }));
+                }
+
+                // checked after each event, so that an idle node is alerted at the next gate check as well
+                let drained = in_flight.is_empty() && self.config.serve_gate.is_maintenance();
+                if drained && !is_drained {
+                    log::info!(
+                        "Finished the tasks in progress for coordinator {}, it can be stopped safely.",
+                        self.coordinator.address()
+                    );
+                }
+                is_drained = drained;
+
                 tokio::select! {
                     _ = cancellation.cancelled() => {
                         log::debug!("Cancellation signal received. Stopping...");
+                        if !in_flight.is_empty() {
+                            log::info!("Waiting for {} tasks in progress.", in_flight.len());
+                            while in_flight.join_next().await.is_some() {}
+                        }
+                        log::info!("Task queue: {}", queue);
+                        log::info!("Nonce mining: {}", self.mining_stats);
                         return Ok(());
                     }
+                    Some(result) = in_flight.join_next(), if !in_flight.is_empty() => {
+                        // the outer error is of the spawned handle, the inner one of the pinned task itself
+                        if let Ok(Err(e)) | Err(e) = result {
+                            log::error!("Task has panicked: {}", e);
+                        }
+                    }
+                    _ = gate_check.tick() => {}
+                    Some(task) = enqueuing.next(), if !enqueuing.is_empty() => {
+                        if let Some(task) = task {
+                            let task_id = task.event.taskId;
+                            queue.push(task);
+                            log::debug!("Queued task {} ({})", task_id, queue);
+                        }
+                    }
+                    // the storage is probed alongside the event loop, as an unhealthy one may be slow to respond
+                    _ = storage_check.tick(), if storage.is_some() && storage_checking.is_empty() => {
+                        let Some(arweave) = &storage else { continue };
+                        storage_checking.push(arweave.check_health());
+                    }
+                    Some(result) = storage_checking.next(), if !storage_checking.is_empty() => {
+                        match (result, is_storage_healthy) {
+                            (Ok(()), false) => {
+                                log::info!("Storage is healthy again, resuming the tasks.");
+                                is_storage_healthy = true;
+                            }
+                            (Err(e), true) => {
+                                log::error!("Storage is unhealthy, pausing the tasks until it recovers: {:#}", e);
+                                is_storage_healthy = false;
+                            }
+                            _ => {}
+                        }
+                    }
+                    // the base fee is read alongside the event loop, so that a slow RPC does not hold up the tasks
+                    _ = gas_check.tick(), if self.config.gas_budget.is_some() && gas_checking.is_empty() => {
+                        gas_checking.push(self.get_base_fee());
+                    }
+                    Some(result) = gas_checking.next(), if !gas_checking.is_empty() => {
+                        match result {
+                            Ok(base_fee) => {
+                                if let Some(gas_budget) = &self.config.gas_budget {
+                                    is_gas_affordable = gas_budget.allows(base_fee);
+                                }
+                            }
+                            Err(e) => log::warn!("Could not check the base fee: {:#}", e),
+                        }
+                    }
+                    // the interface is probed alongside the event loop, as it takes several calls
+                    _ = interface_check.tick(), if interface_checking.is_empty() => {
+                        interface_checking.push(self.probe_coordinator_interface());
+                    }
+                    Some(result) = interface_checking.next(), if !interface_checking.is_empty() => {
+                        match result {
+                            Ok((address, interface)) if implementation != Some(address) => {
+                                if implementation.is_some() {
+                                    log::warn!("Coordinator has been upgraded to {}", address);
+                                }
+                                if !interface.is_compatible() {
+                                    log::error!(
+                                        "Coordinator implementation {} is incompatible with this version of the node ({}), please update the node!",
+                                        address,
+                                        interface
+                                    );
+                                }
+                                implementation = Some(address);
+
+                                let kinds = self
+                                    .kinds
+                                    .iter()
+                                    .copied()
+                                    .filter(|kind| interface.supports(*kind))
+                                    .collect::<Vec<_>>();
+                                for kind in &self.kinds {
+                                    match (supported_kinds.contains(kind), kinds.contains(kind)) {
+                                        (true, false) => log::error!("Tasks of {} can not be handled until the node is updated.", kind),
+                                        (false, true) => log::info!("Tasks of {} can be handled again.", kind),
+                                        _ => {}
+                                    }
+                                }
+                                supported_kinds = kinds;
+                                if supported_kinds.is_empty() {
+                                    if !in_flight.is_empty() {
+                                        log::info!("Waiting for {} tasks in progress.", in_flight.len());
+                                        while in_flight.join_next().await.is_some() {}
+                                    }
+                                    return Err(eyre!(
+                                        "None of the kinds can be handled with the coordinator implementation {}, please update the node!",
+                                        address
+                                    ));
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::warn!("Could not probe the coordinator interface: {:#}", e),
+                        }
+                    }
+                    _ = db_compaction.tick(), if !self.config.retention.is_unbounded() => {
+                        if let Err(e) = self.prune_db(&self.config.retention).await {
+                            log::warn!("Could not prune the database: {:#}", e);
+                        }
+                    }
+                    _ = outbox_retry.tick(), if outbox_retrying.is_empty() => {
+                        outbox_retrying.push(self.retry_outbox());
+                    }
+                    Some(result) = outbox_retrying.next(), if !outbox_retrying.is_empty() => {
+                        match result {
+                            Ok((0, 0)) => {}
+                            Ok((sent, remaining)) => log::info!(
+                                "Sent {} responses from the outbox, {} remaining.",
+                                sent,
+                                remaining
+                            ),
+                            Err(e) => log::warn!("Could not retry the outbox: {:#}", e),
+                        }
+                    }
+                    Some(result) = peer_recording.next(), if !peer_recording.is_empty() => {
+                        if let Err(e) = result {
+                            log::warn!("Could not record the responses of a completed task: {:#}", e);
+                        }
+                    }
                     next = event_stream.next() => {
                         match next {
                             Some(Ok((event, log))) => {
-                                log::debug!(
-                                    "Handling task {} (tx: {})",
-                                    event.taskId,
-                                    log.transaction_hash.unwrap_or_default()
-                                );
-                                self.process_task_by_event(event).await
+                                self.publish_event(BridgeEvent::status_update(
+                                    *self.coordinator.address(),
+                                    &event,
+                                    &log,
+                                ));
+                                if event.statusAfter == u8::from(TaskStatus::Completed) {
+                                    let task_id = event.taskId;
+                                    peer_recording.push(async move {
+                                        self.record_completed_tasks(&[task_id]).await
+                                    });
+                                    continue;
+                                }
+                                match self.shard_delay(event.taskId) {
+                                    Some(delay) if delay.is_zero() => {
+                                        log::debug!(
+                                            "Queueing task {} (tx: {})",
+                                            event.taskId,
+                                            self.explorer.tx(log.transaction_hash.unwrap_or_default())
+                                        );
+                                        enqueuing.push(self.prepare_task(event, log).boxed());
+                                    }
+                                    Some(delay) => {
+                                        log::debug!(
+                                            "Deferring task {} of another shard for {}s",
+                                            event.taskId,
+                                            delay.as_secs()
+                                        );
+                                        enqueuing.push(
+                                            async move {
+                                                tokio::time::sleep(delay).await;
+                                                self.prepare_deferred_task(event, log).await
+                                            }
+                                            .boxed(),
+                                        );
+                                    }
+                                    None => log::debug!("Ignoring task {} of another shard", event.taskId),
+                                }
                             }
                             Some(Err(e)) => log::error!("Could not handle event: {}", e),
                             None => {
@@ -96,4 +359,20 @@ impl DriaOracle {
             }
         }
     }
+
+    /// Processes the previous tasks within the given range, in parallel if `workers` is given.
+    async fn catch_up(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+        workers: Option<usize>,
+    ) -> Result<()> {
+        match workers {
+            Some(workers) => {
+                self.process_tasks_within_range_parallel(from_block, to_block, workers)
+                    .await
+            }
+            None => self.process_tasks_within_range(from_block, to_block).await,
+        }
+    }
 }
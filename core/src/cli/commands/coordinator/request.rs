@@ -1,10 +1,14 @@
-use alloy::primitives::utils::format_ether;
+use alloy::primitives::{Bytes, U256};
+use alloy::rpc::types::TransactionReceipt;
 use dkn_workflows::Model;
-use dria_oracle_contracts::string_to_bytes;
-use eyre::Result;
+use dria_oracle_contracts::{string_to_bytes, OracleCoordinator::Request, TokenBalance};
+use dria_oracle_storage::ArweaveStorage;
+use eyre::{eyre, Context, Result};
+
+use crate::OracleRequest;
 
 impl crate::DriaOracle {
-    /// Requests a task with the given parameters.
+    /// Requests a task with the given parameters, returns the id of the created task.
     ///
     /// Oracle does not usually do this, but we still provide the capability for testing & playing around.
     pub async fn request_task(
@@ -15,15 +19,47 @@ impl crate::DriaOracle {
         num_gens: u64,
         num_vals: u64,
         protocol: String,
-    ) -> Result<()> {
+    ) -> Result<U256> {
+        let request = OracleRequest::new(protocol, string_to_bytes(input.to_string()), &models)?
+            .with_difficulty(difficulty)
+            .with_generations(num_gens)
+            .with_validations(num_vals);
+
+        self.send_request(request).await
+    }
+
+    /// Makes sure that a chat can continue the given history, i.e. its task exists and its best
+    /// response can be rebuilt into a message history.
+    ///
+    /// This is checked before a chat request is made, so that the fee is not paid for a request
+    /// that the nodes would fail to handle.
+    pub async fn check_chat_history(&self, history_id: usize) -> Result<()> {
+        let next_task_id = self.coordinator.nextTaskId().call().await?._0;
+        if history_id == 0 || U256::from(history_id) >= next_task_id {
+            return Err(eyre!("history task {} does not exist", history_id));
+        }
+
+        let history = self
+            .get_chat_history(history_id)
+            .await
+            .wrap_err_with(|| format!("could not resolve the history of task {}", history_id))?;
+        log::info!(
+            "Continuing the history of task {} with {} messages.",
+            history_id,
+            history.messages.len()
+        );
+
+        Ok(())
+    }
+
+    /// Sends a request built with one of the request builders, returns the id of the created task.
+    ///
+    /// The input is uploaded to Arweave if it is too large, and the fee is approved if needed.
+    pub async fn send_request(&self, request: OracleRequest) -> Result<U256> {
         log::info!("Requesting a new task.");
-        let input = string_to_bytes(input.to_string());
-        let models_str = models
-            .iter()
-            .map(|m| m.to_string())
-            .collect::<Vec<String>>()
-            .join(",");
-        let models = string_to_bytes(models_str);
+        let input = upload_if_large(request.input().clone()).await?;
+        let (difficulty, num_gens, num_vals) =
+            (request.difficulty, request.num_gens, request.num_vals);
 
         // get total fee for the request
         log::debug!("Checking fee & allowance.");
@@ -39,17 +75,23 @@ impl crate::DriaOracle {
             ));
         }
 
+        self.confirm_spending(
+            "Pay the fee of a task request to the coordinator.",
+            total_fee,
+            *self.coordinator.address(),
+        )
+        .await?;
+
         // check current allowance
         let allowance = self
             .allowance(self.address(), *self.coordinator.address())
-            .await?
-            .amount;
+            .await?;
         // make sure we have enough allowance
-        if allowance < total_fee {
-            let approval_amount = total_fee - allowance;
+        if allowance.amount < total_fee {
+            let approval_amount = total_fee - allowance.amount;
             log::info!(
                 "Insufficient allowance. Approving the required amount: {}.",
-                format_ether(approval_amount)
+                TokenBalance::format_amount(approval_amount, allowance.decimals)
             );
 
             self.approve(*self.coordinator.address(), approval_amount)
@@ -59,13 +101,52 @@ impl crate::DriaOracle {
 
         // make the request
         let receipt = self
-            .request(input, models, difficulty, num_gens, num_vals, protocol)
+            .request(
+                input,
+                request.models().clone(),
+                difficulty,
+                num_gens,
+                num_vals,
+                request.protocol().to_string(),
+            )
             .await?;
+        let task_id = task_id_from_receipt(&receipt)?;
         log::info!(
-            "Task requested successfully. tx: {}",
-            receipt.transaction_hash
+            "Task {} requested successfully. tx: {}",
+            task_id,
+            self.explorer.tx(receipt.transaction_hash)
         );
 
-        Ok(())
+        Ok(task_id)
+    }
+}
+
+/// Returns the id of the task created by the given request receipt.
+pub(super) fn task_id_from_receipt(receipt: &TransactionReceipt) -> Result<U256> {
+    receipt
+        .inner
+        .logs()
+        .iter()
+        .find_map(|log| log.log_decode::<Request>().ok())
+        .map(|log| log.inner.data.taskId)
+        .ok_or_else(|| eyre!("could not find the task id within the request receipt"))
+}
+
+/// Converts the input to bytes, uploading it to Arweave if it is larger than the configured byte limit,
+/// in which case the storage key is used as the input instead.
+///
+/// If Arweave is not configured (i.e. there is no `ARWEAVE_WALLET_PATH`), the input is used as is.
+pub(super) async fn input_to_bytes(input: String) -> Result<Bytes> {
+    upload_if_large(string_to_bytes(input)).await
+}
+
+/// Uploads the input to Arweave if it is larger than the configured byte limit, see [`input_to_bytes`].
+async fn upload_if_large(input: Bytes) -> Result<Bytes> {
+    match ArweaveStorage::new_from_env() {
+        Ok(arweave) => arweave.put_if_large(input).await,
+        Err(_) => {
+            log::debug!("Arweave is not configured, using the input as is.");
+            Ok(input)
+        }
     }
 }
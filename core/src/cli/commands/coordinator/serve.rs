@@ -1,9 +1,18 @@
-use crate::{compute::handle_request, DriaOracle};
-use alloy::{eips::BlockNumberOrTag, primitives::U256};
+use crate::{compute::handle_request, DriaOracle, QueuedTask, TaskQueue, WalDecision};
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::rpc::types::{BlockTransactionsKind, Log};
 use dria_oracle_contracts::{
-    bytes32_to_string, bytes_to_string, OracleCoordinator::StatusUpdate, TaskStatus,
+    bytes32_to_string, bytes_to_string, OracleCoordinator::StatusUpdate, OracleKind, TaskStatus,
 };
-use eyre::Result;
+use eyre::{eyre, Result};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Time to wait for the pending transactions before resuming the interrupted tasks, if there is no tx timeout.
+const RESUME_PENDING_TX_TIMEOUT: Duration = Duration::from_secs(120);
 
 impl DriaOracle {
     pub(in crate::cli) async fn process_task_by_id(&self, task_id: U256) -> Result<()> {
@@ -32,17 +41,218 @@ impl DriaOracle {
         Ok(())
     }
 
-    pub(in crate::cli) async fn process_task_by_event(&self, event: StatusUpdate) {
+    pub(in crate::cli) async fn process_task_by_event(&self, event: StatusUpdate, log: &Log) {
         let Ok(status) = TaskStatus::try_from(event.statusAfter) else {
             log::error!("Could not parse task status: {}", event.statusAfter);
             return;
         };
 
+        match self.is_in_shard(event.taskId, log).await {
+            Ok(true) => {}
+            Ok(false) => {
+                log::debug!("Skipping task {} of another shard.", event.taskId);
+                return;
+            }
+            Err(e) => {
+                log::warn!("Could not check shard of task {}: {:#}", event.taskId, e);
+                return;
+            }
+        }
+
+        match self.is_past_deadline(status, log).await {
+            Ok(false) => {}
+            Ok(true) => {
+                log::info!("Skipping task {} as it is past its deadline.", event.taskId);
+                return;
+            }
+            Err(e) => log::warn!("Could not check deadline of task {}: {:#}", event.taskId, e),
+        }
+
         if let Err(err) = handle_request(self, status, event.taskId, event.protocol).await {
             log::error!("Could not process task {}: {:?}", event.taskId, err);
         }
     }
 
+    /// Resumes the tasks that were being handled when the node stopped, as recorded by the write-ahead log.
+    ///
+    /// A response may have been broadcast right before the node stopped without being recorded to the
+    /// ledger, so the pending transactions of our address are awaited first, and the tasks that we have
+    /// responded to on-chain are not handled again. A task that can not be checked is left for the next start.
+    pub(in crate::cli) async fn resume_interrupted_tasks(&self) {
+        let interrupted = match self.db.get_interrupted_tasks(self.response_scope()) {
+            Ok(interrupted) => interrupted,
+            Err(e) => {
+                log::warn!("Could not read the write-ahead log: {:#}", e);
+                return;
+            }
+        };
+        if interrupted
+            .iter()
+            .any(|entry| matches!(entry.decision, WalDecision::Submitting { .. }))
+        {
+            let timeout = self.config.tx_timeout.unwrap_or(RESUME_PENDING_TX_TIMEOUT);
+            match self.wait_for_pending_transactions(timeout).await {
+                Ok(true) => {}
+                Ok(false) => log::warn!("There are still pending transactions, resuming anyways."),
+                Err(e) => log::warn!("Could not check the pending transactions: {:#}", e),
+            }
+        }
+
+        let mut task_ids = Vec::new();
+        for entry in interrupted {
+            let Some(kind) = self.kinds.iter().find(|k| k.to_string() == entry.kind) else {
+                continue;
+            };
+            match self.has_responded_on_chain(entry.task_id, *kind).await {
+                Ok(false) => {}
+                Ok(true) => {
+                    log::info!(
+                        "Task {} is already responded as {}, not resuming it.",
+                        entry.task_id,
+                        kind
+                    );
+                    self.log_decision(entry.task_id, *kind, WalDecision::Finished)
+                        .await;
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not check the responses of task {}, not resuming it: {:#}",
+                        entry.task_id,
+                        e
+                    );
+                    continue;
+                }
+            }
+
+            // the task is no longer interrupted even if it has moved on to a status that we ignore
+            self.log_decision(entry.task_id, *kind, WalDecision::Resumed)
+                .await;
+            task_ids.push(entry.task_id);
+        }
+        task_ids.dedup();
+        if task_ids.is_empty() {
+            return;
+        }
+
+        log::warn!("Resuming {} interrupted tasks.", task_ids.len());
+        for task_id in task_ids {
+            self.wait_for_serve_gate().await;
+            if let Err(e) = self.process_task_by_id(task_id).await {
+                log::error!("Could not resume task {}: {:#}", task_id, e);
+            }
+        }
+    }
+
+    /// Waits until the serve gate is open, so that the tasks handled outside of the event loop, i.e. the
+    /// interrupted ones & the ones within a previous range, are not taken out of the serve windows or
+    /// in maintenance mode either.
+    async fn wait_for_serve_gate(&self) {
+        if self.config.serve_gate.is_open() {
+            return;
+        }
+
+        log::info!(
+            "Waiting for the serve gate ({}) to open.",
+            self.config.serve_gate
+        );
+        while !self.config.serve_gate.is_open() {
+            tokio::time::sleep(Self::SERVE_GATE_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Returns the queue entry of the task of the given event, if it is pending for one of our kinds.
+    ///
+    /// This reads the task from the chain, so it is run alongside the event loop rather than within it,
+    /// so that the queue is still dispatched meanwhile.
+    pub(in crate::cli) async fn prepare_task(
+        &self,
+        event: StatusUpdate,
+        log: Log,
+    ) -> Option<QueuedTask> {
+        let (status, kind) = match TaskStatus::try_from(event.statusAfter) {
+            Ok(TaskStatus::PendingGeneration) => {
+                (TaskStatus::PendingGeneration, OracleKind::Generator)
+            }
+            Ok(TaskStatus::PendingValidation) => {
+                (TaskStatus::PendingValidation, OracleKind::Validator)
+            }
+            Ok(_) => return None,
+            Err(_) => {
+                log::error!("Could not parse task status: {}", event.statusAfter);
+                return None;
+            }
+        };
+        if !self.kinds.contains(&kind) {
+            log::debug!(
+                "Ignoring {} task {} as you are not {}.",
+                status,
+                event.taskId,
+                kind
+            );
+            return None;
+        }
+
+        let request = match self.get_request_cached(event.taskId, status).await {
+            Ok(request) => request,
+            Err(e) => {
+                log::error!("Could not read task {}: {:#}", event.taskId, e);
+                return None;
+            }
+        };
+        let fee = match kind {
+            OracleKind::Generator => request.generatorFee,
+            OracleKind::Validator => request.validatorFee,
+        };
+
+        Some(QueuedTask {
+            event,
+            log,
+            protocol: bytes32_to_string(&request.protocol).unwrap_or_default(),
+            fee,
+            difficulty: request.parameters.difficulty,
+            queued_at: Instant::now(),
+        })
+    }
+
+    /// Returns the queue entry of the task of the given event after it was deferred for being of another
+    /// shard, if it is still at the status of the event, i.e. the node of its shard has not handled it.
+    pub(in crate::cli) async fn prepare_deferred_task(
+        &self,
+        event: StatusUpdate,
+        log: Log,
+    ) -> Option<QueuedTask> {
+        match self
+            .is_task_at_status(event.taskId, event.statusAfter)
+            .await
+        {
+            Ok(true) => {
+                log::info!("Failing over to task {} of another shard.", event.taskId);
+                self.prepare_task(event, log).await
+            }
+            Ok(false) => {
+                log::debug!(
+                    "Skipping task {} as it was handled by its shard.",
+                    event.taskId
+                );
+                None
+            }
+            Err(e) => {
+                log::error!("Could not check status of task {}: {:#}", event.taskId, e);
+                None
+            }
+        }
+    }
+
+    /// Returns the delay before queueing a new task with respect to the shard of this node, i.e. zero
+    /// for the tasks of our shard and the failover duration for others, `None` if it is not to be handled.
+    pub(in crate::cli) fn shard_delay(&self, task_id: U256) -> Option<Duration> {
+        match &self.config.shard {
+            Some(shard) if !shard.owns(task_id) => shard.failover,
+            _ => Some(Duration::ZERO),
+        }
+    }
+
     pub(in crate::cli) async fn process_tasks_within_range(
         &self,
         from_block: BlockNumberOrTag,
@@ -60,10 +270,275 @@ impl DriaOracle {
                 .unwrap_or(to_block.to_string())
         );
 
-        for (event, _) in self.get_tasks_in_range(from_block, to_block).await? {
-            self.process_task_by_event(event).await;
+        let events = self.get_tasks_in_range(from_block, to_block).await?;
+        for (event, log) in latest_task_events(events) {
+            // the task may have moved on since this event, e.g. completed by others
+            match self
+                .is_task_at_status(event.taskId, event.statusAfter)
+                .await
+            {
+                Ok(true) => {
+                    self.wait_for_serve_gate().await;
+                    self.process_task_by_event(event, &log).await
+                }
+                Ok(false) => log::debug!(
+                    "Skipping task {} as it is no longer {}.",
+                    event.taskId,
+                    TaskStatus::try_from(event.statusAfter).unwrap_or_default()
+                ),
+                Err(e) => log::error!("Could not check status of task {}: {:#}", event.taskId, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the task can not be handled before the configured deadline, if any.
+    ///
+    /// The given log belongs to the event that has moved the task to its given status,
+    /// and the age of the task is measured from the block of that event.
+    async fn is_past_deadline(&self, status: TaskStatus, log: &Log) -> Result<bool> {
+        let Some(deadline) = self.config.task_deadline else {
+            return Ok(false);
+        };
+        let kind = match status {
+            TaskStatus::PendingGeneration => OracleKind::Generator,
+            TaskStatus::PendingValidation => OracleKind::Validator,
+            _ => return Ok(false),
+        };
+        let age = self.task_age(log).await?;
+
+        Ok(self.execution_times.is_past_deadline(kind, age, deadline))
+    }
+
+    /// Returns `true` if the task belongs to the shard of this node or has failed over to it,
+    /// where the given log belongs to the event that has moved the task to its current status.
+    ///
+    /// Returns `true` if there is no shard configured.
+    async fn is_in_shard(&self, task_id: U256, log: &Log) -> Result<bool> {
+        let Some(shard) = &self.config.shard else {
+            return Ok(true);
+        };
+        if shard.owns(task_id) {
+            return Ok(true);
+        }
+        if shard.failover.is_none() {
+            return Ok(false);
+        }
+        let age = self.task_age(log).await?;
+
+        Ok(shard.should_handle(task_id, age))
+    }
+
+    /// Returns the time since the block of the given log.
+    async fn task_age(&self, log: &Log) -> Result<Duration> {
+        let timestamp = match log.block_timestamp {
+            Some(timestamp) => timestamp,
+            None => {
+                let block_number = log
+                    .block_number
+                    .ok_or_else(|| eyre!("log has no block number"))?;
+                self.provider
+                    .get_block_by_number(block_number.into(), BlockTransactionsKind::Hashes)
+                    .await?
+                    .ok_or_else(|| eyre!("block {} not found", block_number))?
+                    .header
+                    .timestamp
+            }
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        Ok(Duration::from_secs(now.saturating_sub(timestamp)))
+    }
+
+    /// Returns `true` if the current status of the task on-chain is the given one.
+    async fn is_task_at_status(&self, task_id: U256, status: u8) -> Result<bool> {
+        let request = self.coordinator.requests(task_id).call().await?;
+        Ok(request.status == status)
+    }
+
+    /// Processes the tasks within the given range with a pipeline, instead of handling each event in order.
+    ///
+    /// 1. Logs are fetched in chunks, with `workers` chunks at the same time.
+    /// 2. Tasks are read in batches via multicall, with `workers` batches at the same time.
+    /// 3. Tasks that are still pending for our kinds are kept, and those that we have responded to are dropped.
+    /// 4. Remaining tasks are handled with `workers` tasks at the same time, where the actual
+    ///    computations are still limited by the node's capacity.
+    pub(in crate::cli) async fn process_tasks_within_range_parallel(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+        workers: usize,
+    ) -> Result<()> {
+        let workers = workers.max(1);
+        let from_block = self.resolve_block_number(from_block).await?;
+        let to_block = self.resolve_block_number(to_block).await?;
+        log::info!(
+            "Processing tasks between blocks: {} - {} with {} workers",
+            from_block,
+            to_block,
+            workers
+        );
+
+        // fetch logs in chunks
+        let chunks = (from_block..=to_block)
+            .step_by(Self::LOG_QUERY_CHUNK_SIZE as usize)
+            .map(|start| (start, to_block.min(start + Self::LOG_QUERY_CHUNK_SIZE - 1)));
+        let events = stream::iter(chunks)
+            .map(|(start, end)| self.get_tasks_in_range(start, end))
+            .buffered(workers)
+            .try_concat()
+            .await?;
+
+        // collect the protocol of each task, in the order they first appear,
+        // along with the latest log for each status of the task
+        let mut protocols = HashMap::new();
+        let mut task_ids = Vec::new();
+        let mut status_logs = HashMap::new();
+        for (event, log) in &events {
+            if protocols.insert(event.taskId, event.protocol).is_none() {
+                task_ids.push(event.taskId);
+            }
+            status_logs.insert((event.taskId, event.statusAfter), log);
         }
 
+        // read tasks in batches
+        let tasks = stream::iter(task_ids.chunks(Self::TASKS_PER_MULTICALL))
+            .map(|chunk| self.get_tasks(chunk))
+            .buffered(workers)
+            .try_concat()
+            .await?;
+
+        // keep the tasks that are pending for us
+        let pending = tasks
+            .into_iter()
+            .filter(|task| match task.request.status {
+                TaskStatus::PendingGeneration => {
+                    self.kinds.contains(&OracleKind::Generator)
+                        && !task.responses.iter().any(|r| r.responder == self.address())
+                }
+                TaskStatus::PendingValidation => {
+                    self.kinds.contains(&OracleKind::Validator)
+                        && !task
+                            .validations
+                            .iter()
+                            .any(|v| v.validator == self.address())
+                }
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+        log::info!(
+            "Found {} events of {} tasks, {} of them are pending for us.",
+            events.len(),
+            task_ids.len(),
+            pending.len()
+        );
+
+        // handle the pending tasks
+        stream::iter(pending)
+            .for_each_concurrent(workers, |task| {
+                let protocol = protocols[&task.task_id];
+                let log = status_logs.get(&(task.task_id, task.request.status.into()));
+                async move {
+                    let in_shard = match log {
+                        Some(log) => self.is_in_shard(task.task_id, log).await,
+                        None => Ok(self.shard_delay(task.task_id).is_some()),
+                    };
+                    match in_shard {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            log::debug!("Skipping task {} of another shard.", task.task_id);
+                            return;
+                        }
+                        Err(e) => {
+                            log::warn!("Could not check shard of task {}: {:#}", task.task_id, e);
+                            return;
+                        }
+                    }
+
+                    if let Some(log) = log {
+                        match self.is_past_deadline(task.request.status, log).await {
+                            Ok(false) => {}
+                            Ok(true) => {
+                                log::info!(
+                                    "Skipping task {} as it is past its deadline.",
+                                    task.task_id
+                                );
+                                return;
+                            }
+                            Err(e) => log::warn!(
+                                "Could not check deadline of task {}: {:#}",
+                                task.task_id,
+                                e
+                            ),
+                        }
+                    }
+
+                    self.wait_for_serve_gate().await;
+                    if let Err(err) =
+                        handle_request(self, task.request.status, task.task_id, protocol).await
+                    {
+                        log::error!("Could not process task {}: {:?}", task.task_id, err);
+                    }
+                }
+            })
+            .await;
+
         Ok(())
     }
 }
+
+/// Collapses the events of each task to its latest event, so that a task is handled once
+/// with respect to its latest status within the range.
+///
+/// Tasks are ordered by their latest events.
+fn latest_task_events(events: Vec<(StatusUpdate, Log)>) -> Vec<(StatusUpdate, Log)> {
+    let latest = events
+        .iter()
+        .enumerate()
+        .map(|(idx, (event, _))| (event.taskId, idx))
+        .collect::<HashMap<_, _>>();
+
+    events
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, (event, _))| latest[&event.taskId] == *idx)
+        .map(|(_, event)| event)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::FixedBytes;
+
+    #[test]
+    fn test_latest_task_events() {
+        let event = |task_id: u64, before: TaskStatus, after: TaskStatus| {
+            let event = StatusUpdate {
+                taskId: U256::from(task_id),
+                protocol: FixedBytes::ZERO,
+                statusBefore: before.into(),
+                statusAfter: after.into(),
+            };
+            (event, Log::default())
+        };
+
+        let events = vec![
+            event(1, TaskStatus::None, TaskStatus::PendingGeneration),
+            event(2, TaskStatus::None, TaskStatus::PendingGeneration),
+            event(
+                1,
+                TaskStatus::PendingGeneration,
+                TaskStatus::PendingValidation,
+            ),
+            event(1, TaskStatus::PendingValidation, TaskStatus::Completed),
+        ];
+
+        let latest = latest_task_events(events);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].0.taskId, U256::from(2));
+        assert_eq!(latest[1].0.taskId, U256::from(1));
+        assert_eq!(latest[1].0.statusAfter, u8::from(TaskStatus::Completed));
+    }
+}
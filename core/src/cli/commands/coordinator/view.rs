@@ -1,5 +1,5 @@
 use alloy::{eips::BlockNumberOrTag, primitives::U256};
-use dria_oracle_contracts::{bytes32_to_string, bytes_to_string, TaskStatus};
+use dria_oracle_contracts::TaskStatus;
 use eyre::Result;
 
 impl crate::DriaOracle {
@@ -29,7 +29,22 @@ impl crate::DriaOracle {
                 TaskStatus::try_from(event.statusBefore).unwrap_or_default(),
                 TaskStatus::try_from(event.statusAfter).unwrap_or_default(),
                 log.block_number.unwrap_or_default(),
-                log.transaction_hash.unwrap_or_default()
+                self.explorer.tx(log.transaction_hash.unwrap_or_default())
+            );
+        }
+
+        // other events are shown as well, e.g. registrations & upgrades
+        let from_block = self.resolve_block_number(from_block).await?;
+        let to_block = self.resolve_block_number(to_block).await?;
+        for event in self
+            .get_contract_events_in_range(from_block, to_block)
+            .await?
+        {
+            log::info!(
+                "{} at block {}, tx: {}",
+                event.event,
+                event.block_number,
+                self.explorer.tx(event.tx_hash)
             );
         }
 
@@ -39,47 +54,71 @@ impl crate::DriaOracle {
     /// Views the request, responses and validations of a single task, logs everything on screen.
     pub(in crate::cli) async fn view_task(&self, task_id: U256) -> Result<()> {
         log::info!("Viewing task {}.", task_id);
-        let (request, responses, validations) = self.get_task(task_id).await?;
+        let task = self.get_task(task_id).await?;
 
         log::info!(
           "Request Information:\nRequester: {}\nStatus:    {}\nInput:     {}\nModels:    {}\nProtocol:   {}",
-          request.requester,
-          TaskStatus::try_from(request.status)?,
-          bytes_to_string(&request.input)?,
-          bytes_to_string(&request.models)?,
-          bytes32_to_string(&request.protocol)?
+          self.explorer.address(task.request.requester),
+          task.request.status,
+          task.request.input,
+          task.request.models.join(","),
+          task.request.protocol
       );
 
         log::info!("Responses:");
-        if responses._0.is_empty() {
+        if task.responses.is_empty() {
             log::info!("There are no responses yet.");
         } else {
-            for (idx, response) in responses._0.iter().enumerate() {
+            for (idx, response) in task.responses.iter().enumerate() {
                 log::info!(
                     "Response  #{}\nOutput:    {}\nMetadata:  {}\nGenerator: {}",
                     idx,
-                    bytes_to_string(&response.output)?,
-                    bytes_to_string(&response.metadata)?,
-                    response.responder
+                    response.output,
+                    response.metadata,
+                    self.explorer.address(response.responder)
                 );
             }
         }
 
         log::info!("Validations:");
-        if validations._0.is_empty() {
+        if task.validations.is_empty() {
             log::info!("There are no validations yet.");
         } else {
-            for (idx, validation) in validations._0.iter().enumerate() {
+            for (idx, validation) in task.validations.iter().enumerate() {
                 log::info!(
                     "Validation #{}\nScores:     {:?}\nMetadata:   {}\nValidator:  {}",
                     idx,
                     validation.scores,
-                    bytes_to_string(&validation.metadata)?,
-                    validation.validator
+                    validation.metadata,
+                    self.explorer.address(validation.validator)
                 );
             }
         }
 
+        // the history is best-effort, as some RPCs limit the block range of log queries
+        log::info!("History:");
+        match self
+            .get_task_events(
+                task_id,
+                BlockNumberOrTag::Earliest,
+                BlockNumberOrTag::Latest,
+            )
+            .await
+        {
+            Ok(events) => {
+                for (event, log) in events {
+                    log::info!(
+                        "{} -> {} at block {}, tx: {}",
+                        TaskStatus::try_from(event.statusBefore).unwrap_or_default(),
+                        TaskStatus::try_from(event.statusAfter).unwrap_or_default(),
+                        log.block_number.unwrap_or_default(),
+                        self.explorer.tx(log.transaction_hash.unwrap_or_default())
+                    );
+                }
+            }
+            Err(e) => log::warn!("Could not get the task history: {}", e),
+        }
+
         Ok(())
     }
 }
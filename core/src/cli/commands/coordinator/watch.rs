@@ -0,0 +1,134 @@
+use alloy::primitives::{B256, U256};
+use dria_oracle_contracts::TaskStatus;
+use eyre::{eyre, Context, Result};
+use futures_util::StreamExt;
+use tokio_util::sync::CancellationToken;
+
+impl crate::DriaOracle {
+    /// Follows a task until it is completed, logging its status transitions, responses and validations.
+    ///
+    /// Once the task is completed, its best response is logged along with the validation scores.
+    pub(in crate::cli) async fn watch_task(
+        &self,
+        task_id: U256,
+        cancellation: CancellationToken,
+    ) -> Result<()> {
+        if self.follow_task(task_id, cancellation).await? {
+            self.display_task_result(task_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Follows a task until it is completed like [`Self::watch_task`], without logging its result.
+    ///
+    /// Returns `true` once the task is completed, or `false` if the cancellation is requested before.
+    pub(in crate::cli) async fn follow_task(
+        &self,
+        task_id: U256,
+        cancellation: CancellationToken,
+    ) -> Result<bool> {
+        // subscribe before reading the status, so that no transition is missed in between
+        let topic = B256::from(task_id);
+        let mut status_stream = self
+            .coordinator
+            .StatusUpdate_filter()
+            .topic1(topic)
+            .watch()
+            .await
+            .wrap_err("could not subscribe to status updates")?
+            .into_stream();
+        let mut response_stream = self
+            .coordinator
+            .Response_filter()
+            .topic1(topic)
+            .watch()
+            .await
+            .wrap_err("could not subscribe to responses")?
+            .into_stream();
+        let mut validation_stream = self
+            .coordinator
+            .Validation_filter()
+            .topic1(topic)
+            .watch()
+            .await
+            .wrap_err("could not subscribe to validations")?
+            .into_stream();
+
+        let status = self.get_task(task_id).await?.request.status;
+        log::info!("Task {} is {}.", task_id, status);
+        if matches!(status, TaskStatus::Completed) {
+            return Ok(true);
+        }
+
+        log::info!("Watching task {}...", task_id);
+        loop {
+            tokio::select! {
+                _ = cancellation.cancelled() => {
+                    log::debug!("Cancellation signal received. Stopping...");
+                    return Ok(false);
+                }
+                Some(next) = status_stream.next() => match next {
+                    Ok((event, log)) => {
+                        let status = TaskStatus::try_from(event.statusAfter)?;
+                        log::info!(
+                            "Task {} changed from {} to {}, tx: {}",
+                            task_id,
+                            TaskStatus::try_from(event.statusBefore).unwrap_or_default(),
+                            status,
+                            self.explorer.tx(log.transaction_hash.unwrap_or_default())
+                        );
+
+                        if matches!(status, TaskStatus::Completed) {
+                            return Ok(true);
+                        }
+                    }
+                    Err(e) => log::error!("Could not handle status update: {}", e),
+                },
+                Some(next) = response_stream.next() => match next {
+                    Ok((event, log)) => log::info!(
+                        "Task {} received a response from {}, tx: {}",
+                        task_id,
+                        self.explorer.address(event.responder),
+                        self.explorer.tx(log.transaction_hash.unwrap_or_default())
+                    ),
+                    Err(e) => log::error!("Could not handle response: {}", e),
+                },
+                Some(next) = validation_stream.next() => match next {
+                    Ok((event, log)) => log::info!(
+                        "Task {} received a validation from {}, tx: {}",
+                        task_id,
+                        self.explorer.address(event.validator),
+                        self.explorer.tx(log.transaction_hash.unwrap_or_default())
+                    ),
+                    Err(e) => log::error!("Could not handle validation: {}", e),
+                },
+                else => return Err(eyre!("Event streams of task {} ended unexpectedly.", task_id)),
+            }
+        }
+    }
+
+    /// Logs the best response of a completed task along with its validation scores,
+    /// downloading the output from storage if needed.
+    pub(in crate::cli) async fn display_task_result(&self, task_id: U256) -> Result<()> {
+        let best = self.get_task_best_response(task_id).await?;
+        log::info!(
+            "Best Response\nScore:     {}\nGenerator: {}\nMetadata:  {}\nOutput:    {}",
+            best.score,
+            self.explorer.address(best.responder),
+            best.metadata,
+            best.output.fetch().await?
+        );
+
+        let validations = self.coordinator.getValidations(task_id).call().await?._0;
+        for validation in validations {
+            log::info!(
+                "Scores {:?} by {}",
+                validation.scores,
+                self.explorer.address(validation.validator)
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{utils::format_ether, U256};
+use eyre::Result;
+use std::collections::BTreeMap;
+
+use crate::{DriaOracle, TaskCost, TaskTimings};
+
+/// Aggregated costs & rewards over a group of tasks.
+#[derive(Debug, Default)]
+struct CostSummary {
+    tasks: usize,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    provider_cost_usd: f64,
+    gas_fee: U256,
+    reward: U256,
+}
+
+impl CostSummary {
+    fn add(&mut self, cost: &TaskCost) {
+        self.tasks += 1;
+        self.prompt_tokens += cost.prompt_tokens;
+        self.completion_tokens += cost.completion_tokens;
+        self.provider_cost_usd += cost.provider_cost_usd;
+        self.gas_fee += cost.gas_fee;
+        self.reward += cost.reward;
+    }
+}
+
+impl std::fmt::Display for CostSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} tasks, {} prompt / {} completion tokens, ~${:.4} provider cost, {} ETH gas, {} tokens reward",
+            self.tasks,
+            self.prompt_tokens,
+            self.completion_tokens,
+            self.provider_cost_usd,
+            format_ether(self.gas_fee),
+            format_ether(self.reward)
+        )
+    }
+}
+
+impl DriaOracle {
+    /// Displays the recorded costs & rewards between two blocks, grouped by protocol and by model.
+    pub(in crate::cli) async fn display_costs(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+    ) -> Result<()> {
+        let from_block = self.resolve_block_number(from_block).await?;
+        let to_block = self.resolve_block_number(to_block).await?;
+        log::info!(
+            "Viewing costs between blocks: {} - {}",
+            from_block,
+            to_block
+        );
+
+        let costs = self
+            .db
+            .get_costs_in_range(self.response_scope(), from_block, to_block)?;
+        if costs.is_empty() {
+            log::warn!("There are no recorded costs within this range.");
+            return Ok(());
+        }
+
+        let mut total = CostSummary::default();
+        let mut by_protocol = BTreeMap::<String, CostSummary>::new();
+        let mut by_model = BTreeMap::<String, CostSummary>::new();
+        for cost in &costs {
+            total.add(cost);
+            by_protocol
+                .entry(cost.protocol.clone())
+                .or_default()
+                .add(cost);
+            by_model.entry(cost.model.clone()).or_default().add(cost);
+        }
+
+        log::info!("Costs per protocol:");
+        for (protocol, summary) in by_protocol {
+            log::info!("{}: {}", protocol, summary);
+        }
+
+        log::info!("Costs per model:");
+        for (model, summary) in by_model {
+            log::info!("{}: {}", model, summary);
+        }
+
+        log::info!("Total: {}", total);
+
+        // rewards are recorded in fee tokens, w.r.t the decimals of the token
+        let prices = self.get_usd_prices().await;
+        let reward = self.to_token_balance(total.reward).await?;
+        if let (Some(reward_usd), Some(gas_usd)) = (
+            prices.token_usd(reward.amount, reward.decimals),
+            prices.eth_usd(total.gas_fee),
+        ) {
+            log::info!(
+                "Total in USD: ~${:.2} reward, ~${:.2} gas, ~${:.2} provider cost, ~${:.2} profit",
+                reward_usd,
+                gas_usd,
+                total.provider_cost_usd,
+                reward_usd - gas_usd - total.provider_cost_usd
+            );
+        }
+
+        // older records do not have timings
+        let timings = costs
+            .iter()
+            .map(|cost| cost.timings)
+            .filter(|timings| *timings != TaskTimings::default())
+            .collect::<Vec<_>>();
+        if !timings.is_empty() {
+            let average = |stage: fn(&TaskTimings) -> u64| {
+                timings.iter().map(stage).sum::<u64>() / timings.len() as u64
+            };
+            log::info!(
+                "Average time per stage over {} tasks: download {}ms, parse {}ms, model {}ms, post-process {}ms, upload {}ms, mining {}ms, tx {}ms",
+                timings.len(),
+                average(|t| t.download_ms),
+                average(|t| t.parse_ms),
+                average(|t| t.model_ms),
+                average(|t| t.post_process_ms),
+                average(|t| t.upload_ms),
+                average(|t| t.mining_ms),
+                average(|t| t.tx_ms)
+            );
+        }
+
+        Ok(())
+    }
+}
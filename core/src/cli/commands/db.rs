@@ -0,0 +1,325 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::rpc::types::BlockTransactionsKind;
+use clap::Subcommand;
+use eyre::{eyre, Result};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::parse_block_number_or_tag;
+use crate::{DriaOracle, IndexFilter, IndexedTask, RetentionPolicy};
+
+/// Number of tasks to read from the chain & write to the index at once while verifying or backfilling the index.
+const TASK_BATCH_SIZE: usize = 100;
+
+/// Number of recent blocks to estimate the block time of the chain with, when pruning by age.
+const BLOCK_TIME_SAMPLE: u64 = 1_000;
+
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// Re-scan the chain between two blocks, and insert the missed tasks & fix the diverged ones within the index.
+    Backfill {
+        #[arg(long, help = "Starting block number.", required = true, value_parser = parse_block_number_or_tag)]
+        from: BlockNumberOrTag,
+        #[arg(long, help = "Ending block number, defaults to 'latest'.", value_parser = parse_block_number_or_tag)]
+        to: Option<BlockNumberOrTag>,
+    },
+    /// Compare the indexed tasks against the chain, and report the ones that have diverged.
+    Verify,
+    /// Prune the rows older than an age and the oldest rows of the tables larger than a size, and compact the tables.
+    Prune {
+        #[arg(
+            long,
+            env = "DB_MAX_AGE_DAYS",
+            help = "Prune the rows written, or the tasks & events emitted, more than this many days ago."
+        )]
+        max_age_days: Option<u64>,
+        #[arg(
+            long,
+            env = "DB_MAX_TABLE_SIZE_MB",
+            help = "Prune the oldest rows of each table until it is at most this many megabytes."
+        )]
+        max_table_size_mb: Option<u64>,
+    },
+}
+
+impl RetentionPolicy {
+    /// Creates a retention policy from the `db prune` & `serve` arguments.
+    pub(in crate::cli) fn from_args(
+        max_age_days: Option<u64>,
+        max_table_size_mb: Option<u64>,
+    ) -> Self {
+        Self {
+            max_age: max_age_days.map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            max_table_size: max_table_size_mb.map(|mb| mb * 1024 * 1024),
+        }
+    }
+}
+
+impl DriaOracle {
+    /// Reconciles the index with the tasks that had events between two blocks, where the missing
+    /// tasks are inserted and the ones whose on-chain fields differ are replaced.
+    ///
+    /// Unlike the `index` command, the checkpoint is not moved and all tasks are kept regardless of filters.
+    pub(in crate::cli) async fn backfill_index(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+    ) -> Result<()> {
+        let from_block = self.resolve_block_number(from_block).await?;
+        let to_block = self.resolve_block_number(to_block).await?;
+        log::info!(
+            "Backfilling the index between blocks: {} - {}",
+            from_block,
+            to_block
+        );
+
+        let coordinator = *self.coordinator.address();
+        // the range is scanned once, for both the status updates & the other events
+        let logs = self
+            .get_contract_logs_in_range(from_block, to_block)
+            .await?;
+        let task_blocks = self
+            .decode_status_updates(&logs)
+            .into_iter()
+            .map(|(event, log)| (event.taskId, log.block_number.unwrap_or(to_block)))
+            .collect::<BTreeMap<U256, u64>>();
+        let indexed = self
+            .db
+            .get_indexed_tasks(coordinator, &IndexFilter::default())?
+            .into_iter()
+            .map(|task| (task.task_id, task))
+            .collect::<BTreeMap<_, _>>();
+
+        let task_ids = task_blocks.keys().copied().collect::<Vec<_>>();
+        let (mut inserted, mut fixed) = (0, 0);
+        // each batch is written on its own, so that a failure midway keeps the progress so far
+        for batch in task_ids.chunks(TASK_BATCH_SIZE) {
+            let mut tasks = Vec::new();
+            for mut task in self.get_tasks(batch).await? {
+                let block_number = task_blocks[&task.task_id];
+                let on_chain = IndexedTask::from_view(coordinator, block_number, &task);
+                let block_number = match indexed.get(&task.task_id) {
+                    None => {
+                        log::info!("Inserting missed task {}", task.task_id);
+                        inserted += 1;
+                        block_number
+                    }
+                    Some(existing) => {
+                        let divergence = existing.divergence(&on_chain);
+                        if divergence.is_empty() {
+                            continue;
+                        }
+                        log::info!(
+                            "Fixing task {} with diverged {}",
+                            task.task_id,
+                            divergence.join(", ")
+                        );
+                        fixed += 1;
+                        block_number.max(existing.block_number)
+                    }
+                };
+
+                // contents are only downloaded for the tasks that are written
+                task.resolve_storage().await;
+                tasks.push(IndexedTask::from_view(coordinator, block_number, &task));
+            }
+            self.db.upsert_indexed_tasks(tasks)?;
+        }
+
+        let num_events = self
+            .db
+            .upsert_indexed_events(self.decode_contract_events(&logs))?;
+
+        log::info!(
+            "Backfilled {} tasks: {} inserted, {} fixed, {} already up to date. Reconciled {} other events.",
+            task_ids.len(),
+            inserted,
+            fixed,
+            task_ids.len() - inserted - fixed,
+            num_events
+        );
+
+        Ok(())
+    }
+
+    /// Compares each indexed task against its current state on the chain, and reports the ones
+    /// that have diverged along with the block to backfill from.
+    ///
+    /// The indexed tasks are compared against the next task id as well, so that the tasks that do not
+    /// exist on-chain are reported, and so are the ones that are missing between the indexed tasks.
+    pub(in crate::cli) async fn verify_index(&self) -> Result<()> {
+        let coordinator = *self.coordinator.address();
+        let indexed = self
+            .db
+            .get_indexed_tasks(coordinator, &IndexFilter::default())?;
+        if indexed.is_empty() {
+            log::warn!("There are no indexed tasks, index them with the `index` command.");
+            return Ok(());
+        }
+        log::info!("Verifying {} indexed tasks.", indexed.len());
+
+        // the tasks at or after the next task id are not created on-chain, so they are not read
+        let next_task_id = self.coordinator.nextTaskId().call().await?._0;
+        let (indexed, phantom) = indexed
+            .into_iter()
+            .partition::<Vec<_>, _>(|task| task.task_id < next_task_id);
+        let mut diverged = Vec::new();
+        for task in &phantom {
+            log::warn!(
+                "Task {} (block {}) has diverged: existence",
+                task.task_id,
+                task.block_number
+            );
+            diverged.push(task.block_number);
+        }
+
+        // the tasks between the indexed ones must be indexed as well, the missing ones are backfilled
+        // from the block of the task indexed right before them
+        let mut previous: Option<&IndexedTask> = None;
+        for task in &indexed {
+            if let Some(previous) = previous {
+                let missing = task.task_id - previous.task_id - U256::from(1);
+                if !missing.is_zero() {
+                    log::warn!(
+                        "{} tasks between {} and {} are missing from the index.",
+                        missing,
+                        previous.task_id,
+                        task.task_id
+                    );
+                    diverged.push(previous.block_number);
+                }
+            }
+            previous = Some(task);
+        }
+        if let Some(latest) = indexed.last() {
+            let newer = next_task_id - latest.task_id - U256::from(1);
+            if !newer.is_zero() {
+                log::info!(
+                    "{} tasks are requested after task {}, run `index` to catch up.",
+                    newer,
+                    latest.task_id
+                );
+            }
+        }
+
+        for batch in indexed.chunks(TASK_BATCH_SIZE) {
+            let task_ids = batch.iter().map(|task| task.task_id).collect::<Vec<_>>();
+            let on_chain = self
+                .get_tasks(&task_ids)
+                .await?
+                .into_iter()
+                .map(|task| (task.task_id, task))
+                .collect::<BTreeMap<_, _>>();
+
+            for task in batch {
+                let divergence = match on_chain.get(&task.task_id) {
+                    Some(view) => task.divergence(&IndexedTask::from_view(
+                        coordinator,
+                        task.block_number,
+                        view,
+                    )),
+                    None => vec!["existence"],
+                };
+                if !divergence.is_empty() {
+                    log::warn!(
+                        "Task {} (block {}) has diverged: {}",
+                        task.task_id,
+                        task.block_number,
+                        divergence.join(", ")
+                    );
+                    diverged.push(task.block_number);
+                }
+            }
+        }
+
+        // the checkpoint can not be ahead of the chain, e.g. after switching the RPC to another chain
+        let latest_block = self.resolve_block_number(BlockNumberOrTag::Latest).await?;
+        if let Some(checkpoint) = self.db.get_index_checkpoint(coordinator)? {
+            if checkpoint > latest_block {
+                log::warn!(
+                    "Index checkpoint {} is ahead of the latest block {}.",
+                    checkpoint,
+                    latest_block
+                );
+            }
+        }
+
+        let total = indexed.len() + phantom.len();
+        match diverged.iter().min() {
+            Some(from_block) => log::warn!(
+                "{} of {} indexed tasks have diverged or are missing, repair them with `db backfill --from={}`.",
+                diverged.len(),
+                total,
+                from_block
+            ),
+            None => log::info!("All {} indexed tasks match the chain.", total),
+        }
+
+        Ok(())
+    }
+
+    /// Prunes & compacts the local database with the given policy, where the indexed tasks & events
+    /// of our coordinator are pruned by the block that is estimated to be mined at the age limit.
+    pub(in crate::cli) async fn prune_db(&self, policy: &RetentionPolicy) -> Result<()> {
+        let oldest_block = match policy.max_age {
+            Some(max_age) => Some((
+                *self.coordinator.address(),
+                self.block_before(max_age).await?,
+            )),
+            None => None,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        // pruning reads & rewrites the tables, so it is kept off of the async workers
+        let (db, policy) = (self.db.clone(), *policy);
+        let pruned =
+            tokio::task::spawn_blocking(move || db.prune(&policy, now, oldest_block)).await??;
+        let removed = pruned.iter().map(|table| table.removed).sum::<usize>();
+        if removed == 0 {
+            log::debug!("Nothing to prune in the database.");
+            return Ok(());
+        }
+
+        for table in pruned.iter().filter(|table| table.removed > 0) {
+            log::info!(
+                "Pruned {} rows from {}, {} bytes left.",
+                table.removed,
+                table.table,
+                table.size
+            );
+        }
+        log::info!(
+            "Pruned {} rows in total, the database is {} bytes.",
+            removed,
+            pruned.iter().map(|table| table.size).sum::<u64>()
+        );
+
+        Ok(())
+    }
+
+    /// Returns the block that is estimated to be mined `age` ago, using the block time of the recent blocks.
+    async fn block_before(&self, age: Duration) -> Result<u64> {
+        let timestamp_of = |block_number: u64| async move {
+            self.provider
+                .get_block_by_number(block_number.into(), BlockTransactionsKind::Hashes)
+                .await?
+                .map(|block| block.header.timestamp)
+                .ok_or_else(|| eyre!("block {} not found", block_number))
+        };
+
+        let latest_block = self.resolve_block_number(BlockNumberOrTag::Latest).await?;
+        let sample_block = latest_block.saturating_sub(BLOCK_TIME_SAMPLE);
+        if sample_block == latest_block {
+            return Ok(0);
+        }
+        let elapsed = timestamp_of(latest_block)
+            .await?
+            .saturating_sub(timestamp_of(sample_block).await?);
+        // the number of blocks within the age, which allows sub-second block times as well
+        let blocks = age.as_secs().saturating_mul(latest_block - sample_block) / elapsed.max(1);
+
+        Ok(latest_block.saturating_sub(blocks))
+    }
+}
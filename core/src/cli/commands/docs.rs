@@ -0,0 +1,35 @@
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::Cli;
+
+/// Name of the binary, used within the completion scripts.
+const BIN_NAME: &str = "dria-oracle";
+
+/// Prints the completion script of the CLI for the given shell to standard output.
+pub(in crate::cli) fn print_completions(shell: Shell) {
+    clap_complete::generate(shell, &mut Cli::command(), BIN_NAME, &mut std::io::stdout());
+}
+
+/// Prints the manpage of the CLI to standard output, in roff format.
+pub(in crate::cli) fn print_manpage() -> eyre::Result<()> {
+    clap_mangen::Man::new(Cli::command().name(BIN_NAME)).render(&mut std::io::stdout())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        // catches conflicting flags & names before they reach the generated scripts
+        Cli::command().debug_assert();
+
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), BIN_NAME, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("serve"));
+    }
+}
@@ -0,0 +1,69 @@
+use bytes::Bytes;
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use http_body_util::Full;
+use hyper::{body::Incoming, server::conn::http1, service::service_fn, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::future::Future;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+/// Accepts the connections of the given listener & serves each of them with the given handler,
+/// until the cancellation is requested.
+///
+/// The connections are polled within this future instead of being spawned, so that the handler
+/// can borrow the node & the state of the caller.
+pub(in crate::cli) async fn serve_connections<H, F>(
+    listener: TcpListener,
+    cancellation: CancellationToken,
+    handler: H,
+) where
+    H: Fn(hyper::Request<Incoming>) -> F,
+    F: Future<Output = hyper::Response<Full<Bytes>>>,
+{
+    let handler = &handler;
+    let mut connections = FuturesUnordered::new();
+    loop {
+        tokio::select! {
+            _ = cancellation.cancelled() => {
+                log::debug!("Cancellation signal received. Stopping...");
+                return;
+            }
+            Some(()) = connections.next(), if !connections.is_empty() => {}
+            accepted = listener.accept() => {
+                let (stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::error!("Could not accept connection: {}", e);
+                        continue;
+                    }
+                };
+                log::debug!("Accepted connection from {}", peer);
+
+                let service = service_fn(move |req| async move {
+                    Ok::<_, std::convert::Infallible>(handler(req).await)
+                });
+                connections.push(async move {
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        log::error!("Could not serve connection from {}: {}", peer, e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Creates a JSON response with the given status.
+pub(in crate::cli) fn json_response(
+    status: StatusCode,
+    body: &impl serde::Serialize,
+) -> hyper::Response<Full<Bytes>> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    hyper::Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .expect("response should be valid")
+}
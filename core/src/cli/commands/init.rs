@@ -0,0 +1,229 @@
+use alloy::hex;
+use alloy::primitives::{utils::format_ether, Address, B256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::signers::local::PrivateKeySigner;
+use dkn_workflows::{DriaWorkflowsConfig, Model};
+use dria_oracle_contracts::get_deployment_by_id;
+use eyre::{eyre, Context, Result};
+use reqwest::Url;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::cli::parsers::{parse_model, parse_secret_key, parse_url};
+use crate::node::prompt;
+
+/// Template of the written environment file, which is the example within the repository.
+const ENV_TEMPLATE: &str = include_str!("../../../../.env.example");
+
+/// Writes a starter environment file to the given path, and prints what is missing to run a node.
+///
+/// Values that are not given are prompted for if `interactive` is set and the input is a terminal,
+/// and the empty variables of the template are filled from the process environment (e.g. API keys
+/// passed to a container) so that the written file works as is.
+pub(in crate::cli) async fn init_env(
+    path: &Path,
+    secret_key: Option<B256>,
+    mut rpc_url: Option<Url>,
+    mut models: Vec<Model>,
+    force: bool,
+    interactive: bool,
+) -> Result<()> {
+    if path.exists() && !force {
+        return Err(eyre!(
+            "{} already exists, use --force to overwrite it.",
+            path.display()
+        ));
+    }
+    let interactive = interactive && std::io::stdin().is_terminal();
+
+    // import the given wallet, or create a new one
+    let signer = match secret_key {
+        Some(secret_key) => PrivateKeySigner::from_bytes(&secret_key)?,
+        None => match interactive {
+            true => match prompt("Secret key to import (leave empty to create a new wallet)")? {
+                answer if answer.is_empty() => PrivateKeySigner::random(),
+                answer => PrivateKeySigner::from_bytes(&parse_secret_key(&answer)?)?,
+            },
+            false => PrivateKeySigner::random(),
+        },
+    };
+    let address = signer.address();
+    if secret_key.is_none() {
+        log::info!("Using wallet {}", address);
+    }
+
+    if rpc_url.is_none() && interactive {
+        let answer = prompt("RPC URL (leave empty to set it later)")?;
+        if !answer.is_empty() {
+            rpc_url = Some(parse_url(&answer)?);
+        }
+    }
+    if models.is_empty() && interactive {
+        let answer = prompt("Models to serve, comma-separated (leave empty to skip the check)")?;
+        models = answer
+            .split(',')
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(parse_model)
+            .collect::<Result<_>>()?;
+    }
+
+    // write the environment file, and load it for the checks below
+    let secret_key_hex = hex::encode(signer.to_bytes());
+    let rpc_url_str = rpc_url.as_ref().map(Url::to_string).unwrap_or_default();
+    let env = fill_env_template(
+        ENV_TEMPLATE,
+        &[("SECRET_KEY", &secret_key_hex), ("RPC_URL", &rpc_url_str)],
+        |key| std::env::var(key).ok(),
+    );
+    std::fs::write(path, env).wrap_err_with(|| format!("could not write {}", path.display()))?;
+    log::info!("Wrote {}", path.display());
+    dotenvy::from_path_override(path)?;
+
+    let mut missing = Vec::new();
+    match rpc_url {
+        Some(rpc_url) => check_chain(rpc_url, address, &mut missing).await,
+        None => {
+            missing.push("RPC_URL is not set, add it to the .env file or use --rpc-url.".into())
+        }
+    }
+    if models.is_empty() {
+        log::info!("No models given, skipping the model checks. Use --model to check them.");
+    } else if let Err(e) = DriaWorkflowsConfig::new(models.clone())
+        .check_services()
+        .await
+    {
+        missing.push(format!(
+            "Models are not available: {}. Check the API keys or the Ollama setup in the .env file.",
+            e
+        ));
+    }
+
+    match super::configured_storage() {
+        Ok(Some(arweave)) => match arweave.check_health().await {
+            Ok(()) => match arweave.get_balance().await {
+                Ok(0) => missing.push(
+                    "Arweave wallet has no balance at the bundler. Fund it with `dria-oracle storage fund`."
+                        .to_string(),
+                ),
+                Ok(_) => log::info!("Arweave storage is ready"),
+                Err(e) => missing.push(format!("Could not read the Arweave balance: {:#}", e)),
+            },
+            Err(e) => missing.push(format!(
+                "Arweave storage is not ready: {:#}. Check ARWEAVE_WALLET_PATH and ARWEAVE_BASE_URL.",
+                e
+            )),
+        },
+        Ok(None) => log::info!("Arweave wallet is not set, skipping the storage checks."),
+        Err(e) => missing.push(format!("Arweave storage is misconfigured: {:#}", e)),
+    }
+
+    if !missing.is_empty() {
+        let missing = missing
+            .iter()
+            .map(|m| format!("  - {}", m))
+            .collect::<Vec<_>>()
+            .join("\n");
+        log::warn!("Missing to run a node:\n{}", missing);
+        return Err(eyre!("Setup is incomplete."));
+    }
+
+    let models = models
+        .iter()
+        .map(|m| format!(" -m {}", m))
+        .collect::<String>();
+    log::info!(
+        "Setup is complete! Register with `dria-oracle register generator` and start with `dria-oracle serve{}`.",
+        models
+    );
+
+    Ok(())
+}
+
+/// Checks the connection to the RPC, whether the chain is supported, and the balance for gas fees.
+async fn check_chain(rpc_url: Url, address: Address, missing: &mut Vec<String>) {
+    let provider = ProviderBuilder::new().on_http(rpc_url.clone());
+    let chain_id = match provider.get_chain_id().await {
+        Ok(chain_id) => chain_id,
+        Err(e) => {
+            missing.push(format!("Could not connect to RPC at {}: {}", rpc_url, e));
+            return;
+        }
+    };
+
+    match get_deployment_by_id(chain_id) {
+        Some(deployment) => log::info!("Connected to {}", deployment.chain),
+        None if std::env::var("COORDINATOR_ADDRESS").is_ok_and(|a| !a.is_empty()) => {
+            log::info!("Connected to chain {} with a custom coordinator", chain_id)
+        }
+        None => missing.push(format!(
+            "Chain {} has no known deployment, use a supported chain (see `dria-oracle chains`) or set COORDINATOR_ADDRESS.",
+            chain_id
+        )),
+    }
+
+    match provider.get_balance(address).await {
+        Ok(balance) if balance.is_zero() => missing.push(format!(
+            "{} has no ETH to pay for the gas fees, fund it first.",
+            address
+        )),
+        Ok(balance) => log::info!("Balance: {} ETH", format_ether(balance)),
+        Err(e) => missing.push(format!("Could not read the balance of {}: {}", address, e)),
+    }
+}
+
+/// Fills the variables of an environment file template.
+///
+/// Given values replace the values in the template, and empty variables are filled with `fallback`.
+fn fill_env_template(
+    template: &str,
+    values: &[(&str, &str)],
+    fallback: impl Fn(&str) -> Option<String>,
+) -> String {
+    template
+        .lines()
+        .map(|line| {
+            let Some((key, value)) = line.split_once('=').filter(|_| !line.starts_with('#')) else {
+                return line.to_string();
+            };
+
+            match values.iter().find(|(k, _)| *k == key) {
+                Some((_, given)) => format!("{}={}", key, given),
+                None if value.is_empty() => match fallback(key).filter(|v| !v.is_empty()) {
+                    Some(fallback) => format!("{}={}", key, fallback),
+                    None => line.to_string(),
+                },
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_env_template() {
+        let template =
+            "# comment=ignored\nRPC_URL=your-rpc-url\nSECRET_KEY=\nOPENAI_API_KEY=\nJINA_API_KEY=";
+        let env = fill_env_template(
+            template,
+            &[("RPC_URL", "http://localhost:8545"), ("SECRET_KEY", "ab")],
+            |key| (key == "OPENAI_API_KEY").then(|| "sk-test".to_string()),
+        );
+
+        assert_eq!(
+            env,
+            "# comment=ignored\nRPC_URL=http://localhost:8545\nSECRET_KEY=ab\nOPENAI_API_KEY=sk-test\nJINA_API_KEY=\n"
+        );
+    }
+
+    #[test]
+    fn test_env_template_has_required_keys() {
+        assert!(ENV_TEMPLATE.lines().any(|l| l.starts_with("SECRET_KEY=")));
+        assert!(ENV_TEMPLATE.lines().any(|l| l.starts_with("RPC_URL=")));
+    }
+}
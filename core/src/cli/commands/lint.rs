@@ -0,0 +1,15 @@
+use eyre::{Context, Result};
+use std::path::Path;
+
+use crate::check_workflow;
+
+/// Lints the workflow within the given JSON file, and returns an error if it has any errors.
+pub(in crate::cli) fn lint_workflow_file(path: &Path) -> Result<()> {
+    let json = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("could not read {}", path.display()))?;
+
+    let warnings = check_workflow(&json).wrap_err_with(|| path.display().to_string())?;
+    log::info!("{} has no errors, {} warnings", path.display(), warnings);
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use eyre::Result;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{benchmark_hash_rate, estimate_mining_time, OracleDb};
+
+/// Minimum total mining time of the recorded nonces for their hash rate to be used,
+/// as the time spent on a small difficulty is too short to be measured precisely.
+const MIN_RECORDED_MILLIS: u64 = 1000;
+
+/// Number of difficulties to display on each side of the given one.
+const DIFFICULTY_SPAN: u8 = 2;
+
+/// Estimates the time it takes to mine a nonce with the given difficulty on this machine.
+///
+/// The hash rate is measured with a benchmark, and from the recorded mined nonces within the
+/// local database if there are enough of them, in which case they take precedence as they
+/// reflect the performance while serving.
+pub(in crate::cli) fn estimate_difficulty(
+    difficulty: u8,
+    benchmark_secs: u64,
+    data_dir: PathBuf,
+) -> Result<()> {
+    log::info!("Benchmarking the hash rate for {}s", benchmark_secs);
+    let benchmark_rate = benchmark_hash_rate(Duration::from_secs(benchmark_secs));
+    log::info!("Benchmarked hash rate: {:.0} hashes/s", benchmark_rate);
+
+    // group recorded nonces by difficulty, as (count, hashes, elapsed ms)
+    let records = OracleDb::new(data_dir).get_mining_records()?;
+    let mut recorded = BTreeMap::<u8, (u64, u64, u64)>::new();
+    for record in &records {
+        let entry = recorded.entry(record.difficulty).or_default();
+        entry.0 += 1;
+        entry.1 += record.hashes;
+        entry.2 += record.elapsed_ms;
+    }
+    let (hashes, elapsed_ms) = recorded
+        .values()
+        .fold((0, 0), |(h, e), (_, hashes, elapsed_ms)| {
+            (h + hashes, e + elapsed_ms)
+        });
+    let hash_rate = if elapsed_ms >= MIN_RECORDED_MILLIS {
+        let recorded_rate = hashes as f64 * 1000.0 / elapsed_ms as f64;
+        log::info!(
+            "Recorded hash rate: {:.0} hashes/s (over {} nonces)",
+            recorded_rate,
+            records.len()
+        );
+        recorded_rate
+    } else {
+        log::info!("Recorded hash rate: - (not enough mined nonces yet)");
+        benchmark_rate
+    };
+
+    let from = difficulty.saturating_sub(DIFFICULTY_SPAN);
+    let to = difficulty.saturating_add(DIFFICULTY_SPAN);
+    for d in from..=to {
+        let (expected, p95) = estimate_mining_time(d, hash_rate);
+        let observed = match recorded.get(&d) {
+            Some((count, _, elapsed_ms)) => format!(
+                ", observed {:.2?} (over {})",
+                Duration::from_millis(elapsed_ms / count),
+                count
+            ),
+            None => String::new(),
+        };
+        log::info!(
+            "{} Difficulty {:>3}: expected {:.2?}, 95% within {:.2?}{}",
+            if d == difficulty { ">" } else { " " },
+            d,
+            expected,
+            p95,
+            observed
+        );
+    }
+
+    Ok(())
+}
@@ -1,12 +1,67 @@
-use alloy::{eips::BlockNumberOrTag, primitives::U256};
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{Address, B256, U256},
+};
 use clap::Subcommand;
 use dkn_workflows::Model;
-use dria_oracle_contracts::OracleKind;
+use dria_oracle_contracts::{OracleKind, TaskStatus};
+use reqwest::Url;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{
+    DriaOracle, GuardrailAction, QueueOrder, RetryPolicy, ScoreMap, ServeWindow, Shard, TaskLock,
+};
 
 use super::parsers::*;
 
+mod chains;
+pub(in crate::cli) use chains::display_chains;
+
+mod init;
+pub(in crate::cli) use init::init_env;
+
+mod docs;
+pub(in crate::cli) use docs::{print_completions, print_manpage};
+
 mod coordinator;
+pub(in crate::cli) use coordinator::GatewayOptions;
+
+mod storage;
+pub use storage::StorageCommands;
+pub(in crate::cli) use storage::{
+    configured_storage, handle_storage_command, monitor_storage_balance,
+};
+
+mod mining;
+pub(in crate::cli) use mining::estimate_difficulty;
+
+mod benchmark;
+pub(in crate::cli) use benchmark::benchmark_models;
+
+mod lint;
+pub(in crate::cli) use lint::lint_workflow_file;
+
+mod presets;
+pub(in crate::cli) use presets::handle_presets_command;
+pub use presets::PresetCommands;
+
+mod replay;
+pub(in crate::cli) use replay::replay_wal;
+
+mod whitelist;
+pub use whitelist::WhitelistCommands;
+
+mod db;
+pub use db::DbCommands;
+
+mod costs;
+mod http;
+mod peers;
 mod registry;
+mod signer;
+mod stats;
 mod token;
 
 // https://docs.rs/clap/latest/clap/_derive/index.html#arg-attributes
@@ -24,18 +79,72 @@ pub enum Commands {
     },
     /// See all registrations.
     Registrations,
+    /// See staked amounts for each registration.
+    Stakes,
+    /// See or wait for the whitelist status, which is required to serve as a validator.
+    Whitelist {
+        #[command(subcommand)]
+        command: WhitelistCommands,
+    },
     /// See the current balance of the oracle node.
     Balance,
+    /// See the supported chains along with their known deployments.
+    Chains,
     /// See claimable rewards from the coordinator.
     Rewards,
     /// Claim rewards from the coordinator.
     Claim,
+    /// Wrap ETH to the fee token, if it is WETH.
+    Wrap {
+        #[arg(help = "The amount of ETH to wrap, e.g. 0.1")]
+        amount: String,
+    },
+    /// Transfer fee tokens to another address.
+    Transfer {
+        #[arg(help = "The address to transfer to.", value_parser = parse_address)]
+        to: Address,
+        #[arg(help = "The amount of tokens to transfer, e.g. 0.1")]
+        amount: String,
+    },
+    /// Unwrap the fee token to ETH, if it is WETH.
+    Unwrap {
+        #[arg(help = "The amount of WETH to unwrap, e.g. 0.1")]
+        amount: String,
+    },
+    /// See past reward claims from the coordinator.
+    Claims {
+        #[arg(long, help = "Starting block number, defaults to 'earliest'.", value_parser = parse_block_number_or_tag)]
+        from: Option<BlockNumberOrTag>,
+        #[arg(long, help = "Ending block number, defaults to 'latest'.", value_parser = parse_block_number_or_tag)]
+        to: Option<BlockNumberOrTag>,
+    },
     /// Serve the oracle node.
     Serve {
         #[arg(help = "The oracle kinds to handle tasks as, if omitted will default to all registered kinds.", value_parser = parse_oracle_kind)]
         kinds: Vec<OracleKind>,
-        #[arg(short, long = "model", help = "The model(s) to serve.", required = true, value_parser = parse_model)]
+        #[arg(short, long = "model", help = "The model(s) to serve, required for generators; validators only use gpt-4o.", value_parser = parse_model)]
         models: Vec<Model>,
+        #[arg(
+            long,
+            help = "Warm up each model the first time that a task requires it, instead of checking all models before serving."
+        )]
+        lazy_models: bool,
+        #[arg(
+            long,
+            help = "Seconds to wait for a model to warm up with --lazy-models, including pulling a local model.",
+            requires = "lazy_models"
+        )]
+        warmup_timeout: Option<u64>,
+        #[arg(
+            long,
+            help = "Pull the local models that are missing from Ollama when a task requires them, if the disk has enough space."
+        )]
+        ollama_auto_pull: bool,
+        #[arg(
+            long,
+            help = "Skip the local models that would not fit into the free VRAM of the NVIDIA GPUs, falling back to the other allowed models."
+        )]
+        vram_aware: bool,
         #[arg(
             long,
             help = "Block number to starting listening from, omit to start from latest block.",
@@ -55,6 +164,334 @@ pub enum Commands {
             required = false
         )]
         task_id: Option<U256>,
+        #[arg(
+            long,
+            env = "MIN_PROFIT",
+            help = "Skip tasks with an expected profit (in ETH) below this amount, omit to accept all tasks."
+        )]
+        min_profit: Option<f64>,
+        #[arg(
+            long,
+            env = "ETH_PRICE",
+            help = "Price of ETH in USD, used to account for model costs with --min-profit or --max-validation-cost-ratio, read from PRICE_FEED if omitted."
+        )]
+        eth_price: Option<f64>,
+        #[arg(
+            long,
+            env = "MAX_VALIDATION_COST_RATIO",
+            help = "Skip validations whose expected gas & model costs, which grow with the number of generations, are above this ratio of the validator fee."
+        )]
+        max_validation_cost_ratio: Option<f64>,
+        #[arg(
+            long,
+            help = "Wait a random duration up to this many milliseconds before handling a generation task."
+        )]
+        max_start_delay: Option<u64>,
+        #[arg(
+            long,
+            help = "Skip tasks that can not be finished within this many seconds since they entered their current status."
+        )]
+        task_deadline: Option<u64>,
+        #[arg(
+            long,
+            help = "Abort the execution of a task after this many seconds, plus a few seconds per difficulty level."
+        )]
+        task_budget: Option<u64>,
+        #[arg(
+            long = "protocol-budget",
+            help = "Execution budget of a protocol in seconds as <protocol>=<seconds>, overrides --task-budget for its tasks.",
+            value_parser = parse_protocol_budget
+        )]
+        protocol_budgets: Vec<(String, Duration)>,
+        #[arg(
+            long,
+            help = "Maximum number of attempts to execute a workflow, across the models that a task allows.",
+            default_value_t = RetryPolicy::default().max_attempts
+        )]
+        max_attempts: usize,
+        #[arg(
+            long,
+            help = "Number of failed attempts with a model before falling back to the next model that a task allows.",
+            default_value_t = RetryPolicy::default().attempts_per_model
+        )]
+        attempts_per_model: usize,
+        #[arg(
+            long,
+            help = "Delay before the first retry in milliseconds, doubled for each retry after that.",
+            default_value_t = RetryPolicy::default().initial_backoff.as_millis() as u64
+        )]
+        retry_backoff: u64,
+        #[arg(
+            long,
+            help = "Map of the validation scores to the scores written to the contract, as <score>:<value> pairs, e.g. 1:51,2:102,3:153,4:204,5:255.",
+            value_parser = parse_score_map
+        )]
+        score_map: Option<ScoreMap>,
+        #[arg(
+            long = "protocol-score-map",
+            help = "Map of the validation scores of a protocol as <protocol>=<map>, overrides --score-map for its tasks.",
+            value_parser = parse_protocol_score_map
+        )]
+        protocol_score_maps: Vec<(String, ScoreMap)>,
+        #[arg(
+            long = "workflow-preset",
+            help = "Workflow file to use instead of the default generation workflow for a protocol prefix, as <protocol-prefix>=<path>.",
+            value_parser = parse_workflow_preset
+        )]
+        workflow_presets: Vec<(String, PathBuf)>,
+        #[arg(
+            long,
+            help = "JSON fixture file of canned outputs keyed by input patterns, returned instead of executing the generations; for tests only."
+        )]
+        mock_model: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Screen the inputs for prompt injections and the outputs for blocked patterns, and either flag them within the metadata or refuse with a structured refusal: flag, refuse.",
+            value_parser = parse_guardrail_action
+        )]
+        guardrails: Option<GuardrailAction>,
+        #[arg(
+            long = "block-input",
+            help = "Additional pattern to screen within the inputs, case-insensitive.",
+            requires = "guardrails"
+        )]
+        blocked_inputs: Vec<String>,
+        #[arg(
+            long = "block-output",
+            help = "Pattern to screen within the outputs, case-insensitive.",
+            requires = "guardrails"
+        )]
+        blocked_outputs: Vec<String>,
+        #[arg(
+            long,
+            help = "Score each generation with a validation pass before responding, and regenerate once if it scores below this (within 1-5).",
+            value_parser = clap::value_parser!(u8).range(1..=5)
+        )]
+        self_check: Option<u8>,
+        #[arg(
+            long,
+            help = "Model to score the generations with for --self-check.",
+            requires = "self_check",
+            value_parser = parse_model
+        )]
+        self_check_model: Option<Model>,
+        #[arg(
+            long = "allow-tool",
+            help = "Tool(s) that the workflows of the requesters can use, where `ALL` is narrowed down to these. Omit to allow all tools."
+        )]
+        allowed_tools: Vec<String>,
+        #[arg(
+            long = "deny-tool",
+            help = "Tool(s) that the workflows of the requesters can not use."
+        )]
+        denied_tools: Vec<String>,
+        #[arg(
+            long = "allow-tool-domain",
+            help = "Domain(s) that the custom tools of the requesters can target, along with their subdomains. Omit to allow any public domain."
+        )]
+        allowed_tool_domains: Vec<String>,
+        #[arg(
+            long,
+            help = "Maximum number of steps of the workflows that use tools, which bounds the number of tool calls."
+        )]
+        max_tool_steps: Option<u64>,
+        #[arg(long, help = "Skip tasks with an input larger than this many bytes.")]
+        max_input_bytes: Option<usize>,
+        #[arg(long, help = "Skip tasks with a workflow of more steps than this.")]
+        max_workflow_steps: Option<u64>,
+        #[arg(
+            long,
+            help = "Skip tasks with a workflow that can run longer than this many seconds."
+        )]
+        max_workflow_time: Option<u64>,
+        #[arg(
+            long,
+            help = "Skip tasks with a workflow whose external memory is larger than this many bytes."
+        )]
+        max_memory_bytes: Option<usize>,
+        #[arg(
+            long,
+            help = "Upload the response to the storage regardless of its size when its estimated gas (calldata & storage) exceeds this ceiling, to avoid a revert."
+        )]
+        response_gas_ceiling: Option<u64>,
+        #[arg(
+            long,
+            env = "OPERATOR_TAG",
+            help = "Short name or URL of the operator, reported within the node status so that the node can be attributed to it.",
+            value_parser = parse_operator_tag
+        )]
+        operator_tag: Option<String>,
+        #[arg(
+            long,
+            help = "Summarize the older turns of a chat history above this many estimated tokens, before it is used & uploaded again."
+        )]
+        max_history_tokens: Option<u64>,
+        #[arg(
+            long,
+            help = "Upload only the new messages of a chat as a versioned delta of its history, instead of the whole history. Older nodes & clients can not read the deltas."
+        )]
+        incremental_history: bool,
+        #[arg(
+            long,
+            help = "Maximum estimated spending (in USD) on paid providers within an hour, tasks that require them are paused once reached."
+        )]
+        max_hourly_spend: Option<f64>,
+        #[arg(
+            long,
+            help = "Maximum estimated spending (in USD) on paid providers within a day, tasks that require them are paused once reached."
+        )]
+        max_daily_spend: Option<f64>,
+        #[arg(
+            long,
+            help = "Defer new tasks while the base fee (in gwei) is above this ceiling, overrides MAX_GAS_PRICE.",
+            value_parser = parse_gas_price
+        )]
+        max_gas_price: Option<u128>,
+        #[arg(
+            long,
+            help = "Maximum gas fees (in ETH) within a day, new tasks are deferred once reached, overrides MAX_DAILY_GAS_SPEND.",
+            value_parser = parse_gas_spend
+        )]
+        max_daily_gas_spend: Option<U256>,
+        #[arg(
+            long,
+            help = "Minimum Arweave balance (in AR) at the bundler, an error is logged periodically when it is below.",
+            value_parser = dria_oracle_storage::parse_winston
+        )]
+        min_storage_balance: Option<u128>,
+        #[arg(
+            long = "coordinator",
+            help = "Additional coordinator address(es) to serve along with the default one.",
+            value_parser = parse_address
+        )]
+        coordinators: Vec<Address>,
+        #[arg(
+            long,
+            help = "Process previous tasks (see --from) with a parallel pipeline of this many workers, instead of one by one."
+        )]
+        catch_up_workers: Option<usize>,
+        #[arg(
+            long = "chain-env",
+            help = "Env file(s) of additional chains to serve, each with its own RPC_URL, SECRET_KEY and optional COORDINATOR_ADDRESS."
+        )]
+        chain_envs: Vec<PathBuf>,
+        #[arg(
+            long,
+            env = "GENERATOR_SECRET_KEY",
+            hide_env_values = true,
+            help = "Secret key of a separate account to handle the generation tasks with, instead of SECRET_KEY.",
+            value_parser = parse_secret_key
+        )]
+        generator_key: Option<B256>,
+        #[arg(
+            long,
+            env = "VALIDATOR_SECRET_KEY",
+            hide_env_values = true,
+            help = "Secret key of a separate account to handle the validation tasks with, instead of SECRET_KEY.",
+            value_parser = parse_secret_key
+        )]
+        validator_key: Option<B256>,
+        #[arg(
+            long,
+            env = "REMOTE_SIGNER_URL",
+            help = "URL of a signer (see the `signer` command) to submit the responses with, so that SECRET_KEY needs no funds.",
+            value_parser = parse_url,
+            requires = "remote_signer_token",
+            conflicts_with_all = ["generator_key", "validator_key"]
+        )]
+        remote_signer: Option<Url>,
+        #[arg(
+            long,
+            env = "REMOTE_SIGNER_TOKEN",
+            hide_env_values = true,
+            help = "Token to authenticate with the remote signer.",
+            requires = "remote_signer"
+        )]
+        remote_signer_token: Option<String>,
+        #[arg(
+            long,
+            help = "Keep serving the kinds that still match if the coordinator is upgraded to an incompatible implementation, instead of refusing to serve."
+        )]
+        allow_abi_mismatch: bool,
+        #[arg(
+            long,
+            help = "Maximum number of tasks to handle at the same time, shared across all coordinators & chains.",
+            default_value_t = DriaOracle::DEFAULT_CAPACITY
+        )]
+        max_concurrent_tasks: usize,
+        #[arg(
+            long = "serve-window",
+            env = "SERVE_WINDOWS",
+            help = "Window(s) of the day to take new tasks within as HH:MM-HH:MM in UTC, e.g. 22:00-06:00, where the tasks in progress are still finished outside of them.",
+            value_delimiter = ',',
+            value_parser = parse_serve_window
+        )]
+        serve_windows: Vec<ServeWindow>,
+        #[arg(
+            long,
+            env = "QUEUE_ORDER",
+            help = "Criteria to order the pending tasks by, in order of importance: fee, age, difficulty.",
+            value_delimiter = ',',
+            value_parser = parse_queue_order
+        )]
+        queue_order: Vec<QueueOrder>,
+        #[arg(
+            long = "prefer-protocol",
+            env = "PREFERRED_PROTOCOLS",
+            help = "Protocol(s) to handle before all others when there are more tasks than the capacity, in order of preference.",
+            value_delimiter = ','
+        )]
+        preferred_protocols: Vec<String>,
+        #[arg(
+            long,
+            env = "TASK_LOCK",
+            help = "Lock each task before handling it, so that replicas of the same wallet share the tasks: file://<dir> for a single host, redis://<host> for multiple hosts.",
+            value_parser = parse_task_lock
+        )]
+        task_lock: Option<TaskLock>,
+        #[arg(
+            long,
+            help = "Only handle the tasks of this shard as <index>/<count>, i.e. the tasks where taskId % count == index.",
+            value_parser = parse_shard
+        )]
+        shard: Option<Shard>,
+        #[arg(
+            long,
+            help = "Handle the tasks of other shards as well if they are still pending after this many seconds.",
+            requires = "shard"
+        )]
+        shard_failover: Option<u64>,
+        #[arg(
+            long,
+            env = "EVENT_BRIDGE",
+            help = "Publish the observed task events & our responses as JSON to a message broker: nats://<host>:<port>/<subject> or kafka://<host>:<port>/<topic>.",
+            value_parser = parse_url
+        )]
+        event_bridge: Option<Url>,
+        #[arg(
+            long,
+            env = "DB_MAX_AGE_DAYS",
+            help = "Prune the rows of the local database older than this many days, compacted periodically while serving."
+        )]
+        db_max_age_days: Option<u64>,
+        #[arg(
+            long,
+            env = "DB_MAX_TABLE_SIZE_MB",
+            help = "Prune the oldest rows of each table of the local database larger than this many megabytes, compacted periodically while serving."
+        )]
+        db_max_table_size_mb: Option<u64>,
+        #[cfg(feature = "grpc")]
+        #[arg(long, help = "Serve the gRPC API at this address along with the node.")]
+        grpc_address: Option<SocketAddr>,
+        #[cfg(feature = "grpc")]
+        #[arg(
+            long,
+            env = "GRPC_TOKEN",
+            hide_env_values = true,
+            requires = "grpc_address",
+            help = "Token that the clients of the gRPC API must authenticate with, at least 32 characters. Required unless the API is served on the loopback."
+        )]
+        grpc_token: Option<String>,
     },
     /// View tasks.
     View {
@@ -65,6 +502,183 @@ pub enum Commands {
         #[arg(short, long, help = "Task id to view.")]
         task_id: Option<U256>,
     },
+    /// Request multiple tasks from a JSON-lines file.
+    RequestBatch {
+        #[arg(
+            help = "Path to the file, where each line is a request object with `input`, `models` and optional `difficulty`, `num_gens`, `num_vals`, `protocol` fields.",
+            required = true
+        )]
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "Maximum number of requests to wait for at the same time.",
+            default_value_t = 1
+        )]
+        concurrency: usize,
+    },
+    /// Request synthetic tasks and report their request to completion latencies & throughput.
+    Loadtest {
+        #[arg(long, help = "The number of tasks to request.", default_value_t = 10)]
+        tasks: usize,
+        #[arg(
+            long,
+            help = "Maximum number of tasks in progress at the same time.",
+            default_value_t = 1
+        )]
+        concurrency: usize,
+        #[arg(short, long = "model", help = "The model(s) to accept.", required = true, value_parser = parse_model)]
+        models: Vec<Model>,
+        #[arg(long, help = "The difficulty of the tasks.", default_value_t = 2)]
+        difficulty: u8,
+        #[arg(long, help = "Protocol name for the requests", default_value = concat!("loadtest/", env!("CARGO_PKG_VERSION")))]
+        protocol: String,
+        #[arg(
+            long,
+            help = "Maximum time to wait for each task to complete in seconds.",
+            default_value_t = 600
+        )]
+        timeout: u64,
+    },
+    /// Watch a task until it is completed, and show its best response.
+    Watch {
+        #[arg(short, long, help = "Task id to watch.", required = true)]
+        task_id: U256,
+    },
+    /// Recompute validation scores of a completed task locally, and compare them with the validators.
+    Audit {
+        #[arg(short, long, help = "Task id to audit.", required = true)]
+        task_id: U256,
+    },
+    /// Summarize costs vs. rewards of handled tasks, per protocol and per model.
+    Costs {
+        #[arg(long, help = "Starting block number, defaults to 'earliest'.", value_parser = parse_block_number_or_tag)]
+        from: Option<BlockNumberOrTag>,
+        #[arg(long, help = "Ending block number, defaults to 'latest'.", value_parser = parse_block_number_or_tag)]
+        to: Option<BlockNumberOrTag>,
+    },
+    /// Send the responses whose transactions have failed again, from the outbox.
+    RetryOutbox,
+    /// Summarize tasks within a block range, e.g. per status, protocol and model.
+    Stats {
+        #[arg(long, help = "Starting block number, defaults to 'earliest'.", value_parser = parse_block_number_or_tag)]
+        from: Option<BlockNumberOrTag>,
+        #[arg(long, help = "Ending block number, defaults to 'latest'.", value_parser = parse_block_number_or_tag)]
+        to: Option<BlockNumberOrTag>,
+    },
+    /// See the reputation of the generators, i.e. their average final score & response rate within the recorded tasks.
+    Peers {
+        #[arg(long, help = "Record the tasks completed from this block number before displaying, e.g. 'earliest'.", value_parser = parse_block_number_or_tag)]
+        from: Option<BlockNumberOrTag>,
+        #[arg(long, help = "Ending block number to record up to, defaults to 'latest'.", value_parser = parse_block_number_or_tag)]
+        to: Option<BlockNumberOrTag>,
+    },
+    /// Repair or verify the indexed tasks within the local database against the chain, or prune the database.
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+    /// Index the tasks & events of the coordinator into the local database until stopped, or list the indexed ones.
+    Index {
+        #[arg(long, help = "Block number to start indexing from, defaults to where the index was left off.", value_parser = parse_block_number_or_tag)]
+        from: Option<BlockNumberOrTag>,
+        #[arg(
+            long,
+            help = "List only the tasks of this protocol.",
+            requires = "list"
+        )]
+        protocol: Option<String>,
+        #[arg(long, help = "List only the tasks of this requester.", value_parser = parse_address, requires = "list")]
+        requester: Option<Address>,
+        #[arg(long, help = "List only the tasks with this status: pending-generation, pending-validation or completed.", value_parser = parse_task_status, requires = "list")]
+        status: Option<TaskStatus>,
+        #[arg(long, help = "List the indexed tasks instead of indexing.")]
+        list: bool,
+        #[arg(
+            long,
+            help = "List the indexed events of the coordinator & registry instead, e.g. registrations & upgrades.",
+            requires = "list"
+        )]
+        events: bool,
+    },
+    /// Request a workflow task from a JSON file, which is validated before the request is made.
+    RequestWorkflow {
+        #[arg(help = "Path to the workflow JSON file.", required = true)]
+        file: PathBuf,
+        #[arg(short, long = "model", help = "The model(s) to accept.", required = true, value_parser = parse_model)]
+        models: Vec<Model>,
+        #[arg(long, help = "The difficulty of the task.", default_value_t = 2)]
+        difficulty: u8,
+        #[arg(long, help = "Protocol name for the request", default_value = crate::DEFAULT_PROTOCOL)]
+        protocol: String,
+        #[arg(
+            long,
+            help = "The number of generations to request.",
+            default_value_t = 1
+        )]
+        num_gens: u64,
+        #[arg(
+            long,
+            help = "The number of validations to request.",
+            default_value_t = 1
+        )]
+        num_vals: u64,
+        #[arg(
+            long,
+            help = "Wait until the task is completed, and show its best response."
+        )]
+        wait: bool,
+        #[arg(
+            long,
+            help = "Maximum time to wait for the task to complete in seconds, waits indefinitely if omitted.",
+            requires = "wait"
+        )]
+        wait_timeout: Option<u64>,
+    },
+    /// Request a chat task, optionally continuing the conversation of a previous task.
+    RequestChat {
+        #[arg(long, help = "The message to send.", required = true)]
+        content: String,
+        #[arg(
+            long,
+            help = "Task id whose output is the history of the conversation to continue."
+        )]
+        history_id: Option<usize>,
+        #[arg(short, long = "model", help = "The model(s) to accept.", required = true, value_parser = parse_model)]
+        models: Vec<Model>,
+        #[arg(long, help = "The difficulty of the task.", default_value_t = 2)]
+        difficulty: u8,
+        #[arg(long, help = "Protocol name for the request", default_value = crate::DEFAULT_PROTOCOL)]
+        protocol: String,
+        #[arg(
+            long,
+            help = "The number of generations to request.",
+            default_value_t = 1
+        )]
+        num_gens: u64,
+        #[arg(
+            long,
+            help = "The number of validations to request.",
+            default_value_t = 1
+        )]
+        num_vals: u64,
+        #[arg(
+            long,
+            help = "Request without making sure that the history task exists and has a response.",
+            requires = "history_id"
+        )]
+        skip_history_check: bool,
+        #[arg(
+            long,
+            help = "Wait until the task is completed, and show its best response."
+        )]
+        wait: bool,
+        #[arg(
+            long,
+            help = "Maximum time to wait for the task to complete in seconds, waits indefinitely if omitted.",
+            requires = "wait"
+        )]
+        wait_timeout: Option<u64>,
+    },
     /// Request a task.
     Request {
         #[arg(help = "The input to request a task with.", required = true)]
@@ -73,7 +687,7 @@ pub enum Commands {
         models: Vec<Model>,
         #[arg(long, help = "The difficulty of the task.", default_value_t = 2)]
         difficulty: u8,
-        #[arg(long, help = "Protocol name for the request", default_value = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))]
+        #[arg(long, help = "Protocol name for the request", default_value = crate::DEFAULT_PROTOCOL)]
         protocol: String,
         #[arg(
             long,
@@ -87,5 +701,144 @@ pub enum Commands {
             default_value_t = 1
         )]
         num_vals: u64,
+        #[arg(
+            long,
+            help = "Wait until the task is completed, and show its best response."
+        )]
+        wait: bool,
+        #[arg(
+            long,
+            help = "Maximum time to wait for the task to complete in seconds, waits indefinitely if omitted.",
+            requires = "wait"
+        )]
+        wait_timeout: Option<u64>,
+    },
+    /// Serve an API that submits the responses of remote workers with the wallet of this node.
+    Signer {
+        #[arg(
+            long,
+            help = "Address to serve the API at.",
+            default_value = "127.0.0.1:8090"
+        )]
+        address: SocketAddr,
+        #[arg(
+            long,
+            env = "SIGNER_TOKEN",
+            hide_env_values = true,
+            help = "Token that the workers must authenticate with, at least 32 characters."
+        )]
+        token: String,
+    },
+    /// Serve an OpenAI-compatible chat completions API, where each completion is an oracle request.
+    Gateway {
+        #[arg(
+            long,
+            help = "Address to serve the API at.",
+            default_value = "127.0.0.1:8080"
+        )]
+        address: SocketAddr,
+        #[arg(long, help = "The difficulty of the tasks.", default_value_t = 2)]
+        difficulty: u8,
+        #[arg(long, help = "Protocol name for the requests", default_value = crate::DEFAULT_PROTOCOL)]
+        protocol: String,
+        #[arg(
+            long,
+            help = "The number of generations to request per task.",
+            default_value_t = 1
+        )]
+        num_gens: u64,
+        #[arg(
+            long,
+            help = "The number of validations to request per task.",
+            default_value_t = 1
+        )]
+        num_vals: u64,
+        #[arg(
+            long,
+            help = "Maximum time to wait for a task to complete in seconds.",
+            default_value_t = 600
+        )]
+        timeout: u64,
+    },
+    /// Create a starter .env file with a new or imported wallet, and check what is missing to run a node.
+    Init {
+        #[arg(long, help = "Secret key to import, a new wallet is generated if omitted.", value_parser = parse_secret_key)]
+        secret_key: Option<B256>,
+        #[arg(long, help = "RPC URL to connect with the blockchain.", value_parser = parse_url)]
+        rpc_url: Option<Url>,
+        #[arg(short, long = "model", help = "The model(s) to check the availability of.", value_parser = parse_model)]
+        models: Vec<Model>,
+        #[arg(long, help = "Overwrite the .env file if it exists.")]
+        force: bool,
+        #[arg(
+            long,
+            help = "Do not prompt for missing values, e.g. within a container."
+        )]
+        no_input: bool,
+    },
+    /// Print the shell completion script for the given shell.
+    Completions {
+        #[arg(help = "The shell to generate the completions for.")]
+        shell: clap_complete::Shell,
+    },
+    /// Print the manpage of the CLI.
+    Man,
+    /// Manage the Arweave storage, e.g. the balance that uploads are paid from.
+    Storage {
+        #[command(subcommand)]
+        command: StorageCommands,
+    },
+    /// List the built-in workflows, or print one of them as JSON.
+    Presets {
+        #[command(subcommand)]
+        command: PresetCommands,
+    },
+    /// Print the decisions made about the tasks while serving, as recorded by the write-ahead log.
+    Replay {
+        #[arg(
+            long,
+            help = "Print the decisions made within this duration, e.g. 30m, 12h or 7d.",
+            default_value = "1h",
+            value_parser = parse_age
+        )]
+        since: Duration,
+        #[arg(long, help = "Print the decisions made about this task only.")]
+        task_id: Option<U256>,
+    },
+    /// Check a workflow JSON file for the issues that would make it fail, without making a request.
+    LintWorkflow {
+        #[arg(help = "Path to the workflow JSON file.", required = true)]
+        file: PathBuf,
+    },
+    /// Benchmark models with a built-in battery of generation & validation prompts, measuring their
+    /// latency, throughput & scores on this machine.
+    Benchmark {
+        #[arg(short, long = "model", help = "The model(s) to benchmark.", required = true, value_parser = parse_model)]
+        models: Vec<Model>,
+        #[arg(long, help = "Model to score the outputs with, defaults to the benchmarked model itself.", value_parser = parse_model)]
+        judge: Option<Model>,
+        #[arg(
+            long,
+            help = "Number of times to generate each prompt.",
+            default_value_t = 1
+        )]
+        rounds: usize,
+        #[arg(
+            long,
+            help = "Number of generations at a time, can be given multiple times to compare them.",
+            default_values_t = [1]
+        )]
+        concurrency: Vec<usize>,
+    },
+    /// Estimate the time it takes to mine a nonce with the given difficulty on this machine.
+    EstimateDifficulty {
+        #[arg(help = "The difficulty to estimate the mining time of.")]
+        difficulty: u8,
+        #[arg(
+            long,
+            help = "Duration of the hash rate benchmark in seconds.",
+            default_value_t = 3
+        )]
+        benchmark_secs: u64,
     },
 }
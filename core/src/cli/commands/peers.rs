@@ -0,0 +1,99 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::U256;
+use dria_oracle_contracts::TaskStatus;
+use eyre::Result;
+
+use crate::{DriaOracle, PeerResponse};
+
+impl DriaOracle {
+    /// Records the responses of the tasks that were completed between two blocks, so that
+    /// they are counted towards the reputation of their generators.
+    pub(in crate::cli) async fn record_peer_responses(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+    ) -> Result<()> {
+        let from_block = self.resolve_block_number(from_block).await?;
+        let to_block = self.resolve_block_number(to_block).await?;
+        log::info!(
+            "Recording the responses of completed tasks between blocks: {} - {}",
+            from_block,
+            to_block
+        );
+
+        let mut task_ids = self
+            .get_tasks_in_range_chunked(from_block, to_block)
+            .await?
+            .into_iter()
+            .filter(|(event, _)| event.statusAfter == u8::from(TaskStatus::Completed))
+            .map(|(event, _)| event.taskId)
+            .collect::<Vec<_>>();
+        task_ids.sort();
+        task_ids.dedup();
+
+        let recorded = self.record_completed_tasks(&task_ids).await?;
+        log::info!(
+            "Recorded {} new responses of {} completed tasks.",
+            recorded,
+            task_ids.len()
+        );
+
+        Ok(())
+    }
+
+    /// Records the responses of the given completed tasks, returns the number of new responses.
+    ///
+    /// This is also done while serving as the tasks are completed, see [`Self::serve`].
+    pub(in crate::cli) async fn record_completed_tasks(&self, task_ids: &[U256]) -> Result<usize> {
+        let coordinator = *self.coordinator.address();
+        let responses = self
+            .get_tasks(task_ids)
+            .await?
+            .into_iter()
+            .flat_map(|task| {
+                task.responses
+                    .into_iter()
+                    .map(move |response| PeerResponse {
+                        coordinator,
+                        task_id: task.task_id,
+                        responder: response.responder,
+                        score: response.score,
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        self.db.insert_peer_responses(responses)
+    }
+
+    /// Displays the reputation of the generators within the recorded tasks, best first.
+    pub(in crate::cli) fn display_peers(&self) -> Result<()> {
+        let reputations = self.db.get_peer_reputations(*self.coordinator.address())?;
+        if reputations.is_empty() {
+            log::warn!("There are no recorded tasks, record them with `peers --from <block>` or by serving.");
+            return Ok(());
+        }
+
+        log::info!(
+            "Reputation of {} generators over {} completed tasks:",
+            reputations.len(),
+            reputations[0].tasks
+        );
+        for reputation in reputations {
+            log::info!(
+                "{}{}: average score {:.2}, responded to {}/{} ({:.1}%)",
+                reputation.address,
+                if reputation.address == self.address() {
+                    " (you)"
+                } else {
+                    ""
+                },
+                reputation.average_score,
+                reputation.responses,
+                reputation.tasks,
+                reputation.response_rate() * 100.0
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use clap::Subcommand;
+use eyre::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::{BuiltinPreset, BUILTIN_PRESETS};
+
+#[derive(Subcommand)]
+pub enum PresetCommands {
+    /// List the built-in workflows.
+    List,
+    /// Print a built-in workflow as JSON, to use it as a starting point for a custom workflow.
+    Show {
+        #[arg(help = "Name of the preset.", required = true)]
+        name: String,
+        #[arg(
+            long,
+            help = "Write the workflow to this file instead of the standard output."
+        )]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Handles a presets command, which requires nothing but the built-in workflows.
+pub(in crate::cli) fn handle_presets_command(command: &PresetCommands) -> Result<()> {
+    match command {
+        PresetCommands::List => {
+            let presets = BUILTIN_PRESETS
+                .iter()
+                .map(|preset| format!("{:<16}{}", preset.name, preset.description))
+                .collect::<Vec<_>>()
+                .join("\n");
+            log::info!("Built-in workflows:\n{}", presets);
+        }
+        PresetCommands::Show { name, output } => {
+            let workflow = serde_json::to_string_pretty(&BuiltinPreset::find(name)?.to_json()?)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(path, workflow + "\n")
+                        .wrap_err_with(|| format!("could not write {}", path.display()))?;
+                    log::info!("Written the {} workflow to {}", name, path.display());
+                }
+                None => writeln!(std::io::stdout(), "{}", workflow)?,
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,4 @@
-use alloy::primitives::utils::format_ether;
-use dria_oracle_contracts::OracleKind;
+use dria_oracle_contracts::{OracleKind, TokenBalance};
 use eyre::Result;
 
 use crate::DriaOracle;
@@ -25,12 +24,19 @@ impl DriaOracle {
             .allowance(self.address(), *self.registry.address())
             .await?;
 
+        self.confirm_spending(
+            &format!("Stake tokens to the registry to register as a {}.", kind),
+            stake.amount,
+            *self.registry.address(),
+        )
+        .await?;
+
         // approve if necessary
         if allowance.amount < stake.amount {
             let difference = stake.amount - allowance.amount;
             log::info!(
                 "Approving {} tokens for {} registration.",
-                format_ether(difference),
+                TokenBalance::format_amount(difference, stake.decimals),
                 kind
             );
 
@@ -40,7 +46,7 @@ impl DriaOracle {
                 return Err(eyre::eyre!(
                     "Not enough balance to approve. (have: {}, required: {})",
                     balance,
-                    difference
+                    TokenBalance::format_amount(difference, stake.decimals)
                 ));
             }
 
@@ -88,6 +94,27 @@ impl DriaOracle {
         Ok(())
     }
 
+    /// Displays the staked amounts of the oracle node for all oracle kinds,
+    /// along with the amount required by the registry.
+    ///
+    /// The registry stakes a fixed amount per kind at registration, and returns it all
+    /// at unregistration, so there is no partial top-up or withdrawal.
+    pub(in crate::cli) async fn display_stakes(&self) -> Result<()> {
+        for kind in [OracleKind::Generator, OracleKind::Validator] {
+            let staked = self.get_registry_stake(kind).await?;
+            let required = self.get_registry_stake_amount(kind).await?;
+            log::info!(
+                "{}: {} {} (required: {})",
+                kind,
+                staked.formatted(),
+                staked.symbol,
+                required.formatted()
+            );
+        }
+
+        Ok(())
+    }
+
     /// Displays the registration status of the oracle node for all oracle kinds.
     pub(in crate::cli) async fn display_registrations(&self) -> Result<()> {
         for kind in [OracleKind::Generator, OracleKind::Validator] {
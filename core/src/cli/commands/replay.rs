@@ -0,0 +1,54 @@
+use alloy::primitives::U256;
+use eyre::Result;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{OracleDb, WalDecision};
+
+/// Prints the decisions made by the node within the given time, as recorded by the write-ahead log,
+/// optionally for a single task only.
+pub(in crate::cli) fn replay_wal(
+    data_dir: PathBuf,
+    since: Duration,
+    task_id: Option<U256>,
+) -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let entries = OracleDb::new(data_dir)
+        .get_wal_entries(now.saturating_sub(since.as_secs()))?
+        .into_iter()
+        .filter(|entry| task_id.is_none() || task_id == Some(entry.task_id))
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        log::info!("No decisions within the last {} seconds.", since.as_secs());
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let decision = match &entry.decision {
+            WalDecision::Accepted => "accepted".to_string(),
+            WalDecision::Skipped { reason } => format!("skipped ({})", reason),
+            WalDecision::Submitting { nonce } => format!("submitting (nonce {})", nonce),
+            WalDecision::Submitted { tx_hash } => format!("submitted ({})", tx_hash),
+            WalDecision::Finished => "finished".to_string(),
+            WalDecision::Failed { error } => format!("failed ({})", error),
+            WalDecision::Resumed => "resumed".to_string(),
+        };
+        let trace_id = entry
+            .trace_id
+            .map(|trace_id| format!(" (trace {})", trace_id))
+            .unwrap_or_default();
+        log::info!(
+            "[{}] {} task {} as {} of {}: {}{}",
+            entry.created_at,
+            entry.scope.coordinator,
+            entry.task_id,
+            entry.kind,
+            entry.scope.responder,
+            decision,
+            trace_id
+        );
+    }
+    log::info!("Replayed {} decisions.", entries.len());
+
+    Ok(())
+}
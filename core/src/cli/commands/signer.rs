@@ -0,0 +1,153 @@
+use bytes::Bytes;
+use eyre::{Context, Result};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, Method, StatusCode};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use super::http::{json_response, serve_connections};
+use crate::node::{constant_time_eq, status_of, SignerAddress, SignerError, MIN_TOKEN_LEN};
+use crate::{DriaOracle, OutboxPayload, SignerRequest};
+
+impl DriaOracle {
+    /// Serves a small API at the given address that submits the responses of remote workers with
+    /// the wallet of this node, so that the workers that execute the tasks never hold the key.
+    ///
+    /// Each request must have the given token as its bearer token. The responses are simulated
+    /// before they are sent as usual, and only the coordinator of this node is responded to.
+    ///
+    /// The failed responses are not saved to the outbox of the signer, as the worker saves them to
+    /// its own outbox & retries them through the signer.
+    pub(in crate::cli) async fn serve_signer(
+        &self,
+        address: SocketAddr,
+        token: String,
+        cancellation: CancellationToken,
+    ) -> Result<()> {
+        if token.len() < MIN_TOKEN_LEN {
+            return Err(eyre::eyre!(
+                "Signer token must be at least {} characters.",
+                MIN_TOKEN_LEN
+            ));
+        }
+
+        let listener = TcpListener::bind(address)
+            .await
+            .wrap_err_with(|| format!("could not bind to {}", address))?;
+        log::info!(
+            "Serving the signer for {} at http://{}",
+            self.address(),
+            address
+        );
+
+        // the bearer token is sent as is, so it must not cross an untrusted network in plain text
+        if !address.ip().is_loopback() {
+            log::warn!(
+                "Signer is served over plain HTTP on {}, put it behind a TLS-terminating proxy so that the token is not sent in plain text.",
+                address
+            );
+        }
+
+        serve_connections(listener, cancellation, |req| {
+            self.handle_signer_request(req, &token)
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Authenticates & routes a request to the signer.
+    async fn handle_signer_request(
+        &self,
+        req: hyper::Request<Incoming>,
+        token: &str,
+    ) -> hyper::Response<Full<Bytes>> {
+        let authorized = req
+            .headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()));
+        if !authorized {
+            log::warn!("Refused an unauthorized request to {}", req.uri().path());
+            return error_response(StatusCode::UNAUTHORIZED, "unauthorized".to_string());
+        }
+
+        let (method, path) = (req.method().clone(), req.uri().path().to_string());
+        match (method, path.as_str()) {
+            (Method::GET, "/address") => json_response(
+                StatusCode::OK,
+                &SignerAddress {
+                    address: self.address(),
+                },
+            ),
+            (Method::POST, "/respond") => {
+                let body = match req.into_body().collect().await {
+                    Ok(body) => body.to_bytes(),
+                    Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+                };
+                let request: SignerRequest = match serde_json::from_slice(&body) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        return error_response(
+                            StatusCode::BAD_REQUEST,
+                            format!("could not parse response: {}", e),
+                        )
+                    }
+                };
+                if request.coordinator != *self.coordinator.address() {
+                    return error_response(
+                        StatusCode::BAD_REQUEST,
+                        format!("coordinator {} is not served", request.coordinator),
+                    );
+                }
+
+                log::info!(
+                    "Submitting response for task {} on behalf of a worker.",
+                    request.task_id
+                );
+                let result = match request.payload {
+                    OutboxPayload::Generator { output } => {
+                        self.submit_generation(
+                            request.task_id,
+                            output,
+                            request.metadata,
+                            request.nonce,
+                            false,
+                        )
+                        .await
+                    }
+                    OutboxPayload::Validator { scores } => {
+                        self.submit_validation(
+                            request.task_id,
+                            scores,
+                            request.metadata,
+                            request.nonce,
+                            false,
+                        )
+                        .await
+                    }
+                };
+
+                match result {
+                    Ok(receipt) => json_response(StatusCode::OK, &receipt),
+                    Err(e) => {
+                        log::error!(
+                            "Could not submit response for task {}: {}",
+                            request.task_id,
+                            e
+                        );
+                        error_response(status_of(&e), e.to_string())
+                    }
+                }
+            }
+            (_, path) => error_response(StatusCode::NOT_FOUND, format!("unknown path {}", path)),
+        }
+    }
+}
+
+/// Creates an error response with the given status.
+fn error_response(status: StatusCode, error: String) -> hyper::Response<Full<Bytes>> {
+    json_response(status, &SignerError { error })
+}
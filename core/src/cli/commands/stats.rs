@@ -0,0 +1,172 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::U256;
+use dria_oracle_contracts::TaskStatus;
+use eyre::Result;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::DriaOracle;
+
+/// Running average of a list of numbers.
+#[derive(Debug, Default)]
+struct Average {
+    sum: f64,
+    count: usize,
+}
+
+impl Average {
+    fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+impl std::fmt::Display for Average {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.count == 0 {
+            write!(f, "-")
+        } else {
+            write!(
+                f,
+                "{:.2} (over {})",
+                self.sum / self.count as f64,
+                self.count
+            )
+        }
+    }
+}
+
+impl DriaOracle {
+    /// Displays statistics of the tasks that had events between two blocks.
+    pub(in crate::cli) async fn display_stats(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+    ) -> Result<()> {
+        let from_block = self.resolve_block_number(from_block).await?;
+        let to_block = self.resolve_block_number(to_block).await?;
+        log::info!(
+            "Computing task statistics between blocks: {} - {}",
+            from_block,
+            to_block
+        );
+
+        // find the request & completion blocks of each task within the range
+        let events = self
+            .get_tasks_in_range_chunked(from_block, to_block)
+            .await?;
+        let mut request_blocks = HashMap::<U256, u64>::new();
+        let mut completion_blocks = HashMap::<U256, u64>::new();
+        for (event, log) in &events {
+            let block_number = log.block_number.unwrap_or_default();
+            match TaskStatus::try_from(event.statusAfter).unwrap_or_default() {
+                TaskStatus::PendingGeneration => {
+                    request_blocks.insert(event.taskId, block_number);
+                }
+                TaskStatus::Completed => {
+                    completion_blocks.insert(event.taskId, block_number);
+                }
+                _ => {}
+            }
+        }
+        let mut task_ids = events
+            .iter()
+            .map(|(event, _)| event.taskId)
+            .collect::<Vec<_>>();
+        task_ids.sort();
+        task_ids.dedup();
+        if task_ids.is_empty() {
+            log::warn!("There are no tasks within this range.");
+            return Ok(());
+        }
+
+        let mut by_status = BTreeMap::<String, usize>::new();
+        let mut by_protocol = BTreeMap::<String, usize>::new();
+        let mut by_model = BTreeMap::<String, usize>::new();
+        let mut validation_score = Average::default();
+        let mut final_score = Average::default();
+        let mut latency = Average::default();
+        let (mut responses, mut our_responses) = (0usize, 0usize);
+        let (mut validations, mut our_validations) = (0usize, 0usize);
+        let mut our_fees = U256::ZERO;
+        for task in self.get_tasks(&task_ids).await? {
+            let task_id = &task.task_id;
+
+            *by_status
+                .entry(task.request.status.to_string())
+                .or_default() += 1;
+            *by_protocol
+                .entry(task.request.protocol.clone())
+                .or_default() += 1;
+            for model in &task.request.models {
+                *by_model.entry(model.clone()).or_default() += 1;
+            }
+
+            for response in &task.responses {
+                responses += 1;
+                if response.responder == self.address() {
+                    our_responses += 1;
+                    // generators are paid when the task is completed
+                    if matches!(task.request.status, TaskStatus::Completed) {
+                        our_fees += task.request.generator_fee;
+                    }
+                }
+                if matches!(task.request.status, TaskStatus::Completed) {
+                    final_score.add(response.score.saturating_to::<u64>() as f64);
+                }
+            }
+            for validation in &task.validations {
+                validations += 1;
+                if validation.validator == self.address() {
+                    our_validations += 1;
+                    our_fees += task.request.validator_fee;
+                }
+                for score in &validation.scores {
+                    validation_score.add(score.saturating_to::<u64>() as f64);
+                }
+            }
+
+            if let (Some(requested), Some(completed)) =
+                (request_blocks.get(task_id), completion_blocks.get(task_id))
+            {
+                latency.add(completed.saturating_sub(*requested) as f64);
+            }
+        }
+
+        log::info!("Tasks per status:");
+        for (status, count) in by_status {
+            log::info!("{}: {}", status, count);
+        }
+        log::info!("Tasks per protocol:");
+        for (protocol, count) in by_protocol {
+            log::info!("{}: {}", protocol, count);
+        }
+        log::info!("Tasks per model:");
+        for (model, count) in by_model {
+            log::info!("{}: {}", model, count);
+        }
+
+        log::info!(
+            "Total tasks: {}\nAverage validation score: {}\nAverage final score: {}\nAverage latency (blocks): {}\nOur responses: {}/{}\nOur validations: {}/{}",
+            task_ids.len(),
+            validation_score,
+            final_score,
+            latency,
+            our_responses,
+            responses,
+            our_validations,
+            validations
+        );
+
+        let our_fees = self.to_token_balance(our_fees).await?;
+        match self
+            .get_usd_prices()
+            .await
+            .token_usd(our_fees.amount, our_fees.decimals)
+        {
+            Some(usd) => log::info!("Our fees: {} (~${:.2})", our_fees, usd),
+            None => log::info!("Our fees: {}", our_fees),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use clap::Subcommand;
+use dria_oracle_storage::{format_winston, parse_winston, ArweaveStorage};
+use eyre::{eyre, Result};
+use std::io::IsTerminal;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::node::prompt;
+
+/// Interval to check the balance at the bundler while serving.
+const BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Subcommand)]
+pub enum StorageCommands {
+    /// See the balance of the Arweave wallet at the bundler, which uploads are paid from.
+    Balance,
+    /// Fund the balance of the Arweave wallet at the bundler.
+    Fund {
+        #[arg(help = "The amount of AR to fund, e.g. 0.5.", value_parser = parse_winston)]
+        amount: u128,
+    },
+}
+
+/// Handles a storage command, which only requires the Arweave wallet.
+pub(in crate::cli) async fn handle_storage_command(
+    command: &StorageCommands,
+    confirm: bool,
+) -> Result<()> {
+    let arweave = ArweaveStorage::new_from_env()?;
+    match command {
+        StorageCommands::Balance => {
+            let balance = arweave.get_balance().await?;
+            log::info!(
+                "Arweave balance of {}: {}",
+                arweave.wallet_address()?,
+                format_winston(balance)
+            );
+        }
+        StorageCommands::Fund { amount } => {
+            let amount_winston =
+                u64::try_from(*amount).map_err(|_| eyre!("Amount is too large to fund at once"))?;
+            if confirm {
+                log::info!(
+                    "Funding the Arweave balance at the bundler\n  Amount: {}\n  Wallet: {}",
+                    format_winston(*amount),
+                    arweave.wallet_address()?
+                );
+                if !std::io::stdin().is_terminal() {
+                    return Err(eyre!(
+                        "Confirmation is required but the input is not a terminal, use --yes to skip it."
+                    ));
+                }
+                match prompt("Proceed? [y/N]")?.to_lowercase().as_str() {
+                    "y" | "yes" => {}
+                    _ => return Err(eyre!("Cancelled.")),
+                }
+            }
+
+            arweave.fund(amount_winston).await?;
+            log::info!(
+                "Funded {}, it may take a while for the bundler to confirm it.",
+                format_winston(*amount)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the Arweave storage from the environment, or `None` if there is no wallet to upload with,
+/// i.e. `ARWEAVE_WALLET_PATH` is not set.
+pub(in crate::cli) fn configured_storage() -> Result<Option<ArweaveStorage>> {
+    match std::env::var("ARWEAVE_WALLET_PATH") {
+        Ok(path) if !path.is_empty() => ArweaveStorage::new_from_env().map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Checks the balance at the bundler periodically until cancelled, alerting when it is below the given
+/// minimum so that uploads do not start failing silently.
+///
+/// Does nothing if there is no minimum, or if Arweave is not configured.
+pub(in crate::cli) async fn monitor_storage_balance(
+    min_balance: Option<u128>,
+    cancellation: CancellationToken,
+) {
+    let Some(min_balance) = min_balance else {
+        return;
+    };
+    let arweave = match configured_storage() {
+        Ok(Some(arweave)) => arweave,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Not checking the Arweave balance: {:#}", e);
+            return;
+        }
+    };
+
+    let mut interval = tokio::time::interval(BALANCE_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = cancellation.cancelled() => return,
+            _ = interval.tick() => match arweave.get_balance().await {
+                Ok(balance) if balance < min_balance => log::error!(
+                    "Arweave balance {} is below {}, uploads will fail once it runs dry. Fund it with `dria-oracle storage fund <amount>`.",
+                    format_winston(balance),
+                    format_winston(min_balance)
+                ),
+                Ok(balance) => log::debug!("Arweave balance: {}", format_winston(balance)),
+                Err(e) => log::warn!("Could not check the Arweave balance: {:#}", e),
+            },
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use crate::DriaOracle;
-use eyre::Result;
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use dria_oracle_contracts::{TaskStatus, TokenBalance};
+use eyre::{eyre, Result};
+use std::collections::{BTreeSet, HashMap};
 
 impl DriaOracle {
     /// Display token balances.
@@ -17,7 +22,12 @@ impl DriaOracle {
         Ok(())
     }
 
-    /// Show the amount of claimable rewards.
+    /// Show the amount of claimable rewards, along with a breakdown by generator & validator roles.
+    ///
+    /// The breakdown is estimated from the responses & validations made since the last claim, where
+    /// the logs are queried in chunks of [`Self::LOG_QUERY_CHUNK_SIZE`] blocks and the tasks are read
+    /// in batches. Generators are only paid for the responses that are scored well enough, so their
+    /// figure is an upper bound.
     pub(in crate::cli) async fn display_rewards(&self) -> Result<()> {
         let allowance = self
             .allowance(*self.coordinator.address(), self.address())
@@ -27,8 +37,211 @@ impl DriaOracle {
         log::info!("{} ", allowance);
         if allowance.amount.is_zero() {
             log::warn!("You have no claimable rewards!");
+            return Ok(());
         }
 
+        let latest_block = self.provider.get_block_number().await?;
+        let chunks = (0..=latest_block)
+            .step_by(Self::LOG_QUERY_CHUNK_SIZE as usize)
+            .map(|start| {
+                (
+                    start,
+                    latest_block.min(start + Self::LOG_QUERY_CHUNK_SIZE - 1),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // rewards since the last claim are the ones that are claimable,
+        // so the claims are searched from the latest block backwards
+        let mut since_block = 0;
+        for &(start, end) in chunks.iter().rev() {
+            let last_claim = self
+                .get_transfers_in_range(*self.coordinator.address(), self.address(), start, end)
+                .await?
+                .iter()
+                .filter_map(|(_, log)| log.block_number)
+                .max();
+            if let Some(last_claim) = last_claim {
+                since_block = last_claim;
+                break;
+            }
+        }
+
+        let mut generated = Vec::new();
+        let mut validated = Vec::new();
+        for &(start, end) in chunks.iter().filter(|(_, end)| *end >= since_block) {
+            let start = start.max(since_block);
+            generated.extend(
+                self.get_responses_in_range(self.address(), start, end)
+                    .await?
+                    .into_iter()
+                    .map(|(response, _)| response.taskId),
+            );
+            validated.extend(
+                self.get_validations_in_range(self.address(), start, end)
+                    .await?
+                    .into_iter()
+                    .map(|(validation, _)| validation.taskId),
+            );
+        }
+
+        // the tasks are read in batches, each within a single multicall
+        let task_ids = generated
+            .iter()
+            .chain(&validated)
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let requests = self
+            .get_tasks(&task_ids)
+            .await?
+            .into_iter()
+            .map(|task| (task.task_id, task.request))
+            .collect::<HashMap<_, _>>();
+
+        // generators may be paid when the task is completed
+        let generator_rewards = generated
+            .iter()
+            .filter_map(|task_id| requests.get(task_id))
+            .filter(|request| matches!(request.status, TaskStatus::Completed))
+            .fold(U256::ZERO, |total, request| total + request.generator_fee);
+
+        // validators are paid as soon as they validate
+        let validator_rewards = validated
+            .iter()
+            .filter_map(|task_id| requests.get(task_id))
+            .fold(U256::ZERO, |total, request| total + request.validator_fee);
+
+        log::info!(
+            "Estimated breakdown since block {}:\nGenerator: up to {} {}\nValidator: {} {}",
+            since_block,
+            TokenBalance::format_amount(generator_rewards, allowance.decimals),
+            allowance.symbol,
+            TokenBalance::format_amount(validator_rewards, allowance.decimals),
+            allowance.symbol
+        );
+
+        let prices = self.get_usd_prices().await;
+        if let Some(usd) = prices.token_usd(allowance.amount, allowance.decimals) {
+            log::info!(
+                "Claimable rewards in USD: ~${:.2} (at ${:.2} per {})",
+                usd,
+                prices.token.unwrap_or_default(),
+                allowance.symbol
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Show the past reward claims between two blocks.
+    pub(in crate::cli) async fn display_claims(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+    ) -> Result<()> {
+        let claims = self
+            .get_transfers_in_range(
+                *self.coordinator.address(),
+                self.address(),
+                from_block,
+                to_block,
+            )
+            .await?;
+
+        if claims.is_empty() {
+            log::warn!("There are no claims within this range.");
+            return Ok(());
+        }
+
+        let mut total = self.to_token_balance(U256::ZERO).await?;
+        for (transfer, log) in claims {
+            log::info!(
+                "Claimed {} at block {}, tx: {}",
+                TokenBalance::format_amount(transfer.value, total.decimals),
+                log.block_number.unwrap_or_default(),
+                self.explorer.tx(log.transaction_hash.unwrap_or_default())
+            );
+            total.amount += transfer.value;
+        }
+        log::info!("Total claimed: {}", total);
+
+        Ok(())
+    }
+
+    /// Wraps the given amount of ETH to the fee token, which must be WETH.
+    pub(in crate::cli) async fn wrap_eth(&self, amount: &str) -> Result<()> {
+        let amount = TokenBalance::parse_amount(amount, 18)?;
+        let balance = self.get_native_balance(self.address()).await?;
+        if balance.amount < amount {
+            return Err(eyre!(
+                "Not enough ETH to wrap. (have: {}, required: {})",
+                balance.formatted(),
+                TokenBalance::format_amount(amount, balance.decimals)
+            ));
+        }
+
+        self.confirm_spending("Wrap ETH to WETH.", amount, *self.token.address())
+            .await?;
+        let receipt = self.deposit_weth(amount).await?;
+        log::info!(
+            "Wrapped {} ETH. (tx: {})",
+            TokenBalance::format_amount(amount, balance.decimals),
+            self.explorer.tx(receipt.transaction_hash)
+        );
+
+        self.display_balance().await
+    }
+
+    /// Unwraps the given amount of the fee token to ETH, where the fee token must be WETH.
+    pub(in crate::cli) async fn unwrap_eth(&self, amount: &str) -> Result<()> {
+        let amount = self.parse_token_amount(amount).await?;
+        let balance = self.get_token_balance(self.address()).await?;
+        if balance.amount < amount {
+            return Err(eyre!(
+                "Not enough tokens to unwrap. (have: {}, required: {})",
+                balance,
+                TokenBalance::format_amount(amount, balance.decimals)
+            ));
+        }
+
+        self.confirm_spending("Unwrap WETH to ETH.", amount, *self.token.address())
+            .await?;
+        let receipt = self.withdraw_weth(amount).await?;
+        log::info!(
+            "Unwrapped {} {}. (tx: {})",
+            TokenBalance::format_amount(amount, balance.decimals),
+            balance.symbol,
+            self.explorer.tx(receipt.transaction_hash)
+        );
+
+        self.display_balance().await
+    }
+
+    /// Transfers the given amount of fee tokens to another address, e.g. to move rewards to a treasury.
+    pub(in crate::cli) async fn transfer_tokens(&self, to: Address, amount: &str) -> Result<()> {
+        let amount = self.parse_token_amount(amount).await?;
+        let balance = self.get_token_balance(self.address()).await?;
+        if balance.amount < amount {
+            return Err(eyre!(
+                "Not enough tokens to transfer. (have: {}, required: {})",
+                balance,
+                TokenBalance::format_amount(amount, balance.decimals)
+            ));
+        }
+
+        self.confirm_spending(&format!("Transfer tokens to {}.", to), amount, to)
+            .await?;
+        let receipt = self.transfer(to, amount).await?;
+        log::info!(
+            "Transferred {} {} to {}. (tx: {})",
+            TokenBalance::format_amount(amount, balance.decimals),
+            balance.symbol,
+            self.explorer.address(to),
+            self.explorer.tx(receipt.transaction_hash)
+        );
+
         Ok(())
     }
 
@@ -43,14 +256,26 @@ impl DriaOracle {
         if allowance.amount.is_zero() {
             log::warn!("No rewards to claim.");
         } else {
-            // transfer rewards
-            self.transfer_from(
-                *self.coordinator.address(),
-                self.address(),
+            self.confirm_spending(
+                "Claim rewards from the coordinator.",
                 allowance.amount,
+                *self.coordinator.address(),
             )
             .await?;
-            log::info!("Rewards claimed: {}.", allowance);
+
+            // transfer rewards
+            let receipt = self
+                .transfer_from(
+                    *self.coordinator.address(),
+                    self.address(),
+                    allowance.amount,
+                )
+                .await?;
+            log::info!(
+                "Rewards claimed: {}. (tx: {})",
+                allowance,
+                self.explorer.tx(receipt.transaction_hash)
+            );
         }
 
         Ok(())
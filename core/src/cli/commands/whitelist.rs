@@ -0,0 +1,116 @@
+use clap::Subcommand;
+use eyre::{Context, Result};
+use reqwest::Url;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use super::parse_url;
+use crate::DriaOracle;
+
+#[derive(Subcommand)]
+pub enum WhitelistCommands {
+    /// See whether the oracle node is whitelisted to serve as a validator.
+    Status,
+    /// Wait until the oracle node is whitelisted, checking the registry periodically.
+    Watch {
+        #[arg(
+            long,
+            help = "Interval in seconds to check the whitelist status.",
+            default_value_t = 60,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        interval_secs: u64,
+        #[arg(
+            long,
+            help = "URL to POST a JSON notification to once the node is whitelisted.",
+            value_parser = parse_url
+        )]
+        webhook: Option<Url>,
+    },
+}
+
+impl DriaOracle {
+    /// Displays the whitelist status of the oracle node, along with what to do if it is not whitelisted.
+    pub(in crate::cli) async fn display_whitelist_status(&self) -> Result<()> {
+        let address = self.address();
+        if self.is_whitelisted(address).await? {
+            log::info!("{} is whitelisted, you can serve as a validator.", address);
+        } else {
+            log::warn!(
+                "{} is not whitelisted in the registry {}, so it can not serve as a validator.",
+                address,
+                self.registry.address()
+            );
+            log::info!(
+                "Apply to be whitelisted with this address, and run `whitelist watch` to be notified once you are."
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks the whitelist status of the oracle node periodically until it is whitelisted or cancelled,
+    /// and notifies the given webhook (if any) once it is whitelisted.
+    pub(in crate::cli) async fn watch_whitelist(
+        &self,
+        interval: Duration,
+        webhook: Option<Url>,
+        cancellation: CancellationToken,
+    ) -> Result<()> {
+        let address = self.address();
+        log::info!(
+            "Waiting for {} to be whitelisted, checking every {}s.",
+            address,
+            interval.as_secs()
+        );
+
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = cancellation.cancelled() => {
+                    log::info!("Stopped waiting for the whitelist.");
+                    return Ok(());
+                }
+                _ = ticker.tick() => {
+                    // a failed check is not fatal, as the watcher may run for days
+                    match self.is_whitelisted(address).await {
+                        Ok(true) => break,
+                        Ok(false) => log::debug!("{} is not whitelisted yet.", address),
+                        Err(e) => log::warn!("Could not check the whitelist status: {:#}", e),
+                    }
+                }
+            }
+        }
+
+        log::info!(
+            "{} is whitelisted, you can serve as a validator now.",
+            address
+        );
+        if let Some(webhook) = webhook {
+            self.notify_whitelisted(webhook).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Posts a notification to the given webhook that the oracle node is whitelisted.
+    async fn notify_whitelisted(&self, webhook: Url) -> Result<()> {
+        let payload = serde_json::json!({
+            "event": "whitelisted",
+            "address": self.address(),
+            "registry": self.registry.address(),
+        });
+
+        reqwest::Client::new()
+            .post(webhook.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload.to_string())
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .wrap_err_with(|| format!("could not notify the webhook {}", webhook))?;
+        log::info!("Notified the webhook {}.", webhook);
+
+        Ok(())
+    }
+}
@@ -1,15 +1,36 @@
 // use alloy::eips::BlockNumberOrTag;
 use alloy::eips::BlockNumberOrTag;
+use alloy::network::EthereumWallet;
+use alloy::signers::local::PrivateKeySigner;
+use dria_oracle_contracts::OracleKind;
 use eyre::Result;
-use std::{env, path::PathBuf};
+use futures_util::future::join_all;
+use std::{env, path::PathBuf, sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
 use tokio_util::sync::CancellationToken;
 
 mod commands;
-use commands::Commands;
+use commands::{
+    benchmark_models, display_chains, estimate_difficulty, handle_presets_command,
+    handle_storage_command, init_env, lint_workflow_file, monitor_storage_balance,
+    print_completions, print_manpage, replay_wal, Commands, DbCommands, GatewayOptions,
+    WhitelistCommands,
+};
 
 mod parsers;
 use parsers::*;
 
+mod reload;
+use reload::{ReloadableSettings, SettingsReloader, PRICE_REFRESH_INTERVAL};
+
+use crate::configurations::DEFAULT_DATA_DIR;
+use crate::{
+    ApiBudget, ChatHistoryPolicy, ChatRequestBuilder, DriaOracleConfig, EventBridge, GasBudget,
+    Guardrails, IndexFilter, InputLimits, LogRotation, MockModel, ModelWarmup, OllamaAutoPull,
+    OllamaClient, RemoteSigner, RetentionPolicy, RetryPolicy, ScoreMaps, SelfCheck, TaskBudget,
+    ToolSandbox, UsdPrices, VramProbe, WorkflowPresets, WorkflowRequestBuilder,
+};
+
 const DEFAULT_TX_TIMEOUT_SECS: u64 = 160;
 
 #[derive(clap::Parser)]
@@ -26,57 +47,540 @@ pub struct Cli {
     /// Enable debug-level logs
     #[arg(short, long)]
     pub debug: bool,
+
+    /// Write the logs to this file instead of the standard error, rotated by the node
+    #[arg(long, env = "LOG_FILE", global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Rotate the log file before it grows larger than this many megabytes, 0 to not limit its size
+    #[arg(long, global = true, default_value_t = 100)]
+    pub log_max_size_mb: u64,
+
+    /// Rotate the log file once it is older than this many hours
+    #[arg(long, global = true)]
+    pub log_rotate_hours: Option<u64>,
+
+    /// Number of rotated log files to keep, where the oldest ones are deleted
+    #[arg(long, global = true, default_value_t = 7)]
+    pub log_max_files: usize,
+
+    /// Skip the confirmation of commands that move tokens, e.g. for automation
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// Coordinator address, overrides `COORDINATOR_ADDRESS` & the known deployment of the chain
+    #[arg(long, global = true, value_parser = parse_address)]
+    pub coordinator_address: Option<alloy::primitives::Address>,
+
+    /// Registry address, overrides `REGISTRY_ADDRESS` & must match the registry of the coordinator
+    #[arg(long, global = true, value_parser = parse_address)]
+    pub registry_address: Option<alloy::primitives::Address>,
+
+    /// Fee token address, overrides `TOKEN_ADDRESS` & must match the fee token of the coordinator
+    #[arg(long, global = true, value_parser = parse_address)]
+    pub token_address: Option<alloy::primitives::Address>,
 }
 
 impl Cli {
-    pub fn read_secret_key() -> Result<alloy::primitives::B256> {
-        let key = env::var("SECRET_KEY")?;
-        parse_secret_key(&key)
+    pub fn read_tx_timeout() -> Result<u64> {
+        let timeout = env::var("TX_TIMEOUT_SECS").unwrap_or(DEFAULT_TX_TIMEOUT_SECS.to_string());
+        timeout.parse().map_err(Into::into)
     }
 
-    pub fn read_rpc_url() -> Result<reqwest::Url> {
-        let url = env::var("RPC_URL")?;
-        parse_url(&url)
+    /// Returns the log level given by `--debug` or `LOG_LEVEL`, if any.
+    ///
+    /// Without one, the levels are left to the defaults & `RUST_LOG` instead of being limited by a maximum.
+    pub fn read_log_level(&self) -> Result<Option<log::LevelFilter>> {
+        if self.debug {
+            return Ok(Some(log::LevelFilter::Debug));
+        }
+
+        match env::var("LOG_LEVEL") {
+            Ok(level) if !level.is_empty() => level.parse().map(Some).map_err(Into::into),
+            _ => Ok(None),
+        }
     }
 
-    pub fn read_tx_timeout() -> Result<u64> {
-        let timeout = env::var("TX_TIMEOUT_SECS").unwrap_or(DEFAULT_TX_TIMEOUT_SECS.to_string());
-        timeout.parse().map_err(Into::into)
+    /// Returns the rotation of the log file as given by the arguments.
+    pub fn log_rotation(&self) -> LogRotation {
+        LogRotation {
+            max_size: Some(self.log_max_size_mb.saturating_mul(1024 * 1024))
+                .filter(|size| *size > 0),
+            max_age: self
+                .log_rotate_hours
+                .map(|hours| Duration::from_secs(hours.saturating_mul(60 * 60))),
+            max_files: self.log_max_files,
+        }
+    }
+
+    pub fn read_data_dir() -> PathBuf {
+        PathBuf::from(env::var("DATA_DIR").unwrap_or(DEFAULT_DATA_DIR.to_string()))
     }
 }
 
+/// Handles a given CLI command that does not require a node, e.g. before reading the secret key.
+///
+/// Returns `true` if the command was handled.
+pub async fn handle_offline_command(cli: &Cli) -> Result<bool> {
+    match &cli.command {
+        Commands::Chains => display_chains(),
+        Commands::Completions { shell } => print_completions(*shell),
+        Commands::Man => print_manpage()?,
+        Commands::Storage { command } => handle_storage_command(command, !cli.yes).await?,
+        Commands::LintWorkflow { file } => lint_workflow_file(file)?,
+        Commands::Presets { command } => handle_presets_command(command)?,
+        Commands::Replay { since, task_id } => replay_wal(Cli::read_data_dir(), *since, *task_id)?,
+        Commands::EstimateDifficulty {
+            difficulty,
+            benchmark_secs,
+        } => estimate_difficulty(*difficulty, *benchmark_secs, Cli::read_data_dir())?,
+        Commands::Benchmark {
+            models,
+            judge,
+            rounds,
+            concurrency,
+        } => benchmark_models(models.clone(), judge.clone(), *rounds, concurrency.clone()).await?,
+        Commands::Init {
+            secret_key,
+            rpc_url,
+            models,
+            force,
+            no_input,
+        } => {
+            init_env(
+                &cli.env,
+                *secret_key,
+                rpc_url.clone(),
+                models.clone(),
+                *force,
+                !no_input,
+            )
+            .await?
+        }
+        _ => return Ok(false),
+    };
+
+    Ok(true)
+}
+
 /// Handles a given CLI command, using the provided node.
-pub async fn handle_command(command: Commands, mut node: crate::DriaOracle) -> Result<()> {
-    match command {
+pub async fn handle_command(cli: Cli, mut node: crate::DriaOracle) -> Result<()> {
+    match cli.command {
         Commands::Balance => node.display_balance().await?,
         Commands::Claim => node.claim_rewards().await?,
+        Commands::Wrap { amount } => node.wrap_eth(&amount).await?,
+        Commands::Unwrap { amount } => node.unwrap_eth(&amount).await?,
+        Commands::Transfer { to, amount } => node.transfer_tokens(to, &amount).await?,
         Commands::Rewards => node.display_rewards().await?,
+        Commands::Claims { from, to } => {
+            node.display_claims(
+                from.unwrap_or(BlockNumberOrTag::Earliest),
+                to.unwrap_or(BlockNumberOrTag::Latest),
+            )
+            .await?
+        }
         Commands::Serve {
             task_id,
             kinds,
             models,
+            lazy_models,
+            warmup_timeout,
+            ollama_auto_pull,
+            vram_aware,
             from,
             to,
+            min_profit,
+            eth_price,
+            max_validation_cost_ratio,
+            max_start_delay,
+            task_deadline,
+            task_budget,
+            protocol_budgets,
+            max_attempts,
+            attempts_per_model,
+            retry_backoff,
+            score_map,
+            protocol_score_maps,
+            workflow_presets,
+            mock_model,
+            guardrails,
+            blocked_inputs,
+            blocked_outputs,
+            self_check,
+            self_check_model,
+            allowed_tools,
+            denied_tools,
+            allowed_tool_domains,
+            max_tool_steps,
+            max_input_bytes,
+            max_workflow_steps,
+            max_workflow_time,
+            max_memory_bytes,
+            response_gas_ceiling,
+            operator_tag,
+            max_history_tokens,
+            incremental_history,
+            max_hourly_spend,
+            max_daily_spend,
+            max_gas_price,
+            max_daily_gas_spend,
+            min_storage_balance,
+            coordinators,
+            catch_up_workers,
+            chain_envs,
+            generator_key,
+            validator_key,
+            remote_signer,
+            remote_signer_token,
+            allow_abi_mismatch,
+            max_concurrent_tasks,
+            serve_windows,
+            queue_order,
+            preferred_protocols,
+            task_lock,
+            shard,
+            shard_failover,
+            event_bridge,
+            db_max_age_days,
+            db_max_table_size_mb,
+            #[cfg(feature = "grpc")]
+            grpc_address,
+            #[cfg(feature = "grpc")]
+            grpc_token,
         } => {
             let token = CancellationToken::new();
-            node.prepare_oracle(kinds, models).await?;
+            node.capacity = Arc::new(Semaphore::new(max_concurrent_tasks));
+            let retry_policy = RetryPolicy {
+                max_attempts,
+                attempts_per_model,
+                initial_backoff: Duration::from_millis(retry_backoff),
+                ..Default::default()
+            };
+            node.config = node
+                .config
+                .clone()
+                .with_max_concurrent_tasks(max_concurrent_tasks)
+                .with_retry_policy(retry_policy)
+                .with_abi_mismatch_allowed(allow_abi_mismatch)
+                .with_serve_windows(serve_windows)
+                .with_retention(RetentionPolicy::from_args(
+                    db_max_age_days,
+                    db_max_table_size_mb,
+                ));
+            if let Some(max_start_delay) = max_start_delay {
+                node.config = node
+                    .config
+                    .clone()
+                    .with_max_start_delay(Duration::from_millis(max_start_delay));
+            }
+            if let Some(task_deadline) = task_deadline {
+                node.config = node
+                    .config
+                    .clone()
+                    .with_task_deadline(Duration::from_secs(task_deadline));
+            }
+            if task_budget.is_some() || !protocol_budgets.is_empty() {
+                // without --task-budget, only the given protocols are effectively limited
+                let base = task_budget
+                    .map(Duration::from_secs)
+                    .unwrap_or(Duration::MAX);
+                let budget = protocol_budgets
+                    .into_iter()
+                    .fold(TaskBudget::new(base), |budget, (protocol, duration)| {
+                        budget.with_protocol(protocol, duration)
+                    });
+                node.config = node.config.clone().with_task_budget(budget);
+            }
+            if score_map.is_some() || !protocol_score_maps.is_empty() {
+                let score_maps = protocol_score_maps.into_iter().fold(
+                    ScoreMaps::new(score_map.unwrap_or_default()),
+                    |maps, (protocol, map)| maps.with_protocol(protocol, map),
+                );
+                node.config = node.config.clone().with_score_maps(score_maps);
+            }
+            if !workflow_presets.is_empty() {
+                let presets = WorkflowPresets::load(workflow_presets)?;
+                node.config = node.config.clone().with_workflow_presets(presets);
+            }
+            if let Some(path) = mock_model {
+                let mock_model = MockModel::load(path)?;
+                log::warn!(
+                    "Using the mock model backend with {} fixtures, generations are not executed!",
+                    mock_model.fixtures.len()
+                );
+                node.config = node.config.clone().with_mock_model(mock_model);
+            }
+            if let Some(action) = guardrails {
+                let guardrails = blocked_inputs
+                    .into_iter()
+                    .fold(Guardrails::new(action), Guardrails::with_input_pattern);
+                let guardrails = blocked_outputs
+                    .into_iter()
+                    .fold(guardrails, Guardrails::with_output_pattern);
+                node.config = node.config.clone().with_guardrails(guardrails);
+            }
+            if let Some(min_score) = self_check {
+                let self_check = match self_check_model {
+                    Some(model) => SelfCheck::new(min_score).with_model(model),
+                    None => SelfCheck::new(min_score),
+                };
+                node.config = node.config.clone().with_self_check(self_check);
+            }
+            let tool_sandbox = ToolSandbox {
+                allowed_tools: (!allowed_tools.is_empty()).then_some(allowed_tools),
+                denied_tools,
+                allowed_domains: (!allowed_tool_domains.is_empty()).then_some(allowed_tool_domains),
+                max_tool_steps,
+            };
+            let input_limits = InputLimits {
+                max_input_bytes,
+                max_workflow_steps,
+                max_workflow_time,
+                max_memory_bytes,
+                ..Default::default()
+            };
+            node.config = node
+                .config
+                .clone()
+                .with_tool_sandbox(tool_sandbox)
+                .with_input_limits(input_limits);
+            if let Some(gas) = response_gas_ceiling {
+                node.config = node.config.clone().with_response_gas_ceiling(gas);
+            }
+            if let Some(tag) = operator_tag {
+                node.config = node.config.clone().with_operator_tag(tag);
+            }
+            node.config = node.config.clone().with_chat_history(ChatHistoryPolicy {
+                max_tokens: max_history_tokens,
+                incremental: incremental_history,
+            });
+            if let Some(shard) = shard {
+                let shard = match shard_failover {
+                    Some(failover) => shard.with_failover(Duration::from_secs(failover)),
+                    None => shard,
+                };
+                node.config = node.config.clone().with_shard(shard);
+            }
+            if let Some(url) = event_bridge {
+                let bridge = EventBridge::connect(&url).await?;
+                node.config = node.config.clone().with_event_bridge(bridge);
+            }
+            if let Some(task_lock) = task_lock {
+                node.config = node.config.clone().with_task_lock(task_lock);
+            }
+            if lazy_models {
+                let timeout = warmup_timeout
+                    .map(Duration::from_secs)
+                    .unwrap_or(ModelWarmup::DEFAULT_TIMEOUT);
+                node.config = node
+                    .config
+                    .clone()
+                    .with_model_warmup(ModelWarmup::new(timeout, node.workflows.ollama.clone()));
+            }
+            if ollama_auto_pull {
+                node.config = node
+                    .config
+                    .clone()
+                    .with_ollama_auto_pull(OllamaAutoPull::new(node.workflows.ollama.clone())?);
+            }
+            if vram_aware {
+                node.config = node
+                    .config
+                    .clone()
+                    .with_vram_probe(VramProbe::new(OllamaClient::new(&node.workflows.ollama)?)?);
+            }
+            if max_hourly_spend.is_some() || max_daily_spend.is_some() {
+                let api_budget = ApiBudget::new(max_hourly_spend, max_daily_spend);
+                node.config = node.config.clone().with_api_budget(api_budget);
+            }
+            // the flags take precedence over the caps within the env, which are parsed with the config
+            if max_gas_price.is_some() || max_daily_gas_spend.is_some() {
+                let gas_budget = GasBudget::new(max_gas_price, max_daily_gas_spend);
+                node.config = node.config.clone().with_gas_budget(gas_budget);
+            }
+            node.restore_gas_budget()?;
+
+            let mut settings = ReloadableSettings {
+                min_profit,
+                eth_price,
+                max_validation_cost_ratio,
+                queue_order,
+                preferred_protocols,
+                feed_prices: UsdPrices::default(),
+            };
+            // the model costs of the policies are in USD, so they need the price of ETH from the
+            // arguments or its feed, and the fees are converted with the price of the fee token
+            // from its feed if it is not WETH; the prices of the feeds are refreshed while serving
+            if settings.uses_feed_prices(&node) {
+                settings.feed_prices = node.get_usd_prices().await;
+            }
+            if settings.has_policy() && settings.eth_price.or(settings.feed_prices.eth).is_none() {
+                return Err(eyre::eyre!(
+                    "The price of ETH is required to account for the model costs with --min-profit or --max-validation-cost-ratio, provide it with --eth-price or PRICE_FEED."
+                ));
+            }
+            if settings.has_policy()
+                && node.config.token_price_feed.is_some()
+                && settings.feed_prices.token.is_none()
+            {
+                return Err(eyre::eyre!(
+                    "The price of the fee token could not be read from TOKEN_PRICE_FEED."
+                ));
+            }
+            settings.apply(&node);
+
+            // additional coordinators share everything except the contracts with the main node
+            let mut nodes = Vec::new();
+            for coordinator_address in coordinators {
+                let mut other = node.with_coordinator(coordinator_address).await?;
+                other.prepare_oracle(kinds.clone(), models.clone()).await?;
+                nodes.push(other);
+            }
+
+            // additional chains have their own provider & wallet, but share the rest with the main node
+            for chain_env in chain_envs {
+                let chain = DriaOracleConfig::new_from_env_file(&chain_env)?;
+                let config = node.config.clone().with_chain_of(chain);
+
+                let mut other = crate::DriaOracle::new(config).await?;
+                other.db = node.db.clone();
+                other.policy = node.policy.clone();
+                other.capacity = node.capacity.clone();
+                other.execution_times = node.execution_times.clone();
+                other.mining_stats = node.mining_stats.clone();
+                other.restore_gas_budget()?;
+                other.prepare_oracle(kinds.clone(), models.clone()).await?;
+                nodes.push(other);
+            }
+
+            // the remote signer responds on the main coordinator, where the wallet of this node only
+            // needs to be valid, as the additional coordinators & chains are not served by the signer
+            if let (Some(url), Some(token)) = (remote_signer, remote_signer_token) {
+                let signer = RemoteSigner::connect(url, token).await?;
+                log::info!(
+                    "Responding with the remote signer {} at {}",
+                    signer.address,
+                    signer.url
+                );
+                node.config = node.config.clone().with_remote_signer(signer);
+            }
+
+            // separate accounts handle their kind instead of the main wallet on the main coordinator,
+            // where each account keeps its own nonces as they are tracked per address
+            let accounts = [
+                (OracleKind::Generator, generator_key),
+                (OracleKind::Validator, validator_key),
+            ]
+            .into_iter()
+            .filter_map(|(kind, key)| key.map(|key| (kind, key)))
+            .collect::<Vec<_>>();
+            let mut account_nodes = Vec::new();
+            for (kind, key) in &accounts {
+                let signer = PrivateKeySigner::from_bytes(key)?;
+                log::info!("Handling {} tasks with {}", kind, signer.address());
+                let mut account = node.connect(EthereumWallet::from(signer));
+                account.prepare_oracle(vec![*kind], models.clone()).await?;
+                account_nodes.push(account);
+            }
+
+            // the main wallet handles the kinds that have no separate account, if any, where the
+            // kinds of the main wallet are its registrations if they are not given
+            let mut registered = Vec::new();
+            if kinds.is_empty() && !accounts.is_empty() {
+                for kind in [OracleKind::Generator, OracleKind::Validator] {
+                    if node.is_registered(kind).await? {
+                        registered.push(kind);
+                    }
+                }
+            }
+            let delegated = accounts.iter().map(|(kind, _)| *kind).collect::<Vec<_>>();
+            let main_kinds = main_kinds(kinds, registered, &delegated);
+            let serves_main = accounts.is_empty() || !main_kinds.is_empty();
+            if serves_main {
+                node.prepare_oracle(main_kinds, models).await?;
+            }
+            let serving = std::iter::once(&node)
+                .filter(|_| serves_main)
+                .chain(&account_nodes)
+                .chain(&nodes)
+                .collect::<Vec<_>>();
 
             if let Some(task_id) = task_id {
-                node.process_task_by_id(task_id).await?
+                // additional coordinators & chains do not serve a specific task
+                let main_accounts = usize::from(serves_main) + account_nodes.len();
+                for node in serving.iter().take(main_accounts) {
+                    node.process_task_by_id(task_id).await?
+                }
             } else {
-                // create a signal handler
+                // create a signal handler, which also reloads the settings on SIGHUP
+                let reloader = SettingsReloader::new(
+                    cli.env,
+                    settings,
+                    node.connect(node.config.wallet.clone()),
+                    cli.read_log_level().is_ok_and(|level| level.is_some()),
+                );
                 let termination_token = token.clone();
                 let termination_handle = tokio::spawn(async move {
-                    wait_for_termination(termination_token).await.unwrap();
+                    wait_for_termination(termination_token, Some(reloader))
+                        .await
+                        .unwrap();
                 });
 
-                // launch node
-                node.serve(from, to, token).await?;
+                // serve the gRPC API with a node of its own, so that it can outlive this scope
+                #[cfg(feature = "grpc")]
+                let grpc_handle = grpc_address
+                    .map(|address| {
+                        // requests of the service are made on behalf of its clients, without a prompt
+                        let mut grpc_node = node.connect(node.config.wallet.clone());
+                        grpc_node.config.confirm_spending = false;
+                        let mut service = crate::grpc::OracleService::new(Arc::new(grpc_node));
+                        if let Some(grpc_token) = grpc_token {
+                            service = service.with_token(grpc_token);
+                        }
+                        service.check_address(address)?;
+
+                        Ok::<_, eyre::Report>(tokio::spawn(service.serve(address, token.clone())))
+                    })
+                    .transpose()?;
+
+                // launch node, along with the ones for separate accounts and additional coordinators & chains,
+                // where a node that fails stops the others so that they drain their tasks as on termination
+                let (results, ()) = tokio::join!(
+                    join_all(serving.iter().map(|node| async {
+                        let result = node.serve(from, to, catch_up_workers, token.clone()).await;
+                        if result.is_err() {
+                            token.cancel();
+                        }
+                        result
+                    })),
+                    monitor_storage_balance(min_storage_balance, token.clone())
+                );
+                let result = results.into_iter().collect::<Result<Vec<_>>>();
+                log::info!("RPC usage: {}", node.rpc_limiter);
+                if let Some(api_budget) = &node.config.api_budget {
+                    log::info!("API spending: {}", api_budget);
+                }
+                if node.config.input_limits.rejected() > 0 {
+                    log::info!(
+                        "Skipped {} tasks that exceeded the input limits",
+                        node.config.input_limits.rejected()
+                    );
+                }
+
+                #[cfg(feature = "grpc")]
+                if let Some(grpc_handle) = grpc_handle {
+                    match grpc_handle.await {
+                        Ok(Err(e)) => log::error!("Error in gRPC server: {:#}", e),
+                        Err(e) => log::error!("Error in gRPC server: {}", e),
+                        Ok(Ok(())) => {}
+                    }
+                }
 
                 // wait for handle
                 if let Err(e) = termination_handle.await {
                     log::error!("Error in termination handler: {}", e);
                 }
+                result?;
             }
         }
         Commands::View { task_id, from, to } => {
@@ -90,6 +594,77 @@ pub async fn handle_command(command: Commands, mut node: crate::DriaOracle) -> R
                 .await?
             }
         }
+        Commands::RequestBatch { file, concurrency } => {
+            node.request_batch(&file, concurrency).await?
+        }
+        Commands::Loadtest {
+            tasks,
+            concurrency,
+            models,
+            difficulty,
+            protocol,
+            timeout,
+        } => {
+            node.load_test(
+                tasks,
+                concurrency,
+                models,
+                difficulty,
+                protocol,
+                Duration::from_secs(timeout),
+            )
+            .await?
+        }
+        Commands::Watch { task_id } => watch_until_completion(&node, task_id, None).await?,
+        Commands::Audit { task_id } => node.audit_task(task_id).await?,
+        Commands::Costs { from, to } => {
+            node.display_costs(
+                from.unwrap_or(BlockNumberOrTag::Earliest),
+                to.unwrap_or(BlockNumberOrTag::Latest),
+            )
+            .await?
+        }
+        Commands::RetryOutbox => {
+            let (sent, remaining) = node.retry_outbox().await?;
+            log::info!(
+                "Sent {} responses from the outbox, {} remaining.",
+                sent,
+                remaining
+            );
+        }
+        Commands::Stats { from, to } => {
+            node.display_stats(
+                from.unwrap_or(BlockNumberOrTag::Earliest),
+                to.unwrap_or(BlockNumberOrTag::Latest),
+            )
+            .await?
+        }
+        Commands::Peers { from, to } => {
+            if let Some(from) = from {
+                node.record_peer_responses(from, to.unwrap_or(BlockNumberOrTag::Latest))
+                    .await?;
+            }
+            node.display_peers()?
+        }
+        Commands::Db { command } => match command {
+            DbCommands::Backfill { from, to } => {
+                node.backfill_index(from, to.unwrap_or(BlockNumberOrTag::Latest))
+                    .await?
+            }
+            DbCommands::Verify => node.verify_index().await?,
+            DbCommands::Prune {
+                max_age_days,
+                max_table_size_mb,
+            } => {
+                let policy = RetentionPolicy::from_args(max_age_days, max_table_size_mb);
+                if policy.is_unbounded() {
+                    return Err(eyre::eyre!(
+                        "Nothing to prune, provide --max-age-days and/or --max-table-size-mb."
+                    ));
+                }
+                node.prune_db(&policy).await?
+            }
+        },
         Commands::Register { kinds } => {
             for kind in kinds {
                 node.register(kind).await?
@@ -101,6 +676,29 @@ pub async fn handle_command(command: Commands, mut node: crate::DriaOracle) -> R
             }
         }
         Commands::Registrations => node.display_registrations().await?,
+        Commands::Stakes => node.display_stakes().await?,
+        Commands::Whitelist { command } => match command {
+            WhitelistCommands::Status => node.display_whitelist_status().await?,
+            WhitelistCommands::Watch {
+                interval_secs,
+                webhook,
+            } => {
+                let token = CancellationToken::new();
+                let termination_token = token.clone();
+                let termination_handle = tokio::spawn(async move {
+                    wait_for_termination(termination_token, None).await.unwrap();
+                });
+
+                let result = node
+                    .watch_whitelist(Duration::from_secs(interval_secs), webhook, token.clone())
+                    .await;
+                token.cancel();
+                if let Err(e) = termination_handle.await {
+                    log::error!("Error in termination handler: {}", e);
+                }
+                result?
+            }
+        },
         Commands::Request {
             input,
             models,
@@ -108,37 +706,288 @@ pub async fn handle_command(command: Commands, mut node: crate::DriaOracle) -> R
             num_gens,
             num_vals,
             protocol,
+            wait,
+            wait_timeout,
+        } => {
+            let task_id = node
+                .request_task(&input, models, difficulty, num_gens, num_vals, protocol)
+                .await?;
+
+            if wait {
+                watch_until_completion(&node, task_id, wait_timeout.map(Duration::from_secs))
+                    .await?;
+            }
+        }
+        Commands::RequestWorkflow {
+            file,
+            models,
+            difficulty,
+            protocol,
+            num_gens,
+            num_vals,
+            wait,
+            wait_timeout,
         } => {
-            node.request_task(&input, models, difficulty, num_gens, num_vals, protocol)
+            let request = WorkflowRequestBuilder::from_file(&file)
                 .await?
+                .with_models(models)
+                .with_protocol(protocol)
+                .build()?
+                .with_difficulty(difficulty)
+                .with_generations(num_gens)
+                .with_validations(num_vals);
+            let task_id = node.send_request(request).await?;
+
+            if wait {
+                watch_until_completion(&node, task_id, wait_timeout.map(Duration::from_secs))
+                    .await?;
+            }
+        }
+        Commands::RequestChat {
+            content,
+            history_id,
+            models,
+            difficulty,
+            protocol,
+            num_gens,
+            num_vals,
+            skip_history_check,
+            wait,
+            wait_timeout,
+        } => {
+            let mut builder = ChatRequestBuilder::new(content)
+                .with_models(models)
+                .with_protocol(protocol);
+            if let Some(history_id) = history_id {
+                if !skip_history_check {
+                    node.check_chat_history(history_id).await?;
+                }
+                builder = builder.with_history(history_id);
+            }
+            let request = builder
+                .build()?
+                .with_difficulty(difficulty)
+                .with_generations(num_gens)
+                .with_validations(num_vals);
+            let task_id = node.send_request(request).await?;
+
+            if wait {
+                watch_until_completion(&node, task_id, wait_timeout.map(Duration::from_secs))
+                    .await?;
+            }
+        }
+        Commands::Index {
+            from,
+            protocol,
+            requester,
+            status,
+            list,
+            events,
+        } => {
+            let filter = IndexFilter {
+                protocol,
+                requester,
+                status: status.map(u8::from),
+            };
+            if events {
+                node.display_indexed_events()?;
+            } else if list {
+                node.display_indexed_tasks(&filter)?;
+            } else {
+                // create a signal handler
+                let token = CancellationToken::new();
+                let termination_token = token.clone();
+                let termination_handle = tokio::spawn(async move {
+                    wait_for_termination(termination_token, None).await.unwrap();
+                });
+
+                node.index_tasks(from, token).await?;
+
+                if let Err(e) = termination_handle.await {
+                    log::error!("Error in termination handler: {}", e);
+                }
+            }
+        }
+        Commands::Gateway {
+            address,
+            difficulty,
+            protocol,
+            num_gens,
+            num_vals,
+            timeout,
+        } => {
+            // requests of the gateway are made on behalf of its clients, without a prompt
+            node.config.confirm_spending = false;
+            let options = GatewayOptions {
+                difficulty,
+                num_gens,
+                num_vals,
+                protocol,
+                timeout: Duration::from_secs(timeout),
+            };
+
+            // create a signal handler
+            let token = CancellationToken::new();
+            let termination_token = token.clone();
+            let termination_handle = tokio::spawn(async move {
+                wait_for_termination(termination_token, None).await.unwrap();
+            });
+
+            node.serve_gateway(address, options, token).await?;
+
+            if let Err(e) = termination_handle.await {
+                log::error!("Error in termination handler: {}", e);
+            }
+        }
+        Commands::Signer { address, token } => {
+            // create a signal handler
+            let cancellation = CancellationToken::new();
+            let termination_token = cancellation.clone();
+            let termination_handle = tokio::spawn(async move {
+                wait_for_termination(termination_token, None).await.unwrap();
+            });
+
+            node.serve_signer(address, token, cancellation).await?;
+
+            if let Err(e) = termination_handle.await {
+                log::error!("Error in termination handler: {}", e);
+            }
+        }
+        Commands::Init { .. }
+        | Commands::Chains
+        | Commands::Completions { .. }
+        | Commands::Man
+        | Commands::Storage { .. }
+        | Commands::Benchmark { .. }
+        | Commands::LintWorkflow { .. }
+        | Commands::Presets { .. }
+        | Commands::Replay { .. }
+        | Commands::EstimateDifficulty { .. } => {
+            unreachable!("handled without a node")
         }
     };
 
     Ok(())
 }
 
+/// Returns the kinds that the main wallet serves, i.e. the given kinds (or its registrations if none are given)
+/// except the ones that are delegated to separate accounts.
+fn main_kinds(
+    kinds: Vec<OracleKind>,
+    registered: Vec<OracleKind>,
+    delegated: &[OracleKind],
+) -> Vec<OracleKind> {
+    let kinds = if kinds.is_empty() { registered } else { kinds };
+    kinds
+        .into_iter()
+        .filter(|kind| !delegated.contains(kind))
+        .collect()
+}
+
+/// Watches a task until it is completed, the timeout (if any) is reached, or a termination signal is received.
+async fn watch_until_completion(
+    node: &crate::DriaOracle,
+    task_id: alloy::primitives::U256,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let token = CancellationToken::new();
+
+    // create a signal handler
+    let termination_token = token.clone();
+    let termination_handle = tokio::spawn(async move {
+        wait_for_termination(termination_token, None).await.unwrap();
+    });
+
+    let watch = node.watch_task(task_id, token.clone());
+    let result = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, watch).await {
+            Ok(result) => result,
+            Err(_) => {
+                log::warn!(
+                    "Task {} did not complete within {}s, you can keep following it with `watch --task-id {}`.",
+                    task_id,
+                    timeout.as_secs(),
+                    task_id
+                );
+                Ok(())
+            }
+        },
+        None => watch.await,
+    };
+
+    // stop the signal handler as we are done watching
+    token.cancel();
+    if let Err(e) = termination_handle.await {
+        log::error!("Error in termination handler: {}", e);
+    }
+
+    result
+}
+
 /// Waits for various termination signals, and cancels the given token when the signal is received.
-async fn wait_for_termination(cancellation: CancellationToken) -> Result<()> {
+///
+/// If a reloader is given, the settings are reloaded on SIGHUP instead of terminating,
+/// and their prices are refreshed periodically.
+async fn wait_for_termination(
+    cancellation: CancellationToken,
+    reloader: Option<SettingsReloader>,
+) -> Result<()> {
     #[cfg(unix)]
     {
         use tokio::signal::unix::{signal, SignalKind};
         let mut sigterm = signal(SignalKind::terminate())?;
         let mut sigint = signal(SignalKind::interrupt())?;
-        tokio::select! {
-            _ = sigterm.recv() => log::warn!("Recieved SIGTERM"),
-            _ = sigint.recv() => log::warn!("Recieved SIGINT"),
-            _ = cancellation.cancelled() => {
-                // no need to wait if cancelled anyways
-                // although this is not likely to happen
-                return Ok(());
-            }
+        // SIGHUP & SIGUSR1 keep their default behavior (termination) unless the node is serving
+        let (mut sighup, mut sigusr1) = match reloader {
+            Some(_) => (
+                Some(signal(SignalKind::hangup())?),
+                Some(signal(SignalKind::user_defined1())?),
+            ),
+            None => (None, None),
         };
+        let mut price_refresh = tokio::time::interval(PRICE_REFRESH_INTERVAL);
+        price_refresh.tick().await;
+        loop {
+            tokio::select! {
+                _ = sigterm.recv() => log::warn!("Recieved SIGTERM"),
+                _ = sigint.recv() => log::warn!("Recieved SIGINT"),
+                Some(_) = async { sighup.as_mut()?.recv().await } => {
+                    log::warn!("Recieved SIGHUP, reloading settings");
+                    if let Some(reloader) = &reloader {
+                        reloader.reload();
+                    }
+                    continue;
+                }
+                _ = price_refresh.tick(), if reloader.is_some() => {
+                    if let Some(reloader) = &reloader {
+                        reloader.refresh_prices().await;
+                    }
+                    continue;
+                }
+                Some(_) = async { sigusr1.as_mut()?.recv().await } => {
+                    log::warn!("Recieved SIGUSR1, toggling maintenance mode");
+                    if let Some(reloader) = &reloader {
+                        let gate = &reloader.node.config.serve_gate;
+                        gate.set_maintenance(!gate.is_maintenance());
+                    }
+                    continue;
+                }
+                _ = cancellation.cancelled() => {
+                    // no need to wait if cancelled anyways
+                    // although this is not likely to happen
+                    return Ok(());
+                }
+            };
+
+            break;
+        }
 
         cancellation.cancel();
     }
 
     #[cfg(not(unix))]
     {
+        let _ = reloader;
         log::error!("No signal handling for this platform: {}", env::consts::OS);
         cancellation.cancel();
     }
@@ -147,3 +996,27 @@ async fn wait_for_termination(cancellation: CancellationToken) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_main_kinds() {
+        use OracleKind::{Generator, Validator};
+
+        // a main wallet that is registered as a generator only, with a separate validator
+        assert_eq!(
+            main_kinds(vec![], vec![Generator], &[Validator]),
+            vec![Generator]
+        );
+        // nothing is left for the main wallet if its only registration is delegated
+        assert!(main_kinds(vec![], vec![Validator], &[Validator]).is_empty());
+        // the given kinds take precedence over the registrations
+        assert_eq!(
+            main_kinds(vec![Generator, Validator], vec![Generator], &[Generator]),
+            vec![Validator]
+        );
+        assert_eq!(main_kinds(vec![Validator], vec![], &[]), vec![Validator]);
+    }
+}
@@ -1,9 +1,17 @@
-use alloy::{eips::BlockNumberOrTag, hex::FromHex, primitives::B256};
+use alloy::{
+    eips::BlockNumberOrTag,
+    hex::FromHex,
+    primitives::{Address, B256, U256},
+};
 use dkn_workflows::Model;
-use dria_oracle_contracts::OracleKind;
+use dria_oracle_contracts::{OracleKind, TaskStatus};
 use eyre::{eyre, Result};
 use reqwest::Url;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{GasBudget, GuardrailAction, QueueOrder, ScoreMap, ServeWindow, Shard, TaskLock};
 
 /// `value_parser` to parse a `str` to `OracleKind`.
 #[inline]
@@ -23,6 +31,12 @@ pub fn parse_secret_key(value: &str) -> Result<B256> {
     B256::from_hex(value).map_err(Into::into)
 }
 
+/// `value_parser` to parse a hexadecimal `str` to `Address`, with or without `0x` prefix.
+#[inline]
+pub fn parse_address(value: &str) -> Result<Address> {
+    Address::from_hex(value).map_err(Into::into)
+}
+
 /// `value_parser` to parse a `str` to `OracleKind`.
 ///
 /// This could be done with `ValueEnum` as well, but we prefer this to not
@@ -36,6 +50,139 @@ pub fn parse_oracle_kind(value: &str) -> Result<OracleKind> {
     }
 }
 
+/// `value_parser` to parse a `str` to `TaskStatus`, excluding `None`.
+#[inline]
+pub fn parse_task_status(value: &str) -> Result<TaskStatus> {
+    match value {
+        "pending-generation" => Ok(TaskStatus::PendingGeneration),
+        "pending-validation" => Ok(TaskStatus::PendingValidation),
+        "completed" => Ok(TaskStatus::Completed),
+        _ => Err(eyre!("Invalid TaskStatus: {}", value)),
+    }
+}
+
+/// `value_parser` to parse a `str` to `QueueOrder`.
+#[inline]
+pub fn parse_queue_order(value: &str) -> Result<QueueOrder> {
+    QueueOrder::from_str(value)
+}
+
+/// `value_parser` to parse a lock URL to `TaskLock`.
+#[inline]
+pub fn parse_task_lock(value: &str) -> Result<TaskLock> {
+    TaskLock::from_str(value)
+}
+
+/// `value_parser` to parse a `<index>/<count>` pair to `Shard`.
+#[inline]
+pub fn parse_shard(value: &str) -> Result<Shard> {
+    Shard::from_str(value)
+}
+
+/// `value_parser` to parse a `HH:MM-HH:MM` window in UTC to `ServeWindow`.
+#[inline]
+pub fn parse_serve_window(value: &str) -> Result<ServeWindow> {
+    ServeWindow::from_str(value)
+}
+
+/// `value_parser` to parse a `str` to `GuardrailAction`.
+#[inline]
+pub fn parse_guardrail_action(value: &str) -> Result<GuardrailAction> {
+    GuardrailAction::from_str(value)
+}
+
+/// `value_parser` to parse a gas price in gwei to wei.
+#[inline]
+pub fn parse_gas_price(value: &str) -> Result<u128> {
+    GasBudget::parse_gwei(value)
+}
+
+/// `value_parser` to parse a gas spending in ETH to wei.
+#[inline]
+pub fn parse_gas_spend(value: &str) -> Result<U256> {
+    GasBudget::parse_eth(value)
+}
+
+/// `value_parser` to parse an operator tag, which is a short printable name or URL.
+#[inline]
+pub fn parse_operator_tag(value: &str) -> Result<String> {
+    const MAX_OPERATOR_TAG_LEN: usize = 64;
+
+    let tag = value.trim();
+    if tag.is_empty() {
+        Err(eyre!("Operator tag can not be empty"))
+    } else if tag.chars().count() > MAX_OPERATOR_TAG_LEN {
+        Err(eyre!(
+            "Operator tag can be at most {} characters, got: {}",
+            MAX_OPERATOR_TAG_LEN,
+            tag.chars().count()
+        ))
+    } else if tag.chars().any(char::is_control) {
+        Err(eyre!("Operator tag can not have control characters"))
+    } else {
+        Ok(tag.to_string())
+    }
+}
+
+/// `value_parser` to parse a duration in seconds, or with a `s`, `m`, `h` or `d` suffix, e.g. `90m`.
+#[inline]
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let (amount, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let secs_per_unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(eyre!(
+                "Expected a duration like 30m, 12h or 7d, got: {}",
+                value
+            ))
+        }
+    };
+
+    Ok(Duration::from_secs(amount.parse::<u64>()? * secs_per_unit))
+}
+
+/// `value_parser` to parse a `<protocol>=<seconds>` pair to a protocol name & duration.
+#[inline]
+pub fn parse_protocol_budget(value: &str) -> Result<(String, Duration)> {
+    let (protocol, seconds) = value
+        .split_once('=')
+        .ok_or_else(|| eyre!("Expected <protocol>=<seconds>, got: {}", value))?;
+
+    Ok((protocol.to_string(), Duration::from_secs(seconds.parse()?)))
+}
+
+/// `value_parser` to parse a `str` to `ScoreMap`.
+#[inline]
+pub fn parse_score_map(value: &str) -> Result<ScoreMap> {
+    ScoreMap::from_str(value)
+}
+
+/// `value_parser` to parse a `<protocol>=<map>` pair to a protocol name & score map.
+#[inline]
+pub fn parse_protocol_score_map(value: &str) -> Result<(String, ScoreMap)> {
+    let (protocol, map) = value
+        .split_once('=')
+        .ok_or_else(|| eyre!("Expected <protocol>=<map>, got: {}", value))?;
+
+    Ok((protocol.to_string(), ScoreMap::from_str(map)?))
+}
+
+/// `value_parser` to parse a `<protocol-prefix>=<path>` pair to a protocol prefix & workflow file.
+#[inline]
+pub fn parse_workflow_preset(value: &str) -> Result<(String, PathBuf)> {
+    let (prefix, path) = value
+        .split_once('=')
+        .ok_or_else(|| eyre!("Expected <protocol-prefix>=<path>, got: {}", value))?;
+
+    Ok((prefix.to_string(), PathBuf::from(path)))
+}
+
 /// `value parser` to parse a `str` to `BlockNumberOrTag`
 /// where if it can be parsed as `u64`, we call `BlockNumberOrTag::from_u64`
 /// otherwise we call `BlockNumberOrTag::from_str`.
@@ -80,6 +227,61 @@ mod tests {
         assert_eq!(secret_key, B256::from_hex(hex_str).unwrap());
     }
 
+    #[test]
+    fn test_parse_address() {
+        let address_str = "0x13f977bde221b470d3ae055cde7e1f84debfe202";
+        let result = parse_address(address_str);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Address::from_hex(address_str).unwrap());
+
+        // without prefix
+        let result = parse_address(&address_str[2..]);
+        assert!(result.is_ok());
+
+        let result = parse_address("0x1234");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_protocol_budget() {
+        let (protocol, budget) = parse_protocol_budget("swan=300").unwrap();
+        assert_eq!(protocol, "swan");
+        assert_eq!(budget, Duration::from_secs(300));
+
+        assert!(parse_protocol_budget("swan").is_err());
+        assert!(parse_protocol_budget("swan=abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_age("15m").unwrap(), Duration::from_secs(15 * 60));
+        assert_eq!(
+            parse_age("2d").unwrap(),
+            Duration::from_secs(2 * 24 * 60 * 60)
+        );
+
+        assert!(parse_age("h").is_err());
+        assert!(parse_age("2w").is_err());
+    }
+
+    #[test]
+    fn test_parse_protocol_score_map() {
+        let (protocol, map) = parse_protocol_score_map("swan=1:1,2:128,3:255").unwrap();
+        assert_eq!(protocol, "swan");
+        assert_eq!(map.max(), 3);
+        assert!(parse_protocol_score_map("swan:1:1").is_err());
+    }
+
+    #[test]
+    fn test_parse_workflow_preset() {
+        let (prefix, path) = parse_workflow_preset("swan=./presets/swan.json").unwrap();
+        assert_eq!(prefix, "swan");
+        assert_eq!(path, PathBuf::from("./presets/swan.json"));
+
+        assert!(parse_workflow_preset("swan").is_err());
+    }
+
     #[test]
     fn test_parse_block_number_or_tag() {
         let block_number_str = "12345";
@@ -104,6 +306,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_queue_order() {
+        assert_eq!(parse_queue_order("fee").unwrap(), QueueOrder::Fee);
+        assert_eq!(parse_queue_order("age").unwrap(), QueueOrder::Age);
+        assert_eq!(
+            parse_queue_order("difficulty").unwrap(),
+            QueueOrder::Difficulty
+        );
+        assert!(parse_queue_order("invalid").is_err());
+    }
+
     #[test]
     fn test_parse_oracle_kind() {
         let kind_str = "generator";
@@ -122,4 +335,29 @@ mod tests {
         let result = parse_oracle_kind(kind_str);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_task_status() {
+        assert!(matches!(
+            parse_task_status("pending-validation"),
+            Ok(TaskStatus::PendingValidation)
+        ));
+        assert!(matches!(
+            parse_task_status("completed"),
+            Ok(TaskStatus::Completed)
+        ));
+        assert!(parse_task_status("none").is_err());
+    }
+
+    #[test]
+    fn test_parse_operator_tag() {
+        assert_eq!(parse_operator_tag(" acme-labs ").unwrap(), "acme-labs");
+        assert_eq!(
+            parse_operator_tag("https://oracle.example.com").unwrap(),
+            "https://oracle.example.com"
+        );
+        assert!(parse_operator_tag("  ").is_err());
+        assert!(parse_operator_tag("a\nb").is_err());
+        assert!(parse_operator_tag(&"a".repeat(65)).is_err());
+    }
 }
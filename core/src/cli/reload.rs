@@ -0,0 +1,257 @@
+use eyre::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    AcceptancePolicy, DriaOracle, PolicySet, ProfitPolicy, QueueOrder, QueuePolicy, UsdPrices,
+    ValidationPolicy,
+};
+
+/// Interval to read the prices of the policies from the price feeds again.
+pub(in crate::cli) const PRICE_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Settings of the serve command that can be changed while serving, without dropping the
+/// event subscriptions or the tasks in progress.
+///
+/// They are reloaded from the env file on SIGHUP, where each setting is read from its variable
+/// (see the flags of `serve`) and keeps its startup value if the variable is not set.
+#[derive(Debug, Clone, Default)]
+pub(in crate::cli) struct ReloadableSettings {
+    pub min_profit: Option<f64>,
+    pub eth_price: Option<f64>,
+    pub max_validation_cost_ratio: Option<f64>,
+    pub queue_order: Vec<QueueOrder>,
+    pub preferred_protocols: Vec<String>,
+    /// Latest prices read from the price feeds, which are refreshed instead of reloaded.
+    pub feed_prices: UsdPrices,
+}
+
+impl ReloadableSettings {
+    /// Applies the settings to the node, which also applies them to the nodes of other
+    /// coordinators & chains that share its policies.
+    pub fn apply(&self, node: &DriaOracle) {
+        // the given price of ETH takes precedence over its feed, and the fee token is assumed
+        // to be WETH unless it has a feed of its own
+        let eth_price = self.eth_price.or(self.feed_prices.eth).unwrap_or_default();
+        let token_price_eth = match self.feed_prices.token {
+            Some(token_price) if node.config.token_price_feed.is_some() && eth_price > 0.0 => {
+                token_price / eth_price
+            }
+            _ => 1.0,
+        };
+        let mut policies = PolicySet::default();
+        if let Some(min_profit) = self.min_profit {
+            policies = policies.with(
+                ProfitPolicy::new(min_profit, eth_price).with_token_price_eth(token_price_eth),
+            );
+        }
+        if let Some(max_cost_ratio) = self.max_validation_cost_ratio {
+            policies = policies.with(
+                ValidationPolicy::new(max_cost_ratio, eth_price)
+                    .with_token_price_eth(token_price_eth),
+            );
+        }
+        let policy =
+            (!policies.is_empty()).then(|| Arc::new(policies) as Arc<dyn AcceptancePolicy>);
+        node.policy.set(policy);
+
+        let mut queue_policy = QueuePolicy {
+            preferred_protocols: self.preferred_protocols.clone(),
+            ..Default::default()
+        };
+        if !self.queue_order.is_empty() {
+            queue_policy.order = self.queue_order.clone();
+        }
+        node.config.set_queue_policy(queue_policy);
+    }
+
+    /// Returns `true` if there is a policy, which needs the prices of ETH & the fee token.
+    pub fn has_policy(&self) -> bool {
+        self.min_profit.is_some() || self.max_validation_cost_ratio.is_some()
+    }
+
+    /// Returns `true` if the policies read any of their prices from the price feeds of the node.
+    pub fn uses_feed_prices(&self, node: &DriaOracle) -> bool {
+        self.has_policy() && (self.eth_price.is_none() || node.config.token_price_feed.is_some())
+    }
+
+    /// Returns the settings with the values of the given variables, where the variables
+    /// that are not set keep the values of these settings.
+    fn with_vars(&self, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |key: &str| var(key).filter(|value| !value.is_empty());
+        let mut settings = self.clone();
+
+        if let Some(min_profit) = var("MIN_PROFIT") {
+            settings.min_profit = Some(min_profit.parse().wrap_err("could not parse MIN_PROFIT")?);
+        }
+        if let Some(eth_price) = var("ETH_PRICE") {
+            settings.eth_price = Some(eth_price.parse().wrap_err("could not parse ETH_PRICE")?);
+        }
+        if let Some(ratio) = var("MAX_VALIDATION_COST_RATIO") {
+            settings.max_validation_cost_ratio = Some(
+                ratio
+                    .parse()
+                    .wrap_err("could not parse MAX_VALIDATION_COST_RATIO")?,
+            );
+        }
+        if let Some(queue_order) = var("QUEUE_ORDER") {
+            settings.queue_order = queue_order
+                .split(',')
+                .map(|order| QueueOrder::from_str(order.trim()))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(protocols) = var("PREFERRED_PROTOCOLS") {
+            settings.preferred_protocols =
+                protocols.split(',').map(|p| p.trim().to_string()).collect();
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Reloads the settings of a serving node from the env file, e.g. on SIGHUP, and refreshes
+/// the prices that they read from the price feeds.
+pub(in crate::cli) struct SettingsReloader {
+    /// Path to the env file.
+    pub env: PathBuf,
+    /// Settings at startup, which are used for the variables that are not set.
+    pub settings: ReloadableSettings,
+    /// The node to apply the settings to.
+    pub node: DriaOracle,
+    /// Whether the log level is given at startup, without which `RUST_LOG` is left as is.
+    pub reloads_log_level: bool,
+    /// Settings that are applied currently.
+    applied: Mutex<ReloadableSettings>,
+}
+
+impl SettingsReloader {
+    pub fn new(
+        env: PathBuf,
+        settings: ReloadableSettings,
+        node: DriaOracle,
+        reloads_log_level: bool,
+    ) -> Self {
+        Self {
+            env,
+            applied: Mutex::new(settings.clone()),
+            settings,
+            node,
+            reloads_log_level,
+        }
+    }
+
+    /// Reads the prices from the price feeds again and applies them, if the policies use them.
+    ///
+    /// A price that can not be read keeps its previous value.
+    pub async fn refresh_prices(&self) {
+        let uses_feed_prices = self
+            .applied
+            .lock()
+            .expect("lock poisoned")
+            .uses_feed_prices(&self.node);
+        if !uses_feed_prices {
+            return;
+        }
+
+        let prices = self.node.get_usd_prices().await;
+        let mut applied = self.applied.lock().expect("lock poisoned");
+        applied.feed_prices = UsdPrices {
+            eth: prices.eth.or(applied.feed_prices.eth),
+            token: prices.token.or(applied.feed_prices.token),
+        };
+        applied.apply(&self.node);
+        log::debug!(
+            "Refreshed the prices of the policies: {:?}",
+            applied.feed_prices
+        );
+    }
+
+    /// Reloads the settings, the workflow presets and the log level (`LOG_LEVEL`), where the latter
+    /// is only changed if it is given at startup as well.
+    ///
+    /// If anything is invalid, the error is logged and the current settings are kept.
+    pub fn reload(&self) {
+        if let Err(e) = self.try_reload() {
+            log::error!("Could not reload settings: {:#}", e);
+        }
+    }
+
+    fn try_reload(&self) -> Result<()> {
+        let vars = read_env_file(&self.env)?;
+        let var = |key: &str| vars.get(key).cloned();
+
+        // parse everything before applying, so that nothing changes if something is invalid
+        let mut settings = self.settings.with_vars(var)?;
+        let log_level = var("LOG_LEVEL")
+            .filter(|level| self.reloads_log_level && !level.is_empty())
+            .map(|level| log::LevelFilter::from_str(&level))
+            .transpose()
+            .wrap_err("could not parse LOG_LEVEL")?;
+
+        // the prices are kept as they were last read from the feeds
+        let mut applied = self.applied.lock().expect("lock poisoned");
+        settings.feed_prices = applied.feed_prices;
+        settings.apply(&self.node);
+        *applied = settings.clone();
+        drop(applied);
+        self.node.config.workflow_presets.reload()?;
+        if let Some(log_level) = log_level {
+            log::set_max_level(log_level);
+        }
+
+        log::info!(
+            "Reloaded settings from {}: {:?} (log level: {})",
+            self.env.display(),
+            settings,
+            log::max_level()
+        );
+
+        Ok(())
+    }
+}
+
+/// Reads the variables within the given env file, without changing the environment of the process.
+fn read_env_file(path: &Path) -> Result<HashMap<String, String>> {
+    dotenvy::from_path_iter(path)
+        .wrap_err_with(|| format!("could not read env file {}", path.display()))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .wrap_err_with(|| format!("could not parse env file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_with_vars() {
+        let startup = ReloadableSettings {
+            min_profit: Some(0.1),
+            eth_price: Some(3000.0),
+            ..Default::default()
+        };
+        let vars = HashMap::from([
+            ("MIN_PROFIT", "0.2"),
+            ("ETH_PRICE", ""),
+            ("MAX_VALIDATION_COST_RATIO", "0.5"),
+            ("QUEUE_ORDER", "age, fee"),
+            ("PREFERRED_PROTOCOLS", "swan,dria"),
+        ]);
+
+        let settings = startup
+            .with_vars(|key| vars.get(key).map(|v| v.to_string()))
+            .unwrap();
+        assert_eq!(settings.min_profit, Some(0.2));
+        assert_eq!(settings.eth_price, Some(3000.0));
+        assert_eq!(settings.max_validation_cost_ratio, Some(0.5));
+        assert_eq!(settings.queue_order, vec![QueueOrder::Age, QueueOrder::Fee]);
+        assert_eq!(settings.preferred_protocols, vec!["swan", "dria"]);
+
+        // invalid values are rejected
+        assert!(startup
+            .with_vars(|key| (key == "QUEUE_ORDER").then(|| "size".to_string()))
+            .is_err());
+    }
+}
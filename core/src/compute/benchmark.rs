@@ -0,0 +1,277 @@
+use dkn_workflows::Model;
+use futures_util::{stream, StreamExt};
+use std::time::{Duration, Instant};
+
+use super::{
+    estimate_tokens, execute_validations, execute_workflow_with_timedout_retries,
+    make_generation_workflow, RetryPolicy,
+};
+
+/// Generation prompts of the benchmark, covering short answers, reasoning, formatting & writing.
+const GENERATION_PROMPTS: [&str; 4] = [
+    "What is the result of 17 * 23? Answer with the number only.",
+    "Explain in two sentences why the sky is blue.",
+    "List three prime numbers greater than 50, separated by commas.",
+    "Write a haiku about the ocean.",
+];
+
+/// Validation cases of the benchmark, as `(instruction, correct response, incorrect response)`.
+const VALIDATION_CASES: [(&str, &str, &str); 3] = [
+    (
+        "What is the capital of France?",
+        "The capital of France is Paris.",
+        "The capital of France is Berlin.",
+    ),
+    ("What is 7 * 8?", "7 * 8 = 56.", "7 * 8 = 54."),
+    (
+        "Name a primary color of light.",
+        "Red is a primary color of light.",
+        "Apples are usually eaten raw.",
+    ),
+];
+
+/// A generation within the benchmark.
+#[derive(Debug, Clone)]
+pub struct GenerationSample {
+    /// Time spent to generate the output.
+    pub latency: Duration,
+    /// Estimated number of tokens of the output.
+    pub tokens: u64,
+    /// Final score of the output within `[1, 5]`, if it could be scored.
+    pub score: Option<u8>,
+}
+
+/// Report of a model benchmark, see [`benchmark_model`].
+#[derive(Debug, Clone)]
+pub struct ModelBenchmark {
+    /// The benchmarked model.
+    pub model: Model,
+    /// Number of generations executed at the same time.
+    pub concurrency: usize,
+    /// Successful generations.
+    pub samples: Vec<GenerationSample>,
+    /// Number of failed generations & validations.
+    pub failures: usize,
+    /// Total time spent on the generations.
+    pub elapsed: Duration,
+    /// Number of validation cases where the correct response is scored above the incorrect one.
+    pub validations_correct: usize,
+    /// Number of validation cases that were executed successfully.
+    pub validations_total: usize,
+}
+
+impl ModelBenchmark {
+    /// Returns the average latency of the generations.
+    pub fn average_latency(&self) -> Option<Duration> {
+        let total = self.samples.iter().map(|s| s.latency).sum::<Duration>();
+        (!self.samples.is_empty()).then(|| total / self.samples.len() as u32)
+    }
+
+    /// Returns the latency that 95% of the generations are within.
+    pub fn p95_latency(&self) -> Option<Duration> {
+        let mut latencies = self.samples.iter().map(|s| s.latency).collect::<Vec<_>>();
+        latencies.sort();
+        let idx = (latencies.len() as f64 * 0.95).ceil() as usize;
+        latencies.get(idx.saturating_sub(1)).copied()
+    }
+
+    /// Returns the number of output tokens generated per second, over all generations at once.
+    pub fn tokens_per_sec(&self) -> f64 {
+        let tokens = self.samples.iter().map(|s| s.tokens).sum::<u64>();
+        if self.elapsed.is_zero() {
+            0.0
+        } else {
+            tokens as f64 / self.elapsed.as_secs_f64()
+        }
+    }
+
+    /// Returns the average score of the generations that could be scored.
+    pub fn average_score(&self) -> Option<f64> {
+        let scores = self
+            .samples
+            .iter()
+            .filter_map(|s| s.score)
+            .collect::<Vec<_>>();
+        (!scores.is_empty())
+            .then(|| scores.iter().map(|s| *s as f64).sum::<f64>() / scores.len() as f64)
+    }
+
+    /// Returns the ratio of the validation cases where the correct response is scored higher.
+    pub fn validation_accuracy(&self) -> Option<f64> {
+        (self.validations_total > 0)
+            .then(|| self.validations_correct as f64 / self.validations_total as f64)
+    }
+}
+
+impl std::fmt::Display for ModelBenchmark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        write!(
+            f,
+            "{} (concurrency {}):\n  Generations: {} ({} failures)\n  Average latency: {}\n  p95 latency: {}\n  Throughput: {:.1} tokens/s\n  Average score: {}\n  Validation accuracy: {}",
+            self.model,
+            self.concurrency,
+            self.samples.len(),
+            self.failures,
+            or_dash(self.average_latency().map(|l| format!("{:.2?}", l))),
+            or_dash(self.p95_latency().map(|l| format!("{:.2?}", l))),
+            self.tokens_per_sec(),
+            or_dash(self.average_score().map(|s| format!("{:.2}", s))),
+            or_dash(self.validation_accuracy().map(|a| format!(
+                "{:.0}% ({}/{})",
+                a * 100.0,
+                self.validations_correct,
+                self.validations_total
+            ))),
+        )
+    }
+}
+
+/// Benchmarks a model with a built-in battery of prompts, which are each generated `rounds` times
+/// with up to `concurrency` generations at once.
+///
+/// Each output is scored with the validation workflow using the `judge` model, and the model is also
+/// asked to validate pairs of correct & incorrect responses to see whether it can tell them apart.
+/// Failures are counted within the report instead of stopping the benchmark.
+pub async fn benchmark_model(
+    model: Model,
+    judge: Model,
+    rounds: usize,
+    concurrency: usize,
+    policy: &RetryPolicy,
+) -> ModelBenchmark {
+    let prompts = (0..rounds.max(1)).flat_map(|_| GENERATION_PROMPTS);
+
+    // generations, at the given concurrency
+    let started_at = Instant::now();
+    let outputs = stream::iter(prompts)
+        .map(|prompt| {
+            let model = model.clone();
+            async move {
+                let (workflow, duration) = make_generation_workflow(prompt.to_string())?;
+                let generated_at = Instant::now();
+                let execution =
+                    execute_workflow_with_timedout_retries(&workflow, &[model], duration, policy)
+                        .await?;
+                eyre::Ok((prompt, execution.output, generated_at.elapsed()))
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    let elapsed = started_at.elapsed();
+
+    // rubric scores of the outputs
+    let mut samples = Vec::new();
+    let mut failures = 0;
+    for output in outputs {
+        let (prompt, output, latency) = match output {
+            Ok(output) => output,
+            Err(e) => {
+                log::warn!("Generation with {} failed: {:#}", model, e);
+                failures += 1;
+                continue;
+            }
+        };
+
+        let score = match execute_validations(
+            prompt.to_string(),
+            vec![output.clone()],
+            judge.clone(),
+            policy,
+        )
+        .await
+        {
+            Ok(results) => results.first().map(|r| r.final_score()),
+            Err(e) => {
+                log::warn!("Could not score the output with {}: {:#}", judge, e);
+                None
+            }
+        };
+        samples.push(GenerationSample {
+            latency,
+            tokens: estimate_tokens(&output),
+            score,
+        });
+    }
+
+    // validations, where the correct response should score higher
+    let (mut validations_correct, mut validations_total) = (0, 0);
+    for (instruction, correct, incorrect) in VALIDATION_CASES {
+        let generations = vec![correct.to_string(), incorrect.to_string()];
+        match execute_validations(instruction.to_string(), generations, model.clone(), policy).await
+        {
+            Ok(results) if results.len() == 2 => {
+                validations_total += 1;
+                if results[0].final_score() > results[1].final_score() {
+                    validations_correct += 1;
+                }
+            }
+            Ok(results) => {
+                log::warn!(
+                    "Validation with {} returned {} results for 2 responses",
+                    model,
+                    results.len()
+                );
+                failures += 1;
+            }
+            Err(e) => {
+                log::warn!("Validation with {} failed: {:#}", model, e);
+                failures += 1;
+            }
+        }
+    }
+
+    ModelBenchmark {
+        model,
+        concurrency,
+        samples,
+        failures,
+        elapsed,
+        validations_correct,
+        validations_total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_benchmark_report() {
+        let sample = |millis: u64, score: Option<u8>| GenerationSample {
+            latency: Duration::from_millis(millis),
+            tokens: 50,
+            score,
+        };
+        let mut report = ModelBenchmark {
+            model: Model::GPT4oMini,
+            concurrency: 2,
+            samples: vec![
+                sample(100, Some(5)),
+                sample(300, Some(4)),
+                sample(200, None),
+                sample(400, Some(3)),
+            ],
+            failures: 1,
+            elapsed: Duration::from_millis(500),
+            validations_correct: 2,
+            validations_total: 3,
+        };
+
+        assert_eq!(report.average_latency(), Some(Duration::from_millis(250)));
+        assert_eq!(report.p95_latency(), Some(Duration::from_millis(400)));
+        assert_eq!(report.tokens_per_sec(), 400.0);
+        assert_eq!(report.average_score(), Some(4.0));
+        assert!((report.validation_accuracy().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+        // nothing to report if everything has failed
+        report.samples.clear();
+        report.validations_total = 0;
+        assert_eq!(report.average_latency(), None);
+        assert_eq!(report.p95_latency(), None);
+        assert_eq!(report.average_score(), None);
+        assert_eq!(report.validation_accuracy(), None);
+        assert!(report.to_string().contains("Average latency: -"));
+    }
+}
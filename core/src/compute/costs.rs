@@ -0,0 +1,88 @@
+use dkn_workflows::Model;
+
+/// Average number of characters per token, a rough estimate that holds for English text
+/// across most tokenizers.
+pub(crate) const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimated token usage of a single workflow execution.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Estimates the token usage from the prompt and completion texts.
+    ///
+    /// Workflows do not report the actual token counts, so we approximate them from the text lengths.
+    pub fn estimate(prompt: &str, completion: &str) -> Self {
+        Self {
+            prompt_tokens: estimate_tokens(prompt),
+            completion_tokens: estimate_tokens(completion),
+        }
+    }
+
+    /// Returns the estimated provider cost in USD for this usage with the given model.
+    pub fn cost_usd(&self, model: &Model) -> f64 {
+        let (prompt_price, completion_price) = model_pricing(model);
+
+        (self.prompt_tokens as f64 * prompt_price
+            + self.completion_tokens as f64 * completion_price)
+            / 1_000_000.0
+    }
+}
+
+/// Estimates the number of tokens within a text.
+#[inline]
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+/// Returns the `(prompt, completion)` prices in USD per 1M tokens for a given model.
+///
+/// Models that are not listed here (e.g. local Ollama models) are considered free.
+pub fn model_pricing(model: &Model) -> (f64, f64) {
+    let name = model.to_string();
+
+    // order matters here, as some model names are prefixes of others
+    const PRICES: [(&str, (f64, f64)); 8] = [
+        ("gpt-4o-mini", (0.15, 0.6)),
+        ("gpt-4o", (2.5, 10.0)),
+        ("gpt-4-turbo", (10.0, 30.0)),
+        ("o1-mini", (3.0, 12.0)),
+        ("o1", (15.0, 60.0)),
+        ("gemini-1.5-flash", (0.075, 0.3)),
+        ("gemini-1.5-pro", (1.25, 5.0)),
+        ("gemini", (0.1, 0.4)),
+    ];
+
+    PRICES
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, prices)| *prices)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("abcdefghi"), 3);
+    }
+
+    #[test]
+    fn test_cost_usd() {
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 1_000_000,
+        };
+        assert!((usage.cost_usd(&Model::GPT4oMini) - 0.75).abs() < 1e-9);
+        assert!((usage.cost_usd(&Model::GPT4o) - 12.5).abs() < 1e-9);
+        assert_eq!(usage.cost_usd(&Model::Llama3_1_8B), 0.0);
+    }
+}
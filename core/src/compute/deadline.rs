@@ -0,0 +1,180 @@
+use dria_oracle_contracts::OracleKind;
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Initial estimate of the execution time of a task, before any task is handled.
+const DEFAULT_EXECUTION_ESTIMATE: Duration = Duration::from_secs(30);
+/// Weight of the latest execution time within the moving average.
+const SMOOTHING_FACTOR: f64 = 0.2;
+/// Additional execution budget for each level of difficulty, on top of the base budget.
+const BUDGET_PER_DIFFICULTY: Duration = Duration::from_secs(5);
+
+/// Moving averages of the execution times of handled tasks, per kind.
+///
+/// These are used to estimate whether a task can be finished before its deadline.
+#[derive(Debug)]
+pub struct ExecutionTimes {
+    generation: Mutex<Duration>,
+    validation: Mutex<Duration>,
+}
+
+impl Default for ExecutionTimes {
+    fn default() -> Self {
+        Self {
+            generation: Mutex::new(DEFAULT_EXECUTION_ESTIMATE),
+            validation: Mutex::new(DEFAULT_EXECUTION_ESTIMATE),
+        }
+    }
+}
+
+impl ExecutionTimes {
+    #[inline]
+    fn average(&self, kind: OracleKind) -> &Mutex<Duration> {
+        match kind {
+            OracleKind::Generator => &self.generation,
+            OracleKind::Validator => &self.validation,
+        }
+    }
+
+    /// Returns the estimated execution time of a task of the given kind.
+    pub fn estimate(&self, kind: OracleKind) -> Duration {
+        *self.average(kind).lock().expect("lock poisoned")
+    }
+
+    /// Records the execution time of a handled task.
+    pub fn record(&self, kind: OracleKind, elapsed: Duration) {
+        let mut average = self.average(kind).lock().expect("lock poisoned");
+        *average = average.mul_f64(1.0 - SMOOTHING_FACTOR) + elapsed.mul_f64(SMOOTHING_FACTOR);
+    }
+
+    /// Returns `true` if a task of the given kind that has been waiting for `age` can not be
+    /// finished before the `deadline`, based on the estimated execution time.
+    pub fn is_past_deadline(&self, kind: OracleKind, age: Duration, deadline: Duration) -> bool {
+        match deadline.checked_sub(age) {
+            Some(time_left) => time_left < self.estimate(kind),
+            None => true,
+        }
+    }
+}
+
+/// Execution budget of a single task, so that no task can occupy a worker for too long.
+///
+/// The budget grows with the difficulty of the task, unless its protocol has a budget of its own.
+#[derive(Debug, Clone)]
+pub struct TaskBudget {
+    /// Budget of a task with zero difficulty.
+    pub base: Duration,
+    /// Additional budget for each level of difficulty.
+    pub per_difficulty: Duration,
+    /// Budgets of specific protocols, which override the computed budget.
+    pub protocols: HashMap<String, Duration>,
+}
+
+impl TaskBudget {
+    /// Creates a budget with the given base, growing by a default amount per difficulty.
+    pub fn new(base: Duration) -> Self {
+        Self {
+            base,
+            per_difficulty: BUDGET_PER_DIFFICULTY,
+            protocols: HashMap::new(),
+        }
+    }
+
+    /// Sets the budget of the tasks with the given protocol.
+    pub fn with_protocol(mut self, protocol: impl Into<String>, budget: Duration) -> Self {
+        self.protocols.insert(protocol.into(), budget);
+        self
+    }
+
+    /// Returns the budget of a task with the given protocol & difficulty.
+    ///
+    /// Protocols are matched either exactly or by their name without the version,
+    /// e.g. a budget for `swan` applies to `swan/0.1.0` as well.
+    pub fn for_task(&self, protocol: &str, difficulty: u8) -> Duration {
+        let name = protocol.split('/').next().unwrap_or_default();
+        self.protocols
+            .get(protocol)
+            .or_else(|| self.protocols.get(name))
+            .copied()
+            .unwrap_or_else(|| {
+                self.base
+                    .saturating_add(self.per_difficulty * difficulty.into())
+            })
+    }
+}
+
+/// Awaits the given execution within the budget if there is one,
+/// returns an error if the budget is exceeded.
+pub(crate) async fn execute_within_budget<T>(
+    budget: Option<Duration>,
+    execution: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(budget) = budget else {
+        return execution.await;
+    };
+
+    tokio::time::timeout(budget, execution).await.map_err(|_| {
+        eyre!(
+            "Execution exceeded the task budget of {}s",
+            budget.as_secs()
+        )
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execution_times() {
+        let times = ExecutionTimes::default();
+        assert_eq!(
+            times.estimate(OracleKind::Generator),
+            DEFAULT_EXECUTION_ESTIMATE
+        );
+
+        times.record(OracleKind::Generator, Duration::from_secs(80));
+        assert_eq!(
+            times.estimate(OracleKind::Generator),
+            Duration::from_secs(40)
+        );
+        assert_eq!(
+            times.estimate(OracleKind::Validator),
+            DEFAULT_EXECUTION_ESTIMATE
+        );
+
+        let deadline = Duration::from_secs(60);
+        assert!(!times.is_past_deadline(OracleKind::Generator, Duration::from_secs(10), deadline));
+        assert!(times.is_past_deadline(OracleKind::Generator, Duration::from_secs(30), deadline));
+        assert!(times.is_past_deadline(OracleKind::Validator, Duration::from_secs(90), deadline));
+    }
+
+    #[test]
+    fn test_task_budget() {
+        let budget = TaskBudget::new(Duration::from_secs(60))
+            .with_protocol("swan", Duration::from_secs(300))
+            .with_protocol("other/1.0.0", Duration::from_secs(10));
+
+        assert_eq!(budget.for_task("dria/0.1.0", 0), Duration::from_secs(60));
+        assert_eq!(budget.for_task("dria/0.1.0", 2), Duration::from_secs(70));
+        assert_eq!(budget.for_task("swan/0.1.0", 2), Duration::from_secs(300));
+        assert_eq!(budget.for_task("other/1.0.0", 2), Duration::from_secs(10));
+        assert_eq!(budget.for_task("other/2.0.0", 0), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_execute_within_budget() {
+        let result = execute_within_budget(Some(Duration::from_millis(10)), async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        })
+        .await;
+        assert!(result.is_err());
+
+        let result = execute_within_budget(None, async { Ok(1) }).await;
+        assert_eq!(result.unwrap(), 1);
+    }
+}
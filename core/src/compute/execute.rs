@@ -1,55 +1,302 @@
 use core::time::Duration;
-use dkn_workflows::{ExecutionError, Executor, Model, ProgramMemory, Workflow};
-use eyre::Context;
+use dkn_workflows::{ExecutionError, Executor, Model, ModelProvider, ProgramMemory, Workflow};
 
-const NUM_RETRIES: usize = 4;
+use super::generation::HistoryCompression;
+use crate::DriaOracleError;
+
+/// Policy to retry the execution of a workflow.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts in total, across all models.
+    pub max_attempts: usize,
+    /// Number of failed attempts with a model before falling back to the next one.
+    pub attempts_per_model: usize,
+    /// Delay before the first retry, doubled for each retry after that.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            attempts_per_model: 2,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the given retry, starting from zero.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        self.initial_backoff
+            .saturating_mul(1u32 << retry.min(16))
+            .min(self.max_backoff)
+    }
+}
+
+/// Class of an execution error, which decides how it is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The execution did not finish in time.
+    Timeout,
+    /// The provider has rate-limited the requests, e.g. with status 429.
+    RateLimited,
+    /// The provider has failed, e.g. with status 5xx.
+    Server,
+    /// The provider has refused the credentials, e.g. with status 401, which is not retried.
+    Auth,
+    /// Any other workflow failure.
+    Other,
+}
+
+impl ErrorClass {
+    /// Classifies an error w.r.t its message, which contains the status code or reason given by the provider.
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let has_code = |codes: &[&str]| {
+            message
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|word| codes.contains(&word))
+        };
+        let has_phrase = |phrases: &[&str]| phrases.iter().any(|p| message.contains(p));
+
+        if has_code(&["401", "403"])
+            || has_phrase(&[
+                "unauthorized",
+                "forbidden",
+                "api key",
+                "api_key",
+                "authentication",
+            ])
+        {
+            Self::Auth
+        } else if has_code(&["429"]) || has_phrase(&["rate limit", "too many requests"]) {
+            Self::RateLimited
+        } else if has_code(&["500", "502", "503", "504"])
+            || has_phrase(&[
+                "internal server error",
+                "bad gateway",
+                "unavailable",
+                "overloaded",
+            ])
+        {
+            Self::Server
+        } else if has_phrase(&["timeout", "timed out"]) {
+            Self::Timeout
+        } else {
+            Self::Other
+        }
+    }
+
+    /// Returns `true` if the same model may succeed when retried.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, Self::Auth)
+    }
+
+    /// Returns `true` if the error is caused by the provider rather than the model,
+    /// so that other models of the same provider are likely to fail as well.
+    pub fn is_provider_error(&self) -> bool {
+        matches!(self, Self::RateLimited | Self::Server | Self::Auth)
+    }
+}
+
+impl std::fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Timeout => "timeout",
+            Self::RateLimited => "rate limited",
+            Self::Server => "server error",
+            Self::Auth => "auth error",
+            Self::Other => "failure",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A failed attempt of an execution.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FailedAttempt {
+    pub model: String,
+    pub class: ErrorClass,
+    pub error: String,
+}
+
+/// Output of a successful execution, along with the attempts that have failed before it.
+#[derive(Debug, Clone)]
+pub struct Execution {
+    pub output: String,
+    /// The model that has produced the output.
+    pub model: Model,
+    pub retries: Vec<FailedAttempt>,
+    /// Compression of the chat history that the output continues, if it has been compressed.
+    pub history: Option<HistoryCompression>,
+}
 
 /// A wrapper for executing a workflow with retries.
 ///
-/// - Creates an `Executor` with the given model.
+/// - Creates an `Executor` with the first of the given models.
 /// - Executes the given workflow with the executor over an empty memory.
-/// - If the execution fails due to timeout, retries up to 3 times with slightly increasing timeout durations.
+/// - If the execution fails due to timeout or a workflow failure, retries w.r.t the policy with
+///   an exponential backoff, falling back to the next model after repeated failures. Auth errors
+///   are not retried with the same model, and other errors are returned as is.
+/// - If the failures are caused by the provider (e.g. rate limits or server errors), the fallback
+///   skips the other models of the same provider when there is a model of another provider.
 pub async fn execute_workflow_with_timedout_retries(
     workflow: &Workflow,
-    model: Model,
+    models: &[Model],
     duration: Duration,
-) -> eyre::Result<String> {
-    let executor = Executor::new(model);
+    policy: &RetryPolicy,
+) -> Result<Execution, DriaOracleError> {
+    let mut retries = Vec::new();
+    let (mut model_idx, mut model_failures) = (0, 0);
+    for attempt in 0..policy.max_attempts.max(1) {
+        let model = models.get(model_idx).cloned().ok_or_else(|| {
+            DriaOracleError::Model("no models to execute the workflow with".to_string())
+        })?;
+        if attempt > 0 {
+            let backoff = policy.backoff(attempt - 1);
+            log::warn!(
+                "Retrying {}/{} with {} in {}ms",
+                attempt,
+                policy.max_attempts - 1,
+                model,
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+        }
 
-    let mut retries = 0;
-    while retries < NUM_RETRIES {
+        let executor = Executor::new(model.clone());
         let mut memory = ProgramMemory::new();
-        tokio::select! {
-            result = executor.execute(None, workflow, &mut memory) => {
-              if let Err(ExecutionError::WorkflowFailed(reason)) = result {
-                // handle Workflow failed errors with retries
-                log::warn!("Execution gave WorkflowFailed error with: {}", reason);
-                if retries < NUM_RETRIES {
-                  retries += 1;
-                  log::warn!("Retrying {}/{}", retries, NUM_RETRIES);
-                  continue;
+        let (class, error) = tokio::select! {
+            result = executor.execute(None, workflow, &mut memory) => match result {
+                Ok(output) => {
+                    return Ok(Execution {
+                        output,
+                        model,
+                        retries,
+                        history: None,
+                    })
                 }
-              } else {
-                return result.wrap_err("could not execute workflow");
-              }
+                // handle Workflow failed errors with retries
+                Err(ExecutionError::WorkflowFailed(reason)) => (ErrorClass::classify(&reason), reason),
+                // other errors are returned as is
+                Err(err) => return Err(DriaOracleError::Model(format!("could not execute workflow: {}", err))),
             },
             // normally the workflow has a timeout logic as well, but it doesnt work that well, and may get stuck
-            _ = tokio::time::sleep(duration) => {
-                // if we have retries left, log a warning and continue
-                // note that other errors will be returned as is
-                log::warn!("Execution timed out");
-                if retries < NUM_RETRIES {
-                  retries += 1;
-                  log::warn!("Retrying {}/{}", retries, NUM_RETRIES);
-                  continue;
-                }
-            }
+            _ = tokio::time::sleep(duration) => (ErrorClass::Timeout, "execution timed out".to_string()),
         };
+        log::warn!("Execution with {} failed ({}): {}", model, class, error);
+        retries.push(FailedAttempt {
+            model: model.to_string(),
+            class,
+            error,
+        });
+
+        // fall back to the next model after repeated failures, or right away if retrying is futile
+        model_failures += 1;
+        if !class.is_retryable() || model_failures >= policy.attempts_per_model {
+            if let Some(next_idx) = next_model_idx(models, model_idx, class) {
+                model_idx = next_idx;
+                model_failures = 0;
+                log::warn!("Falling back from {} to {}", model, models[model_idx]);
+            } else if !class.is_retryable() {
+                return Err(DriaOracleError::Model(format!(
+                    "Execution with {} failed ({}), not retrying",
+                    model, class
+                )));
+            }
+        }
     }
 
     // all retries failed
-    Err(eyre::eyre!(
-        "Execution failed after {} retries",
-        NUM_RETRIES
-    ))
+    Err(DriaOracleError::Model(format!(
+        "Execution failed after {} attempts",
+        retries.len()
+    )))
+}
+
+/// Returns the index of the model to fall back to after the model at `idx` has failed, if any.
+///
+/// For provider errors, a model of another provider is preferred over the next model.
+fn next_model_idx(models: &[Model], idx: usize, class: ErrorClass) -> Option<usize> {
+    let remaining = idx + 1..models.len();
+    if class.is_provider_error() {
+        let provider = ModelProvider::from(models[idx].clone());
+        let other_provider = remaining
+            .clone()
+            .find(|&i| ModelProvider::from(models[i].clone()) != provider);
+        if other_provider.is_some() {
+            return other_provider;
+        }
+    }
+
+    remaining.min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_class() {
+        assert_eq!(
+            ErrorClass::classify("OpenAI error: 401 Unauthorized"),
+            ErrorClass::Auth
+        );
+        assert_eq!(
+            ErrorClass::classify("Incorrect API key provided"),
+            ErrorClass::Auth
+        );
+        assert_eq!(
+            ErrorClass::classify("status: 429, Too Many Requests"),
+            ErrorClass::RateLimited
+        );
+        assert_eq!(
+            ErrorClass::classify("503 Service Unavailable"),
+            ErrorClass::Server
+        );
+        assert_eq!(
+            ErrorClass::classify("request timed out"),
+            ErrorClass::Timeout
+        );
+        // codes are matched as whole words only
+        assert_eq!(
+            ErrorClass::classify("max_tokens must be below 5000"),
+            ErrorClass::Other
+        );
+
+        assert!(!ErrorClass::Auth.is_retryable());
+        assert!(ErrorClass::RateLimited.is_retryable());
+    }
+
+    #[test]
+    fn test_next_model_idx() {
+        let models = [Model::GPT4o, Model::GPT4oMini, Model::Llama3_1_8B];
+
+        // a model failure falls back to the next model
+        assert_eq!(next_model_idx(&models, 0, ErrorClass::Timeout), Some(1));
+        // a provider failure skips the models of the same provider
+        assert_eq!(next_model_idx(&models, 0, ErrorClass::RateLimited), Some(2));
+        assert_eq!(next_model_idx(&models, 1, ErrorClass::Server), Some(2));
+        assert_eq!(next_model_idx(&models, 2, ErrorClass::Server), None);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(1), Duration::from_secs(2));
+        assert_eq!(policy.backoff(2), Duration::from_secs(4));
+        assert_eq!(policy.backoff(3), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
 }
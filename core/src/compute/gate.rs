@@ -0,0 +1,209 @@
+use eyre::{eyre, Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of minutes within a day.
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// A daily window of time in UTC, given as `HH:MM-HH:MM`, where a window that ends before it
+/// starts wraps around midnight, e.g. `22:00-06:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServeWindow {
+    /// Start of the window in minutes since midnight, inclusive.
+    start: u32,
+    /// End of the window in minutes since midnight, exclusive.
+    end: u32,
+}
+
+impl ServeWindow {
+    /// Returns `true` if the given minute of the day is within the window.
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+impl std::str::FromStr for ServeWindow {
+    type Err = eyre::Report;
+
+    /// Parses a window given as `HH:MM-HH:MM`, e.g. `09:00-17:30`.
+    fn from_str(s: &str) -> Result<Self> {
+        let parse_time = |time: &str| -> Result<u32> {
+            let (hours, minutes) = time
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| eyre!("Invalid time: {}, expected HH:MM", time))?;
+            let hours = hours.parse::<u32>().wrap_err("invalid hours")?;
+            let minutes = minutes.parse::<u32>().wrap_err("invalid minutes")?;
+            if hours > 23 || minutes > 59 {
+                return Err(eyre!("Invalid time: {}", time));
+            }
+
+            Ok(hours * 60 + minutes)
+        };
+
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| eyre!("Invalid serve window: {}, expected HH:MM-HH:MM", s))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end {
+            return Err(eyre!("Serve window {} is empty", s));
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+impl std::fmt::Display for ServeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+/// Decides whether new tasks are taken, by the serve windows & the maintenance mode of the node.
+///
+/// While the gate is closed, the tasks in progress are finished and the new ones are kept within the queue,
+/// so that a node can be drained before its host goes down without losing the rewards of half-done work.
+#[derive(Debug, Default)]
+pub struct ServeGate {
+    /// Windows of the day to take new tasks within, or any time if there are none.
+    windows: Vec<ServeWindow>,
+    /// Whether the node is in maintenance mode.
+    maintenance: AtomicBool,
+    /// Whether the gate was closed when it was last checked, to alert on its changes.
+    closed: AtomicBool,
+}
+
+impl ServeGate {
+    /// Creates a gate that takes new tasks within the given windows only.
+    pub fn new(windows: Vec<ServeWindow>) -> Self {
+        Self {
+            windows,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the windows of the day to take new tasks within.
+    pub fn windows(&self) -> &[ServeWindow] {
+        &self.windows
+    }
+
+    /// Returns `true` if the node is in maintenance mode.
+    pub fn is_maintenance(&self) -> bool {
+        self.maintenance.load(Ordering::Relaxed)
+    }
+
+    /// Enters or leaves the maintenance mode.
+    pub fn set_maintenance(&self, enabled: bool) {
+        if self.maintenance.swap(enabled, Ordering::Relaxed) == enabled {
+            return;
+        }
+
+        if enabled {
+            log::warn!("Entering maintenance mode, the tasks in progress are finished but no new tasks are taken.");
+        } else {
+            log::info!("Leaving maintenance mode, resuming the tasks.");
+        }
+    }
+
+    /// Returns `true` if the given minute of the day is within one of the windows, or if there are none.
+    pub fn is_within_windows(&self, minute: u32) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|w| w.contains(minute))
+    }
+
+    /// Returns `true` if new tasks can be taken now, i.e. the node is not in maintenance mode
+    /// and the time is within the serve windows.
+    ///
+    /// Alerts when the node goes out of its serve windows, and when it is within them again.
+    pub fn is_open(&self) -> bool {
+        let minute = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / 60
+            % MINUTES_PER_DAY as u64;
+        let within_windows = self.is_within_windows(minute as u32);
+
+        match (
+            self.closed.swap(!within_windows, Ordering::Relaxed),
+            within_windows,
+        ) {
+            (false, false) => log::info!(
+                "Out of the serve windows ({}), deferring the new tasks.",
+                self
+            ),
+            (true, true) => log::info!("Within the serve windows ({}), resuming the tasks.", self),
+            _ => {}
+        }
+
+        within_windows && !self.is_maintenance()
+    }
+}
+
+impl std::fmt::Display for ServeGate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let windows = self
+            .windows
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{} UTC", windows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_window() {
+        let window = "09:00-17:30".parse::<ServeWindow>().unwrap();
+        assert_eq!(window.to_string(), "09:00-17:30");
+        assert!(window.contains(9 * 60));
+        assert!(window.contains(17 * 60 + 29));
+        assert!(!window.contains(17 * 60 + 30));
+        assert!(!window.contains(8 * 60));
+
+        // wraps around midnight
+        let window = "22:00-06:00".parse::<ServeWindow>().unwrap();
+        assert!(window.contains(23 * 60));
+        assert!(window.contains(0));
+        assert!(!window.contains(6 * 60));
+        assert!(!window.contains(12 * 60));
+
+        assert!("09:00".parse::<ServeWindow>().is_err());
+        assert!("09:00-24:00".parse::<ServeWindow>().is_err());
+        assert!("09:00-09:00".parse::<ServeWindow>().is_err());
+    }
+
+    #[test]
+    fn test_serve_gate() {
+        let gate = ServeGate::default();
+        assert!(gate.is_within_windows(0));
+        assert!(gate.is_open());
+
+        gate.set_maintenance(true);
+        assert!(!gate.is_open());
+        gate.set_maintenance(false);
+        assert!(gate.is_open());
+
+        let gate = ServeGate::new(vec![
+            "01:00-02:00".parse().unwrap(),
+            "23:00-00:30".parse().unwrap(),
+        ]);
+        assert!(gate.is_within_windows(60));
+        assert!(gate.is_within_windows(10));
+        assert!(!gate.is_within_windows(12 * 60));
+    }
+}
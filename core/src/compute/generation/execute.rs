@@ -2,40 +2,53 @@ use std::time::Duration;
 
 use alloy::primitives::U256;
 use dkn_workflows::{MessageInput, Model};
-use eyre::{eyre, Context, Result};
+use eyre::{eyre, Result};
 
+use super::history::ChatHistory;
+use super::mock::MockModel;
 use super::request::GenerationRequest;
 use super::workflow::*;
 
-use crate::compute::execute_workflow_with_timedout_retries;
-use crate::compute::parse_downloadable;
+use crate::compute::{execute_workflow_with_timedout_retries, Execution, RetryPolicy};
 use crate::DriaOracle;
 
-/// Executes a request using the given model, and optionally a node.
-/// Returns the raw string output.
+/// Executes a request using the given models in order of preference, and optionally a node.
+///
+/// The retry policy of the node is used if given, otherwise the default policy.
+/// If the node has a [`MockModel`], its canned outputs are returned instead of executing the workflows.
+/// Returns the raw string output along with the model that has produced it.
 pub async fn execute_generation(
     request: &GenerationRequest,
-    model: Model,
+    models: &[Model],
     node: Option<&DriaOracle>,
-) -> Result<String> {
+) -> Result<Execution> {
     log::debug!(
-        "Executing {} generation request with: {}",
+        "Executing {} generation request with: {:?}",
         request.request_type(),
-        model
+        models
     );
+    let default_policy = RetryPolicy::default();
+    let policy = node.map_or(&default_policy, |node| &node.config.retry_policy);
+    let mock = node.and_then(|node| node.config.mock_model.as_ref());
 
     match request {
         // workflows are executed directly without any prompts
         // as we expect their memory to be pre-filled
         GenerationRequest::Workflow(workflow) => {
+            if let Some(mock) = mock {
+                return mock.execute(&serde_json::to_string(workflow)?, models);
+            }
             let duration = Duration::from_secs(workflow.get_config().max_time);
-            execute_workflow_with_timedout_retries(workflow, model, duration).await
+            Ok(execute_workflow_with_timedout_retries(workflow, models, duration, policy).await?)
         }
 
         // string requests are used with the generation workflow with a given prompt
         GenerationRequest::String(input) => {
+            if let Some(mock) = mock {
+                return mock.execute(input, models);
+            }
             let (workflow, duration) = make_generation_workflow(input.clone())?;
-            execute_workflow_with_timedout_retries(&workflow, model, duration).await
+            Ok(execute_workflow_with_timedout_retries(&workflow, models, duration, policy).await?)
         }
 
         // chat history requests are used with the chat workflow
@@ -43,7 +56,7 @@ pub async fn execute_generation(
         GenerationRequest::ChatHistory(chat_request) => {
             let mut history = if chat_request.history_id == 0 {
                 // if task id is zero, there is no prior history
-                Vec::new()
+                ChatHistory::default()
             } else if let Some(node) = node {
                 let history_id = U256::from(chat_request.history_id);
                 // if task id is non-zero, we need the node to get the history
@@ -54,45 +67,55 @@ pub async fn execute_generation(
                     ));
                 }
 
-                let history_task = node
-                    .coordinator
-                    .getBestResponse(history_id)
-                    .call()
-                    .await
-                    .wrap_err("could not get chat history task from contract")?
-                    ._0;
-
-                // parse it as chat history output
-                let history_str = parse_downloadable(&history_task.output).await?;
-
-                // if its a previous message array, we can parse it directly
-                if let Ok(messages) = serde_json::from_str::<Vec<MessageInput>>(&history_str) {
-                    messages
-                } else {
-                    // otherwise, we can fallback to fetching input manually and creating a new history on-the-fly
-                    let request = node.coordinator.requests(history_id).call().await?;
-                    let input = parse_downloadable(&request.input).await?;
-
-                    // create a new history with the input
-                    vec![
-                        MessageInput::new_user_message(input),
-                        MessageInput::new_assistant_message(history_str),
-                    ]
-                }
+                node.get_chat_history(chat_request.history_id).await?
             } else {
                 return Err(eyre!("node is required for chat history"));
             };
 
-            // prepare the workflow with chat history
-            let (workflow, duration) =
-                make_chat_workflow(history.clone(), chat_request.content.clone(), None, None)?;
-            let output = execute_workflow_with_timedout_retries(&workflow, model, duration).await?;
+            // older turns are summarized with the same models if the history is above the token budget
+            let history_policy = node
+                .map(|node| node.config.chat_history)
+                .unwrap_or_default();
+            let compression = history_policy
+                .compress(&mut history.messages, |prompt| async move {
+                    if mock.is_some() {
+                        return Err(eyre!("mock model does not summarize"));
+                    }
+                    let (workflow, duration) = make_generation_workflow(prompt)?;
+                    let execution =
+                        execute_workflow_with_timedout_retries(&workflow, models, duration, policy)
+                            .await?;
+                    Ok(execution.output)
+                })
+                .await;
 
-            // append user input to chat history
-            history.push(MessageInput::new_assistant_message(output));
+            // prepare the workflow with chat history
+            let (workflow, duration) = make_chat_workflow(
+                history.messages.clone(),
+                chat_request.content.clone(),
+                None,
+                None,
+            )?;
+            let mut execution = match mock {
+                Some(mock) => mock.execute(&chat_request.content, models)?,
+                None => {
+                    execute_workflow_with_timedout_retries(&workflow, models, duration, policy)
+                        .await?
+                }
+            };
 
-            // return the stringified output
-            serde_json::to_string(&history).wrap_err("could not serialize chat history")
+            // append the response to chat history
+            let new_messages = [MessageInput::new_assistant_message(execution.output)];
+
+            // return the stringified output, which is only the new messages if the history is incremental
+            execution.output = history_policy.output(
+                chat_request.history_id,
+                &history,
+                &new_messages,
+                compression.is_some(),
+            )?;
+            execution.history = compression;
+            Ok(execution)
         }
     }
 }
@@ -106,9 +129,10 @@ mod tests {
     async fn test_ollama_generation() {
         dotenvy::dotenv().unwrap();
         let request = GenerationRequest::String("What is the result of 2 + 2?".to_string());
-        let output = execute_generation(&request, Model::Llama3_1_8B, None)
+        let output = execute_generation(&request, &[Model::Llama3_1_8B], None)
             .await
-            .unwrap();
+            .unwrap()
+            .output;
 
         println!("Output:\n{}", output);
         assert!(output.contains('4'));
@@ -119,9 +143,10 @@ mod tests {
     async fn test_openai_generation() {
         dotenvy::dotenv().unwrap();
         let request = GenerationRequest::String("What is the result of 2 + 2?".to_string());
-        let output = execute_generation(&request, Model::GPT4Turbo, None)
+        let output = execute_generation(&request, &[Model::GPT4Turbo], None)
             .await
-            .unwrap();
+            .unwrap()
+            .output;
 
         println!("Output:\n{}", output);
         assert!(output.contains('4'));
@@ -139,9 +164,10 @@ mod tests {
         let request = GenerationRequest::try_parse_bytes(&request_bytes.into())
             .await
             .unwrap();
-        let output = execute_generation(&request, Model::GPT4Turbo, None)
+        let output = execute_generation(&request, &[Model::GPT4Turbo], None)
             .await
-            .unwrap();
+            .unwrap()
+            .output;
 
         println!("Output:\n{}", output);
         assert!(output.contains('4'));
@@ -157,9 +183,10 @@ mod tests {
         let request = GenerationRequest::try_parse_bytes(&contract_result.into())
             .await
             .unwrap();
-        let output = execute_generation(&request, Model::GPT4o, None)
+        let output = execute_generation(&request, &[Model::GPT4o], None)
             .await
-            .unwrap();
+            .unwrap()
+            .output;
 
         println!("{}", output);
     }
@@ -177,7 +204,7 @@ mod tests {
         let (workflow, _) =
             make_chat_workflow(Vec::new(), "What is 2+2".into(), Some(1), None).unwrap();
         let request = GenerationRequest::Workflow(workflow);
-        let result = execute_generation(&request, Model::ORDeepSeek2_5, None).await;
+        let result = execute_generation(&request, &[Model::ORDeepSeek2_5], None).await;
         assert!(result.is_err());
     }
 }
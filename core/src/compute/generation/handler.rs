@@ -1,12 +1,24 @@
-use crate::{compute::generation::execute::execute_generation, mine_nonce, DriaOracle};
+use crate::compute::{
+    exceeds_gas_ceiling, execute_within_budget, mine_nonce_blocking, parse_downloadable_within,
+    Execution, Stopwatch, TaskTimings, TokenUsage,
+};
+use crate::{
+    compute::generation::execute::execute_generation, DriaOracle, DriaOracleError, OutboxPayload,
+    TaskCost, TraceId,
+};
 use alloy::{
     primitives::{FixedBytes, U256},
     rpc::types::TransactionReceipt,
 };
-use dria_oracle_contracts::{bytes32_to_string, bytes_to_string};
+use dria_oracle_contracts::{
+    bytes32_to_string, check_nonce, check_response_size, OracleKind, TaskStatus,
+};
 use dria_oracle_storage::ArweaveStorage;
 use eyre::Result;
+use rand::Rng;
+use std::time::{Duration, Instant};
 
+use super::models::select_models;
 use super::postprocess::*;
 use super::request::GenerationRequest;
 
@@ -16,6 +28,12 @@ use super::request::GenerationRequest;
 ///    Contract will revert even if we dont do this check ourselves, but its better to provide the error here.
 ///
 /// 2. Then, we check if our models are compatible with the request. If not, we return an error.
+///
+/// 3. If guardrails are enabled, the input is screened before the execution and the output after it.
+///    A triggered guardrail is noted within the recorded costs, or the response is replaced by a refusal.
+///
+/// 4. After the output is generated, we check if the task has been filled by other generators in the meantime,
+///    and skip the response if so.
 pub async fn handle_generation(
     node: &DriaOracle,
     task_id: U256,
@@ -31,79 +49,346 @@ pub async fn handle_generation(
         return Ok(None);
     }
 
+    // wait a bit to reduce racing with other generators, if configured
+    if let Some(max_start_delay) = node.config.max_start_delay {
+        let delay = rand::thread_rng().gen_range(0..=max_start_delay.as_millis() as u64);
+        log::debug!("Waiting {}ms before handling task {}", delay, task_id);
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+
     // fetch the request from contract
     log::debug!("Fetching the task request");
-    let request = node.coordinator.requests(task_id).call().await?;
+    let request = node
+        .get_request_cached(task_id, TaskStatus::PendingGeneration)
+        .await?;
 
-    // choose model based on the request
+    // choose the models based on the request, skipping the task if none of them can be used
     log::debug!("Choosing model to use");
-    let models_string = bytes_to_string(&request.models)?;
-    let models_vec = models_string.split(',').map(|s| s.to_string()).collect();
-    let model = match node.workflows.get_any_matching_model(models_vec) {
-        Ok((_, model)) => model,
-        Err(e) => {
-            log::error!(
-                "No matching model found: {}, falling back to random model.",
-                e
-            );
-
-            node.workflows
-                .get_matching_model("*".to_string())
-                .expect("should return at least one model")
-                .1
-        }
+    let Some(models) = select_models(node, task_id, &request.models).await? else {
+        return Ok(None);
     };
-    log::debug!("Using model: {} from {}", model, models_string);
+
+    // the local models are reserved while the task runs, so that the other tasks do not count on evicting them
+    let vram_reservation = node
+        .config
+        .vram_probe
+        .as_ref()
+        .map(|vram_probe| vram_probe.reserve(&models));
 
     // parse protocol string early, in case it cannot be parsed
     let protocol_string = bytes32_to_string(&protocol)?;
 
     // execute task
     log::debug!("Executing the workflow");
-    let input = GenerationRequest::try_parse_bytes(&request.input).await?;
-    let output = execute_generation(&input, model, Some(node)).await?;
+    let mut stopwatch = Stopwatch::start();
+    let mut timings = TaskTimings::default();
+    let input_limits = &node.config.input_limits;
+    let Some(input_string) =
+        parse_downloadable_within(&request.input, input_limits.max_input_bytes).await?
+    else {
+        input_limits.record_rejection();
+        log::warn!(
+            "Skipping generation task {}: input size exceeds the limit {}",
+            task_id,
+            input_limits.max_input_bytes.unwrap_or_default()
+        );
+        return Ok(None);
+    };
+    timings.download_ms = stopwatch.lap();
+    if let Err(e) = input_limits.check_input(&input_string) {
+        log::warn!("Skipping generation task {}: {}", task_id, e);
+        return Ok(None);
+    }
+    let input_string = node.config.tool_sandbox.sandbox_input(input_string).await?;
+    let mut input = GenerationRequest::try_parse_string(input_string.clone()).await;
+    if let GenerationRequest::String(text) = &input {
+        if let Some(workflow) = node
+            .config
+            .workflow_presets
+            .workflow_for(&protocol_string, text)?
+        {
+            log::debug!("Using the workflow preset of {}", protocol_string);
+            input = GenerationRequest::Workflow(workflow);
+        }
+    }
+    timings.parse_ms = stopwatch.lap();
+    let guardrails = node.config.guardrails.as_ref();
+    let mut guardrail = guardrails.and_then(|g| g.check_input(&input_string));
+    let budget = node
+        .config
+        .task_budget
+        .as_ref()
+        .map(|b| b.for_task(&protocol_string, request.parameters.difficulty));
+    let budget_started_at = Instant::now();
+    let execution = match &guardrail {
+        // no need to execute the workflow if we are going to refuse anyways
+        Some(decision) if decision.is_refusal() => Execution {
+            output: String::new(),
+            model: models[0].clone(),
+            retries: Vec::new(),
+            history: None,
+        },
+        _ => execute_within_budget(budget, execute_generation(&input, &models, Some(node))).await?,
+    };
+
+    // self-check the output, and regenerate once if it scores poorly within what is left of the budget
+    let (execution, self_check_score, discarded) = match (&guardrail, &node.config.self_check) {
+        (None, Some(self_check)) => {
+            let checked = self_check
+                .check(
+                    &protocol_string,
+                    &input_string,
+                    execution,
+                    &node.config.retry_policy,
+                    node.config.api_budget.as_deref(),
+                    || {
+                        let remaining =
+                            budget.map(|b| b.saturating_sub(budget_started_at.elapsed()));
+                        execute_within_budget(
+                            remaining,
+                            execute_generation(&input, &models, Some(node)),
+                        )
+                    },
+                )
+                .await;
+            (checked.execution, checked.score, checked.discarded)
+        }
+        _ => (execution, None, None),
+    };
+    timings.model_ms = stopwatch.lap();
+    drop(vram_reservation);
+    let usage = match &guardrail {
+        Some(decision) if decision.is_refusal() => TokenUsage::default(),
+        _ => TokenUsage::estimate(&input_string, &execution.output),
+    };
+    // the discarded execution of a self-check is paid for as well
+    let discarded_usage = discarded.as_ref().map(|discarded| {
+        (
+            discarded.model.clone(),
+            TokenUsage::estimate(&input_string, &discarded.output),
+        )
+    });
+    let (output, model) = (execution.output, execution.model);
+    if let Some(api_budget) = &node.config.api_budget {
+        api_budget.record(&model, usage.cost_usd(&model));
+        if let Some((discarded_model, discarded_usage)) = &discarded_usage {
+            api_budget.record(discarded_model, discarded_usage.cost_usd(discarded_model));
+        }
+    }
+    let substituted = (model != models[0]).then(|| models[0].clone());
+    if let Some(chosen) = &substituted {
+        log::info!(
+            "Task {} is generated with {} instead of {} due to failures.",
+            task_id,
+            model,
+            chosen
+        );
+    }
     log::debug!("Output: {}", output);
 
-    // post-processing
+    // screen the output, unless the input has triggered a guardrail already
+    if guardrail.is_none() {
+        guardrail = guardrails.and_then(|g| g.check_output(&output));
+    }
+    if let Some(decision) = &guardrail {
+        log::warn!(
+            "Guardrail triggered for task {} by its {}: \"{}\" ({})",
+            task_id,
+            decision.stage,
+            decision.pattern,
+            decision.action
+        );
+    }
+
+    // post-processing, where a refusal is responded as is
     log::debug!(
         "Post-processing the output for protocol: {}",
         protocol_string
     );
-    let (output, metadata, use_storage) =
-        match protocol_string.split('/').next().unwrap_or_default() {
-            SwanPurchasePostProcessor::PROTOCOL => {
-                SwanPurchasePostProcessor::new("<shop_list>", "</shop_list>").post_process(output)
-            }
-            _ => IdentityPostProcessor.post_process(output),
-        }?;
-
-    // uploading to storage
-    let arweave = ArweaveStorage::new_from_env()?;
-    let output = if use_storage {
-        log::debug!("Uploading output to storage");
-        arweave.put_if_large(output).await?
-    } else {
-        log::debug!("Not uploading output to storage");
-        output
+    let (output, metadata, use_storage) = match &guardrail {
+        Some(decision) if decision.is_refusal() => (decision.refusal(), Default::default(), false),
+        _ => post_process_protocol(&protocol_string, output)?,
     };
-    log::debug!("Uploading metadata to storage");
-    let metadata = arweave.put_if_large(metadata).await?;
+    timings.post_process_ms = stopwatch.lap();
+
+    // note the failed attempts, the model substitution, the guardrail decision, the self-check score
+    // & the history compression within the local costs, as the metadata is consumed by the validators
+    let mut notes = serde_json::json!({ "retries": execution.retries });
+    if let Some(chosen) = substituted {
+        notes["substitution"] = serde_json::json!({
+            "from": chosen.to_string(),
+            "to": model.to_string(),
+        });
+    }
+    if let Some(decision) = guardrail {
+        notes["guardrail"] = serde_json::to_value(decision)?;
+    }
+    if let Some(score) = self_check_score {
+        notes["self_check"] = serde_json::json!({ "score": score });
+    }
+    if let Some((discarded_model, discarded_usage)) = &discarded_usage {
+        notes["self_check"]["discarded"] = serde_json::json!({
+            "model": discarded_model.to_string(),
+            "prompt_tokens": discarded_usage.prompt_tokens,
+            "completion_tokens": discarded_usage.completion_tokens,
+            "provider_cost_usd": discarded_usage.cost_usd(discarded_model),
+        });
+    }
+    if let Some(compression) = &execution.history {
+        notes["history_compression"] = serde_json::to_value(compression)?;
+    }
 
-    // mine nonce
+    // check if the task has been filled while we were generating
+    if is_generation_filled(node, task_id).await? {
+        log::info!(
+            "Task {} has been filled by other generators, skipping.",
+            task_id
+        );
+        return Ok(None);
+    }
+
+    // mine nonce, where the check above is not counted as a stage
+    let mut stopwatch = Stopwatch::start();
     log::debug!("Mining nonce for task");
-    let nonce = mine_nonce(
+    let mined = mine_nonce_blocking(
+        request.parameters.difficulty,
+        request.requester,
+        node.address(),
+        request.input.clone(),
+        task_id,
+    )
+    .await?;
+    node.record_mining(
+        task_id,
+        OracleKind::Generator,
         request.parameters.difficulty,
+        &mined,
+    )
+    .await;
+    let nonce = mined.nonce;
+    timings.mining_ms = stopwatch.lap();
+
+    // uploading to storage, where the payloads of a response that would exceed the gas ceiling are
+    // uploaded regardless of their size, except for the outputs that the protocol needs on-chain
+    let mut arweave = ArweaveStorage::new_from_env().map_err(DriaOracleError::config)?;
+    let response = node
+        .coordinator
+        .respond(task_id, nonce, output.clone(), metadata.clone());
+    if exceeds_gas_ceiling(node, task_id, &response, &[&output, &metadata]).await {
+        arweave = arweave.with_upload_byte_limit(0);
+    }
+    let mut receipts = Vec::new();
+    let (output, metadata) = if arweave.has_encryption() && !metadata.is_empty() {
+        // metadata is encrypted on its own, while the output stays public for the contract
+        let output = if use_storage {
+            log::debug!("Uploading output to storage");
+            let (output, receipt) = arweave
+                .put_if_large_with_receipt(output)
+                .await
+                .map_err(DriaOracleError::storage)?;
+            receipts.extend(receipt);
+            output
+        } else {
+            output
+        };
+        log::debug!("Uploading encrypted metadata to storage");
+        let (metadata, receipt) = arweave
+            .put_encrypted_with_receipt(metadata)
+            .await
+            .map_err(DriaOracleError::storage)?;
+        receipts.push(receipt);
+        (output, metadata)
+    } else if use_storage {
+        // output & metadata are bundled into a single upload if both are large
+        log::debug!("Uploading output & metadata to storage");
+        let ([output, metadata], receipt) = arweave
+            .put_all_if_large([("output", output), ("metadata", metadata)])
+            .await
+            .map_err(DriaOracleError::storage)?;
+        receipts.extend(receipt);
+        (output, metadata)
+    } else {
+        log::debug!("Uploading metadata to storage");
+        let (metadata, receipt) = arweave
+            .put_if_large_with_receipt(metadata)
+            .await
+            .map_err(DriaOracleError::storage)?;
+        receipts.extend(receipt);
+        (output, metadata)
+    };
+
+    // catch the errors that the coordinator would revert with before broadcasting
+    check_nonce(
+        &task_id,
+        request.parameters.difficulty,
+        &request.input,
         &request.requester,
         &node.address(),
-        &request.input,
-        &task_id,
-    )
-    .nonce;
+        &nonce,
+    )?;
+    check_response_size(&task_id, &[&output, &metadata])?;
+
+    // the response is not sent before its uploads are retrievable
+    let payload = OutboxPayload::Generator {
+        output: output.clone(),
+    };
+    node.verify_uploads(&arweave, task_id, &payload, &metadata, nonce, receipts)
+        .await?;
+    timings.upload_ms = stopwatch.lap();
 
     // respond
     log::debug!("Responding with generation");
     let tx_receipt = node
         .respond_generation(task_id, output, metadata, nonce)
         .await?;
+    timings.tx_ms = stopwatch.lap();
+    log::debug!("Timings of task {}: {:?}", task_id, timings);
+
+    // record costs
+    let cost = TaskCost {
+        scope: node.response_scope(),
+        task_id,
+        block_number: tx_receipt.block_number.unwrap_or_default(),
+        kind: OracleKind::Generator.to_string(),
+        protocol: protocol_string,
+        model: model.to_string(),
+        prompt_tokens: usage.prompt_tokens
+            + discarded_usage.as_ref().map_or(0, |(_, u)| u.prompt_tokens),
+        completion_tokens: usage.completion_tokens
+            + discarded_usage
+                .as_ref()
+                .map_or(0, |(_, u)| u.completion_tokens),
+        provider_cost_usd: usage.cost_usd(&model)
+            + discarded_usage
+                .as_ref()
+                .map_or(0.0, |(model, u)| u.cost_usd(model)),
+        gas_fee: U256::from(tx_receipt.gas_used) * U256::from(tx_receipt.effective_gas_price),
+        reward: request.generatorFee,
+        timings,
+        notes,
+        trace_id: TraceId::current(),
+    };
+    if let Err(e) = node
+        .db
+        .write_blocking(move |db| db.insert_cost(&cost))
+        .await
+    {
+        log::warn!("Could not record costs of task {}: {:#}", task_id, e);
+    }
+
     Ok(Some(tx_receipt))
 }
+
+/// Returns `true` if the task is no longer pending generation, or has as many generations as requested.
+async fn is_generation_filled(node: &DriaOracle, task_id: U256) -> Result<bool> {
+    let request = node.coordinator.requests(task_id).call().await?;
+    if !matches!(
+        TaskStatus::try_from(request.status)?,
+        TaskStatus::PendingGeneration
+    ) {
+        return Ok(true);
+    }
+
+    let responses = node.coordinator.getResponses(task_id).call().await?._0;
+    Ok(responses.len() as u64 >= request.parameters.numGenerations.to::<u64>())
+}
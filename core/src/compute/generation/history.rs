@@ -0,0 +1,523 @@
+use alloy::primitives::U256;
+use dkn_workflows::MessageInput;
+use eyre::{eyre, Context, Result};
+use serde_json::Value;
+use std::future::Future;
+
+use crate::compute::{estimate_tokens, parse_downloadable, CHARS_PER_TOKEN};
+use crate::DriaOracle;
+
+/// Maximum number of deltas that are followed to rebuild a history, after which the full history is uploaded.
+const MAX_DELTA_DEPTH: usize = 32;
+
+/// Share of the token budget that is used by the summary of the older turns, as its divisor.
+const SUMMARY_BUDGET_DIVISOR: u64 = 4;
+
+/// Maximum number of characters of each message within the summary, when it is made without the model.
+const SUMMARY_LINE_CHARS: usize = 200;
+
+/// Prefix of the message that summarizes the older turns of a compressed history.
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:";
+
+/// Version of the [`HistoryDelta`] format, noted within each delta under `history_delta` so that
+/// a delta is told apart from the other outputs, and a newer format is not misread.
+pub const HISTORY_DELTA_VERSION: u64 = 1;
+
+/// How the chat histories are compressed before they are used & uploaded again.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChatHistoryPolicy {
+    /// Estimated tokens that a history can have, above which its older turns are replaced by a summary.
+    pub max_tokens: Option<u64>,
+    /// Whether to upload the new messages along with the task of the history, instead of the whole history.
+    pub incremental: bool,
+}
+
+/// Compression of a chat history, noted within the costs of the task.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct HistoryCompression {
+    /// Number of the older messages that are replaced by the summary.
+    pub summarized_messages: usize,
+    /// Estimated tokens of the messages that are replaced.
+    pub summarized_tokens: u64,
+    /// Estimated tokens of the summary.
+    pub summary_tokens: u64,
+    /// Whether the summary is made of the beginnings of the messages, as the model could not summarize them.
+    pub truncated: bool,
+}
+
+/// The new messages of a chat, uploaded along with the task of the history that they continue.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HistoryDelta {
+    /// Version of the delta format, see [`HISTORY_DELTA_VERSION`].
+    #[serde(rename = "history_delta")]
+    pub version: u64,
+    /// Task id of the history that is continued.
+    pub parent_id: usize,
+    /// Number of deltas up to the last full history, including this one.
+    pub depth: usize,
+    /// Messages that are added to the history.
+    pub messages: Vec<MessageInput>,
+}
+
+/// A chat history, along with the number of deltas that it is rebuilt from.
+#[derive(Debug, Clone, Default)]
+pub struct ChatHistory {
+    pub messages: Vec<MessageInput>,
+    pub depth: usize,
+}
+
+/// Output of a task that a chat continues.
+#[derive(Debug)]
+enum ChatOutput {
+    /// A whole message history.
+    History(Vec<MessageInput>),
+    /// New messages that continue the history of another task.
+    Delta(HistoryDelta),
+    /// Any other output, e.g. of a plain generation.
+    Other(String),
+}
+
+impl ChatOutput {
+    /// Parses the output of a task, where a delta of an unknown version is an error.
+    fn parse(output: String) -> Result<Self> {
+        if let Ok(messages) = serde_json::from_str::<Vec<MessageInput>>(&output) {
+            return Ok(Self::History(messages));
+        }
+
+        let version = serde_json::from_str::<Value>(&output)
+            .ok()
+            .and_then(|value| value.get("history_delta").cloned());
+        match version {
+            Some(version) if version.as_u64() == Some(HISTORY_DELTA_VERSION) => {
+                let delta =
+                    serde_json::from_str(&output).wrap_err("could not parse history delta")?;
+                Ok(Self::Delta(delta))
+            }
+            Some(version) => Err(eyre!(
+                "history delta version {} is not supported, please update the node",
+                version
+            )),
+            None => Ok(Self::Other(output)),
+        }
+    }
+}
+
+impl ChatHistoryPolicy {
+    /// Replaces the older messages of the history with a summary if it is above the token budget,
+    /// such that the most recent messages that fit within the rest of the budget are kept as they are.
+    ///
+    /// The older messages are summarized by `summarize`, which is given a prompt to summarize them within
+    /// the budget. If it fails, the summary is made of the beginning of each message instead. The summary
+    /// is added as a system message, so that it is not taken for a turn of the user.
+    ///
+    /// Returns the compression that has been made, if any.
+    pub async fn compress<F, Fut>(
+        &self,
+        history: &mut Vec<MessageInput>,
+        summarize: F,
+    ) -> Option<HistoryCompression>
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        let max_tokens = self.max_tokens?;
+        let tokens = history.iter().map(message_tokens).collect::<Vec<_>>();
+        if tokens.iter().sum::<u64>() <= max_tokens {
+            return None;
+        }
+
+        let summary_budget = max_tokens / SUMMARY_BUDGET_DIVISOR;
+        let mut kept_tokens = 0;
+        let kept = tokens
+            .iter()
+            .rev()
+            .take_while(|t| {
+                kept_tokens += **t;
+                kept_tokens <= max_tokens - summary_budget
+            })
+            .count();
+
+        let summarized = history.drain(..history.len() - kept).collect::<Vec<_>>();
+        let (summary, truncated) =
+            match summarize(summary_prompt(&summarized, summary_budget)).await {
+                Ok(summary) if !summary.trim().is_empty() => (
+                    format!(
+                        "{}\n{}",
+                        SUMMARY_PREFIX,
+                        take_tokens(summary.trim(), summary_budget)
+                    ),
+                    false,
+                ),
+                Ok(_) => {
+                    log::warn!("Could not summarize the chat history: the summary is empty");
+                    (truncated_summary(&summarized, summary_budget), true)
+                }
+                Err(e) => {
+                    log::warn!("Could not summarize the chat history: {:#}", e);
+                    (truncated_summary(&summarized, summary_budget), true)
+                }
+            };
+        let compression = HistoryCompression {
+            summarized_messages: summarized.len(),
+            summarized_tokens: tokens[..summarized.len()].iter().sum(),
+            summary_tokens: estimate_tokens(&summary),
+            truncated,
+        };
+        history.insert(0, system_message(summary));
+
+        Some(compression)
+    }
+
+    /// Returns the output to upload for a chat that continues the given history with the new messages.
+    ///
+    /// The new messages alone are uploaded if the history is incremental, unless the history has been
+    /// compressed or it is rebuilt from too many deltas, in which case the whole history is uploaded.
+    pub fn output(
+        &self,
+        history_id: usize,
+        history: &ChatHistory,
+        new_messages: &[MessageInput],
+        compressed: bool,
+    ) -> Result<String> {
+        let output = if self.incremental
+            && history_id != 0
+            && !compressed
+            && history.depth < MAX_DELTA_DEPTH
+        {
+            serde_json::to_string(&HistoryDelta {
+                version: HISTORY_DELTA_VERSION,
+                parent_id: history_id,
+                depth: history.depth + 1,
+                messages: new_messages.to_vec(),
+            })
+        } else {
+            let messages = history
+                .messages
+                .iter()
+                .chain(new_messages)
+                .collect::<Vec<_>>();
+            serde_json::to_string(&messages)
+        };
+
+        output.wrap_err("could not serialize chat history")
+    }
+}
+
+impl DriaOracle {
+    /// Returns the chat history that is the output of the given task, following its deltas if it is incremental.
+    ///
+    /// If the output is not a history, a new one is made of the input & the output of the task.
+    pub async fn get_chat_history(&self, history_id: usize) -> Result<ChatHistory> {
+        resolve_history(
+            history_id,
+            |task_id| async move {
+                self.get_task_best_response(U256::from(task_id))
+                    .await
+                    .wrap_err("could not get chat history task from contract")?
+                    .output
+                    .fetch()
+                    .await
+            },
+            |task_id| async move {
+                let request = self
+                    .coordinator
+                    .requests(U256::from(task_id))
+                    .call()
+                    .await?;
+                parse_downloadable(&request.input).await
+            },
+        )
+        .await
+    }
+}
+
+/// Rebuilds the chat history that is the output of the given task, where `fetch_output` returns the output
+/// of a task and `fetch_input` returns its input, which is only used if the output is not a history.
+///
+/// The deltas are followed through their parents until a whole history, and their messages are appended to it.
+async fn resolve_history<O, OFut, I, IFut>(
+    history_id: usize,
+    mut fetch_output: O,
+    fetch_input: I,
+) -> Result<ChatHistory>
+where
+    O: FnMut(usize) -> OFut,
+    OFut: Future<Output = Result<String>>,
+    I: FnOnce(usize) -> IFut,
+    IFut: Future<Output = Result<String>>,
+{
+    let mut deltas = Vec::new();
+    let mut task_id = history_id;
+    let mut messages = loop {
+        match ChatOutput::parse(fetch_output(task_id).await?)? {
+            // if its a previous message array, we can parse it directly
+            ChatOutput::History(messages) => break messages,
+            // if its a delta, the history that it continues is fetched as well
+            ChatOutput::Delta(delta) => {
+                if delta.parent_id >= task_id || deltas.len() >= MAX_DELTA_DEPTH {
+                    return Err(eyre!("chat history {} has an invalid delta", history_id));
+                }
+                task_id = delta.parent_id;
+                deltas.push(delta.messages);
+            }
+            // otherwise, we can fallback to fetching input manually and creating a new history on-the-fly
+            ChatOutput::Other(output) => {
+                let input = fetch_input(task_id).await?;
+                break vec![
+                    MessageInput::new_user_message(input),
+                    MessageInput::new_assistant_message(output),
+                ];
+            }
+        }
+    };
+
+    let depth = deltas.len();
+    messages.extend(deltas.into_iter().rev().flatten());
+    Ok(ChatHistory { messages, depth })
+}
+
+/// Returns a field of the message as a string, e.g. its role or content.
+fn message_field(message: &MessageInput, field: &str) -> String {
+    serde_json::to_value(message)
+        .ok()
+        .and_then(|m| m.get(field).and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Returns a system message with the given content, or a user message if the message type does not allow it.
+fn system_message(content: String) -> MessageInput {
+    serde_json::from_value(serde_json::json!({ "role": "system", "content": content }))
+        .unwrap_or_else(|_| MessageInput::new_user_message(content))
+}
+
+/// Estimates the number of tokens within the content of a message.
+fn message_tokens(message: &MessageInput) -> u64 {
+    estimate_tokens(&message_field(message, "content"))
+}
+
+/// Returns the content of a message as a single line, without the prefix of an earlier summary.
+fn message_line(message: &MessageInput) -> String {
+    let content = message_field(message, "content");
+    // the summary of an earlier compression is summarized along with the messages
+    let content = content.strip_prefix(SUMMARY_PREFIX).unwrap_or(&content);
+    content.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the beginning of the text that fits within the given number of tokens.
+fn take_tokens(text: &str, max_tokens: u64) -> String {
+    let max_chars = (max_tokens as usize).saturating_mul(CHARS_PER_TOKEN);
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    text.chars()
+        .take(max_chars.saturating_sub(3))
+        .collect::<String>()
+        + "..."
+}
+
+/// Returns the prompt that asks the model to summarize the messages within the token budget.
+fn summary_prompt(messages: &[MessageInput], max_tokens: u64) -> String {
+    let mut prompt = format!(
+        "Summarize the following conversation in at most {} words, keeping the facts, names, numbers and decisions that the rest of the conversation may refer to. Respond with the summary only.\n",
+        max_tokens * 3 / 4
+    );
+    for message in messages {
+        prompt.push_str(&format!(
+            "\n{}: {}",
+            message_field(message, "role"),
+            message_line(message)
+        ));
+    }
+
+    prompt
+}
+
+/// Summarizes the messages by the beginning of each, in order, until the summary reaches the token budget.
+///
+/// This is used when the model could not summarize the messages.
+fn truncated_summary(messages: &[MessageInput], max_tokens: u64) -> String {
+    let mut summary = SUMMARY_PREFIX.to_string();
+    for (i, message) in messages.iter().enumerate() {
+        let mut line = message_line(message);
+        if line.chars().count() > SUMMARY_LINE_CHARS {
+            line = line.chars().take(SUMMARY_LINE_CHARS).collect::<String>() + "...";
+        }
+        let line = format!("\n- {}: {}", message_field(message, "role"), line);
+
+        if estimate_tokens(&summary) + estimate_tokens(&line) > max_tokens {
+            summary.push_str(&format!(
+                "\n- ({} more messages are omitted)",
+                messages.len() - i
+            ));
+            break;
+        }
+        summary.push_str(&line);
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn contents(messages: &[MessageInput]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| message_field(m, "content"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_compress_history() {
+        let messages = (0..10)
+            .map(|i| MessageInput::new_user_message(format!("{} {}", i, "word ".repeat(20))))
+            .collect::<Vec<_>>();
+        let policy = ChatHistoryPolicy {
+            max_tokens: Some(1000),
+            incremental: false,
+        };
+        let mut history = messages.clone();
+        let compression = policy
+            .compress(&mut history, |_| async { Ok(String::new()) })
+            .await;
+        assert_eq!(compression, None);
+
+        // each message is 26 tokens, so the most recent 3 fit within the 90 tokens left for the messages
+        let policy = ChatHistoryPolicy {
+            max_tokens: Some(120),
+            incremental: false,
+        };
+        let mut history = messages.clone();
+        let compression = policy
+            .compress(&mut history, |prompt| async move {
+                assert!(prompt.contains("6 word"));
+                assert!(!prompt.contains("7 word"));
+                Ok("They counted words.".to_string())
+            })
+            .await
+            .unwrap();
+        assert_eq!(compression.summarized_messages, 7);
+        assert!(!compression.truncated);
+        assert_eq!(history.len(), 4);
+        assert_eq!(message_field(&history[0], "role"), "system");
+        let contents = contents(&history);
+        assert_eq!(
+            contents[0],
+            format!("{}\nThey counted words.", SUMMARY_PREFIX)
+        );
+        assert!(contents[1].starts_with("7 "));
+
+        // the beginnings of the messages are used if the model fails
+        let mut history = messages;
+        let compression = policy
+            .compress(&mut history, |_| async { Err(eyre!("model is down")) })
+            .await
+            .unwrap();
+        assert!(compression.truncated);
+        assert!(compression.summary_tokens <= 30);
+        let summary = message_field(&history[0], "content");
+        assert!(summary.starts_with(SUMMARY_PREFIX));
+        assert!(summary.contains("more messages are omitted"));
+    }
+
+    #[test]
+    fn test_history_output() {
+        let history = ChatHistory {
+            messages: vec![MessageInput::new_user_message("hi".to_string())],
+            depth: 0,
+        };
+        let new_messages = [MessageInput::new_assistant_message("hello".to_string())];
+
+        // the whole history is uploaded by default
+        let policy = ChatHistoryPolicy::default();
+        let output = policy.output(1, &history, &new_messages, false).unwrap();
+        let messages = serde_json::from_str::<Vec<MessageInput>>(&output).unwrap();
+        assert_eq!(contents(&messages), vec!["hi", "hello"]);
+
+        // only the new messages are uploaded if incremental, along with the version of the format
+        let policy = ChatHistoryPolicy {
+            max_tokens: None,
+            incremental: true,
+        };
+        let output = policy.output(1, &history, &new_messages, false).unwrap();
+        let delta = serde_json::from_str::<HistoryDelta>(&output).unwrap();
+        assert_eq!(delta.version, HISTORY_DELTA_VERSION);
+        assert_eq!((delta.parent_id, delta.depth), (1, 1));
+        assert_eq!(contents(&delta.messages), vec!["hello"]);
+        assert!(matches!(
+            ChatOutput::parse(output).unwrap(),
+            ChatOutput::Delta(_)
+        ));
+
+        // unless the history is compressed, or there is no history to continue
+        let output = policy.output(1, &history, &new_messages, true).unwrap();
+        assert!(serde_json::from_str::<Vec<MessageInput>>(&output).is_ok());
+        let output = policy.output(0, &history, &new_messages, false).unwrap();
+        assert!(serde_json::from_str::<Vec<MessageInput>>(&output).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_history() {
+        let delta = |parent_id: usize, depth: usize, content: &str| {
+            serde_json::to_string(&HistoryDelta {
+                version: HISTORY_DELTA_VERSION,
+                parent_id,
+                depth,
+                messages: vec![MessageInput::new_assistant_message(content.to_string())],
+            })
+            .unwrap()
+        };
+        let outputs = HashMap::from([
+            (
+                1,
+                serde_json::to_string(&[
+                    MessageInput::new_user_message("hi".to_string()),
+                    MessageInput::new_assistant_message("hello".to_string()),
+                ])
+                .unwrap(),
+            ),
+            (2, delta(1, 1, "how are you?")),
+            (3, delta(2, 2, "fine")),
+            (4, "a plain answer".to_string()),
+            (5, delta(4, 1, "continued")),
+            (6, delta(6, 1, "cyclic")),
+            (
+                7,
+                r#"{"history_delta":2,"parent_id":1,"depth":1,"messages":[]}"#.to_string(),
+            ),
+        ]);
+        let resolve = |history_id: usize| {
+            resolve_history(
+                history_id,
+                |task_id| {
+                    let output = outputs.get(&task_id).cloned();
+                    async move { output.ok_or_else(|| eyre!("no task {}", task_id)) }
+                },
+                |task_id| async move { Ok(format!("question {}", task_id)) },
+            )
+        };
+
+        // the deltas are followed through their parents, and appended in order
+        let history = resolve(3).await.unwrap();
+        assert_eq!(history.depth, 2);
+        assert_eq!(
+            contents(&history.messages),
+            vec!["hi", "hello", "how are you?", "fine"]
+        );
+
+        // an output that is not a history is made into one with the input of its task
+        let history = resolve(5).await.unwrap();
+        assert_eq!(history.depth, 1);
+        assert_eq!(
+            contents(&history.messages),
+            vec!["question 4", "a plain answer", "continued"]
+        );
+
+        // a delta can not continue itself or a later task, nor have an unknown version
+        assert!(resolve(6).await.is_err());
+        assert!(resolve(7).await.is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use eyre::{eyre, Result};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Limits on the size & complexity of the inputs, so that a malicious or buggy requester
+/// can not tie up the node for hours.
+///
+/// The workflow limits apply to the inputs that are workflows, where `max_steps` and `max_time`
+/// are read from their config and the external memory is measured by its JSON size.
+#[derive(Debug, Clone, Default)]
+pub struct InputLimits {
+    /// Maximum size of an input in bytes, which also stops its download from storage once exceeded.
+    pub max_input_bytes: Option<usize>,
+    /// Maximum `max_steps` of a workflow.
+    pub max_workflow_steps: Option<u64>,
+    /// Maximum `max_time` of a workflow in seconds.
+    pub max_workflow_time: Option<u64>,
+    /// Maximum size of the external memory of a workflow in bytes.
+    pub max_memory_bytes: Option<usize>,
+    /// Number of inputs that have been rejected, shared by the clones.
+    rejected: Arc<AtomicU64>,
+}
+
+impl InputLimits {
+    /// Checks the input against the limits, returns an error with the exceeded limit if any.
+    pub fn check_input(&self, input: &str) -> Result<()> {
+        let result = self.exceeded_limit(input);
+        if result.is_err() {
+            self.record_rejection();
+        }
+
+        result
+    }
+
+    /// Counts an input that has been rejected outside [`Self::check_input`], e.g. one whose download
+    /// has been stopped for exceeding `max_input_bytes`.
+    pub fn record_rejection(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of inputs that have been rejected.
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    fn exceeded_limit(&self, input: &str) -> Result<()> {
+        check_limit("input size", input.len(), self.max_input_bytes)?;
+
+        let Ok(workflow) = serde_json::from_str::<Value>(input) else {
+            return Ok(());
+        };
+        let Some(config) = workflow.get("config") else {
+            return Ok(());
+        };
+
+        if let Some(max_steps) = config.get("max_steps").and_then(Value::as_u64) {
+            check_limit("workflow steps", max_steps, self.max_workflow_steps)?;
+        }
+        if let Some(max_time) = config.get("max_time").and_then(Value::as_u64) {
+            check_limit("workflow time", max_time, self.max_workflow_time)?;
+        }
+        if let Some(memory) = workflow.get("external_memory") {
+            check_limit(
+                "external memory size",
+                memory.to_string().len(),
+                self.max_memory_bytes,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns an error if the value exceeds the limit, if there is one.
+#[inline]
+fn check_limit<T: PartialOrd + std::fmt::Display>(
+    name: &str,
+    value: T,
+    limit: Option<T>,
+) -> Result<()> {
+    match limit {
+        Some(limit) if value > limit => {
+            Err(eyre!("{} {} exceeds the limit {}", name, value, limit))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_limits() {
+        let limits = InputLimits {
+            max_input_bytes: Some(1000),
+            max_workflow_steps: Some(20),
+            max_workflow_time: Some(60),
+            max_memory_bytes: Some(100),
+            ..Default::default()
+        };
+
+        assert!(limits.check_input("What is 2+2?").is_ok());
+        assert!(limits.check_input(&"a".repeat(1001)).is_err());
+
+        let workflow = |steps: u64, time: u64, memory: &str| {
+            format!(
+                r#"{{"config": {{"max_steps": {}, "max_time": {}, "tools": [""]}}, "external_memory": {{"context": "{}"}}, "tasks": []}}"#,
+                steps, time, memory
+            )
+        };
+        assert!(limits.check_input(&workflow(10, 30, "short")).is_ok());
+        assert!(limits.check_input(&workflow(50, 30, "short")).is_err());
+        assert!(limits.check_input(&workflow(10, 3600, "short")).is_err());
+        assert!(limits
+            .check_input(&workflow(10, 30, &"a".repeat(200)))
+            .is_err());
+
+        assert_eq!(limits.rejected(), 4);
+    }
+}
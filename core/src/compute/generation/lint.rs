@@ -0,0 +1,553 @@
+use dkn_workflows::Workflow;
+use eyre::{eyre, Result};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+use super::sandbox::ToolSandbox;
+
+/// A minimal workflow that the executor accepts, where a single value is replaced to check it against
+/// the schema of the executor, see [`schema_error`].
+///
+/// This way the operators & the input / output types are not copied from the schema, and they are
+/// reported by the parser itself along with the values that it expects.
+const PROBE_WORKFLOW: &str = r#"{
+    "config": { "max_steps": 1, "max_time": 1 },
+    "tasks": [
+        {
+            "id": "A",
+            "name": "A",
+            "description": "A",
+            "operator": "generation",
+            "messages": [{ "role": "user", "content": "A" }],
+            "outputs": [{ "type": "write", "key": "A", "value": "__result" }]
+        },
+        {
+            "id": "__end",
+            "operator": "end",
+            "messages": [{ "role": "user", "content": "End of the task" }]
+        }
+    ],
+    "steps": [{ "source": "A", "target": "__end" }],
+    "return_value": { "input": { "type": "read", "key": "A" } }
+}"#;
+
+/// Input types that read from the memory, the other ones are given within the workflow.
+///
+/// Unlike the other values, this can not be told from the schema, so an unknown type that the schema
+/// accepts is not checked against the memory.
+const MEMORY_INPUT_TYPES: [&str; 5] = ["read", "pop", "peek", "get_all", "size"];
+
+/// Operator of the tasks that end a workflow.
+const END_OPERATOR: &str = "end";
+
+/// Number of steps above which a workflow is likely to be rejected or to run away.
+const MAX_REASONABLE_STEPS: u64 = 100;
+
+/// Time in seconds above which a workflow is likely to be rejected or to time out.
+const MAX_REASONABLE_TIME: u64 = 600;
+
+/// Severity of a lint issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintSeverity {
+    /// The executor would reject the workflow, or it can not succeed.
+    Error,
+    /// The workflow may run, but it is likely to fail or to be rejected by the nodes.
+    Warning,
+}
+
+/// An issue of a workflow, along with where it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub severity: LintSeverity,
+    /// Path of the value within the workflow, e.g. `tasks[1].operator`, or the line & column of a syntax error.
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Ids of the tasks of a workflow, along with the memory keys that they write.
+#[derive(Default)]
+struct Tasks {
+    ids: HashSet<String>,
+    /// Ids of the tasks with the end operator.
+    ends: HashSet<String>,
+    written: HashSet<String>,
+}
+
+/// Collects the issues of a workflow.
+#[derive(Default)]
+struct Linter {
+    issues: Vec<LintIssue>,
+}
+
+/// Returns the error of the executor for the given value at the JSON `pointer` of [`PROBE_WORKFLOW`],
+/// or `None` if it accepts the value.
+///
+/// Returns `None` as well if the executor does not accept the probe itself, e.g. a newer schema, so that
+/// the values are not reported falsely; the parser still reports them along with the other errors.
+fn schema_error(pointer: &str, value: &str) -> Option<String> {
+    let mut probe = serde_json::from_str::<Value>(PROBE_WORKFLOW).expect("probe should be JSON");
+    if serde_json::from_value::<Workflow>(probe.clone()).is_err() {
+        return None;
+    }
+
+    *probe.pointer_mut(pointer)? = value.into();
+    serde_json::from_value::<Workflow>(probe)
+        .err()
+        .map(|e| e.to_string())
+}
+
+impl Linter {
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(LintSeverity::Error, path.into(), message.into());
+    }
+
+    fn warn(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(LintSeverity::Warning, path.into(), message.into());
+    }
+
+    fn push(&mut self, severity: LintSeverity, path: String, message: String) {
+        self.issues.push(LintIssue {
+            severity,
+            path,
+            message,
+        });
+    }
+
+    /// Returns the field of the object, or reports it as missing.
+    fn field<'a>(
+        &mut self,
+        object: &'a Map<String, Value>,
+        path: &str,
+        key: &str,
+    ) -> Option<&'a Value> {
+        let value = object.get(key).filter(|v| !v.is_null());
+        if value.is_none() {
+            self.error(path, format!("missing `{}`", key));
+        }
+
+        value
+    }
+
+    fn lint_config(&mut self, config: &Value) {
+        let Some(config) = config.as_object() else {
+            return self.error("config", "must be an object");
+        };
+
+        for (key, max) in [
+            ("max_steps", MAX_REASONABLE_STEPS),
+            ("max_time", MAX_REASONABLE_TIME),
+        ] {
+            let path = format!("config.{}", key);
+            match self.field(config, "config", key).map(Value::as_u64) {
+                None => {}
+                Some(None) => self.error(path, "must be a non-negative integer"),
+                Some(Some(0)) => self.error(path, "must be larger than zero"),
+                Some(Some(value)) if value > max => self.warn(
+                    path,
+                    format!(
+                        "{} is likely to be rejected by the nodes, keep it at most {}",
+                        value, max
+                    ),
+                ),
+                Some(Some(_)) => {}
+            }
+        }
+
+        // tools
+        match config.get("tools").map(Value::as_array) {
+            None => {}
+            Some(None) => self.error("config.tools", "must be an array of tool names"),
+            Some(Some(tools)) => {
+                for (i, tool) in tools.iter().enumerate() {
+                    match tool.as_str() {
+                        None => self.error(format!("config.tools[{}]", i), "must be a tool name"),
+                        Some("ALL") => self.warn(
+                            format!("config.tools[{}]", i),
+                            "enables every tool, which the nodes may reject; list the tools that are needed",
+                        ),
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+
+        // custom tools, which are called by the nodes
+        let custom_tools = config
+            .get("custom_tools")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten();
+        let sandbox = ToolSandbox::default();
+        for (i, tool) in custom_tools.enumerate() {
+            let path = format!("config.custom_tools[{}].url", i);
+            match tool.get("url").and_then(Value::as_str) {
+                None => self.error(path, "missing the URL of the custom tool"),
+                Some(url) => {
+                    if let Err(e) = sandbox.check_url(url) {
+                        self.error(path, e.to_string());
+                    } else if !url.starts_with("https://") {
+                        self.warn(
+                            path,
+                            "is not HTTPS, so its requests & responses can be read or altered",
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lints the tasks, and returns their ids along with the memory keys that they write.
+    fn lint_tasks(&mut self, tasks: &Value) -> Tasks {
+        let mut result = Tasks::default();
+        let Some(tasks) = tasks.as_array() else {
+            self.error("tasks", "must be an array");
+            return result;
+        };
+        if tasks.is_empty() {
+            self.error("tasks", "has no tasks");
+        }
+
+        for (i, task) in tasks.iter().enumerate() {
+            let path = format!("tasks[{}]", i);
+            let Some(task) = task.as_object() else {
+                self.error(path, "must be an object");
+                continue;
+            };
+
+            let id = self.field(task, &path, "id");
+            if let Some(id) = id {
+                match id.as_str() {
+                    None => self.error(format!("{}.id", path), "must be a string"),
+                    Some(id) if !result.ids.insert(id.to_string()) => self.error(
+                        format!("{}.id", path),
+                        format!("duplicate task id `{}`", id),
+                    ),
+                    Some(_) => {}
+                }
+            }
+
+            if let Some(operator) = self.field(task, &path, "operator") {
+                let operator = operator.as_str().unwrap_or_default();
+                if let (END_OPERATOR, Some(id)) = (operator, id.and_then(Value::as_str)) {
+                    result.ends.insert(id.to_string());
+                }
+                if let Some(e) = schema_error("/tasks/0/operator", operator) {
+                    self.error(format!("{}.operator", path), e);
+                }
+            }
+
+            match self.field(task, &path, "messages").map(Value::as_array) {
+                Some(Some(messages)) if messages.is_empty() => {
+                    self.error(format!("{}.messages", path), "has no messages")
+                }
+                Some(None) => self.error(format!("{}.messages", path), "must be an array"),
+                _ => {}
+            }
+
+            for (j, output) in task
+                .get("outputs")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                let path = format!("{}.outputs[{}]", path, j);
+                let kind = output
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                if let Some(e) = schema_error("/tasks/0/outputs/0/type", kind) {
+                    self.error(format!("{}.type", path), e);
+                }
+                match output.get("key").and_then(Value::as_str) {
+                    Some(key) => {
+                        result.written.insert(key.to_string());
+                    }
+                    None => self.error(path, "missing `key`"),
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Lints the memory reads of the tasks & the return value against the keys that are written.
+    fn lint_memory_reads(&mut self, workflow: &Value, written: &HashSet<String>) {
+        let mut reads = Vec::new();
+        for (i, task) in workflow["tasks"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            for (j, input) in task
+                .get("inputs")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                let path = format!("tasks[{}].inputs[{}].value", i, j);
+                let required = input
+                    .get("required")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true);
+                reads.push((path, &input["value"], required));
+            }
+        }
+        for (i, step) in workflow["steps"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            if let Some(input) = step.get("condition").and_then(|c| c.get("input")) {
+                reads.push((format!("steps[{}].condition.input", i), input, true));
+            }
+        }
+        reads.push((
+            "return_value.input".to_string(),
+            &workflow["return_value"]["input"],
+            true,
+        ));
+
+        for (path, value, required) in reads {
+            let kind = value
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            if let Some(e) = schema_error("/return_value/input/type", kind) {
+                self.error(format!("{}.type", path), e);
+                continue;
+            }
+            if !MEMORY_INPUT_TYPES.contains(&kind) {
+                continue;
+            }
+
+            let key = value.get("key").and_then(Value::as_str).unwrap_or_default();
+            if !written.contains(key) {
+                let message = format!(
+                    "memory key `{}` is never written by a task or the external memory",
+                    key
+                );
+                if required {
+                    self.error(format!("{}.key", path), message);
+                } else {
+                    self.warn(format!("{}.key", path), message);
+                }
+            }
+        }
+    }
+
+    fn lint_steps(&mut self, steps: &Value, tasks: &Tasks) {
+        let Some(steps) = steps.as_array() else {
+            return self.error("steps", "must be an array");
+        };
+        if steps.is_empty() {
+            self.error("steps", "has no steps");
+        }
+
+        let mut reaches_end = false;
+        for (i, step) in steps.iter().enumerate() {
+            let condition_target = step.get("condition").and_then(|c| c.get("target_if_not"));
+            for (key, id) in [
+                ("source", step.get("source")),
+                ("target", step.get("target")),
+                ("fallback", step.get("fallback")),
+                ("condition.target_if_not", condition_target),
+            ] {
+                let Some(id) = id.filter(|v| !v.is_null()) else {
+                    if matches!(key, "source" | "target") {
+                        self.error(format!("steps[{}]", i), format!("missing `{}`", key));
+                    }
+                    continue;
+                };
+                let id = id.as_str().unwrap_or_default();
+                reaches_end |= key != "source" && tasks.ends.contains(id);
+                if !tasks.ids.contains(id) {
+                    self.error(
+                        format!("steps[{}].{}", i, key),
+                        format!("unknown task id `{}`", id),
+                    );
+                }
+            }
+        }
+
+        if !reaches_end {
+            self.error(
+                "steps",
+                format!(
+                    "no step targets a task with the `{}` operator, so the workflow never ends",
+                    END_OPERATOR
+                ),
+            );
+        }
+    }
+}
+
+/// Checks a workflow JSON for the issues that the executor would reject at runtime, or that are likely
+/// to make it fail: missing tasks & steps, unknown operators, memory keys that are never written,
+/// absurd limits and dangerous tools.
+///
+/// Each issue is located by its path within the workflow, while the syntax & schema errors of the
+/// parser are located by their line & column.
+pub fn lint_workflow(json: &str) -> Vec<LintIssue> {
+    let mut linter = Linter::default();
+    let workflow = match serde_json::from_str::<Value>(json) {
+        Ok(workflow) => workflow,
+        Err(e) => {
+            linter.error(
+                format!("line {} column {}", e.line(), e.column()),
+                "invalid JSON",
+            );
+            return linter.issues;
+        }
+    };
+    let Some(object) = workflow.as_object() else {
+        linter.error("$", "a workflow must be an object");
+        return linter.issues;
+    };
+
+    if let Some(config) = linter.field(object, "$", "config") {
+        linter.lint_config(config);
+    }
+    let mut tasks = match linter.field(object, "$", "tasks") {
+        Some(tasks) => linter.lint_tasks(tasks),
+        None => Tasks::default(),
+    };
+    if let Some(steps) = linter.field(object, "$", "steps") {
+        linter.lint_steps(steps, &tasks);
+    }
+    if linter.field(object, "$", "return_value").is_some() {
+        if let Some(memory) = object.get("external_memory").and_then(Value::as_object) {
+            tasks.written.extend(memory.keys().cloned());
+        }
+        linter.lint_memory_reads(&workflow, &tasks.written);
+    }
+
+    // the parser of the executor may reject what the checks above can not see, e.g. the message roles
+    if let Err(e) = serde_json::from_str::<Workflow>(json) {
+        let message = e.to_string();
+        let message = message.split(" at line ").next().unwrap_or(&message);
+        linter.error(format!("line {} column {}", e.line(), e.column()), message);
+    }
+
+    linter.issues
+}
+
+/// Lints the workflow with [`lint_workflow`] and logs its issues, returns an error if it has any errors.
+///
+/// Returns the number of warnings otherwise.
+pub fn check_workflow(json: &str) -> Result<usize> {
+    let issues = lint_workflow(json);
+    for issue in &issues {
+        match issue.severity {
+            LintSeverity::Error => log::error!("{}", issue),
+            LintSeverity::Warning => log::warn!("{}", issue),
+        }
+    }
+
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == LintSeverity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    if errors > 0 {
+        return Err(eyre!(
+            "workflow has {} errors and {} warnings",
+            errors,
+            warnings
+        ));
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKFLOW: &str = r#"{
+        "config": { "max_steps": 10, "max_time": 50, "tools": [""] },
+        "tasks": [
+            {
+                "id": "A",
+                "name": "Generate",
+                "description": "Generates a haiku",
+                "operator": "generation",
+                "messages": [{ "role": "user", "content": "Write a haiku." }],
+                "outputs": [{ "type": "write", "key": "result", "value": "__result" }]
+            },
+            {
+                "id": "__end",
+                "operator": "end",
+                "messages": [{ "role": "user", "content": "End of the task" }]
+            }
+        ],
+        "steps": [{ "source": "A", "target": "__end" }],
+        "return_value": { "input": { "type": "read", "key": "result" } }
+    }"#;
+
+    fn paths(issues: &[LintIssue], severity: LintSeverity) -> Vec<&str> {
+        issues
+            .iter()
+            .filter(|i| i.severity == severity)
+            .map(|i| i.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_valid_workflow() {
+        assert_eq!(lint_workflow(WORKFLOW), vec![]);
+        assert_eq!(lint_workflow(PROBE_WORKFLOW), vec![]);
+        assert_eq!(check_workflow(WORKFLOW).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_lint_workflow_issues() {
+        let mut workflow = serde_json::from_str::<Value>(WORKFLOW).unwrap();
+        workflow["config"]["max_time"] = 86400.into();
+        workflow["config"]["tools"] = serde_json::json!(["ALL"]);
+        workflow["config"]["custom_tools"] =
+            serde_json::json!([{ "name": "meta", "url": "http://169.254.169.254/latest" }]);
+        workflow["tasks"][0]["operator"] = "generate".into();
+        workflow["steps"][0]["target"] = "B".into();
+        workflow["return_value"]["input"]["key"] = "output".into();
+
+        let issues = lint_workflow(&workflow.to_string());
+        assert_eq!(
+            paths(&issues, LintSeverity::Warning),
+            vec!["config.max_time", "config.tools[0]"]
+        );
+        let errors = paths(&issues, LintSeverity::Error);
+        assert!(check_workflow(&workflow.to_string()).is_err());
+        for path in [
+            "config.custom_tools[0].url",
+            "tasks[0].operator",
+            "steps[0].target",
+            "steps",
+            "return_value.input.key",
+        ] {
+            assert!(errors.contains(&path), "{} should be an error", path);
+        }
+        // the unknown values are reported by the schema of the executor
+        let operator = issues
+            .iter()
+            .find(|i| i.path == "tasks[0].operator")
+            .unwrap();
+        assert!(operator.message.contains("generation"));
+    }
+
+    #[test]
+    fn test_lint_invalid_json() {
+        let issues = lint_workflow("{\n  \"config\": }");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "line 2 column 13");
+    }
+}
@@ -0,0 +1,125 @@
+use dkn_workflows::Model;
+use eyre::{eyre, Context, Result};
+use std::path::Path;
+
+use crate::compute::Execution;
+
+/// A canned output, returned for the inputs that contain its pattern.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MockFixture {
+    /// Pattern to look for within the input, case-insensitive.
+    pub pattern: String,
+    /// Output to return for the matching inputs.
+    pub output: String,
+}
+
+/// A deterministic model backend that returns canned outputs instead of executing the workflows,
+/// so that the post-processors, storage & transactions can be tested without any API keys or GPUs.
+///
+/// Loaded from a JSON fixture file:
+///
+/// ```json
+/// {
+///   "fixtures": [
+///     { "pattern": "2 + 2", "output": "4" },
+///     { "pattern": "capital of france", "output": "Paris" }
+///   ],
+///   "default": "I don't know."
+/// }
+/// ```
+///
+/// The first fixture whose pattern is within the input is used, otherwise the default output.
+/// The input is the request itself for string & chat requests, and the workflow as JSON for workflow requests.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct MockModel {
+    /// Fixtures in the order that they are matched.
+    #[serde(default)]
+    pub fixtures: Vec<MockFixture>,
+    /// Output for the inputs that match no fixture, which are failed if there is none.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl MockModel {
+    /// Loads the fixtures from the given JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("could not read mock fixtures {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("invalid mock fixtures {}", path.display()))
+    }
+
+    /// Adds a fixture, which is matched after the existing ones.
+    pub fn with_fixture(mut self, pattern: impl Into<String>, output: impl Into<String>) -> Self {
+        self.fixtures.push(MockFixture {
+            pattern: pattern.into(),
+            output: output.into(),
+        });
+        self
+    }
+
+    /// Change the output for the inputs that match no fixture.
+    pub fn with_default(mut self, output: impl Into<String>) -> Self {
+        self.default = Some(output.into());
+        self
+    }
+
+    /// Returns the canned output for the given input.
+    pub fn respond(&self, input: &str) -> Result<&str> {
+        let input = input.to_lowercase();
+        self.fixtures
+            .iter()
+            .find(|fixture| input.contains(&fixture.pattern.to_lowercase()))
+            .map(|fixture| fixture.output.as_str())
+            .or(self.default.as_deref())
+            .ok_or_else(|| eyre!("no mock fixture matches the input"))
+    }
+
+    /// Returns the canned output for the given input as if it is produced by the first of the given models.
+    pub(crate) fn execute(&self, input: &str, models: &[Model]) -> Result<Execution> {
+        let model = models
+            .first()
+            .cloned()
+            .ok_or_else(|| eyre!("no models to execute the workflow with"))?;
+        log::debug!("Responding with the mock model instead of {}", model);
+
+        Ok(Execution {
+            output: self.respond(input)?.to_string(),
+            model,
+            retries: Vec::new(),
+            history: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_model() {
+        let mock: MockModel = serde_json::from_str(
+            r#"{ "fixtures": [{ "pattern": "2 + 2", "output": "4" }, { "pattern": "France", "output": "Paris" }] }"#,
+        )
+        .unwrap();
+        assert_eq!(mock.respond("What is 2 + 2?").unwrap(), "4");
+        assert_eq!(
+            mock.respond("what is the capital of france?").unwrap(),
+            "Paris"
+        );
+        assert!(mock.respond("What is 3 + 3?").is_err());
+
+        let mock = mock
+            .with_fixture("3 + 3", "6")
+            .with_default("I don't know.");
+        assert_eq!(mock.respond("What is 3 + 3?").unwrap(), "6");
+        assert_eq!(mock.respond("Who are you?").unwrap(), "I don't know.");
+
+        let execution = mock.execute("2 + 2", &[Model::GPT4o]).unwrap();
+        assert_eq!(execution.output, "4");
+        assert_eq!(execution.model, Model::GPT4o);
+        assert!(mock.execute("2 + 2", &[]).is_err());
+    }
+}
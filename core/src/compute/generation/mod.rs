@@ -1,10 +1,42 @@
 mod execute;
 
 mod postprocess;
+pub(crate) use postprocess::{
+    PostProcess, SwanPurchasePostProcessor, SHOP_LIST_END_MARKER, SHOP_LIST_START_MARKER,
+};
 
 mod workflow;
+pub(crate) use workflow::{make_chat_workflow, make_generation_workflow};
+
+mod presets;
+pub(crate) use presets::INPUT_PLACEHOLDER;
+pub use presets::{BuiltinPreset, WorkflowPresets, BUILTIN_PRESETS};
+
+mod guardrails;
+pub use guardrails::{GuardrailAction, GuardrailDecision, Guardrails};
+
+mod selfcheck;
+pub use selfcheck::SelfCheck;
+
+mod sandbox;
+pub use sandbox::ToolSandbox;
+
+mod history;
+pub use history::{ChatHistoryPolicy, HistoryCompression};
+
+mod lint;
+pub use lint::{check_workflow, lint_workflow, LintIssue, LintSeverity};
+
+mod limits;
+pub use limits::InputLimits;
+
+mod mock;
+pub use mock::{MockFixture, MockModel};
+
+mod models;
 
 mod handler;
 pub use handler::handle_generation;
 
 mod request;
+pub(crate) use request::{ChatHistoryRequest, GenerationRequest};
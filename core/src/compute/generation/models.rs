@@ -0,0 +1,125 @@
+use alloy::primitives::{Bytes, U256};
+use dkn_workflows::Model;
+use dria_oracle_contracts::bytes_to_string;
+use eyre::Result;
+
+use crate::{ApiBudget, DriaOracle};
+
+/// Selects the models to execute a generation task with, in order of preference.
+///
+/// The model chosen for the requested models is followed by the fallback models (see [`fallback_models`]),
+/// which are then narrowed down by each enabled stage in turn:
+///
+/// 1. the paid providers are dropped while the API budget is exhausted,
+/// 2. the local models that would not fit into the free VRAM are dropped,
+/// 3. the local models that are missing are pulled, dropping the ones that could not be,
+/// 4. the local models are warmed up, dropping the ones that could not be.
+///
+/// Returns `None` if no model is left after a stage, in which case the task is to be skipped.
+pub(super) async fn select_models(
+    node: &DriaOracle,
+    task_id: U256,
+    requested: &Bytes,
+) -> Result<Option<Vec<Model>>> {
+    let mut models = requested_models(node, requested)?;
+
+    // paid providers are not used while the API budget is exhausted
+    if let Some(api_budget) = &node.config.api_budget {
+        if !api_budget.allows_paid() {
+            models.retain(|model| !ApiBudget::is_paid(model));
+            if none_left(
+                task_id,
+                &models,
+                "it requires a paid provider and the API budget is exhausted",
+            ) {
+                return Ok(None);
+            }
+        }
+    }
+
+    // the local models are managed by the node only if they are not mocked
+    if node.config.mock_model.is_some() {
+        return Ok(Some(models));
+    }
+
+    // the local models that would not fit into the free VRAM are skipped, if enabled
+    if let Some(vram_probe) = &node.config.vram_probe {
+        models = vram_probe.retain_fitting(models).await;
+        if none_left(
+            task_id,
+            &models,
+            "none of its models fit into the free VRAM",
+        ) {
+            return Ok(None);
+        }
+    }
+
+    // the local models that are missing are pulled before executing, if enabled
+    if let Some(auto_pull) = &node.config.ollama_auto_pull {
+        models = auto_pull.first_available(models).await;
+        if none_left(task_id, &models, "none of its models could be pulled") {
+            return Ok(None);
+        }
+    }
+
+    // the models are warmed up the first time that a task requires them, if they are prepared lazily
+    if let Some(warmup) = &node.config.model_warmup {
+        models = warmup.first_ready(models).await;
+        if none_left(task_id, &models, "none of its models could be warmed up") {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(models))
+}
+
+/// Returns the model chosen for the requested models, followed by its fallback models.
+///
+/// A random served model is chosen if none of them are served.
+fn requested_models(node: &DriaOracle, requested: &Bytes) -> Result<Vec<Model>> {
+    let models_string = bytes_to_string(requested)?;
+    let models_vec: Vec<String> = models_string.split(',').map(|s| s.to_string()).collect();
+    let model = match node.workflows.get_any_matching_model(models_vec.clone()) {
+        Ok((_, model)) => model,
+        Err(e) => {
+            log::error!(
+                "No matching model found: {}, falling back to random model.",
+                e
+            );
+
+            node.workflows
+                .get_matching_model("*".to_string())
+                .expect("should return at least one model")
+                .1
+        }
+    };
+    log::debug!("Using model: {} from {}", model, models_string);
+
+    Ok(fallback_models(node, model, &models_vec))
+}
+
+/// Returns the models to execute a request with in order of preference, that is the chosen model
+/// followed by the other served models that the request allows, to fall back to on repeated failures
+/// (e.g. when the provider of the chosen model is rate-limited).
+fn fallback_models(node: &DriaOracle, chosen: Model, requested: &[String]) -> Vec<Model> {
+    let mut models = vec![chosen];
+    for (_, model) in &node.workflows.models {
+        let is_allowed = requested
+            .iter()
+            .any(|r| r == "*" || *r == model.to_string());
+        if is_allowed && !models.contains(model) {
+            models.push(model.clone());
+        }
+    }
+
+    models
+}
+
+/// Returns `true` if there are no models left, logging that the task is skipped for the given reason.
+fn none_left(task_id: U256, models: &[Model], reason: &str) -> bool {
+    if models.is_empty() {
+        log::warn!("Skipping generation task {} as {}.", task_id, reason);
+    }
+
+    models.is_empty()
+}
@@ -1,4 +1,7 @@
 use alloy::primitives::Bytes;
+use semver::{Version, VersionReq};
+
+use crate::DriaOracleError;
 
 mod identity;
 pub use identity::*;
@@ -21,3 +24,109 @@ pub trait PostProcess {
     /// - A boolean indicating if the output should be uploaded to a storage if large enough.
     fn post_process(&self, input: String) -> eyre::Result<(Bytes, Bytes, bool)>;
 }
+
+/// Post-processes the output with the post-processor of the given protocol, e.g. `swan-agent-purchase/0.2.1`.
+///
+/// The post-processor is chosen by the protocol name, and its output format by the protocol version,
+/// so that the tasks of older versions that are still in flight keep their format. Protocols without
+/// a version use the oldest format, and protocols without a post-processor have their output as is.
+pub fn post_process_protocol(
+    protocol: &str,
+    output: String,
+) -> Result<(Bytes, Bytes, bool), DriaOracleError> {
+    let (name, version) = parse_protocol(protocol);
+    let result = match name {
+        SwanPurchasePostProcessor::PROTOCOL => {
+            let post_processor =
+                SwanPurchasePostProcessor::new(SHOP_LIST_START_MARKER, SHOP_LIST_END_MARKER);
+            if matches_version(version.as_ref(), SWAN_PURCHASE_WITH_COST_VERSIONS) {
+                post_processor
+                    .with_total_cost(TOTAL_COST_START_MARKER, TOTAL_COST_END_MARKER)
+                    .post_process(output)
+            } else {
+                post_processor.post_process(output)
+            }
+        }
+        _ => IdentityPostProcessor.post_process(output),
+    };
+
+    result.map_err(DriaOracleError::post_process)
+}
+
+/// Splits the protocol into its name and version, e.g. `foobar/1.0` into `foobar` and `1.0.0`.
+///
+/// Missing minor & patch versions are taken as zero, and the version is `None` if it is missing or invalid.
+fn parse_protocol(protocol: &str) -> (&str, Option<Version>) {
+    let Some((name, version)) = protocol.split_once('/') else {
+        return (protocol, None);
+    };
+
+    let version = match version.matches('.').count() {
+        0 => format!("{}.0.0", version),
+        1 => format!("{}.0", version),
+        _ => version.to_string(),
+    };
+    (name, Version::parse(&version).ok())
+}
+
+/// Returns `true` if the version matches the given semver requirement, where a missing version does not.
+fn matches_version(version: Option<&Version>, requirement: &str) -> bool {
+    let requirement = VersionReq::parse(requirement).expect("version requirement should be valid");
+    version.is_some_and(|version| requirement.matches(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        primitives::{address, Address, U256},
+        sol_types::SolValue,
+    };
+
+    #[test]
+    fn test_parse_protocol() {
+        let (name, version) = parse_protocol("swan-agent-purchase/0.2");
+        assert_eq!(name, "swan-agent-purchase");
+        assert_eq!(version, Some(Version::new(0, 2, 0)));
+        assert_eq!(parse_protocol("foobar"), ("foobar", None));
+        assert_eq!(parse_protocol("foobar/latest"), ("foobar", None));
+
+        assert!(matches_version(Some(&Version::new(0, 2, 7)), "^0.2"));
+        assert!(!matches_version(Some(&Version::new(0, 1, 9)), "^0.2"));
+        assert!(!matches_version(Some(&Version::new(0, 3, 0)), "^0.2"));
+        assert!(!matches_version(None, "^0.2"));
+    }
+
+    #[test]
+    fn test_post_process_protocol_versions() {
+        const OUTPUT: &str = r#"
+<shop_list>
+["0x4200000000000000000000000000000000000001"]
+</shop_list>
+
+<total_cost>
+6000000000000000
+</total_cost>
+"#;
+        let expected = vec![address!("4200000000000000000000000000000000000001")];
+
+        // older versions only encode the addresses
+        for protocol in ["swan-agent-purchase", "swan-agent-purchase/0.1.0"] {
+            let (output, _, _) = post_process_protocol(protocol, OUTPUT.to_string()).unwrap();
+            let addresses = <Vec<Address>>::abi_decode(&output, true).unwrap();
+            assert_eq!(addresses, expected);
+        }
+
+        // newer versions encode the total cost as well
+        let (output, _, _) =
+            post_process_protocol("swan-agent-purchase/0.2.1", OUTPUT.to_string()).unwrap();
+        let (addresses, total_cost) =
+            <(Vec<Address>, U256)>::abi_decode_params(&output, true).unwrap();
+        assert_eq!(addresses, expected);
+        assert_eq!(total_cost, U256::from(6000000000000000u64));
+
+        // other protocols are as is
+        let (output, _, _) = post_process_protocol("foobar/0.2.0", OUTPUT.to_string()).unwrap();
+        assert_eq!(output, Bytes::from(OUTPUT));
+    }
+}
@@ -1,9 +1,24 @@
-use alloy::{primitives::Bytes, sol_types::SolValue};
+use alloy::{
+    primitives::{Bytes, U256},
+    sol_types::SolValue,
+};
 use eyre::Result;
 use std::str::FromStr;
 
 use super::PostProcess;
 
+/// Marker that starts the list of assets to buy within the output.
+pub const SHOP_LIST_START_MARKER: &str = "<shop_list>";
+/// Marker that ends the list of assets to buy within the output.
+pub const SHOP_LIST_END_MARKER: &str = "</shop_list>";
+/// Marker that starts the total cost of the assets to buy within the output.
+pub const TOTAL_COST_START_MARKER: &str = "<total_cost>";
+/// Marker that ends the total cost of the assets to buy within the output.
+pub const TOTAL_COST_END_MARKER: &str = "</total_cost>";
+
+/// Versions of the purchase protocol where the total cost is encoded along with the addresses.
+pub const SWAN_PURCHASE_WITH_COST_VERSIONS: &str = "^0.2";
+
 /// Swan post-processor that seeks for lines between `<shop_list>` and `</shop_list>`.
 /// and returns the intermediate strings as an array of strings.
 ///
@@ -13,6 +28,8 @@ pub struct SwanPurchasePostProcessor {
     start_marker: &'static str,
     /// End marker to look for to stop collecting assets.
     end_marker: &'static str,
+    /// Start & end markers of the total cost, if it is to be encoded along with the addresses.
+    cost_markers: Option<(&'static str, &'static str)>,
 }
 
 impl SwanPurchasePostProcessor {
@@ -21,8 +38,41 @@ impl SwanPurchasePostProcessor {
         Self {
             start_marker,
             end_marker,
+            cost_markers: None,
         }
     }
+
+    /// Encode the total cost between the given markers along with the addresses,
+    /// i.e. the output is decodable as `(address[], uint256)` instead of `address[]`.
+    pub fn with_total_cost(mut self, start_marker: &'static str, end_marker: &'static str) -> Self {
+        self.cost_markers = Some((start_marker, end_marker));
+        self
+    }
+}
+
+/// Parses the total cost as a plain decimal number of wei.
+///
+/// `U256::from_str` is not used, as it would also take an output such as `0x10` as a hexadecimal number,
+/// which a model may write for a cost in any unit.
+fn parse_total_cost(cost: &str) -> Option<U256> {
+    let cost = cost.trim();
+    if cost.is_empty() || !cost.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    U256::from_str_radix(cost, 10).ok()
+}
+
+/// Returns the string between the given markers, with the markers excluded.
+fn between<'a>(input: &'a str, start_marker: &str, end_marker: &str) -> Option<&'a str> {
+    input
+        .find(start_marker)
+        .map(|start| start + start_marker.len())
+        .and_then(|start| {
+            input[start..]
+                .find(end_marker)
+                .map(|end| &input[start..start + end])
+        })
 }
 
 impl PostProcess for SwanPurchasePostProcessor {
@@ -34,14 +84,8 @@ impl PostProcess for SwanPurchasePostProcessor {
 
         // get region of interest, that is between <shop_list> and </shop_list>
         // with the markers excluded
-        let roi = input
-            .find(self.start_marker)
-            .map(|start| start + self.start_marker.len())
-            .and_then(|start| {
-                input[start..]
-                    .find(self.end_marker)
-                    .map(|end| input[start..start + end].to_string())
-            })
+        let roi = between(&input, self.start_marker, self.end_marker)
+            .map(|roi| roi.to_string())
             .ok_or_else(|| {
                 eyre::eyre!(
                     "could not find {} ~ {} in result: {}",
@@ -75,8 +119,24 @@ impl PostProcess for SwanPurchasePostProcessor {
             })
             .collect::<Vec<Address>>();
 
-        // `abi.encode` the list of addresses to be decodable by contract
-        let addresses_encoded = addresses.abi_encode();
+        // `abi.encode` the list of addresses to be decodable by contract,
+        // along with the total cost if it is required by the protocol version
+        let addresses_encoded = match self.cost_markers {
+            Some((start_marker, end_marker)) => {
+                let total_cost = between(&input, start_marker, end_marker)
+                    .and_then(parse_total_cost)
+                    .ok_or_else(|| {
+                        eyre::eyre!(
+                            "could not find a total cost between {} ~ {} in result: {}",
+                            start_marker,
+                            end_marker,
+                            input
+                        )
+                    })?;
+                (addresses, total_cost).abi_encode_params()
+            }
+            None => addresses.abi_encode(),
+        };
 
         Ok((Bytes::from(addresses_encoded), Bytes::from(input), false))
     }
@@ -202,6 +262,31 @@ im not even an address lol
         );
     }
 
+    #[test]
+    fn test_swan_post_processor_total_cost() {
+        assert_eq!(
+            parse_total_cost(" 6000000000000000\n"),
+            Some(U256::from(6000000000000000u64))
+        );
+        for cost in [
+            "0x10",
+            "0b1",
+            "-5",
+            "0.006",
+            "6,000",
+            "6000 wei",
+            "",
+            "9".repeat(80).as_str(),
+        ] {
+            assert_eq!(parse_total_cost(cost), None, "{} must be rejected", cost);
+        }
+
+        let post_processor = SwanPurchasePostProcessor::new("<shop_list>", "</shop_list>")
+            .with_total_cost("<total_cost>", "</total_cost>");
+        let input = "<shop_list>\n</shop_list>\n<total_cost>0x10</total_cost>";
+        assert!(post_processor.post_process(input.to_string()).is_err());
+    }
+
     /// Run command:
     ///
     /// ```sh
@@ -294,9 +379,10 @@ Write now:
         })).unwrap();
 
         let request = GenerationRequest::Workflow(workflow);
-        let output = execute_generation(&request, dkn_workflows::Model::GPT4o, None)
+        let output = execute_generation(&request, &[dkn_workflows::Model::GPT4o], None)
             .await
-            .unwrap();
+            .unwrap()
+            .output;
         println!("{}", output);
         assert!(output.contains("<reasoning>"), "must have <reasoning> tag");
         assert!(
@@ -396,9 +482,10 @@ Now, first, an omnipotent being watches you through out the day outlining what y
         )).unwrap();
 
         let request = GenerationRequest::Workflow(workflow);
-        let output = execute_generation(&request, dkn_workflows::Model::GPT4o, None)
+        let output = execute_generation(&request, &[dkn_workflows::Model::GPT4o], None)
             .await
-            .unwrap();
+            .unwrap()
+            .output;
         println!("{}", output);
 
         assert!(output.contains("<journal>"), "must have <journal> tag");
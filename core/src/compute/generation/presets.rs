@@ -0,0 +1,372 @@
+use dkn_workflows::{MessageInput, Workflow};
+use eyre::{eyre, Context, Result};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::workflow::{make_chat_workflow, make_generation_workflow};
+use crate::compute::make_validation_workflow;
+
+/// Placeholder within a preset, which is replaced by the input of the request.
+pub const INPUT_PLACEHOLDER: &str = "{{input}}";
+
+/// Preset files are checked for modifications at most once within this interval, so that each
+/// generation does not read their metadata.
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A workflow file that replaces the default generation workflow for a protocol prefix.
+#[derive(Debug)]
+struct Preset {
+    prefix: String,
+    path: PathBuf,
+    template: String,
+    /// Modification time of the file when it was loaded.
+    modified: Option<SystemTime>,
+}
+
+impl Preset {
+    fn load(prefix: String, path: PathBuf) -> Result<Self> {
+        let template = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("could not read workflow preset {}", path.display()))?;
+        render(&template, "")
+            .wrap_err_with(|| format!("invalid workflow preset {}", path.display()))?;
+        if !template.contains(INPUT_PLACEHOLDER) {
+            log::warn!(
+                "Workflow preset {} has no {} placeholder, the inputs of the requests are ignored.",
+                path.display(),
+                INPUT_PLACEHOLDER
+            );
+        }
+
+        Ok(Self {
+            modified: modified_at(&path),
+            prefix,
+            path,
+            template,
+        })
+    }
+
+    /// Returns `true` if the file has been modified since it was loaded.
+    fn is_stale(&self) -> bool {
+        modified_at(&self.path) != self.modified
+    }
+}
+
+#[inline]
+fn modified_at(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Renders the workflow of a preset with the given input.
+fn render(template: &str, input: &str) -> Result<Workflow> {
+    // the input is placed within a JSON string, so it is escaped without the quotes
+    let escaped = serde_json::to_string(input)?;
+    let escaped = &escaped[1..escaped.len() - 1];
+
+    serde_json::from_str(&template.replace(INPUT_PLACEHOLDER, escaped)).map_err(Into::into)
+}
+
+/// Workflow presets of protocols, which replace the default generation workflow of their plain string requests.
+///
+/// Each preset is a workflow JSON file for a protocol prefix, where [`INPUT_PLACEHOLDER`] is replaced by the input.
+/// Files are loaded at startup, and are reloaded when they are modified, which is checked every few seconds.
+#[derive(Debug, Default)]
+pub struct WorkflowPresets {
+    presets: RwLock<Vec<Preset>>,
+    /// Time that the files were last checked for modifications, `None` if they have not been yet.
+    checked_at: Mutex<Option<Instant>>,
+}
+
+impl WorkflowPresets {
+    /// Loads the presets from the given protocol prefixes & workflow files.
+    pub fn load(presets: impl IntoIterator<Item = (String, PathBuf)>) -> Result<Self> {
+        let presets = presets
+            .into_iter()
+            .map(|(prefix, path)| Preset::load(prefix, path))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            presets: RwLock::new(presets),
+            checked_at: Mutex::new(Some(Instant::now())),
+        })
+    }
+
+    /// Returns `true` if there are no presets.
+    pub fn is_empty(&self) -> bool {
+        self.presets.read().expect("lock poisoned").is_empty()
+    }
+
+    /// Reloads all preset files, keeping the loaded presets if any of them is invalid.
+    pub fn reload(&self) -> Result<()> {
+        let mut presets = self.presets.write().expect("lock poisoned");
+        *presets = presets
+            .iter()
+            .map(|preset| Preset::load(preset.prefix.clone(), preset.path.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(())
+    }
+
+    /// Reloads the preset files that have been modified since they were loaded, if they have not been
+    /// checked within [`STALE_CHECK_INTERVAL`].
+    fn reload_stale(&self) {
+        {
+            let mut checked_at = self.checked_at.lock().expect("lock poisoned");
+            if checked_at.is_some_and(|at| at.elapsed() < STALE_CHECK_INTERVAL) {
+                return;
+            }
+            *checked_at = Some(Instant::now());
+        }
+
+        if !self
+            .presets
+            .read()
+            .expect("lock poisoned")
+            .iter()
+            .any(Preset::is_stale)
+        {
+            return;
+        }
+
+        let mut presets = self.presets.write().expect("lock poisoned");
+        for preset in presets.iter_mut().filter(|p| p.is_stale()) {
+            match Preset::load(preset.prefix.clone(), preset.path.clone()) {
+                Ok(reloaded) => {
+                    log::info!("Reloaded workflow preset {}", preset.path.display());
+                    *preset = reloaded;
+                }
+                Err(e) => {
+                    log::warn!("Keeping the previous workflow preset: {:#}", e);
+                    // do not try again until the file is modified again
+                    preset.modified = modified_at(&preset.path);
+                }
+            }
+        }
+    }
+
+    /// Returns the workflow of the preset for the given protocol with the given input, if there is one.
+    ///
+    /// The preset with the longest matching prefix is used, e.g. `swan-agent` over `swan`.
+    pub fn workflow_for(&self, protocol: &str, input: &str) -> Result<Option<Workflow>> {
+        self.reload_stale();
+
+        let presets = self.presets.read().expect("lock poisoned");
+        presets
+            .iter()
+            .filter(|preset| protocol.starts_with(&preset.prefix))
+            .max_by_key(|preset| preset.prefix.len())
+            .map(|preset| render(&preset.template, input))
+            .transpose()
+    }
+}
+
+/// A workflow that is built into the oracle, which can be used as a starting point for custom workflows
+/// & the workflow presets of protocols, see [`WorkflowPresets`].
+///
+/// The input of a request is marked with [`INPUT_PLACEHOLDER`] within each workflow. The `chat` & `validation`
+/// workflows have an example history & example generations in place of the ones of a task, so they must be
+/// edited before they are used as a preset; the other ones can be given to `serve --workflow-preset` as is.
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltinPreset {
+    /// Name of the preset, e.g. `generation`.
+    pub name: &'static str,
+    /// What the workflow does.
+    pub description: &'static str,
+    build: fn() -> Result<Value>,
+}
+
+impl BuiltinPreset {
+    /// Returns the workflow of the preset as JSON.
+    pub fn to_json(&self) -> Result<Value> {
+        (self.build)()
+    }
+
+    /// Returns the workflow of the preset.
+    pub fn workflow(&self) -> Result<Workflow> {
+        serde_json::from_value(self.to_json()?).map_err(Into::into)
+    }
+
+    /// Returns the preset with the given name, or an error with the names of the presets.
+    pub fn find(name: &str) -> Result<&'static Self> {
+        BUILTIN_PRESETS
+            .iter()
+            .find(|preset| preset.name == name)
+            .ok_or_else(|| {
+                eyre!(
+                    "unknown preset {}, expected one of: {}",
+                    name,
+                    BUILTIN_PRESETS.map(|preset| preset.name).join(", ")
+                )
+            })
+    }
+}
+
+/// The built-in workflows.
+pub const BUILTIN_PRESETS: [BuiltinPreset; 5] = [
+    BuiltinPreset {
+        name: "generation",
+        description: "Generates a response to the input, used for plain string requests.",
+        build: generation,
+    },
+    BuiltinPreset {
+        name: "chat",
+        description: "Continues a conversation with the input, used for chat requests with a history (an example one here).",
+        build: chat,
+    },
+    BuiltinPreset {
+        name: "validation",
+        description: "Scores the generations of a task against its input, used by the validators (example ones here).",
+        build: validation,
+    },
+    BuiltinPreset {
+        name: "system-prompt",
+        description: "Generates a response to the input following a system prompt, e.g. a persona or an output format.",
+        build: system_prompt,
+    },
+    BuiltinPreset {
+        name: "summarize",
+        description: "Summarizes the input into a few sentences.",
+        build: summarize,
+    },
+];
+
+fn generation() -> Result<Value> {
+    let (workflow, _) = make_generation_workflow(INPUT_PLACEHOLDER.to_string())?;
+    Ok(serde_json::to_value(workflow)?)
+}
+
+fn chat() -> Result<Value> {
+    let history = vec![
+        MessageInput::new_user_message("What is the capital of France?".to_string()),
+        MessageInput::new_assistant_message("The capital of France is Paris.".to_string()),
+    ];
+    let (workflow, _) = make_chat_workflow(history, INPUT_PLACEHOLDER.to_string(), None, None)?;
+    Ok(serde_json::to_value(workflow)?)
+}
+
+fn validation() -> Result<Value> {
+    let generations = vec![
+        "<first generation>".to_string(),
+        "<second generation>".to_string(),
+    ];
+    let (workflow, _) = make_validation_workflow(INPUT_PLACEHOLDER.to_string(), generations)?;
+    Ok(serde_json::to_value(workflow)?)
+}
+
+fn system_prompt() -> Result<Value> {
+    with_system_prompt(
+        generation()?,
+        "You are a helpful assistant. Answer concisely, and say so when you do not know the answer.",
+    )
+}
+
+fn summarize() -> Result<Value> {
+    with_system_prompt(
+        generation()?,
+        "Summarize the text given by the user in at most three sentences, keeping the key facts and leaving out the details.",
+    )
+}
+
+/// Places a system message before the messages of the first task of the workflow.
+fn with_system_prompt(mut workflow: Value, prompt: &str) -> Result<Value> {
+    let messages = workflow["tasks"][0]["messages"]
+        .as_array_mut()
+        .ok_or_else(|| eyre!("workflow has no messages"))?;
+    messages.insert(0, json!({ "role": "system", "content": prompt }));
+
+    Ok(workflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+    use crate::{lint_workflow, LintSeverity};
+
+    const PRESET: &str = r#"{
+        "config": { "max_steps": 10, "max_time": 50, "tools": [""] },
+        "tasks": [
+            {
+                "id": "A",
+                "name": "Generate",
+                "description": "Generates with a custom system prompt",
+                "operator": "generation",
+                "messages": [
+                    { "role": "system", "content": "Answer like a pirate." },
+                    { "role": "user", "content": "{{input}}" }
+                ],
+                "outputs": [{ "type": "write", "key": "result", "value": "__result" }]
+            },
+            {
+                "id": "__end",
+                "operator": "end",
+                "messages": [{ "role": "user", "content": "End of the task" }]
+            }
+        ],
+        "steps": [{ "source": "A", "target": "__end" }],
+        "return_value": { "input": { "type": "read", "key": "result" } }
+    }"#;
+
+    #[test]
+    fn test_workflow_presets() {
+        let dir = TestDir::new("presets");
+        let path = dir.join("preset.json");
+        std::fs::write(&path, PRESET).unwrap();
+
+        let presets = WorkflowPresets::load([("pirate".to_string(), path.clone())]).unwrap();
+        assert!(presets
+            .workflow_for("pirate-talk/0.1.0", "say \"hello\"")
+            .unwrap()
+            .is_some());
+        assert!(presets
+            .workflow_for("dria/0.1.0", "hello")
+            .unwrap()
+            .is_none());
+
+        // invalid files are rejected
+        std::fs::write(&path, "not a workflow").unwrap();
+        assert!(presets.reload().is_err());
+        assert!(!presets.is_empty());
+
+        // the loaded preset is kept while the file is invalid
+        assert!(presets
+            .workflow_for("pirate-talk/0.1.0", "hello")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_builtin_presets() {
+        for preset in BUILTIN_PRESETS {
+            assert!(
+                preset.workflow().is_ok(),
+                "{} is not a workflow",
+                preset.name
+            );
+
+            // the presets are a starting point, so they should have no errors
+            let json = preset.to_json().unwrap().to_string();
+            let errors = lint_workflow(&json)
+                .into_iter()
+                .filter(|issue| issue.severity == LintSeverity::Error)
+                .collect::<Vec<_>>();
+            assert!(errors.is_empty(), "{}: {:?}", preset.name, errors);
+            assert!(json.contains(INPUT_PLACEHOLDER), "{}", preset.name);
+        }
+
+        assert!(BuiltinPreset::find("chat").is_ok());
+        assert!(BuiltinPreset::find("unknown").is_err());
+
+        // a dumped preset can be served as is
+        let dir = TestDir::new("builtin-presets");
+        let path = dir.join("summarize.json");
+        let json = BuiltinPreset::find("summarize").unwrap().to_json().unwrap();
+        std::fs::write(&path, json.to_string()).unwrap();
+        let presets = WorkflowPresets::load([("summary".to_string(), path.clone())]).unwrap();
+        assert!(presets
+            .workflow_for("summary/0.1.0", "some text")
+            .unwrap()
+            .is_some());
+    }
+}
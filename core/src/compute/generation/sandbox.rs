@@ -0,0 +1,359 @@
+use eyre::{eyre, Result};
+use reqwest::{redirect, Url};
+use serde_json::Value;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+/// The tool name that enables all built-in tools of a workflow.
+const ALL_TOOLS: &str = "ALL";
+
+/// Custom tools that do not respond within this duration are not checked for redirects.
+const REDIRECT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Limits on the tools of the workflows that are given by the requesters, to protect the
+/// operator from abuse such as requests to its internal network (SSRF).
+///
+/// The limits are applied to the workflow JSON before it is executed:
+/// - built-in tools (`config.tools`) are checked against the allowed & denied tools, where `ALL` is narrowed
+///   down to the allowed tools,
+/// - custom HTTP tools (`config.custom_tools`) must target a public host within the allowed domains, if given,
+/// - workflows that use tools have their `max_steps` capped, which bounds the number of tool calls.
+///
+/// Custom tools that target private, loopback or link-local addresses are always rejected, including the
+/// domains that resolve to such addresses, and so are the ones that redirect.
+///
+/// These checks are best-effort, as the requests of the tools are made by the workflow executor, which
+/// resolves the domains again: a domain that resolves to a private address only afterwards (DNS rebinding)
+/// is not caught, and neither is a tool that redirects only afterwards. For a strict guarantee, the requests
+/// of the node must go through an egress proxy (e.g. with `HTTPS_PROXY`) that blocks the private addresses.
+#[derive(Debug, Clone, Default)]
+pub struct ToolSandbox {
+    /// Built-in tools that can be used, all tools are allowed if `None`.
+    pub allowed_tools: Option<Vec<String>>,
+    /// Built-in tools that can not be used.
+    pub denied_tools: Vec<String>,
+    /// Domains that custom tools can target (including their subdomains), any public domain if `None`.
+    pub allowed_domains: Option<Vec<String>>,
+    /// Maximum number of steps of a workflow that uses tools.
+    pub max_tool_steps: Option<u64>,
+}
+
+impl ToolSandbox {
+    /// Applies the limits to the given input if it is a workflow, returns other inputs as is.
+    ///
+    /// Returns an error if the workflow uses a tool that is not allowed.
+    pub async fn sandbox_input(&self, input: String) -> Result<String> {
+        let Ok(mut workflow) = serde_json::from_str::<Value>(&input) else {
+            return Ok(input);
+        };
+        let Some(config) = workflow.get_mut("config").and_then(Value::as_object_mut) else {
+            return Ok(input);
+        };
+
+        // built-in tools
+        let mut uses_tools = false;
+        if let Some(tools) = config.get_mut("tools").and_then(Value::as_array_mut) {
+            let mut sandboxed = Vec::new();
+            for tool in tools
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|t| !t.is_empty())
+            {
+                sandboxed.extend(self.sandbox_tool(tool)?);
+            }
+            uses_tools = !sandboxed.is_empty();
+            *tools = match sandboxed.is_empty() {
+                // an empty tool name stands for no tools
+                true => vec![Value::from("")],
+                false => sandboxed.into_iter().map(Value::from).collect(),
+            };
+        }
+
+        // custom tools
+        if let Some(custom_tools) = config.get("custom_tools").filter(|t| !t.is_null()) {
+            let mut urls = Vec::new();
+            collect_urls(custom_tools, &mut urls);
+            for url in urls {
+                self.check_resolved_url(url).await?;
+            }
+            uses_tools = true;
+        }
+
+        if let (true, Some(max_tool_steps)) = (uses_tools, self.max_tool_steps) {
+            let max_steps = config.get("max_steps").and_then(Value::as_u64);
+            if max_steps.map_or(true, |steps| steps > max_tool_steps) {
+                config.insert("max_steps".to_string(), Value::from(max_tool_steps));
+            }
+        }
+
+        Ok(workflow.to_string())
+    }
+
+    /// Returns the tools that the given tool is allowed as, which is all allowed tools for `ALL`.
+    fn sandbox_tool(&self, tool: &str) -> Result<Vec<String>> {
+        if tool == ALL_TOOLS {
+            return match &self.allowed_tools {
+                Some(allowed) => Ok(allowed
+                    .iter()
+                    .filter(|t| !self.denied_tools.contains(t))
+                    .cloned()
+                    .collect()),
+                // we can not know which tools `ALL` stands for, so it can not be narrowed down
+                None if !self.denied_tools.is_empty() => {
+                    Err(eyre!("Workflow uses all tools, while some are denied"))
+                }
+                None => Ok(vec![tool.to_string()]),
+            };
+        }
+
+        let is_allowed = self
+            .allowed_tools
+            .as_ref()
+            .map_or(true, |allowed| allowed.iter().any(|t| t == tool));
+        if !is_allowed || self.denied_tools.iter().any(|t| t == tool) {
+            return Err(eyre!("Workflow uses a tool that is not allowed: {}", tool));
+        }
+
+        Ok(vec![tool.to_string()])
+    }
+
+    /// Checks that the URL of a custom tool targets a public host within the allowed domains, like
+    /// [`Self::check_url`], that its domain does not resolve to a private address, and that it does
+    /// not redirect (see [`check_redirect`]).
+    ///
+    /// A domain that can not be resolved is rejected as well, as it may resolve differently later.
+    async fn check_resolved_url(&self, url: &str) -> Result<()> {
+        self.check_url(url)?;
+
+        let url = Url::parse(url)?;
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            return Ok(());
+        };
+        if host.starts_with('[') || host.parse::<IpAddr>().is_ok() {
+            return check_redirect(&url, None).await;
+        }
+
+        let target = (host.to_string(), port);
+        let addrs = tokio::task::spawn_blocking(move || target.to_socket_addrs())
+            .await?
+            .map_err(|e| eyre!("Could not resolve custom tool host {}: {}", host, e))?
+            .collect::<Vec<_>>();
+        for addr in &addrs {
+            if is_private_ip(addr.ip()) {
+                return Err(eyre!(
+                    "Custom tool targets a host that resolves to a private address: {} ({})",
+                    host,
+                    addr.ip()
+                ));
+            }
+        }
+
+        check_redirect(&url, addrs.first().copied()).await
+    }
+
+    /// Checks that the URL of a custom tool targets a public host within the allowed domains.
+    ///
+    /// Domains are not resolved here, see [`Self::check_resolved_url`].
+    pub(super) fn check_url(&self, url: &str) -> Result<()> {
+        let url = Url::parse(url).map_err(|e| eyre!("Invalid custom tool URL {}: {}", url, e))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| eyre!("Custom tool URL has no host: {}", url))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_lowercase();
+
+        let is_private = match host.parse::<IpAddr>() {
+            Ok(ip) => is_private_ip(ip),
+            Err(_) => {
+                host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local")
+            }
+        };
+        if is_private {
+            return Err(eyre!("Custom tool targets a private host: {}", host));
+        }
+
+        if let Some(domains) = &self.allowed_domains {
+            let is_allowed = domains
+                .iter()
+                .any(|d| host == *d || host.ends_with(&format!(".{}", d)));
+            if !is_allowed {
+                return Err(eyre!(
+                    "Custom tool targets a domain that is not allowed: {}",
+                    host
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rejects the URL of a custom tool if it redirects, as the executor follows the redirects to any host,
+/// e.g. to a private address. The request is made to the given address, which has been checked already,
+/// instead of resolving the domain again.
+///
+/// A tool that can not be reached is not rejected, as it may still work with another method.
+async fn check_redirect(url: &Url, resolved: Option<SocketAddr>) -> Result<()> {
+    let mut client = reqwest::Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(REDIRECT_CHECK_TIMEOUT);
+    if let (Some(host), Some(addr)) = (url.host_str(), resolved) {
+        client = client.resolve(host, addr);
+    }
+
+    let Ok(response) = client.build()?.head(url.clone()).send().await else {
+        return Ok(());
+    };
+    if response.status().is_redirection() {
+        return Err(eyre!(
+            "Custom tool redirects, which is not allowed: {}",
+            url
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the address is not a public one, e.g. a private, loopback, link-local or
+/// shared (CGNAT) address.
+fn is_private_ip(ip: IpAddr) -> bool {
+    fn is_private_v4(ip: Ipv4Addr) -> bool {
+        let [a, b, ..] = ip.octets();
+        ip.is_private()
+            || ip.is_loopback()
+            || ip.is_link_local()
+            || ip.is_unspecified()
+            || ip.is_broadcast()
+            // "this network" (0.0.0.0/8) & shared address space (100.64.0.0/10)
+            || a == 0
+            || (a == 100 && (b & 0xc0) == 64)
+    }
+
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => {
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // unique local (fc00::/7) & link-local (fe80::/10) addresses
+                || (ip.segments()[0] & 0xfe00) == 0xfc00
+                || (ip.segments()[0] & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(is_private_v4)
+        }
+    }
+}
+
+/// Collects the values of the `url` fields within the given value, recursively.
+fn collect_urls<'a>(value: &'a Value, urls: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value.as_str()) {
+                    ("url", Some(url)) => urls.push(url),
+                    _ => collect_urls(value, urls),
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|v| collect_urls(v, urls)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow(tools: &str, custom_tools: &str) -> String {
+        format!(
+            r#"{{"config": {{"max_steps": 50, "max_time": 200, "tools": {}, "custom_tools": {}}}, "tasks": []}}"#,
+            tools, custom_tools
+        )
+    }
+
+    fn config(input: &str) -> Value {
+        serde_json::from_str::<Value>(input).unwrap()["config"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_tools() {
+        let sandbox = ToolSandbox {
+            allowed_tools: Some(vec!["jina".to_string(), "serper".to_string()]),
+            denied_tools: vec!["serper".to_string()],
+            max_tool_steps: Some(20),
+            ..Default::default()
+        };
+
+        // all tools are narrowed down to the allowed ones, and steps are capped
+        let sandboxed = sandbox
+            .sandbox_input(workflow(r#"["ALL"]"#, "null"))
+            .await
+            .unwrap();
+        assert_eq!(config(&sandboxed)["tools"], serde_json::json!(["jina"]));
+        assert_eq!(config(&sandboxed)["max_steps"], 20);
+
+        // no tools, no limits
+        let sandboxed = sandbox
+            .sandbox_input(workflow(r#"[""]"#, "null"))
+            .await
+            .unwrap();
+        assert_eq!(config(&sandboxed)["max_steps"], 50);
+
+        assert!(sandbox
+            .sandbox_input(workflow(r#"["serper"]"#, "null"))
+            .await
+            .is_err());
+        assert!(sandbox
+            .sandbox_input(workflow(r#"["browserless"]"#, "null"))
+            .await
+            .is_err());
+
+        // plain inputs are not changed
+        assert_eq!(sandbox.sandbox_input("hi".to_string()).await.unwrap(), "hi");
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_custom_tools() {
+        let sandbox = ToolSandbox {
+            allowed_domains: Some(vec!["example.com".to_string()]),
+            ..Default::default()
+        };
+        let custom_tool = |url: &str| format!(r#"[{{"name": "fetch", "url": "{}"}}]"#, url);
+
+        // the domains are resolved only when sandboxing, so the allowlist is checked without them
+        assert!(sandbox.check_url("https://api.example.com/x").is_ok());
+        assert!(sandbox.check_url("https://example.org").is_err());
+
+        // a domain that can not be resolved is rejected, as it may resolve to a private address later
+        assert!(sandbox
+            .sandbox_input(workflow(
+                r#"[""]"#,
+                &custom_tool("https://example.invalid/x")
+            ))
+            .await
+            .is_err());
+
+        // private hosts are rejected even without an allowlist
+        let sandbox = ToolSandbox::default();
+        for url in [
+            "http://127.0.0.1:8545",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://localhost:11434",
+            "http://localhost.:11434",
+            "http://10.0.0.1",
+            "http://100.64.0.1",
+            "http://0.0.0.1",
+            "http://[::ffff:100.100.100.100]/",
+        ] {
+            assert!(
+                sandbox
+                    .sandbox_input(workflow(r#"[""]"#, &custom_tool(url)))
+                    .await
+                    .is_err(),
+                "{} should be rejected",
+                url
+            );
+        }
+    }
+}
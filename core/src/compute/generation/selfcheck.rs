@@ -0,0 +1,257 @@
+use dkn_workflows::Model;
+use eyre::Result;
+use std::future::Future;
+
+use crate::compute::{execute_validations, Execution, RetryPolicy, TokenUsage};
+use crate::ApiBudget;
+
+use super::postprocess::post_process_protocol;
+
+/// Result of a self-check, see [`SelfCheck::check`].
+#[derive(Debug)]
+pub(crate) struct SelfChecked {
+    /// Execution to respond with.
+    pub execution: Execution,
+    /// Score of the execution to respond with, if it could be scored.
+    pub score: Option<u8>,
+    /// The other execution if the output has been regenerated, whose usage is paid for as well.
+    pub discarded: Option<Execution>,
+}
+
+/// A quick validation pass of the generator over its own output before responding,
+/// where an output that scores poorly is regenerated once.
+///
+/// This uses the same validation workflow as the validators, so an output that passes the
+/// self-check is more likely to get a good score from them as well.
+#[derive(Debug, Clone)]
+pub struct SelfCheck {
+    /// Lowest final score (within `[1, 5]`) that an output must have to be responded without regenerating.
+    pub min_score: u8,
+    /// Model to score the outputs with.
+    pub model: Model,
+}
+
+impl SelfCheck {
+    /// Creates a self-check with the given minimum score, using `gpt-4o-mini` to score the outputs.
+    pub fn new(min_score: u8) -> Self {
+        Self {
+            min_score,
+            model: Model::GPT4oMini,
+        }
+    }
+
+    /// Change the model to score the outputs with.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Scores the output of a generation, where an output that is empty or can not be post-processed
+    /// for its protocol scores zero without being validated.
+    ///
+    /// Returns `None` if the output could not be scored, e.g. due to an error of the provider.
+    async fn score(
+        &self,
+        protocol: &str,
+        instruction: &str,
+        output: &str,
+        policy: &RetryPolicy,
+        api_budget: Option<&ApiBudget>,
+    ) -> Option<u8> {
+        if output.trim().is_empty() || post_process_protocol(protocol, output.to_string()).is_err()
+        {
+            return Some(0);
+        }
+
+        match execute_validations(
+            instruction.to_string(),
+            vec![output.to_string()],
+            self.model.clone(),
+            policy,
+        )
+        .await
+        {
+            Ok(results) => {
+                if let Some(api_budget) = api_budget {
+                    let completion = serde_json::to_string(&results).unwrap_or_default();
+                    let usage =
+                        TokenUsage::estimate(&format!("{}{}", instruction, output), &completion);
+                    api_budget.record(&self.model, usage.cost_usd(&self.model));
+                }
+                results.first().map(|result| result.final_score())
+            }
+            Err(e) => {
+                log::warn!("Could not self-check the output: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Scores the execution of a generation, and regenerates it once if it scores below the minimum.
+    ///
+    /// The execution with the higher score is returned along with its score, where an execution that
+    /// could not be scored is kept as is. A regeneration that fails keeps the first execution as well.
+    pub(crate) async fn check<F, Fut>(
+        &self,
+        protocol: &str,
+        instruction: &str,
+        execution: Execution,
+        policy: &RetryPolicy,
+        api_budget: Option<&ApiBudget>,
+        regenerate: F,
+    ) -> SelfChecked
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Execution>>,
+    {
+        self.check_with(execution, regenerate, |output| async move {
+            self.score(protocol, instruction, &output, policy, api_budget)
+                .await
+        })
+        .await
+    }
+
+    /// Same as [`Self::check`], with the given scoring of the outputs.
+    async fn check_with<F, Fut, S, SFut>(
+        &self,
+        execution: Execution,
+        regenerate: F,
+        score: S,
+    ) -> SelfChecked
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Execution>>,
+        S: Fn(String) -> SFut,
+        SFut: Future<Output = Option<u8>>,
+    {
+        let first_score = score(execution.output.clone()).await;
+        let Some(first_score) = first_score.filter(|score| *score < self.min_score) else {
+            return SelfChecked {
+                execution,
+                score: first_score,
+                discarded: None,
+            };
+        };
+
+        log::info!(
+            "Regenerating as the output has scored {} in the self-check, below {}.",
+            first_score,
+            self.min_score
+        );
+        let regenerated = match regenerate().await {
+            Ok(regenerated) => regenerated,
+            Err(e) => {
+                log::warn!("Could not regenerate the output: {:#}", e);
+                return SelfChecked {
+                    execution,
+                    score: Some(first_score),
+                    discarded: None,
+                };
+            }
+        };
+        let regenerated_score = score(regenerated.output.clone()).await;
+        if regenerated_score.is_some_and(|regenerated_score| regenerated_score > first_score) {
+            SelfChecked {
+                execution: regenerated,
+                score: regenerated_score,
+                discarded: Some(execution),
+            }
+        } else {
+            SelfChecked {
+                execution,
+                score: Some(first_score),
+                discarded: Some(regenerated),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execution(output: &str) -> Execution {
+        Execution {
+            output: output.to_string(),
+            model: Model::GPT4oMini,
+            retries: Vec::new(),
+            history: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_self_check_structure() {
+        let self_check = SelfCheck::new(3);
+        let policy = RetryPolicy::default();
+
+        // a swan purchase without a shopping list is regenerated without validating
+        let checked = self_check
+            .check(
+                "swan-agent-purchase/0.1.0",
+                "What should I buy?",
+                execution("I would not buy anything."),
+                &policy,
+                None,
+                || async { Ok(execution("   ")) },
+            )
+            .await;
+        assert_eq!(checked.execution.output, "I would not buy anything.");
+        assert_eq!(checked.score, Some(0));
+        assert_eq!(checked.discarded.unwrap().output, "   ");
+    }
+
+    #[tokio::test]
+    async fn test_self_check_regeneration() {
+        let self_check = SelfCheck::new(3);
+        let score = |output: String| async move {
+            match output.as_str() {
+                "poor" => Some(2),
+                "good" => Some(4),
+                _ => None,
+            }
+        };
+
+        // an output above the minimum is not regenerated
+        let checked = self_check
+            .check_with(
+                execution("good"),
+                || async { Ok(execution("unused")) },
+                score,
+            )
+            .await;
+        assert_eq!(checked.execution.output, "good");
+        assert_eq!(checked.score, Some(4));
+        assert!(checked.discarded.is_none());
+
+        // an output below the minimum is regenerated, and the better one is kept
+        let checked = self_check
+            .check_with(execution("poor"), || async { Ok(execution("good")) }, score)
+            .await;
+        assert_eq!(checked.execution.output, "good");
+        assert_eq!(checked.score, Some(4));
+        assert_eq!(checked.discarded.unwrap().output, "poor");
+
+        // a regeneration that can not be scored is discarded
+        let checked = self_check
+            .check_with(
+                execution("poor"),
+                || async { Ok(execution("other")) },
+                score,
+            )
+            .await;
+        assert_eq!(checked.execution.output, "poor");
+        assert_eq!(checked.score, Some(2));
+        assert_eq!(checked.discarded.unwrap().output, "other");
+
+        // a regeneration that fails, e.g. as it is out of the budget, keeps the first output
+        let checked = self_check
+            .check_with(
+                execution("poor"),
+                || async { Err(eyre::eyre!("out of budget")) },
+                score,
+            )
+            .await;
+        assert_eq!(checked.execution.output, "poor");
+        assert!(checked.discarded.is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::DriaOracle;
+use crate::{BridgeEvent, DriaOracle, TraceId, WalDecision};
 use dria_oracle_contracts::{OracleKind, TaskStatus};
 
 use alloy::{
@@ -6,10 +6,13 @@ use alloy::{
     rpc::types::TransactionReceipt,
 };
 use eyre::Result;
+use std::future::Future;
+use std::time::Instant;
 
-use super::{handle_generation, handle_validation};
+use super::{handle_generation, handle_validation, task_skip_reason};
 
-/// Handles a task request.
+/// Handles a task request, as an attempt with a trace id of its own that the log lines,
+/// events & records of the attempt are tagged with.
 ///
 /// - Generation tasks are forwarded to `handle_generation`
 /// - Validation tasks are forwarded to `handle_validation`
@@ -18,32 +21,46 @@ pub async fn handle_request(
     status: TaskStatus,
     task_id: U256,
     protocol: FixedBytes<32>,
+) -> Result<Option<TransactionReceipt>> {
+    TraceId::new()
+        .scope(handle_task(node, status, task_id, protocol))
+        .await
+}
+
+/// Handles a task request within the scope of its trace id.
+async fn handle_task(
+    node: &DriaOracle,
+    status: TaskStatus,
+    task_id: U256,
+    protocol: FixedBytes<32>,
 ) -> Result<Option<TransactionReceipt>> {
     log::debug!("Received event for task {} ()", task_id);
 
     // we check the `statusAfter` field of the event, which indicates the final status of the listened task
     let response_receipt = match status {
         TaskStatus::PendingGeneration => {
-            if node.kinds.contains(&OracleKind::Generator) {
-                handle_generation(node, task_id, protocol).await?
-            } else {
+            if !node.kinds.contains(&OracleKind::Generator) {
                 log::debug!(
                     "Ignoring generation task {} as you are not generator.",
                     task_id
                 );
                 return Ok(None);
             }
+
+            let generation = handle_generation(node, task_id, protocol);
+            handle_kind(node, OracleKind::Generator, task_id, generation).await?
         }
         TaskStatus::PendingValidation => {
-            if node.kinds.contains(&OracleKind::Validator) {
-                handle_validation(node, task_id).await?
-            } else {
+            if !node.kinds.contains(&OracleKind::Validator) {
                 log::debug!(
-                    "Ignoring generation task {} as you are not validator.",
+                    "Ignoring validation task {} as you are not validator.",
                     task_id
                 );
                 return Ok(None);
             }
+
+            let validation = handle_validation(node, task_id);
+            handle_kind(node, OracleKind::Validator, task_id, validation).await?
         }
         TaskStatus::Completed => {
             return Ok(None);
@@ -60,7 +77,7 @@ pub async fn handle_request(
         log::info!(
             "Task {} processed successfully. (tx: {})",
             task_id,
-            receipt.transaction_hash
+            node.explorer.tx(receipt.transaction_hash)
         );
     } else {
         log::debug!("Task {} ignored.", task_id)
@@ -68,3 +85,61 @@ pub async fn handle_request(
 
     Ok(response_receipt)
 }
+
+/// Handles a task as the given kind with the given future, unless it is skipped: the response has
+/// been submitted already, the acceptance policy skips it, or another replica is handling it.
+///
+/// The decisions on the task are logged, and its response is published to the event bridge.
+async fn handle_kind(
+    node: &DriaOracle,
+    kind: OracleKind,
+    task_id: U256,
+    fut: impl Future<Output = Result<Option<TransactionReceipt>>>,
+) -> Result<Option<TransactionReceipt>> {
+    if node.has_submitted_response(task_id, kind).await? {
+        log::info!("Already submitted a {} response to task {}", kind, task_id);
+        let reason = "already submitted a response".to_string();
+        node.log_decision(task_id, kind, WalDecision::Skipped { reason })
+            .await;
+        return Ok(None);
+    }
+    if let Some(reason) = task_skip_reason(node, kind, task_id).await? {
+        node.log_decision(task_id, kind, WalDecision::Skipped { reason })
+            .await;
+        return Ok(None);
+    }
+    let _permit = node.capacity.acquire().await?;
+    if !node.try_lock_task(task_id, kind).await? {
+        log::info!("Task {} is being handled by another replica", task_id);
+        let reason = "handled by another replica".to_string();
+        node.log_decision(task_id, kind, WalDecision::Skipped { reason })
+            .await;
+        return Ok(None);
+    }
+
+    node.log_decision(task_id, kind, WalDecision::Accepted)
+        .await;
+    let started_at = Instant::now();
+    let result = fut.await;
+    if result.is_ok() {
+        node.unlock_task(task_id, kind).await;
+    }
+    node.log_decision(task_id, kind, WalDecision::after(&result))
+        .await;
+    if let Some(event) = BridgeEvent::response(
+        *node.coordinator.address(),
+        task_id,
+        kind,
+        &result,
+        |receipt| receipt.transaction_hash,
+    ) {
+        node.publish_event(event);
+    }
+
+    let receipt = result?;
+    if receipt.is_some() {
+        node.execution_times.record(kind, started_at.elapsed());
+    }
+
+    Ok(receipt)
+}
@@ -0,0 +1,367 @@
+use eyre::{eyre, Context, Result};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default time-to-live of a Redis lock, after which it expires unless it is renewed by its holder,
+/// e.g. as its holder has crashed. Held locks are renewed every third of it.
+const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(60);
+
+/// Prefix of the lock keys within Redis.
+#[cfg(feature = "redis")]
+const REDIS_KEY_PREFIX: &str = "dria-oracle:lock:";
+
+/// Deletes a key only if it is still held by the given owner.
+#[cfg(feature = "redis")]
+const REDIS_RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+
+/// Renews the expiry of a key only if it is still held by the given owner.
+#[cfg(feature = "redis")]
+const REDIS_RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+
+/// Where the locks are kept.
+#[derive(Debug, Clone)]
+enum LockBackend {
+    /// A lock file per key within a directory, for replicas on the same host, which is locked by the
+    /// operating system so that it is released as soon as its holder exits.
+    File(PathBuf),
+    /// A key per lock within a Redis server, for replicas across hosts.
+    #[cfg(feature = "redis")]
+    Redis(RedisConnection),
+}
+
+/// A Redis client along with its connection, which is opened on first use and is shared by the clones,
+/// reconnecting when it is dropped.
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+struct RedisConnection {
+    client: redis::Client,
+    conn: Arc<tokio::sync::OnceCell<redis::aio::ConnectionManager>>,
+}
+
+#[cfg(feature = "redis")]
+impl RedisConnection {
+    /// Returns the connection, opening it on first use.
+    async fn get(&self) -> Result<redis::aio::ConnectionManager> {
+        self.conn
+            .get_or_try_init(|| redis::aio::ConnectionManager::new(self.client.clone()))
+            .await
+            .cloned()
+            .wrap_err("could not connect to Redis")
+    }
+}
+
+#[cfg(feature = "redis")]
+impl std::fmt::Debug for RedisConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisConnection")
+            .field("client", &self.client)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A lock held by this process.
+#[derive(Debug)]
+enum HeldLock {
+    /// The open lock file, which is unlocked when it is closed.
+    File(File),
+    /// The task that renews the expiry of the key.
+    #[cfg(feature = "redis")]
+    Redis(tokio::task::AbortHandle),
+}
+
+/// A lock acquired per task before handling it, so that multiple replicas of the same wallet
+/// share the tasks instead of racing each other for them.
+///
+/// Created from a URL, either `file://<dir>` or `redis://<host>` (requires the `redis` feature).
+/// The locks are held until they are released, however long the task takes, and the clones share them.
+#[derive(Debug, Clone)]
+pub struct TaskLock {
+    backend: LockBackend,
+    /// Identifies this process as the holder of its locks.
+    owner: String,
+    /// Time after which a Redis lock expires unless it is renewed.
+    ttl: Duration,
+    /// Locks held by this process, by their keys.
+    held: Arc<Mutex<HashMap<String, HeldLock>>>,
+}
+
+impl TaskLock {
+    /// Creates a lock that keeps a lock file per key within the given directory.
+    pub fn file(dir: impl Into<PathBuf>) -> Self {
+        Self::new(LockBackend::File(dir.into()))
+    }
+
+    /// Creates a lock that keeps a key per lock within the Redis server at the given URL.
+    #[cfg(feature = "redis")]
+    pub fn redis(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).wrap_err("could not parse Redis URL")?;
+        Ok(Self::new(LockBackend::Redis(RedisConnection {
+            client,
+            conn: Arc::default(),
+        })))
+    }
+
+    fn new(backend: LockBackend) -> Self {
+        Self {
+            backend,
+            owner: format!("{}-{:016x}", std::process::id(), rand::random::<u64>()),
+            ttl: DEFAULT_LOCK_TTL,
+            held: Arc::default(),
+        }
+    }
+
+    /// Change the time after which a Redis lock expires unless it is renewed, e.g. as its holder has crashed.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Tries to acquire the lock for the given key.
+    ///
+    /// Returns `false` if it is held by someone else, which can be this process as well.
+    pub async fn try_acquire(&self, key: &str) -> Result<bool> {
+        if self.held.lock().expect("lock poisoned").contains_key(key) {
+            return Ok(false);
+        }
+
+        let held = match &self.backend {
+            LockBackend::File(dir) => {
+                // file locks are taken on a blocking thread, so that a slow disk does not stall the runtime
+                let path = dir.join(format!("{}.lock", key));
+                let (dir, owner) = (dir.clone(), self.owner.clone());
+                let file =
+                    tokio::task::spawn_blocking(move || acquire_file_lock(&dir, &path, &owner))
+                        .await??;
+                let Some(file) = file else {
+                    return Ok(false);
+                };
+
+                HeldLock::File(file)
+            }
+            #[cfg(feature = "redis")]
+            LockBackend::Redis(redis) => {
+                let redis_key = format!("{}{}", REDIS_KEY_PREFIX, key);
+                let mut conn = redis.get().await?;
+                let reply: Option<String> = redis::cmd("SET")
+                    .arg(&redis_key)
+                    .arg(&self.owner)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(self.ttl.as_millis() as u64)
+                    .query_async(&mut conn)
+                    .await
+                    .wrap_err("could not acquire lock")?;
+                if reply.is_none() {
+                    return Ok(false);
+                }
+
+                let renewal = tokio::spawn(renew_redis_lock(
+                    conn,
+                    redis_key,
+                    self.owner.clone(),
+                    self.ttl,
+                ));
+                HeldLock::Redis(renewal.abort_handle())
+            }
+        };
+        self.held
+            .lock()
+            .expect("lock poisoned")
+            .insert(key.to_string(), held);
+
+        Ok(true)
+    }
+
+    /// Releases the lock for the given key, if it is held by this process.
+    pub async fn release(&self, key: &str) -> Result<()> {
+        let Some(held) = self.held.lock().expect("lock poisoned").remove(key) else {
+            return Ok(());
+        };
+
+        match (&self.backend, held) {
+            (LockBackend::File(dir), HeldLock::File(file)) => {
+                let path = dir.join(format!("{}.lock", key));
+                tokio::task::spawn_blocking(move || release_file_lock(&path, file)).await?
+            }
+            #[cfg(feature = "redis")]
+            (LockBackend::Redis(redis), HeldLock::Redis(renewal)) => {
+                renewal.abort();
+                let mut conn = redis.get().await?;
+                let _: i64 = redis::Script::new(REDIS_RELEASE_SCRIPT)
+                    .key(format!("{}{}", REDIS_KEY_PREFIX, key))
+                    .arg(&self.owner)
+                    .invoke_async(&mut conn)
+                    .await
+                    .wrap_err("could not release lock")?;
+
+                Ok(())
+            }
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("lock is held by its backend"),
+        }
+    }
+}
+
+/// Locks the lock file at the given path within the directory, unless it is locked by someone else.
+///
+/// Returns the open file, which holds the lock until it is closed.
+fn acquire_file_lock(dir: &Path, path: &Path, owner: &str) -> Result<Option<File>> {
+    fs::create_dir_all(dir)
+        .wrap_err_with(|| format!("could not create lock dir {}", dir.display()))?;
+
+    loop {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .wrap_err_with(|| format!("could not open lock file {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => {
+                return Err(e).wrap_err_with(|| format!("could not lock file {}", path.display()))
+            }
+        }
+
+        // the previous holder removes the file before unlocking it, so a file that has been removed
+        // after it was opened is locked in vain, and the new one is tried instead
+        if is_same_file(&file, path) {
+            file.set_len(0)
+                .and_then(|()| file.write_all(owner.as_bytes()))
+                .wrap_err("could not write lock file")?;
+            return Ok(Some(file));
+        }
+    }
+}
+
+/// Returns `true` if the open file is still the one at the given path.
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+/// Returns `true` if the open file is still the one at the given path, which it always is
+/// as the lock files are not removed on these platforms (see [`release_file_lock`]).
+#[cfg(not(unix))]
+fn is_same_file(_: &File, _: &Path) -> bool {
+    true
+}
+
+/// Unlocks the given lock file, which is removed before so that the lock files do not pile up.
+fn release_file_lock(path: &Path, file: File) -> Result<()> {
+    // an open file can not be removed on the other platforms, so the file is kept there
+    #[cfg(unix)]
+    fs::remove_file(path)
+        .wrap_err_with(|| format!("could not remove lock file {}", path.display()))?;
+    #[cfg(not(unix))]
+    let _ = path;
+    drop(file);
+
+    Ok(())
+}
+
+/// Renews the expiry of the given Redis lock every third of its time-to-live, until it is aborted
+/// on release or the lock is no longer held by the given owner.
+#[cfg(feature = "redis")]
+async fn renew_redis_lock(
+    mut conn: redis::aio::ConnectionManager,
+    key: String,
+    owner: String,
+    ttl: Duration,
+) {
+    let mut renewal = tokio::time::interval((ttl / 3).max(Duration::from_secs(1)));
+    renewal.tick().await;
+    loop {
+        renewal.tick().await;
+        let reply: redis::RedisResult<i64> = redis::Script::new(REDIS_RENEW_SCRIPT)
+            .key(&key)
+            .arg(&owner)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async(&mut conn)
+            .await;
+        match reply {
+            Ok(1) => {}
+            Ok(_) => {
+                log::warn!("Lock {} is no longer held, stopping its renewal", key);
+                return;
+            }
+            Err(e) => log::warn!("Could not renew lock {}: {}", key, e),
+        }
+    }
+}
+
+impl FromStr for TaskLock {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(dir) = s.strip_prefix("file://") {
+            return Ok(Self::file(dir));
+        }
+
+        if s.starts_with("redis://") || s.starts_with("rediss://") {
+            #[cfg(feature = "redis")]
+            return Self::redis(s);
+            #[cfg(not(feature = "redis"))]
+            return Err(eyre!("Redis locks require the `redis` feature"));
+        }
+
+        Err(eyre!(
+            "Invalid lock URL: {}, expected file://<dir> or redis://<host>",
+            s
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    #[tokio::test]
+    async fn test_file_lock() {
+        let dir = TestDir::new("lock");
+        let lock = TaskLock::from_str(&format!("file://{}", dir.path().display())).unwrap();
+        let replica = TaskLock::file(dir.path());
+
+        assert!(lock.try_acquire("task-1").await.unwrap());
+        assert!(!lock.try_acquire("task-1").await.unwrap());
+        assert!(!replica.try_acquire("task-1").await.unwrap());
+        assert!(replica.try_acquire("task-2").await.unwrap());
+
+        // only the holder can release its lock
+        replica.release("task-1").await.unwrap();
+        assert!(!replica.try_acquire("task-1").await.unwrap());
+        lock.release("task-1").await.unwrap();
+        assert!(!dir.join("task-1.lock").exists() || cfg!(not(unix)));
+        assert!(replica.try_acquire("task-1").await.unwrap());
+
+        // locks are released when their holder exits, however long it has held them
+        assert!(!lock.try_acquire("task-2").await.unwrap());
+        drop(replica);
+        assert!(lock.try_acquire("task-2").await.unwrap());
+
+        assert!(TaskLock::from_str("postgres://localhost").is_err());
+    }
+}
@@ -2,16 +2,81 @@ mod handler;
 pub use handler::handle_request;
 
 mod nonce;
-pub use nonce::mine_nonce;
+pub(crate) use nonce::mine_nonce_blocking;
+pub use nonce::{
+    benchmark_hash_rate, estimate_mining_time, expected_hashes, mine_nonce, MiningStats,
+    NonceResult,
+};
 
 mod generation;
-pub use generation::handle_generation;
+pub use generation::{
+    check_workflow, handle_generation, lint_workflow, BuiltinPreset, ChatHistoryPolicy,
+    GuardrailAction, GuardrailDecision, Guardrails, HistoryCompression, InputLimits, LintIssue,
+    LintSeverity, MockFixture, MockModel, SelfCheck, ToolSandbox, WorkflowPresets, BUILTIN_PRESETS,
+};
+pub(crate) use generation::{
+    make_chat_workflow, make_generation_workflow, ChatHistoryRequest, GenerationRequest,
+    PostProcess, SwanPurchasePostProcessor, INPUT_PLACEHOLDER, SHOP_LIST_END_MARKER,
+    SHOP_LIST_START_MARKER,
+};
 
 pub mod validation;
-pub use validation::handle_validation;
+pub(crate) use validation::{execute_validations, make_validation_workflow};
+pub use validation::{handle_validation, ScoreMap, ScoreMaps};
+
+mod benchmark;
+pub use benchmark::{benchmark_model, GenerationSample, ModelBenchmark};
 
 mod utils;
-use utils::parse_downloadable;
+use utils::{exceeds_gas_ceiling, parse_downloadable, parse_downloadable_within};
 
 mod execute;
-use execute::execute_workflow_with_timedout_retries;
+use execute::{execute_workflow_with_timedout_retries, Execution};
+pub use execute::{ErrorClass, FailedAttempt, RetryPolicy};
+
+mod costs;
+use costs::TokenUsage;
+pub(crate) use costs::{estimate_tokens, CHARS_PER_TOKEN};
+
+mod deadline;
+use deadline::execute_within_budget;
+pub use deadline::{ExecutionTimes, TaskBudget};
+
+mod timings;
+pub(crate) use timings::Stopwatch;
+pub use timings::TaskTimings;
+
+mod spending;
+pub use spending::{ApiBudget, GasBudget};
+
+mod ollama;
+pub use ollama::{OllamaAutoPull, OllamaClient, OllamaModel};
+
+mod vram;
+pub use vram::{VramProbe, VramReservation};
+
+mod warmup;
+pub(crate) use warmup::with_ollama_checks;
+pub use warmup::ModelWarmup;
+
+mod queue;
+pub use queue::{QueueOrder, QueuePolicy, QueuedTask, TaskQueue};
+
+mod shard;
+pub use shard::Shard;
+
+mod gate;
+pub use gate::{ServeGate, ServeWindow};
+
+mod trace;
+pub use trace::TraceId;
+
+mod lock;
+pub use lock::TaskLock;
+
+mod policy;
+use policy::task_skip_reason;
+pub use policy::{
+    AcceptancePolicy, PolicyDecision, PolicySet, ProfitPolicy, SharedPolicy, TaskQuote,
+    ValidationPolicy,
+};
@@ -1,15 +1,26 @@
-use alloy::primitives::{keccak256, Address, Bytes, U256};
-use alloy::sol_types::SolValue;
+use alloy::primitives::{Address, Bytes, U256};
+use dria_oracle_contracts::nonce_candidate;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Natural logarithm of 20, the number of expected hashes is multiplied by this to find the number of
+/// hashes that 95% of the nonces are mined within.
+const P95_FACTOR: f64 = 2.995_732_273_553_991;
 
 pub struct NonceResult {
     pub nonce: U256,
     pub candidate: U256,
     pub target: U256,
+    /// Number of hashes computed to find the nonce.
+    pub hashes: u64,
+    /// Time spent to find the nonce.
+    pub elapsed: Duration,
 }
 
 /// Mines a nonce for the oracle proof-of-work.
 ///
-/// Returns (nonce, candidate, target).
+/// Returns a [`NonceResult`] with the nonce, its candidate hash that is below the target, the target for the
+/// difficulty, and the number of hashes computed & the time spent to find the nonce.
 pub fn mine_nonce(
     difficulty: u8,
     requester: &Address,
@@ -19,27 +30,21 @@ pub fn mine_nonce(
 ) -> NonceResult {
     let big_one = U256::from(1);
     let mut nonce = U256::ZERO;
+    let started_at = Instant::now();
 
     // target is (2^256-1) / 2^difficulty
     let target = U256::MAX >> difficulty;
 
     loop {
-        // encode packed
-        let mut message = Vec::new();
-        task_id.abi_encode_packed_to(&mut message);
-        input.abi_encode_packed_to(&mut message);
-        requester.abi_encode_packed_to(&mut message);
-        responder.abi_encode_packed_to(&mut message);
-        nonce.abi_encode_packed_to(&mut message);
-
         // check hash
-        let digest = keccak256(message.clone());
-        let candidate = U256::from_be_bytes(*digest); // big-endian!
+        let candidate = nonce_candidate(task_id, input, requester, responder, &nonce);
         if candidate < target {
             return NonceResult {
                 nonce,
                 candidate,
                 target,
+                hashes: nonce.saturating_to::<u64>() + 1,
+                elapsed: started_at.elapsed(),
             };
         }
 
@@ -47,6 +52,101 @@ pub fn mine_nonce(
     }
 }
 
+/// Mines a nonce like [`mine_nonce`] on a blocking thread, so that the runtime keeps polling the event
+/// loop & the other tasks meanwhile, as mining is a tight loop that never yields.
+pub async fn mine_nonce_blocking(
+    difficulty: u8,
+    requester: Address,
+    responder: Address,
+    input: Bytes,
+    task_id: U256,
+) -> eyre::Result<NonceResult> {
+    let mined = tokio::task::spawn_blocking(move || {
+        mine_nonce(difficulty, &requester, &responder, &input, &task_id)
+    })
+    .await?;
+
+    Ok(mined)
+}
+
+/// Returns the expected number of hashes to mine a nonce with the given difficulty, i.e. `2^difficulty`.
+#[inline]
+pub fn expected_hashes(difficulty: u8) -> f64 {
+    2f64.powi(difficulty as i32)
+}
+
+/// Estimates the time it takes to mine a nonce with the given difficulty at the given hash rate
+/// (hashes per second).
+///
+/// Returns the expected time, and the time that 95% of the nonces are mined within.
+pub fn estimate_mining_time(difficulty: u8, hash_rate: f64) -> (Duration, Duration) {
+    let expected = expected_hashes(difficulty) / hash_rate;
+    let to_duration = |secs: f64| Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX);
+
+    (to_duration(expected), to_duration(expected * P95_FACTOR))
+}
+
+/// Measures the hash rate (hashes per second) of this machine, by hashing for the given duration.
+pub fn benchmark_hash_rate(duration: Duration) -> f64 {
+    let task_id = U256::from(1);
+    let input = Bytes::from_static(b"benchmark");
+    let mut nonce = U256::ZERO;
+
+    let started_at = Instant::now();
+    let mut hashes = 0u64;
+    loop {
+        // checking the time is slower than hashing, so its done in batches
+        for _ in 0..1024 {
+            nonce_candidate(&task_id, &input, &Address::ZERO, &Address::ZERO, &nonce);
+            nonce += U256::from(1);
+        }
+        hashes += 1024;
+
+        let elapsed = started_at.elapsed();
+        if elapsed >= duration {
+            return hashes as f64 / elapsed.as_secs_f64();
+        }
+    }
+}
+
+/// Total number of hashes computed & the time spent on mining nonces by this node.
+#[derive(Debug, Default)]
+pub struct MiningStats(Mutex<(u64, Duration)>);
+
+impl MiningStats {
+    /// Records a mined nonce.
+    pub fn record(&self, result: &NonceResult) {
+        let mut totals = self.0.lock().expect("lock poisoned");
+        totals.0 += result.hashes;
+        totals.1 += result.elapsed;
+    }
+
+    /// Returns the total number of hashes & the time spent on them.
+    pub fn totals(&self) -> (u64, Duration) {
+        *self.0.lock().expect("lock poisoned")
+    }
+
+    /// Returns the average hash rate (hashes per second), `None` if nothing is mined yet.
+    pub fn hash_rate(&self) -> Option<f64> {
+        let (hashes, elapsed) = self.totals();
+        (!elapsed.is_zero()).then(|| hashes as f64 / elapsed.as_secs_f64())
+    }
+}
+
+impl std::fmt::Display for MiningStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (hashes, elapsed) = self.totals();
+        match self.hash_rate() {
+            Some(hash_rate) => write!(
+                f,
+                "{} hashes in {:.2?} ({:.0} hashes/s)",
+                hashes, elapsed, hash_rate
+            ),
+            None => write!(f, "no nonces mined"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +194,7 @@ mod tests {
             nonce,
             candidate,
             target,
+            ..
         } = mine_nonce(difficulty, &requester, &responder, &input, &task_id);
         // println!("Nonce:     {}", nonce);
         // println!("Target:    {:x}", target);
@@ -125,11 +226,37 @@ mod tests {
             nonce,
             candidate,
             target,
+            hashes,
+            ..
         } = mine_nonce(difficulty, &requester, &responder, &input, &task_id);
         assert!(!nonce.is_zero());
+        assert_eq!(U256::from(hashes), nonce + U256::from(1));
 
         println!("Nonce: {}", nonce);
         println!("Target: {:x}", target);
         println!("Candidate: {:x}", candidate);
     }
+
+    #[test]
+    fn test_mining_estimates() {
+        assert_eq!(expected_hashes(0), 1.0);
+        assert_eq!(expected_hashes(10), 1024.0);
+
+        let (expected, p95) = estimate_mining_time(10, 1024.0);
+        assert_eq!(expected, Duration::from_secs(1));
+        assert!(p95 > Duration::from_secs(2) && p95 < Duration::from_secs(4));
+
+        let stats = MiningStats::default();
+        assert!(stats.hash_rate().is_none());
+        assert_eq!(stats.to_string(), "no nonces mined");
+        stats.record(&NonceResult {
+            nonce: U256::from(99),
+            candidate: U256::ZERO,
+            target: U256::MAX,
+            hashes: 100,
+            elapsed: Duration::from_millis(50),
+        });
+        assert_eq!(stats.hash_rate(), Some(2000.0));
+        assert_eq!(stats.to_string(), "100 hashes in 50.00ms (2000 hashes/s)");
+    }
 }
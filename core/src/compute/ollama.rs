@@ -0,0 +1,258 @@
+use dkn_workflows::{DriaWorkflowsConfig, Model, ModelProvider, OllamaConfig};
+use eyre::{eyre, Context, Result};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Free space that the disk of the models must have for a model to be pulled, in bytes,
+/// which fits the usual quantized models along with some room to spare.
+const MIN_FREE_SPACE: u64 = 16 * 1024 * 1024 * 1024;
+
+/// A model that is present in Ollama, or is loaded by it.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct OllamaModel {
+    /// Name of the model along with its tag, e.g. `llama3.1:latest`.
+    pub name: String,
+    /// Size of the model on disk, or in memory if it is loaded, in bytes.
+    #[serde(default)]
+    pub size: u64,
+    /// Size of the model within the VRAM if it is loaded, in bytes.
+    #[serde(default)]
+    pub size_vram: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OllamaModels {
+    models: Vec<OllamaModel>,
+}
+
+/// A small client of the Ollama API, for the parts that the workflows do not cover,
+/// i.e. listing the models that are present or loaded.
+#[derive(Debug, Clone)]
+pub struct OllamaClient {
+    /// Base URL of Ollama.
+    pub url: Url,
+    client: reqwest::Client,
+}
+
+impl OllamaClient {
+    /// Creates the client for the Ollama of the given workflows config.
+    pub fn new(config: &OllamaConfig) -> Result<Self> {
+        let url = Url::parse(&format!(
+            "{}:{}",
+            config.host.trim_end_matches('/'),
+            config.port
+        ))
+        .wrap_err("invalid OLLAMA_HOST or OLLAMA_PORT")?;
+
+        Ok(Self {
+            url,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Returns `true` if Ollama runs on this machine.
+    pub fn is_local(&self) -> bool {
+        matches!(self.url.host_str(), Some("127.0.0.1" | "localhost"))
+    }
+
+    /// Returns the models that are present in Ollama.
+    pub async fn list_models(&self) -> Result<Vec<OllamaModel>> {
+        self.get_models("api/tags").await
+    }
+
+    /// Returns the models that are loaded into memory by Ollama, i.e. `ollama ps`.
+    pub async fn running_models(&self) -> Result<Vec<OllamaModel>> {
+        self.get_models("api/ps").await
+    }
+
+    async fn get_models(&self, path: &str) -> Result<Vec<OllamaModel>> {
+        let response = self
+            .client
+            .get(self.url.join(path)?)
+            .send()
+            .await
+            .wrap_err("could not reach Ollama")?
+            .error_for_status()?;
+        let models: OllamaModels = serde_json::from_str(&response.text().await?)?;
+
+        Ok(models.models)
+    }
+}
+
+/// Pulls the local models that are missing from Ollama before a task is executed with them,
+/// so that every model variant need not be pulled before serving.
+///
+/// The pull itself is done by the service checks of the workflows with their auto-pull enabled,
+/// and a model is only pulled if the disk that Ollama stores the models in has enough free space.
+/// Each model is looked up once, and is not looked up again after it is found or pulled.
+#[derive(Debug)]
+pub struct OllamaAutoPull {
+    config: OllamaConfig,
+    ollama: OllamaClient,
+    /// Whether each model is available, where the lock of a model is held while it is looked up
+    /// or pulled so that the concurrent tasks wait for the same pull.
+    models: Mutex<HashMap<String, Arc<tokio::sync::Mutex<bool>>>>,
+}
+
+impl OllamaAutoPull {
+    /// Creates the auto-pull for the Ollama of the given workflows config.
+    pub fn new(config: OllamaConfig) -> Result<Self> {
+        Ok(Self {
+            ollama: OllamaClient::new(&config)?,
+            config: config.with_auto_pull(true),
+            models: Mutex::default(),
+        })
+    }
+
+    /// Pulls the given models that are missing in order, until one of them is available,
+    /// and returns the models starting from that one, i.e. the ones that could not be pulled are dropped.
+    ///
+    /// The models of the other providers are always available.
+    pub async fn first_available(&self, mut models: Vec<Model>) -> Vec<Model> {
+        while let Some(model) = models.first() {
+            match self.ensure_pulled(model).await {
+                Ok(()) => break,
+                Err(e) => {
+                    log::error!("Could not pull model {}: {:#}", model, e);
+                    models.remove(0);
+                }
+            }
+        }
+
+        models
+    }
+
+    /// Pulls the model unless it is present in Ollama, or it is not a local model.
+    pub async fn ensure_pulled(&self, model: &Model) -> Result<()> {
+        if ModelProvider::from(model.clone()) != ModelProvider::Ollama {
+            return Ok(());
+        }
+
+        let name = model.to_string();
+        let lock = self
+            .models
+            .lock()
+            .expect("lock poisoned")
+            .entry(name.clone())
+            .or_default()
+            .clone();
+        let mut is_available = lock.lock().await;
+        if *is_available {
+            return Ok(());
+        }
+
+        let models = self.ollama.list_models().await?;
+        if !models.iter().any(|m| is_same_model(&m.name, &name)) {
+            self.check_disk_space(&name).await?;
+
+            log::info!("Model {} is not found in Ollama, pulling it.", name);
+            let started_at = Instant::now();
+            DriaWorkflowsConfig::new(vec![model.clone()])
+                .with_ollama_config(self.config.clone())
+                .check_services()
+                .await?;
+            log::info!(
+                "Model {} is pulled in {}s.",
+                name,
+                started_at.elapsed().as_secs()
+            );
+        }
+
+        *is_available = true;
+        Ok(())
+    }
+
+    /// Makes sure that the disk has enough space for a model, if Ollama runs on this machine.
+    ///
+    /// The check is skipped with a warning when the free space can not be found.
+    async fn check_disk_space(&self, name: &str) -> Result<()> {
+        if !self.ollama.is_local() {
+            log::debug!("Ollama is not local, skipping the disk space check.");
+            return Ok(());
+        }
+
+        let dir = models_dir();
+        let Some(available) = available_space(&dir).await else {
+            log::warn!("Could not find the free space at {}", dir.display());
+            return Ok(());
+        };
+
+        if available < MIN_FREE_SPACE {
+            return Err(eyre!(
+                "not enough disk space at {} to pull {}: {} MB free, {} MB required",
+                dir.display(),
+                name,
+                available / (1024 * 1024),
+                MIN_FREE_SPACE / (1024 * 1024)
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns `true` if the name within Ollama is the given model, where the tag defaults to `latest`.
+pub(crate) fn is_same_model(ollama_name: &str, model: &str) -> bool {
+    let with_tag = |name: &str| {
+        if name.contains(':') {
+            name.to_string()
+        } else {
+            format!("{}:latest", name)
+        }
+    };
+    with_tag(ollama_name) == with_tag(model)
+}
+
+/// Returns the directory that Ollama stores the models in, i.e. `OLLAMA_MODELS` or `~/.ollama/models`.
+fn models_dir() -> PathBuf {
+    match std::env::var("OLLAMA_MODELS") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".ollama/models"),
+    }
+}
+
+/// Returns the free space of the disk that the given path (or its closest existing parent) is on, in bytes.
+async fn available_space(path: &Path) -> Option<u64> {
+    let path = path.ancestors().find(|p| p.exists())?;
+    let output = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .output()
+        .await
+        .ok()?;
+    parse_df_available(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the available space from the POSIX output of `df -Pk`, in bytes.
+fn parse_df_available(output: &str) -> Option<u64> {
+    let kilobytes = output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_names() {
+        assert!(is_same_model("llama3.1:latest", "llama3.1"));
+        assert!(is_same_model("phi3:3.8b", "phi3:3.8b"));
+        assert!(!is_same_model("phi3:latest", "phi3:3.8b"));
+    }
+
+    #[test]
+    fn test_parse_df_available() {
+        let output = "Filesystem 1024-blocks Used Available Capacity Mounted on\n/dev/sda1 1000 600 400 60% /\n";
+        assert_eq!(parse_df_available(output), Some(400 * 1024));
+        assert_eq!(parse_df_available(""), None);
+    }
+}
@@ -0,0 +1,418 @@
+use alloy::primitives::{utils::format_units, U256};
+use alloy::providers::Provider;
+use dria_oracle_contracts::{bytes32_to_string, bytes_to_string, OracleKind, TaskStatus};
+use dria_oracle_storage::{ArweaveStorage, IsExternalStorage};
+use eyre::Result;
+use std::sync::{Arc, RwLock};
+
+use super::costs::{estimate_tokens, TokenUsage};
+use crate::DriaOracle;
+
+/// Expected gas usage of a `respond` transaction, used for estimates before the response exists.
+const ESTIMATED_GENERATION_GAS: u64 = 300_000;
+/// Expected gas usage of a `validate` transaction, used for estimates before the response exists.
+const ESTIMATED_VALIDATION_GAS: u64 = 250_000;
+/// Expected number of completion tokens per generation.
+const ESTIMATED_COMPLETION_TOKENS: u64 = 1_000;
+/// Expected number of tokens of a generation that is uploaded to the storage, as its size is unknown before it is downloaded.
+const ESTIMATED_STORED_GENERATION_TOKENS: u64 = 4_000;
+/// Expected number of completion tokens of a validation, per generation.
+const ESTIMATED_VALIDATION_TOKENS: u64 = ESTIMATED_COMPLETION_TOKENS / 4;
+
+/// Estimated economics of a task, given to an [`AcceptancePolicy`] to decide on it.
+#[derive(Debug, Clone)]
+pub struct TaskQuote {
+    /// Task id.
+    pub task_id: U256,
+    /// The role that the task would be handled as.
+    pub kind: OracleKind,
+    /// Protocol name of the task.
+    pub protocol: String,
+    /// The fee reserved for us within the task, in fee tokens.
+    pub reward: U256,
+    /// Decimals of the fee token, to convert the reward.
+    pub reward_decimals: u8,
+    /// Estimated gas fee of the response transaction, in wei.
+    pub gas_fee: U256,
+    /// Estimated provider cost in USD.
+    pub provider_cost_usd: f64,
+    /// Number of generations to score, zero for generation tasks.
+    pub num_generations: u64,
+}
+
+/// Decision of an [`AcceptancePolicy`] for a task.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyDecision {
+    /// Handle the task.
+    Accept,
+    /// Skip the task, with the reason why.
+    Skip(String),
+}
+
+/// A policy that decides whether a task should be handled or not, consulted before handling each task.
+///
+/// Implement this trait for custom policies, and set it as the node's `policy`.
+pub trait AcceptancePolicy: Send + Sync {
+    /// Returns the decision for the given task quote.
+    fn decide(&self, quote: &TaskQuote) -> PolicyDecision;
+}
+
+/// The acceptance policy of a node, which is shared by its clones and can be replaced while serving.
+///
+/// Accepts all tasks if there is no policy.
+#[derive(Clone, Default)]
+pub struct SharedPolicy(Arc<RwLock<Option<Arc<dyn AcceptancePolicy>>>>);
+
+impl SharedPolicy {
+    /// Returns the current policy, if any.
+    pub fn get(&self) -> Option<Arc<dyn AcceptancePolicy>> {
+        self.0.read().expect("lock poisoned").clone()
+    }
+
+    /// Replaces the policy, where `None` accepts all tasks.
+    pub fn set(&self, policy: Option<Arc<dyn AcceptancePolicy>>) {
+        *self.0.write().expect("lock poisoned") = policy;
+    }
+}
+
+/// A built-in policy that skips tasks that would be net-negative.
+///
+/// Everything is converted to ETH for comparison: rewards with `token_price_eth` and
+/// provider costs with `eth_price_usd`.
+#[derive(Debug, Clone)]
+pub struct ProfitPolicy {
+    /// Minimum expected profit to accept a task, in ETH.
+    pub min_profit_eth: f64,
+    /// Price of a fee token in ETH, which is 1 for WETH.
+    pub token_price_eth: f64,
+    /// Price of ETH in USD, to convert provider costs.
+    pub eth_price_usd: f64,
+}
+
+impl ProfitPolicy {
+    /// Creates a new profit policy with the given minimum profit and ETH price,
+    /// assuming that the fee token is WETH.
+    pub fn new(min_profit_eth: f64, eth_price_usd: f64) -> Self {
+        Self {
+            min_profit_eth,
+            token_price_eth: 1.0,
+            eth_price_usd,
+        }
+    }
+
+    /// Change the price of the fee token in ETH.
+    pub fn with_token_price_eth(mut self, token_price_eth: f64) -> Self {
+        self.token_price_eth = token_price_eth;
+        self
+    }
+
+    /// Returns the expected profit of a task in ETH.
+    pub fn expected_profit_eth(&self, quote: &TaskQuote) -> f64 {
+        let reward_eth = to_f64(quote.reward, quote.reward_decimals) * self.token_price_eth;
+        let gas_eth = to_f64(quote.gas_fee, 18);
+        let provider_eth = if self.eth_price_usd > 0.0 {
+            quote.provider_cost_usd / self.eth_price_usd
+        } else {
+            0.0
+        };
+
+        reward_eth - gas_eth - provider_eth
+    }
+}
+
+impl AcceptancePolicy for ProfitPolicy {
+    fn decide(&self, quote: &TaskQuote) -> PolicyDecision {
+        let profit = self.expected_profit_eth(quote);
+        if profit < self.min_profit_eth {
+            PolicyDecision::Skip(format!(
+                "expected profit {:.8} ETH is below {:.8} ETH",
+                profit, self.min_profit_eth
+            ))
+        } else {
+            PolicyDecision::Accept
+        }
+    }
+}
+
+/// A built-in policy that skips the validations that are clearly unprofitable, i.e. whose estimated
+/// gas & provider costs (which grow with the number of generations to download & score) are more than
+/// `max_cost_ratio` times the validator fee. Generation tasks are always accepted.
+#[derive(Debug, Clone)]
+pub struct ValidationPolicy {
+    /// Maximum ratio of the estimated costs to the fee, e.g. 1 skips the validations that cost more than they pay.
+    pub max_cost_ratio: f64,
+    /// Price of a fee token in ETH, which is 1 for WETH.
+    pub token_price_eth: f64,
+    /// Price of ETH in USD, to convert provider costs.
+    pub eth_price_usd: f64,
+}
+
+impl ValidationPolicy {
+    /// Creates a new validation policy with the given cost ratio and ETH price,
+    /// assuming that the fee token is WETH.
+    pub fn new(max_cost_ratio: f64, eth_price_usd: f64) -> Self {
+        Self {
+            max_cost_ratio,
+            token_price_eth: 1.0,
+            eth_price_usd,
+        }
+    }
+
+    /// Change the price of the fee token in ETH.
+    pub fn with_token_price_eth(mut self, token_price_eth: f64) -> Self {
+        self.token_price_eth = token_price_eth;
+        self
+    }
+
+    /// Returns the expected cost of a task in ETH.
+    pub fn expected_cost_eth(&self, quote: &TaskQuote) -> f64 {
+        let provider_eth = if self.eth_price_usd > 0.0 {
+            quote.provider_cost_usd / self.eth_price_usd
+        } else {
+            0.0
+        };
+
+        to_f64(quote.gas_fee, 18) + provider_eth
+    }
+}
+
+impl AcceptancePolicy for ValidationPolicy {
+    fn decide(&self, quote: &TaskQuote) -> PolicyDecision {
+        if quote.kind != OracleKind::Validator {
+            return PolicyDecision::Accept;
+        }
+
+        let cost = self.expected_cost_eth(quote);
+        let reward = to_f64(quote.reward, quote.reward_decimals) * self.token_price_eth;
+        if cost > reward * self.max_cost_ratio {
+            PolicyDecision::Skip(format!(
+                "expected cost {:.8} ETH of {} generations is above {}x the fee of {:.8} ETH",
+                cost, quote.num_generations, self.max_cost_ratio, reward
+            ))
+        } else {
+            PolicyDecision::Accept
+        }
+    }
+}
+
+/// A policy that accepts a task only if all of its policies accept it, skipping with the first reason otherwise.
+#[derive(Clone, Default)]
+pub struct PolicySet(Vec<Arc<dyn AcceptancePolicy>>);
+
+impl PolicySet {
+    /// Adds a policy, which is consulted after the existing ones.
+    pub fn with(mut self, policy: impl AcceptancePolicy + 'static) -> Self {
+        self.0.push(Arc::new(policy));
+        self
+    }
+
+    /// Returns `true` if there are no policies, i.e. all tasks are accepted.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl AcceptancePolicy for PolicySet {
+    fn decide(&self, quote: &TaskQuote) -> PolicyDecision {
+        self.0
+            .iter()
+            .map(|policy| policy.decide(quote))
+            .find(|decision| *decision != PolicyDecision::Accept)
+            .unwrap_or(PolicyDecision::Accept)
+    }
+}
+
+/// Converts an amount to a float w.r.t the given decimals, e.g. wei to ether for 18 decimals.
+#[inline]
+fn to_f64(amount: U256, decimals: u8) -> f64 {
+    format_units(amount, decimals)
+        .ok()
+        .and_then(|units| units.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Creates a quote for the given task, using the request fees, current gas price and model prices.
+pub async fn quote_task(node: &DriaOracle, kind: OracleKind, task_id: U256) -> Result<TaskQuote> {
+    let status = match kind {
+        OracleKind::Generator => TaskStatus::PendingGeneration,
+        OracleKind::Validator => TaskStatus::PendingValidation,
+    };
+    let request = node.get_request_cached(task_id, status).await?;
+    let gas_price = node.provider.get_gas_price().await?;
+    let input_tokens = estimate_tokens(&bytes_to_string(&request.input).unwrap_or_default());
+
+    let (reward, gas, usage, model, num_generations) = match kind {
+        OracleKind::Generator => {
+            let models_string = bytes_to_string(&request.models)?;
+            let models_vec = models_string.split(',').map(|s| s.to_string()).collect();
+            let model = node
+                .workflows
+                .get_any_matching_model(models_vec)
+                .or_else(|_| node.workflows.get_matching_model("*".to_string()))
+                .map(|(_, model)| model)
+                .ok();
+            let usage = TokenUsage {
+                prompt_tokens: input_tokens,
+                completion_tokens: ESTIMATED_COMPLETION_TOKENS,
+            };
+
+            (
+                request.generatorFee,
+                ESTIMATED_GENERATION_GAS,
+                usage,
+                model,
+                0,
+            )
+        }
+        OracleKind::Validator => {
+            // validators read the input once along with each generation, whose sizes are known
+            // unless they are uploaded to the storage
+            let responses = node
+                .get_responses_cached(task_id, TaskStatus::PendingValidation)
+                .await?;
+            let generation_tokens = responses
+                .iter()
+                .map(|response| {
+                    let output = bytes_to_string(&response.output).unwrap_or_default();
+                    match ArweaveStorage::is_key(&output) {
+                        Some(_) => ESTIMATED_STORED_GENERATION_TOKENS,
+                        None => estimate_tokens(&output),
+                    }
+                })
+                .sum::<u64>();
+            let num_gens = responses.len() as u64;
+            let usage = TokenUsage {
+                prompt_tokens: input_tokens + generation_tokens,
+                completion_tokens: num_gens * ESTIMATED_VALIDATION_TOKENS,
+            };
+
+            (
+                request.validatorFee,
+                ESTIMATED_VALIDATION_GAS,
+                usage,
+                Some(DriaOracle::VALIDATION_MODEL),
+                num_gens,
+            )
+        }
+    };
+
+    Ok(TaskQuote {
+        task_id,
+        kind,
+        protocol: bytes32_to_string(&request.protocol).unwrap_or_default(),
+        reward,
+        reward_decimals: node.get_token_decimals_cached().await?,
+        gas_fee: U256::from(gas) * U256::from(gas_price),
+        provider_cost_usd: model.map(|m| usage.cost_usd(&m)).unwrap_or_default(),
+        num_generations,
+    })
+}
+
+/// Consults the acceptance policy of the node (if any) for the given task.
+///
+/// Returns the reason to skip the task, or `None` if the task should be handled.
+pub async fn task_skip_reason(
+    node: &DriaOracle,
+    kind: OracleKind,
+    task_id: U256,
+) -> Result<Option<String>> {
+    let Some(policy) = node.policy.get() else {
+        return Ok(None);
+    };
+
+    let quote = quote_task(node, kind, task_id).await?;
+    match policy.decide(&quote) {
+        PolicyDecision::Accept => Ok(None),
+        PolicyDecision::Skip(reason) => {
+            log::info!("Skipping {} task {}: {}", kind, task_id, reason);
+            Ok(Some(reason))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::utils::parse_ether;
+
+    fn quote(reward: &str, gas_fee: &str, provider_cost_usd: f64) -> TaskQuote {
+        TaskQuote {
+            task_id: U256::from(1),
+            kind: OracleKind::Generator,
+            protocol: "test".to_string(),
+            reward: parse_ether(reward).unwrap(),
+            reward_decimals: 18,
+            gas_fee: parse_ether(gas_fee).unwrap(),
+            provider_cost_usd,
+            num_generations: 0,
+        }
+    }
+
+    #[test]
+    fn test_profit_policy() {
+        let policy = ProfitPolicy::new(0.0, 2000.0);
+
+        // 0.01 reward - 0.001 gas - 0.0005 provider
+        assert_eq!(
+            policy.decide(&quote("0.01", "0.001", 1.0)),
+            PolicyDecision::Accept
+        );
+
+        // 0.001 reward - 0.001 gas - 0.0005 provider
+        assert!(matches!(
+            policy.decide(&quote("0.001", "0.001", 1.0)),
+            PolicyDecision::Skip(_)
+        ));
+
+        // 10 tokens of 6 decimals at 0.0005 ETH each - 0.001 gas - 0.0005 provider
+        let policy = policy.with_token_price_eth(0.0005);
+        let quote = TaskQuote {
+            reward: U256::from(10_000_000),
+            reward_decimals: 6,
+            ..quote("0", "0.001", 1.0)
+        };
+        assert!((policy.expected_profit_eth(&quote) - 0.0035).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_validation_policy() {
+        let policy = ValidationPolicy::new(1.0, 2000.0);
+
+        // generations are not decided on
+        assert_eq!(
+            policy.decide(&quote("0.001", "0.01", 100.0)),
+            PolicyDecision::Accept
+        );
+
+        // 0.001 gas + 0.0005 provider for a 0.002 fee
+        let validation = TaskQuote {
+            kind: OracleKind::Validator,
+            num_generations: 5,
+            ..quote("0.002", "0.001", 1.0)
+        };
+        assert_eq!(policy.decide(&validation), PolicyDecision::Accept);
+
+        // 0.001 gas + 0.005 provider for a 0.002 fee
+        let validation = TaskQuote {
+            provider_cost_usd: 10.0,
+            ..validation
+        };
+        assert!(matches!(
+            policy.decide(&validation),
+            PolicyDecision::Skip(_)
+        ));
+
+        // the profit policy accepts it, but the set does not
+        let policies = PolicySet::default()
+            .with(ProfitPolicy::new(-1.0, 2000.0))
+            .with(policy);
+        assert!(matches!(
+            policies.decide(&validation),
+            PolicyDecision::Skip(_)
+        ));
+        assert_eq!(
+            PolicySet::default().decide(&validation),
+            PolicyDecision::Accept
+        );
+    }
+}
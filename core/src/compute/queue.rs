@@ -0,0 +1,244 @@
+//! A priority queue for the pending tasks, so that a saturated node does the most valuable work first.
+//!
+//! Tasks are ordered by the criteria of a [`QueuePolicy`], e.g. by their fees, and then by their age.
+
+use alloy::primitives::U256;
+use alloy::rpc::types::Log;
+use dria_oracle_contracts::OracleCoordinator::StatusUpdate;
+use dria_oracle_contracts::{OracleKind, TaskStatus};
+use eyre::{eyre, Result};
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+/// A criterion to order the queued tasks by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueOrder {
+    /// Tasks with higher fees for us come first.
+    Fee,
+    /// Tasks that have been waiting longer come first.
+    Age,
+    /// Tasks with lower difficulty come first.
+    Difficulty,
+}
+
+impl std::str::FromStr for QueueOrder {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fee" => Ok(Self::Fee),
+            "age" => Ok(Self::Age),
+            "difficulty" => Ok(Self::Difficulty),
+            _ => Err(eyre!("Invalid queue order: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for QueueOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fee => write!(f, "fee"),
+            Self::Age => write!(f, "age"),
+            Self::Difficulty => write!(f, "difficulty"),
+        }
+    }
+}
+
+/// Ordering policy of the task queue.
+#[derive(Debug, Clone)]
+pub struct QueuePolicy {
+    /// Criteria to order the tasks by, in order of importance.
+    pub order: Vec<QueueOrder>,
+    /// Protocols that come before all others, in order of preference.
+    pub preferred_protocols: Vec<String>,
+}
+
+impl Default for QueuePolicy {
+    fn default() -> Self {
+        Self {
+            order: vec![QueueOrder::Fee, QueueOrder::Age, QueueOrder::Difficulty],
+            preferred_protocols: Vec::new(),
+        }
+    }
+}
+
+impl QueuePolicy {
+    /// Compares two tasks, where the greater one should be handled first.
+    pub fn compare(&self, a: &QueuedTask, b: &QueuedTask) -> Ordering {
+        // preferred protocols come first, earlier in the list is better
+        let preference = |task: &QueuedTask| {
+            self.preferred_protocols
+                .iter()
+                .position(|p| *p == task.protocol)
+                .unwrap_or(usize::MAX)
+        };
+
+        self.order
+            .iter()
+            .fold(preference(b).cmp(&preference(a)), |ordering, order| {
+                ordering.then_with(|| match order {
+                    QueueOrder::Fee => a.fee.cmp(&b.fee),
+                    QueueOrder::Age => b.queued_at.cmp(&a.queued_at),
+                    QueueOrder::Difficulty => b.difficulty.cmp(&a.difficulty),
+                })
+            })
+    }
+}
+
+/// A task waiting in the queue, along with the values it is ordered by.
+#[derive(Debug, Clone)]
+pub struct QueuedTask {
+    /// The event that has moved the task to its current status.
+    pub event: StatusUpdate,
+    /// Log of the event.
+    pub log: Log,
+    /// Protocol name of the task.
+    pub protocol: String,
+    /// The fee reserved for us within the task.
+    pub fee: U256,
+    /// Difficulty of the task.
+    pub difficulty: u8,
+    /// Time that the task was queued at.
+    pub queued_at: Instant,
+}
+
+impl QueuedTask {
+    /// Returns the kind of oracle that the task is pending for.
+    pub fn kind(&self) -> OracleKind {
+        if self.event.statusAfter == u8::from(TaskStatus::PendingValidation) {
+            OracleKind::Validator
+        } else {
+            OracleKind::Generator
+        }
+    }
+}
+
+/// A priority queue of tasks, ordered with respect to a [`QueuePolicy`].
+///
+/// The queue holds at most one entry per task, a newer event for a task replaces the older one.
+#[derive(Debug, Default)]
+pub struct TaskQueue {
+    policy: QueuePolicy,
+    tasks: Vec<QueuedTask>,
+    /// Number of tasks that have been dispatched from the queue.
+    dispatched: u64,
+    /// Total waiting time of the dispatched tasks.
+    total_wait: Duration,
+    /// Maximum number of tasks that were in the queue at once.
+    max_depth: usize,
+}
+
+impl TaskQueue {
+    /// Creates an empty queue with the given policy.
+    pub fn new(policy: QueuePolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Changes the policy, which applies to the tasks that are already in the queue as well.
+    pub fn set_policy(&mut self, policy: QueuePolicy) {
+        self.policy = policy;
+    }
+
+    /// Adds a task to the queue, replacing the existing entry of the same task.
+    pub fn push(&mut self, task: QueuedTask) {
+        self.tasks.retain(|t| t.event.taskId != task.event.taskId);
+        self.tasks.push(task);
+        self.max_depth = self.max_depth.max(self.tasks.len());
+    }
+
+    /// Removes and returns the task that should be handled first, if any.
+    pub fn pop(&mut self) -> Option<QueuedTask> {
+        let (idx, _) = self
+            .tasks
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| self.policy.compare(a, b))?;
+        let task = self.tasks.swap_remove(idx);
+
+        self.dispatched += 1;
+        self.total_wait += task.queued_at.elapsed();
+        Some(task)
+    }
+
+    /// Returns the number of tasks in the queue.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns the average time that the dispatched tasks have waited in the queue.
+    pub fn average_wait(&self) -> Duration {
+        self.total_wait
+            .checked_div(self.dispatched as u32)
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for TaskQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} queued, {} dispatched, max depth {}, average wait {}ms",
+            self.depth(),
+            self.dispatched,
+            self.max_depth,
+            self.average_wait().as_millis()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(task_id: u64, protocol: &str, fee: u64, difficulty: u8) -> QueuedTask {
+        QueuedTask {
+            event: StatusUpdate {
+                taskId: U256::from(task_id),
+                protocol: Default::default(),
+                statusBefore: 0,
+                statusAfter: 1,
+            },
+            log: Log::default(),
+            protocol: protocol.to_string(),
+            fee: U256::from(fee),
+            difficulty,
+            queued_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_queue_order() {
+        let mut queue = TaskQueue::new(QueuePolicy::default());
+        queue.push(task(1, "a", 10, 1));
+        queue.push(task(2, "a", 30, 1));
+        queue.push(task(3, "a", 20, 1));
+        queue.push(task(1, "a", 40, 1)); // replaces the first one
+        assert_eq!(queue.depth(), 3);
+
+        let order = std::iter::from_fn(|| queue.pop())
+            .map(|t| t.event.taskId.to::<u64>())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![1, 2, 3]);
+        assert_eq!(queue.dispatched, 3);
+    }
+
+    #[test]
+    fn test_queue_preferred_protocols() {
+        let mut queue = TaskQueue::new(QueuePolicy {
+            order: vec![QueueOrder::Difficulty, QueueOrder::Fee],
+            preferred_protocols: vec!["b".to_string()],
+        });
+        queue.push(task(1, "a", 30, 1));
+        queue.push(task(2, "b", 10, 5));
+        queue.push(task(3, "a", 20, 0));
+
+        let order = std::iter::from_fn(|| queue.pop())
+            .map(|t| t.event.taskId.to::<u64>())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![2, 3, 1]);
+    }
+}
@@ -0,0 +1,110 @@
+use alloy::primitives::U256;
+use eyre::{eyre, Context, Result};
+use std::time::Duration;
+
+/// A shard of the tasks, so that a fleet of nodes with distinct wallets can split the tasks
+/// without racing each other for them.
+///
+/// A task is assigned to shard `i` of `n` if `taskId % n == i`. With a failover, the tasks of
+/// other shards are handled as well if they are still pending after the failover duration,
+/// e.g. when the node of their shard is down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shard {
+    /// Index of this shard, less than `count`.
+    pub index: u64,
+    /// Total number of shards.
+    pub count: u64,
+    /// Optional duration after which the tasks of other shards are handled as well.
+    pub failover: Option<Duration>,
+}
+
+impl Shard {
+    /// Creates the shard `index` of `count` shards, without a failover.
+    pub fn new(index: u64, count: u64) -> Result<Self> {
+        if index >= count {
+            return Err(eyre!(
+                "Shard index {} must be less than the shard count {}",
+                index,
+                count
+            ));
+        }
+
+        Ok(Self {
+            index,
+            count,
+            failover: None,
+        })
+    }
+
+    /// Handle the tasks of other shards that are still pending after the given duration.
+    pub fn with_failover(mut self, failover: Duration) -> Self {
+        self.failover = Some(failover);
+        self
+    }
+
+    /// Returns `true` if the task is assigned to this shard.
+    #[inline]
+    pub fn owns(&self, task_id: U256) -> bool {
+        task_id % U256::from(self.count) == U256::from(self.index)
+    }
+
+    /// Returns `true` if the task should be handled by this shard, where `age` is the time
+    /// since the task has entered its current status.
+    pub fn should_handle(&self, task_id: U256, age: Duration) -> bool {
+        self.owns(task_id) || self.failover.is_some_and(|failover| age >= failover)
+    }
+}
+
+impl std::str::FromStr for Shard {
+    type Err = eyre::Report;
+
+    /// Parses a shard given as `<index>/<count>`, e.g. `0/3`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| eyre!("Invalid shard: {}, expected <index>/<count>", s))?;
+        let index = index.trim().parse().wrap_err("invalid shard index")?;
+        let count = count.trim().parse().wrap_err("invalid shard count")?;
+
+        Self::new(index, count)
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_shard() {
+        let shard = Shard::from_str("1/3").unwrap();
+        assert_eq!(shard.to_string(), "1/3");
+        assert!(shard.owns(U256::from(4)));
+        assert!(!shard.owns(U256::from(5)));
+
+        // every task belongs to exactly one shard
+        let shards = (0..3)
+            .map(|i| Shard::new(i, 3).unwrap())
+            .collect::<Vec<_>>();
+        for task_id in 0..30u64 {
+            let owners = shards.iter().filter(|s| s.owns(U256::from(task_id)));
+            assert_eq!(owners.count(), 1);
+        }
+
+        // other shards are only handled with a failover, after it has passed
+        assert!(!shard.should_handle(U256::from(5), Duration::from_secs(3600)));
+        let shard = shard.with_failover(Duration::from_secs(60));
+        assert!(!shard.should_handle(U256::from(5), Duration::from_secs(30)));
+        assert!(shard.should_handle(U256::from(5), Duration::from_secs(60)));
+
+        assert!(Shard::from_str("3/3").is_err());
+        assert!(Shard::from_str("0/0").is_err());
+        assert!(Shard::from_str("1").is_err());
+    }
+}
@@ -0,0 +1,303 @@
+//! Caps on the spending of paid model providers & gas, so that a flood of tasks or a fee spike
+//! does not result in a surprise bill.
+
+use alloy::primitives::utils::{format_ether, format_units, parse_ether, parse_units};
+use alloy::primitives::U256;
+use dkn_workflows::{Model, ModelProvider};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Caps on the estimated spending (in USD) of the paid providers, i.e. all except Ollama,
+/// within the last hour and the last day.
+///
+/// Once a cap is reached, the tasks that require a paid provider are skipped until enough spending
+/// falls out of the window, while the tasks that can use local models are still served.
+#[derive(Debug, Default)]
+pub struct ApiBudget {
+    /// Maximum spending within the last hour.
+    pub max_usd_per_hour: Option<f64>,
+    /// Maximum spending within the last day.
+    pub max_usd_per_day: Option<f64>,
+    /// Spending within the last day, as the time & cost of each execution.
+    spending: Mutex<VecDeque<(Instant, f64)>>,
+    /// Whether the budget was exhausted at the last check, so that changes are alerted once.
+    exhausted: AtomicBool,
+}
+
+impl ApiBudget {
+    /// Creates a budget with the given hourly & daily caps.
+    pub fn new(max_usd_per_hour: Option<f64>, max_usd_per_day: Option<f64>) -> Self {
+        Self {
+            max_usd_per_hour,
+            max_usd_per_day,
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if the model is served by a paid provider.
+    #[inline]
+    pub fn is_paid(model: &Model) -> bool {
+        ModelProvider::from(model.clone()) != ModelProvider::Ollama
+    }
+
+    /// Records the cost of an execution with the given model, if it is a paid one.
+    pub fn record(&self, model: &Model, cost_usd: f64) {
+        if cost_usd <= 0.0 || !Self::is_paid(model) {
+            return;
+        }
+
+        let mut spending = self.spending.lock().expect("lock poisoned");
+        spending.push_back((Instant::now(), cost_usd));
+        while spending.front().is_some_and(|(at, _)| at.elapsed() > DAY) {
+            spending.pop_front();
+        }
+    }
+
+    /// Returns the spending within the given window until now.
+    pub fn spent_within(&self, window: Duration) -> f64 {
+        self.spending
+            .lock()
+            .expect("lock poisoned")
+            .iter()
+            .filter(|(at, _)| at.elapsed() <= window)
+            .map(|(_, cost)| cost)
+            .sum()
+    }
+
+    /// Returns `true` if the paid providers can be used, i.e. none of the caps are reached.
+    ///
+    /// Alerts when the budget gets exhausted, and when it is available again.
+    pub fn allows_paid(&self) -> bool {
+        let exceeds = |cap: Option<f64>, window: Duration| {
+            cap.is_some_and(|cap| self.spent_within(window) >= cap)
+        };
+        let exhausted = exceeds(self.max_usd_per_hour, HOUR) || exceeds(self.max_usd_per_day, DAY);
+
+        match (self.exhausted.swap(exhausted, Ordering::Relaxed), exhausted) {
+            (false, true) => log::error!(
+                "API budget is exhausted ({}), pausing the tasks that require paid providers.",
+                self
+            ),
+            (true, false) => log::info!(
+                "API budget is available again ({}), resuming the tasks that require paid providers.",
+                self
+            ),
+            _ => {}
+        }
+
+        !exhausted
+    }
+}
+
+impl std::fmt::Display for ApiBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cap = |cap: Option<f64>| cap.map_or("-".to_string(), |cap| format!("${:.2}", cap));
+        write!(
+            f,
+            "${:.2}/{} within the last hour, ${:.2}/{} within the last day",
+            self.spent_within(HOUR),
+            cap(self.max_usd_per_hour),
+            self.spent_within(DAY),
+            cap(self.max_usd_per_day)
+        )
+    }
+}
+
+/// Caps on the gas of the transactions of a chain: a ceiling on the base fee, above which the new tasks are
+/// deferred until the fees fall, and a budget on the gas fees spent within the last day.
+///
+/// The tasks stay within the queue while either cap is reached, and the responses that are already made
+/// are still sent from the outbox.
+#[derive(Debug, Default)]
+pub struct GasBudget {
+    /// Maximum base fee (in wei) to take new tasks at.
+    pub max_gas_price: Option<u128>,
+    /// Maximum gas fees (in wei) within the last day.
+    pub max_wei_per_day: Option<U256>,
+    /// Gas fees within the last day, as the time & fee of each transaction.
+    ///
+    /// The wall-clock time is used instead of an instant, so that the fees can be restored after a restart.
+    spending: Mutex<VecDeque<(SystemTime, U256)>>,
+    /// Whether the base fee was above the ceiling at the last check, so that changes are alerted once.
+    above_ceiling: AtomicBool,
+    /// Whether the budget was exhausted at the last check, so that changes are alerted once.
+    exhausted: AtomicBool,
+}
+
+impl GasBudget {
+    /// Window of the daily cap.
+    pub const WINDOW: Duration = DAY;
+
+    /// Creates a budget with the given base fee ceiling & daily cap.
+    pub fn new(max_gas_price: Option<u128>, max_wei_per_day: Option<U256>) -> Self {
+        Self {
+            max_gas_price,
+            max_wei_per_day,
+            ..Default::default()
+        }
+    }
+
+    /// Parses a gas price given in gwei, e.g. `30` or `0.5`, to wei.
+    pub fn parse_gwei(value: &str) -> eyre::Result<u128> {
+        let wei: U256 = parse_units(value, "gwei")?.into();
+        wei.try_into()
+            .map_err(|_| eyre::eyre!("Gas price is too large: {}", value))
+    }
+
+    /// Parses a gas spending given in ETH, e.g. `0.05`, to wei.
+    pub fn parse_eth(value: &str) -> eyre::Result<U256> {
+        Ok(parse_ether(value)?)
+    }
+
+    /// Records the gas fee of a transaction.
+    pub fn record(&self, gas_fee: U256) {
+        if gas_fee.is_zero() {
+            return;
+        }
+
+        let mut spending = self.spending.lock().expect("lock poisoned");
+        spending.push_back((SystemTime::now(), gas_fee));
+        while spending.front().is_some_and(|(at, _)| Self::is_expired(at)) {
+            spending.pop_front();
+        }
+    }
+
+    /// Restores the gas fees recorded before a restart, given with the time of each transaction.
+    pub fn restore(&self, fees: impl IntoIterator<Item = (SystemTime, U256)>) {
+        let mut spending = self.spending.lock().expect("lock poisoned");
+        spending.extend(fees.into_iter().filter(|(at, _)| !Self::is_expired(at)));
+        spending.make_contiguous().sort_by_key(|(at, _)| *at);
+    }
+
+    /// Returns the gas fees within the last day.
+    pub fn spent_today(&self) -> U256 {
+        self.spending
+            .lock()
+            .expect("lock poisoned")
+            .iter()
+            .filter(|(at, _)| !Self::is_expired(at))
+            .map(|(_, fee)| *fee)
+            .sum()
+    }
+
+    /// Returns `true` if a fee paid at the given time is out of the window.
+    fn is_expired(at: &SystemTime) -> bool {
+        at.elapsed().is_ok_and(|elapsed| elapsed > DAY)
+    }
+
+    /// Returns `true` if new tasks can be taken at the given base fee, i.e. it is within the ceiling
+    /// and the daily budget is not exhausted.
+    ///
+    /// Alerts when either cap is reached, and when the tasks can be taken again.
+    pub fn allows(&self, base_fee: u128) -> bool {
+        let gwei = |wei: u128| format_units(wei, "gwei").unwrap_or_else(|_| wei.to_string());
+        let above_ceiling = self.max_gas_price.is_some_and(|max| base_fee > max);
+        match (
+            self.above_ceiling.swap(above_ceiling, Ordering::Relaxed),
+            above_ceiling,
+        ) {
+            (false, true) => log::warn!(
+                "Base fee {} gwei is above the ceiling of {} gwei, deferring the tasks until it falls.",
+                gwei(base_fee),
+                gwei(self.max_gas_price.unwrap_or_default())
+            ),
+            (true, false) => log::info!(
+                "Base fee {} gwei is within the ceiling again, resuming the tasks.",
+                gwei(base_fee)
+            ),
+            _ => {}
+        }
+
+        let exhausted = self
+            .max_wei_per_day
+            .is_some_and(|max| self.spent_today() >= max);
+        match (self.exhausted.swap(exhausted, Ordering::Relaxed), exhausted) {
+            (false, true) => log::error!(
+                "Gas budget is exhausted ({}), deferring the tasks until the spending falls out of the day.",
+                self
+            ),
+            (true, false) => log::info!(
+                "Gas budget is available again ({}), resuming the tasks.",
+                self
+            ),
+            _ => {}
+        }
+
+        !above_ceiling && !exhausted
+    }
+}
+
+impl std::fmt::Display for GasBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cap = self
+            .max_wei_per_day
+            .map_or("-".to_string(), |cap| format!("{} ETH", format_ether(cap)));
+        write!(
+            f,
+            "{} ETH/{} within the last day",
+            format_ether(self.spent_today()),
+            cap
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_budget() {
+        let budget = ApiBudget::new(Some(1.0), None);
+        assert!(budget.allows_paid());
+
+        // local models are free
+        budget.record(&Model::Llama3_1_8B, 10.0);
+        assert!(budget.allows_paid());
+
+        budget.record(&Model::GPT4oMini, 0.6);
+        assert!(budget.allows_paid());
+        budget.record(&Model::GPT4oMini, 0.6);
+        assert!(!budget.allows_paid());
+        assert!((budget.spent_within(HOUR) - 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_gas_budget() {
+        let gwei = |n: u128| n * 1_000_000_000;
+        let budget = GasBudget::new(Some(gwei(50)), Some(U256::from(gwei(1000))));
+        assert!(budget.allows(gwei(20)));
+        assert!(!budget.allows(gwei(60)));
+        assert!(budget.allows(gwei(50)));
+
+        budget.record(U256::from(gwei(600)));
+        assert!(budget.allows(gwei(20)));
+        budget.record(U256::from(gwei(400)));
+        assert!(!budget.allows(gwei(20)));
+        assert_eq!(budget.spent_today(), U256::from(gwei(1000)));
+
+        // fees before a restart count, unless they are out of the window
+        let budget = GasBudget::new(None, Some(U256::from(gwei(1000))));
+        let now = SystemTime::now();
+        budget.restore([
+            (now - Duration::from_secs(60), U256::from(gwei(1000))),
+            (now - 2 * DAY, U256::from(gwei(1000))),
+        ]);
+        assert_eq!(budget.spent_today(), U256::from(gwei(1000)));
+        assert!(!budget.allows(gwei(20)));
+
+        // no caps at all
+        assert!(GasBudget::default().allows(u128::MAX));
+
+        assert_eq!(GasBudget::parse_gwei("0.5").unwrap(), gwei(1) / 2);
+        assert_eq!(
+            GasBudget::parse_eth("0.001").unwrap(),
+            U256::from(gwei(1_000_000))
+        );
+        assert!(GasBudget::parse_gwei("cheap").is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Instant;
+
+/// Time spent on each stage of handling a task in milliseconds, noted within the metadata of
+/// the response (for the stages before the upload) and recorded along with its costs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TaskTimings {
+    /// Downloading the input (and the responses, for validations) from the storage.
+    pub download_ms: u64,
+    /// Checking & parsing the input into a request.
+    pub parse_ms: u64,
+    /// Executing the workflow, including its retries.
+    pub model_ms: u64,
+    /// Post-processing the output for the protocol.
+    pub post_process_ms: u64,
+    /// Uploading the output & metadata to the storage.
+    pub upload_ms: u64,
+    /// Mining the nonce of the response.
+    pub mining_ms: u64,
+    /// Sending the response and waiting for its receipt.
+    pub tx_ms: u64,
+}
+
+/// Measures the time between consecutive stages.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stopwatch(Instant);
+
+impl Stopwatch {
+    /// Starts measuring from now.
+    pub fn start() -> Self {
+        Self(Instant::now())
+    }
+
+    /// Returns the milliseconds since the previous lap (or the start), and starts the next lap.
+    pub fn lap(&mut self) -> u64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.0).as_millis() as u64;
+        self.0 = now;
+        elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_stopwatch() {
+        let mut stopwatch = Stopwatch::start();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(stopwatch.lap() >= 20);
+        assert!(stopwatch.lap() < 20);
+
+        let timings = TaskTimings {
+            model_ms: 1200,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(timings).unwrap();
+        assert_eq!(json["model_ms"], 1200);
+        assert_eq!(json["tx_ms"], 0);
+    }
+}
@@ -0,0 +1,81 @@
+use std::future::Future;
+
+tokio::task_local! {
+    /// Trace id of the attempt that the current task is handling.
+    static TRACE_ID: TraceId;
+}
+
+/// Identifier of an attempt to handle a task, which ties together the log lines, the events and the recorded
+/// decisions & costs of the attempt, so that a response can be traced back to the logs that produced it.
+///
+/// It is kept out of the response metadata, which the validators & the requesters consume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceId(u64);
+
+impl TraceId {
+    /// Creates a random trace id.
+    pub fn new() -> Self {
+        Self(rand::random())
+    }
+
+    /// Returns the trace id of the attempt that the current task is handling, if any.
+    pub fn current() -> Option<Self> {
+        TRACE_ID.try_with(|trace_id| *trace_id).ok()
+    }
+
+    /// Runs the given future with this trace id, which is returned by [`Self::current`] within it.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        TRACE_ID.scope(self, f).await
+    }
+}
+
+impl Default for TraceId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for TraceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl std::str::FromStr for TraceId {
+    type Err = std::num::ParseIntError;
+
+    /// Parses a trace id given as 16 hexadecimal digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
+impl serde::Serialize for TraceId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TraceId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trace_id() {
+        let trace_id = TraceId::new();
+        let json = serde_json::to_string(&trace_id).unwrap();
+        assert_eq!(json.len(), 16 + 2);
+        assert_eq!(serde_json::from_str::<TraceId>(&json).unwrap(), trace_id);
+
+        assert_eq!(TraceId::current(), None);
+        let current = trace_id.scope(async { TraceId::current() }).await;
+        assert_eq!(current, Some(trace_id));
+    }
+}
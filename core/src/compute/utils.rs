@@ -1,25 +1,94 @@
-use alloy::primitives::Bytes;
-use dria_oracle_contracts::bytes_to_string;
+use alloy::contract::{CallBuilder, CallDecoder};
+use alloy::network::Network;
+use alloy::primitives::{Bytes, U256};
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use dria_oracle_contracts::{bytes_to_string, estimate_response_gas};
 use dria_oracle_storage::{ArweaveStorage, IsExternalStorage};
 use eyre::{Context, Result};
 
+use crate::{DriaOracle, DriaOracleError};
+
 /// Parses a given bytes input to a string,
 /// and if it is a storage key identifier it automatically downloads the data from Arweave.
 pub async fn parse_downloadable(input_bytes: &Bytes) -> Result<String> {
+    // without a limit, the input is always there
+    Ok(parse_downloadable_within(input_bytes, None)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Parses a given bytes input like [`parse_downloadable`], but returns `None` if the downloaded data
+/// exceeds the given size in bytes, in which case the download is stopped as soon as it does.
+pub async fn parse_downloadable_within(
+    input_bytes: &Bytes,
+    max_bytes: Option<usize>,
+) -> Result<Option<String>> {
     // first, convert to string
     let mut input_string = bytes_to_string(input_bytes)?;
 
     // then, check storage
     if let Some(key) = ArweaveStorage::is_key(&input_string) {
         // if its a txid, we download the data and parse it again
-        let input_bytes_from_arweave = ArweaveStorage::new_readonly()
-            .get(key)
-            .await
-            .wrap_err("could not download from Arweave")?;
+        let arweave = ArweaveStorage::new_readonly();
+        let input_bytes_from_arweave = match max_bytes {
+            Some(max_bytes) => arweave.get_within(key, max_bytes).await,
+            None => arweave.get(key).await.map(Some),
+        }
+        .wrap_err("could not download from Arweave")
+        .map_err(DriaOracleError::storage)?;
+        let Some(input_bytes_from_arweave) = input_bytes_from_arweave else {
+            return Ok(None);
+        };
 
         // convert the input to string
         input_string = bytes_to_string(&input_bytes_from_arweave)?;
     }
 
-    Ok(input_string)
+    Ok(Some(input_string))
+}
+
+/// Returns `true` if the gas of the given response exceeds the response gas ceiling of the node, in which
+/// case its payloads should be uploaded to the storage regardless of their size.
+///
+/// The gas is estimated by simulating the response with its exact payload (see [`DriaOracle::preflight`]),
+/// or roughly from the sizes of its payloads if it can not be simulated, e.g. if it does not fit in a block.
+pub async fn exceeds_gas_ceiling<T, P, D, N>(
+    node: &DriaOracle,
+    task_id: U256,
+    req: &CallBuilder<T, P, D, N>,
+    payloads: &[&Bytes],
+) -> bool
+where
+    T: Transport + Clone,
+    P: Provider<T, N> + Clone,
+    D: CallDecoder + Clone,
+    N: Network,
+{
+    let Some(ceiling) = node.config.response_gas_ceiling else {
+        return false;
+    };
+
+    let gas = match node.preflight(req).await {
+        Ok(gas) => gas,
+        Err(e) => {
+            log::debug!(
+                "Could not simulate the response for task {}, estimating its gas from its size: {}",
+                task_id,
+                e
+            );
+            estimate_response_gas(payloads)
+        }
+    };
+    if gas > ceiling {
+        log::warn!(
+            "Response for task {} is estimated at {} gas (ceiling {}), uploading it to storage.",
+            task_id,
+            gas,
+            ceiling
+        );
+        return true;
+    }
+
+    false
 }
@@ -2,9 +2,10 @@ use alloy::primitives::U256;
 use dkn_workflows::Model;
 use eyre::{Context, Result};
 
-use crate::compute::execute::execute_workflow_with_timedout_retries;
+use crate::compute::execute::{execute_workflow_with_timedout_retries, RetryPolicy};
 
 use super::workflow::*;
+use super::ScoreMap;
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct ValidationResult {
@@ -23,16 +24,14 @@ pub struct ValidationResult {
 }
 
 impl ValidationResult {
-    /// Clamps the score to the range `[1, 5]` and scales it to the range `[1-255]`.
-    pub fn final_score_as_solidity_type(&self) -> U256 {
-        U256::from(match self.final_score.clamp(1, 5) {
-            1 => 51,
-            2 => 102,
-            3 => 153,
-            4 => 204,
-            5 => 255,
-            _ => unreachable!(),
-        })
+    /// The final score of the response, within `[1, 5]` unless the model misbehaves.
+    pub fn final_score(&self) -> u8 {
+        self.final_score
+    }
+
+    /// Maps the final score to the score written to the contract, see [`ScoreMap`].
+    pub fn final_score_as_solidity_type(&self, map: &ScoreMap) -> U256 {
+        map.map(self.final_score)
     }
 }
 
@@ -41,11 +40,14 @@ pub async fn execute_validations(
     instruction: String,
     generations: Vec<String>,
     model: Model,
+    policy: &RetryPolicy,
 ) -> Result<Vec<ValidationResult>> {
     let (workflow, duration) = make_validation_workflow(instruction, generations)?;
 
     log::debug!("Executing validation request with: {}", model);
-    let result_str = execute_workflow_with_timedout_retries(&workflow, model, duration).await?;
+    let result_str = execute_workflow_with_timedout_retries(&workflow, &[model], duration, policy)
+        .await?
+        .output;
 
     // first parse as vec of string
     // then parse each string as a ValidationResult
@@ -86,9 +88,10 @@ mod tests {
         .collect();
 
         let model = Model::GPT4oMini;
-        let results = execute_validations(instruction, generations.clone(), model)
-            .await
-            .unwrap();
+        let results =
+            execute_validations(instruction, generations.clone(), model, &Default::default())
+                .await
+                .unwrap();
 
         assert_eq!(
             results.len(),
@@ -131,9 +134,10 @@ mod tests {
         let generations: Vec<String> = ["Yes they can."].iter().map(|s| s.to_string()).collect();
 
         let model = Model::GPT4oMini;
-        let results = execute_validations(instruction, generations.clone(), model)
-            .await
-            .unwrap();
+        let results =
+            execute_validations(instruction, generations.clone(), model, &Default::default())
+                .await
+                .unwrap();
 
         assert!(
             results[0].final_score == 5,
@@ -1,6 +1,16 @@
-use crate::{compute::parse_downloadable, mine_nonce, DriaOracle};
-use alloy::{primitives::U256, rpc::types::TransactionReceipt};
-use dkn_workflows::Model;
+use crate::compute::{
+    exceeds_gas_ceiling, execute_within_budget, mine_nonce_blocking, parse_downloadable, Stopwatch,
+    TaskTimings, TokenUsage,
+};
+use crate::{DriaOracle, DriaOracleError, OutboxPayload, TaskCost, TraceId};
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    rpc::types::TransactionReceipt,
+};
+use dria_oracle_contracts::{
+    bytes32_to_string, check_nonce, check_response_size, check_validation_scores, OracleKind,
+    TaskStatus,
+};
 use dria_oracle_storage::ArweaveStorage;
 use eyre::{eyre, Context, Result};
 
@@ -15,7 +25,9 @@ pub async fn handle_validation(
 
     // check if already responded as generator, because we cant validate our own answer
     log::debug!("Checking if we are a generator for this task");
-    let responses = node.coordinator.getResponses(task_id).call().await?._0;
+    let responses = node
+        .get_responses_cached(task_id, TaskStatus::PendingValidation)
+        .await?;
     if responses.iter().any(|r| r.responder == node.address()) {
         log::debug!(
             "Cant validate {} with your own generation response",
@@ -31,52 +43,224 @@ pub async fn handle_validation(
         return Err(eyre!("Already validated {}", task_id));
     }
 
+    // validations require a paid provider
+    if let Some(api_budget) = &node.config.api_budget {
+        if !api_budget.allows_paid() {
+            log::warn!(
+                "Skipping validation task {} as the API budget is exhausted.",
+                task_id
+            );
+            return Ok(None);
+        }
+    }
+
     // fetch the request from contract
     log::debug!("Fetching the task request");
-    let request = node.coordinator.requests(task_id).call().await?;
+    let request = node
+        .get_request_cached(task_id, TaskStatus::PendingValidation)
+        .await?;
 
     // fetch each generation response & download its metadata
     log::debug!("Fetching response messages");
-    let responses = node.coordinator.getResponses(task_id).call().await?._0;
+    let responses = node
+        .get_responses_cached(task_id, TaskStatus::PendingValidation)
+        .await?;
+    let mut stopwatch = Stopwatch::start();
+    let mut timings = TaskTimings::default();
     let mut generations = Vec::new();
+    let generators = responses.iter().map(|r| r.responder).collect::<Vec<_>>();
     for response in responses {
         let metadata_str = parse_downloadable(&response.metadata).await?;
         generations.push(metadata_str);
     }
     let input = parse_downloadable(&request.input).await?;
+    let prompt = format!("{}{}", input, generations.concat());
+    timings.download_ms = stopwatch.lap();
 
     // validate each response
     log::debug!("Computing validation scores");
-    let model = Model::GPT4o; // all validations use Gpt 4o
-    let validations = execute_validations(input, generations, model).await?;
+    let model = DriaOracle::VALIDATION_MODEL;
+    if let Some(warmup) = &node.config.model_warmup {
+        warmup.ensure_ready(&model).await?;
+    }
+    let protocol = bytes32_to_string(&request.protocol).unwrap_or_default();
+    let budget = node
+        .config
+        .task_budget
+        .as_ref()
+        .map(|b| b.for_task(&protocol, request.parameters.difficulty));
+    let validations = execute_within_budget(
+        budget,
+        execute_validations(input, generations, model.clone(), &node.config.retry_policy),
+    )
+    .await?;
+    timings.model_ms = stopwatch.lap();
+    let score_map = node.config.score_maps.for_protocol(&protocol);
     let scores = validations
         .iter()
-        .map(|v| v.final_score_as_solidity_type())
+        .map(|v| v.final_score_as_solidity_type(score_map))
         .collect::<Vec<_>>();
+    let peers = note_peer_reputations(node, &generators, &scores);
     let metadata =
         serde_json::to_string(&validations).wrap_err("could not serialize validations")?;
     log::debug!("Validation metadata:\n{}", metadata);
-
-    // uploading to storage
-    log::debug!("Uploading metadata to storage");
-    let arweave = ArweaveStorage::new_from_env()?;
-    let metadata = arweave.put_if_large(metadata.into()).await?;
+    let usage = TokenUsage::estimate(&prompt, &metadata);
+    if let Some(api_budget) = &node.config.api_budget {
+        api_budget.record(&model, usage.cost_usd(&model));
+    }
+    timings.post_process_ms = stopwatch.lap();
 
     // mine nonce
     log::debug!("Mining nonce for task");
-    let nonce = mine_nonce(
+    let mined = mine_nonce_blocking(
         request.parameters.difficulty,
-        &request.requester,
+        request.requester,
+        node.address(),
+        request.input.clone(),
+        task_id,
+    )
+    .await?;
+    node.record_mining(
+        task_id,
+        OracleKind::Validator,
+        request.parameters.difficulty,
+        &mined,
+    )
+    .await;
+    let nonce = mined.nonce;
+    timings.mining_ms = stopwatch.lap();
+
+    // uploading to storage, where the metadata of a response that would exceed the gas ceiling
+    // is uploaded regardless of its size
+    log::debug!("Uploading metadata to storage");
+    let metadata = Bytes::from(metadata);
+    let mut arweave = ArweaveStorage::new_from_env().map_err(DriaOracleError::config)?;
+    let response = node
+        .coordinator
+        .validate(task_id, nonce, scores.clone(), metadata.clone());
+    if exceeds_gas_ceiling(node, task_id, &response, &[&metadata]).await {
+        arweave = arweave.with_upload_byte_limit(0);
+    }
+    let (metadata, receipt) = if arweave.has_encryption() {
+        let (metadata, receipt) = arweave
+            .put_encrypted_with_receipt(metadata)
+            .await
+            .map_err(DriaOracleError::storage)?;
+        (metadata, Some(receipt))
+    } else {
+        arweave
+            .put_if_large_with_receipt(metadata)
+            .await
+            .map_err(DriaOracleError::storage)?
+    };
+
+    // catch the errors that the coordinator would revert with before broadcasting
+    check_validation_scores(
+        &task_id,
         &node.address(),
-        &request.input,
+        &scores,
+        request.parameters.numGenerations.to::<u64>(),
+    )?;
+    check_nonce(
         &task_id,
+        request.parameters.difficulty,
+        &request.input,
+        &request.requester,
+        &node.address(),
+        &nonce,
+    )?;
+    check_response_size(&task_id, &[&metadata])?;
+
+    // the response is not sent before its upload is retrievable
+    let payload = OutboxPayload::Validator {
+        scores: scores.clone(),
+    };
+    node.verify_uploads(
+        &arweave,
+        task_id,
+        &payload,
+        &metadata,
+        nonce,
+        receipt.into_iter().collect(),
     )
-    .nonce;
+    .await?;
+    timings.upload_ms = stopwatch.lap();
 
     // respond
     log::debug!("Responding with validation");
     let tx_receipt = node
         .respond_validation(task_id, scores, metadata, nonce)
         .await?;
+    timings.tx_ms = stopwatch.lap();
+    log::debug!("Timings of task {}: {:?}", task_id, timings);
+
+    // record costs
+    let cost = TaskCost {
+        scope: node.response_scope(),
+        task_id,
+        block_number: tx_receipt.block_number.unwrap_or_default(),
+        kind: OracleKind::Validator.to_string(),
+        protocol,
+        model: model.to_string(),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        provider_cost_usd: usage.cost_usd(&model),
+        gas_fee: U256::from(tx_receipt.gas_used) * U256::from(tx_receipt.effective_gas_price),
+        reward: request.validatorFee,
+        timings,
+        notes: serde_json::json!({ "peers": peers }),
+        trace_id: TraceId::current(),
+    };
+    if let Err(e) = node
+        .db
+        .write_blocking(move |db| db.insert_cost(&cost))
+        .await
+    {
+        log::warn!("Could not record costs of task {}: {:#}", task_id, e);
+    }
+
     Ok(Some(tx_receipt))
 }
+
+/// Returns the scores of the generators along with their reputation within the recorded tasks
+/// (see the `peers` command), so that a score that deviates from the track record of its generator
+/// can be looked into afterwards.
+///
+/// A failure to read the reputations is only logged, as they are not needed to respond.
+fn note_peer_reputations(
+    node: &DriaOracle,
+    generators: &[Address],
+    scores: &[U256],
+) -> Vec<serde_json::Value> {
+    let reputations = node
+        .db
+        .get_peer_reputations(*node.coordinator.address())
+        .unwrap_or_else(|e| {
+            log::warn!("Could not read the reputation of the generators: {:#}", e);
+            Vec::new()
+        });
+
+    generators
+        .iter()
+        .zip(scores)
+        .map(|(generator, score)| {
+            let reputation = reputations.iter().find(|r| r.address == *generator);
+            if let Some(reputation) = reputation {
+                log::debug!(
+                    "Scored generator {} with {}, its average score is {:.2} over {} responses",
+                    generator,
+                    score,
+                    reputation.average_score,
+                    reputation.responses
+                );
+            }
+
+            serde_json::json!({
+                "address": generator,
+                "score": score,
+                "average_score": reputation.map(|r| r.average_score),
+                "responses": reputation.map_or(0, |r| r.responses),
+            })
+        })
+        .collect()
+}
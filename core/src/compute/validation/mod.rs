@@ -2,4 +2,10 @@ mod execute;
 mod handler;
 mod workflow;
 
+mod scores;
+pub use scores::{ScoreMap, ScoreMaps};
+
 pub use handler::handle_validation;
+
+pub(crate) use execute::execute_validations;
+pub(crate) use workflow::make_validation_workflow;
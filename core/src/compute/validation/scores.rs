@@ -0,0 +1,171 @@
+use alloy::primitives::U256;
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Maps the final scores of the validations to the scores written to the contract.
+///
+/// Final scores are clamped to the range of the map, e.g. with the default map of `[1, 5]`
+/// a score of 0 is written as 51 and a score of 7 is written as 255.
+///
+/// Parsed from comma-separated `<score>:<value>` pairs of consecutive scores, e.g. `1:51,2:102,3:153,4:204,5:255`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreMap {
+    /// Lowest final score, which is mapped to the first value.
+    min: u8,
+    /// Values of the final scores from `min` on.
+    values: Vec<U256>,
+}
+
+impl Default for ScoreMap {
+    /// Maps the scores `[1, 5]` to `[51, 255]` with steps of 51.
+    fn default() -> Self {
+        Self {
+            min: 1,
+            values: [51u64, 102, 153, 204, 255].map(U256::from).to_vec(),
+        }
+    }
+}
+
+impl ScoreMap {
+    /// Creates a map of the consecutive scores starting from `min` to the given values.
+    pub fn new(min: u8, values: Vec<U256>) -> Result<Self> {
+        if values.is_empty() {
+            return Err(eyre!("Score map must have at least one score"));
+        }
+        if usize::from(min) + values.len() > usize::from(u8::MAX) + 1 {
+            return Err(eyre!("Score map exceeds the maximum score of {}", u8::MAX));
+        }
+
+        Ok(Self { min, values })
+    }
+
+    /// Lowest final score of the map.
+    pub fn min(&self) -> u8 {
+        self.min
+    }
+
+    /// Highest final score of the map.
+    pub fn max(&self) -> u8 {
+        // length is checked to fit when the map is created
+        self.min + (self.values.len() - 1) as u8
+    }
+
+    /// Returns the value of the given final score, after clamping it to the range of the map.
+    pub fn map(&self, score: u8) -> U256 {
+        let score = score.clamp(self.min(), self.max());
+        self.values[usize::from(score - self.min)]
+    }
+}
+
+impl FromStr for ScoreMap {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pairs = s
+            .split(',')
+            .map(|pair| {
+                let (score, value) = pair
+                    .trim()
+                    .split_once(':')
+                    .ok_or_else(|| eyre!("Expected <score>:<value>, got: {}", pair))?;
+                Ok((score.trim().parse::<u8>()?, U256::from_str(value.trim())?))
+            })
+            .collect::<Result<Vec<(u8, U256)>>>()?;
+        pairs.sort_by_key(|(score, _)| *score);
+
+        let min = pairs.first().map(|(score, _)| *score).unwrap_or_default();
+        for (idx, (score, _)) in pairs.iter().enumerate() {
+            if usize::from(*score) != usize::from(min) + idx {
+                return Err(eyre!("Score map must have consecutive scores, got: {}", s));
+            }
+        }
+
+        Self::new(min, pairs.into_iter().map(|(_, value)| value).collect())
+    }
+}
+
+/// Score maps of the validations, with an optional map per protocol.
+#[derive(Debug, Clone, Default)]
+pub struct ScoreMaps {
+    /// Map of the protocols without a map of their own.
+    base: ScoreMap,
+    /// Maps of protocols, keyed by either the full protocol or its name.
+    protocols: HashMap<String, ScoreMap>,
+}
+
+impl ScoreMaps {
+    /// Creates score maps where all protocols use the given map.
+    pub fn new(base: ScoreMap) -> Self {
+        Self {
+            base,
+            protocols: HashMap::new(),
+        }
+    }
+
+    /// Sets the map of the tasks with the given protocol.
+    pub fn with_protocol(mut self, protocol: impl Into<String>, map: ScoreMap) -> Self {
+        self.protocols.insert(protocol.into(), map);
+        self
+    }
+
+    /// Returns the map of a task with the given protocol.
+    ///
+    /// Protocols are matched either exactly or by their name without the version,
+    /// e.g. a map for `swan` applies to `swan/0.1.0` as well.
+    pub fn for_protocol(&self, protocol: &str) -> &ScoreMap {
+        let name = protocol.split('/').next().unwrap_or_default();
+        self.protocols
+            .get(protocol)
+            .or_else(|| self.protocols.get(name))
+            .unwrap_or(&self.base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_map_boundaries() {
+        let map = ScoreMap::default();
+        assert_eq!(map.map(0), U256::from(51));
+        assert_eq!(map.map(1), U256::from(51));
+        assert_eq!(map.map(3), U256::from(153));
+        assert_eq!(map.map(5), U256::from(255));
+        assert_eq!(map.map(u8::MAX), U256::from(255));
+
+        let map = ScoreMap::from_str("0:0, 1:255").unwrap();
+        assert_eq!((map.min(), map.max()), (0, 1));
+        assert_eq!(map.map(0), U256::ZERO);
+        assert_eq!(map.map(9), U256::from(255));
+
+        // single score maps everything to the same value
+        let map = ScoreMap::from_str("255:1").unwrap();
+        assert_eq!(map.map(0), U256::from(1));
+        assert_eq!(map.max(), u8::MAX);
+    }
+
+    #[test]
+    fn test_score_map_parse() {
+        assert_eq!(
+            ScoreMap::from_str("5:255,4:204,3:153,2:102,1:51").unwrap(),
+            ScoreMap::default()
+        );
+        assert!(ScoreMap::from_str("").is_err());
+        assert!(ScoreMap::from_str("1:51,3:153").is_err());
+        assert!(ScoreMap::from_str("1:51,1:52").is_err());
+        assert!(ScoreMap::from_str("1=51").is_err());
+        assert!(ScoreMap::from_str("256:1").is_err());
+        assert!(ScoreMap::new(200, vec![U256::ZERO; 57]).is_err());
+        assert!(ScoreMap::new(200, vec![U256::ZERO; 56]).is_ok());
+    }
+
+    #[test]
+    fn test_score_maps() {
+        let swan = ScoreMap::from_str("1:1,2:64,3:128,4:192,5:255").unwrap();
+        let maps = ScoreMaps::default().with_protocol("swan", swan.clone());
+        assert_eq!(maps.for_protocol("swan/0.1.0"), &swan);
+        assert_eq!(maps.for_protocol("dria/0.1.0"), &ScoreMap::default());
+    }
+}
@@ -0,0 +1,276 @@
+use dkn_workflows::{Model, ModelProvider};
+use eyre::{eyre, Context, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::ollama::{is_same_model, OllamaClient, OllamaModel};
+
+/// Memory that a model takes within the VRAM in addition to its weights, e.g. for its context, in percent.
+const VRAM_OVERHEAD_PERCENT: u64 = 20;
+
+/// Probes the free VRAM of the machine that Ollama runs on, so that the local models that would not
+/// fit are not chosen for a task, as loading them fails or spills them onto the CPU.
+///
+/// The models that Ollama has loaded already are counted as free memory, as Ollama evicts them to load
+/// another one, unless they are reserved by the tasks in progress. The VRAM is read with `nvidia-smi`,
+/// i.e. only NVIDIA GPUs are supported, and nothing is filtered when there is no GPU to read.
+///
+/// A probe is reused for [`Self::CACHE_DURATION`], so that the tasks do not run it each time.
+#[derive(Debug)]
+pub struct VramProbe {
+    ollama: OllamaClient,
+    /// The last probe along with its time, where `None` is a failed probe.
+    cache: tokio::sync::Mutex<Option<(Instant, Option<VramState>)>>,
+    /// Number of the tasks in progress that have reserved each model.
+    reserved: Mutex<HashMap<String, usize>>,
+}
+
+/// Models that a task in progress may run on, which are not counted as evictable for the other
+/// tasks until this is dropped, see [`VramProbe::reserve`].
+#[derive(Debug)]
+pub struct VramReservation<'a> {
+    probe: &'a VramProbe,
+    models: Vec<String>,
+}
+
+impl Drop for VramReservation<'_> {
+    fn drop(&mut self) {
+        let mut reserved = self.probe.reserved.lock().expect("lock poisoned");
+        for name in &self.models {
+            if let Some(count) = reserved.get_mut(name) {
+                *count -= 1;
+                if *count == 0 {
+                    reserved.remove(name);
+                }
+            }
+        }
+    }
+}
+
+/// Memory of the GPUs and the models in Ollama, at the time that the probe is run.
+#[derive(Debug, Clone, Default)]
+struct VramState {
+    /// Free VRAM in bytes, summed over all GPUs.
+    free: u64,
+    /// Models that are loaded by Ollama.
+    running: Vec<OllamaModel>,
+    /// Models that are present in Ollama, with their sizes on disk.
+    present: Vec<OllamaModel>,
+}
+
+impl VramState {
+    /// Returns `true` if the model fits into the VRAM, i.e. it is loaded already, or its size along with
+    /// the overhead is within the free VRAM plus the memory of the loaded models that are not reserved.
+    ///
+    /// A model whose size is not known is assumed to fit.
+    fn fits(&self, name: &str, size: Option<u64>, is_reserved: impl Fn(&str) -> bool) -> bool {
+        if self.running.iter().any(|m| is_same_model(&m.name, name)) {
+            return true;
+        }
+        let Some(size) = size else {
+            return true;
+        };
+
+        let evictable = self
+            .running
+            .iter()
+            .filter(|m| !is_reserved(&m.name))
+            .map(|m| m.size_vram)
+            .sum::<u64>();
+        size + size * VRAM_OVERHEAD_PERCENT / 100 <= self.free + evictable
+    }
+
+    /// Returns the size of the model on disk if it is present in Ollama.
+    fn present_size(&self, name: &str) -> Option<u64> {
+        self.present
+            .iter()
+            .find(|m| is_same_model(&m.name, name))
+            .map(|m| m.size)
+            .filter(|size| *size > 0)
+    }
+}
+
+impl VramProbe {
+    /// Time to reuse a probe for.
+    pub const CACHE_DURATION: Duration = Duration::from_secs(15);
+
+    /// Creates the probe for the given Ollama, which must run on this machine for its VRAM to be read.
+    pub fn new(ollama: OllamaClient) -> Result<Self> {
+        if !ollama.is_local() {
+            return Err(eyre!(
+                "Ollama at {} is not local, its VRAM can not be probed",
+                ollama.url
+            ));
+        }
+
+        Ok(Self {
+            ollama,
+            cache: Default::default(),
+            reserved: Default::default(),
+        })
+    }
+
+    /// Reserves the local models among the given ones for a task in progress, so that the other tasks
+    /// do not count on evicting them. The reservation is released when the returned guard is dropped.
+    pub fn reserve(&self, models: &[Model]) -> VramReservation<'_> {
+        let models = models
+            .iter()
+            .filter(|model| ModelProvider::from((*model).clone()) == ModelProvider::Ollama)
+            .map(|model| model.to_string())
+            .collect::<Vec<_>>();
+        let mut reserved = self.reserved.lock().expect("lock poisoned");
+        for name in &models {
+            *reserved.entry(name.clone()).or_default() += 1;
+        }
+
+        VramReservation {
+            probe: self,
+            models,
+        }
+    }
+
+    /// Returns `true` if the model within Ollama is reserved by a task in progress.
+    fn is_reserved(&self, ollama_name: &str) -> bool {
+        self.reserved
+            .lock()
+            .expect("lock poisoned")
+            .keys()
+            .any(|name| is_same_model(ollama_name, name))
+    }
+
+    /// Drops the local models that would not fit into the VRAM, keeping the models of the other
+    /// providers so that the task falls back to a smaller matching model or a cloud provider.
+    ///
+    /// The models are returned as they are if the VRAM can not be probed.
+    pub async fn retain_fitting(&self, models: Vec<Model>) -> Vec<Model> {
+        let is_local = |model: &Model| ModelProvider::from(model.clone()) == ModelProvider::Ollama;
+        if !models.iter().any(is_local) {
+            return models;
+        }
+
+        let Some(state) = self.probe_cached().await else {
+            return models;
+        };
+
+        let mut fitting = Vec::with_capacity(models.len());
+        for model in models {
+            if is_local(&model) {
+                let name = model.to_string();
+                // the size of a model that is not pulled yet is not known, so it is assumed to fit
+                let size = state.present_size(&name);
+                if !state.fits(&name, size, |name| self.is_reserved(name)) {
+                    log::warn!(
+                        "Model {} ({} MB) does not fit into the {} MB of free VRAM, skipping it.",
+                        name,
+                        size.unwrap_or_default() / (1024 * 1024),
+                        state.free / (1024 * 1024)
+                    );
+                    continue;
+                }
+            }
+            fitting.push(model);
+        }
+
+        fitting
+    }
+
+    /// Returns the last probe if it is recent enough, or probes again otherwise.
+    ///
+    /// A failed probe is logged and cached as well, so that it is not retried by every task.
+    async fn probe_cached(&self) -> Option<VramState> {
+        let mut cache = self.cache.lock().await;
+        if let Some((at, state)) = cache.as_ref() {
+            if at.elapsed() < Self::CACHE_DURATION {
+                return state.clone();
+            }
+        }
+
+        let state = match self.probe().await {
+            Ok(state) => Some(state),
+            Err(e) => {
+                log::warn!("Could not probe the VRAM: {:#}", e);
+                None
+            }
+        };
+        *cache = Some((Instant::now(), state.clone()));
+
+        state
+    }
+
+    /// Reads the free VRAM and the models in Ollama.
+    async fn probe(&self) -> Result<VramState> {
+        let output = tokio::process::Command::new("nvidia-smi")
+            .args(["--query-gpu=memory.free", "--format=csv,noheader,nounits"])
+            .output()
+            .await
+            .wrap_err("could not run nvidia-smi")?;
+        if !output.status.success() {
+            return Err(eyre!("nvidia-smi has exited with {}", output.status));
+        }
+        let free = parse_free_vram(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| eyre!("could not parse the output of nvidia-smi"))?;
+
+        Ok(VramState {
+            free,
+            running: self.ollama.running_models().await?,
+            present: self.ollama.list_models().await?,
+        })
+    }
+}
+
+/// Parses the free memory of each GPU in MiB, one per line, into their total in bytes.
+fn parse_free_vram(output: &str) -> Option<u64> {
+    let mut lines = output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .peekable();
+    lines.peek()?;
+
+    lines
+        .map(|line| line.trim().parse::<u64>().ok())
+        .sum::<Option<u64>>()
+        .map(|mib| mib * 1024 * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_parse_free_vram() {
+        assert_eq!(parse_free_vram("8192\n"), Some(8 * GB));
+        assert_eq!(parse_free_vram("1024\n3072\n"), Some(4 * GB));
+        assert_eq!(parse_free_vram(""), None);
+        assert_eq!(parse_free_vram("[N/A]\n"), None);
+    }
+
+    #[test]
+    fn test_fits() {
+        let model = |name: &str, size_vram: u64| OllamaModel {
+            name: name.to_string(),
+            size: size_vram,
+            size_vram,
+        };
+        let state = VramState {
+            free: 4 * GB,
+            running: vec![model("phi3:3.8b", 3 * GB)],
+            present: vec![],
+        };
+
+        let none = |_: &str| false;
+
+        // loaded models always fit, and unknown sizes are assumed to fit
+        assert!(state.fits("phi3:3.8b", Some(100 * GB), none));
+        assert!(state.fits("llama3.1:latest", None, none));
+        // the loaded model is evicted for another one, along with the overhead
+        assert!(state.fits("llama3.1:latest", Some(5 * GB), none));
+        assert!(!state.fits("llama3.1:latest", Some(6 * GB), none));
+        assert!(!state.fits("llama3.1:70b", Some(40 * GB), none));
+        // unless another task is using it
+        assert!(!state.fits("llama3.1:latest", Some(5 * GB), |name| name == "phi3:3.8b"));
+        assert!(state.fits("llama3.1:latest", Some(3 * GB), |name| name == "phi3:3.8b"));
+    }
+}
@@ -0,0 +1,221 @@
+use dkn_workflows::{DriaWorkflowsConfig, Model, OllamaConfig};
+use eyre::{eyre, Context, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Minimum tokens per second that a local model must generate with to be served.
+const MIN_OLLAMA_TPS: f64 = 5.0;
+
+/// Applies the checks of the local models to the given config, i.e. their minimum throughput
+/// and the time to wait for them to respond.
+pub(crate) fn with_ollama_checks(
+    config: DriaWorkflowsConfig,
+    timeout: Duration,
+) -> DriaWorkflowsConfig {
+    let ollama_config = config.ollama.clone();
+    config.with_ollama_config(
+        ollama_config
+            .with_min_tps(MIN_OLLAMA_TPS)
+            .with_timeout(timeout),
+    )
+}
+
+/// Readiness of a model that has been warmed up.
+#[derive(Debug, Clone)]
+enum Readiness {
+    Ready,
+    Failed { at: Instant, error: String },
+}
+
+/// Prepares each model the first time that a task requires it, instead of checking all of them
+/// before serving, which reduces the startup time of a node that serves many optional models.
+///
+/// Warming up a model runs the same checks as serving it eagerly, i.e. a local model is loaded
+/// and its throughput is measured, while the API key is checked for the other providers. A local
+/// model that is missing is not pulled here, see [`OllamaAutoPull`](super::OllamaAutoPull) to pull
+/// it before its warmup. The readiness is cached, and a model that has failed to warm up is not
+/// tried again for [`Self::RETRY_DELAY`].
+#[derive(Debug)]
+pub struct ModelWarmup {
+    /// Time to wait for a model to warm up, which is also the most that a task waits for its models.
+    pub timeout: Duration,
+    /// Config of the Ollama that the local models are warmed up with.
+    ollama: OllamaConfig,
+    /// Readiness of each model, where the lock of a model is held while it is warmed up so that
+    /// the concurrent tasks that require it wait for the same warmup.
+    models: Mutex<HashMap<String, Arc<tokio::sync::Mutex<Option<Readiness>>>>>,
+}
+
+impl ModelWarmup {
+    /// Default time to wait for a model to warm up, which includes pulling a local model.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+    /// Time to wait before warming up a model that has failed to warm up again.
+    pub const RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+    /// Creates a warmup with the given timeout per model, for the given Ollama.
+    pub fn new(timeout: Duration, ollama: OllamaConfig) -> Self {
+        Self {
+            timeout,
+            ollama: ollama.with_auto_pull(false),
+            models: Mutex::default(),
+        }
+    }
+
+    /// Returns the readiness of the given model, shared by all tasks.
+    fn readiness(&self, model: &Model) -> Arc<tokio::sync::Mutex<Option<Readiness>>> {
+        self.models
+            .lock()
+            .expect("lock poisoned")
+            .entry(model.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Warms up the model unless it is ready already, and returns an error if it is not ready.
+    #[inline]
+    pub async fn ensure_ready(&self, model: &Model) -> Result<()> {
+        self.ensure_ready_within(model, self.timeout).await
+    }
+
+    /// Warms up the model unless it is ready already, and returns an error if it is not ready
+    /// within the given time.
+    ///
+    /// The warmup is not cancelled if it takes longer, so that its readiness is still cached
+    /// for the later tasks.
+    async fn ensure_ready_within(&self, model: &Model, wait: Duration) -> Result<()> {
+        let deadline = Instant::now() + wait;
+        let mut readiness = tokio::time::timeout(wait, self.readiness(model).lock_owned())
+            .await
+            .map_err(|_| eyre!("model {} is still warming up", model))?;
+        match &*readiness {
+            Some(Readiness::Ready) => return Ok(()),
+            Some(Readiness::Failed { at, error }) if at.elapsed() < Self::RETRY_DELAY => {
+                return Err(eyre!("model {} has failed to warm up: {}", model, error));
+            }
+            _ => {}
+        }
+
+        log::info!("Warming up model {} for the first time.", model);
+        let (timeout, ollama, name) = (self.timeout, self.ollama.clone(), model.to_string());
+        let model = model.clone();
+        let warmup = tokio::spawn(async move {
+            let started = Instant::now();
+            let result = Self::warm_up(&model, timeout, ollama).await;
+            match &result {
+                Ok(()) => {
+                    log::info!(
+                        "Model {} is ready after {}s.",
+                        model,
+                        started.elapsed().as_secs()
+                    );
+                    *readiness = Some(Readiness::Ready);
+                }
+                Err(e) => {
+                    log::error!("Could not warm up model {}: {:#}", model, e);
+                    *readiness = Some(Readiness::Failed {
+                        at: Instant::now(),
+                        error: format!("{:#}", e),
+                    });
+                }
+            }
+
+            result
+        });
+
+        let wait = deadline.saturating_duration_since(Instant::now());
+        match tokio::time::timeout(wait, warmup).await {
+            Ok(result) => result.wrap_err("warmup has panicked")?,
+            Err(_) => Err(eyre!("model {} is still warming up", name)),
+        }
+    }
+
+    /// Warms up the given models in order until one of them is ready, and returns the models
+    /// starting from that one, i.e. the ones that have failed are dropped.
+    ///
+    /// The models after the ready one are kept as they are, to fall back to. The timeout applies to
+    /// all models together, so that a task does not wait for the timeout once per model; a model
+    /// that is still warming up when it runs out is dropped for this task only.
+    pub async fn first_ready(&self, mut models: Vec<Model>) -> Vec<Model> {
+        let deadline = Instant::now() + self.timeout;
+        while let Some(model) = models.first() {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match self.ensure_ready_within(model, wait).await {
+                Ok(()) => break,
+                Err(e) => log::debug!("Skipping model {}: {:#}", model, e),
+            }
+            models.remove(0);
+        }
+
+        models
+    }
+
+    /// Runs the checks of the model within the timeout.
+    async fn warm_up(model: &Model, timeout: Duration, ollama: OllamaConfig) -> Result<()> {
+        let mut config = with_ollama_checks(
+            DriaWorkflowsConfig::new(vec![model.clone()]).with_ollama_config(ollama),
+            timeout,
+        );
+
+        tokio::time::timeout(timeout, config.check_services())
+            .await
+            .map_err(|_| eyre!("timed out after {}s", timeout.as_secs()))?
+            .wrap_err_with(|| format!("model {} is not available", model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cached_readiness() {
+        let warmup = ModelWarmup::new(Duration::from_secs(1), OllamaConfig::default());
+        let (ready, failed) = (Model::GPT4o, Model::GPT4oMini);
+
+        *warmup.readiness(&ready).lock().await = Some(Readiness::Ready);
+        *warmup.readiness(&failed).lock().await = Some(Readiness::Failed {
+            at: Instant::now(),
+            error: "not found".to_string(),
+        });
+
+        // neither is warmed up again, as their readiness is cached
+        assert!(warmup.ensure_ready(&ready).await.is_ok());
+        assert!(warmup.ensure_ready(&failed).await.is_err());
+        assert_eq!(
+            warmup
+                .first_ready(vec![failed.clone(), ready.clone(), failed.clone()])
+                .await,
+            vec![ready, failed]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warmup_budget() {
+        let timeout = Duration::from_millis(500);
+        let warmup = ModelWarmup::new(timeout, OllamaConfig::default());
+        let (ready, busy, other) = (Model::GPT4o, Model::GPT4oMini, Model::GPT4Turbo);
+        *warmup.readiness(&ready).lock().await = Some(Readiness::Ready);
+
+        // the locks are held as if the models were warming up for another task
+        let busy_lock = warmup.readiness(&busy);
+        let other_lock = warmup.readiness(&other);
+        let _busy_guard = busy_lock.lock().await;
+        let _other_guard = other_lock.lock().await;
+
+        // the task waits for the timeout once for all models, and a cached model is ready regardless
+        let started = Instant::now();
+        assert_eq!(
+            warmup
+                .first_ready(vec![busy.clone(), other.clone(), ready.clone()])
+                .await,
+            vec![ready]
+        );
+        assert!(started.elapsed() < timeout * 2);
+
+        let started = Instant::now();
+        assert!(warmup.first_ready(vec![busy, other]).await.is_empty());
+        assert!(started.elapsed() < timeout * 2);
+    }
+}
@@ -1,12 +1,28 @@
 use alloy::{
-    hex::FromHex, network::EthereumWallet, primitives::B256, signers::local::PrivateKeySigner,
+    hex::FromHex,
+    network::EthereumWallet,
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
     transports::http::reqwest::Url,
 };
 
-use eyre::{Context, Result};
+use eyre::{eyre, Context, Result};
+use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+use crate::{
+    ApiBudget, ChatHistoryPolicy, DriaOracle, DriaOracleError, EventBridge, GasBudget, Guardrails,
+    InputLimits, MockModel, ModelWarmup, OllamaAutoPull, PriceFeed, QueuePolicy, RemoteSigner,
+    RetentionPolicy, RetryPolicy, ScoreMaps, SelfCheck, ServeGate, ServeWindow, Shard, TaskBudget,
+    TaskLock, ToolSandbox, VramProbe, WorkflowPresets,
+};
+
+/// Default directory for the local database.
+pub(crate) const DEFAULT_DATA_DIR: &str = "./data";
+
 /// Configuration for the Dria Oracle.
 #[derive(Debug, Clone)]
 pub struct DriaOracleConfig {
@@ -14,20 +30,134 @@ pub struct DriaOracleConfig {
     pub wallet: EthereumWallet,
     /// RPC URL for the oracle, decides the connected chain.
     pub rpc_url: Url,
+    /// Optional coordinator address, overrides the known deployment of the connected chain.
+    pub coordinator_address: Option<Address>,
+    /// Optional registry address, must match the registry of the coordinator if it can be read.
+    pub registry_address: Option<Address>,
+    /// Optional fee token address, must match the fee token of the coordinator if it can be read.
+    pub token_address: Option<Address>,
     /// Optional transaction timeout, is useful to avoid getting stuck at `get_receipt()` when making a transaction.
     pub tx_timeout: Option<Duration>,
+    /// Directory for the local database, e.g. task costs.
+    pub data_dir: PathBuf,
+    /// Optional maximum for a random delay before handling a generation task, to reduce racing with other generators.
+    pub max_start_delay: Option<Duration>,
+    /// Optional RPC rate limit as requests per second and burst size.
+    pub rpc_rate_limit: Option<(f64, u32)>,
+    /// Optional deadline for handling a task, counted from when the task has entered its current status.
+    pub task_deadline: Option<Duration>,
+    /// Optional execution budget of each task, the execution is aborted once it is exceeded.
+    pub task_budget: Option<TaskBudget>,
+    /// Policy to retry the failed executions of workflows.
+    pub retry_policy: RetryPolicy,
+    /// Maps of the validation scores to the scores written to the contract, per protocol.
+    pub score_maps: ScoreMaps,
+    /// Workflow presets of protocols, which replace the default generation workflow.
+    pub workflow_presets: Arc<WorkflowPresets>,
+    /// Optional guardrails to screen the inputs & outputs of generations.
+    pub guardrails: Option<Guardrails>,
+    /// Optional validation pass of the generations before responding, see [`SelfCheck`].
+    pub self_check: Option<SelfCheck>,
+    /// Limits on the tools of the workflows that are given by the requesters.
+    pub tool_sandbox: ToolSandbox,
+    /// Optional mock model backend that returns canned outputs for the generations, for tests.
+    pub mock_model: Option<MockModel>,
+    /// Limits on the size & complexity of the inputs, tasks that exceed them are skipped.
+    pub input_limits: InputLimits,
+    /// Optional ceiling on the estimated gas of the response payloads, above which they are uploaded to the storage.
+    pub response_gas_ceiling: Option<u64>,
+    /// Optional tag of the operator (e.g. a short name or URL), reported within the node status.
+    ///
+    /// It is not noted within the response metadata, which the validators & the requesters consume.
+    pub operator_tag: Option<String>,
+    /// How the chat histories are compressed & uploaded.
+    pub chat_history: ChatHistoryPolicy,
+    /// Optional source of the ETH price, to display the token & gas amounts in USD.
+    pub price_feed: Option<PriceFeed>,
+    /// Optional source of the fee token price, for a fee token that is not WETH.
+    pub token_price_feed: Option<PriceFeed>,
+    /// Optional remote signer that submits the responses, so that the wallet of the node needs no funds.
+    pub remote_signer: Option<RemoteSigner>,
+    /// Optional caps on the spending of paid providers, shared by the clones of the config.
+    pub api_budget: Option<Arc<ApiBudget>>,
+    /// Optional caps on the gas of this chain, shared by the clones of the config but not by the other chains.
+    pub gas_budget: Option<Arc<GasBudget>>,
+    /// Optional warmup of the models on demand instead of checking them before serving, shared by the clones of the config.
+    pub model_warmup: Option<Arc<ModelWarmup>>,
+    /// Optional pulling of the missing local models before executing a task, shared by the clones of the config.
+    pub ollama_auto_pull: Option<Arc<OllamaAutoPull>>,
+    /// Optional probe of the free VRAM to skip the local models that would not fit, shared by the clones of the config.
+    pub vram_probe: Option<Arc<VramProbe>>,
+    /// Ordering policy of the pending tasks, when there are more tasks than the node can handle at once.
+    ///
+    /// Shared by the clones of the config, so that it can be changed while serving.
+    pub queue_policy: Arc<RwLock<QueuePolicy>>,
+    /// Maximum number of tasks dispatched from the queue at the same time.
+    pub max_concurrent_tasks: usize,
+    /// Serve windows & maintenance mode of the node, shared by the clones of the config so that
+    /// the maintenance mode can be entered while serving.
+    pub serve_gate: Arc<ServeGate>,
+    /// Whether to ask for confirmation before the actions that move tokens, e.g. approvals.
+    pub confirm_spending: bool,
+    /// Whether to serve the kinds that still match when the coordinator has been upgraded
+    /// to an incompatible implementation, instead of refusing to serve at all.
+    pub allow_abi_mismatch: bool,
+    /// Optional lock acquired per task before handling it, shared with the other replicas of this node.
+    pub task_lock: Option<TaskLock>,
+    /// Optional shard of the tasks that this node handles, see [`Shard`].
+    pub shard: Option<Shard>,
+    /// Optional bridge to publish the observed events & our responses to a message broker.
+    pub event_bridge: Option<EventBridge>,
+    /// Retention of the rows within the local database, compacted periodically while serving.
+    pub retention: RetentionPolicy,
 }
 
 impl DriaOracleConfig {
-    pub fn new(private_key: &B256, rpc_url: Url) -> Result<Self> {
-        let signer =
-            PrivateKeySigner::from_bytes(private_key).wrap_err("could not parse private key")?;
+    pub fn new(private_key: &B256, rpc_url: Url) -> Result<Self, DriaOracleError> {
+        let signer = PrivateKeySigner::from_bytes(private_key)
+            .map_err(|e| DriaOracleError::Config(format!("could not parse private key: {}", e)))?;
         let wallet = EthereumWallet::from(signer);
 
         Ok(Self {
             wallet,
             rpc_url,
+            coordinator_address: None,
+            registry_address: None,
+            token_address: None,
             tx_timeout: None,
+            data_dir: PathBuf::from(DEFAULT_DATA_DIR),
+            max_start_delay: None,
+            rpc_rate_limit: None,
+            task_deadline: None,
+            task_budget: None,
+            retry_policy: RetryPolicy::default(),
+            score_maps: ScoreMaps::default(),
+            workflow_presets: Arc::default(),
+            guardrails: None,
+            self_check: None,
+            tool_sandbox: ToolSandbox::default(),
+            mock_model: None,
+            input_limits: InputLimits::default(),
+            response_gas_ceiling: None,
+            operator_tag: None,
+            chat_history: ChatHistoryPolicy::default(),
+            price_feed: None,
+            token_price_feed: None,
+            remote_signer: None,
+            api_budget: None,
+            gas_budget: None,
+            model_warmup: None,
+            ollama_auto_pull: None,
+            vram_probe: None,
+            queue_policy: Arc::default(),
+            max_concurrent_tasks: DriaOracle::DEFAULT_CAPACITY,
+            serve_gate: Arc::default(),
+            confirm_spending: false,
+            allow_abi_mismatch: false,
+            task_lock: None,
+            shard: None,
+            event_bridge: None,
+            retention: RetentionPolicy::default(),
         })
     }
 
@@ -36,16 +166,149 @@ impl DriaOracleConfig {
     /// Required environment variables:
     /// - `SECRET_KEY`
     /// - `RPC_URL`
+    ///
+    /// Optional environment variables:
+    /// - `COORDINATOR_ADDRESS`
+    /// - `REGISTRY_ADDRESS` & `TOKEN_ADDRESS`, checked against the ones of the coordinator
+    /// - `RPC_RATE_LIMIT`, as requests per second
+    /// - `RPC_RATE_BURST`, defaults to the rate limit
+    /// - `MAX_GAS_PRICE`, as the base fee in gwei to take new tasks at
+    /// - `MAX_DAILY_GAS_SPEND`, as the gas fees in ETH within a day
+    /// - `PRICE_FEED`, as a Chainlink ETH / USD feed address or an HTTP URL, see [`PriceFeed`]
+    /// - `TOKEN_PRICE_FEED`, same as `PRICE_FEED` for the fee token, if it is not WETH
     pub fn new_from_env() -> Result<Self> {
+        Self::new_from_vars(|key| env::var(key).ok())
+    }
+
+    /// Creates the config from the variables within the given environment file,
+    /// without changing the environment of the process.
+    ///
+    /// This allows having a separate config for each chain, see [`Self::new_from_env`] for the variables.
+    pub fn new_from_env_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let vars = dotenvy::from_path_iter(path)
+            .wrap_err_with(|| format!("could not read env file {}", path.display()))?
+            .collect::<Result<HashMap<String, String>, _>>()
+            .wrap_err_with(|| format!("could not parse env file {}", path.display()))?;
+
+        Self::new_from_vars(|key| vars.get(key).cloned())
+    }
+
+    /// Creates the config using the given variable lookup.
+    fn new_from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
         // parse private key
-        let private_key_hex = env::var("SECRET_KEY")?;
+        let private_key_hex = var("SECRET_KEY").ok_or_else(|| eyre!("SECRET_KEY is not set"))?;
         let secret_key = B256::from_hex(private_key_hex).wrap_err("could not decode secret key")?;
 
         // parse rpc url
-        let rpc_url_env = env::var("RPC_URL")?;
+        let rpc_url_env = var("RPC_URL").ok_or_else(|| eyre!("RPC_URL is not set"))?;
         let rpc_url = Url::parse(&rpc_url_env).wrap_err("could not parse RPC_URL")?;
 
-        Self::new(&secret_key, rpc_url)
+        let mut config = Self::new(&secret_key, rpc_url)?;
+
+        // parse coordinator address, if any
+        // (address can have 0x at the start, or not, does not matter)
+        if let Some(addr) = var("COORDINATOR_ADDRESS").filter(|addr| !addr.is_empty()) {
+            let coordinator_address =
+                Address::from_hex(addr).wrap_err("could not parse coordinator address")?;
+            config = config.with_coordinator_address(coordinator_address);
+        }
+        if let Some(addr) = var("REGISTRY_ADDRESS").filter(|addr| !addr.is_empty()) {
+            let registry_address =
+                Address::from_hex(addr).wrap_err("could not parse registry address")?;
+            config = config.with_registry_address(registry_address);
+        }
+        if let Some(addr) = var("TOKEN_ADDRESS").filter(|addr| !addr.is_empty()) {
+            let token_address =
+                Address::from_hex(addr).wrap_err("could not parse token address")?;
+            config = config.with_token_address(token_address);
+        }
+
+        // parse rpc rate limit, if any
+        if let Some(rate_limit) = var("RPC_RATE_LIMIT").filter(|rate| !rate.is_empty()) {
+            let requests_per_second = rate_limit
+                .parse::<f64>()
+                .wrap_err("could not parse RPC_RATE_LIMIT")?;
+            let burst = match var("RPC_RATE_BURST").filter(|burst| !burst.is_empty()) {
+                Some(burst) => burst.parse().wrap_err("could not parse RPC_RATE_BURST")?,
+                None => requests_per_second.ceil() as u32,
+            };
+            config = config.with_rpc_rate_limit(requests_per_second, burst);
+        }
+
+        // parse gas caps, if any, so that each chain can have its own
+        let max_gas_price = var("MAX_GAS_PRICE")
+            .filter(|price| !price.is_empty())
+            .map(|price| GasBudget::parse_gwei(&price).wrap_err("could not parse MAX_GAS_PRICE"))
+            .transpose()?;
+        let max_daily_gas = var("MAX_DAILY_GAS_SPEND")
+            .filter(|spend| !spend.is_empty())
+            .map(|spend| {
+                GasBudget::parse_eth(&spend).wrap_err("could not parse MAX_DAILY_GAS_SPEND")
+            })
+            .transpose()?;
+        if max_gas_price.is_some() || max_daily_gas.is_some() {
+            config = config.with_gas_budget(GasBudget::new(max_gas_price, max_daily_gas));
+        }
+
+        // parse price feed, if any, as the feeds differ per chain
+        if let Some(feed) = var("PRICE_FEED").filter(|feed| !feed.is_empty()) {
+            config = config.with_price_feed(feed.parse().wrap_err("could not parse PRICE_FEED")?);
+        }
+        if let Some(feed) = var("TOKEN_PRICE_FEED").filter(|feed| !feed.is_empty()) {
+            config = config
+                .with_token_price_feed(feed.parse().wrap_err("could not parse TOKEN_PRICE_FEED")?);
+        }
+
+        Ok(config)
+    }
+
+    /// Replaces the fields that differ per chain with the ones of the given config, i.e. the wallet,
+    /// the RPC, the contracts, the rate limit, the price feeds & the gas caps, keeping the rest of this config.
+    ///
+    /// The gas caps of this config are kept if the given one has none, while the remote signer is
+    /// not kept at all, as it only responds on its own coordinator.
+    pub fn with_chain_of(mut self, chain: Self) -> Self {
+        self.wallet = chain.wallet;
+        self.rpc_url = chain.rpc_url;
+        self.coordinator_address = chain.coordinator_address;
+        self.registry_address = chain.registry_address;
+        self.token_address = chain.token_address;
+        self.rpc_rate_limit = chain.rpc_rate_limit;
+        self.price_feed = chain.price_feed;
+        self.token_price_feed = chain.token_price_feed;
+        self.remote_signer = None;
+        // gas is spent per chain, so the caps are copied instead of shared with this chain
+        self.gas_budget = match (chain.gas_budget, self.gas_budget) {
+            (Some(gas_budget), _) => Some(gas_budget),
+            (None, Some(gas_budget)) => Some(Arc::new(GasBudget::new(
+                gas_budget.max_gas_price,
+                gas_budget.max_wei_per_day,
+            ))),
+            (None, None) => None,
+        };
+
+        self
+    }
+
+    /// Change the coordinator address, overriding the known deployment of the connected chain.
+    pub fn with_coordinator_address(mut self, coordinator_address: Address) -> Self {
+        self.coordinator_address = Some(coordinator_address);
+        self
+    }
+
+    /// Change the registry address, which is used if the coordinator does not return one,
+    /// and must match the one that it returns otherwise.
+    pub fn with_registry_address(mut self, registry_address: Address) -> Self {
+        self.registry_address = Some(registry_address);
+        self
+    }
+
+    /// Change the fee token address, which is used if the coordinator does not return one,
+    /// and must match the one that it returns otherwise.
+    pub fn with_token_address(mut self, token_address: Address) -> Self {
+        self.token_address = Some(token_address);
+        self
     }
 
     /// Change the transaction timeout.
@@ -57,6 +320,224 @@ impl DriaOracleConfig {
         self
     }
 
+    /// Change the maximum random delay before handling a generation task.
+    ///
+    /// Each generation task will wait a random duration between zero and this value before it is handled,
+    /// which reduces the chance of racing with other generators for the same task.
+    pub fn with_max_start_delay(mut self, max_start_delay: Duration) -> Self {
+        self.max_start_delay = Some(max_start_delay);
+        self
+    }
+
+    /// Change the deadline for handling a task.
+    ///
+    /// Tasks that are estimated to be finished after this much time has passed since they have
+    /// entered their current status are skipped, as they are likely to be handled by others already.
+    pub fn with_task_deadline(mut self, task_deadline: Duration) -> Self {
+        self.task_deadline = Some(task_deadline);
+        self
+    }
+
+    /// Change the execution budget of each task.
+    ///
+    /// The execution of a task (including its retries) is aborted once its budget is exceeded,
+    /// so that no single task can keep a worker busy for too long.
+    pub fn with_task_budget(mut self, task_budget: TaskBudget) -> Self {
+        self.task_budget = Some(task_budget);
+        self
+    }
+
+    /// Change the policy to retry the failed executions of workflows.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Change the maps of the validation scores, e.g. if the score semantics of the contract change.
+    pub fn with_score_maps(mut self, score_maps: ScoreMaps) -> Self {
+        self.score_maps = score_maps;
+        self
+    }
+
+    /// Change the workflow presets of protocols.
+    pub fn with_workflow_presets(mut self, workflow_presets: WorkflowPresets) -> Self {
+        self.workflow_presets = Arc::new(workflow_presets);
+        self
+    }
+
+    /// Enable the self-check of the generations before responding.
+    pub fn with_self_check(mut self, self_check: SelfCheck) -> Self {
+        self.self_check = Some(self_check);
+        self
+    }
+
+    /// Enable guardrails for generations.
+    pub fn with_guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+
+    /// Change the limits on the tools of the workflows that are given by the requesters.
+    pub fn with_tool_sandbox(mut self, tool_sandbox: ToolSandbox) -> Self {
+        self.tool_sandbox = tool_sandbox;
+        self
+    }
+
+    /// Use the mock model backend for the generations, instead of the actual models.
+    pub fn with_mock_model(mut self, mock_model: MockModel) -> Self {
+        self.mock_model = Some(mock_model);
+        self
+    }
+
+    /// Change the limits on the size & complexity of the inputs.
+    pub fn with_input_limits(mut self, input_limits: InputLimits) -> Self {
+        self.input_limits = input_limits;
+        self
+    }
+
+    /// Upload the response payloads to the storage when their estimated gas exceeds the given ceiling.
+    pub fn with_response_gas_ceiling(mut self, gas: u64) -> Self {
+        self.response_gas_ceiling = Some(gas);
+        self
+    }
+
+    /// Change how the chat histories are compressed & uploaded.
+    pub fn with_chat_history(mut self, chat_history: ChatHistoryPolicy) -> Self {
+        self.chat_history = chat_history;
+        self
+    }
+
+    /// Report the given operator tag within the node status, so that the node can be attributed to the operator.
+    pub fn with_operator_tag(mut self, tag: impl Into<String>) -> Self {
+        self.operator_tag = Some(tag.into());
+        self
+    }
+
+    /// Display the token & gas amounts in USD w.r.t the given price feed.
+    pub fn with_price_feed(mut self, price_feed: PriceFeed) -> Self {
+        self.price_feed = Some(price_feed);
+        self
+    }
+
+    /// Display the fee token amounts in USD w.r.t the given price feed, instead of pricing the fee token as ETH.
+    pub fn with_token_price_feed(mut self, token_price_feed: PriceFeed) -> Self {
+        self.token_price_feed = Some(token_price_feed);
+        self
+    }
+
+    /// Respond to the tasks with the given remote signer, instead of the wallet.
+    pub fn with_remote_signer(mut self, remote_signer: RemoteSigner) -> Self {
+        self.remote_signer = Some(remote_signer);
+        self
+    }
+
+    /// Warm up each model the first time that a task requires it, instead of before serving.
+    pub fn with_model_warmup(mut self, model_warmup: ModelWarmup) -> Self {
+        self.model_warmup = Some(Arc::new(model_warmup));
+        self
+    }
+
+    /// Pull the local models that are missing from Ollama when a task requires them.
+    pub fn with_ollama_auto_pull(mut self, ollama_auto_pull: OllamaAutoPull) -> Self {
+        self.ollama_auto_pull = Some(Arc::new(ollama_auto_pull));
+        self
+    }
+
+    /// Skip the local models that would not fit into the free VRAM when choosing a model for a task.
+    pub fn with_vram_probe(mut self, vram_probe: VramProbe) -> Self {
+        self.vram_probe = Some(Arc::new(vram_probe));
+        self
+    }
+
+    /// Cap the spending of paid providers.
+    pub fn with_api_budget(mut self, api_budget: ApiBudget) -> Self {
+        self.api_budget = Some(Arc::new(api_budget));
+        self
+    }
+
+    /// Cap the gas price to take new tasks at, and the gas spending within a day.
+    pub fn with_gas_budget(mut self, gas_budget: GasBudget) -> Self {
+        self.gas_budget = Some(Arc::new(gas_budget));
+        self
+    }
+
+    /// Take new tasks only within the given windows of the day.
+    pub fn with_serve_windows(mut self, windows: Vec<ServeWindow>) -> Self {
+        self.serve_gate = Arc::new(ServeGate::new(windows));
+        self
+    }
+
+    /// Change the ordering policy of the pending tasks.
+    pub fn with_queue_policy(mut self, queue_policy: QueuePolicy) -> Self {
+        self.queue_policy = Arc::new(RwLock::new(queue_policy));
+        self
+    }
+
+    /// Returns the current ordering policy of the pending tasks.
+    pub fn queue_policy(&self) -> QueuePolicy {
+        self.queue_policy.read().expect("lock poisoned").clone()
+    }
+
+    /// Replaces the ordering policy of the pending tasks, for all clones of this config.
+    pub fn set_queue_policy(&self, queue_policy: QueuePolicy) {
+        *self.queue_policy.write().expect("lock poisoned") = queue_policy;
+    }
+
+    /// Change the maximum number of tasks dispatched from the queue at the same time.
+    pub fn with_max_concurrent_tasks(mut self, max_concurrent_tasks: usize) -> Self {
+        self.max_concurrent_tasks = max_concurrent_tasks.max(1);
+        self
+    }
+
+    /// Change whether to ask for confirmation before the actions that move tokens.
+    pub fn with_spending_confirmation(mut self, confirm_spending: bool) -> Self {
+        self.confirm_spending = confirm_spending;
+        self
+    }
+
+    /// Change whether to serve the kinds that still match an incompatible coordinator implementation.
+    pub fn with_abi_mismatch_allowed(mut self, allow_abi_mismatch: bool) -> Self {
+        self.allow_abi_mismatch = allow_abi_mismatch;
+        self
+    }
+
+    /// Prune the local database with the given policy while serving.
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Acquire the given lock per task before handling it, so that replicas share the tasks.
+    pub fn with_task_lock(mut self, task_lock: TaskLock) -> Self {
+        self.task_lock = Some(task_lock);
+        self
+    }
+
+    /// Only handle the tasks of the given shard, along with the failed over tasks of others.
+    pub fn with_shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    /// Publish the observed events & our responses to the given bridge.
+    pub fn with_event_bridge(mut self, event_bridge: EventBridge) -> Self {
+        self.event_bridge = Some(event_bridge);
+        self
+    }
+
+    /// Limit the RPC requests to the given number of requests per second on average,
+    /// allowing bursts of `burst` requests at once.
+    pub fn with_rpc_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.rpc_rate_limit = Some((requests_per_second, burst));
+        self
+    }
+
+    /// Change the directory of the local database.
+    pub fn with_data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = data_dir.into();
+        self
+    }
+
     /// Change the RPC URL.
     pub fn with_rpc_url(mut self, rpc_url: Url) -> Self {
         self.rpc_url = rpc_url;
@@ -83,3 +564,37 @@ impl DriaOracleConfig {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_chain_of() {
+        let base = DriaOracleConfig::new(&B256::repeat_byte(1), "http://base".parse().unwrap())
+            .unwrap()
+            .with_operator_tag("operator")
+            .with_max_concurrent_tasks(8)
+            .with_gas_budget(GasBudget::new(Some(100), None));
+        let chain = DriaOracleConfig::new(&B256::repeat_byte(2), "http://chain".parse().unwrap())
+            .unwrap()
+            .with_coordinator_address(Address::repeat_byte(3))
+            .with_rpc_rate_limit(5.0, 5);
+
+        let config = base.clone().with_chain_of(chain.clone());
+        assert_eq!(config.rpc_url, chain.rpc_url);
+        assert_eq!(
+            config.wallet.default_signer().address(),
+            chain.wallet.default_signer().address()
+        );
+        assert_eq!(config.coordinator_address, Some(Address::repeat_byte(3)));
+        assert_eq!(config.rpc_rate_limit, Some((5.0, 5)));
+        assert_eq!(config.operator_tag.as_deref(), Some("operator"));
+        assert_eq!(config.max_concurrent_tasks, 8);
+
+        // the caps are inherited, but the spending is not shared with the base chain
+        let gas_budget = config.gas_budget.unwrap();
+        assert_eq!(gas_budget.max_gas_price, Some(100));
+        assert!(!Arc::ptr_eq(&gas_budget, base.gas_budget.as_ref().unwrap()));
+    }
+}
@@ -0,0 +1,68 @@
+use alloy::primitives::U256;
+use eyre::Result;
+
+use super::OracleDb;
+use super::ResponseScope;
+use crate::{TaskTimings, TraceId};
+
+pub(super) const COSTS_TABLE: &str = "costs";
+
+/// Resources spent & earned for a single task handled by this node.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TaskCost {
+    #[serde(flatten)]
+    pub scope: ResponseScope,
+    /// Task id.
+    pub task_id: U256,
+    /// Block number that the response was included in.
+    pub block_number: u64,
+    /// The role we handled the task as, i.e. `generator` or `validator`.
+    pub kind: String,
+    /// Protocol name of the task, e.g. `swan-agent-purchase`.
+    pub protocol: String,
+    /// Model used to handle the task.
+    pub model: String,
+    /// Estimated number of prompt tokens.
+    pub prompt_tokens: u64,
+    /// Estimated number of completion tokens.
+    pub completion_tokens: u64,
+    /// Estimated provider cost in USD.
+    pub provider_cost_usd: f64,
+    /// Gas fee paid for the response transaction, in wei.
+    pub gas_fee: U256,
+    /// The fee reserved for us within the task, in fee tokens.
+    pub reward: U256,
+    /// Time spent on each stage of handling the task, missing for the older records.
+    #[serde(default)]
+    pub timings: TaskTimings,
+    /// Notes on how the response was produced, e.g. the failed attempts of a generation.
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub notes: serde_json::Value,
+    /// Trace id of the attempt that made the response, missing for the older records.
+    #[serde(default)]
+    pub trace_id: Option<TraceId>,
+}
+
+impl OracleDb {
+    /// Records the cost of a task.
+    pub fn insert_cost(&self, cost: &TaskCost) -> Result<()> {
+        self.append(COSTS_TABLE, cost)
+    }
+
+    /// Returns the costs recorded within the scope in the given (inclusive) block range.
+    pub fn get_costs_in_range(
+        &self,
+        scope: ResponseScope,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<TaskCost>> {
+        let costs = self
+            .read_all::<TaskCost>(COSTS_TABLE)?
+            .into_iter()
+            .filter(|c| c.scope == scope)
+            .filter(|c| c.block_number >= from_block && c.block_number <= to_block)
+            .collect();
+
+        Ok(costs)
+    }
+}
@@ -0,0 +1,337 @@
+use alloy::primitives::{Address, Log, TxHash, U256};
+use alloy::sol_types::SolEventInterface;
+use dria_oracle_contracts::OracleCoordinator::OracleCoordinatorEvents;
+use dria_oracle_contracts::OracleRegistry::OracleRegistryEvents;
+use dria_oracle_contracts::{bytes32_to_string, OracleKind};
+use eyre::Result;
+
+use super::OracleDb;
+
+pub(super) const EVENTS_TABLE: &str = "events";
+
+/// An event of the coordinator or its registry, other than the status updates of the tasks
+/// which are indexed as tasks instead.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ContractEvent {
+    /// A task is requested.
+    Request {
+        task_id: U256,
+        requester: Address,
+        protocol: String,
+    },
+    /// A generation response is given to a task.
+    Response { task_id: U256, responder: Address },
+    /// A validation is given to a task.
+    Validation { task_id: U256, validator: Address },
+    /// An oracle has registered as a kind.
+    Registered { oracle: Address, kind: String },
+    /// An oracle has unregistered from a kind.
+    Unregistered { oracle: Address, kind: String },
+    /// An account is whitelisted to serve as a validator.
+    AddedToWhitelist { account: Address },
+    /// An account is removed from the whitelist.
+    RemovedFromWhitelist { account: Address },
+    /// The contract is upgraded to a new implementation.
+    Upgraded { implementation: Address },
+    /// The ownership of the contract is transferred, e.g. the parameters are now set by another account.
+    OwnershipTransferred {
+        previous_owner: Address,
+        new_owner: Address,
+    },
+    /// The contract is initialized, at the given version.
+    Initialized { version: u64 },
+}
+
+impl ContractEvent {
+    /// Returns `true` for the requests, responses & validations, which are indexed along with their tasks.
+    pub fn is_task_event(&self) -> bool {
+        matches!(
+            self,
+            Self::Request { .. } | Self::Response { .. } | Self::Validation { .. }
+        )
+    }
+
+    /// Decodes a log of the coordinator, or of the registry if it is emitted by the given registry.
+    ///
+    /// Returns `None` for the status updates and the logs that can not be decoded.
+    pub fn decode(log: &Log, registry: Address) -> Option<Self> {
+        if log.address == registry {
+            return Self::decode_registry(log);
+        }
+
+        let event = match OracleCoordinatorEvents::decode_log(log, true).ok()?.data {
+            OracleCoordinatorEvents::Request(e) => Self::Request {
+                task_id: e.taskId,
+                requester: e.requester,
+                protocol: bytes32_to_string(&e.protocol).unwrap_or_else(|_| e.protocol.to_string()),
+            },
+            OracleCoordinatorEvents::Response(e) => Self::Response {
+                task_id: e.taskId,
+                responder: e.responder,
+            },
+            OracleCoordinatorEvents::Validation(e) => Self::Validation {
+                task_id: e.taskId,
+                validator: e.validator,
+            },
+            OracleCoordinatorEvents::Upgraded(e) => Self::Upgraded {
+                implementation: e.implementation,
+            },
+            OracleCoordinatorEvents::OwnershipTransferred(e) => Self::OwnershipTransferred {
+                previous_owner: e.previousOwner,
+                new_owner: e.newOwner,
+            },
+            OracleCoordinatorEvents::Initialized(e) => Self::Initialized { version: e.version },
+            OracleCoordinatorEvents::StatusUpdate(_) => return None,
+        };
+
+        Some(event)
+    }
+
+    /// Decodes a log of the registry.
+    fn decode_registry(log: &Log) -> Option<Self> {
+        let kind = |kind: u8| {
+            OracleKind::try_from(kind)
+                .map(|kind| kind.to_string())
+                .unwrap_or_else(|_| kind.to_string())
+        };
+
+        let event = match OracleRegistryEvents::decode_log(log, true).ok()?.data {
+            OracleRegistryEvents::Registered(e) => Self::Registered {
+                oracle: e._0,
+                kind: kind(e.kind),
+            },
+            OracleRegistryEvents::Unregistered(e) => Self::Unregistered {
+                oracle: e._0,
+                kind: kind(e.kind),
+            },
+            OracleRegistryEvents::AddedToWhitelist(e) => {
+                Self::AddedToWhitelist { account: e.account }
+            }
+            OracleRegistryEvents::RemovedFromWhitelist(e) => {
+                Self::RemovedFromWhitelist { account: e.account }
+            }
+            OracleRegistryEvents::Upgraded(e) => Self::Upgraded {
+                implementation: e.implementation,
+            },
+            OracleRegistryEvents::OwnershipTransferred(e) => Self::OwnershipTransferred {
+                previous_owner: e.previousOwner,
+                new_owner: e.newOwner,
+            },
+            OracleRegistryEvents::Initialized(e) => Self::Initialized { version: e.version },
+        };
+
+        Some(event)
+    }
+}
+
+impl std::fmt::Display for ContractEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request {
+                task_id,
+                requester,
+                protocol,
+            } => write!(
+                f,
+                "Task {} is requested by {} ({})",
+                task_id, requester, protocol
+            ),
+            Self::Response { task_id, responder } => {
+                write!(f, "Task {} is responded by {}", task_id, responder)
+            }
+            Self::Validation { task_id, validator } => {
+                write!(f, "Task {} is validated by {}", task_id, validator)
+            }
+            Self::Registered { oracle, kind } => write!(f, "{} registered as {}", oracle, kind),
+            Self::Unregistered { oracle, kind } => {
+                write!(f, "{} unregistered from {}", oracle, kind)
+            }
+            Self::AddedToWhitelist { account } => write!(f, "{} is whitelisted", account),
+            Self::RemovedFromWhitelist { account } => {
+                write!(f, "{} is removed from the whitelist", account)
+            }
+            Self::Upgraded { implementation } => write!(f, "Upgraded to {}", implementation),
+            Self::OwnershipTransferred {
+                previous_owner,
+                new_owner,
+            } => write!(
+                f,
+                "Ownership is transferred from {} to {}",
+                previous_owner, new_owner
+            ),
+            Self::Initialized { version } => write!(f, "Initialized at version {}", version),
+        }
+    }
+}
+
+/// An event of the coordinator or its registry, along with where it is emitted.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct IndexedEvent {
+    /// Address of the coordinator that the event belongs to.
+    pub coordinator: Address,
+    /// Address of the contract that has emitted the event, i.e. the coordinator or the registry.
+    pub contract: Address,
+    pub block_number: u64,
+    pub log_index: u64,
+    pub tx_hash: TxHash,
+    #[serde(flatten)]
+    pub event: ContractEvent,
+}
+
+impl OracleDb {
+    /// Appends the given events to the index, ignoring the ones that are already indexed for their coordinator,
+    /// returns the number of appended events.
+    ///
+    /// The task events are ignored as well, as they are indexed along with their tasks, see [`ContractEvent::is_task_event`].
+    pub fn upsert_indexed_events(&self, events: Vec<IndexedEvent>) -> Result<usize> {
+        let events = events
+            .into_iter()
+            .filter(|e| !e.event.is_task_event())
+            .collect();
+
+        self.append_new(EVENTS_TABLE, events, |e: &IndexedEvent| {
+            (e.coordinator, e.tx_hash, e.log_index)
+        })
+    }
+
+    /// Returns the indexed events of the given coordinator & its registry, in the order they are emitted.
+    pub fn get_indexed_events(&self, coordinator: Address) -> Result<Vec<IndexedEvent>> {
+        let mut events = self
+            .read_all::<IndexedEvent>(EVENTS_TABLE)?
+            .into_iter()
+            .filter(|e| e.coordinator == coordinator)
+            .collect::<Vec<_>>();
+        // the events of an older range can be appended later, e.g. by a backfill
+        events.sort_by_key(|e| (e.block_number, e.log_index));
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+    use alloy::sol_types::SolEvent;
+    use dria_oracle_contracts::{string_to_bytes32, OracleCoordinator, OracleRegistry};
+
+    #[test]
+    fn test_decode_contract_events() {
+        let (coordinator, registry) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let oracle = Address::repeat_byte(3);
+
+        let registered = OracleRegistry::Registered {
+            _0: oracle,
+            kind: u8::from(OracleKind::Validator),
+        };
+        let log = Log {
+            address: registry,
+            data: registered.encode_log_data(),
+        };
+        let event = ContractEvent::decode(&log, registry).unwrap();
+        assert_eq!(
+            event,
+            ContractEvent::Registered {
+                oracle,
+                kind: "Validator".to_string()
+            }
+        );
+        assert_eq!(
+            event.to_string(),
+            format!("{} registered as Validator", oracle)
+        );
+
+        let request = OracleCoordinator::Request {
+            taskId: U256::from(1),
+            requester: oracle,
+            protocol: string_to_bytes32("swan/0.1".to_string()).unwrap(),
+        };
+        let log = Log {
+            address: coordinator,
+            data: request.encode_log_data(),
+        };
+        assert_eq!(
+            ContractEvent::decode(&log, registry),
+            Some(ContractEvent::Request {
+                task_id: U256::from(1),
+                requester: oracle,
+                protocol: "swan/0.1".to_string()
+            })
+        );
+
+        // status updates are indexed as tasks
+        let status = OracleCoordinator::StatusUpdate {
+            taskId: U256::from(1),
+            protocol: Default::default(),
+            statusBefore: 0,
+            statusAfter: 1,
+        };
+        let log = Log {
+            address: coordinator,
+            data: status.encode_log_data(),
+        };
+        assert_eq!(ContractEvent::decode(&log, registry), None);
+    }
+
+    #[test]
+    fn test_indexed_events() {
+        let dir = TestDir::new("events");
+        let db = OracleDb::new(dir.path());
+        let coordinator = Address::repeat_byte(1);
+
+        let event = |block_number: u64, log_index: u64| IndexedEvent {
+            coordinator,
+            contract: coordinator,
+            block_number,
+            log_index,
+            tx_hash: TxHash::repeat_byte(block_number as u8),
+            event: ContractEvent::Initialized { version: 1 },
+        };
+        assert_eq!(
+            db.upsert_indexed_events(vec![event(2, 0), event(1, 1)])
+                .unwrap(),
+            2
+        );
+        // indexing the same blocks again does not duplicate the events
+        assert_eq!(
+            db.upsert_indexed_events(vec![event(1, 1), event(1, 0)])
+                .unwrap(),
+            1
+        );
+        // the same log of another coordinator is indexed on its own
+        let other = IndexedEvent {
+            coordinator: Address::repeat_byte(2),
+            ..event(1, 1)
+        };
+        assert_eq!(db.upsert_indexed_events(vec![other]).unwrap(), 1);
+        // the task events are indexed along with their tasks instead
+        let request = IndexedEvent {
+            event: ContractEvent::Response {
+                task_id: U256::from(1),
+                responder: coordinator,
+            },
+            ..event(3, 0)
+        };
+        assert_eq!(db.upsert_indexed_events(vec![request]).unwrap(), 0);
+
+        let events = db.get_indexed_events(coordinator).unwrap();
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (e.block_number, e.log_index))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (1, 1), (2, 0)]
+        );
+        assert_eq!(
+            db.get_indexed_events(Address::repeat_byte(2))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(db
+            .get_indexed_events(Address::repeat_byte(9))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use alloy::primitives::{TxHash, U256};
+use eyre::Result;
+
+use super::OracleDb;
+
+pub(super) const GAS_TABLE: &str = "gas";
+
+/// Gas fee paid for a transaction of this node, so that the daily gas budget is not reset by a restart.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GasSpend {
+    /// Chain id of the transaction, as each chain has its own budget.
+    pub chain_id: u64,
+    /// Transaction hash.
+    pub tx_hash: TxHash,
+    /// Gas fee paid for the transaction, in wei.
+    pub gas_fee: U256,
+    /// Time that the row is written at in seconds since the epoch, stamped by the database.
+    #[serde(default, skip_serializing)]
+    pub created_at: u64,
+}
+
+impl OracleDb {
+    /// Records the gas fee of a transaction.
+    pub fn insert_gas_spend(&self, spend: &GasSpend) -> Result<()> {
+        self.append(GAS_TABLE, spend)
+    }
+
+    /// Returns the gas fees paid on the given chain since the given time, in seconds since the epoch.
+    pub fn get_gas_spending(&self, chain_id: u64, since: u64) -> Result<Vec<GasSpend>> {
+        let spending = self
+            .read_all::<GasSpend>(GAS_TABLE)?
+            .into_iter()
+            .filter(|spend| spend.chain_id == chain_id && spend.created_at >= since)
+            .collect();
+
+        Ok(spending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    #[test]
+    fn test_gas_spending() {
+        let dir = TestDir::new("gas");
+        let db = OracleDb::new(dir.path());
+
+        for (chain_id, byte) in [(1, 1), (1, 2), (8453, 3)] {
+            db.insert_gas_spend(&GasSpend {
+                chain_id,
+                tx_hash: TxHash::repeat_byte(byte),
+                gas_fee: U256::from(byte),
+                created_at: 0,
+            })
+            .unwrap();
+        }
+
+        let spending = db.get_gas_spending(1, 0).unwrap();
+        assert_eq!(spending.len(), 2);
+        assert!(spending.iter().all(|spend| spend.created_at > 0));
+        assert_eq!(spending[1].gas_fee, U256::from(2));
+        assert!(db.get_gas_spending(1, u64::MAX).unwrap().is_empty());
+    }
+}
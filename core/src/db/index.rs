@@ -0,0 +1,434 @@
+use alloy::primitives::{Address, U256};
+use dria_oracle_client::TaskView;
+use eyre::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use super::{OracleDb, PrunedTable};
+
+const INDEX_TABLE: &str = "index";
+
+/// Schema of the index, where each task is stored as a whole along with the columns that it is
+/// queried by, and its id is zero-padded hex so that the tasks are ordered by their ids.
+const INDEX_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS tasks (
+    coordinator TEXT NOT NULL,
+    task_id TEXT NOT NULL,
+    block_number INTEGER NOT NULL,
+    requester TEXT NOT NULL,
+    protocol TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    task TEXT NOT NULL,
+    PRIMARY KEY (coordinator, task_id)
+);
+CREATE INDEX IF NOT EXISTS tasks_by_block ON tasks (coordinator, block_number);
+CREATE TABLE IF NOT EXISTS checkpoints (
+    coordinator TEXT PRIMARY KEY,
+    block_number INTEGER NOT NULL
+);
+";
+
+/// A response to an indexed task, with its contents downloaded from the storage.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IndexedResponse {
+    pub responder: Address,
+    pub score: U256,
+    pub output: String,
+    pub metadata: String,
+}
+
+/// A validation of an indexed task, with its contents downloaded from the storage.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IndexedValidation {
+    pub validator: Address,
+    pub scores: Vec<U256>,
+    pub metadata: String,
+}
+
+/// A task along with its responses & validations as of its latest event, see the `index` command.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IndexedTask {
+    /// Address of the coordinator that the task belongs to.
+    pub coordinator: Address,
+    /// Task id.
+    pub task_id: U256,
+    /// Block number of the latest event of the task.
+    pub block_number: u64,
+    pub requester: Address,
+    pub protocol: String,
+    pub status: u8,
+    pub input: String,
+    pub models: Vec<String>,
+    pub responses: Vec<IndexedResponse>,
+    pub validations: Vec<IndexedValidation>,
+}
+
+impl IndexedTask {
+    /// Creates an indexed task from a task view, where the storage contents are expected to be resolved.
+    pub fn from_view(coordinator: Address, block_number: u64, task: &TaskView) -> Self {
+        Self {
+            coordinator,
+            task_id: task.task_id,
+            block_number,
+            requester: task.request.requester,
+            protocol: task.request.protocol.clone(),
+            status: task.request.status.into(),
+            input: task.request.input.content().to_string(),
+            models: task.request.models.clone(),
+            responses: task
+                .responses
+                .iter()
+                .map(|r| IndexedResponse {
+                    responder: r.responder,
+                    score: r.score,
+                    output: r.output.content().to_string(),
+                    metadata: r.metadata.content().to_string(),
+                })
+                .collect(),
+            validations: task
+                .validations
+                .iter()
+                .map(|v| IndexedValidation {
+                    validator: v.validator,
+                    scores: v.scores.clone(),
+                    metadata: v.metadata.content().to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the names of the on-chain fields that differ between the two tasks, e.g. a status
+    /// update that was missed. The contents are not compared, as they may be downloaded from the storage.
+    pub fn divergence(&self, other: &IndexedTask) -> Vec<&'static str> {
+        let responses = |task: &IndexedTask| {
+            task.responses
+                .iter()
+                .map(|r| (r.responder, r.score))
+                .collect::<Vec<_>>()
+        };
+        let validations = |task: &IndexedTask| {
+            task.validations
+                .iter()
+                .map(|v| (v.validator, v.scores.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        [
+            ("requester", self.requester == other.requester),
+            ("protocol", self.protocol == other.protocol),
+            ("status", self.status == other.status),
+            ("responses", responses(self) == responses(other)),
+            ("validations", validations(self) == validations(other)),
+        ]
+        .into_iter()
+        .filter(|(_, matches)| !matches)
+        .map(|(field, _)| field)
+        .collect()
+    }
+}
+
+/// Filters of the indexed tasks when they are queried, where `None` matches all.
+#[derive(Debug, Clone, Default)]
+pub struct IndexFilter {
+    pub protocol: Option<String>,
+    pub requester: Option<Address>,
+    pub status: Option<u8>,
+}
+
+/// The sqlite database of the indexed tasks & the blocks that they are indexed up to,
+/// which is opened on its first use.
+#[derive(Debug)]
+pub(super) struct IndexStore {
+    path: PathBuf,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl IndexStore {
+    pub(super) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            connection: Mutex::default(),
+        }
+    }
+
+    /// Runs `f` with the connection, opening it & creating the tables first if needed.
+    fn with<T>(&self, f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let mut connection = self.connection.lock().expect("index lock poisoned");
+        if connection.is_none() {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)
+                    .wrap_err_with(|| format!("could not create data dir {}", dir.display()))?;
+            }
+            let opened = Connection::open(&self.path)
+                .wrap_err_with(|| format!("could not open index {}", self.path.display()))?;
+            opened
+                .execute_batch(INDEX_SCHEMA)
+                .wrap_err("could not create the index tables")?;
+            *connection = Some(opened);
+        }
+
+        f(connection.as_mut().expect("connection is opened")).wrap_err("index query failed")
+    }
+
+    /// Returns the size of the index file in bytes, zero if it does not exist.
+    fn size(&self) -> u64 {
+        std::fs::metadata(&self.path).map_or(0, |m| m.len())
+    }
+}
+
+/// Returns the key of an address within the index.
+#[inline]
+fn address_key(address: &Address) -> String {
+    address.to_string().to_lowercase()
+}
+
+/// Returns the key of a task id within the index, which is ordered the same as the ids.
+#[inline]
+fn task_id_key(task_id: &U256) -> String {
+    format!("{:064x}", task_id)
+}
+
+impl OracleDb {
+    /// Adds the given tasks to the index, replacing the existing ones of the same tasks.
+    pub fn upsert_indexed_tasks(&self, tasks: Vec<IndexedTask>) -> Result<()> {
+        let rows = tasks
+            .iter()
+            .map(|task| Ok((task, serde_json::to_string(task)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.index.with(|connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(
+                    "INSERT INTO tasks (coordinator, task_id, block_number, requester, protocol, status, task)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT (coordinator, task_id) DO UPDATE SET
+                        block_number = excluded.block_number,
+                        requester = excluded.requester,
+                        protocol = excluded.protocol,
+                        status = excluded.status,
+                        task = excluded.task",
+                )?;
+                for (task, json) in &rows {
+                    statement.execute(params![
+                        address_key(&task.coordinator),
+                        task_id_key(&task.task_id),
+                        task.block_number,
+                        address_key(&task.requester),
+                        task.protocol,
+                        task.status,
+                        json
+                    ])?;
+                }
+            }
+            transaction.commit()
+        })
+    }
+
+    /// Returns the indexed tasks of the given coordinator that match the filter, ordered by task id.
+    pub fn get_indexed_tasks(
+        &self,
+        coordinator: Address,
+        filter: &IndexFilter,
+    ) -> Result<Vec<IndexedTask>> {
+        let rows = self.index.with(|connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT task FROM tasks
+                 WHERE coordinator = ?1
+                    AND (?2 IS NULL OR protocol = ?2)
+                    AND (?3 IS NULL OR requester = ?3)
+                    AND (?4 IS NULL OR status = ?4)
+                 ORDER BY task_id",
+            )?;
+            let rows = statement
+                .query_map(
+                    params![
+                        address_key(&coordinator),
+                        filter.protocol,
+                        filter.requester.as_ref().map(address_key),
+                        filter.status
+                    ],
+                    |row| row.get::<_, String>(0),
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })?;
+
+        rows.iter()
+            .map(|row| serde_json::from_str(row).wrap_err("could not parse indexed task"))
+            .collect()
+    }
+
+    /// Returns the last block that the tasks of the given coordinator are indexed up to, if any.
+    pub fn get_index_checkpoint(&self, coordinator: Address) -> Result<Option<u64>> {
+        self.index.with(|connection| {
+            connection
+                .query_row(
+                    "SELECT block_number FROM checkpoints WHERE coordinator = ?1",
+                    params![address_key(&coordinator)],
+                    |row| row.get(0),
+                )
+                .optional()
+        })
+    }
+
+    /// Records the last block that the tasks of the given coordinator are indexed up to.
+    pub fn set_index_checkpoint(&self, coordinator: Address, block_number: u64) -> Result<()> {
+        self.index.with(|connection| {
+            connection.execute(
+                "INSERT INTO checkpoints (coordinator, block_number) VALUES (?1, ?2)
+                 ON CONFLICT (coordinator) DO UPDATE SET block_number = excluded.block_number",
+                params![address_key(&coordinator), block_number],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Prunes the indexed tasks of the given coordinator whose latest event is before the given block,
+    /// and then the tasks with the oldest events until their contents fit within `max_size` bytes.
+    pub(super) fn prune_index(
+        &self,
+        oldest_block: Option<(Address, u64)>,
+        max_size: Option<u64>,
+    ) -> Result<PrunedTable> {
+        // nothing to prune if there is no index at all, and it is not created for nothing
+        if !self.index.path.try_exists()? {
+            return Ok(PrunedTable {
+                table: INDEX_TABLE,
+                removed: 0,
+                size: 0,
+            });
+        }
+
+        let removed = self.index.with(|connection| {
+            let transaction = connection.transaction()?;
+            let mut removed = 0;
+            if let Some((coordinator, block_number)) = oldest_block {
+                removed += transaction.execute(
+                    "DELETE FROM tasks WHERE coordinator = ?1 AND block_number < ?2",
+                    params![address_key(&coordinator), block_number],
+                )?;
+            }
+            if let Some(max_size) = max_size {
+                let size: u64 = transaction.query_row(
+                    "SELECT COALESCE(SUM(LENGTH(task)), 0) FROM tasks",
+                    [],
+                    |row| row.get(0),
+                )?;
+                if size > max_size {
+                    // the newest tasks are kept while they fit, the rest are pruned
+                    removed += transaction.execute(
+                        "DELETE FROM tasks WHERE rowid IN (
+                            SELECT rowid FROM (
+                                SELECT rowid, SUM(LENGTH(task)) OVER (
+                                    ORDER BY block_number DESC, rowid DESC
+                                ) AS kept FROM tasks
+                            ) WHERE kept > ?1
+                        )",
+                        params![max_size],
+                    )?;
+                }
+            }
+            transaction.commit()?;
+            Ok(removed)
+        })?;
+
+        Ok(PrunedTable {
+            table: INDEX_TABLE,
+            removed,
+            size: self.index.size(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    #[test]
+    fn test_index() {
+        let dir = TestDir::new("index");
+        let db = OracleDb::new(dir.path());
+        let coordinator = Address::repeat_byte(1);
+
+        let task = |task_id: u64, protocol: &str, status: u8| IndexedTask {
+            coordinator,
+            task_id: U256::from(task_id),
+            block_number: task_id,
+            requester: Address::repeat_byte(2),
+            protocol: protocol.to_string(),
+            status,
+            input: "hello".to_string(),
+            models: vec!["gpt-4o".to_string()],
+            responses: Vec::new(),
+            validations: Vec::new(),
+        };
+
+        db.upsert_indexed_tasks(vec![
+            task(2, "swan", 1),
+            task(1, "dria", 1),
+            task(256, "dria", 1),
+        ])
+        .unwrap();
+        db.upsert_indexed_tasks(vec![task(2, "swan", 3)]).unwrap();
+        let tasks = db
+            .get_indexed_tasks(coordinator, &IndexFilter::default())
+            .unwrap();
+        assert_eq!(tasks.len(), 3);
+        assert_eq!((tasks[1].task_id, tasks[1].status), (U256::from(2), 3));
+        assert_eq!(tasks[2].task_id, U256::from(256));
+
+        // all tasks are stored, and filtered when they are queried
+        let filter = IndexFilter {
+            protocol: Some("dria".to_string()),
+            requester: Some(Address::repeat_byte(2)),
+            ..Default::default()
+        };
+        assert_eq!(db.get_indexed_tasks(coordinator, &filter).unwrap().len(), 2);
+
+        let filter = IndexFilter {
+            protocol: Some("swan".to_string()),
+            status: Some(1),
+            ..Default::default()
+        };
+        assert!(db
+            .get_indexed_tasks(coordinator, &filter)
+            .unwrap()
+            .is_empty());
+        assert!(db
+            .get_indexed_tasks(Address::ZERO, &IndexFilter::default())
+            .unwrap()
+            .is_empty());
+
+        // contents are not compared, only the on-chain fields
+        let mut diverged = task(1, "dria", 3);
+        diverged.input = "downloaded".to_string();
+        assert!(task(1, "dria", 3).divergence(&diverged).is_empty());
+        diverged.validations.push(IndexedValidation {
+            validator: Address::repeat_byte(3),
+            scores: vec![U256::from(5)],
+            metadata: String::new(),
+        });
+        assert_eq!(
+            task(1, "dria", 1).divergence(&diverged),
+            vec!["status", "validations"]
+        );
+
+        assert_eq!(db.get_index_checkpoint(coordinator).unwrap(), None);
+        db.set_index_checkpoint(coordinator, 10).unwrap();
+        db.set_index_checkpoint(coordinator, 20).unwrap();
+        assert_eq!(db.get_index_checkpoint(coordinator).unwrap(), Some(20));
+
+        // the tasks are pruned by the block of their latest event
+        let pruned = db.prune_index(Some((coordinator, 2)), None).unwrap();
+        assert_eq!(pruned.removed, 1);
+        assert_eq!(
+            db.get_indexed_tasks(coordinator, &IndexFilter::default())
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
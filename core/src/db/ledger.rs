@@ -0,0 +1,170 @@
+use alloy::primitives::{Address, TxHash, U256};
+use eyre::Result;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::OracleDb;
+
+pub(super) const LEDGER_TABLE: &str = "ledger";
+
+/// Identifies who a record belongs to, so that the nodes sharing a database (e.g. for multiple
+/// chains, coordinators or accounts) only see their own records.
+///
+/// The fields are missing in the older records, which are then not matched by any node.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct ResponseScope {
+    /// Chain id of the coordinator.
+    #[serde(default)]
+    pub chain_id: u64,
+    /// Address of the coordinator.
+    #[serde(default)]
+    pub coordinator: Address,
+    /// Address that the response is made with.
+    #[serde(default)]
+    pub responder: Address,
+}
+
+/// A response transaction sent by this node, recorded before waiting for its receipt.
+///
+/// This allows a restarted node to see that it has already submitted a response
+/// for a task, even if the transaction is still pending.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ResponseRecord {
+    #[serde(flatten)]
+    pub scope: ResponseScope,
+    /// Task id.
+    pub task_id: U256,
+    /// The role we responded as, i.e. `generator` or `validator`.
+    pub kind: String,
+    /// Hash of the response transaction.
+    pub tx_hash: TxHash,
+}
+
+/// Key of the latest response transaction in the ledger.
+type LedgerKey = (ResponseScope, U256, String);
+
+/// The latest response transaction of each key, read from the table once and kept
+/// up to date with the inserted records, so that the table is not read for each task.
+#[derive(Debug, Default)]
+pub(super) struct LedgerIndex(Mutex<Option<HashMap<LedgerKey, TxHash>>>);
+
+impl OracleDb {
+    /// Records a sent response transaction.
+    pub fn insert_response_record(&self, record: &ResponseRecord) -> Result<()> {
+        self.append(LEDGER_TABLE, record)?;
+
+        let mut index = self.ledger.0.lock().expect("ledger lock poisoned");
+        if let Some(index) = index.as_mut() {
+            index.insert(
+                (record.scope, record.task_id, record.kind.clone()),
+                record.tx_hash,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the latest response transaction sent within the scope for the given task & kind, if any.
+    pub fn get_response_record(
+        &self,
+        scope: ResponseScope,
+        task_id: U256,
+        kind: &str,
+    ) -> Result<Option<ResponseRecord>> {
+        let mut index = self.ledger.0.lock().expect("ledger lock poisoned");
+        if index.is_none() {
+            let records = self.read_all::<ResponseRecord>(LEDGER_TABLE)?;
+            *index = Some(
+                records
+                    .into_iter()
+                    .map(|r| ((r.scope, r.task_id, r.kind), r.tx_hash))
+                    .collect(),
+            );
+        }
+
+        let record = index
+            .as_ref()
+            .and_then(|index| index.get(&(scope, task_id, kind.to_string())))
+            .map(|tx_hash| ResponseRecord {
+                scope,
+                task_id,
+                kind: kind.to_string(),
+                tx_hash: *tx_hash,
+            });
+
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    #[test]
+    fn test_response_records() {
+        let dir = TestDir::new("ledger");
+        let db = OracleDb::new(dir.path());
+
+        let scope = ResponseScope {
+            chain_id: 1,
+            coordinator: Address::repeat_byte(1),
+            responder: Address::repeat_byte(2),
+        };
+        let task_id = U256::from(7);
+        assert!(db
+            .get_response_record(scope, task_id, "generator")
+            .unwrap()
+            .is_none());
+
+        for tx_hash in [TxHash::repeat_byte(1), TxHash::repeat_byte(2)] {
+            db.insert_response_record(&ResponseRecord {
+                scope,
+                task_id,
+                kind: "generator".to_string(),
+                tx_hash,
+            })
+            .unwrap();
+        }
+
+        let record = db
+            .get_response_record(scope, task_id, "generator")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.tx_hash, TxHash::repeat_byte(2));
+        assert!(db
+            .get_response_record(scope, task_id, "validator")
+            .unwrap()
+            .is_none());
+
+        // another responder, coordinator or chain sharing the database has not responded
+        for other in [
+            ResponseScope {
+                responder: Address::repeat_byte(3),
+                ..scope
+            },
+            ResponseScope {
+                coordinator: Address::repeat_byte(3),
+                ..scope
+            },
+            ResponseScope {
+                chain_id: 2,
+                ..scope
+            },
+        ] {
+            assert!(db
+                .get_response_record(other, task_id, "generator")
+                .unwrap()
+                .is_none());
+        }
+
+        // a new handle reads the records from the table
+        let record = OracleDb::new(dir.path())
+            .get_response_record(scope, task_id, "generator")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.tx_hash, TxHash::repeat_byte(2));
+    }
+}
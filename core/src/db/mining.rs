@@ -0,0 +1,33 @@
+use alloy::primitives::U256;
+use eyre::Result;
+
+use super::OracleDb;
+
+pub(super) const MINING_TABLE: &str = "mining";
+
+/// A nonce mined for a task, to keep track of the mining performance of this machine.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MiningRecord {
+    /// Task id.
+    pub task_id: U256,
+    /// The role we mined as, i.e. `generator` or `validator`.
+    pub kind: String,
+    /// Difficulty of the task.
+    pub difficulty: u8,
+    /// Number of hashes computed to find the nonce.
+    pub hashes: u64,
+    /// Time spent to find the nonce, in milliseconds.
+    pub elapsed_ms: u64,
+}
+
+impl OracleDb {
+    /// Records a mined nonce.
+    pub fn insert_mining_record(&self, record: &MiningRecord) -> Result<()> {
+        self.append(MINING_TABLE, record)
+    }
+
+    /// Returns all recorded mined nonces.
+    pub fn get_mining_records(&self) -> Result<Vec<MiningRecord>> {
+        self.read_all(MINING_TABLE)
+    }
+}
@@ -0,0 +1,259 @@
+//! A small local database for the oracle node.
+//!
+//! Each table is stored as a JSON-lines file under the configured data directory,
+//! where every line is a single serialized row. This keeps the database human-readable
+//! and avoids any external database dependency. The only exception is the index of the
+//! `index` command, which is queried by its fields and is kept in sqlite instead.
+//!
+//! Appended rows are stamped with the time they are written at under `created_at`, so that
+//! they can be pruned by age, see [`RetentionPolicy`].
+
+use eyre::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod costs;
+pub use costs::TaskCost;
+
+mod events;
+pub use events::{ContractEvent, IndexedEvent};
+
+mod index;
+use index::IndexStore;
+pub use index::{IndexFilter, IndexedResponse, IndexedTask, IndexedValidation};
+
+mod gas;
+pub use gas::GasSpend;
+
+mod ledger;
+use ledger::LedgerIndex;
+pub use ledger::{ResponseRecord, ResponseScope};
+
+mod mining;
+pub use mining::MiningRecord;
+
+mod outbox;
+pub use outbox::{OutboxEntry, OutboxPayload};
+
+mod peers;
+pub use peers::{PeerReputation, PeerResponse};
+
+mod retention;
+pub use retention::{PrunedTable, RetentionPolicy};
+
+mod uploads;
+pub use uploads::UploadRecord;
+
+mod wal;
+pub use wal::{WalDecision, WalEntry};
+
+/// Field that the appended rows are stamped with, in seconds since the epoch.
+const CREATED_AT: &str = "created_at";
+
+/// Stamps the row with the current time under `created_at`, unless it is already stamped.
+pub(crate) fn stamp_created_at(mut row: serde_json::Value) -> serde_json::Value {
+    if let Some(fields) = row.as_object_mut() {
+        fields.entry(CREATED_AT).or_insert_with(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .into()
+        });
+    }
+
+    row
+}
+
+/// Local database of the oracle node.
+#[derive(Debug, Clone)]
+pub struct OracleDb {
+    /// Directory that the table files are stored in.
+    dir: PathBuf,
+    /// A lock to prevent concurrent writes to the same file.
+    lock: Arc<Mutex<()>>,
+    /// Index of the response ledger, loaded on its first use.
+    ledger: Arc<LedgerIndex>,
+    /// Index of the tasks, opened on its first use.
+    index: Arc<IndexStore>,
+}
+
+impl OracleDb {
+    /// Creates a database at the given directory.
+    ///
+    /// The directory is not created until the first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            index: Arc::new(IndexStore::new(dir.join("index.sqlite"))),
+            dir,
+            lock: Arc::new(Mutex::new(())),
+            ledger: Arc::default(),
+        }
+    }
+
+    /// Runs the given write on a blocking thread, so that the disk (e.g. the flushes of the write-ahead log)
+    /// does not stall the async workers that the tasks are handled on.
+    pub async fn write_blocking(
+        &self,
+        write: impl FnOnce(&OracleDb) -> Result<()> + Send + 'static,
+    ) -> Result<()> {
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || write(&db)).await?
+    }
+
+    /// Returns the path of the file for the given table.
+    #[inline]
+    fn table_path(&self, table: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", table))
+    }
+
+    /// Appends a row to the given table.
+    pub(crate) fn append<T: Serialize>(&self, table: &str, row: &T) -> Result<()> {
+        self.append_row(table, row, false)
+    }
+
+    /// Appends a row to the given table, and flushes it to the disk before returning so that
+    /// the row is not lost if the machine crashes right after.
+    pub(crate) fn append_synced<T: Serialize>(&self, table: &str, row: &T) -> Result<()> {
+        self.append_row(table, row, true)
+    }
+
+    /// Appends the rows that are not within the given table yet w.r.t their `key`,
+    /// returns the number of appended rows.
+    ///
+    /// Unlike [`Self::update`], the existing rows are only read and are not written back.
+    pub(crate) fn append_new<T: Serialize + DeserializeOwned, K: Ord>(
+        &self,
+        table: &str,
+        rows: Vec<T>,
+        key: impl Fn(&T) -> K,
+    ) -> Result<usize> {
+        let _guard = self.lock.lock().expect("db lock poisoned");
+
+        let mut keys = self
+            .read_rows::<T>(table)?
+            .iter()
+            .map(&key)
+            .collect::<BTreeSet<_>>();
+        let rows = rows
+            .into_iter()
+            .filter(|row| keys.insert(key(row)))
+            .collect::<Vec<_>>();
+        if !rows.is_empty() {
+            self.write_rows(table, &rows, false)?;
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Appends a row to the given table, flushing it to the disk if `sync` is set.
+    fn append_row<T: Serialize>(&self, table: &str, row: &T, sync: bool) -> Result<()> {
+        let _guard = self.lock.lock().expect("db lock poisoned");
+        self.write_rows(table, std::slice::from_ref(row), sync)
+    }
+
+    /// Appends the rows to the given table without locking, flushing them to the disk if `sync` is set.
+    fn write_rows<T: Serialize>(&self, table: &str, rows: &[T], sync: bool) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .wrap_err_with(|| format!("could not create data dir {}", self.dir.display()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.table_path(table))
+            .wrap_err_with(|| format!("could not open table {}", table))?;
+
+        let mut lines = String::new();
+        for row in rows {
+            let row =
+                stamp_created_at(serde_json::to_value(row).wrap_err("could not serialize row")?);
+            lines.push_str(&row.to_string());
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())
+            .wrap_err_with(|| format!("could not write to table {}", table))?;
+        if sync {
+            file.sync_data()
+                .wrap_err_with(|| format!("could not flush table {}", table))?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads all rows of the given table, returns an empty vector if the table does not exist.
+    ///
+    /// Rows that can not be parsed are skipped with a warning.
+    pub(crate) fn read_all<T: DeserializeOwned>(&self, table: &str) -> Result<Vec<T>> {
+        let _guard = self.lock.lock().expect("db lock poisoned");
+        self.read_rows(table)
+    }
+
+    /// Reads all rows of the given table, lets `f` change them, and writes them back.
+    pub(crate) fn update<T: Serialize + DeserializeOwned>(
+        &self,
+        table: &str,
+        f: impl FnOnce(&mut Vec<T>),
+    ) -> Result<()> {
+        let _guard = self.lock.lock().expect("db lock poisoned");
+
+        let mut rows = self.read_rows(table)?;
+        f(&mut rows);
+
+        let lines = rows
+            .iter()
+            .map(|row| serde_json::to_string(row).wrap_err("could not serialize row"))
+            .collect::<Result<Vec<_>>>()?;
+        self.write_lines(table, &lines)
+    }
+
+    /// Replaces the rows of the given table with the given lines, without locking.
+    ///
+    /// The table is written to a temporary file first, so that it is not lost if writing fails midway.
+    fn write_lines(&self, table: &str, lines: &[impl AsRef<str>]) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .wrap_err_with(|| format!("could not create data dir {}", self.dir.display()))?;
+        let path = self.table_path(table);
+        let tmp_path = path.with_extension("jsonl.tmp");
+        let mut contents = String::new();
+        for line in lines {
+            contents.push_str(line.as_ref());
+            contents.push('\n');
+        }
+        fs::write(&tmp_path, contents)
+            .wrap_err_with(|| format!("could not write to table {}", table))?;
+        fs::rename(&tmp_path, &path)
+            .wrap_err_with(|| format!("could not write to table {}", table))?;
+
+        Ok(())
+    }
+
+    /// Reads all rows of the given table, without locking.
+    fn read_rows<T: DeserializeOwned>(&self, table: &str) -> Result<Vec<T>> {
+        let path = self.table_path(table);
+        if !path.try_exists()? {
+            return Ok(Vec::new());
+        }
+
+        let file =
+            fs::File::open(&path).wrap_err_with(|| format!("could not open table {}", table))?;
+        let mut rows = Vec::new();
+        for (line_no, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<T>(&line) {
+                Ok(row) => rows.push(row),
+                Err(e) => log::warn!("Skipping malformed row {} in {}: {}", line_no, table, e),
+            }
+        }
+
+        Ok(rows)
+    }
+}
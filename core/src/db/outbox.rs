@@ -0,0 +1,212 @@
+use alloy::primitives::{Bytes, U256};
+use dria_oracle_contracts::OracleKind;
+use dria_oracle_storage::ArweaveKey;
+use eyre::Result;
+
+use super::{OracleDb, ResponseScope};
+
+const OUTBOX_TABLE: &str = "outbox";
+
+/// The payload of a response, as it was sent to the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum OutboxPayload {
+    /// Output of a generation.
+    Generator { output: Bytes },
+    /// Scores of a validation.
+    Validator { scores: Vec<U256> },
+}
+
+impl OutboxPayload {
+    /// Returns the role of the response.
+    #[inline]
+    pub fn kind(&self) -> OracleKind {
+        match self {
+            Self::Generator { .. } => OracleKind::Generator,
+            Self::Validator { .. } => OracleKind::Validator,
+        }
+    }
+}
+
+/// A response whose transaction has failed after the work was done, e.g. due to an RPC outage
+/// or a lack of gas, kept so that it can be sent again without redoing the work.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OutboxEntry {
+    /// Task id.
+    pub task_id: U256,
+    /// Chain & coordinator of the task, and the address that the nonce is mined for.
+    #[serde(flatten)]
+    pub scope: ResponseScope,
+    /// The response itself.
+    #[serde(flatten)]
+    pub payload: OutboxPayload,
+    /// Metadata of the response.
+    pub metadata: Bytes,
+    /// The nonce that was mined for the response.
+    pub nonce: U256,
+    /// Error of the last attempt.
+    pub error: String,
+    /// Number of failed attempts.
+    pub attempts: u32,
+    /// Unix timestamp (in seconds) of the first failed attempt.
+    pub created_at: u64,
+}
+
+impl OutboxEntry {
+    /// Returns the role of the response.
+    #[inline]
+    pub fn kind(&self) -> OracleKind {
+        self.payload.kind()
+    }
+
+    /// Returns the ids of the Arweave uploads that the output & metadata of the response refer to.
+    pub fn upload_ids(&self) -> Vec<String> {
+        let output = match &self.payload {
+            OutboxPayload::Generator { output } => Some(output),
+            OutboxPayload::Validator { .. } => None,
+        };
+
+        let mut ids = output
+            .into_iter()
+            .chain(std::iter::once(&self.metadata))
+            .filter_map(|value| std::str::from_utf8(value).ok())
+            .filter_map(ArweaveKey::parse)
+            .map(|key| key.arweave)
+            .collect::<Vec<_>>();
+        ids.dedup();
+        ids
+    }
+
+    /// Returns `true` if both entries are for the same response, i.e. same task, kind & scope.
+    #[inline]
+    fn is_same_response(&self, other: &Self) -> bool {
+        self.task_id == other.task_id && self.scope == other.scope && self.kind() == other.kind()
+    }
+}
+
+impl OracleDb {
+    /// Adds a failed response to the outbox.
+    ///
+    /// If there is already an entry for the same response, it is replaced while its
+    /// number of attempts is incremented and its creation time is kept.
+    pub fn insert_outbox_entry(&self, mut entry: OutboxEntry) -> Result<()> {
+        self.update(OUTBOX_TABLE, |entries: &mut Vec<OutboxEntry>| {
+            if let Some(pos) = entries.iter().position(|e| e.is_same_response(&entry)) {
+                let existing = entries.remove(pos);
+                entry.attempts = existing.attempts + 1;
+                entry.created_at = existing.created_at;
+            }
+            entries.push(entry);
+        })
+    }
+
+    /// Returns the entries of the outbox within the given scope, oldest first.
+    pub fn get_outbox_entries(&self, scope: ResponseScope) -> Result<Vec<OutboxEntry>> {
+        let entries = self
+            .read_all::<OutboxEntry>(OUTBOX_TABLE)?
+            .into_iter()
+            .filter(|e| e.scope == scope)
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Removes the entry of the given response from the outbox, if any.
+    pub fn remove_outbox_entry(&self, entry: &OutboxEntry) -> Result<()> {
+        self.update(OUTBOX_TABLE, |entries: &mut Vec<OutboxEntry>| {
+            entries.retain(|e| !e.is_same_response(entry))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_outbox() {
+        let dir = TestDir::new("outbox");
+        let db = OracleDb::new(dir.path());
+        let scope = ResponseScope {
+            chain_id: 1,
+            coordinator: Address::repeat_byte(1),
+            responder: Address::repeat_byte(3),
+        };
+
+        let entry = |task_id: u64, payload: OutboxPayload| OutboxEntry {
+            task_id: U256::from(task_id),
+            scope,
+            payload,
+            metadata: Bytes::new(),
+            nonce: U256::from(42),
+            error: "rpc is down".to_string(),
+            attempts: 1,
+            created_at: task_id,
+        };
+        let generation = |task_id| {
+            entry(
+                task_id,
+                OutboxPayload::Generator {
+                    output: Bytes::from_static(b"hello"),
+                },
+            )
+        };
+
+        db.insert_outbox_entry(generation(1)).unwrap();
+        db.insert_outbox_entry(entry(
+            1,
+            OutboxPayload::Validator {
+                scores: vec![U256::from(1)],
+            },
+        ))
+        .unwrap();
+        db.insert_outbox_entry(generation(2)).unwrap();
+        assert_eq!(db.get_outbox_entries(scope).unwrap().len(), 3);
+        for other in [
+            ResponseScope {
+                coordinator: Address::repeat_byte(2),
+                ..scope
+            },
+            ResponseScope {
+                responder: Address::repeat_byte(4),
+                ..scope
+            },
+        ] {
+            assert!(db.get_outbox_entries(other).unwrap().is_empty());
+        }
+
+        // failing again replaces the entry
+        let mut retried = generation(1);
+        retried.created_at = 100;
+        db.insert_outbox_entry(retried).unwrap();
+        let entries = db.get_outbox_entries(scope).unwrap();
+        assert_eq!(entries.len(), 3);
+        let last = entries.last().unwrap();
+        assert_eq!((last.task_id, last.attempts), (U256::from(1), 2));
+        assert_eq!(last.created_at, 1);
+
+        db.remove_outbox_entry(last).unwrap();
+        let entries = db.get_outbox_entries(scope).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind(), OracleKind::Validator);
+
+        // both values of a bundle refer to the same upload
+        assert!(generation(3).upload_ids().is_empty());
+        let key = r#"{"arweave":"Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA","item":"output"}"#;
+        let bundled = OutboxEntry {
+            metadata: Bytes::from(key.replace("output", "metadata")),
+            ..entry(
+                3,
+                OutboxPayload::Generator {
+                    output: Bytes::from_static(key.as_bytes()),
+                },
+            )
+        };
+        assert_eq!(
+            bundled.upload_ids(),
+            ["Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA"]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use alloy::primitives::{Address, U256};
+use eyre::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::OracleDb;
+
+pub(super) const PEERS_TABLE: &str = "peers";
+
+/// A response of a generator to a completed task, along with its final score, see the `peers` command.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PeerResponse {
+    /// Address of the coordinator that the task belongs to.
+    pub coordinator: Address,
+    /// Task id.
+    pub task_id: U256,
+    /// Address of the generator.
+    pub responder: Address,
+    /// Final score of the response, as given by the validators.
+    pub score: U256,
+}
+
+/// Reputation of a generator, w.r.t the completed tasks that are recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerReputation {
+    /// Address of the generator.
+    pub address: Address,
+    /// Number of completed tasks that the generator has responded to.
+    pub responses: usize,
+    /// Number of completed tasks that are recorded, whether the generator has responded or not.
+    pub tasks: usize,
+    /// Average final score of the responses.
+    pub average_score: f64,
+}
+
+impl PeerReputation {
+    /// Returns the ratio of the recorded tasks that the generator has responded to.
+    pub fn response_rate(&self) -> f64 {
+        if self.tasks == 0 {
+            0.0
+        } else {
+            self.responses as f64 / self.tasks as f64
+        }
+    }
+
+    /// Computes the reputation of each generator within the given responses,
+    /// ordered by the average score & then the number of responses, best first.
+    pub fn from_responses(responses: &[PeerResponse]) -> Vec<Self> {
+        let tasks = responses
+            .iter()
+            .map(|r| (r.coordinator, r.task_id))
+            .collect::<BTreeSet<_>>()
+            .len();
+
+        let mut scores = BTreeMap::<Address, Vec<f64>>::new();
+        for response in responses {
+            scores
+                .entry(response.responder)
+                .or_default()
+                .push(response.score.saturating_to::<u64>() as f64);
+        }
+
+        let mut reputations = scores
+            .into_iter()
+            .map(|(address, scores)| Self {
+                address,
+                responses: scores.len(),
+                tasks,
+                average_score: scores.iter().sum::<f64>() / scores.len() as f64,
+            })
+            .collect::<Vec<_>>();
+        reputations.sort_by(|a, b| {
+            b.average_score
+                .total_cmp(&a.average_score)
+                .then(b.responses.cmp(&a.responses))
+        });
+
+        reputations
+    }
+}
+
+impl OracleDb {
+    /// Records the responses of completed tasks, returns the number of new responses.
+    ///
+    /// The responses of a completed task do not change, so the ones that are already recorded
+    /// are skipped and the table is only appended to.
+    pub fn insert_peer_responses(&self, responses: Vec<PeerResponse>) -> Result<usize> {
+        self.append_new(PEERS_TABLE, responses, |r| {
+            (r.coordinator, r.task_id, r.responder)
+        })
+    }
+
+    /// Returns the reputation of the generators within the recorded tasks of the given coordinator.
+    pub fn get_peer_reputations(&self, coordinator: Address) -> Result<Vec<PeerReputation>> {
+        let responses = self
+            .read_all::<PeerResponse>(PEERS_TABLE)?
+            .into_iter()
+            .filter(|r| r.coordinator == coordinator)
+            .collect::<Vec<_>>();
+
+        Ok(PeerReputation::from_responses(&responses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CREATED_AT;
+    use crate::testing::TestDir;
+
+    fn response(task_id: u64, responder: u8, score: u64) -> PeerResponse {
+        PeerResponse {
+            coordinator: Address::ZERO,
+            task_id: U256::from(task_id),
+            responder: Address::repeat_byte(responder),
+            score: U256::from(score),
+        }
+    }
+
+    #[test]
+    fn test_peer_reputations() {
+        let dir = TestDir::new("peers");
+        let db = OracleDb::new(dir.path());
+
+        db.insert_peer_responses(vec![response(1, 1, 100), response(1, 2, 200)])
+            .unwrap();
+        db.insert_peer_responses(vec![response(2, 1, 60), response(3, 2, 250)])
+            .unwrap();
+        // recording a task again does not count it twice
+        assert_eq!(
+            db.insert_peer_responses(vec![response(2, 1, 60)]).unwrap(),
+            0
+        );
+        // the rows are stamped, so that they can be pruned by age
+        let rows = db.read_all::<serde_json::Value>(PEERS_TABLE).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|row| row[CREATED_AT].is_u64()));
+
+        let reputations = db.get_peer_reputations(Address::ZERO).unwrap();
+        assert_eq!(reputations.len(), 2);
+        assert_eq!(reputations[0].address, Address::repeat_byte(2));
+        assert_eq!(reputations[0].responses, 2);
+        assert_eq!(reputations[0].average_score, 225.0);
+        assert_eq!(reputations[1].address, Address::repeat_byte(1));
+        assert_eq!(reputations[1].average_score, 80.0);
+        assert_eq!(reputations[1].tasks, 3);
+        assert!((reputations[1].response_rate() - 2.0 / 3.0).abs() < 1e-9);
+
+        // other coordinators are not included
+        assert!(db
+            .get_peer_reputations(Address::repeat_byte(9))
+            .unwrap()
+            .is_empty());
+    }
+}
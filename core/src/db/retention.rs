@@ -0,0 +1,286 @@
+use alloy::primitives::Address;
+use eyre::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::time::Duration;
+
+use super::costs::COSTS_TABLE;
+use super::events::EVENTS_TABLE;
+use super::gas::GAS_TABLE;
+use super::mining::MINING_TABLE;
+use super::peers::PEERS_TABLE;
+use super::uploads::UPLOADS_TABLE;
+use super::wal::WAL_TABLE;
+use super::{OracleDb, CREATED_AT};
+
+/// Tables that can be pruned, where the outbox & the index checkpoints are left out
+/// as they are needed to retry the responses & to resume indexing. The ledger is left out
+/// as well, as it prevents responding to the same task twice.
+///
+/// The indexed tasks are kept in sqlite, and are pruned on their own, see [`OracleDb::prune_index`].
+const PRUNABLE_TABLES: [&str; 7] = [
+    COSTS_TABLE,
+    GAS_TABLE,
+    MINING_TABLE,
+    UPLOADS_TABLE,
+    PEERS_TABLE,
+    EVENTS_TABLE,
+    WAL_TABLE,
+];
+
+/// How long the rows of the local database are kept for, and how large each table can grow.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Rows older than this are pruned, omit to keep them regardless of their age.
+    pub max_age: Option<Duration>,
+    /// Each table is trimmed to this many bytes by pruning its oldest rows, omit to not limit the size.
+    pub max_table_size: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Returns `true` if neither the age nor the size of the rows are limited.
+    pub fn is_unbounded(&self) -> bool {
+        self.max_age.is_none() && self.max_table_size.is_none()
+    }
+
+    /// Returns `true` if the row has expired, i.e. it is written before `max_age`, or it belongs to
+    /// the given coordinator and is emitted before the given block.
+    ///
+    /// Rows that have neither a write time nor a block are kept, e.g. the ones of older versions.
+    fn is_expired(&self, row: &Value, now: u64, oldest_block: Option<(Address, u64)>) -> bool {
+        let Some(max_age) = self.max_age else {
+            return false;
+        };
+        if let Some(created_at) = row.get(CREATED_AT).and_then(Value::as_u64) {
+            return now.saturating_sub(created_at) > max_age.as_secs();
+        }
+
+        let Some((coordinator, block_number)) = oldest_block else {
+            return false;
+        };
+        let row_coordinator = row
+            .get("coordinator")
+            .and_then(|c| serde_json::from_value::<Address>(c.clone()).ok());
+        row_coordinator == Some(coordinator)
+            && row
+                .get("block_number")
+                .and_then(Value::as_u64)
+                .is_some_and(|b| b < block_number)
+    }
+}
+
+/// Result of pruning a table, see [`OracleDb::prune`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrunedTable {
+    /// Name of the table.
+    pub table: &'static str,
+    /// Number of rows that are removed, including the malformed ones.
+    pub removed: usize,
+    /// Size of the table in bytes after pruning.
+    pub size: u64,
+}
+
+impl OracleDb {
+    /// Prunes the tables with the given policy, where `now` is in seconds since the epoch.
+    ///
+    /// The appended rows are pruned by the time they are written at, while the indexed tasks & events
+    /// are pruned by their block if `oldest_block` is given, only for its coordinator as each chain has
+    /// its own blocks. Then, the oldest rows are pruned until each table fits within the size limit.
+    ///
+    /// Each table is compacted as well, i.e. the malformed & empty rows are dropped.
+    pub fn prune(
+        &self,
+        policy: &RetentionPolicy,
+        now: u64,
+        oldest_block: Option<(Address, u64)>,
+    ) -> Result<Vec<PrunedTable>> {
+        let mut pruned = PRUNABLE_TABLES
+            .into_iter()
+            .map(|table| {
+                self.compact_table(
+                    table,
+                    |row| policy.is_expired(row, now, oldest_block),
+                    policy.max_table_size,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let oldest_indexed_block = oldest_block.filter(|_| policy.max_age.is_some());
+        pruned.push(self.prune_index(oldest_indexed_block, policy.max_table_size)?);
+
+        Ok(pruned)
+    }
+
+    /// Drops the expired & malformed rows of a table, and then its oldest rows until it fits within `max_size`.
+    ///
+    /// The lines that are kept are written back as is, and the table is only written if it has changed.
+    fn compact_table(
+        &self,
+        table: &'static str,
+        is_expired: impl Fn(&Value) -> bool,
+        max_size: Option<u64>,
+    ) -> Result<PrunedTable> {
+        let _guard = self.lock.lock().expect("db lock poisoned");
+
+        let path = self.table_path(table);
+        if !path.try_exists()? {
+            return Ok(PrunedTable {
+                table,
+                removed: 0,
+                size: 0,
+            });
+        }
+        let contents = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("could not read table {}", table))?;
+
+        let mut removed = 0;
+        let mut lines = Vec::new();
+        for (line_no, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<Value>(line) {
+                Ok(row) if is_expired(&row) => removed += 1,
+                Ok(_) => lines.push(line),
+                Err(e) => {
+                    log::warn!("Dropping malformed row {} in {}: {}", line_no, table, e);
+                    removed += 1;
+                }
+            }
+        }
+
+        // the oldest rows come first, as the rows are either appended or ordered by task id / block
+        let mut size = lines.iter().map(|line| line.len() as u64 + 1).sum::<u64>();
+        if let Some(max_size) = max_size {
+            let mut oldest = 0;
+            while size > max_size && oldest < lines.len() {
+                size -= lines[oldest].len() as u64 + 1;
+                oldest += 1;
+            }
+            lines.drain(..oldest);
+            removed += oldest;
+        }
+
+        if size != contents.len() as u64 {
+            self.write_lines(table, &lines)?;
+        }
+
+        Ok(PrunedTable {
+            table,
+            removed,
+            size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+    use crate::{IndexedEvent, MiningRecord, OutboxEntry, OutboxPayload};
+    use alloy::primitives::{Bytes, TxHash, U256};
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn test_prune() {
+        let dir = TestDir::new("retention");
+        let db = OracleDb::new(dir.path());
+        let coordinator = Address::repeat_byte(1);
+
+        for task_id in 0..10 {
+            db.insert_mining_record(&MiningRecord {
+                task_id: U256::from(task_id),
+                kind: "generator".to_string(),
+                difficulty: 2,
+                hashes: 4,
+                elapsed_ms: 1,
+            })
+            .unwrap();
+        }
+        let event = |coordinator: Address, block_number: u64| IndexedEvent {
+            coordinator,
+            contract: coordinator,
+            block_number,
+            log_index: coordinator.0[0] as u64,
+            tx_hash: TxHash::repeat_byte(block_number as u8),
+            event: crate::ContractEvent::Initialized { version: 1 },
+        };
+        db.upsert_indexed_events(vec![
+            event(coordinator, 10),
+            event(coordinator, 20),
+            event(Address::repeat_byte(2), 10),
+        ])
+        .unwrap();
+        let scope = crate::ResponseScope {
+            coordinator,
+            ..Default::default()
+        };
+        db.insert_outbox_entry(OutboxEntry {
+            task_id: U256::from(1),
+            scope,
+            payload: OutboxPayload::Validator { scores: vec![] },
+            metadata: Bytes::new(),
+            nonce: U256::ZERO,
+            error: String::new(),
+            attempts: 1,
+            created_at: 0,
+        })
+        .unwrap();
+        // a malformed row is dropped while compacting
+        let mining_path = dir.join("mining.jsonl");
+        let mut contents = fs::read_to_string(&mining_path).unwrap();
+        contents.push_str("{not json\n");
+        fs::write(&mining_path, contents).unwrap();
+
+        // nothing has expired yet, only the malformed row is removed
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(DAY)),
+            max_table_size: None,
+        };
+        let pruned = db.prune(&policy, now, Some((coordinator, 15))).unwrap();
+        let removed = |table: &str| {
+            pruned
+                .iter()
+                .find(|p| p.table == table)
+                .map(|p| p.removed)
+                .unwrap()
+        };
+        assert_eq!(removed(MINING_TABLE), 1);
+        assert_eq!(db.get_mining_records().unwrap().len(), 10);
+        // the events are pruned by block for the given coordinator only
+        assert_eq!(removed(EVENTS_TABLE), 1);
+        assert_eq!(db.get_indexed_events(coordinator).unwrap().len(), 1);
+        assert_eq!(
+            db.get_indexed_events(Address::repeat_byte(2))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // the oldest rows are pruned to fit the size
+        let size = fs::metadata(&mining_path).unwrap().len();
+        let policy = RetentionPolicy {
+            max_age: None,
+            max_table_size: Some(size / 2),
+        };
+        db.prune(&policy, now, None).unwrap();
+        let records = db.get_mining_records().unwrap();
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].task_id, U256::from(5));
+        assert!(fs::metadata(&mining_path).unwrap().len() <= size / 2);
+
+        // all appended rows have expired a few days later, while the outbox is kept
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(DAY)),
+            max_table_size: None,
+        };
+        db.prune(&policy, now + 2 * DAY, None).unwrap();
+        assert!(db.get_mining_records().unwrap().is_empty());
+        assert_eq!(db.get_outbox_entries(scope).unwrap().len(), 1);
+    }
+}
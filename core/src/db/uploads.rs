@@ -0,0 +1,36 @@
+use alloy::primitives::U256;
+use dria_oracle_storage::ArweaveReceipt;
+use eyre::Result;
+
+use super::OracleDb;
+
+pub(super) const UPLOADS_TABLE: &str = "uploads";
+
+/// An upload to Arweave made for a task, recorded after it is verified to be retrievable.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UploadRecord {
+    /// Task id.
+    pub task_id: U256,
+    /// The role we uploaded as, i.e. `generator` or `validator`.
+    pub kind: String,
+    /// Receipt of the upload, as returned by the bundler.
+    pub receipt: ArweaveReceipt,
+}
+
+impl OracleDb {
+    /// Records a verified upload.
+    pub fn insert_upload_record(&self, record: &UploadRecord) -> Result<()> {
+        self.append(UPLOADS_TABLE, record)
+    }
+
+    /// Returns the uploads made for the given task.
+    pub fn get_upload_records(&self, task_id: U256) -> Result<Vec<UploadRecord>> {
+        let records = self
+            .read_all::<UploadRecord>(UPLOADS_TABLE)?
+            .into_iter()
+            .filter(|r| r.task_id == task_id)
+            .collect();
+
+        Ok(records)
+    }
+}
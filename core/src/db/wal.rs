@@ -0,0 +1,186 @@
+use alloy::primitives::{Address, TxHash, U256};
+use eyre::Result;
+use std::collections::BTreeMap;
+
+use super::{OracleDb, ResponseScope};
+use crate::TraceId;
+
+pub(super) const WAL_TABLE: &str = "wal";
+
+/// A decision made about a task while serving, see [`WalEntry`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalDecision {
+    /// The task is accepted, and is about to be handled.
+    Accepted,
+    /// The task is skipped without being handled.
+    Skipped { reason: String },
+    /// The response with the given nonce is about to be sent, written before it is broadcast
+    /// so that a crash right after the broadcast is not taken for a task without a response.
+    Submitting { nonce: U256 },
+    /// The response transaction is sent, and its receipt is awaited.
+    Submitted { tx_hash: TxHash },
+    /// Handling of the task has finished, with or without a response.
+    Finished,
+    /// Handling of the task has failed.
+    Failed { error: String },
+    /// The task is handled again after its handling was interrupted, e.g. by a crash.
+    Resumed,
+}
+
+/// A row of the write-ahead log, which records each decision made about a task before acting on it.
+///
+/// Along with the ledger of the sent responses, this lets a restarted node resume the tasks that it
+/// was handling when it stopped, without responding to any of them twice.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WalEntry {
+    /// Chain & coordinator of the task, and the address that it is responded with.
+    #[serde(flatten)]
+    pub scope: ResponseScope,
+    /// Task id.
+    pub task_id: U256,
+    /// The role that the decision is made as, i.e. `generator` or `validator`.
+    pub kind: String,
+    pub decision: WalDecision,
+    /// Trace id of the attempt that made the decision, if it is made while handling the task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<TraceId>,
+    /// Time that the entry is written at in seconds since the epoch, stamped by the database.
+    #[serde(default, skip_serializing)]
+    pub created_at: u64,
+}
+
+impl WalDecision {
+    /// Returns the decision that records the result of handling a task.
+    pub fn after<T>(result: &Result<T>) -> Self {
+        match result {
+            Ok(_) => Self::Finished,
+            Err(e) => Self::Failed {
+                error: format!("{:#}", e),
+            },
+        }
+    }
+}
+
+impl WalEntry {
+    /// Returns `true` if handling of the task has started but not finished as of this entry.
+    pub fn is_in_flight(&self) -> bool {
+        matches!(
+            self.decision,
+            WalDecision::Accepted | WalDecision::Submitting { .. } | WalDecision::Submitted { .. }
+        )
+    }
+}
+
+impl OracleDb {
+    /// Appends an entry to the write-ahead log, which is flushed to the disk before returning.
+    pub fn insert_wal_entry(&self, entry: &WalEntry) -> Result<()> {
+        self.append_synced(WAL_TABLE, entry)
+    }
+
+    /// Returns the entries of the write-ahead log written at or after the given time, in seconds since the epoch.
+    pub fn get_wal_entries(&self, since: u64) -> Result<Vec<WalEntry>> {
+        let entries = self
+            .read_all::<WalEntry>(WAL_TABLE)?
+            .into_iter()
+            .filter(|entry| entry.created_at >= since)
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Returns the latest entry of each task within the scope that was still in flight when it was
+    /// written, i.e. the tasks whose handling was interrupted, ordered by task id.
+    pub fn get_interrupted_tasks(&self, scope: ResponseScope) -> Result<Vec<WalEntry>> {
+        let mut latest = BTreeMap::new();
+        for entry in self.read_all::<WalEntry>(WAL_TABLE)? {
+            if entry.scope == scope {
+                latest.insert((entry.task_id, entry.kind.clone()), entry);
+            }
+        }
+
+        Ok(latest
+            .into_values()
+            .filter(WalEntry::is_in_flight)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    #[test]
+    fn test_interrupted_tasks() {
+        let dir = TestDir::new("wal");
+        let db = OracleDb::new(dir.path());
+        let scope = ResponseScope {
+            chain_id: 1,
+            coordinator: Address::repeat_byte(1),
+            responder: Address::repeat_byte(2),
+        };
+        let other_account = ResponseScope {
+            responder: Address::repeat_byte(3),
+            ..scope
+        };
+        let entry = |scope: ResponseScope, task_id: u64, decision: WalDecision| WalEntry {
+            scope,
+            task_id: U256::from(task_id),
+            kind: "generator".to_string(),
+            decision,
+            trace_id: None,
+            created_at: 0,
+        };
+
+        for entry in [
+            entry(scope, 1, WalDecision::Accepted),
+            entry(scope, 2, WalDecision::Accepted),
+            entry(scope, 3, WalDecision::Accepted),
+            entry(other_account, 4, WalDecision::Accepted),
+            entry(scope, 6, WalDecision::Accepted),
+            entry(scope, 1, WalDecision::Finished),
+            entry(
+                scope,
+                6,
+                WalDecision::Submitting {
+                    nonce: U256::from(42),
+                },
+            ),
+            entry(
+                scope,
+                2,
+                WalDecision::Submitted {
+                    tx_hash: TxHash::repeat_byte(2),
+                },
+            ),
+            entry(
+                scope,
+                5,
+                WalDecision::Skipped {
+                    reason: "unprofitable".to_string(),
+                },
+            ),
+        ] {
+            db.insert_wal_entry(&entry).unwrap();
+        }
+
+        // tasks 2, 3 & 6 were in flight, while task 4 belongs to another account
+        let interrupted = db.get_interrupted_tasks(scope).unwrap();
+        let task_ids = interrupted.iter().map(|e| e.task_id).collect::<Vec<_>>();
+        assert_eq!(task_ids, vec![U256::from(2), U256::from(3), U256::from(6)]);
+        let task_ids = db
+            .get_interrupted_tasks(other_account)
+            .unwrap()
+            .iter()
+            .map(|e| e.task_id)
+            .collect::<Vec<_>>();
+        assert_eq!(task_ids, vec![U256::from(4)]);
+
+        // the entries are stamped with the time they are written at
+        let entries = db.get_wal_entries(0).unwrap();
+        assert_eq!(entries.len(), 9);
+        assert!(entries.iter().all(|e| e.created_at > 0));
+        assert!(db.get_wal_entries(u64::MAX).unwrap().is_empty());
+    }
+}
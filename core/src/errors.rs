@@ -0,0 +1,297 @@
+use alloy::primitives::Address;
+use alloy::rpc::json_rpc::ErrorPayload;
+use alloy::transports::{RpcError, TransportError, TransportErrorKind};
+use dria_oracle_contracts::contract_error_report;
+
+/// Errors of the node, so that the callers can tell the failures apart and react to them,
+/// e.g. retry an RPC failure but not a revert.
+///
+/// These are converted to `eyre` reports at the CLI, see [`DriaOracleError::from_report`] to get them back.
+#[derive(Debug, thiserror::Error)]
+pub enum DriaOracleError {
+    /// The RPC could not be reached, or has failed to respond.
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    /// A contract call has reverted, with the decoded contract error.
+    #[error("{0}")]
+    Contract(String),
+    /// The account can not pay for the gas & value of a transaction.
+    #[error("Insufficient funds for gas & value in {0}, please fund your wallet.")]
+    InsufficientFunds(Address),
+    /// The storage could not be read from or written to.
+    #[error("Storage error: {0}")]
+    Storage(String),
+    /// The model has failed to execute a workflow.
+    #[error("Model error: {0}")]
+    Model(String),
+    /// The output could not be post-processed w.r.t its protocol.
+    #[error("Post-processing error: {0}")]
+    PostProcess(String),
+    /// The configuration is invalid.
+    #[error("Config error: {0}")]
+    Config(String),
+}
+
+impl DriaOracleError {
+    /// Returns the error within the given report, if it is one of ours.
+    pub fn from_report(report: &eyre::Report) -> Option<&Self> {
+        report.downcast_ref::<Self>()
+    }
+
+    /// Returns `true` if the failure may be temporary, and the same call can be retried later.
+    ///
+    /// Contract reverts, invalid outputs and configurations fail the same way again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Rpc(_) | Self::Storage(_) | Self::Model(_))
+    }
+
+    /// Creates a storage error from the report of the storage.
+    pub(crate) fn storage(report: eyre::Report) -> Self {
+        Self::Storage(format!("{:#}", report))
+    }
+
+    /// Creates a post-processing error from the report of a post-processor.
+    pub(crate) fn post_process(report: eyre::Report) -> Self {
+        Self::PostProcess(format!("{:#}", report))
+    }
+
+    /// Creates a config error from the report of a parser.
+    pub(crate) fn config(report: eyre::Report) -> Self {
+        Self::Config(format!("{:#}", report))
+    }
+}
+
+impl From<RpcError<TransportErrorKind>> for DriaOracleError {
+    fn from(error: RpcError<TransportErrorKind>) -> Self {
+        Self::Rpc(error.to_string())
+    }
+}
+
+impl From<alloy::contract::Error> for DriaOracleError {
+    fn from(error: alloy::contract::Error) -> Self {
+        match error {
+            // transport errors without a revert never reached the contract, e.g. the RPC has failed
+            // or rate-limited us, so they are not taken as a verdict of the contract
+            alloy::contract::Error::TransportError(error)
+                if !error.as_error_resp().is_some_and(is_revert) =>
+            {
+                error.into()
+            }
+            error => Self::Contract(format!("{:#}", contract_error_report(error))),
+        }
+    }
+}
+
+/// Returns `true` if the error response is a revert of the contract, i.e. it has the revert data
+/// or says so, rather than a failure of the RPC itself.
+fn is_revert(payload: &ErrorPayload) -> bool {
+    payload.as_revert_data().is_some() || payload.message.to_lowercase().contains("revert")
+}
+
+/// Class of an RPC error of a transaction, which decides how it is handled, see
+/// [`DriaOracle::send_with_gas_hikes`](crate::DriaOracle::send_with_gas_hikes).
+///
+/// RPC providers word these errors differently, so the known wordings of each are matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxErrorClass {
+    /// The gas price is too low, or too low to replace a pending transaction.
+    Underpriced,
+    /// The nonce is already used by a mined transaction.
+    NonceTooLow,
+    /// The same transaction is already in the mempool.
+    AlreadyKnown,
+    /// The account can not pay for the gas & value.
+    InsufficientFunds,
+    /// The RPC has rate-limited the requests.
+    RateLimited,
+    /// Any other error, e.g. a revert.
+    Other,
+}
+
+impl TxErrorClass {
+    /// JSON-RPC error code for rate-limited requests, as used by Infura & Alchemy.
+    const LIMIT_EXCEEDED_CODE: i64 = -32005;
+    /// HTTP status code for rate-limited requests.
+    const TOO_MANY_REQUESTS_STATUS: u16 = 429;
+
+    /// Classifies the given transport error, w.r.t the message of its error response if there is one.
+    pub fn classify(error: &TransportError) -> Self {
+        if let RpcError::Transport(TransportErrorKind::HttpError(e)) = error {
+            if e.status == Self::TOO_MANY_REQUESTS_STATUS {
+                return Self::RateLimited;
+            }
+        }
+
+        match error.as_error_resp() {
+            Some(payload) if payload.code == Self::LIMIT_EXCEEDED_CODE => Self::RateLimited,
+            Some(payload) => Self::classify_message(&payload.message),
+            None => Self::classify_message(&error.to_string()),
+        }
+    }
+
+    /// Classifies an error w.r.t its message.
+    pub fn classify_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+        // a status code is only taken as such right after its name, e.g. `HTTP error 429` or `status: 429`,
+        // as the same number may be anywhere else within the message, e.g. a block number or an amount
+        let has_status = |code: &str| {
+            let words = message
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>();
+            words.windows(2).any(|pair| {
+                pair[1] == code && matches!(pair[0], "http" | "error" | "status" | "code")
+            })
+        };
+        let has_phrase = |phrases: &[&str]| phrases.iter().any(|p| message.contains(p));
+
+        if has_phrase(&[
+            "already known",
+            "known transaction",
+            "already imported",
+            "alreadyknown",
+            "already in mempool",
+        ]) {
+            Self::AlreadyKnown
+        } else if has_phrase(&[
+            "nonce too low",
+            "nonce is too low",
+            "oldnonce",
+            "nonce has already been used",
+        ]) {
+            Self::NonceTooLow
+        } else if has_phrase(&[
+            "underpriced",
+            "fee too low",
+            "feetoolow",
+            "gas price too low",
+            "less than block base fee",
+        ]) {
+            Self::Underpriced
+        } else if has_phrase(&["insufficient funds", "insufficientfunds"]) {
+            Self::InsufficientFunds
+        } else if has_status("429")
+            || has_phrase(&[
+                "rate limit",
+                "too many requests",
+                "request limit",
+                "limit exceeded",
+            ])
+        {
+            Self::RateLimited
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl std::fmt::Display for TxErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Underpriced => "underpriced",
+            Self::NonceTooLow => "nonce too low",
+            Self::AlreadyKnown => "already known",
+            Self::InsufficientFunds => "insufficient funds",
+            Self::RateLimited => "rate limited",
+            Self::Other => "failure",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Context;
+
+    #[test]
+    fn test_oracle_error() {
+        let error = DriaOracleError::from(RpcError::<TransportErrorKind>::NullResp);
+        assert!(matches!(error, DriaOracleError::Rpc(_)));
+        assert!(error.is_retryable());
+
+        let error = DriaOracleError::from(alloy::contract::Error::ContractNotDeployed);
+        assert!(matches!(error, DriaOracleError::Contract(_)));
+        assert!(!error.is_retryable());
+        assert_eq!(error.to_string(), "Contract is not deployed");
+
+        // only the error responses of a revert are contract errors, the rest are of the RPC
+        for (response, is_contract) in [
+            (
+                r#"{"code":3,"message":"execution reverted","data":"0x3ee5aeb5"}"#,
+                true,
+            ),
+            (r#"{"code":-32000,"message":"execution reverted"}"#, true),
+            (r#"{"code":-32005,"message":"limit exceeded"}"#, false),
+            (r#"{"code":-32603,"message":"internal error"}"#, false),
+        ] {
+            let payload = serde_json::from_str::<ErrorPayload>(response).unwrap();
+            let error = DriaOracleError::from(alloy::contract::Error::TransportError(
+                RpcError::ErrorResp(payload),
+            ));
+            assert_eq!(
+                matches!(error, DriaOracleError::Contract(_)),
+                is_contract,
+                "{}",
+                response
+            );
+            assert_eq!(error.is_retryable(), !is_contract, "{}", response);
+        }
+
+        // the error is kept within the report, even with a context on top
+        let report = Err::<(), _>(DriaOracleError::Storage("503".to_string()))
+            .wrap_err("could not upload")
+            .unwrap_err();
+        assert!(matches!(
+            DriaOracleError::from_report(&report),
+            Some(DriaOracleError::Storage(_))
+        ));
+        assert!(DriaOracleError::from_report(&eyre::eyre!("other")).is_none());
+    }
+
+    #[test]
+    fn test_tx_error_class() {
+        let cases = [
+            (
+                "replacement transaction underpriced",
+                TxErrorClass::Underpriced,
+            ),
+            ("transaction underpriced", TxErrorClass::Underpriced),
+            (
+                "max fee per gas less than block base fee",
+                TxErrorClass::Underpriced,
+            ),
+            (
+                "nonce too low: next nonce 5, tx nonce 4",
+                TxErrorClass::NonceTooLow,
+            ),
+            ("OldNonce, Current nonce: 5", TxErrorClass::NonceTooLow),
+            ("already known", TxErrorClass::AlreadyKnown),
+            ("ALREADY_EXISTS: already known", TxErrorClass::AlreadyKnown),
+            (
+                "insufficient funds for gas * price + value",
+                TxErrorClass::InsufficientFunds,
+            ),
+            ("HTTP error 429 with body: ", TxErrorClass::RateLimited),
+            (
+                "server returned status code: 429",
+                TxErrorClass::RateLimited,
+            ),
+            ("header not found for block 429", TxErrorClass::Other),
+            ("gas required exceeds allowance (429)", TxErrorClass::Other),
+            ("daily request limit reached", TxErrorClass::RateLimited),
+            ("execution reverted", TxErrorClass::Other),
+        ];
+        for (message, class) in cases {
+            assert_eq!(
+                TxErrorClass::classify_message(message),
+                class,
+                "{}",
+                message
+            );
+        }
+
+        let error = TransportErrorKind::custom_str("HTTP error 429 with body: too many requests");
+        assert_eq!(TxErrorClass::classify(&error), TxErrorClass::RateLimited);
+    }
+}
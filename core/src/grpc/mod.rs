@@ -0,0 +1,340 @@
+//! A gRPC service for programmatic access to a running oracle node, enabled with the `grpc` feature.
+//!
+//! The service definition is at `proto/oracle.proto`, so that clients in other languages can be generated from it.
+
+use alloy::primitives::{B256, U256};
+use alloy::providers::Provider;
+use dkn_workflows::Model;
+use dria_oracle_contracts::{bytes32_to_string, TaskStatus};
+use futures_util::{Stream, StreamExt};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status};
+
+use crate::node::{constant_time_eq, MIN_TOKEN_LEN};
+use crate::{DriaOracle, TaskView};
+
+/// Types & service generated from the protobuf definitions.
+pub mod proto {
+    tonic::include_proto!("dria.oracle.v1");
+}
+use proto::oracle_server::{Oracle, OracleServer};
+
+/// The gRPC service, backed by an oracle node.
+pub struct OracleService {
+    node: Arc<DriaOracle>,
+    /// Tasks are requested one at a time to avoid nonce conflicts, same as the gateway.
+    request_lock: Mutex<()>,
+    /// Token that each request must have as its bearer token, if any.
+    token: Option<String>,
+}
+
+impl OracleService {
+    pub fn new(node: Arc<DriaOracle>) -> Self {
+        Self {
+            node,
+            request_lock: Mutex::new(()),
+            token: None,
+        }
+    }
+
+    /// Require each request to have the given token as its bearer token.
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Checks that the service can be served at the given address: as tasks are submitted with
+    /// the wallet of the node, the service is served without a token only on the loopback.
+    pub fn check_address(&self, address: SocketAddr) -> eyre::Result<()> {
+        match &self.token {
+            Some(token) if token.len() < MIN_TOKEN_LEN => Err(eyre::eyre!(
+                "gRPC token must be at least {} characters.",
+                MIN_TOKEN_LEN
+            )),
+            None if !address.ip().is_loopback() => Err(eyre::eyre!(
+                "gRPC API can not be served on {} without a token, as it submits tasks with the wallet of this node.",
+                address
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Serves the service at the given address until the cancellation token is cancelled,
+    /// see [`Self::check_address`] for the addresses that it can be served at.
+    pub async fn serve(
+        self,
+        address: SocketAddr,
+        cancellation: CancellationToken,
+    ) -> eyre::Result<()> {
+        self.check_address(address)?;
+
+        log::info!("Serving gRPC API at {}", address);
+        // the bearer token is sent as is, so it must not cross an untrusted network in plain text
+        if self.token.is_some() && !address.ip().is_loopback() {
+            log::warn!(
+                "gRPC API is served without TLS on {}, put it behind a TLS-terminating proxy so that the token is not sent in plain text.",
+                address
+            );
+        }
+        let token = self.token.clone();
+        let service = OracleServer::with_interceptor(self, move |request: Request<()>| {
+            authorize(request, token.as_deref())
+        });
+        tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_shutdown(address, cancellation.cancelled())
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Lets the request through if it has the given token as its bearer token, or if there is no token.
+fn authorize(request: Request<()>, token: Option<&str>) -> Result<Request<()>, Status> {
+    let Some(token) = token else {
+        return Ok(request);
+    };
+
+    let authorized = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()));
+    if !authorized {
+        log::warn!("Refused an unauthorized gRPC request");
+        return Err(Status::unauthenticated("unauthorized"));
+    }
+
+    Ok(request)
+}
+
+/// Converts an error report to an internal status, keeping its causes within the message.
+#[inline]
+fn internal(err: eyre::Report) -> Status {
+    Status::internal(format!("{:#}", err))
+}
+
+/// Parses a task id given as a decimal string.
+#[inline]
+fn parse_task_id(task_id: &str) -> Result<U256, Status> {
+    task_id
+        .parse()
+        .map_err(|_| Status::invalid_argument(format!("invalid task id: {}", task_id)))
+}
+
+/// Returns the name of a status given by its value within an event.
+#[inline]
+fn status_name(status: u8) -> String {
+    TaskStatus::try_from(status)
+        .map(|s| s.to_string())
+        .unwrap_or_else(|_| status.to_string())
+}
+
+impl From<TaskView> for proto::Task {
+    fn from(task: TaskView) -> Self {
+        Self {
+            task_id: task.task_id.to_string(),
+            requester: task.request.requester.to_string(),
+            protocol: task.request.protocol,
+            status: task.request.status.to_string(),
+            difficulty: task.request.difficulty.into(),
+            num_generations: task.request.num_generations,
+            num_validations: task.request.num_validations,
+            generator_fee: task.request.generator_fee.to_string(),
+            validator_fee: task.request.validator_fee.to_string(),
+            platform_fee: task.request.platform_fee.to_string(),
+            input: task.request.input.content().to_string(),
+            models: task.request.models,
+            responses: task
+                .responses
+                .into_iter()
+                .map(|response| proto::TaskResponse {
+                    responder: response.responder.to_string(),
+                    score: response.score.to_string(),
+                    output: response.output.content().to_string(),
+                    metadata: response.metadata.content().to_string(),
+                })
+                .collect(),
+            validations: task
+                .validations
+                .into_iter()
+                .map(|validation| proto::TaskValidation {
+                    validator: validation.validator.to_string(),
+                    scores: validation.scores.iter().map(|s| s.to_string()).collect(),
+                    metadata: validation.metadata.content().to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Oracle for OracleService {
+    async fn submit_task(
+        &self,
+        request: Request<proto::SubmitTaskRequest>,
+    ) -> Result<Response<proto::SubmitTaskResponse>, Status> {
+        let request = request.into_inner();
+        let models = request
+            .models
+            .into_iter()
+            .map(Model::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let difficulty = u8::try_from(request.difficulty)
+            .map_err(|_| Status::invalid_argument("difficulty must fit in a byte"))?;
+
+        let _guard = self.request_lock.lock().await;
+        let task_id = self
+            .node
+            .request_task(
+                &request.input,
+                models,
+                difficulty,
+                request.num_generations,
+                request.num_validations,
+                request.protocol,
+            )
+            .await
+            .map_err(internal)?;
+
+        Ok(Response::new(proto::SubmitTaskResponse {
+            task_id: task_id.to_string(),
+        }))
+    }
+
+    async fn get_task(
+        &self,
+        request: Request<proto::GetTaskRequest>,
+    ) -> Result<Response<proto::Task>, Status> {
+        let request = request.into_inner();
+        let task_id = parse_task_id(&request.task_id)?;
+
+        let mut task = self.node.get_task(task_id).await.map_err(internal)?;
+        if request.resolve_storage {
+            task.resolve_storage().await;
+        }
+
+        Ok(Response::new(task.into()))
+    }
+
+    type StreamTaskUpdatesStream =
+        Pin<Box<dyn Stream<Item = Result<proto::TaskUpdate, Status>> + Send + 'static>>;
+
+    async fn stream_task_updates(
+        &self,
+        request: Request<proto::StreamTaskUpdatesRequest>,
+    ) -> Result<Response<Self::StreamTaskUpdatesStream>, Status> {
+        let request = request.into_inner();
+
+        let mut filter = self.node.coordinator.StatusUpdate_filter();
+        if let Some(task_id) = request.task_id {
+            filter = filter.topic1(B256::from(parse_task_id(&task_id)?));
+        }
+        let stream = filter
+            .watch()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?
+            .into_stream()
+            .map(|next| {
+                let (event, log) = next.map_err(|e| Status::internal(e.to_string()))?;
+                Ok(proto::TaskUpdate {
+                    task_id: event.taskId.to_string(),
+                    protocol: bytes32_to_string(&event.protocol).unwrap_or_default(),
+                    status_before: status_name(event.statusBefore),
+                    status_after: status_name(event.statusAfter),
+                    block_number: log.block_number.unwrap_or_default(),
+                    tx_hash: log.transaction_hash.unwrap_or_default().to_string(),
+                })
+            });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn node_status(
+        &self,
+        _request: Request<proto::NodeStatusRequest>,
+    ) -> Result<Response<proto::NodeStatusResponse>, Status> {
+        self.status().await.map(Response::new)
+    }
+
+    async fn set_maintenance(
+        &self,
+        request: Request<proto::SetMaintenanceRequest>,
+    ) -> Result<Response<proto::NodeStatusResponse>, Status> {
+        self.node
+            .config
+            .serve_gate
+            .set_maintenance(request.into_inner().enabled);
+
+        self.status().await.map(Response::new)
+    }
+}
+
+impl OracleService {
+    /// Returns the status of the node.
+    async fn status(&self) -> Result<proto::NodeStatusResponse, Status> {
+        let chain_id = self
+            .node
+            .provider
+            .get_chain_id()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+        let gate = &self.node.config.serve_gate;
+        let tasks_in_progress = self
+            .node
+            .config
+            .max_concurrent_tasks
+            .saturating_sub(self.node.capacity.available_permits());
+
+        Ok(proto::NodeStatusResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            address: self.node.address().to_string(),
+            chain_id,
+            coordinator: self.node.coordinator.address().to_string(),
+            kinds: self.node.kinds.iter().map(|k| k.to_string()).collect(),
+            models: self
+                .node
+                .workflows
+                .models
+                .iter()
+                .map(|(_, m)| m.to_string())
+                .collect(),
+            maintenance: gate.is_maintenance(),
+            serving: gate.is_open(),
+            tasks_in_progress: tasks_in_progress as u64,
+            operator: self.node.config.operator_tag.clone().unwrap_or_default(),
+            rejected_inputs: self.node.config.input_limits.rejected(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorize() {
+        let token = "a".repeat(MIN_TOKEN_LEN);
+        let request = |authorization: Option<&str>| {
+            let mut request = Request::new(());
+            if let Some(authorization) = authorization {
+                request
+                    .metadata_mut()
+                    .insert("authorization", authorization.parse().unwrap());
+            }
+            request
+        };
+
+        assert!(authorize(request(None), None).is_ok());
+        assert!(authorize(request(Some(&format!("Bearer {}", token))), Some(&token)).is_ok());
+        assert!(authorize(request(Some(&token)), Some(&token)).is_err());
+        assert!(authorize(request(Some("Bearer wrong")), Some(&token)).is_err());
+        assert!(authorize(request(None), Some(&token)).is_err());
+    }
+}
@@ -1,14 +1,57 @@
 #![doc = include_str!("../../README.md")]
 
 mod cli;
-pub use cli::{handle_command, Cli};
+pub use cli::{handle_command, handle_offline_command, Cli};
 
 mod node;
-pub use node::DriaOracle;
+pub use node::{
+    DriaOracle, PriceFeed, RateLimiter, RemoteSigner, SignerRequest, StoredContent, TaskCache,
+    TaskRequest, TaskResponse, TaskValidation, TaskView, UsdPrices,
+};
 
 /// Node configurations.
 mod configurations;
 pub use configurations::DriaOracleConfig;
 
+mod errors;
+pub use errors::{DriaOracleError, TxErrorClass};
+
+mod bridge;
+pub use bridge::{BridgeEvent, EventBridge};
+
+mod requests;
+pub use requests::{
+    ChatRequestBuilder, OracleRequest, SwanPurchaseRequestBuilder, WorkflowRequestBuilder,
+    DEFAULT_PROTOCOL,
+};
+
+mod logging;
+pub use logging::{LogRotation, RotatingLogFile};
+
+mod db;
+pub use db::{
+    ContractEvent, GasSpend, IndexFilter, IndexedEvent, IndexedResponse, IndexedTask,
+    IndexedValidation, MiningRecord, OracleDb, OutboxEntry, OutboxPayload, PeerReputation,
+    PeerResponse, PrunedTable, ResponseRecord, ResponseScope, RetentionPolicy, TaskCost,
+    UploadRecord, WalDecision, WalEntry,
+};
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+#[cfg(test)]
+mod testing;
+
 mod compute;
-pub use compute::{handle_generation, handle_request, handle_validation, mine_nonce};
+pub use compute::{
+    benchmark_hash_rate, benchmark_model, check_workflow, estimate_mining_time, expected_hashes,
+    handle_generation, handle_request, handle_validation, lint_workflow, mine_nonce,
+    AcceptancePolicy, ApiBudget, BuiltinPreset, ChatHistoryPolicy, ErrorClass, ExecutionTimes,
+    FailedAttempt, GasBudget, GenerationSample, GuardrailAction, GuardrailDecision, Guardrails,
+    HistoryCompression, InputLimits, LintIssue, LintSeverity, MiningStats, MockFixture, MockModel,
+    ModelBenchmark, ModelWarmup, NonceResult, OllamaAutoPull, OllamaClient, OllamaModel,
+    PolicyDecision, PolicySet, ProfitPolicy, QueueOrder, QueuePolicy, QueuedTask, RetryPolicy,
+    ScoreMap, ScoreMaps, SelfCheck, ServeGate, ServeWindow, Shard, SharedPolicy, TaskBudget,
+    TaskLock, TaskQueue, TaskQuote, TaskTimings, ToolSandbox, TraceId, ValidationPolicy, VramProbe,
+    VramReservation, WorkflowPresets, BUILTIN_PRESETS,
+};
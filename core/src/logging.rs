@@ -0,0 +1,241 @@
+//! Logging to a file that is rotated by the node itself, so that a long-running node does not fill
+//! the disk without relying on an external `logrotate`.
+//!
+//! The current logs are written to the given path, and a rotated file is renamed to the path with the
+//! time of its rotation as a suffix in seconds since the epoch, e.g. `oracle.log.1735689600`.
+
+use eyre::{eyre, Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Time to wait before rotating again after a failed rotation, so that a failure is not retried
+/// & reported for every line.
+const ROTATE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When the log file is rotated, and how many of the rotated files are kept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogRotation {
+    /// The file is rotated before it grows larger than this many bytes, omit to not limit the size.
+    pub max_size: Option<u64>,
+    /// The file is rotated once it is older than this, omit to not limit the age.
+    pub max_age: Option<Duration>,
+    /// Number of rotated files to keep, where the oldest ones are deleted.
+    pub max_files: usize,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_size: Some(100 * 1024 * 1024),
+            max_age: None,
+            max_files: 7,
+        }
+    }
+}
+
+/// A log file that is rotated by size & age, to be used as the target of the logger.
+#[derive(Debug)]
+pub struct RotatingLogFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    /// Size of the current file in bytes.
+    size: u64,
+    /// Time that the current file is started at.
+    started_at: SystemTime,
+    /// Time that the file can be rotated again at, after a failed rotation.
+    retry_at: Option<Instant>,
+}
+
+impl RotatingLogFile {
+    /// Opens the log file at the given path, appending to it if it exists.
+    pub fn open(path: impl Into<PathBuf>, rotation: LogRotation) -> Result<Self> {
+        let path = path.into();
+        if path.file_name().is_none() {
+            return Err(eyre!("Invalid log file: {}", path.display()));
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .wrap_err_with(|| format!("could not create log dir {}", dir.display()))?;
+        }
+
+        let file = open_append(&path)
+            .wrap_err_with(|| format!("could not open log file {}", path.display()))?;
+        let metadata = file.metadata()?;
+        Ok(Self {
+            size: metadata.len(),
+            started_at: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            path,
+            rotation,
+            file,
+            retry_at: None,
+        })
+    }
+
+    /// Returns `true` if the file should be rotated before writing the given number of bytes to it.
+    fn should_rotate(&self, len: usize) -> bool {
+        if self
+            .retry_at
+            .is_some_and(|retry_at| Instant::now() < retry_at)
+        {
+            return false;
+        }
+
+        let too_large = self
+            .rotation
+            .max_size
+            .is_some_and(|max_size| self.size > 0 && self.size + len as u64 > max_size);
+        let too_old = self.rotation.max_age.is_some_and(|max_age| {
+            self.started_at
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed >= max_age)
+        });
+
+        too_large || too_old
+    }
+
+    /// Renames the current file with the time of rotation as a suffix, starts a new one,
+    /// and deletes the oldest rotated files beyond the retention.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let now = SystemTime::now();
+        let mut rotated = self.rotated_files()?;
+        rotated.sort();
+        // the suffix is kept increasing even if the file is rotated more than once within a second
+        let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let secs = match rotated.last() {
+            Some((last, _)) => secs.max(last + 1),
+            None => secs,
+        };
+        let rotated_path = self
+            .path
+            .with_file_name(format!("{}.{}", file_name(&self.path), secs));
+        fs::rename(&self.path, &rotated_path)?;
+        rotated.push((secs, rotated_path));
+
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.started_at = now;
+
+        if rotated.len() > self.rotation.max_files {
+            for (_, path) in &rotated[..rotated.len() - self.rotation.max_files] {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the rotated files of the log file along with their rotation times.
+    fn rotated_files(&self) -> io::Result<Vec<(u64, PathBuf)>> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let prefix = format!("{}.", file_name(&self.path));
+
+        let mut rotated = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let secs = file_name(&path)
+                .strip_prefix(&prefix)
+                .and_then(|suffix| suffix.parse::<u64>().ok());
+            if let Some(secs) = secs {
+                rotated.push((secs, path));
+            }
+        }
+
+        Ok(rotated)
+    }
+}
+
+impl Write for RotatingLogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            // logging should go on even if the rotation fails, e.g. when the disk is full
+            match self.rotate() {
+                Ok(()) => self.retry_at = None,
+                Err(e) => {
+                    eprintln!(
+                        "Could not rotate log file {}, retrying in {}s: {}",
+                        self.path.display(),
+                        ROTATE_RETRY_INTERVAL.as_secs(),
+                        e
+                    );
+                    self.retry_at = Some(Instant::now() + ROTATE_RETRY_INTERVAL);
+                }
+            }
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Opens the file at the given path for appending, creating it if it does not exist.
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Returns the file name of the path as a string.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    #[test]
+    fn test_rotating_log_file() {
+        let dir = TestDir::new("logs");
+        let path = dir.join("oracle.log");
+        let rotation = LogRotation {
+            max_size: Some(64),
+            max_age: None,
+            max_files: 2,
+        };
+        let mut file = RotatingLogFile::open(&path, rotation).unwrap();
+
+        // each line is 40 bytes, so every line after the first starts a new file
+        let line = format!("{}\n", "x".repeat(39));
+        for _ in 0..5 {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), line);
+        let rotated = file.rotated_files().unwrap();
+        assert_eq!(rotated.len(), 2);
+        for (_, rotated_path) in rotated {
+            assert_eq!(fs::read_to_string(rotated_path).unwrap(), line);
+        }
+
+        // the file is rotated by age as well
+        let rotation = LogRotation {
+            max_size: None,
+            max_age: Some(Duration::ZERO),
+            max_files: 2,
+        };
+        let mut file = RotatingLogFile::open(&path, rotation).unwrap();
+        assert!(file.should_rotate(1));
+
+        // a failed rotation is not retried for a while, while the lines are still written
+        fs::remove_file(&path).unwrap();
+        file.write_all(line.as_bytes()).unwrap();
+        assert!(file.retry_at.is_some());
+        assert!(!file.should_rotate(1));
+        file.write_all(line.as_bytes()).unwrap();
+    }
+}
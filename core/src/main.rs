@@ -1,30 +1,57 @@
+use std::io::Write;
 use std::time::Duration;
 
 use clap::Parser;
-use dria_oracle::{Cli, DriaOracle, DriaOracleConfig};
+use dria_oracle::{Cli, DriaOracle, DriaOracleConfig, RotatingLogFile, TraceId};
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     // default commands such as version and help exit at this point
-    let cli = Cli::parse();
-
     // read env w.r.t cli argument, defaults to `.env`
-    let dotenv_result = dotenvy::from_path(&cli.env);
+    let dotenv_result = dotenvy::from_path(&Cli::parse().env);
+    // parsed again, so that the arguments with an env variable can be given within the .env file as well
+    let cli = Cli::parse();
 
-    // init env logger
-    let log_level = match cli.debug {
-        true => log::LevelFilter::Debug,
-        false => log::LevelFilter::Info,
+    // init env logger, where the modules are limited by the maximum level instead of their filters
+    // if a level is given, so that it can be changed while serving; otherwise `RUST_LOG` is left as is
+    let log_level = cli.read_log_level()?;
+    let module_level = match log_level {
+        Some(_) => log::LevelFilter::Trace,
+        None => log::LevelFilter::Info,
     };
-    env_logger::builder()
-        .format_timestamp(Some(env_logger::TimestampPrecision::Millis))
+    let mut logger = env_logger::builder();
+    logger
+        // the lines logged while handling a task are tagged with the trace id of the attempt
+        .format(|buf, record| {
+            let level = buf.default_level_style(record.level());
+            write!(
+                buf,
+                "[{} {level}{:<5}{level:#} {}] ",
+                buf.timestamp_millis(),
+                record.level(),
+                record.target()
+            )?;
+            if let Some(trace_id) = TraceId::current() {
+                write!(buf, "[{}] ", trace_id)?;
+            }
+            writeln!(buf, "{}", record.args())
+        })
         .filter(None, log::LevelFilter::Off)
-        .filter_module("dria_oracle", log_level)
-        .filter_module("dkn_workflows", log_level)
-        .filter_module("dria_oracle_contracts", log_level)
-        .filter_module("dria_oracle_storage", log_level)
-        .parse_default_env()
-        .init();
+        .filter_module("dria_oracle", module_level)
+        .filter_module("dkn_workflows", module_level)
+        .filter_module("dria_oracle_contracts", module_level)
+        .filter_module("dria_oracle_client", module_level)
+        .filter_module("dria_oracle_storage", module_level)
+        .parse_default_env();
+    // logs are written to the file instead of stderr if given, which is rotated by the node itself
+    if let Some(log_file) = cli.log_file.clone() {
+        let file = RotatingLogFile::open(log_file, cli.log_rotation())?;
+        logger.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+    logger.init();
+    if let Some(log_level) = log_level {
+        log::set_max_level(log_level);
+    }
 
     // log about env usage after env logger init is executed
     match dotenv_result {
@@ -32,21 +59,33 @@ async fn main() -> eyre::Result<()> {
         Err(e) => eprintln!("Could not load .env file: {}", e),
     }
 
-    // read required env variables
-    let secret_key = Cli::read_secret_key()?;
-    let rpc_url = Cli::read_rpc_url()?;
-    let tx_timeout = Cli::read_tx_timeout()?;
+    // handle commands that do not need a node
+    if dria_oracle::handle_offline_command(&cli).await? {
+        return Ok(());
+    }
 
-    // create config
-    let config = DriaOracleConfig::new(&secret_key, rpc_url)?
-        .with_tx_timeout(Duration::from_secs(tx_timeout));
+    // create config from the env variables, see `DriaOracleConfig::new_from_env`
+    let mut config = DriaOracleConfig::new_from_env()?
+        .with_tx_timeout(Duration::from_secs(Cli::read_tx_timeout()?))
+        .with_data_dir(Cli::read_data_dir())
+        .with_spending_confirmation(!cli.yes);
+    // contract addresses given as arguments take precedence over the env
+    if let Some(coordinator_address) = cli.coordinator_address {
+        config = config.with_coordinator_address(coordinator_address);
+    }
+    if let Some(registry_address) = cli.registry_address {
+        config = config.with_registry_address(registry_address);
+    }
+    if let Some(token_address) = cli.token_address {
+        config = config.with_token_address(token_address);
+    }
 
     // create node
     let node = DriaOracle::new(config).await?;
     log::info!("{}", node);
 
     // handle cli command
-    dria_oracle::handle_command(cli.command, node).await?;
+    dria_oracle::handle_command(cli, node).await?;
 
     log::info!("Bye!");
     Ok(())
@@ -1,7 +1,9 @@
 //! Anvil-related utilities.
 //!
-//! This module is only available when the `anvil` feature is enabled,
-//! which is only expected to happen in tests.
+//! This module is only available when the `testing` feature is enabled (which the `anvil` feature enables
+//! as well), which is only expected to happen in tests. The helpers work for a node that spawns its own
+//! Anvil fork with the `anvil` feature, as well as for a node that connects to a fork through its RPC URL,
+//! e.g. the one spawned by the `dria-oracle-testing` harness.
 
 use super::DriaOracle;
 
@@ -10,10 +12,10 @@ use alloy::primitives::Address;
 use alloy::primitives::{utils::parse_ether, U256};
 use alloy::providers::ext::AnvilApi;
 use alloy::providers::{PendingTransactionBuilder, Provider, ProviderBuilder};
-use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
+use alloy::rpc::types::{anvil::ReorgOptions, TransactionReceipt, TransactionRequest};
 use alloy::signers::local::PrivateKeySigner;
 use alloy::transports::http::Http;
-use eyre::Result;
+use eyre::{eyre, Context, Result};
 use reqwest::{Client, Url};
 
 impl DriaOracle {
@@ -26,8 +28,19 @@ impl DriaOracle {
     ///
     /// If `fund` is not provided, 10K ETH is used.
     pub async fn anvil_new_funded_wallet(&self, fund: Option<U256>) -> Result<EthereumWallet> {
+        self.anvil_fund_wallet(PrivateKeySigner::random(), fund)
+            .await
+    }
+
+    /// Funds the given signer with the given `fund` amount, returns its wallet.
+    ///
+    /// If `fund` is not provided, 10K ETH is used.
+    pub async fn anvil_fund_wallet(
+        &self,
+        signer: PrivateKeySigner,
+        fund: Option<U256>,
+    ) -> Result<EthereumWallet> {
         let fund = fund.unwrap_or_else(|| parse_ether(Self::ANVIL_FUND_ETHER).unwrap());
-        let signer = PrivateKeySigner::random();
         self.provider
             .anvil_set_balance(signer.address(), fund)
             .await?;
@@ -35,6 +48,64 @@ impl DriaOracle {
         Ok(wallet)
     }
 
+    /// Generates a node with a random wallet, funded with 10K ETH and the given amount of WETH.
+    pub async fn anvil_new_funded_oracle(&self, weth: U256) -> Result<Self> {
+        self.anvil_funded_oracle(PrivateKeySigner::random(), weth)
+            .await
+    }
+
+    /// Generates a node with the given signer, funded with 10K ETH and the given amount of WETH.
+    pub async fn anvil_funded_oracle(&self, signer: PrivateKeySigner, weth: U256) -> Result<Self> {
+        let node = self.connect(self.anvil_fund_wallet(signer, None).await?);
+        node.deposit_weth(weth)
+            .await
+            .wrap_err("could not deposit fee tokens")?;
+
+        Ok(node)
+    }
+
+    /// Takes a snapshot of the chain state, returns its id.
+    ///
+    /// This allows an expensive setup (e.g. funding, registrations & whitelisting) to be done once,
+    /// and then restored before each test case with [`Self::anvil_restore`].
+    pub async fn anvil_snapshot(&self) -> Result<U256> {
+        let id = self.provider.evm_snapshot().await?;
+        log::debug!("Took Anvil snapshot {}", id);
+
+        Ok(id)
+    }
+
+    /// Reverts the chain state to the given snapshot, which is consumed by Anvil in doing so.
+    pub async fn anvil_revert(&self, snapshot: U256) -> Result<()> {
+        if !self.provider.evm_revert(snapshot).await? {
+            return Err(eyre!("could not revert to Anvil snapshot {}", snapshot));
+        }
+        log::debug!("Reverted to Anvil snapshot {}", snapshot);
+
+        Ok(())
+    }
+
+    /// Reverts the chain state to the given snapshot, and takes it again so that it can be restored later as well.
+    pub async fn anvil_restore(&self, snapshot: &mut U256) -> Result<()> {
+        self.anvil_revert(*snapshot).await?;
+        *snapshot = self.anvil_snapshot().await?;
+
+        Ok(())
+    }
+
+    /// Replaces the latest `depth` blocks with empty ones, dropping their transactions.
+    pub async fn anvil_reorg(&self, depth: u64) -> Result<()> {
+        self.provider
+            .anvil_reorg(ReorgOptions {
+                depth,
+                tx_block_pairs: Vec::new(),
+            })
+            .await?;
+        log::info!("Reorged the latest {} blocks of the fork.", depth);
+
+        Ok(())
+    }
+
     /// Whitelists a given address, impersonates the owner in doing so.
     pub async fn anvil_whitelist_registry(&self, address: Address) -> Result<TransactionReceipt> {
         let owner = self.registry.owner().call().await?._0;
@@ -66,7 +137,8 @@ impl DriaOracle {
         Ok(())
     }
 
-    /// Assumes that an Anvil instance is running already at the given port.
+    /// Sends a transaction from the given address by impersonating it, through a provider
+    /// without a wallet at [`Self::anvil_endpoint`].
     ///
     /// We use this due to the issue: https://github.com/alloy-rs/alloy/issues/1918
     #[inline]
@@ -75,7 +147,7 @@ impl DriaOracle {
         tx: TransactionRequest,
         from: Address,
     ) -> Result<PendingTransactionBuilder<Http<Client>, Ethereum>> {
-        let anvil = ProviderBuilder::new().on_http(Self::anvil_url());
+        let anvil = ProviderBuilder::new().on_http(self.anvil_endpoint());
 
         anvil.anvil_impersonate_account(from).await?;
         let pending_tx = anvil.send_transaction(tx.from(from)).await?;
@@ -84,6 +156,17 @@ impl DriaOracle {
         Ok(pending_tx)
     }
 
+    /// Returns the URL of the Anvil fork that this node is connected to, i.e. the one spawned by the node
+    /// with the `anvil` feature, or the RPC URL of the node otherwise.
+    #[inline]
+    pub fn anvil_endpoint(&self) -> Url {
+        #[cfg(feature = "anvil")]
+        return Self::anvil_url();
+
+        #[cfg(not(feature = "anvil"))]
+        return self.config.rpc_url.clone();
+    }
+
     /// Returns the spawned Anvil URL, can be used with `ProviderBuilder::new().on_http(url)`.
     #[inline(always)]
     pub fn anvil_url() -> Url {
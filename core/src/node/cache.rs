@@ -0,0 +1,164 @@
+use alloy::primitives::U256;
+use dria_oracle_contracts::OracleCoordinator::{requestsReturn, LLMOracleTaskResponse};
+use dria_oracle_contracts::TaskStatus;
+use eyre::Result;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use super::DriaOracle;
+
+/// Maximum number of entries within each cache, the oldest entries are evicted first.
+const CACHE_CAPACITY: usize = 512;
+
+/// A map with a maximum number of entries, evicting the oldest entry when it is full.
+#[derive(Debug)]
+struct BoundedMap<K, V> {
+    entries: HashMap<K, V>,
+    order: VecDeque<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> BoundedMap<K, V> {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.entries.insert(key.clone(), value).is_none() {
+            self.order.push_back(key);
+            if self.order.len() > CACHE_CAPACITY {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+/// Cache for task data that is immutable for a given task status, keyed by `(task_id, status)`.
+///
+/// - A request only changes its status, so it is immutable within a status.
+/// - Responses are only added while the task is pending generation, and their scores are
+///   only set when it is completed, so they are immutable within the other statuses.
+///
+/// The cache belongs to a single coordinator, as task ids are not unique across coordinators.
+#[derive(Debug)]
+pub struct TaskCache {
+    requests: Mutex<BoundedMap<(U256, u8), requestsReturn>>,
+    responses: Mutex<BoundedMap<(U256, u8), Vec<LLMOracleTaskResponse>>>,
+    /// Decimals of the fee token of the coordinator, which never change.
+    token_decimals: tokio::sync::OnceCell<u8>,
+}
+
+impl Default for TaskCache {
+    fn default() -> Self {
+        Self {
+            requests: Mutex::new(BoundedMap::new()),
+            responses: Mutex::new(BoundedMap::new()),
+            token_decimals: tokio::sync::OnceCell::new(),
+        }
+    }
+}
+
+impl DriaOracle {
+    /// Returns the request of a task that is expected to be at the given status,
+    /// using the cache if the request has been fetched before at that status.
+    pub async fn get_request_cached(
+        &self,
+        task_id: U256,
+        status: TaskStatus,
+    ) -> Result<requestsReturn> {
+        let key = (task_id, u8::from(status));
+        if let Some(request) = self
+            .cache
+            .requests
+            .lock()
+            .expect("cache lock poisoned")
+            .get(&key)
+        {
+            log::debug!("Using cached request of task {}", task_id);
+            return Ok(request);
+        }
+
+        // the request is stored with its actual status, which may have changed since
+        let request = self.coordinator.requests(task_id).call().await?;
+        self.cache
+            .requests
+            .lock()
+            .expect("cache lock poisoned")
+            .insert((task_id, request.status), request.clone());
+
+        Ok(request)
+    }
+
+    /// Returns the responses of a task that is expected to be at the given status,
+    /// using the cache if the responses can not change anymore at that status.
+    pub async fn get_responses_cached(
+        &self,
+        task_id: U256,
+        status: TaskStatus,
+    ) -> Result<Vec<LLMOracleTaskResponse>> {
+        // responses are still being added while pending generation
+        if matches!(status, TaskStatus::None | TaskStatus::PendingGeneration) {
+            return Ok(self.coordinator.getResponses(task_id).call().await?._0);
+        }
+
+        let key = (task_id, u8::from(status));
+        if let Some(responses) = self
+            .cache
+            .responses
+            .lock()
+            .expect("cache lock poisoned")
+            .get(&key)
+        {
+            log::debug!("Using cached responses of task {}", task_id);
+            return Ok(responses);
+        }
+
+        let responses = self.coordinator.getResponses(task_id).call().await?._0;
+        self.cache
+            .responses
+            .lock()
+            .expect("cache lock poisoned")
+            .insert(key, responses.clone());
+
+        Ok(responses)
+    }
+
+    /// Returns the decimals of the fee token, which are fetched once.
+    pub async fn get_token_decimals_cached(&self) -> Result<u8> {
+        self.cache
+            .token_decimals
+            .get_or_try_init(|| async {
+                Ok::<_, eyre::Report>(self.token.decimals().call().await?._0)
+            })
+            .await
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_map() {
+        let mut map = BoundedMap::<usize, usize>::new();
+        for i in 0..CACHE_CAPACITY + 1 {
+            map.insert(i, i);
+        }
+
+        // the oldest entry is evicted
+        assert_eq!(map.get(&0), None);
+        assert_eq!(map.get(&1), Some(1));
+        assert_eq!(map.get(&CACHE_CAPACITY), Some(CACHE_CAPACITY));
+        assert_eq!(map.entries.len(), CACHE_CAPACITY);
+    }
+}
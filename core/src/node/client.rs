@@ -0,0 +1,23 @@
+use alloy::primitives::U256;
+use dria_oracle_client::OracleClient;
+use eyre::Result;
+
+use super::{DriaOracle, DriaOracleProvider, DriaOracleTransport, TaskView};
+
+impl DriaOracle {
+    /// Maximum number of tasks to read within a single multicall, each task takes three calls.
+    pub const TASKS_PER_MULTICALL: usize = dria_oracle_client::TASKS_PER_MULTICALL;
+
+    /// Returns a read-only client for the coordinator, sharing the provider of this node.
+    #[inline]
+    pub fn client(&self) -> OracleClient<DriaOracleTransport, DriaOracleProvider> {
+        OracleClient::new(*self.coordinator.address(), self.provider.clone())
+    }
+
+    /// Get task info for the given task ids, in the same order.
+    ///
+    /// See [`OracleClient::get_tasks`].
+    pub async fn get_tasks(&self, task_ids: &[U256]) -> Result<Vec<TaskView>> {
+        self.client().get_tasks(task_ids).await
+    }
+}
@@ -0,0 +1,55 @@
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use alloy_chains::Chain;
+use eyre::{eyre, Result};
+use std::io::{IsTerminal, Write};
+
+use super::DriaOracle;
+
+impl DriaOracle {
+    /// Shows a summary of an action that moves tokens and asks for confirmation,
+    /// returns an error if it is not confirmed.
+    ///
+    /// Does nothing unless [`crate::DriaOracleConfig::confirm_spending`] is set, which is
+    /// the case for the CLI without `--yes`.
+    pub async fn confirm_spending(
+        &self,
+        action: &str,
+        amount: U256,
+        counterparty: Address,
+    ) -> Result<()> {
+        if !self.config.confirm_spending {
+            return Ok(());
+        }
+
+        let amount = self.to_token_balance(amount).await?;
+        let chain = Chain::from_id(self.provider.get_chain_id().await?);
+        log::info!(
+            "{}\n  Amount:       {}\n  Counterparty: {}\n  Chain:        {}",
+            action,
+            amount,
+            self.explorer.address(counterparty),
+            chain
+        );
+
+        if !std::io::stdin().is_terminal() {
+            return Err(eyre!(
+                "Confirmation is required but the input is not a terminal, use --yes to skip it."
+            ));
+        }
+        match prompt("Proceed? [y/N]")?.to_lowercase().as_str() {
+            "y" | "yes" => Ok(()),
+            _ => Err(eyre!("Cancelled.")),
+        }
+    }
+}
+
+/// Prompts a question on the terminal, and returns the trimmed answer.
+pub(crate) fn prompt(question: &str) -> Result<String> {
+    eprint!("{}: ", question);
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
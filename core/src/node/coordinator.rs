@@ -1,14 +1,21 @@
-use super::DriaOracle;
+use super::{DriaOracle, TaskResponse, TaskView};
+use crate::{
+    BridgeEvent, ContractEvent, DriaOracleError, IndexedEvent, MiningRecord, NonceResult,
+    OutboxPayload, ResponseRecord, TraceId, UploadRecord, WalDecision, WalEntry,
+};
 use alloy::eips::BlockNumberOrTag;
 use alloy::primitives::aliases::U40;
-use alloy::primitives::{Bytes, U256};
-use alloy::rpc::types::{Log, TransactionReceipt};
-use dria_oracle_contracts::string_to_bytes32;
-use eyre::{eyre, Result};
+use alloy::primitives::{Address, Bytes, TxHash, B256, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Log, TransactionReceipt};
+use alloy::sol_types::SolEvent;
+use dria_oracle_contracts::{string_to_bytes32, OracleKind};
+use dria_oracle_storage::{ArweaveReceipt, ArweaveStorage};
+use eyre::{eyre, Context, Result};
+use std::time::{Duration, Instant};
 
 use dria_oracle_contracts::OracleCoordinator::{
-    getFeeReturn, getResponsesReturn, getValidationsReturn, requestsReturn,
-    LLMOracleTaskParameters, StatusUpdate,
+    getFeeReturn, LLMOracleTaskParameters, Response, StatusUpdate, Validation,
 };
 
 impl DriaOracle {
@@ -32,34 +39,399 @@ impl DriaOracle {
             .coordinator
             .request(string_to_bytes32(protocol)?, input, models, parameters);
         let tx = self.send_with_gas_hikes(req).await?;
-        self.wait_for_tx(tx).await
+        Ok(self.wait_for_tx(tx).await?)
     }
 
     /// Responds to a generation request with the response, metadata, and a valid nonce.
+    ///
+    /// The response is simulated first, and is not sent at all if it would revert,
+    /// e.g. due to another oracle filling the task before us. If the transaction fails
+    /// afterwards, the response is saved to the outbox to be sent again later.
+    ///
+    /// With a remote signer, the response is submitted by the signer instead.
     pub async fn respond_generation(
         &self,
         task_id: U256,
         response: Bytes,
         metadata: Bytes,
         nonce: U256,
-    ) -> Result<TransactionReceipt> {
-        let req = self.coordinator.respond(task_id, nonce, response, metadata);
-        let tx = self.send_with_gas_hikes(req).await?;
-        self.wait_for_tx(tx).await
+    ) -> Result<TransactionReceipt, DriaOracleError> {
+        if let Some(signer) = &self.config.remote_signer {
+            let payload = OutboxPayload::Generator { output: response };
+            return self
+                .respond_with_signer(signer, task_id, payload, metadata, nonce)
+                .await;
+        }
+
+        self.submit_generation(task_id, response, metadata, nonce, true)
+            .await
+    }
+
+    /// Sends a generation response with our wallet, see [`Self::respond_generation`].
+    ///
+    /// A failed transaction is saved to the outbox only if `use_outbox` is set, so that a signer
+    /// that submits on behalf of a worker leaves the retries to the outbox of the worker.
+    pub(crate) async fn submit_generation(
+        &self,
+        task_id: U256,
+        response: Bytes,
+        metadata: Bytes,
+        nonce: U256,
+        use_outbox: bool,
+    ) -> Result<TransactionReceipt, DriaOracleError> {
+        let req = self
+            .coordinator
+            .respond(task_id, nonce, response.clone(), metadata.clone());
+        // only a revert is final, the RPC failures are left retryable for the callers
+        self.preflight(&req).await.map_err(|e| match e {
+            DriaOracleError::Contract(e) => DriaOracleError::Contract(format!(
+                "generation response for task {} would revert: {}",
+                task_id, e
+            )),
+            e => e,
+        })?;
+
+        self.log_decision(
+            task_id,
+            OracleKind::Generator,
+            WalDecision::Submitting { nonce },
+        )
+        .await;
+        let result = async {
+            let tx = self.send_with_gas_hikes(req).await?;
+            self.record_response(task_id, OracleKind::Generator, *tx.tx_hash())
+                .await;
+            self.wait_for_tx(tx).await
+        }
+        .await;
+        if let (Err(e), true) = (&result, use_outbox) {
+            let payload = OutboxPayload::Generator { output: response };
+            self.save_to_outbox(task_id, payload, metadata, nonce, e);
+        }
+
+        result
     }
 
     /// Responds to a validation request with the score, metadata, and a valid nonce.
-    #[inline]
+    ///
+    /// The response is simulated first, and is not sent at all if it would revert,
+    /// e.g. due to another oracle filling the task before us. If the transaction fails
+    /// afterwards, the response is saved to the outbox to be sent again later.
+    ///
+    /// With a remote signer, the response is submitted by the signer instead.
     pub async fn respond_validation(
         &self,
         task_id: U256,
         scores: Vec<U256>,
         metadata: Bytes,
         nonce: U256,
-    ) -> Result<TransactionReceipt> {
-        let req = self.coordinator.validate(task_id, nonce, scores, metadata);
-        let tx = self.send_with_gas_hikes(req).await?;
-        self.wait_for_tx(tx).await
+    ) -> Result<TransactionReceipt, DriaOracleError> {
+        if let Some(signer) = &self.config.remote_signer {
+            let payload = OutboxPayload::Validator { scores };
+            return self
+                .respond_with_signer(signer, task_id, payload, metadata, nonce)
+                .await;
+        }
+
+        self.submit_validation(task_id, scores, metadata, nonce, true)
+            .await
+    }
+
+    /// Sends a validation response with our wallet, see [`Self::respond_validation`].
+    ///
+    /// A failed transaction is saved to the outbox only if `use_outbox` is set, see [`Self::submit_generation`].
+    pub(crate) async fn submit_validation(
+        &self,
+        task_id: U256,
+        scores: Vec<U256>,
+        metadata: Bytes,
+        nonce: U256,
+        use_outbox: bool,
+    ) -> Result<TransactionReceipt, DriaOracleError> {
+        let req = self
+            .coordinator
+            .validate(task_id, nonce, scores.clone(), metadata.clone());
+        // only a revert is final, the RPC failures are left retryable for the callers
+        self.preflight(&req).await.map_err(|e| match e {
+            DriaOracleError::Contract(e) => DriaOracleError::Contract(format!(
+                "validation response for task {} would revert: {}",
+                task_id, e
+            )),
+            e => e,
+        })?;
+
+        self.log_decision(
+            task_id,
+            OracleKind::Validator,
+            WalDecision::Submitting { nonce },
+        )
+        .await;
+        let result = async {
+            let tx = self.send_with_gas_hikes(req).await?;
+            self.record_response(task_id, OracleKind::Validator, *tx.tx_hash())
+                .await;
+            self.wait_for_tx(tx).await
+        }
+        .await;
+        if let (Err(e), true) = (&result, use_outbox) {
+            let payload = OutboxPayload::Validator { scores };
+            self.save_to_outbox(task_id, payload, metadata, nonce, e);
+        }
+
+        result
+    }
+
+    /// Records a sent response transaction to the local ledger, before waiting for its receipt.
+    ///
+    /// A failure to record is only logged, as the response is already sent at this point.
+    pub(crate) async fn record_response(&self, task_id: U256, kind: OracleKind, tx_hash: TxHash) {
+        let record = ResponseRecord {
+            scope: self.response_scope(),
+            task_id,
+            kind: kind.to_string(),
+            tx_hash,
+        };
+        let write = self
+            .db
+            .write_blocking(move |db| db.insert_response_record(&record));
+        if let Err(e) = write.await {
+            log::warn!("Could not record response for task {}: {}", task_id, e);
+        }
+        self.log_decision(task_id, kind, WalDecision::Submitted { tx_hash })
+            .await;
+    }
+
+    /// Writes a decision made about a task to the write-ahead log, before acting on it.
+    ///
+    /// A failure to write is only logged, as the ledger still prevents responding twice.
+    pub(crate) async fn log_decision(
+        &self,
+        task_id: U256,
+        kind: OracleKind,
+        decision: WalDecision,
+    ) {
+        let entry = WalEntry {
+            scope: self.response_scope(),
+            task_id,
+            kind: kind.to_string(),
+            decision,
+            trace_id: TraceId::current(),
+            created_at: 0,
+        };
+        let write = self
+            .db
+            .write_blocking(move |db| db.insert_wal_entry(&entry));
+        if let Err(e) = write.await {
+            log::warn!("Could not log decision for task {}: {}", task_id, e);
+        }
+    }
+
+    /// Verifies the uploads made for a response to a task (see [`ArweaveStorage::verify_upload`]),
+    /// and records their receipts.
+    ///
+    /// If an upload is not retrievable in time, the response is saved to the outbox, where it is
+    /// held back until its uploads are retrievable (see [`Self::retry_outbox`]).
+    pub(crate) async fn verify_uploads(
+        &self,
+        arweave: &ArweaveStorage,
+        task_id: U256,
+        payload: &OutboxPayload,
+        metadata: &Bytes,
+        nonce: U256,
+        receipts: Vec<ArweaveReceipt>,
+    ) -> Result<(), DriaOracleError> {
+        let kind = payload.kind();
+        for receipt in receipts {
+            if let Err(e) = arweave.verify_upload(&receipt).await {
+                let e = DriaOracleError::storage(e);
+                self.save_to_outbox(task_id, payload.clone(), metadata.clone(), nonce, &e);
+                return Err(e);
+            }
+            self.record_upload(task_id, kind, receipt).await;
+        }
+
+        Ok(())
+    }
+
+    /// Records the receipt of a verified upload made for a task.
+    ///
+    /// A failure to record is only logged, as the upload is already made at this point.
+    async fn record_upload(&self, task_id: U256, kind: OracleKind, receipt: ArweaveReceipt) {
+        let record = UploadRecord {
+            task_id,
+            kind: kind.to_string(),
+            receipt,
+        };
+        let write = self
+            .db
+            .write_blocking(move |db| db.insert_upload_record(&record));
+        if let Err(e) = write.await {
+            log::warn!("Could not record upload for task {}: {}", task_id, e);
+        }
+    }
+
+    /// Returns the key of the task lock for a response of the given kind to this task, which is
+    /// scoped by the responder so that the nodes of different accounts do not block each other.
+    #[inline]
+    fn task_lock_key(&self, task_id: U256, kind: OracleKind) -> String {
+        format!(
+            "{}-{}-{}-{}",
+            self.coordinator.address(),
+            self.address(),
+            task_id,
+            kind
+        )
+    }
+
+    /// Tries to acquire the task lock for a response of the given kind to this task,
+    /// so that the other replicas of this node do not handle it at the same time.
+    ///
+    /// Returns `true` if there is no task lock configured.
+    pub async fn try_lock_task(&self, task_id: U256, kind: OracleKind) -> Result<bool> {
+        match &self.config.task_lock {
+            Some(lock) => lock.try_acquire(&self.task_lock_key(task_id, kind)).await,
+            None => Ok(true),
+        }
+    }
+
+    /// Releases the task lock acquired with [`Self::try_lock_task`].
+    ///
+    /// This is only called once the task is handled; the lock of a failed task is kept until it expires,
+    /// as its response may still be sent from the outbox, so that the other replicas do not respond too.
+    /// A failure to release is only logged, as the lock expires anyways.
+    pub async fn unlock_task(&self, task_id: U256, kind: OracleKind) {
+        if let Some(lock) = &self.config.task_lock {
+            if let Err(e) = lock.release(&self.task_lock_key(task_id, kind)).await {
+                log::warn!("Could not release the lock of task {}: {:#}", task_id, e);
+            }
+        }
+    }
+
+    /// Publishes the given event to the event bridge, if there is one.
+    pub(crate) fn publish_event(&self, event: BridgeEvent) {
+        if let Some(bridge) = &self.config.event_bridge {
+            bridge.publish(event);
+        }
+    }
+
+    /// Records the mining performance of a nonce mined for a task.
+    ///
+    /// A failure to record is only logged, as it does not affect the response.
+    pub(crate) async fn record_mining(
+        &self,
+        task_id: U256,
+        kind: OracleKind,
+        difficulty: u8,
+        result: &NonceResult,
+    ) {
+        self.mining_stats.record(result);
+        log::debug!(
+            "Mined nonce for task {} with difficulty {} in {}ms ({} hashes)",
+            task_id,
+            difficulty,
+            result.elapsed.as_millis(),
+            result.hashes
+        );
+
+        let record = MiningRecord {
+            task_id,
+            kind: kind.to_string(),
+            difficulty,
+            hashes: result.hashes,
+            elapsed_ms: result.elapsed.as_millis() as u64,
+        };
+        let write = self
+            .db
+            .write_blocking(move |db| db.insert_mining_record(&record));
+        if let Err(e) = write.await {
+            log::warn!("Could not record mining for task {}: {}", task_id, e);
+        }
+    }
+
+    /// Checks the local ledger to see if we have already submitted a response of the given kind
+    /// to this task, which is the case if the recorded transaction is pending or has succeeded.
+    ///
+    /// Reverted or dropped transactions do not count, so that the task can be retried.
+    pub async fn has_submitted_response(&self, task_id: U256, kind: OracleKind) -> Result<bool> {
+        let Some(record) =
+            self.db
+                .get_response_record(self.response_scope(), task_id, &kind.to_string())?
+        else {
+            return Ok(false);
+        };
+
+        if let Some(receipt) = self
+            .provider
+            .get_transaction_receipt(record.tx_hash)
+            .await?
+        {
+            return Ok(receipt.status());
+        }
+
+        // no receipt yet, the tx is pending if the node still knows about it
+        let is_pending = self
+            .provider
+            .get_transaction_by_hash(record.tx_hash)
+            .await?
+            .is_some();
+        Ok(is_pending)
+    }
+
+    /// Checks the coordinator to see if our address has responded to this task with the given kind.
+    ///
+    /// Unlike [`Self::has_submitted_response`], this does not rely on the local ledger.
+    pub async fn has_responded_on_chain(&self, task_id: U256, kind: OracleKind) -> Result<bool> {
+        let address = self.address();
+        let has_responded = match kind {
+            OracleKind::Generator => self
+                .coordinator
+                .getResponses(task_id)
+                .call()
+                .await?
+                ._0
+                .iter()
+                .any(|r| r.responder == address),
+            OracleKind::Validator => self
+                .coordinator
+                .getValidations(task_id)
+                .call()
+                .await?
+                ._0
+                .iter()
+                .any(|v| v.validator == address),
+        };
+
+        Ok(has_responded)
+    }
+
+    /// Waits until the transactions that our address has sent are no longer pending, i.e. they are
+    /// either mined or dropped, for at most the given timeout.
+    ///
+    /// Returns `false` if there are still pending transactions after the timeout.
+    pub async fn wait_for_pending_transactions(&self, timeout: Duration) -> Result<bool> {
+        let started = Instant::now();
+        loop {
+            let address = self.address();
+            let pending = self
+                .provider
+                .get_transaction_count(address)
+                .pending()
+                .await?;
+            let latest = self.provider.get_transaction_count(address).await?;
+            if pending <= latest {
+                return Ok(true);
+            }
+            if started.elapsed() >= timeout {
+                return Ok(false);
+            }
+
+            log::info!(
+                "Waiting for {} pending transactions of {}.",
+                pending - latest,
+                address
+            );
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
     }
 
     /// Get previous tasks within the range of blocks.
@@ -68,35 +440,177 @@ impl DriaOracle {
         from_block: impl Into<BlockNumberOrTag>,
         to_block: impl Into<BlockNumberOrTag>,
     ) -> Result<Vec<(StatusUpdate, Log)>> {
-        let tasks = self
+        self.client().get_tasks_in_range(from_block, to_block).await
+    }
+
+    /// Get task events within the range of blocks, querying at most [`Self::LOG_QUERY_CHUNK_SIZE`] blocks at once.
+    pub async fn get_tasks_in_range_chunked(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<(StatusUpdate, Log)>> {
+        let mut tasks = Vec::new();
+        let mut chunk_start = from_block;
+        while chunk_start <= to_block {
+            let chunk_end = to_block.min(chunk_start + Self::LOG_QUERY_CHUNK_SIZE - 1);
+            log::debug!(
+                "Querying task events in blocks {} - {}",
+                chunk_start,
+                chunk_end
+            );
+            tasks.extend(self.get_tasks_in_range(chunk_start, chunk_end).await?);
+            chunk_start = chunk_end + 1;
+        }
+
+        Ok(tasks)
+    }
+
+    /// Get all logs of the coordinator & its registry within the range of blocks,
+    /// querying at most [`Self::LOG_QUERY_CHUNK_SIZE`] blocks at once.
+    ///
+    /// The logs can be decoded to the status updates with [`Self::decode_status_updates`], and to the
+    /// other events with [`Self::decode_contract_events`], so that a range is scanned only once for both.
+    pub async fn get_contract_logs_in_range(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>> {
+        let addresses = vec![*self.coordinator.address(), *self.registry.address()];
+
+        let mut logs = Vec::new();
+        let mut chunk_start = from_block;
+        while chunk_start <= to_block {
+            let chunk_end = to_block.min(chunk_start + Self::LOG_QUERY_CHUNK_SIZE - 1);
+            let filter = Filter::new()
+                .address(addresses.clone())
+                .from_block(chunk_start)
+                .to_block(chunk_end);
+            logs.extend(self.provider.get_logs(&filter).await?);
+            chunk_start = chunk_end + 1;
+        }
+
+        Ok(logs)
+    }
+
+    /// Get the other events of the coordinator & its registry within the range of blocks, i.e.
+    /// everything but the status updates, see [`Self::get_contract_logs_in_range`].
+    pub async fn get_contract_events_in_range(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<IndexedEvent>> {
+        let logs = self
+            .get_contract_logs_in_range(from_block, to_block)
+            .await?;
+        Ok(self.decode_contract_events(&logs))
+    }
+
+    /// Decodes the status updates of the coordinator within the given logs.
+    pub fn decode_status_updates(&self, logs: &[Log]) -> Vec<(StatusUpdate, Log)> {
+        logs.iter()
+            .filter(|log| log.inner.address == *self.coordinator.address())
+            .filter_map(|log| {
+                let event = StatusUpdate::decode_log(&log.inner, true).ok()?.data;
+                Some((event, log.clone()))
+            })
+            .collect()
+    }
+
+    /// Decodes the other events of the coordinator & its registry within the given logs, i.e.
+    /// everything but the status updates.
+    ///
+    /// Logs that can not be decoded are skipped, e.g. the events of a newer implementation.
+    pub fn decode_contract_events(&self, logs: &[Log]) -> Vec<IndexedEvent> {
+        let coordinator = *self.coordinator.address();
+        let registry = *self.registry.address();
+
+        logs.iter()
+            .filter_map(|log| {
+                let event = ContractEvent::decode(&log.inner, registry)?;
+                Some(IndexedEvent {
+                    coordinator,
+                    contract: log.inner.address,
+                    block_number: log.block_number.unwrap_or_default(),
+                    log_index: log.log_index.unwrap_or_default(),
+                    tx_hash: log.transaction_hash.unwrap_or_default(),
+                    event,
+                })
+            })
+            .collect()
+    }
+
+    /// Get the status updates of a single task within the range of blocks.
+    pub async fn get_task_events(
+        &self,
+        task_id: U256,
+        from_block: impl Into<BlockNumberOrTag>,
+        to_block: impl Into<BlockNumberOrTag>,
+    ) -> Result<Vec<(StatusUpdate, Log)>> {
+        let events = self
             .coordinator
             .StatusUpdate_filter()
+            .topic1(B256::from(task_id))
             .from_block(from_block)
             .to_block(to_block)
             .query()
             .await?;
 
-        Ok(tasks)
+        Ok(events)
     }
 
-    /// Get task info for a given task id.
-    pub async fn get_task(
+    /// Get generation responses of a given responder within the range of blocks.
+    pub async fn get_responses_in_range(
         &self,
-        task_id: U256,
-    ) -> Result<(requestsReturn, getResponsesReturn, getValidationsReturn)> {
-        // check if task id is valid
-        if task_id.is_zero() {
-            return Err(eyre!("Task ID must be non-zero."));
-        } else if task_id >= self.coordinator.nextTaskId().call().await?._0 {
-            return Err(eyre!("Task with id {} has not been created yet.", task_id));
-        }
+        responder: Address,
+        from_block: impl Into<BlockNumberOrTag>,
+        to_block: impl Into<BlockNumberOrTag>,
+    ) -> Result<Vec<(Response, Log)>> {
+        let responses = self
+            .coordinator
+            .Response_filter()
+            .topic2(responder.into_word())
+            .from_block(from_block)
+            .to_block(to_block)
+            .query()
+            .await?;
+
+        Ok(responses)
+    }
+
+    /// Get validations of a given validator within the range of blocks.
+    pub async fn get_validations_in_range(
+        &self,
+        validator: Address,
+        from_block: impl Into<BlockNumberOrTag>,
+        to_block: impl Into<BlockNumberOrTag>,
+    ) -> Result<Vec<(Validation, Log)>> {
+        let validations = self
+            .coordinator
+            .Validation_filter()
+            .topic2(validator.into_word())
+            .from_block(from_block)
+            .to_block(to_block)
+            .query()
+            .await?;
 
-        // get task info
-        let request = self.coordinator.requests(task_id).call().await?;
-        let responses = self.coordinator.getResponses(task_id).call().await?;
-        let validations = self.coordinator.getValidations(task_id).call().await?;
+        Ok(validations)
+    }
 
-        Ok((request, responses, validations))
+    /// Get task info for a given task id.
+    ///
+    /// Contents that are stored externally are not downloaded, see [`TaskView::resolve_storage`].
+    pub async fn get_task(&self, task_id: U256) -> Result<TaskView> {
+        self.get_tasks(&[task_id])
+            .await?
+            .pop()
+            .ok_or_else(|| eyre!("Task {} could not be read.", task_id))
+    }
+
+    /// Get the best response of a task.
+    ///
+    /// See [`dria_oracle_client::OracleClient::get_task_best_response`].
+    pub async fn get_task_best_response(&self, task_id: U256) -> Result<TaskResponse> {
+        self.client().get_task_best_response(task_id).await
     }
 
     /// Get fee details for a given request setting.
@@ -106,14 +620,8 @@ impl DriaOracle {
         num_gens: u64,
         num_vals: u64,
     ) -> Result<getFeeReturn> {
-        let parameters = LLMOracleTaskParameters {
-            difficulty,
-            numGenerations: U40::from(num_gens),
-            numValidations: U40::from(num_vals),
-        };
-
-        let fees = self.coordinator.getFee(parameters).call().await?;
-
-        Ok(fees)
+        self.client()
+            .get_request_fee(difficulty, num_gens, num_vals)
+            .await
     }
 }
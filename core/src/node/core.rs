@@ -1,32 +1,83 @@
 use alloy::contract::CallBuilder;
-use alloy::hex::FromHex;
+use alloy::eips::eip2718::Encodable2718;
+use alloy::eips::BlockNumberOrTag;
 use alloy::providers::{PendingTransactionBuilder, WalletProvider};
-use alloy::transports::RpcError;
+use alloy::rpc::types::BlockTransactionsKind;
+use alloy::transports::{TransportError, TransportErrorKind};
 use alloy::{
-    network::EthereumWallet,
-    primitives::Address,
+    network::{Ethereum, EthereumWallet, ReceiptResponse},
+    primitives::{Address, TxHash, B256, U256},
     providers::{Provider, ProviderBuilder},
 };
 use alloy_chains::Chain;
 use dkn_workflows::{DriaWorkflowsConfig, Model, ModelProvider};
+use dria_oracle_contracts::OracleCoordinator::OracleCoordinatorInstance;
+use dria_oracle_contracts::OracleRegistry::OracleRegistryInstance;
 use dria_oracle_contracts::ERC20::ERC20Instance;
 use dria_oracle_contracts::{
-    contract_error_report, get_coordinator_address, OracleCoordinator, OracleKind, OracleRegistry,
-    TokenBalance, ERC20,
+    get_deployment_by_id, CoordinatorInterface, Explorer, OracleCoordinator, OracleKind,
+    OracleRegistry, TokenBalance, ERC1967_IMPLEMENTATION_SLOT, ERC20,
 };
 use eyre::{eyre, Context, Result};
-use std::env;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+
+#[cfg(not(feature = "anvil"))]
+use super::RateLimitedTransport;
+use super::{DriaOracleProvider, DriaOracleTransport, RateLimiter, TaskCache};
+use crate::compute::with_ollama_checks;
+use crate::{
+    DriaOracle, DriaOracleError, ExecutionTimes, GasBudget, GasSpend, MiningStats, OracleDb,
+    ResponseScope, SharedPolicy, TxErrorClass,
+};
+
+impl DriaOracle {
+    /// Default number of tasks that can be handled at the same time.
+    pub const DEFAULT_CAPACITY: usize = 1;
+
+    /// Model that all validations are executed with.
+    pub const VALIDATION_MODEL: Model = Model::GPT4o;
+
+    /// Number of blocks to query logs for at once, as RPCs usually limit the block range of log queries.
+    pub const LOG_QUERY_CHUNK_SIZE: u64 = 10_000;
+
+    /// Interval to check the health of the storage while serving.
+    pub const STORAGE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Interval to check the base fee against the gas budget while serving.
+    pub const GAS_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Interval to check whether the coordinator has been upgraded while serving.
+    pub const INTERFACE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+    /// Interval to check the serve windows & the maintenance mode while serving.
+    pub const SERVE_GATE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+    /// Interval to prune & compact the local database while serving, if a retention policy is given.
+    pub const DB_COMPACTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
-impl crate::DriaOracle {
     /// Creates a new Oracle node with the given private key and connected to the chain at the given RPC URL.
     ///
     /// If `anvil` feature is enabled, the node will connect to an Anvil fork of the chain.
     pub async fn new(config: crate::DriaOracleConfig) -> Result<Self> {
+        let rpc_limiter = Arc::new(match config.rpc_rate_limit {
+            Some((requests_per_second, burst)) => RateLimiter::new(requests_per_second, burst),
+            None => RateLimiter::unlimited(),
+        });
+
         #[cfg(not(feature = "anvil"))]
-        let provider = ProviderBuilder::new()
-            .with_recommended_fillers()
-            .wallet(config.wallet.clone())
-            .on_http(config.rpc_url.clone());
+        let provider = {
+            use alloy::transports::{http::Http, Transport};
+
+            let transport =
+                RateLimitedTransport::new(Http::new(config.rpc_url.clone()), rpc_limiter.clone());
+            let is_local = transport.guess_local();
+            ProviderBuilder::new()
+                .with_recommended_fillers()
+                .wallet(config.wallet.clone())
+                .on_client(alloy::rpc::client::RpcClient::new(transport, is_local))
+        };
 
         #[cfg(feature = "anvil")]
         let provider = ProviderBuilder::new()
@@ -37,46 +88,72 @@ impl crate::DriaOracle {
             });
 
         // fetch the chain id so that we can use the correct addresses
-        let chain = Chain::from_id(provider.get_chain_id().await?)
-            .named()
-            .ok_or_else(|| eyre!("expected a named chain"))?;
+        let chain_id = provider.get_chain_id().await?;
+        let chain = Chain::from_id(chain_id);
+        let deployment = get_deployment_by_id(chain_id);
 
         #[cfg(not(feature = "anvil"))]
         log::info!("Connected to {} network", chain);
         #[cfg(feature = "anvil")]
         log::info!("Connected to Anvil forked from {} network", chain);
 
-        // get coordinator address from static list or the environment
-        // (address within env can have 0x at the start, or not, does not matter)
-        // and then create the coordinator instance
-        let coordinator_address = if let Ok(addr) = env::var("COORDINATOR_ADDRESS") {
-            Address::from_hex(addr).wrap_err("could not parse coordinator address in env")?
-        } else {
-            get_coordinator_address(chain)?
+        // get coordinator address from the config or the known deployments,
+        // an unsupported chain is only allowed when the coordinator is given explicitly
+        let coordinator_address = match (config.coordinator_address, deployment) {
+            (Some(address), Some(_)) => address,
+            (Some(address), None) => {
+                log::warn!(
+                    "Chain {} is not a known deployment, using the given coordinator {}",
+                    chain,
+                    address
+                );
+                address
+            }
+            (None, Some(deployment)) => deployment.coordinator,
+            (None, None) => {
+                return Err(eyre!(
+                    "Chain {} is not supported, see `chains` command for supported chains or provide a coordinator address explicitly",
+                    chain
+                ))
+            }
         };
-        let coordinator = OracleCoordinator::new(coordinator_address, provider.clone());
+        let (coordinator, registry, token) = Self::contracts_from_coordinator(
+            coordinator_address,
+            (config.registry_address, config.token_address),
+            &provider,
+        )
+        .await?;
+        if let Some(expected_token) = deployment.and_then(|d| d.token) {
+            if *token.address() != expected_token {
+                log::warn!(
+                    "Fee token {} differs from the known token {} of {}",
+                    token.address(),
+                    expected_token,
+                    chain
+                );
+            }
+        }
 
-        // get registry address from the coordinator & create instance
-        let registry_address = coordinator
-            .registry()
-            .call()
-            .await
-            .wrap_err("could not get registry address from the coordinator")?
-            ._0;
-        let registry = OracleRegistry::new(registry_address, provider.clone());
+        let db = OracleDb::new(config.data_dir.clone());
 
-        // get token address from the coordinator & create instance
-        let token_address = coordinator
-            .feeToken()
-            .call()
-            .await
-            .wrap_err("could not get token address from the coordinator")?
-            ._0;
-        let token = ERC20::new(token_address, provider.clone());
+        // transactions on a local fork can not be found on the explorer
+        #[cfg(not(feature = "anvil"))]
+        let explorer = Explorer::from_chain_id(chain_id);
+        #[cfg(feature = "anvil")]
+        let explorer = Explorer::default();
 
         let node = Self {
+            cache: Arc::new(TaskCache::default()),
+            explorer,
+            rpc_limiter,
+            db,
+            policy: SharedPolicy::default(),
+            capacity: Arc::new(Semaphore::new(config.max_concurrent_tasks)),
+            execution_times: Arc::new(ExecutionTimes::default()),
+            mining_stats: Arc::new(MiningStats::default()),
             config,
             provider,
+            chain_id,
             token,
             coordinator,
             registry,
@@ -87,6 +164,74 @@ impl crate::DriaOracle {
         Ok(node)
     }
 
+    /// Creates a new node that uses the coordinator at the given address, along with its registry & token.
+    ///
+    /// Everything else such as the provider, wallet and configurations are shared with this node,
+    /// which allows serving multiple coordinator deployments within a single process.
+    pub async fn with_coordinator(&self, coordinator_address: Address) -> Result<Self> {
+        // the overrides of the registry & token belong to the main coordinator
+        let (coordinator, registry, token) =
+            Self::contracts_from_coordinator(coordinator_address, (None, None), &self.provider)
+                .await?;
+
+        Ok(Self {
+            provider: self.provider.clone(),
+            chain_id: self.chain_id,
+            cache: Arc::new(TaskCache::default()),
+            rpc_limiter: self.rpc_limiter.clone(),
+            explorer: self.explorer.clone(),
+            config: self.config.clone(),
+            kinds: self.kinds.clone(),
+            workflows: self.workflows.clone(),
+            db: self.db.clone(),
+            policy: self.policy.clone(),
+            capacity: self.capacity.clone(),
+            execution_times: self.execution_times.clone(),
+            mining_stats: self.mining_stats.clone(),
+            token,
+            coordinator,
+            registry,
+        })
+    }
+
+    /// Creates the coordinator instance at the given address, and the registry & token instances
+    /// that are read from the coordinator, w.r.t the given `(registry, token)` overrides.
+    ///
+    /// Each contract must have code at its address, so that a half-configured deployment
+    /// (e.g. a coordinator on another chain) fails here instead of at the first call.
+    async fn contracts_from_coordinator(
+        coordinator_address: Address,
+        (registry_override, token_override): (Option<Address>, Option<Address>),
+        provider: &DriaOracleProvider,
+    ) -> Result<(
+        OracleCoordinatorInstance<DriaOracleTransport, DriaOracleProvider, Ethereum>,
+        OracleRegistryInstance<DriaOracleTransport, DriaOracleProvider, Ethereum>,
+        ERC20Instance<DriaOracleTransport, DriaOracleProvider, Ethereum>,
+    )> {
+        ensure_code(provider, "coordinator", coordinator_address).await?;
+        let coordinator = OracleCoordinator::new(coordinator_address, provider.clone());
+
+        // get registry address from the coordinator & create instance
+        let registry_address = resolve_address(
+            "registry",
+            coordinator.registry().call().await.map(|r| r._0),
+            registry_override,
+        )?;
+        ensure_code(provider, "registry", registry_address).await?;
+        let registry = OracleRegistry::new(registry_address, provider.clone());
+
+        // get token address from the coordinator & create instance
+        let token_address = resolve_address(
+            "token",
+            coordinator.feeToken().call().await.map(|r| r._0),
+            token_override,
+        )?;
+        ensure_code(provider, "token", token_address).await?;
+        let token = ERC20::new(token_address, provider.clone());
+
+        Ok((coordinator, registry, token))
+    }
+
     /// Creates a new node that uses the given wallet as its signer.
     pub fn connect(&self, wallet: EthereumWallet) -> Self {
         // first, clone the provider and set the wallet
@@ -100,9 +245,18 @@ impl crate::DriaOracle {
 
         Self {
             provider,
+            chain_id: self.chain_id,
+            cache: self.cache.clone(),
+            rpc_limiter: self.rpc_limiter.clone(),
+            explorer: self.explorer.clone(),
             config: self.config.clone().with_wallet(wallet),
             kinds: self.kinds.clone(),
             workflows: self.workflows.clone(),
+            db: self.db.clone(),
+            policy: self.policy.clone(),
+            capacity: self.capacity.clone(),
+            execution_times: self.execution_times.clone(),
+            mining_stats: self.mining_stats.clone(),
             token,
             coordinator,
             registry,
@@ -112,7 +266,10 @@ impl crate::DriaOracle {
     /// Given the kinds and models, prepares the configurations for the oracle.
     ///
     /// - If `kinds` is empty, it will check the registrations and use them as kinds.
-    /// - If `models` is empty, gives an error.
+    /// - If `models` is empty, gives an error for generators, see [`required_models`].
+    ///
+    /// The requirements of each kind are checked independently, so that a validator-only node
+    /// checks its OpenAI model alone and skips the other services, e.g. probing Ollama.
     pub async fn prepare_oracle(
         &mut self,
         mut kinds: Vec<OracleKind>,
@@ -139,32 +296,79 @@ impl crate::DriaOracle {
             }
         }
 
-        // prepare model config & check services
+        // make sure the coordinator still matches the ABI of this node, as it is upgradable
+        match self.probe_coordinator_interface().await {
+            Ok((_, interface)) if interface.is_compatible() => {}
+            Ok((implementation, interface)) => {
+                if !self.config.allow_abi_mismatch {
+                    return Err(eyre!(
+                        "Coordinator implementation {} is incompatible with this version of the node ({}), please update the node or serve with --allow-abi-mismatch to handle the tasks that still match.",
+                        implementation,
+                        interface
+                    ))?;
+                }
+
+                log::error!(
+                    "Coordinator implementation {} is incompatible with this version of the node ({}), please update the node!",
+                    implementation,
+                    interface
+                );
+                kinds.retain(|kind| {
+                    let supported = interface.supports(*kind);
+                    if !supported {
+                        log::error!(
+                            "Tasks of {} can not be handled until the node is updated.",
+                            kind
+                        );
+                    }
+                    supported
+                });
+                if kinds.is_empty() {
+                    return Err(eyre!(
+                        "None of the kinds can be handled with the coordinator implementation {}.",
+                        implementation
+                    ))?;
+                }
+            }
+            Err(e) => log::warn!("Could not probe the coordinator interface: {:#}", e),
+        }
+
+        // prepare model config & check services, only for the models that the kinds use
+        let models = required_models(&kinds, models)?;
         let mut model_config = DriaWorkflowsConfig::new(models);
-        let ollama_config = model_config.ollama.clone();
-        model_config = model_config.with_ollama_config(
-            ollama_config
-                .with_min_tps(5.0)
-                .with_timeout(std::time::Duration::from_secs(150)),
-        );
-        model_config.check_services().await?;
+        if kinds.contains(&OracleKind::Generator) {
+            // the throughput of the local models only matters for the generations
+            model_config = with_ollama_checks(model_config, Duration::from_secs(150));
+        }
+        if self.config.mock_model.is_some() {
+            log::warn!("Using the mock model backend, the services of the models are not checked.");
+        } else if self.config.model_warmup.is_some() {
+            log::info!("Models are not checked before serving, each is warmed up when a task first requires it.");
+        } else {
+            model_config.check_services().await?;
+        }
         if model_config.models.is_empty() {
             return Err(eyre!("No models provided."))?;
         }
 
         // validator-specific checks here
         if kinds.contains(&OracleKind::Validator) {
-            // make sure we have GPT4o model
+            // make sure the validation model has passed the service checks
             if !model_config
                 .models
-                .contains(&(ModelProvider::OpenAI, Model::GPT4o))
+                .contains(&(ModelProvider::OpenAI, Self::VALIDATION_MODEL))
             {
-                return Err(eyre!("Validator must have GPT4o model."))?;
+                return Err(eyre!(
+                    "Validator must have {} model, which requires OPENAI_API_KEY.",
+                    Self::VALIDATION_MODEL
+                ))?;
             }
 
             // make sure node is whitelisted
             if !self.is_whitelisted(self.address()).await? {
-                return Err(eyre!("You are not whitelisted in the registry."))?;
+                return Err(eyre!(
+                    "You are not whitelisted in the registry, see `whitelist status` to apply and `whitelist watch` to wait for it."
+                ))?;
             }
         }
 
@@ -174,6 +378,54 @@ impl crate::DriaOracle {
         Ok(())
     }
 
+    /// Returns the implementation behind the coordinator proxy along with its missing interface,
+    /// see [`CoordinatorInterface::probe`]. The coordinator itself is probed if it is not a proxy.
+    ///
+    /// The views that the node reads are called as well, so that a change of their return types is
+    /// found, see [`CoordinatorInterface::check_return`]. They are called with the latest task, as
+    /// the arrays of an empty task would decode regardless of the types of their elements.
+    pub async fn probe_coordinator_interface(&self) -> Result<(Address, CoordinatorInterface)> {
+        let coordinator = *self.coordinator.address();
+        let slot = self
+            .provider
+            .get_storage_at(
+                coordinator,
+                U256::from_be_bytes(ERC1967_IMPLEMENTATION_SLOT.0),
+            )
+            .await?;
+        let implementation = match Address::from_word(B256::from(slot.to_be_bytes())) {
+            Address::ZERO => coordinator,
+            implementation => implementation,
+        };
+
+        let code = self.provider.get_code_at(implementation).await?;
+        if code.is_empty() {
+            return Err(eyre!(
+                "coordinator implementation {} has no code",
+                implementation
+            ));
+        }
+
+        let mut interface = CoordinatorInterface::probe(&code);
+        let coordinator = &self.coordinator;
+        interface.check_return("registry", coordinator.registry().call().await)?;
+        interface.check_return("feeToken", coordinator.feeToken().call().await)?;
+        let task_id = interface
+            .check_return("nextTaskId", coordinator.nextTaskId().call().await)?
+            .map_or(U256::ZERO, |r| r._0.saturating_sub(U256::from(1)));
+        interface.check_return("requests", coordinator.requests(task_id).call().await)?;
+        interface.check_return(
+            "getResponses",
+            coordinator.getResponses(task_id).call().await,
+        )?;
+        interface.check_return(
+            "getValidations",
+            coordinator.getValidations(task_id).call().await,
+        )?;
+
+        Ok((implementation, interface))
+    }
+
     /// Returns the native token (ETH) balance of a given address.
     #[inline]
     pub async fn get_native_balance(&self, address: Address) -> Result<TokenBalance> {
@@ -181,89 +433,434 @@ impl crate::DriaOracle {
         Ok(TokenBalance::new(balance, "ETH", None))
     }
 
-    /// Returns the address of the configured wallet.
+    /// Resolves a block tag to a block number, `earliest` is zero and other tags are the latest block.
+    pub async fn resolve_block_number(&self, block: BlockNumberOrTag) -> Result<u64> {
+        match block {
+            BlockNumberOrTag::Number(number) => Ok(number),
+            BlockNumberOrTag::Earliest => Ok(0),
+            _ => Ok(self.provider.get_block_number().await?),
+        }
+    }
+
+    /// Returns the base fee of the latest block, or the gas price for the chains without one.
+    pub async fn get_base_fee(&self) -> Result<u128> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Latest, BlockTransactionsKind::Hashes)
+            .await?
+            .ok_or_else(|| eyre!("latest block not found"))?;
+
+        match block.header.base_fee_per_gas {
+            Some(base_fee) => Ok(base_fee.into()),
+            None => Ok(self.provider.get_gas_price().await?),
+        }
+    }
+
+    /// Returns the address that the tasks are responded with, i.e. the address of the
+    /// remote signer if there is one, otherwise the address of the configured wallet.
     #[inline(always)]
     pub fn address(&self) -> Address {
+        match &self.config.remote_signer {
+            Some(signer) => signer.address,
+            None => self.wallet_address(),
+        }
+    }
+
+    /// Returns the scope of the records that this node makes in the local database,
+    /// i.e. the chain, the coordinator and the address that the tasks are responded with.
+    #[inline]
+    pub fn response_scope(&self) -> ResponseScope {
+        ResponseScope {
+            chain_id: self.chain_id,
+            coordinator: *self.coordinator.address(),
+            responder: self.address(),
+        }
+    }
+
+    /// Returns the address of the configured wallet, which the transactions are sent from.
+    #[inline(always)]
+    pub fn wallet_address(&self) -> Address {
         self.config.wallet.default_signer().address()
     }
 
     /// Waits for a transaction to be mined, returning the receipt.
+    ///
+    /// The gas fee of the transaction is recorded to the gas budget, if there is one.
     #[inline]
     pub async fn wait_for_tx<T, N>(
         &self,
         tx: PendingTransactionBuilder<T, N>,
-    ) -> Result<N::ReceiptResponse>
+    ) -> Result<N::ReceiptResponse, DriaOracleError>
     where
         T: alloy::transports::Transport + Clone,
         N: alloy::network::Network,
     {
         let tx_hash = *tx.tx_hash();
-        log::info!("Waiting for tx: {:?}", tx_hash);
+        log::info!("Waiting for tx: {}", self.explorer.tx(tx_hash));
         let receipt = tx
             .with_timeout(self.config.tx_timeout)
             .get_receipt()
             .await
-            .wrap_err(format!("tx {} failed", tx_hash))?;
+            .map_err(|e| DriaOracleError::Rpc(format!("tx {} failed: {}", tx_hash, e)))?;
+        if let Some(gas_budget) = &self.config.gas_budget {
+            let gas_fee =
+                U256::from(receipt.gas_used()) * U256::from(receipt.effective_gas_price());
+            gas_budget.record(gas_fee);
+            let spend = GasSpend {
+                chain_id: self.chain_id,
+                tx_hash,
+                gas_fee,
+                created_at: 0,
+            };
+            let write = self
+                .db
+                .write_blocking(move |db| db.insert_gas_spend(&spend));
+            if let Err(e) = write.await {
+                log::warn!("Could not record the gas fee of tx {}: {}", tx_hash, e);
+            }
+        }
+
         Ok(receipt)
     }
 
-    /// Given a request, retries sending it with increasing gas prices to avoid
-    /// the "tx underpriced" errors.
+    /// Restores the gas fees paid on this chain within the last day from the local database,
+    /// so that the daily gas budget is not reset by a restart.
+    pub fn restore_gas_budget(&self) -> eyre::Result<()> {
+        let Some(gas_budget) = &self.config.gas_budget else {
+            return Ok(());
+        };
+
+        let since = SystemTime::now()
+            .checked_sub(GasBudget::WINDOW)
+            .and_then(|since| since.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let spending = self.db.get_gas_spending(self.chain_id, since.as_secs())?;
+        gas_budget.restore(spending.iter().map(|spend| {
+            (
+                UNIX_EPOCH + Duration::from_secs(spend.created_at),
+                spend.gas_fee,
+            )
+        }));
+        log::debug!("Restored gas budget: {}", gas_budget);
+
+        Ok(())
+    }
+
+    /// Simulates the given request with `eth_estimateGas` using its exact payload,
+    /// so that we can avoid sending a transaction that would revert anyways.
+    ///
+    /// Returns the estimated gas, the decoded contract error if the call would revert, or an RPC error
+    /// if it could not be simulated at all.
     #[inline]
-    pub async fn send_with_gas_hikes<T, P, D, N>(
+    pub async fn preflight<T, P, D, N>(
         &self,
-        req: CallBuilder<T, P, D, N>,
-    ) -> Result<PendingTransactionBuilder<T, N>>
+        req: &CallBuilder<T, P, D, N>,
+    ) -> Result<u64, DriaOracleError>
     where
         T: alloy::transports::Transport + Clone,
         P: alloy::providers::Provider<T, N> + Clone,
         D: alloy::contract::CallDecoder + Clone,
         N: alloy::network::Network,
+    {
+        let gas = req.estimate_gas().await?;
+        log::debug!("Estimated gas: {}", gas);
+
+        Ok(gas)
+    }
+
+    /// Given a request, signs & sends it while handling the RPC errors w.r.t their [`TxErrorClass`]:
+    ///
+    /// - Underpriced transactions are retried with increasing gas prices.
+    /// - A nonce that is too low is synced with the pending nonce of the account, and retried.
+    /// - A transaction that is already known by the RPC is taken as sent.
+    /// - Rate-limited requests are retried after a backoff.
+    /// - Insufficient funds and other errors are returned as is.
+    #[inline]
+    pub async fn send_with_gas_hikes<P, D>(
+        &self,
+        req: CallBuilder<DriaOracleTransport, P, D, Ethereum>,
+    ) -> Result<PendingTransactionBuilder<DriaOracleTransport, Ethereum>, DriaOracleError>
+    where
+        P: alloy::providers::Provider<DriaOracleTransport, Ethereum> + Clone,
+        D: alloy::contract::CallDecoder + Clone,
     {
         // gas price hikes to try in increasing order, first is 0 to simply use the
         // initial gas fee for the first attempt
         const GAS_PRICE_HIKES: [u128; 4] = [0, 12, 24, 36];
+        // attempts in total, so that the other retryable errors do not retry forever
+        const MAX_SEND_ATTEMPTS: usize = 8;
 
-        // try and send tx, with increasing gas prices for few attempts
+        let mut req = req;
+        let mut hike_idx = 0;
         let initial_gas_price = self.provider.get_gas_price().await?;
-        for (attempt_no, increase_percentage) in GAS_PRICE_HIKES.iter().enumerate() {
+        for attempt_no in 0..MAX_SEND_ATTEMPTS {
             // set gas price
-            let gas_price = initial_gas_price + (initial_gas_price / 100) * increase_percentage;
+            let gas_price =
+                initial_gas_price + (initial_gas_price / 100) * GAS_PRICE_HIKES[hike_idx];
 
             // try to send tx with gas price
-            match req.clone().gas_price(gas_price).send().await {
-                // if all is well, we can return the tx
-                Ok(tx) => {
-                    return Ok(tx);
-                }
-                // if we get an RPC error; specifically, if the tx is underpriced, we try again with higher gas
-                Err(alloy::contract::Error::TransportError(RpcError::ErrorResp(err))) => {
-                    // TODO: kind of a code-smell, can we do better check here?
-                    if err.message.contains("underpriced") {
-                        log::warn!(
-                            "{} with gas {} in attempt {}",
-                            err.message,
-                            gas_price,
-                            attempt_no + 1,
-                        );
+            let (err, tx_hash) = match self.try_send(req.clone().gas_price(gas_price)).await {
+                Ok(tx) => return Ok(tx),
+                Err(err) => err,
+            };
 
-                        // wait just a little bit
-                        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            let class = TxErrorClass::classify(&err);
+            log::warn!(
+                "Sending tx failed ({}) with gas {} in attempt {}: {}",
+                class,
+                gas_price,
+                attempt_no + 1,
+                err
+            );
+            match class {
+                TxErrorClass::Underpriced => {
+                    hike_idx += 1;
+                    if hike_idx == GAS_PRICE_HIKES.len() {
+                        return Err(DriaOracleError::Rpc(
+                            "Failed all attempts send tx due to underpriced gas.".to_string(),
+                        ));
+                    }
 
-                        continue;
-                    } else {
-                        // otherwise let it be handled by the error report
-                        return Err(contract_error_report(
-                            alloy::contract::Error::TransportError(RpcError::ErrorResp(err)),
+                    // wait just a little bit
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                }
+                TxErrorClass::NonceTooLow => {
+                    let nonce = self
+                        .provider
+                        .get_transaction_count(self.wallet_address())
+                        .pending()
+                        .await?;
+                    log::info!("Resyncing the nonce to {}", nonce);
+                    req = req.nonce(nonce);
+                }
+                TxErrorClass::AlreadyKnown => {
+                    if let Some(tx_hash) = tx_hash {
+                        log::info!("Tx {} is already known, waiting for it.", tx_hash);
+                        return Ok(PendingTransactionBuilder::new(
+                            self.provider.root().clone(),
+                            tx_hash,
                         ));
                     }
+
+                    return Err(alloy::contract::Error::TransportError(err).into());
                 }
-                // if we get any other error, we report it
-                Err(err) => return Err(contract_error_report(err)),
-            };
+                TxErrorClass::RateLimited => {
+                    tokio::time::sleep(Duration::from_secs(1 << attempt_no.min(5))).await;
+                }
+                TxErrorClass::InsufficientFunds => {
+                    return Err(DriaOracleError::InsufficientFunds(self.wallet_address()));
+                }
+                // otherwise let it be handled by the error report
+                TxErrorClass::Other => {
+                    return Err(alloy::contract::Error::TransportError(err).into());
+                }
+            }
         }
 
         // all attempts failed
-        Err(eyre!("Failed all attempts send tx due to underpriced gas."))
+        Err(DriaOracleError::Rpc(format!(
+            "Failed all {} attempts to send tx.",
+            MAX_SEND_ATTEMPTS
+        )))
+    }
+
+    /// Fills & signs the given request, and sends it as a raw transaction.
+    ///
+    /// The hash of the transaction is known before it is sent, so that it is returned along with
+    /// the error if the transaction has been signed.
+    async fn try_send<P, D>(
+        &self,
+        req: CallBuilder<DriaOracleTransport, P, D, Ethereum>,
+    ) -> Result<
+        PendingTransactionBuilder<DriaOracleTransport, Ethereum>,
+        (TransportError, Option<TxHash>),
+    >
+    where
+        P: alloy::providers::Provider<DriaOracleTransport, Ethereum> + Clone,
+        D: alloy::contract::CallDecoder + Clone,
+    {
+        // the gas is estimated while filling, so reverts are caught here as well
+        let tx = self
+            .provider
+            .fill(req.into_transaction_request())
+            .await
+            .map_err(|err| (err, None))?;
+        let Some(envelope) = tx.as_envelope() else {
+            return Err((
+                TransportErrorKind::custom_str("transaction could not be signed"),
+                None,
+            ));
+        };
+
+        let tx_hash = *envelope.tx_hash();
+        self.provider
+            .send_raw_transaction(&envelope.encoded_2718())
+            .await
+            .map_err(|err| (err, Some(tx_hash)))
+    }
+}
+
+/// Makes sure that there is a contract at the given address, where `name` is used within the error.
+async fn ensure_code(provider: &DriaOracleProvider, name: &str, address: Address) -> Result<()> {
+    let code = provider
+        .get_code_at(address)
+        .await
+        .wrap_err_with(|| format!("could not get the code of the {} {}", name, address))?;
+    if code.is_empty() {
+        return Err(eyre!(
+            "There is no {} contract at {} on this chain, please check the contract addresses.",
+            name,
+            address
+        ));
+    }
+
+    Ok(())
+}
+
+/// Resolves the address of a contract from the one that is read from the coordinator and the
+/// given override, where the override is only used if the coordinator does not have the getter,
+/// i.e. the call has reverted or returned nothing.
+///
+/// RPC failures are returned as they are, so that a flaky RPC does not make an override that may be
+/// stale be used without being checked against the coordinator.
+fn resolve_address(
+    name: &str,
+    read: Result<Address, alloy::contract::Error>,
+    given: Option<Address>,
+) -> Result<Address> {
+    let read = read.map_err(DriaOracleError::from);
+    if let Err(DriaOracleError::Rpc(e)) = &read {
+        return Err(eyre!(
+            "could not get {} address from the coordinator: {}",
+            name,
+            e
+        ));
+    }
+
+    match (read, given) {
+        (Ok(read), Some(given)) if read != given => Err(eyre!(
+            "The given {} {} does not match the {} {} of the coordinator, the deployment may be half-configured.",
+            name,
+            given,
+            name,
+            read
+        )),
+        (Ok(read), _) => Ok(read),
+        (Err(e), Some(given)) => {
+            log::warn!(
+                "Could not read the {} from the coordinator ({}), using the given {}",
+                name,
+                e,
+                given
+            );
+            Ok(given)
+        }
+        (Err(e), None) => Err(eyre!(e).wrap_err(format!(
+            "could not get {} address from the coordinator",
+            name
+        ))),
+    }
+}
+
+/// Returns the models to prepare for the given kinds, out of the models given to serve.
+///
+/// - Generators serve all given models, so at least one is required.
+/// - Validators only use [`DriaOracle::VALIDATION_MODEL`], which must be given along with the models of a generator,
+///   and is used by default for a validator-only node. The other models of a validator-only node are
+///   skipped, so that their services are not checked at all.
+fn required_models(kinds: &[OracleKind], models: Vec<Model>) -> Result<Vec<Model>> {
+    if kinds.contains(&OracleKind::Generator) {
+        if models.is_empty() {
+            return Err(eyre!("Generator must have at least one model."));
+        }
+        if kinds.contains(&OracleKind::Validator) && !models.contains(&DriaOracle::VALIDATION_MODEL)
+        {
+            return Err(eyre!(
+                "Validator must have {} model.",
+                DriaOracle::VALIDATION_MODEL
+            ));
+        }
+
+        return Ok(models);
+    }
+
+    if kinds.contains(&OracleKind::Validator) {
+        for model in models
+            .iter()
+            .filter(|model| **model != DriaOracle::VALIDATION_MODEL)
+        {
+            log::warn!(
+                "Skipping model {}, as it is only used for generations.",
+                model
+            );
+        }
+        return Ok(vec![DriaOracle::VALIDATION_MODEL]);
+    }
+
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_models() {
+        use OracleKind::*;
+        let llama = Model::Llama3_1_8B;
+
+        // a validator-only node needs no models, and skips the ones for generations
+        assert_eq!(
+            required_models(&[Validator], vec![]).unwrap(),
+            vec![DriaOracle::VALIDATION_MODEL]
+        );
+        assert_eq!(
+            required_models(&[Validator], vec![llama.clone()]).unwrap(),
+            vec![DriaOracle::VALIDATION_MODEL]
+        );
+
+        // generators serve all given models
+        assert!(required_models(&[Generator], vec![]).is_err());
+        assert_eq!(
+            required_models(&[Generator], vec![llama.clone()]).unwrap(),
+            vec![llama.clone()]
+        );
+
+        // the validation model is not served for generations unless it is given
+        assert!(required_models(&[Generator, Validator], vec![llama.clone()]).is_err());
+        assert_eq!(
+            required_models(
+                &[Generator, Validator],
+                vec![llama.clone(), DriaOracle::VALIDATION_MODEL]
+            )
+            .unwrap(),
+            vec![llama, DriaOracle::VALIDATION_MODEL]
+        );
+    }
+
+    #[test]
+    fn test_resolve_address() {
+        let (read, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let unreadable = || Err(alloy::contract::Error::UnknownFunction("registry".into()));
+
+        assert_eq!(resolve_address("registry", Ok(read), None).unwrap(), read);
+        assert_eq!(
+            resolve_address("registry", Ok(read), Some(read)).unwrap(),
+            read
+        );
+        assert!(resolve_address("registry", Ok(read), Some(other)).is_err());
+        assert_eq!(
+            resolve_address("registry", unreadable(), Some(other)).unwrap(),
+            other
+        );
+        assert!(resolve_address("registry", unreadable(), None).is_err());
+
+        // a failing RPC is not taken as a coordinator without the getter
+        let rpc_error = || {
+            Err(alloy::contract::Error::TransportError(
+                alloy::transports::TransportErrorKind::custom_str("connection refused"),
+            ))
+        };
+        assert!(resolve_address("registry", rpc_error(), Some(other)).is_err());
     }
 }
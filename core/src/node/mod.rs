@@ -1,20 +1,42 @@
 use alloy::network::Ethereum;
 use dkn_workflows::DriaWorkflowsConfig;
-use dria_oracle_contracts::OracleKind;
+use dria_oracle_contracts::{Explorer, OracleKind};
 use dria_oracle_contracts::{OracleCoordinator, OracleRegistry, ERC20};
 
+mod client;
+mod confirm;
 mod coordinator;
 mod core;
+mod outbox;
 mod registry;
 mod token;
 
+mod cache;
+pub use cache::TaskCache;
+
+mod price;
+pub use price::{PriceFeed, UsdPrices};
+
+mod signer;
+pub(crate) use signer::{constant_time_eq, status_of, MIN_TOKEN_LEN};
+pub use signer::{RemoteSigner, SignerAddress, SignerError, SignerRequest};
+
+mod ratelimit;
+pub use ratelimit::{RateLimitedTransport, RateLimiter};
+
+pub use dria_oracle_client::{StoredContent, TaskRequest, TaskResponse, TaskValidation, TaskView};
+
 mod types;
 use types::*;
 
-#[cfg(feature = "anvil")]
+pub(crate) use confirm::prompt;
+
+#[cfg(feature = "testing")]
 mod anvil;
 
-use super::DriaOracleConfig;
+use super::{DriaOracleConfig, ExecutionTimes, MiningStats, OracleDb, SharedPolicy};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 pub struct DriaOracle {
     pub config: DriaOracleConfig,
     /// Contract addresses for the oracle, respects the connected chain.
@@ -29,10 +51,32 @@ pub struct DriaOracle {
         OracleRegistry::OracleRegistryInstance<DriaOracleTransport, DriaOracleProvider, Ethereum>,
     /// Underlying provider type.
     pub provider: DriaOracleProvider,
+    /// Chain id of the connected chain.
+    pub chain_id: u64,
+    /// Rate limiter of the RPC requests, shared by the nodes that share the provider.
+    ///
+    /// Transactions on a local fork are not limited, i.e. with the `anvil` feature.
+    pub rpc_limiter: Arc<RateLimiter>,
+    /// Block explorer of the connected chain, used to print links.
+    pub explorer: Explorer,
     /// Kinds of this oracle, i.e. `generator`, `validator`.
     pub kinds: Vec<OracleKind>,
     /// Workflows config, defines the available models & services.
     pub workflows: DriaWorkflowsConfig,
+    /// Cache for immutable task data of the coordinator.
+    pub cache: Arc<TaskCache>,
+    /// Local database, e.g. for task costs.
+    pub db: OracleDb,
+    /// Policy to decide whether a task should be handled, accepts all tasks if there is none.
+    pub policy: SharedPolicy,
+    /// Limits the number of tasks handled at the same time, shared by the nodes
+    /// served within the same process so that they share the model capacity.
+    pub capacity: Arc<Semaphore>,
+    /// Execution times of the handled tasks, used to skip tasks that can not be finished before
+    /// the configured deadline. Shared along with the capacity.
+    pub execution_times: Arc<ExecutionTimes>,
+    /// Hashes computed & time spent on mining nonces, shared along with the capacity.
+    pub mining_stats: Arc<MiningStats>,
 }
 
 impl std::fmt::Display for DriaOracle {
@@ -41,9 +85,9 @@ impl std::fmt::Display for DriaOracle {
           f,
           "Dria Oracle Node v{}\nOracle Address: {}\nRPC URL: {}\nCoordinator: {}\nTx timeout: {}s",
           env!("CARGO_PKG_VERSION"),
-          self.address(),
+          self.explorer.address(self.address()),
           self.config.rpc_url,
-          self.coordinator.address(),
+          self.explorer.address(*self.coordinator.address()),
           self.config.tx_timeout.map(|t| t.as_secs()).unwrap_or_default()
       )
     }
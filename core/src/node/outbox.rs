@@ -0,0 +1,168 @@
+use crate::{DriaOracle, DriaOracleError, OutboxEntry, OutboxPayload};
+use alloy::primitives::{Bytes, U256};
+use dria_oracle_contracts::{OracleKind, TaskStatus};
+use dria_oracle_storage::ArweaveStorage;
+use eyre::Result;
+use futures_util::{stream, StreamExt};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+impl DriaOracle {
+    /// Interval to retry the responses within the outbox while serving.
+    pub const OUTBOX_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+    /// Number of the responses within the outbox that are sent again at the same time.
+    pub const OUTBOX_RETRY_CONCURRENCY: usize = 4;
+
+    /// Saves a response whose transaction has failed to the outbox, so that it can be sent again
+    /// with [`Self::retry_outbox`].
+    ///
+    /// A failure to save is only logged, as the original error is returned to the caller anyways.
+    pub(crate) fn save_to_outbox(
+        &self,
+        task_id: U256,
+        payload: OutboxPayload,
+        metadata: Bytes,
+        nonce: U256,
+        error: &DriaOracleError,
+    ) {
+        let entry = OutboxEntry {
+            task_id,
+            scope: self.response_scope(),
+            payload,
+            metadata,
+            nonce,
+            error: format!("{:#}", error),
+            attempts: 1,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+
+        match self.db.insert_outbox_entry(entry) {
+            Ok(()) => log::warn!(
+                "Saved the response for task {} to the outbox, it will be retried.",
+                task_id
+            ),
+            Err(e) => log::error!(
+                "Could not save the response for task {} to the outbox: {:#}",
+                task_id,
+                e
+            ),
+        }
+    }
+
+    /// Sends the responses within the outbox of this node again, at most
+    /// [`Self::OUTBOX_RETRY_CONCURRENCY`] at a time.
+    ///
+    /// Responses that are no longer needed are removed without sending, i.e. when the task is no longer
+    /// pending for their kind or when we have already submitted a response to it. Responses whose uploads
+    /// are not retrievable yet are held back, and responses that fail again are kept, with their number
+    /// of attempts incremented.
+    ///
+    /// Returns the number of responses sent, and the number of responses remaining within the outbox.
+    pub async fn retry_outbox(&self) -> Result<(usize, usize)> {
+        let entries = self.db.get_outbox_entries(self.response_scope())?;
+        let mut results = stream::iter(entries)
+            .map(|entry| self.retry_outbox_entry(entry))
+            .buffer_unordered(Self::OUTBOX_RETRY_CONCURRENCY);
+
+        let mut sent = 0;
+        let mut remaining = 0;
+        while let Some(result) = results.next().await {
+            // if the chain can not be reached, there is no point in trying the rest
+            match result? {
+                Some(true) => sent += 1,
+                Some(false) => remaining += 1,
+                None => {}
+            }
+        }
+
+        Ok((sent, remaining))
+    }
+
+    /// Sends a response within the outbox again, see [`Self::retry_outbox`].
+    ///
+    /// Returns whether the response is sent, or `None` if it is no longer needed.
+    async fn retry_outbox_entry(&self, entry: OutboxEntry) -> Result<Option<bool>> {
+        let kind = entry.kind();
+        let pending_status = match kind {
+            OracleKind::Generator => TaskStatus::PendingGeneration,
+            OracleKind::Validator => TaskStatus::PendingValidation,
+        };
+
+        let request = self.coordinator.requests(entry.task_id).call().await?;
+        if request.status != u8::from(pending_status) {
+            log::info!(
+                "Dropping the {} response for task {} from the outbox, as the task is no longer {}.",
+                kind,
+                entry.task_id,
+                pending_status
+            );
+            self.db.remove_outbox_entry(&entry)?;
+            return Ok(None);
+        }
+        if self.has_submitted_response(entry.task_id, kind).await? {
+            log::info!(
+                "Dropping the {} response for task {} from the outbox, as it is already submitted.",
+                kind,
+                entry.task_id
+            );
+            self.db.remove_outbox_entry(&entry)?;
+            return Ok(None);
+        }
+
+        // a response is not sent before the data that its keys point to can be downloaded
+        let arweave = ArweaveStorage::new_readonly();
+        for id in entry.upload_ids() {
+            if !arweave.is_retrievable(&id).await? {
+                log::info!(
+                    "Holding back the {} response for task {} in the outbox, as its upload {} is not retrievable yet.",
+                    kind,
+                    entry.task_id,
+                    id
+                );
+                return Ok(Some(false));
+            }
+        }
+
+        log::info!(
+            "Retrying the {} response for task {} (attempt {}, last error: {})",
+            kind,
+            entry.task_id,
+            entry.attempts + 1,
+            entry.error
+        );
+        let result = match entry.payload.clone() {
+            OutboxPayload::Generator { output } => {
+                self.respond_generation(entry.task_id, output, entry.metadata.clone(), entry.nonce)
+                    .await
+            }
+            OutboxPayload::Validator { scores } => {
+                self.respond_validation(entry.task_id, scores, entry.metadata.clone(), entry.nonce)
+                    .await
+            }
+        };
+        match result {
+            Ok(receipt) => {
+                log::info!(
+                    "Sent the {} response for task {} from the outbox (tx: {})",
+                    kind,
+                    entry.task_id,
+                    self.explorer.tx(receipt.transaction_hash)
+                );
+                self.db.remove_outbox_entry(&entry)?;
+                Ok(Some(true))
+            }
+            Err(e) => {
+                log::warn!(
+                    "Could not send the {} response for task {} from the outbox: {:#}",
+                    kind,
+                    entry.task_id,
+                    e
+                );
+                Ok(Some(false))
+            }
+        }
+    }
+}
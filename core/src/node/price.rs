@@ -0,0 +1,244 @@
+use alloy::primitives::{utils::format_units, Address, U256};
+use dria_oracle_contracts::AggregatorV3;
+use eyre::{eyre, Context, Result};
+use reqwest::Url;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::DriaOracle;
+
+/// Chainlink feeds older than this are considered stale, as they are updated at least daily.
+const MAX_FEED_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// HTTP feeds that do not respond within this duration are given up on, as the prices are only for display.
+const HTTP_FEED_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Source of the price of an asset (ETH, or the fee token) in USD, to display the token & gas amounts in USD.
+///
+/// Parsed from an address for a Chainlink feed on the connected chain, or from an HTTP(S) URL
+/// that returns JSON, where the fragment of the URL (if any) is a JSON pointer to the price
+/// within the response, e.g. `https://example.com/price?ids=ethereum#/ethereum/usd`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceFeed {
+    /// A Chainlink USD feed, at the given address.
+    Chainlink(Address),
+    /// An HTTP endpoint, whose response is the price or has it at the pointer within the fragment.
+    Http(Url),
+}
+
+impl FromStr for PriceFeed {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(Self::Http(
+                Url::parse(s).wrap_err("could not parse price feed URL")?,
+            ))
+        } else {
+            Address::from_str(s)
+                .map(Self::Chainlink)
+                .map_err(|_| eyre!("Expected a feed address or an HTTP URL, got: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for PriceFeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Chainlink(address) => write!(f, "Chainlink feed {}", address),
+            Self::Http(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// Prices of ETH & the fee token in USD, where a price that is not available is `None`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UsdPrices {
+    pub eth: Option<f64>,
+    pub token: Option<f64>,
+}
+
+impl UsdPrices {
+    /// Returns the value of the given amount of wei in USD.
+    pub fn eth_usd(&self, wei: U256) -> Option<f64> {
+        self.eth.map(|price| to_units(wei, 18) * price)
+    }
+
+    /// Returns the value of the given amount of fee tokens in USD.
+    pub fn token_usd(&self, amount: U256, decimals: u8) -> Option<f64> {
+        self.token.map(|price| to_units(amount, decimals) * price)
+    }
+}
+
+/// Converts an amount to a float w.r.t the given decimals, e.g. wei to ether for 18 decimals.
+fn to_units(amount: U256, decimals: u8) -> f64 {
+    format_units(amount, decimals)
+        .ok()
+        .and_then(|units| units.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Parses the price within the response of an HTTP price feed, at the given JSON pointer if any.
+///
+/// The price can be a number or a string of a number, as some APIs return them as strings.
+fn parse_http_price(body: &str, pointer: Option<&str>) -> Result<f64> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).wrap_err("price feed did not return JSON")?;
+    let value = match pointer {
+        Some(pointer) => json
+            .pointer(pointer)
+            .ok_or_else(|| eyre!("price feed has no value at {}", pointer))?,
+        None => &json,
+    };
+
+    let price = match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| eyre!("price feed value is not a number: {}", value))?;
+    if !price.is_finite() || price <= 0.0 {
+        return Err(eyre!("price feed value is not a valid price: {}", price));
+    }
+
+    Ok(price)
+}
+
+impl DriaOracle {
+    /// Returns the prices of ETH & the fee token in USD w.r.t the configured price feeds.
+    ///
+    /// The fee token is priced with its own feed if there is one, otherwise it is priced as ETH
+    /// if it is WETH, and is not priced at all. Prices are only for display, so a feed that can
+    /// not be read is logged and its price is `None`.
+    pub async fn get_usd_prices(&self) -> UsdPrices {
+        let eth = self.get_eth_price().await;
+        let token = match &self.config.token_price_feed {
+            Some(feed) => self.read_price(feed).await,
+            None => {
+                let is_weth = self
+                    .token
+                    .symbol()
+                    .call()
+                    .await
+                    .is_ok_and(|symbol| symbol._0 == "WETH");
+                eth.filter(|_| is_weth)
+            }
+        };
+
+        UsdPrices { eth, token }
+    }
+
+    /// Returns the price of ETH in USD w.r.t the configured price feed, `None` if there is no feed
+    /// or it can not be read.
+    pub async fn get_eth_price(&self) -> Option<f64> {
+        match &self.config.price_feed {
+            Some(feed) => self.read_price(feed).await,
+            None => None,
+        }
+    }
+
+    /// Reads the price from the given feed, logs & returns `None` if it can not be read.
+    async fn read_price(&self, feed: &PriceFeed) -> Option<f64> {
+        match self.read_price_feed(feed).await {
+            Ok(price) => Some(price),
+            Err(e) => {
+                log::warn!("Could not read the price from {}: {:#}", feed, e);
+                None
+            }
+        }
+    }
+
+    /// Reads the price in USD from the given feed.
+    async fn read_price_feed(&self, feed: &PriceFeed) -> Result<f64> {
+        match feed {
+            PriceFeed::Chainlink(address) => {
+                let aggregator = AggregatorV3::new(*address, self.provider.clone());
+                let decimals = aggregator.decimals().call().await?._0;
+                let round = aggregator.latestRoundData().call().await?;
+
+                let updated_at = UNIX_EPOCH + Duration::from_secs(round.updatedAt.saturating_to());
+                if SystemTime::now()
+                    .duration_since(updated_at)
+                    .is_ok_and(|age| age > MAX_FEED_AGE)
+                {
+                    log::warn!(
+                        "Price feed {} has not been updated for over a day.",
+                        address
+                    );
+                }
+                if round.answer.is_negative() || round.answer.is_zero() {
+                    return Err(eyre!("feed has an invalid answer: {}", round.answer));
+                }
+
+                Ok(to_units(round.answer.into_raw(), decimals))
+            }
+            PriceFeed::Http(url) => {
+                let mut request_url = url.clone();
+                request_url.set_fragment(None);
+                let body = reqwest::Client::builder()
+                    .timeout(HTTP_FEED_TIMEOUT)
+                    .build()?
+                    .get(request_url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?;
+
+                parse_http_price(&body, url.fragment())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_price_feed() {
+        let address = "0x71041dddad3595F9CEd3DcCFBe3D1F4b0a16Bb70";
+        assert_eq!(
+            PriceFeed::from_str(address).unwrap(),
+            PriceFeed::Chainlink(Address::from_str(address).unwrap())
+        );
+        let feed =
+            PriceFeed::from_str("https://example.com/price?ids=ethereum#/ethereum/usd").unwrap();
+        assert!(matches!(&feed, PriceFeed::Http(url) if url.fragment() == Some("/ethereum/usd")));
+        assert!(PriceFeed::from_str("chainlink").is_err());
+    }
+
+    #[test]
+    fn test_parse_http_price() {
+        assert_eq!(parse_http_price("3000.5", None).unwrap(), 3000.5);
+        assert_eq!(
+            parse_http_price(r#"{"ethereum":{"usd":3000}}"#, Some("/ethereum/usd")).unwrap(),
+            3000.0
+        );
+        assert_eq!(
+            parse_http_price(r#"{"data":{"amount":"2999.99"}}"#, Some("/data/amount")).unwrap(),
+            2999.99
+        );
+        assert!(parse_http_price(r#"{"ethereum":{}}"#, Some("/ethereum/usd")).is_err());
+        assert!(parse_http_price(r#""abc""#, None).is_err());
+        assert!(parse_http_price("-1", None).is_err());
+    }
+
+    #[test]
+    fn test_usd_prices() {
+        let prices = UsdPrices {
+            eth: Some(2000.0),
+            token: None,
+        };
+        let half_ether = U256::from(500_000_000_000_000_000u64);
+        assert_eq!(prices.eth_usd(half_ether), Some(1000.0));
+        assert_eq!(prices.token_usd(U256::from(1_000_000), 6), None);
+
+        let prices = UsdPrices {
+            token: Some(1.0),
+            ..prices
+        };
+        assert_eq!(prices.token_usd(U256::from(1_500_000), 6), Some(1.5));
+    }
+}
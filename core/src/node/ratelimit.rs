@@ -0,0 +1,191 @@
+//! A client-side rate limiter for RPC requests, wrapped around the transport of the provider.
+//!
+//! Public RPC endpoints throttle aggressive clients, so all requests of a node (and the nodes
+//! that share its provider) go through a token bucket before they are sent.
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportFut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Number of throttled requests between each throttling warning.
+const WARN_EVERY_THROTTLES: u64 = 100;
+
+/// A token bucket rate limiter, allowing `requests_per_second` on average with bursts of `burst` requests.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Average number of requests per second, `None` for no limit.
+    requests_per_second: Option<f64>,
+    /// Maximum number of requests that can be made at once.
+    burst: f64,
+    /// Available tokens along with the time they were last refilled.
+    bucket: Mutex<(f64, Instant)>,
+    /// Total number of requests.
+    requests: AtomicU64,
+    /// Number of requests that had to wait.
+    throttled: AtomicU64,
+    /// Total waiting time of the throttled requests, in milliseconds.
+    throttled_millis: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter with the given average rate and burst size.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        Self {
+            requests_per_second: Some(requests_per_second).filter(|rps| *rps > 0.0),
+            burst,
+            bucket: Mutex::new((burst, Instant::now())),
+            requests: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            throttled_millis: AtomicU64::new(0),
+        }
+    }
+
+    /// Creates a rate limiter that does not limit at all, but still counts the requests.
+    pub fn unlimited() -> Self {
+        Self::new(0.0, 1)
+    }
+
+    /// Waits until a request can be made.
+    pub async fn acquire(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let Some(wait) = self.reserve(Instant::now()) else {
+            return;
+        };
+
+        let throttled = self.throttled.fetch_add(1, Ordering::Relaxed) + 1;
+        self.throttled_millis
+            .fetch_add(wait.as_millis() as u64, Ordering::Relaxed);
+        log::debug!("Throttling RPC request for {}ms", wait.as_millis());
+        if throttled % WARN_EVERY_THROTTLES == 0 {
+            log::warn!("RPC requests are being throttled: {}", self);
+        }
+
+        tokio::time::sleep(wait).await;
+    }
+
+    /// Takes a token from the bucket at the given time, returns the duration to wait
+    /// if there were no tokens available.
+    ///
+    /// Tokens can go negative so that the waiting requests are served in order.
+    fn reserve(&self, now: Instant) -> Option<Duration> {
+        let requests_per_second = self.requests_per_second?;
+
+        let mut bucket = self.bucket.lock().expect("rate limiter lock poisoned");
+        let (tokens, last_refill) = *bucket;
+        let elapsed = now.saturating_duration_since(last_refill).as_secs_f64();
+        let tokens = (tokens + elapsed * requests_per_second).min(self.burst) - 1.0;
+        *bucket = (tokens, now);
+
+        if tokens >= 0.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(-tokens / requests_per_second))
+        }
+    }
+
+    /// Returns the total number of requests.
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests that had to wait.
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total waiting time of the throttled requests.
+    pub fn throttled_duration(&self) -> Duration {
+        Duration::from_millis(self.throttled_millis.load(Ordering::Relaxed))
+    }
+}
+
+impl std::fmt::Display for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} requests, {} throttled for {:.1}s in total",
+            self.requests(),
+            self.throttled(),
+            self.throttled_duration().as_secs_f64()
+        )
+    }
+}
+
+/// A transport that waits for the rate limiter before each request of the inner transport.
+#[derive(Debug, Clone)]
+pub struct RateLimitedTransport<T> {
+    inner: T,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<T> RateLimitedTransport<T> {
+    /// Wraps the given transport with the rate limiter.
+    pub fn new(inner: T, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<T> tower::Service<RequestPacket> for RateLimitedTransport<T>
+where
+    T: tower::Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            limiter.acquire().await;
+            inner.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_reserve() {
+        let limiter = RateLimiter::new(10.0, 2);
+        let now = Instant::now();
+
+        // burst is available right away
+        assert_eq!(limiter.reserve(now), None);
+        assert_eq!(limiter.reserve(now), None);
+
+        // then each request waits for its own token, in order
+        let first = limiter.reserve(now).unwrap();
+        let second = limiter.reserve(now).unwrap();
+        assert!((first.as_secs_f64() - 0.1).abs() < 1e-6);
+        assert!((second.as_secs_f64() - 0.2).abs() < 1e-6);
+
+        // a second later the bucket is refilled up to the burst
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.reserve(later), None);
+
+        // unlimited never waits
+        let unlimited = RateLimiter::unlimited();
+        for _ in 0..100 {
+            assert_eq!(unlimited.reserve(now), None);
+        }
+    }
+}
@@ -2,10 +2,15 @@ use alloy::{primitives::Address, rpc::types::TransactionReceipt};
 use dria_oracle_contracts::{OracleKind, TokenBalance};
 use eyre::Result;
 
+use crate::DriaOracleError;
+
 impl crate::DriaOracle {
     /// Register the oracle with the registry.
     #[inline]
-    pub async fn register_kind(&self, kind: OracleKind) -> Result<TransactionReceipt> {
+    pub async fn register_kind(
+        &self,
+        kind: OracleKind,
+    ) -> Result<TransactionReceipt, DriaOracleError> {
         let req = self.registry.register(kind.into());
         let tx = self.send_with_gas_hikes(req).await?;
 
@@ -14,7 +19,10 @@ impl crate::DriaOracle {
 
     /// Unregister from the oracle registry.
     #[inline]
-    pub async fn unregister_kind(&self, kind: OracleKind) -> Result<TransactionReceipt> {
+    pub async fn unregister_kind(
+        &self,
+        kind: OracleKind,
+    ) -> Result<TransactionReceipt, DriaOracleError> {
         let req = self.registry.unregister(kind.into());
         let tx = self.send_with_gas_hikes(req).await?;
 
@@ -25,14 +33,19 @@ impl crate::DriaOracle {
     pub async fn get_registry_stake_amount(&self, kind: OracleKind) -> Result<TokenBalance> {
         let stake_amount = self.registry.getStakeAmount(kind.into()).call().await?._0;
 
-        // return the symbol as well
-        let token_symbol = self.token.symbol().call().await?._0;
+        self.to_token_balance(stake_amount).await
+    }
+
+    /// Returns the amount of tokens currently staked by the oracle for a given kind.
+    pub async fn get_registry_stake(&self, kind: OracleKind) -> Result<TokenBalance> {
+        let staked = self
+            .registry
+            .registrations(self.address(), kind.into())
+            .call()
+            .await?
+            .amount;
 
-        Ok(TokenBalance::new(
-            stake_amount,
-            token_symbol,
-            Some(*self.token.address()),
-        ))
+        self.to_token_balance(staked).await
     }
 
     /// Returns whether the oracle is registered as a given kind.
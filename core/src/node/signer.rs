@@ -0,0 +1,318 @@
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::rpc::types::TransactionReceipt;
+use dria_oracle_contracts::OracleKind;
+use eyre::{eyre, Context, Result};
+use reqwest::{header::CONTENT_TYPE, StatusCode, Url};
+use std::time::Duration;
+
+use crate::{DriaOracle, DriaOracleError, OutboxPayload, WalDecision};
+
+/// A response to be submitted by a remote signer, on behalf of a worker that executes the tasks.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SignerRequest {
+    /// Address of the coordinator that the task belongs to, must be the one of the signer.
+    pub coordinator: Address,
+    /// Task id.
+    pub task_id: U256,
+    /// Output or scores of the response.
+    #[serde(flatten)]
+    pub payload: OutboxPayload,
+    /// Metadata of the response.
+    pub metadata: Bytes,
+    /// Nonce that is mined for the address of the signer.
+    pub nonce: U256,
+}
+
+/// Maximum time to wait for the signer to submit a response, which covers the gas hikes & the
+/// receipt of the transaction, after which the response is saved to the outbox.
+const SIGNER_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Body of the error responses of the signer.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SignerError {
+    pub error: String,
+}
+
+/// Body of the address response of the signer.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SignerAddress {
+    pub address: Address,
+}
+
+/// A signer that holds the funded key & submits the responses, see the `signer` command.
+///
+/// A worker with a remote signer responds with the address of the signer, so that the box
+/// that executes the models & workflow tools never holds the key that is funded & registered.
+#[derive(Clone)]
+pub struct RemoteSigner {
+    /// Base URL of the signer API.
+    pub url: Url,
+    /// Address of the signer, which the tasks are responded with.
+    pub address: Address,
+    /// Bearer token to authenticate with the signer.
+    token: String,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the token is omitted on purpose, as the configs are logged at times
+        f.debug_struct("RemoteSigner")
+            .field("url", &self.url)
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RemoteSigner {
+    /// Connects to the signer at the given URL, and reads the address that it responds with.
+    ///
+    /// The token is sent as a bearer token, so an HTTP URL that is not on the loopback is warned about.
+    pub async fn connect(url: Url, token: String) -> Result<Self> {
+        if url.scheme() != "https" && !is_loopback(&url) {
+            log::warn!(
+                "Remote signer at {} is reached over plain HTTP, use HTTPS so that the token is not sent in plain text.",
+                url
+            );
+        }
+
+        let client = reqwest::Client::builder().timeout(SIGNER_TIMEOUT).build()?;
+        let response = client
+            .get(url.join("address")?)
+            .bearer_auth(&token)
+            .send()
+            .await
+            .wrap_err_with(|| format!("could not reach the remote signer at {}", url))?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(eyre!(
+                "remote signer has refused to connect ({}): {}",
+                status,
+                error_message(body)
+            ));
+        }
+        let SignerAddress { address } =
+            serde_json::from_str(&body).wrap_err("remote signer did not return an address")?;
+
+        Ok(Self {
+            url,
+            address,
+            token,
+            client,
+        })
+    }
+
+    /// Submits a response with the signer, and returns the receipt of its transaction.
+    async fn submit(&self, request: &SignerRequest) -> Result<TransactionReceipt, DriaOracleError> {
+        let body = serde_json::to_string(request)
+            .map_err(|e| DriaOracleError::Config(format!("could not encode response: {}", e)))?;
+        let url = self
+            .url
+            .join("respond")
+            .map_err(|e| DriaOracleError::Config(format!("invalid remote signer URL: {}", e)))?;
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| {
+                DriaOracleError::Rpc(format!("could not reach the remote signer: {}", e))
+            })?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            DriaOracleError::Rpc(format!("could not read the remote signer: {}", e))
+        })?;
+
+        if status.is_success() {
+            serde_json::from_str(&body).map_err(|e| {
+                DriaOracleError::Rpc(format!("remote signer returned an invalid receipt: {}", e))
+            })
+        } else {
+            Err(error_from_status(status, error_message(body), self.address))
+        }
+    }
+}
+
+/// Minimum length of the tokens that the APIs of the node are authenticated with, so that they can not be guessed.
+pub(crate) const MIN_TOKEN_LEN: usize = 32;
+
+/// Compares two byte strings in a time that does not depend on where they differ,
+/// so that a token can not be guessed byte by byte.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Returns `true` if the host of the URL is on the loopback, where plain HTTP is not seen by others.
+fn is_loopback(url: &Url) -> bool {
+    match url.host_str() {
+        Some("localhost") => true,
+        // IPv6 hosts are within brackets
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    }
+}
+
+/// Returns the status that the signer responds with for the given error, see [`error_from_status`].
+pub(crate) fn status_of(error: &DriaOracleError) -> StatusCode {
+    match error {
+        DriaOracleError::Contract(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        DriaOracleError::InsufficientFunds(_) => StatusCode::PAYMENT_REQUIRED,
+        DriaOracleError::Config(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+/// Converts an error response of the signer back to an error, so that the worker can tell
+/// a revert (which is not retried) apart from a failure of the signer or its RPC.
+fn error_from_status(status: StatusCode, message: String, signer: Address) -> DriaOracleError {
+    match status {
+        StatusCode::UNPROCESSABLE_ENTITY => DriaOracleError::Contract(message),
+        StatusCode::PAYMENT_REQUIRED => DriaOracleError::InsufficientFunds(signer),
+        StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED => DriaOracleError::Config(format!(
+            "remote signer has refused the response: {}",
+            message
+        )),
+        _ => DriaOracleError::Rpc(format!("remote signer ({}): {}", status, message)),
+    }
+}
+
+/// Returns the message within an error response of the signer, or the whole body otherwise.
+fn error_message(body: String) -> String {
+    serde_json::from_str::<SignerError>(&body)
+        .map(|e| e.error)
+        .unwrap_or(body)
+}
+
+impl DriaOracle {
+    /// Responds to a task with the remote signer, instead of sending the transaction with our wallet.
+    ///
+    /// The signer simulates the response before sending it as well, so a revert is not saved to
+    /// the outbox while the other failures are, e.g. when the signer can not be reached.
+    pub(crate) async fn respond_with_signer(
+        &self,
+        signer: &RemoteSigner,
+        task_id: U256,
+        payload: OutboxPayload,
+        metadata: Bytes,
+        nonce: U256,
+    ) -> Result<TransactionReceipt, DriaOracleError> {
+        let kind = match payload {
+            OutboxPayload::Generator { .. } => OracleKind::Generator,
+            OutboxPayload::Validator { .. } => OracleKind::Validator,
+        };
+        let request = SignerRequest {
+            coordinator: *self.coordinator.address(),
+            task_id,
+            payload,
+            metadata,
+            nonce,
+        };
+
+        log::info!(
+            "Submitting {} response for task {} with the remote signer.",
+            kind,
+            task_id
+        );
+        self.log_decision(task_id, kind, WalDecision::Submitting { nonce })
+            .await;
+        match signer.submit(&request).await {
+            Ok(receipt) => {
+                self.record_response(task_id, kind, receipt.transaction_hash)
+                    .await;
+                log::info!(
+                    "Response is sent: {}",
+                    self.explorer.tx(receipt.transaction_hash)
+                );
+                Ok(receipt)
+            }
+            Err(e) => {
+                if !matches!(e, DriaOracleError::Contract(_)) {
+                    let SignerRequest {
+                        payload, metadata, ..
+                    } = request;
+                    self.save_to_outbox(task_id, payload, metadata, nonce, &e);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signer_request() {
+        let request = SignerRequest {
+            coordinator: Address::repeat_byte(1),
+            task_id: U256::from(7),
+            payload: OutboxPayload::Validator {
+                scores: vec![U256::from(3)],
+            },
+            metadata: Bytes::new(),
+            nonce: U256::from(42),
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["kind"], "validator");
+        assert_eq!(
+            serde_json::from_value::<SignerRequest>(json).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn test_signer_errors() {
+        let signer = Address::repeat_byte(2);
+        for error in [
+            DriaOracleError::Contract("reverted".to_string()),
+            DriaOracleError::InsufficientFunds(signer),
+            DriaOracleError::Rpc("timeout".to_string()),
+        ] {
+            let status = status_of(&error);
+            let converted = error_from_status(status, error.to_string(), signer);
+            assert_eq!(converted.is_retryable(), error.is_retryable());
+            assert_eq!(
+                std::mem::discriminant(&converted),
+                std::mem::discriminant(&error)
+            );
+        }
+
+        assert_eq!(
+            error_message(r#"{"error":"unauthorized"}"#.to_string()),
+            "unauthorized"
+        );
+        assert_eq!(error_message("bad gateway".to_string()), "bad gateway");
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+
+    #[test]
+    fn test_is_loopback() {
+        for (url, expected) in [
+            ("http://localhost:8090", true),
+            ("http://127.0.0.1:8090", true),
+            ("http://[::1]:8090", true),
+            ("http://signer.internal:8090", false),
+            ("http://10.0.0.2:8090", false),
+        ] {
+            assert_eq!(is_loopback(&Url::parse(url).unwrap()), expected, "{}", url);
+        }
+    }
+}
@@ -1,20 +1,63 @@
 use super::DriaOracle;
+use crate::DriaOracleError;
+use alloy::eips::BlockNumberOrTag;
 use alloy::primitives::{Address, U256};
-use alloy::rpc::types::TransactionReceipt;
-use dria_oracle_contracts::TokenBalance;
-use eyre::Result;
+use alloy::rpc::types::{Log, TransactionReceipt};
+use dria_oracle_contracts::{TokenBalance, ERC20::Transfer, WETH};
+use eyre::{eyre, Result};
 
 impl DriaOracle {
     /// Returns the token balance of a given address.
     pub async fn get_token_balance(&self, address: Address) -> Result<TokenBalance> {
         let token_balance = self.token.balanceOf(address).call().await?._0;
+
+        self.to_token_balance(token_balance).await
+    }
+
+    /// Wraps an amount of fee tokens as a `TokenBalance`, along with the token symbol & decimals.
+    pub async fn to_token_balance(&self, amount: U256) -> Result<TokenBalance> {
         let token_symbol = self.token.symbol().call().await?._0;
+        let token_decimals = self.token.decimals().call().await?._0;
+
+        Ok(
+            TokenBalance::new(amount, token_symbol, Some(*self.token.address()))
+                .with_decimals(token_decimals),
+        )
+    }
+
+    /// Parses an amount given in token units, w.r.t the decimals of the fee token.
+    pub async fn parse_token_amount(&self, amount: &str) -> Result<U256> {
+        let decimals = self.token.decimals().call().await?._0;
+
+        TokenBalance::parse_amount(amount, decimals)
+    }
 
-        Ok(TokenBalance::new(
-            token_balance,
-            token_symbol,
-            Some(*self.token.address()),
-        ))
+    /// Returns the fee token as WETH, or an error if the fee token is not WETH.
+    async fn weth(
+        &self,
+    ) -> Result<WETH::WETHInstance<super::DriaOracleTransport, &super::DriaOracleProvider>> {
+        let symbol = self.token.symbol().call().await?._0;
+        if symbol != "WETH" {
+            return Err(eyre!("Fee token is {}, not WETH.", symbol));
+        }
+
+        Ok(WETH::new(*self.token.address(), &self.provider))
+    }
+
+    /// Wraps ETH to the fee token, calls `deposit` of the WETH contract.
+    pub async fn deposit_weth(&self, amount: U256) -> Result<TransactionReceipt> {
+        let weth = self.weth().await?;
+        let req = weth.deposit().value(amount);
+        let tx = self.send_with_gas_hikes(req).await?;
+        Ok(self.wait_for_tx(tx).await?)
+    }
+
+    /// Unwraps the fee token to ETH, calls `withdraw` of the WETH contract.
+    pub async fn withdraw_weth(&self, amount: U256) -> Result<TransactionReceipt> {
+        let weth = self.weth().await?;
+        let req = weth.withdraw(amount);
+        let tx = self.send_with_gas_hikes(req).await?;
+        Ok(self.wait_for_tx(tx).await?)
     }
 
     /// Transfer tokens from one address to another, calls `transferFrom` of the ERC20 contract.
@@ -25,14 +68,29 @@ impl DriaOracle {
         from: Address,
         to: Address,
         amount: U256,
-    ) -> Result<TransactionReceipt> {
+    ) -> Result<TransactionReceipt, DriaOracleError> {
         let req = self.token.transferFrom(from, to, amount);
         let tx = self.send_with_gas_hikes(req).await?;
         self.wait_for_tx(tx).await
     }
 
+    /// Transfer tokens of the caller to another address, calls `transfer` of the ERC20 contract.
+    pub async fn transfer(
+        &self,
+        to: Address,
+        amount: U256,
+    ) -> Result<TransactionReceipt, DriaOracleError> {
+        let req = self.token.transfer(to, amount);
+        let tx = self.send_with_gas_hikes(req).await?;
+        self.wait_for_tx(tx).await
+    }
+
     /// Approves the `spender` to spend `amount` tokens on behalf of the caller.
-    pub async fn approve(&self, spender: Address, amount: U256) -> Result<TransactionReceipt> {
+    pub async fn approve(
+        &self,
+        spender: Address,
+        amount: U256,
+    ) -> Result<TransactionReceipt, DriaOracleError> {
         let req = self.token.approve(spender, amount);
         let tx = self.send_with_gas_hikes(req).await?;
         self.wait_for_tx(tx).await
@@ -40,13 +98,29 @@ impl DriaOracle {
 
     /// Returns the allowance of a given `spender` address to spend tokens on behalf of `owner` address.
     pub async fn allowance(&self, owner: Address, spender: Address) -> Result<TokenBalance> {
-        let token_symbol = self.token.symbol().call().await?._0;
         let allowance = self.token.allowance(owner, spender).call().await?._0;
 
-        Ok(TokenBalance::new(
-            allowance,
-            token_symbol,
-            Some(*self.token.address()),
-        ))
+        self.to_token_balance(allowance).await
+    }
+
+    /// Returns the token transfers from `from` to `to` within the range of blocks.
+    pub async fn get_transfers_in_range(
+        &self,
+        from: Address,
+        to: Address,
+        from_block: impl Into<BlockNumberOrTag>,
+        to_block: impl Into<BlockNumberOrTag>,
+    ) -> Result<Vec<(Transfer, Log)>> {
+        let transfers = self
+            .token
+            .Transfer_filter()
+            .topic1(from.into_word())
+            .topic2(to.into_word())
+            .from_block(from_block)
+            .to_block(to_block)
+            .query()
+            .await?;
+
+        Ok(transfers)
     }
 }
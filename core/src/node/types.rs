@@ -12,7 +12,8 @@ use alloy::{
 };
 
 #[cfg(not(feature = "anvil"))]
-pub type DriaOracleTransport = alloy::transports::http::Http<alloy::transports::http::Client>;
+pub type DriaOracleTransport =
+    super::RateLimitedTransport<alloy::transports::http::Http<alloy::transports::http::Client>>;
 
 #[cfg(feature = "anvil")]
 pub type DriaOracleTransport = alloy::transports::BoxTransport;
@@ -0,0 +1,95 @@
+use alloy::primitives::Bytes;
+use dkn_workflows::Model;
+use dria_oracle_contracts::string_to_bytes;
+use eyre::Result;
+
+use super::{OracleRequest, DEFAULT_PROTOCOL};
+use crate::compute::ChatHistoryRequest;
+
+/// Builds a chat request, i.e. a plain message that is optionally a continuation of a previous task.
+///
+/// Without a history the message is sent as is, otherwise the output of the history task
+/// is given to the model as the previous turn of the conversation.
+#[derive(Debug, Clone)]
+pub struct ChatRequestBuilder {
+    content: String,
+    history_id: Option<usize>,
+    models: Vec<Model>,
+    protocol: String,
+}
+
+impl ChatRequestBuilder {
+    /// Creates a chat request with the given message.
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            history_id: None,
+            models: Vec::new(),
+            protocol: DEFAULT_PROTOCOL.to_string(),
+        }
+    }
+
+    /// Continue the conversation of the given task.
+    pub fn with_history(mut self, task_id: usize) -> Self {
+        self.history_id = Some(task_id);
+        self
+    }
+
+    /// Accept the given model, can be called multiple times.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.models.push(model);
+        self
+    }
+
+    /// Accept the given models.
+    pub fn with_models(mut self, models: impl IntoIterator<Item = Model>) -> Self {
+        self.models.extend(models);
+        self
+    }
+
+    /// Change the protocol of the request.
+    pub fn with_protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.protocol = protocol.into();
+        self
+    }
+
+    /// Encodes the request.
+    pub fn build(self) -> Result<OracleRequest> {
+        let input = match self.history_id {
+            Some(history_id) => Bytes::from(serde_json::to_vec(&ChatHistoryRequest {
+                history_id,
+                content: self.content,
+            })?),
+            None => string_to_bytes(self.content),
+        };
+
+        OracleRequest::new(self.protocol, input, &self.models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request() {
+        let request = ChatRequestBuilder::new("hello")
+            .with_model(Model::GPT4o)
+            .build()
+            .unwrap();
+        assert_eq!(request.input(), &Bytes::from("hello"));
+        assert_eq!(request.protocol(), DEFAULT_PROTOCOL);
+
+        let request = ChatRequestBuilder::new("and then?")
+            .with_history(42)
+            .with_model(Model::GPT4o)
+            .build()
+            .unwrap();
+        let chat = serde_json::from_slice::<ChatHistoryRequest>(request.input()).unwrap();
+        assert_eq!(chat.history_id, 42);
+        assert_eq!(chat.content, "and then?");
+
+        // models are required
+        assert!(ChatRequestBuilder::new("hello").build().is_err());
+    }
+}
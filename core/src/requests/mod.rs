@@ -0,0 +1,129 @@
+//! Typed builders for the requests of known protocols, so that the input, models & protocol
+//! are encoded the way the oracle nodes expect them.
+//!
+//! Each builder produces an [`OracleRequest`], whose task parameters can be changed afterwards:
+//!
+//! ```rust,ignore
+//! let request = ChatRequestBuilder::new("What is 2+2?")
+//!     .with_model(Model::GPT4o)
+//!     .build()?
+//!     .with_generations(2);
+//! let task_id = node.send_request(request).await?;
+//! ```
+
+use alloy::primitives::{Bytes, FixedBytes};
+use dkn_workflows::Model;
+use dria_oracle_contracts::{string_to_bytes, string_to_bytes32};
+use eyre::{eyre, Result};
+
+mod chat;
+pub use chat::ChatRequestBuilder;
+
+mod workflow;
+pub use workflow::WorkflowRequestBuilder;
+
+mod swan;
+pub use swan::SwanPurchaseRequestBuilder;
+
+/// Protocol of the requests that do not specify one.
+pub const DEFAULT_PROTOCOL: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// A request with its input, models & protocol encoded, ready to be sent to the coordinator.
+#[derive(Debug, Clone)]
+pub struct OracleRequest {
+    protocol: String,
+    input: Bytes,
+    models: Bytes,
+    /// Difficulty of the proof-of-work of the responses.
+    pub difficulty: u8,
+    /// Number of generations to request.
+    pub num_gens: u64,
+    /// Number of validations to request.
+    pub num_vals: u64,
+}
+
+impl OracleRequest {
+    /// Creates a request from an already encoded input, with a difficulty of 2 and one generation & validation.
+    ///
+    /// Returns an error if there are no models, or if the protocol does not fit into `bytes32`.
+    pub fn new(protocol: impl Into<String>, input: Bytes, models: &[Model]) -> Result<Self> {
+        let protocol = protocol.into();
+        string_to_bytes32(protocol.clone())?;
+        if models.is_empty() {
+            return Err(eyre!("A request must accept at least one model"));
+        }
+
+        let models = models
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        Ok(Self {
+            protocol,
+            input,
+            models: string_to_bytes(models),
+            difficulty: 2,
+            num_gens: 1,
+            num_vals: 1,
+        })
+    }
+
+    /// Change the difficulty of the proof-of-work of the responses.
+    pub fn with_difficulty(mut self, difficulty: u8) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// Change the number of generations to request.
+    pub fn with_generations(mut self, num_gens: u64) -> Self {
+        self.num_gens = num_gens;
+        self
+    }
+
+    /// Change the number of validations to request.
+    pub fn with_validations(mut self, num_vals: u64) -> Self {
+        self.num_vals = num_vals;
+        self
+    }
+
+    /// Protocol of the request, e.g. `swan-agent-purchase/0.1.0`.
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    /// Protocol of the request as `bytes32`.
+    pub fn protocol_bytes(&self) -> FixedBytes<32> {
+        // length is checked when the request is created
+        string_to_bytes32(self.protocol.clone()).expect("protocol should fit into bytes32")
+    }
+
+    /// Input of the request, before it is uploaded to the storage if it is too large.
+    pub fn input(&self) -> &Bytes {
+        &self.input
+    }
+
+    /// Comma-separated models of the request.
+    pub fn models(&self) -> &Bytes {
+        &self.models
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oracle_request() {
+        let request = OracleRequest::new("foo/0.1.0", "bar".into(), &[Model::GPT4o])
+            .unwrap()
+            .with_generations(3);
+        assert_eq!(request.protocol(), "foo/0.1.0");
+        assert_eq!(request.models(), &Bytes::from(Model::GPT4o.to_string()));
+        assert_eq!(request.num_gens, 3);
+        assert_eq!(request.difficulty, 2);
+
+        assert!(OracleRequest::new("foo", "bar".into(), &[]).is_err());
+        assert!(OracleRequest::new("a".repeat(32), "bar".into(), &[Model::GPT4o]).is_err());
+    }
+}
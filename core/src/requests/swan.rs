@@ -0,0 +1,257 @@
+use alloy::primitives::{Address, Bytes, U256};
+use dkn_workflows::{Model, Workflow};
+use eyre::{eyre, Result};
+
+use super::OracleRequest;
+use crate::compute::{
+    PostProcess, SwanPurchasePostProcessor, SHOP_LIST_END_MARKER, SHOP_LIST_START_MARKER,
+};
+
+/// Version of the purchase protocol of the requests that do not specify one.
+const DEFAULT_SWAN_VERSION: &str = "0.1.0";
+
+/// Prompt of the purchase, up to the list of assets to buy.
+const PURCHASE_PROMPT_HEAD: &str = r#"You are a character in a 317-dimensional world. You just woke up to a new day. Your name is {{name}}.
+
+When you look at mirror as you wake up, you reflect on yourself and who you are. You are:
+{{backstory}}
+
+You remember vividly what drove you in your life. The whole purpose of your existence is given by the objective:
+{{objective}}
+
+To be strong and coherent, you repeat out loud how you behave in front of the mirror.
+{{behaviour}}
+
+As you recall who you are, what you do and your drive is, you write down in a notebook your current progress with your goal:
+{{state}}
+
+You look through and see the items in your inventory:
+{{inventory}}
+
+Today's budget is: {{budget}}
+
+You know you can't exceed your budget. You went to a marketplace and saw the following listed items:
+{{listings}}
+
+You think to yourself, "What should I buy today?"
+You think: "I should maximize the utility of my purchases while staying within my budget."
+You are also pretty good at detecting defects and fraudsters. You say to yourself: "I should stay away from fraudsters selling infeasible things, or overpriced items".
+
+You open your notebook and write down the items you want to buy in the following format:
+
+"#;
+
+/// Instructions of the list of assets to buy, between the markers of the post-processor.
+const PURCHASE_PROMPT_LIST: &str = r#"[All the addresses selected for purchasing as a list, separated by a new line. Put the list in brackets and write each address between quotation marks. For example: ["address_1", "address_2", ...]. Only write the list because the addresses will be parsed from the list and will be executed]"#;
+
+/// Prompt of the purchase, after the list of assets to buy.
+const PURCHASE_PROMPT_TAIL: &str = r#"
+
+<total_cost>
+[Insert the total cost of selected items]
+</total_cost>
+
+<reasoning>
+[Your reasoning behind each purchase, i.e. addressing how they align with its objective, current state, and budget constraints from your own words. Choose your words carefully to use a language reflecting the Agent's character. If no items were selected, explain why.]
+</reasoning>
+
+Ensure that you put both the opening and closing tags with respect to the format above.
+
+Write now:
+"#;
+
+/// Builds a purchase request of a Swan agent, which chooses the assets to buy from the listings
+/// within its budget.
+///
+/// The oracle responds with the ABI-encoded addresses of the chosen assets, and the raw output as metadata.
+/// From version `0.2.0` on, the total cost of the chosen assets is encoded along with the addresses.
+#[derive(Debug, Clone)]
+pub struct SwanPurchaseRequestBuilder {
+    name: String,
+    backstory: String,
+    objective: String,
+    behaviour: String,
+    state: String,
+    budget: U256,
+    listings: Vec<String>,
+    inventory: Vec<String>,
+    models: Vec<Model>,
+    version: String,
+}
+
+impl SwanPurchaseRequestBuilder {
+    /// Creates a purchase request for the agent with the given character.
+    pub fn new(
+        name: impl Into<String>,
+        backstory: impl Into<String>,
+        objective: impl Into<String>,
+        behaviour: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            backstory: backstory.into(),
+            objective: objective.into(),
+            behaviour: behaviour.into(),
+            state: String::new(),
+            budget: U256::ZERO,
+            listings: Vec::new(),
+            inventory: Vec::new(),
+            models: Vec::new(),
+            version: DEFAULT_SWAN_VERSION.to_string(),
+        }
+    }
+
+    /// Change the current state of the agent, i.e. its progress towards its objective.
+    pub fn with_state(mut self, state: impl Into<String>) -> Self {
+        self.state = state.into();
+        self
+    }
+
+    /// Change the budget of the agent, in wei.
+    pub fn with_budget(mut self, budget: U256) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Add an asset listed on the market with its price in wei, can be called multiple times.
+    pub fn with_listing(
+        mut self,
+        address: Address,
+        description: impl AsRef<str>,
+        price: U256,
+    ) -> Self {
+        self.listings.push(format!(
+            "Asset: {}, Price: {}, ETH Address {}",
+            description.as_ref(),
+            price,
+            address
+        ));
+        self
+    }
+
+    /// Add an asset within the inventory of the agent, can be called multiple times.
+    pub fn with_inventory(mut self, title: impl AsRef<str>, description: impl AsRef<str>) -> Self {
+        self.inventory.push(format!(
+            "Title: {}, Description {}",
+            title.as_ref(),
+            description.as_ref()
+        ));
+        self
+    }
+
+    /// Accept the given model, can be called multiple times.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.models.push(model);
+        self
+    }
+
+    /// Accept the given models.
+    pub fn with_models(mut self, models: impl IntoIterator<Item = Model>) -> Self {
+        self.models.extend(models);
+        self
+    }
+
+    /// Change the version of the purchase protocol, defaults to `0.1.0`.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Encodes the request, returns an error if there are no listings.
+    pub fn build(self) -> Result<OracleRequest> {
+        if self.listings.is_empty() {
+            return Err(eyre!("A purchase request must have at least one listing"));
+        }
+
+        let prompt = format!(
+            "{}{}\n{}\n{}{}",
+            PURCHASE_PROMPT_HEAD,
+            SHOP_LIST_START_MARKER,
+            PURCHASE_PROMPT_LIST,
+            SHOP_LIST_END_MARKER,
+            PURCHASE_PROMPT_TAIL
+        );
+        let workflow = serde_json::json!({
+            "config": { "max_steps": 50, "max_time": 200, "tools": ["ALL"] },
+            "external_memory": {
+                "name": self.name,
+                "backstory": self.backstory,
+                "objective": self.objective,
+                "behaviour": self.behaviour,
+                "state": self.state,
+                "budget": self.budget.to_string(),
+                "listings": self.listings,
+                "inventory": self.inventory,
+            },
+            "tasks": [
+                {
+                    "id": "buyout",
+                    "name": "Purchase",
+                    "description": "Decides which assets are to be purchased based on the given budget, story, and inventory.",
+                    "messages": [{ "role": "user", "content": prompt }],
+                    "operator": "generation",
+                    "inputs": [
+                        { "name": "name", "value": { "type": "read", "key": "name" }, "required": true },
+                        { "name": "behaviour", "value": { "type": "read", "key": "behaviour" }, "required": true },
+                        { "name": "listings", "value": { "type": "get_all", "key": "listings" }, "required": true },
+                        { "name": "state", "value": { "type": "read", "key": "state" }, "required": true },
+                        { "name": "inventory", "value": { "type": "get_all", "key": "inventory" }, "required": true },
+                        { "name": "budget", "value": { "type": "read", "key": "budget" }, "required": true },
+                        { "name": "objective", "value": { "type": "read", "key": "objective" }, "required": true },
+                        { "name": "backstory", "value": { "type": "read", "key": "backstory" }, "required": true }
+                    ],
+                    "outputs": [{ "type": "write", "key": "buy_list", "value": "__result" }]
+                },
+                {
+                    "id": "_end",
+                    "name": "end",
+                    "description": "End of the task",
+                    "messages": [{ "role": "user", "content": "End of the task" }],
+                    "operator": "end"
+                }
+            ],
+            "steps": [{ "source": "buyout", "target": "_end" }],
+            "return_value": { "input": { "type": "read", "key": "buy_list" }, "to_json": false }
+        });
+
+        // make sure that the oracle can parse the workflow
+        let input = serde_json::to_vec(&workflow)?;
+        serde_json::from_slice::<Workflow>(&input)?;
+
+        let protocol = format!("{}/{}", SwanPurchasePostProcessor::PROTOCOL, self.version);
+        OracleRequest::new(protocol, Bytes::from(input), &self.models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    #[test]
+    fn test_swan_purchase_request() {
+        let builder = SwanPurchaseRequestBuilder::new(
+            "Lucipher",
+            "A guardian of the cypherpunk ethos.",
+            "Collect the best cypherpunk artifact.",
+            "Enigmatic and secretive.",
+        )
+        .with_budget(U256::from(66600000000000000u64))
+        .with_model(Model::GPT4o);
+        assert!(builder.clone().build().is_err(), "must require a listing");
+
+        let request = builder
+            .with_listing(
+                address!("cB024CC466D4e6187e85f193c6022C8Df5320C51"),
+                "A self-replicating smart contract.",
+                U256::from(6000000000000000u64),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(request.protocol(), "swan-agent-purchase/0.1.0");
+
+        let input = String::from_utf8(request.input().to_vec()).unwrap();
+        assert!(input.contains(SHOP_LIST_START_MARKER));
+        assert!(input.contains("ETH Address 0xcB024CC466D4e6187e85f193c6022C8Df5320C51"));
+    }
+}
@@ -0,0 +1,144 @@
+use alloy::primitives::Bytes;
+use dkn_workflows::{Model, Workflow};
+use eyre::{eyre, Context, Result};
+use std::path::Path;
+
+use super::{OracleRequest, DEFAULT_PROTOCOL};
+use crate::check_workflow;
+use crate::compute::GenerationRequest;
+
+/// Builds a workflow request, which is executed by the oracle as is.
+#[derive(Debug)]
+pub struct WorkflowRequestBuilder {
+    workflow: Workflow,
+    models: Vec<Model>,
+    protocol: String,
+}
+
+impl WorkflowRequestBuilder {
+    /// Creates a request of the given workflow.
+    pub fn new(workflow: Workflow) -> Self {
+        Self {
+            workflow,
+            models: Vec::new(),
+            protocol: DEFAULT_PROTOCOL.to_string(),
+        }
+    }
+
+    /// Creates a request of the given workflow JSON, returns an error if it is not a valid workflow.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let workflow = serde_json::from_str::<Workflow>(json).wrap_err("invalid workflow")?;
+        Ok(Self::new(workflow))
+    }
+
+    /// Creates a request of the workflow within the given JSON file, which is linted & parsed the same way
+    /// that the oracle nodes parse the inputs, so that an invalid workflow is caught before the fee is paid.
+    ///
+    /// The issues of the workflow are logged, see [`check_workflow`].
+    pub async fn from_file(path: &Path) -> Result<Self> {
+        let json = tokio::fs::read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("could not read {}", path.display()))?;
+        check_workflow(&json).wrap_err_with(|| path.display().to_string())?;
+        let builder = Self::from_json(&json)
+            .wrap_err_with(|| format!("{} is not a valid workflow", path.display()))?;
+
+        // the nodes try the other request types first, so the workflow must not be mistaken for one
+        match GenerationRequest::try_parse_string(json).await {
+            GenerationRequest::Workflow(_) => Ok(builder),
+            request => Err(eyre!(
+                "{} would be executed as a {} request instead of a workflow",
+                path.display(),
+                request.request_type()
+            )),
+        }
+    }
+
+    /// Accept the given model, can be called multiple times.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.models.push(model);
+        self
+    }
+
+    /// Accept the given models.
+    pub fn with_models(mut self, models: impl IntoIterator<Item = Model>) -> Self {
+        self.models.extend(models);
+        self
+    }
+
+    /// Change the protocol of the request.
+    pub fn with_protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.protocol = protocol.into();
+        self
+    }
+
+    /// Encodes the request.
+    pub fn build(self) -> Result<OracleRequest> {
+        let input = Bytes::from(serde_json::to_vec(&self.workflow)?);
+        OracleRequest::new(self.protocol, input, &self.models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    const WORKFLOW: &str = r#"{
+        "config": { "max_steps": 10, "max_time": 50, "tools": [""] },
+        "tasks": [
+            {
+                "id": "A",
+                "name": "Generate",
+                "description": "Generates a haiku",
+                "operator": "generation",
+                "messages": [{ "role": "user", "content": "Write a haiku." }],
+                "outputs": [{ "type": "write", "key": "result", "value": "__result" }]
+            },
+            {
+                "id": "__end",
+                "operator": "end",
+                "messages": [{ "role": "user", "content": "End of the task" }]
+            }
+        ],
+        "steps": [{ "source": "A", "target": "__end" }],
+        "return_value": { "input": { "type": "read", "key": "result" } }
+    }"#;
+
+    #[test]
+    fn test_workflow_request() {
+        let request = WorkflowRequestBuilder::from_json(WORKFLOW)
+            .unwrap()
+            .with_model(Model::GPT4o)
+            .with_protocol("haiku/0.1.0")
+            .build()
+            .unwrap();
+        assert_eq!(request.protocol(), "haiku/0.1.0");
+
+        // the input is parsed back as a workflow by the oracle
+        assert!(serde_json::from_slice::<Workflow>(request.input()).is_ok());
+
+        assert!(WorkflowRequestBuilder::from_json("{}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_workflow_request_from_file() {
+        let dir = TestDir::new("workflow");
+
+        let path = dir.join("workflow.json");
+        std::fs::write(&path, WORKFLOW).unwrap();
+        assert!(WorkflowRequestBuilder::from_file(&path).await.is_ok());
+
+        // a workflow that the nodes would parse as a chat request is rejected
+        let mut workflow = serde_json::from_str::<serde_json::Value>(WORKFLOW).unwrap();
+        workflow["history_id"] = 1.into();
+        workflow["content"] = "Write another haiku.".into();
+        std::fs::write(&path, workflow.to_string()).unwrap();
+        let err = WorkflowRequestBuilder::from_file(&path).await.unwrap_err();
+        assert!(err.to_string().contains("instead of a workflow"));
+
+        assert!(WorkflowRequestBuilder::from_file(&dir.join("missing.json"))
+            .await
+            .is_err());
+    }
+}
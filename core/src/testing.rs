@@ -0,0 +1,37 @@
+//! Helpers for the tests of this crate.
+
+use std::path::{Path, PathBuf};
+
+/// A temporary directory for a test, which is unique to the process so that the tests of
+/// parallel runs do not share their files, and is removed when dropped.
+pub(crate) struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Creates an empty directory with the given name.
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("dria-oracle-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("could not create test directory");
+
+        Self(path)
+    }
+
+    /// Returns the path of the directory.
+    #[inline]
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Returns the path of the given file within the directory.
+    #[inline]
+    pub(crate) fn join(&self, file: impl AsRef<Path>) -> PathBuf {
+        self.0.join(file)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
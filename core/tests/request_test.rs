@@ -4,15 +4,16 @@
 //! 2. Requester requests a task with a given input, models, difficulty, num_gens, and num_vals.
 //! 3. The task is created in the coordinator contract.
 //!
+//! The setup is done once and snapshotted, and the chain is restored to it before each case.
+//!
 //! ```sh
 //! cargo test --package dria-oracle --test request_test --all-features -- test_request --exact --show-output
 //! ```
 #![cfg(feature = "anvil")]
 
-use alloy::primitives::{aliases::U40, utils::parse_ether};
+use alloy::primitives::utils::parse_ether;
 use dkn_workflows::Model;
 use dria_oracle::{DriaOracle, DriaOracleConfig};
-use dria_oracle_contracts::{bytes_to_string, WETH};
 use eyre::Result;
 
 #[tokio::test]
@@ -35,23 +36,52 @@ async fn test_request() -> Result<()> {
     let config = DriaOracleConfig::new_from_env()?;
     let node = DriaOracle::new(config).await?;
 
-    // setup account & buy some WETH
-    let requester = node.connect(node.anvil_new_funded_wallet(None).await?);
-    let token = WETH::new(*requester.token.address(), &requester.provider);
-    let _ = token.deposit().value(parse_ether("100")?).send().await?;
+    // setup account & buy some WETH, once for all cases
+    let requester = node.anvil_new_funded_oracle(parse_ether("100")?).await?;
+    let mut snapshot = node.anvil_snapshot().await?;
 
     // request a task, and see it in the coordinator
-    let task_id = node.coordinator.nextTaskId().call().await?._0;
-    requester
-        .request_task(&input, models, difficulty, num_gens, num_vals, protocol)
+    let expected_task_id = node.coordinator.nextTaskId().call().await?._0;
+    let task_id = requester
+        .request_task(
+            &input,
+            models.clone(),
+            difficulty,
+            num_gens,
+            num_vals,
+            protocol.clone(),
+        )
         .await?;
+    assert_eq!(task_id, expected_task_id);
 
     // get the task info
-    let (request, _, _) = node.get_task(task_id).await?;
-    assert_eq!(input, bytes_to_string(&request.input).unwrap());
-    assert_eq!(difficulty, request.parameters.difficulty);
-    assert_eq!(U40::from(num_gens), request.parameters.numGenerations);
-    assert_eq!(U40::from(num_vals), request.parameters.numValidations);
+    let request = node.get_task(task_id).await?.request;
+    assert_eq!(input, request.input.raw);
+    assert_eq!(difficulty, request.difficulty);
+    assert_eq!(num_gens, request.num_generations);
+    assert_eq!(num_vals, request.num_validations);
+
+    // the task is gone after a restore, so the same id is given to the next one
+    node.anvil_restore(&mut snapshot).await?;
+    let task_id = requester
+        .request_task(
+            &input,
+            models.clone(),
+            difficulty,
+            num_gens,
+            num_vals,
+            protocol.clone(),
+        )
+        .await?;
+    assert_eq!(task_id, expected_task_id);
+
+    // a requester without WETH can not request
+    node.anvil_restore(&mut snapshot).await?;
+    let poor_requester = node.connect(node.anvil_new_funded_wallet(None).await?);
+    let result = poor_requester
+        .request_task(&input, models, difficulty, num_gens, num_vals, protocol)
+        .await;
+    assert!(result.is_err());
 
     Ok(())
 }
@@ -3,7 +3,7 @@
 use alloy::{eips::BlockNumberOrTag, primitives::utils::parse_ether};
 use dkn_workflows::Model;
 use dria_oracle::{handle_request, DriaOracle, DriaOracleConfig};
-use dria_oracle_contracts::{string_to_bytes, OracleKind, TaskStatus, WETH};
+use dria_oracle_contracts::{string_to_bytes, OracleKind, TaskStatus};
 use eyre::Result;
 
 #[tokio::test]
@@ -37,23 +37,15 @@ async fn test_swan() -> Result<()> {
     let config = DriaOracleConfig::new_from_env()?;
     let node = DriaOracle::new(config).await?;
 
-    // setup accounts
-    let requester = node.connect(node.anvil_new_funded_wallet(None).await?);
-    let mut generator = node.connect(node.anvil_new_funded_wallet(None).await?);
-    let mut validator = node.connect(node.anvil_new_funded_wallet(None).await?);
-
-    // buy some WETH for all people
+    // setup accounts & buy some WETH for all people
     log::info!("Buying WETH for all accounts");
     let amount = parse_ether("100").unwrap();
+    let requester = node.anvil_new_funded_oracle(amount).await?;
+    let mut generator = node.anvil_new_funded_oracle(amount).await?;
+    let mut validator = node.anvil_new_funded_oracle(amount).await?;
     for node in [&requester, &generator, &validator] {
-        let balance_before = node.get_token_balance(node.address()).await?;
-
-        let token = WETH::new(*node.token.address(), &node.provider);
-        let call = token.deposit().value(amount);
-        let _ = call.send().await?.get_receipt().await?;
-
-        let balance_after = node.get_token_balance(node.address()).await?;
-        assert!(balance_after.amount > balance_before.amount);
+        let balance = node.get_token_balance(node.address()).await?;
+        assert_eq!(balance.amount, amount);
     }
 
     // whitelist validator with impersonation
@@ -0,0 +1,317 @@
+//! Python bindings for the requester & viewer side of the Dria Oracle.
+//!
+//! ```py
+//! from dria_oracle import OracleClient
+//!
+//! client = OracleClient.from_env(".env")
+//! task_id = client.request_task("What is 2+2?", ["gpt-4o-mini"])
+//! task = client.wait_task(task_id, timeout=600)
+//! print(task.best_output())
+//! ```
+
+use alloy::hex::FromHex;
+use alloy::primitives::{Address, Bytes, B256, U256};
+use alloy::transports::http::reqwest::Url;
+use dkn_workflows::Model;
+use dria_oracle::{DriaOracle, DriaOracleConfig, StoredContent, TaskView};
+use dria_oracle_contracts::TaskStatus;
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+/// Converts an error report to a Python exception, keeping its causes within the message.
+#[inline]
+fn to_py_err(err: impl Into<eyre::Report>) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", err.into()))
+}
+
+/// Parses a task id given as a decimal string.
+#[inline]
+fn parse_task_id(task_id: &str) -> PyResult<U256> {
+    task_id
+        .parse()
+        .map_err(|_| PyValueError::new_err(format!("invalid task id: {}", task_id)))
+}
+
+/// Creates a runtime for the async calls, which are blocked on without holding the GIL.
+fn new_runtime() -> PyResult<Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+/// A generation response to a task.
+#[pyclass(get_all, frozen)]
+#[derive(Clone)]
+struct Response {
+    responder: String,
+    score: String,
+    output: String,
+    metadata: String,
+}
+
+/// A validation of the responses to a task.
+#[pyclass(get_all, frozen)]
+#[derive(Clone)]
+struct Validation {
+    validator: String,
+    scores: Vec<String>,
+    metadata: String,
+}
+
+/// A task along with its responses & validations.
+///
+/// Task ids and token amounts are decimal strings, as they are 256-bit integers.
+#[pyclass(get_all, frozen)]
+struct Task {
+    task_id: String,
+    requester: String,
+    protocol: String,
+    status: String,
+    input: String,
+    models: Vec<String>,
+    generator_fee: String,
+    validator_fee: String,
+    responses: Vec<Response>,
+    validations: Vec<Validation>,
+}
+
+#[pymethods]
+impl Task {
+    /// Returns the output of the highest-scored response, preferring the earliest one on ties.
+    fn best_output(&self) -> Option<String> {
+        self.responses
+            .iter()
+            .rev()
+            .max_by_key(|r| r.score.parse::<U256>().unwrap_or_default())
+            .map(|r| r.output.clone())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Task(task_id={}, status={}, protocol={}, responses={}, validations={})",
+            self.task_id,
+            self.status,
+            self.protocol,
+            self.responses.len(),
+            self.validations.len()
+        )
+    }
+}
+
+impl From<TaskView> for Task {
+    fn from(task: TaskView) -> Self {
+        Self {
+            task_id: task.task_id.to_string(),
+            requester: task.request.requester.to_string(),
+            protocol: task.request.protocol,
+            status: task.request.status.to_string(),
+            input: task.request.input.content().to_string(),
+            models: task.request.models,
+            generator_fee: task.request.generator_fee.to_string(),
+            validator_fee: task.request.validator_fee.to_string(),
+            responses: task
+                .responses
+                .into_iter()
+                .map(|r| Response {
+                    responder: r.responder.to_string(),
+                    score: r.score.to_string(),
+                    output: r.output.content().to_string(),
+                    metadata: r.metadata.content().to_string(),
+                })
+                .collect(),
+            validations: task
+                .validations
+                .into_iter()
+                .map(|v| Validation {
+                    validator: v.validator.to_string(),
+                    scores: v.scores.iter().map(|s| s.to_string()).collect(),
+                    metadata: v.metadata.content().to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A client to request & view oracle tasks, using the wallet of the given secret key.
+#[pyclass]
+struct OracleClient {
+    node: DriaOracle,
+    runtime: Runtime,
+}
+
+impl OracleClient {
+    fn from_config(config: DriaOracleConfig) -> PyResult<Self> {
+        let runtime = new_runtime()?;
+        let node = runtime
+            .block_on(DriaOracle::new(config))
+            .map_err(to_py_err)?;
+
+        Ok(Self { node, runtime })
+    }
+
+    /// Reads the task, downloading its externally stored contents if `resolve_storage` is set.
+    fn read_task(&self, task_id: U256, resolve_storage: bool) -> eyre::Result<Task> {
+        self.runtime.block_on(async {
+            let mut task = self.node.get_task(task_id).await?;
+            if resolve_storage {
+                task.resolve_storage().await;
+            }
+            Ok(task.into())
+        })
+    }
+}
+
+#[pymethods]
+impl OracleClient {
+    #[new]
+    #[pyo3(signature = (secret_key, rpc_url, coordinator_address=None))]
+    fn new(secret_key: &str, rpc_url: &str, coordinator_address: Option<&str>) -> PyResult<Self> {
+        let secret_key =
+            B256::from_hex(secret_key).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let rpc_url = Url::parse(rpc_url).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let mut config = DriaOracleConfig::new(&secret_key, rpc_url).map_err(to_py_err)?;
+        if let Some(coordinator_address) = coordinator_address {
+            let coordinator_address = Address::from_hex(coordinator_address)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            config = config.with_coordinator_address(coordinator_address);
+        }
+
+        Self::from_config(config)
+    }
+
+    /// Creates a client from the variables of an environment file, or of the process if `path` is omitted.
+    ///
+    /// Requires `SECRET_KEY` and `RPC_URL`, and optionally takes `COORDINATOR_ADDRESS`.
+    #[staticmethod]
+    #[pyo3(signature = (path=None))]
+    fn from_env(path: Option<PathBuf>) -> PyResult<Self> {
+        let config = match path {
+            Some(path) => DriaOracleConfig::new_from_env_file(path),
+            None => DriaOracleConfig::new_from_env(),
+        }
+        .map_err(to_py_err)?;
+
+        Self::from_config(config)
+    }
+
+    /// Address of the wallet.
+    #[getter]
+    fn address(&self) -> String {
+        self.node.address().to_string()
+    }
+
+    /// Requests a task, returns its id.
+    #[pyo3(signature = (input, models, difficulty=2, num_gens=1, num_vals=1, protocol=None))]
+    fn request_task(
+        &self,
+        py: Python<'_>,
+        input: &str,
+        models: Vec<String>,
+        difficulty: u8,
+        num_gens: u64,
+        num_vals: u64,
+        protocol: Option<String>,
+    ) -> PyResult<String> {
+        let models = models
+            .into_iter()
+            .map(Model::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let protocol = protocol.unwrap_or_else(|| {
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string()
+        });
+
+        let task_id = py
+            .allow_threads(|| {
+                self.runtime.block_on(
+                    self.node
+                        .request_task(input, models, difficulty, num_gens, num_vals, protocol),
+                )
+            })
+            .map_err(to_py_err)?;
+
+        Ok(task_id.to_string())
+    }
+
+    /// Reads a task, along with its responses & validations.
+    #[pyo3(signature = (task_id, resolve_storage=true))]
+    fn get_task(&self, py: Python<'_>, task_id: &str, resolve_storage: bool) -> PyResult<Task> {
+        let task_id = parse_task_id(task_id)?;
+        py.allow_threads(|| self.read_task(task_id, resolve_storage))
+            .map_err(to_py_err)
+    }
+
+    /// Waits until the task is completed and returns it, polling its status every `poll_interval` seconds.
+    ///
+    /// Raises `TimeoutError` if the task is not completed within `timeout` seconds, if given,
+    /// and `ValueError` if `poll_interval` is not a positive number of seconds.
+    #[pyo3(signature = (task_id, timeout=None, poll_interval=2.0))]
+    fn wait_task(
+        &self,
+        py: Python<'_>,
+        task_id: &str,
+        timeout: Option<f64>,
+        poll_interval: f64,
+    ) -> PyResult<Task> {
+        let task_id = parse_task_id(task_id)?;
+        let poll_interval = Duration::try_from_secs_f64(poll_interval)
+            .ok()
+            .filter(|interval| !interval.is_zero())
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "poll_interval must be a positive number of seconds, got {}",
+                    poll_interval
+                ))
+            })?;
+        let started_at = Instant::now();
+
+        loop {
+            let status = py
+                .allow_threads(|| {
+                    self.runtime
+                        .block_on(self.node.get_task(task_id))
+                        .map(|task| task.request.status)
+                })
+                .map_err(to_py_err)?;
+            if status == TaskStatus::Completed {
+                return py
+                    .allow_threads(|| self.read_task(task_id, true))
+                    .map_err(to_py_err);
+            }
+
+            if let Some(timeout) = timeout {
+                if started_at.elapsed().as_secs_f64() >= timeout {
+                    return Err(PyTimeoutError::new_err(format!(
+                        "task {} did not complete within {}s, it is {}",
+                        task_id, timeout, status
+                    )));
+                }
+            }
+
+            // allow interrupting the wait, e.g. with Ctrl+C in a notebook
+            py.check_signals()?;
+            py.allow_threads(|| std::thread::sleep(poll_interval));
+        }
+    }
+}
+
+/// Returns the given value as is, or downloads it from Arweave if it is a storage key.
+#[pyfunction]
+fn download(py: Python<'_>, value: &str) -> PyResult<String> {
+    let content = StoredContent::from(&Bytes::copy_from_slice(value.as_bytes()));
+    py.allow_threads(|| new_runtime()?.block_on(content.fetch()).map_err(to_py_err))
+}
+
+#[pymodule]
+fn dria_oracle(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<OracleClient>()?;
+    m.add_class::<Task>()?;
+    m.add_class::<Response>()?;
+    m.add_class::<Validation>()?;
+    m.add_function(wrap_pyfunction!(download, m)?)?;
+    Ok(())
+}
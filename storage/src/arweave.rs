@@ -1,20 +1,41 @@
 use alloy::primitives::Bytes;
 use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use bundlr_sdk::{currency::arweave::ArweaveBuilder, tags::Tag, BundlrBuilder};
 use eyre::{eyre, Context, Result};
 use reqwest::{Client, Url};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use std::{env, path::PathBuf};
 
-use super::IsExternalStorage;
+use super::{ArweaveKey, ContentEncoding, IsExternalStorage, StorageCipher};
 
 const DEFAULT_UPLOAD_BASE_URL: &str = "https://node1.bundlr.network";
 const DEFAULT_DOWNLOAD_BASE_URL: &str = "https://arweave.net";
 const DEFAULT_BYTE_LIMIT: usize = 1024; // 1KB
-
+const DEFAULT_VERIFY_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_VERIFY_BACKOFF: Duration = Duration::from_secs(30);
+/// Time to connect to the gateway or the bundler.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time for a request to the gateway or the bundler, which bounds a download as a whole.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Time for a request that only probes the gateway or the bundler, e.g. for a health check.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const WINSTON_PER_AR: u128 = 1_000_000_000_000;
+
+/// Receipt of an upload, as returned by the bundler.
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
-pub struct ArweaveKey {
-    /// The base64url encoded key, can be used to download data directly.
-    pub arweave: String,
+#[serde(rename_all = "camelCase")]
+pub struct ArweaveReceipt {
+    /// The base64url encoded transaction id.
+    pub id: String,
+    /// Signature of the bundler over the upload.
+    pub signature: String,
+    /// Time of the upload in milliseconds, as given by the bundler.
+    pub timestamp: u64,
+    /// Block height that the bundler promises to settle the upload on Arweave by.
+    pub deadline_height: u64,
 }
 
 /// External data storage for Arweave.
@@ -38,6 +59,18 @@ pub struct ArweaveStorage {
     /// - If the data exceeds this limit, it will be uploaded to Arweave.
     /// - Otherwise, it will be stored as is.
     byte_limit: usize,
+    /// Maximum time to wait for an upload to be retrievable from the download gateway.
+    ///
+    /// If `None`, uploads are not verified, which is the default.
+    verify_timeout: Option<Duration>,
+    /// Size threshold for the uploads to be compressed, in bytes.
+    ///
+    /// If `None`, uploads are not compressed.
+    compression_threshold: Option<usize>,
+    /// Whether the large values of a response are bundled into a single upload.
+    bundling: bool,
+    /// Cipher to encrypt the private uploads with, e.g. metadata.
+    cipher: Option<StorageCipher>,
 }
 
 impl ArweaveStorage {
@@ -54,7 +87,15 @@ impl ArweaveStorage {
             upload_base_url: Url::parse(DEFAULT_UPLOAD_BASE_URL).unwrap(),
             download_base_url: Url::parse(DEFAULT_DOWNLOAD_BASE_URL).unwrap(),
             byte_limit: DEFAULT_BYTE_LIMIT,
-            client: Client::new(),
+            verify_timeout: None,
+            compression_threshold: None,
+            bundling: false,
+            cipher: None,
+            client: Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("could not build HTTP client"),
         }
     }
 
@@ -89,11 +130,55 @@ impl ArweaveStorage {
         Ok(self)
     }
 
+    /// Sets the maximum time to wait for an upload to be retrievable, disabled by default.
+    ///
+    /// See [`Self::verify_upload`]. If `None`, uploads are not verified at all.
+    pub fn with_verify_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.verify_timeout = timeout;
+        self
+    }
+
+    /// Sets the size threshold for the uploads to be compressed (with gzip), disabled by default.
+    ///
+    /// The encoding is marked within the key, so that the value is decompressed after download.
+    /// A value is uploaded as is if compressing does not make it smaller.
+    pub fn with_compression_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.compression_threshold = threshold;
+        self
+    }
+
+    /// Enables bundling the large values into a single upload, disabled by default.
+    ///
+    /// See [`Self::put_all_if_large`]. The keys of the bundled values are of the newer version
+    /// (see [`ArweaveKey`]), so they can not be read by the consumers that only know the plain keys.
+    pub fn with_bundling(mut self, bundling: bool) -> Self {
+        self.bundling = bundling;
+        self
+    }
+
+    /// Sets the key to encrypt the private uploads with, as a 32-byte hexadecimal string.
+    ///
+    /// See [`Self::put_encrypted_with_receipt`].
+    pub fn with_encryption_key(mut self, key: &str) -> Result<Self> {
+        self.cipher = Some(StorageCipher::from_hex(key)?);
+        Ok(self)
+    }
+
+    /// Returns `true` if an encryption key is set.
+    #[inline]
+    pub fn has_encryption(&self) -> bool {
+        self.cipher.is_some()
+    }
+
     /// Creates a new Arweave instance from the environment variables.
     ///
     /// - `ARWEAVE_WALLET_PATH` is required
     /// - `ARWEAVE_BASE_URL` is optional
     /// - `ARWEAVE_BYTE_LIMIT` is optional
+    /// - `ARWEAVE_VERIFY_TIMEOUT` is optional, in seconds where `0` or empty disables the verification
+    /// - `ARWEAVE_COMPRESSION_THRESHOLD` is optional, in bytes
+    /// - `ARWEAVE_BUNDLE` is optional, `true` to bundle the large values
+    /// - `ARWEAVE_ENCRYPTION_KEY` is optional
     ///
     /// All these variables have defaults if they are missing.
     pub fn new_from_env() -> Result<Self> {
@@ -112,12 +197,52 @@ impl ArweaveStorage {
             ar = ar.with_upload_byte_limit(byte_limit.parse().unwrap_or(DEFAULT_BYTE_LIMIT));
         }
 
+        // update verification timeout if needed
+        if let Ok(timeout) = env::var("ARWEAVE_VERIFY_TIMEOUT") {
+            if !timeout.is_empty() {
+                let timeout = timeout
+                    .parse()
+                    .map_or(DEFAULT_VERIFY_TIMEOUT, Duration::from_secs);
+                ar = ar.with_verify_timeout((!timeout.is_zero()).then_some(timeout));
+            }
+        }
+
+        // enable compression if needed
+        if let Ok(threshold) = env::var("ARWEAVE_COMPRESSION_THRESHOLD") {
+            ar = ar.with_compression_threshold(threshold.parse().ok());
+        }
+
+        // enable bundling if needed
+        if let Ok(bundling) = env::var("ARWEAVE_BUNDLE") {
+            ar = ar.with_bundling(bundling.parse().unwrap_or_default());
+        }
+
+        // enable encryption if needed
+        if let Ok(key) = env::var("ARWEAVE_ENCRYPTION_KEY") {
+            if !key.is_empty() {
+                ar = ar.with_encryption_key(&key)?;
+            }
+        }
+
         Ok(ar)
     }
 
-    /// Puts the value if it is larger than the byte limit.
-    #[inline]
+    /// Puts the value if it is larger than the byte limit, and verifies the upload if any.
     pub async fn put_if_large(&self, value: Bytes) -> Result<Bytes> {
+        let (value, receipt) = self.put_if_large_with_receipt(value).await?;
+        if let Some(receipt) = receipt {
+            self.verify_upload(&receipt).await?;
+        }
+        Ok(value)
+    }
+
+    /// Puts the value if it is larger than the byte limit, along with the receipt of the upload if any.
+    ///
+    /// The upload is not verified, see [`Self::verify_upload`].
+    pub async fn put_if_large_with_receipt(
+        &self,
+        value: Bytes,
+    ) -> Result<(Bytes, Option<ArweaveReceipt>)> {
         let value_size = value.len();
         if value_size > self.byte_limit {
             log::info!(
@@ -125,25 +250,159 @@ impl ArweaveStorage {
                 value_size,
                 self.byte_limit
             );
-            let key = self.put(value.clone()).await?;
+            let (value, encoding) = self.compress(value)?;
+            let receipt = self.put_with_receipt(value).await?;
+            let key = ArweaveKey::new(receipt.id.clone()).with_encoding(encoding);
             let key_str = serde_json::to_string(&key).wrap_err("could not serialize key")?;
-            Ok(key_str.into())
+            Ok((key_str.into(), Some(receipt)))
         } else {
-            Ok(value)
+            Ok((value, None))
         }
     }
-}
 
-#[async_trait(?Send)]
-impl IsExternalStorage for ArweaveStorage {
-    type Key = ArweaveKey;
-    type Value = Bytes;
+    /// Puts the values that are larger than the byte limit, returning the values in the same order
+    /// along with the receipt of the upload if any.
+    ///
+    /// If bundling is enabled and there are multiple large values, they are bundled into a single upload
+    /// as a JSON object of their names to values, and their keys refer to their items within that bundle.
+    /// This saves the overhead & fees of an upload per value. Values that are not UTF-8 are not bundled.
+    pub async fn put_all_if_large<const N: usize>(
+        &self,
+        items: [(&str, Bytes); N],
+    ) -> Result<([Bytes; N], Option<ArweaveReceipt>)> {
+        let is_large = |value: &Bytes| value.len() > self.byte_limit;
+
+        // large values are bundled if enabled, there are more than one, and all of them are UTF-8
+        let bundle = items
+            .iter()
+            .filter(|(_, value)| is_large(value))
+            .map(|(name, value)| std::str::from_utf8(value).map(|value| (*name, value)))
+            .collect::<Result<HashMap<_, _>, std::str::Utf8Error>>()
+            .ok()
+            .filter(|bundle| self.bundling && bundle.len() > 1)
+            .map(|bundle| serde_json::to_vec(&bundle))
+            .transpose()?;
+
+        let mut values = Vec::with_capacity(N);
+        let mut receipt = None;
+        match bundle {
+            Some(bundle) => {
+                log::info!(
+                    "Uploading large values to Arweave as a bundle ({}B)",
+                    bundle.len()
+                );
+                let (bundle, encoding) = self.compress(bundle.into())?;
+                let bundle_receipt = self.put_with_receipt(bundle).await?;
+                for (name, value) in items {
+                    if is_large(&value) {
+                        let key = ArweaveKey::new(bundle_receipt.id.clone())
+                            .with_item(name)
+                            .with_encoding(encoding);
+                        let key_str =
+                            serde_json::to_string(&key).wrap_err("could not serialize key")?;
+                        values.push(key_str.into());
+                    } else {
+                        values.push(value);
+                    }
+                }
+                receipt = Some(bundle_receipt);
+            }
+            None => {
+                // nothing to bundle, each value is put on its own
+                for (_, value) in items {
+                    let (value, value_receipt) = self.put_if_large_with_receipt(value).await?;
+                    values.push(value);
+                    receipt = receipt.or(value_receipt);
+                }
+            }
+        }
 
-    async fn get(&self, key: Self::Key) -> Result<Self::Value> {
+        let values = <[Bytes; N]>::try_from(values).expect("all values are pushed");
+        Ok((values, receipt))
+    }
+
+    /// Puts the value encrypted with the encryption key, regardless of its size so that
+    /// it is not readable on-chain either, along with the receipt of the upload.
+    ///
+    /// The value is compressed before it is encrypted, if compression is enabled.
+    /// Returns an error if there is no encryption key.
+    pub async fn put_encrypted_with_receipt(
+        &self,
+        value: Bytes,
+    ) -> Result<(Bytes, ArweaveReceipt)> {
+        let cipher = self
+            .cipher
+            .as_ref()
+            .ok_or_else(|| eyre!("Encryption key is not set"))?;
+
+        log::info!("Uploading encrypted ({}B) value to Arweave", value.len());
+        let (value, encoding) = self.compress(value)?;
+        let encrypted = cipher.encrypt(&value)?;
+        let receipt = self.put_with_receipt(encrypted.into()).await?;
+        let key = ArweaveKey::new(receipt.id.clone())
+            .with_encoding(encoding)
+            .with_encryption(StorageCipher::SCHEME);
+        let key_str = serde_json::to_string(&key).wrap_err("could not serialize key")?;
+
+        Ok((key_str.into(), receipt))
+    }
+
+    /// Compresses the value if it is larger than the compression threshold, and if compressing makes it smaller.
+    ///
+    /// Returns the value to upload along with its encoding, if any.
+    fn compress(&self, value: Bytes) -> Result<(Bytes, Option<ContentEncoding>)> {
+        match self.compression_threshold {
+            Some(threshold) if value.len() > threshold => {
+                let encoding = ContentEncoding::Gzip;
+                let compressed = encoding.encode(&value)?;
+                if compressed.len() < value.len() {
+                    log::debug!(
+                        "Compressed value from {}B to {}B",
+                        value.len(),
+                        compressed.len()
+                    );
+                    return Ok((compressed.into(), Some(encoding)));
+                }
+
+                Ok((value, None))
+            }
+            _ => Ok((value, None)),
+        }
+    }
+
+    /// Uploads the value, returning the receipt of the upload.
+    ///
+    /// The upload is not verified, see [`Self::verify_upload`].
+    #[inline]
+    pub async fn put_with_receipt(&self, value: Bytes) -> Result<ArweaveReceipt> {
+        self.upload(value).await
+    }
+
+    /// Waits until the upload of the given receipt is retrievable, if verification is enabled
+    /// (see [`Self::with_verify_timeout`]).
+    ///
+    /// An upload that is not retrievable in time is not failed, as it may still propagate; the error
+    /// is for the caller to hold its key back until then (see [`Self::is_retrievable`]).
+    pub async fn verify_upload(&self, receipt: &ArweaveReceipt) -> Result<()> {
+        match self.verify_timeout {
+            Some(timeout) => self.wait_until_retrievable(&receipt.id, timeout).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Downloads the value of the given key like [`IsExternalStorage::get`], but returns `None` as soon
+    /// as the upload exceeds the given size in bytes, i.e. by its `Content-Length` header if there is one,
+    /// or else while it is streamed, so that an oversized upload is not downloaded as a whole.
+    pub async fn get_within(&self, key: ArweaveKey, max_bytes: usize) -> Result<Option<Bytes>> {
+        self.fetch(key, Some(max_bytes)).await
+    }
+
+    /// Downloads & decodes the value of the given key, returns `None` if the upload exceeds the limit.
+    async fn fetch(&self, key: ArweaveKey, max_bytes: Option<usize>) -> Result<Option<Bytes>> {
         let url = self.download_base_url.join(&key.arweave)?;
 
         log::debug!("Fetching from Arweave: {}", url);
-        let response = self
+        let mut response = self
             .client
             .get(url)
             .send()
@@ -154,37 +413,102 @@ impl IsExternalStorage for ArweaveStorage {
             return Err(eyre!("Failed to fetch from Arweave: {}", response.status()));
         }
 
-        let response_bytes = response.bytes().await?;
-        Ok(response_bytes.into())
-    }
+        let exceeds = |len: usize| max_bytes.is_some_and(|max_bytes| len > max_bytes);
+        if let Some(len) = response.content_length() {
+            if exceeds(len as usize) {
+                log::debug!(
+                    "Upload {} has {} bytes, exceeding the limit",
+                    key.arweave,
+                    len
+                );
+                return Ok(None);
+            }
+        }
 
-    async fn put(&self, value: Self::Value) -> Result<Self::Key> {
-        let wallet_path = self
-            .wallet
-            .as_ref()
-            .ok_or_else(|| eyre!("Wallet path is not set"))?;
+        let mut response_bytes = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .wrap_err("failed to fetch from Arweave")?
+        {
+            response_bytes.extend_from_slice(&chunk);
+            if exceeds(response_bytes.len()) {
+                log::debug!("Upload {} exceeds the limit while streamed", key.arweave);
+                return Ok(None);
+            }
+        }
 
-        #[derive(Debug, serde::Deserialize)]
-        #[serde(rename_all = "camelCase")]
-        #[allow(unused)]
-        struct UploadResponse {
-            block: u64,
-            deadline_height: u64,
-            id: String,
-            public: String,
-            signature: String,
-            timestamp: u64,
-            validator_signatures: Vec<String>,
-            version: String,
+        if key.enc.is_some() {
+            let cipher = self
+                .cipher
+                .as_ref()
+                .ok_or_else(|| eyre!("Value is encrypted, but the encryption key is not set"))?;
+            response_bytes = cipher.decrypt(&response_bytes)?;
         }
 
-        // ensure that wallet exists
-        // NOTE: we do this here instead of `new` so that we can work without any wallet
-        // in case we only want to download data.
-        if !wallet_path.try_exists()? {
-            return Err(eyre!("Wallet does not exist at {}.", wallet_path.display()));
+        // the decompressed value is bounded by the encoding itself, but may still exceed the limit
+        let value = key.decode(&response_bytes)?;
+        if exceeds(value.len()) {
+            return Ok(None);
         }
 
+        Ok(Some(value.into()))
+    }
+
+    /// Returns `true` if the upload with the given id is retrievable from the download gateway.
+    pub async fn is_retrievable(&self, id: &str) -> Result<bool> {
+        let url = self.download_base_url.join(id)?;
+        match self.client.head(url).timeout(PROBE_TIMEOUT).send().await {
+            Ok(response) if response.status().is_success() => Ok(true),
+            Ok(response) => {
+                log::debug!(
+                    "Upload {} is not retrievable yet: {}",
+                    id,
+                    response.status()
+                );
+                Ok(false)
+            }
+            Err(e) => {
+                log::debug!("Upload {} is not retrievable yet: {}", id, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Polls the download gateway until the upload with the given id is retrievable, with an
+    /// exponential backoff between the attempts.
+    ///
+    /// Returns an error if it is not retrievable within the timeout.
+    pub async fn wait_until_retrievable(&self, id: &str, timeout: Duration) -> Result<()> {
+        let started_at = Instant::now();
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            if self.is_retrievable(id).await? {
+                log::debug!(
+                    "Upload {} is retrievable after {}ms",
+                    id,
+                    started_at.elapsed().as_millis()
+                );
+                return Ok(());
+            }
+
+            if started_at.elapsed() + backoff > timeout {
+                return Err(eyre!(
+                    "Upload {} is not retrievable from {} after {}s",
+                    id,
+                    self.download_base_url,
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_VERIFY_BACKOFF);
+        }
+    }
+
+    /// Uploads the value via the bundler, returning its receipt.
+    async fn upload(&self, value: Bytes) -> Result<ArweaveReceipt> {
+        let wallet_path = self.wallet_path()?;
+
         // create tag
         let base_tag = Tag::new(
             "User-Agent",
@@ -208,13 +532,198 @@ impl IsExternalStorage for ArweaveStorage {
         let mut tx = bundlr.create_transaction(value.into(), vec![base_tag])?;
         bundlr.sign_transaction(&mut tx).await?;
         let response_body = bundlr.send_transaction(tx).await?;
-        let res = serde_json::from_value::<UploadResponse>(response_body)?;
+        let receipt = serde_json::from_value::<ArweaveReceipt>(response_body)?;
+
+        log::debug!("Uploaded to Arweave: {:#?}", receipt);
+        log::info!("Uploaded at {}", self.upload_base_url.join(&receipt.id)?);
 
-        log::debug!("Uploaded to Arweave: {:#?}", res);
-        log::info!("Uploaded at {}", self.upload_base_url.join(&res.id)?);
+        Ok(receipt)
+    }
+
+    /// Checks that uploads & downloads would work, without uploading anything:
+    ///
+    /// - the download gateway is reachable,
+    /// - the bundler is reachable & the wallet can be read, if a wallet is set.
+    ///
+    /// The balance is not checked, as the values below the byte limit are never uploaded;
+    /// see [`Self::get_balance`] for that.
+    pub async fn check_health(&self) -> Result<()> {
+        let response = self
+            .client
+            .head(self.download_base_url.clone())
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .wrap_err_with(|| format!("could not reach {}", self.download_base_url))?;
+        if response.status().is_server_error() {
+            return Err(eyre!(
+                "Download gateway {} is unavailable: {}",
+                self.download_base_url,
+                response.status()
+            ));
+        }
 
-        // the key is in base64 format, we want to convert that to hexadecimals
-        Ok(ArweaveKey { arweave: res.id })
+        if self.wallet.is_some() {
+            let info_url = self.upload_base_url.join("info")?;
+            let response = self
+                .client
+                .get(info_url)
+                .timeout(PROBE_TIMEOUT)
+                .send()
+                .await
+                .wrap_err_with(|| format!("could not reach {}", self.upload_base_url))?;
+            if !response.status().is_success() {
+                return Err(eyre!(
+                    "Bundler {} is unavailable: {}",
+                    self.upload_base_url,
+                    response.status()
+                ));
+            }
+
+            self.wallet_address()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path of the wallet, if it is set and exists.
+    fn wallet_path(&self) -> Result<&PathBuf> {
+        let wallet_path = self
+            .wallet
+            .as_ref()
+            .ok_or_else(|| eyre!("Wallet path is not set"))?;
+
+        // ensure that wallet exists
+        // NOTE: we do this here instead of `new` so that we can work without any wallet
+        // in case we only want to download data.
+        if !wallet_path.try_exists()? {
+            return Err(eyre!("Wallet does not exist at {}.", wallet_path.display()));
+        }
+
+        Ok(wallet_path)
+    }
+
+    /// Returns the address of the wallet, i.e. the base64url encoded SHA-256 hash of its public modulus.
+    pub fn wallet_address(&self) -> Result<String> {
+        #[derive(serde::Deserialize)]
+        struct Jwk {
+            n: String,
+        }
+
+        let wallet_path = self.wallet_path()?;
+        let jwk = serde_json::from_slice::<Jwk>(&std::fs::read(wallet_path)?)
+            .wrap_err("could not parse wallet")?;
+        let modulus = URL_SAFE_NO_PAD
+            .decode(jwk.n)
+            .wrap_err("could not decode wallet modulus")?;
+
+        Ok(URL_SAFE_NO_PAD.encode(Sha256::digest(modulus)))
+    }
+
+    /// Returns the balance of the wallet at the bundler, in winston (10^-12 AR).
+    ///
+    /// Uploads are paid from this balance, and they fail once it runs dry.
+    pub async fn get_balance(&self) -> Result<u128> {
+        #[derive(serde::Deserialize)]
+        struct BalanceResponse {
+            balance: String,
+        }
+
+        let mut url = self.upload_base_url.join("account/balance/arweave")?;
+        url.query_pairs_mut()
+            .append_pair("address", &self.wallet_address()?);
+        let response = self
+            .client
+            .get(url)
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .wrap_err("failed to fetch balance from bundler")?;
+        if !response.status().is_success() {
+            return Err(eyre!(
+                "Failed to fetch balance from bundler: {}",
+                response.status()
+            ));
+        }
+
+        let response = response.json::<BalanceResponse>().await?;
+        response.balance.parse().wrap_err("could not parse balance")
+    }
+
+    /// Funds the balance of the wallet at the bundler with the given amount in winston (10^-12 AR),
+    /// by sending a transaction on Arweave.
+    pub async fn fund(&self, amount: u64) -> Result<()> {
+        let wallet_path = self.wallet_path()?;
+        let currency = ArweaveBuilder::new()
+            .keypair_path(wallet_path.clone())
+            .build()?;
+        let bundlr = BundlrBuilder::new()
+            .url(self.upload_base_url.clone())
+            .currency(currency)
+            .fetch_pub_info()
+            .await?
+            .build()?;
+
+        let funded = bundlr.fund(amount, None).await?;
+        if !funded {
+            return Err(eyre!("Bundler did not accept the funding transaction"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats an amount in winston as AR, e.g. `1.5 AR`.
+pub fn format_winston(amount: u128) -> String {
+    let whole = amount / WINSTON_PER_AR;
+    let fraction = format!("{:012}", amount % WINSTON_PER_AR);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => format!("{} AR", whole),
+        false => format!("{}.{} AR", whole, fraction),
+    }
+}
+
+/// Parses an amount in AR into winston, e.g. `1.5` to `1500000000000`.
+pub fn parse_winston(amount: &str) -> Result<u128> {
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if fraction.len() > 12 {
+        return Err(eyre!("Amount {} has more than 12 decimals", amount));
+    }
+
+    let whole = match whole {
+        "" => 0,
+        whole => whole.parse::<u128>().wrap_err("invalid amount")?,
+    };
+    let fraction = match fraction {
+        "" => 0,
+        fraction => format!("{:0<12}", fraction)
+            .parse::<u128>()
+            .wrap_err("invalid amount")?,
+    };
+
+    whole
+        .checked_mul(WINSTON_PER_AR)
+        .and_then(|w| w.checked_add(fraction))
+        .ok_or_else(|| eyre!("Amount {} is too large", amount))
+}
+
+#[async_trait(?Send)]
+impl IsExternalStorage for ArweaveStorage {
+    type Key = ArweaveKey;
+    type Value = Bytes;
+
+    async fn get(&self, key: Self::Key) -> Result<Self::Value> {
+        // without a limit, the value is always there
+        Ok(self.fetch(key, None).await?.unwrap_or_default())
+    }
+
+    /// Uploads the value and waits until it is retrievable, see [`ArweaveStorage::verify_upload`].
+    async fn put(&self, value: Self::Value) -> Result<Self::Key> {
+        let (value, encoding) = self.compress(value)?;
+        let receipt = self.put_with_receipt(value).await?;
+        self.verify_upload(&receipt).await?;
+        Ok(ArweaveKey::new(receipt.id).with_encoding(encoding))
     }
 
     /// Check if key is an Arweave key, which is a JSON object of type `{arweave: string}`
@@ -225,9 +734,12 @@ impl IsExternalStorage for ArweaveStorage {
     /// ```json
     /// { arweave: "Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA" }
     /// ```
+    ///
+    /// The key may also refer to an item within a bundle with an `item` field,
+    /// and mark the compression & encryption of the upload with `encoding` & `enc` fields.
     #[inline(always)]
     fn is_key(key: impl AsRef<str>) -> Option<Self::Key> {
-        serde_json::from_str::<ArweaveKey>(key.as_ref()).ok()
+        ArweaveKey::parse(key)
     }
 
     #[inline(always)]
@@ -240,6 +752,81 @@ impl IsExternalStorage for ArweaveStorage {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_winston() {
+        assert_eq!(parse_winston("1.5").unwrap(), 1_500_000_000_000);
+        assert_eq!(parse_winston("0.000000000001").unwrap(), 1);
+        assert_eq!(parse_winston("2").unwrap(), 2 * WINSTON_PER_AR);
+        assert!(parse_winston("0.0000000000001").is_err());
+        assert!(parse_winston("abc").is_err());
+
+        assert_eq!(format_winston(1_500_000_000_000), "1.5 AR");
+        assert_eq!(format_winston(2 * WINSTON_PER_AR), "2 AR");
+        assert_eq!(format_winston(1), "0.000000000001 AR");
+    }
+
+    #[tokio::test]
+    async fn test_verify_upload() {
+        // nothing listens on this port, so the upload is never retrievable
+        let arweave = ArweaveStorage::new_readonly()
+            .with_download_base_url("http://127.0.0.1:9/")
+            .unwrap();
+        let receipt = ArweaveReceipt {
+            id: "Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA".to_string(),
+            signature: String::new(),
+            timestamp: 0,
+            deadline_height: 0,
+        };
+        assert!(!arweave.is_retrievable(&receipt.id).await.unwrap());
+
+        // verification is opt-in
+        assert!(arweave.verify_upload(&receipt).await.is_ok());
+
+        let started_at = Instant::now();
+        let arweave = arweave.with_verify_timeout(Some(Duration::from_secs(2)));
+        assert!(arweave.verify_upload(&receipt).await.is_err());
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_get_within() {
+        // serves 100 bytes to each request, with a `Content-Length` header only if the path asks for it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0u8; 1024];
+                let len = stream.read(&mut request).unwrap_or_default();
+                let sized = String::from_utf8_lossy(&request[..len]).contains("GET /sized");
+                let header = match sized {
+                    true => "HTTP/1.1 200 OK\r\nContent-Length: 100\r\nConnection: close\r\n\r\n",
+                    false => "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n",
+                };
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(&[b'a'; 100]);
+            }
+        });
+
+        let arweave = ArweaveStorage::new_readonly()
+            .with_download_base_url(&base_url)
+            .unwrap();
+        for id in ["sized", "streamed"] {
+            let value = arweave.get_within(ArweaveKey::new(id), 100).await.unwrap();
+            assert_eq!(value.unwrap().len(), 100);
+            let value = arweave.get_within(ArweaveKey::new(id), 99).await.unwrap();
+            assert!(value.is_none());
+        }
+        assert_eq!(
+            arweave
+                .get(ArweaveKey::new("streamed"))
+                .await
+                .unwrap()
+                .len(),
+            100
+        );
+    }
+
     #[tokio::test]
     #[ignore = "run manually"]
     async fn test_download_data() -> Result<()> {
@@ -247,7 +834,7 @@ mod tests {
 
         // https://gateway.irys.xyz/Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA
         let tx_id = "Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA".to_string();
-        let key = ArweaveKey { arweave: tx_id };
+        let key = ArweaveKey::new(tx_id);
         let arweave = ArweaveStorage::new_from_env()?;
 
         let result = arweave.get(key).await?;
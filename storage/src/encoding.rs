@@ -0,0 +1,68 @@
+use eyre::{eyre, Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::io::{Read, Write};
+
+/// Maximum size of a decoded value, so that a small malicious upload can not exhaust the memory.
+const MAX_DECODED_BYTES: u64 = 64 * 1024 * 1024; // 64MB
+
+/// Encoding of an uploaded value, marked within its key so that it can be decoded after download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    /// Gzip compression.
+    Gzip,
+}
+
+impl ContentEncoding {
+    /// Encodes the given value.
+    pub fn encode(&self, value: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(value)?;
+                encoder.finish().wrap_err("could not compress value")
+            }
+        }
+    }
+
+    /// Decodes the given value, returns an error if the decoded value is too large.
+    pub fn decode(&self, value: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut decoded = Vec::new();
+                GzDecoder::new(value)
+                    .take(MAX_DECODED_BYTES + 1)
+                    .read_to_end(&mut decoded)
+                    .wrap_err("could not decompress value")?;
+                if decoded.len() as u64 > MAX_DECODED_BYTES {
+                    return Err(eyre!(
+                        "Decompressed value exceeds {} bytes",
+                        MAX_DECODED_BYTES
+                    ));
+                }
+
+                Ok(decoded)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gzip() {
+        let value = r#"[{"role": "user", "content": "Hello!"}]"#.repeat(100);
+        let encoded = ContentEncoding::Gzip.encode(value.as_bytes()).unwrap();
+        assert!(encoded.len() < value.len());
+
+        let decoded = ContentEncoding::Gzip.decode(&encoded).unwrap();
+        assert_eq!(decoded, value.as_bytes());
+
+        assert_eq!(
+            serde_json::to_string(&ContentEncoding::Gzip).unwrap(),
+            r#""gzip""#
+        );
+    }
+}
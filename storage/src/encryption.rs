@@ -0,0 +1,90 @@
+use alloy::hex;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use eyre::{eyre, Context, Result};
+
+use super::EncryptionScheme;
+
+/// Size of the nonce that is prepended to the ciphertext.
+const NONCE_SIZE: usize = 12;
+
+/// Symmetric encryption of the uploaded values, so that only the parties holding the key can read them.
+#[derive(Clone)]
+pub struct StorageCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl StorageCipher {
+    /// Scheme of this cipher.
+    pub const SCHEME: EncryptionScheme = EncryptionScheme::ChaCha20Poly1305;
+
+    /// Creates a cipher from a 32-byte key, given as a hexadecimal string with an optional `0x` prefix.
+    pub fn from_hex(key: &str) -> Result<Self> {
+        let key = hex::decode(key).wrap_err("could not decode encryption key")?;
+        if key.len() != 32 {
+            return Err(eyre!(
+                "Encryption key must be 32 bytes, got {} bytes",
+                key.len()
+            ));
+        }
+
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        })
+    }
+
+    /// Encrypts the value, returning the nonce followed by the ciphertext.
+    pub fn encrypt(&self, value: &[u8]) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, value)
+            .map_err(|_| eyre!("could not encrypt value"))?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Decrypts a value that is encrypted with [`Self::encrypt`].
+    pub fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>> {
+        if value.len() < NONCE_SIZE {
+            return Err(eyre!("Encrypted value is too short"));
+        }
+        let (nonce, ciphertext) = value.split_at(NONCE_SIZE);
+
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| eyre!("could not decrypt value, is the key correct?"))
+    }
+}
+
+impl std::fmt::Debug for StorageCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the key is not printed
+        write!(f, "StorageCipher({:?})", Self::SCHEME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cipher() {
+        let cipher = StorageCipher::from_hex(&"ab".repeat(32)).unwrap();
+        let value = br#"{"messages": [{"role": "user", "content": "secret"}]}"#;
+
+        let encrypted = cipher.encrypt(value).unwrap();
+        assert_ne!(&encrypted[NONCE_SIZE..], value);
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), value);
+
+        // a different key can not decrypt
+        let other = StorageCipher::from_hex(&"cd".repeat(32)).unwrap();
+        assert!(other.decrypt(&encrypted).is_err());
+
+        assert!(StorageCipher::from_hex("abcd").is_err());
+        assert_eq!(
+            serde_json::to_string(&StorageCipher::SCHEME).unwrap(),
+            r#""chacha20poly1305""#
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use eyre::{eyre, Context, Result};
+use std::collections::HashMap;
+
+use super::ContentEncoding;
+
+/// Encryption scheme of an uploaded value, marked within its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptionScheme {
+    /// ChaCha20-Poly1305 with a random nonce prepended to the ciphertext.
+    ChaCha20Poly1305,
+}
+
+/// A key to a value stored on Arweave, as it appears on-chain, e.g.:
+///
+/// ```json
+/// { "arweave": "Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA" }
+/// ```
+///
+/// The key may also refer to an item within a bundle with an `item` field,
+/// and mark the compression & encryption of the upload with `encoding` & `enc` fields.
+/// Such a key is marked with its version, e.g. `"v": 2`, as the consumers that only know the
+/// plain keys above would otherwise download the bundle or the encoded upload as the value.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct ArweaveKey {
+    /// The base64url encoded key, can be used to download data directly.
+    pub arweave: String,
+    /// Version of the key format, which is omitted for the plain keys of version 1.
+    #[serde(
+        rename = "v",
+        default = "ArweaveKey::plain_version",
+        skip_serializing_if = "ArweaveKey::is_plain_version"
+    )]
+    pub version: u8,
+    /// Name of the item within a bundle (see `ArweaveStorage::put_all_if_large`),
+    /// `None` if the upload is the value itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    /// Encoding of the upload (see `ArweaveStorage::with_compression_threshold`),
+    /// `None` if it is stored as is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<ContentEncoding>,
+    /// Encryption scheme of the upload (see `ArweaveStorage::put_encrypted_with_receipt`),
+    /// `None` if it is public.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enc: Option<EncryptionScheme>,
+}
+
+impl ArweaveKey {
+    /// Latest version of the key format that can be decoded, i.e. the one with the `item`,
+    /// `encoding` & `enc` fields.
+    pub const VERSION: u8 = 2;
+
+    /// Creates a plain key to the upload with the given id, which is the value itself.
+    pub fn new(arweave: impl Into<String>) -> Self {
+        Self {
+            arweave: arweave.into(),
+            version: Self::plain_version(),
+            item: None,
+            encoding: None,
+            enc: None,
+        }
+    }
+
+    /// Refers to the given item within the bundle of the upload.
+    pub fn with_item(mut self, item: impl Into<String>) -> Self {
+        self.item = Some(item.into());
+        self.version = Self::VERSION;
+        self
+    }
+
+    /// Marks the encoding of the upload, if any.
+    pub fn with_encoding(mut self, encoding: Option<ContentEncoding>) -> Self {
+        if encoding.is_some() {
+            self.encoding = encoding;
+            self.version = Self::VERSION;
+        }
+        self
+    }
+
+    /// Marks the encryption scheme of the upload.
+    pub fn with_encryption(mut self, enc: EncryptionScheme) -> Self {
+        self.enc = Some(enc);
+        self.version = Self::VERSION;
+        self
+    }
+
+    /// Parses the key, returns `None` if the given value is not a key.
+    ///
+    /// Keys of a newer version are still parsed, so that they are not taken as values;
+    /// they fail to decode instead.
+    #[inline]
+    pub fn parse(value: impl AsRef<str>) -> Option<Self> {
+        serde_json::from_str(value.as_ref()).ok()
+    }
+
+    #[inline]
+    fn plain_version() -> u8 {
+        1
+    }
+
+    #[inline]
+    fn is_plain_version(version: &u8) -> bool {
+        *version == Self::plain_version()
+    }
+
+    /// Decodes the downloaded upload of this key to its value, i.e. decompresses it and
+    /// takes the item within the bundle, if any.
+    ///
+    /// An encrypted upload must be decrypted before, as the key does not hold the cipher.
+    pub fn decode(&self, upload: &[u8]) -> Result<Vec<u8>> {
+        if self.version > Self::VERSION {
+            return Err(eyre!(
+                "Key {} has version {}, while at most {} is supported; please update",
+                self.arweave,
+                self.version,
+                Self::VERSION
+            ));
+        }
+
+        let upload = match self.encoding {
+            Some(encoding) => encoding.decode(upload)?,
+            None => upload.to_vec(),
+        };
+
+        match &self.item {
+            Some(item) => {
+                let mut bundle = serde_json::from_slice::<HashMap<String, String>>(&upload)
+                    .wrap_err("could not parse Arweave bundle")?;
+                let value = bundle.remove(item).ok_or_else(|| {
+                    eyre!("Item {} not found within bundle {}", item, self.arweave)
+                })?;
+                Ok(value.into_bytes())
+            }
+            None => Ok(upload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arweave_key_version() {
+        // plain keys are written as before, so that older consumers can still read them
+        let id = "Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA";
+        let plain = format!(r#"{{"arweave":"{}"}}"#, id);
+        assert_eq!(serde_json::to_string(&ArweaveKey::new(id)).unwrap(), plain);
+        assert_eq!(ArweaveKey::parse(&plain).unwrap().version, 1);
+        assert_eq!(ArweaveKey::new(id).with_encoding(None).version, 1);
+
+        let key = ArweaveKey::new(id).with_encoding(Some(ContentEncoding::Gzip));
+        let key_str = serde_json::to_string(&key).unwrap();
+        assert_eq!(
+            key_str,
+            format!(r#"{{"arweave":"{}","v":2,"encoding":"gzip"}}"#, id)
+        );
+        assert_eq!(ArweaveKey::parse(&key_str).unwrap().version, 2);
+
+        let newer = ArweaveKey::parse(format!(r#"{{"arweave":"{}","v":3}}"#, id)).unwrap();
+        assert!(newer.decode(b"hello").is_err());
+    }
+
+    #[test]
+    fn test_arweave_key() {
+        let key = ArweaveKey::parse(
+            r#"{"arweave":"Zg6CZYfxXCWYnCuKEpnZCYfy7ghit1_v4-BCe53iWuA","v":2,"item":"output","encoding":"gzip"}"#,
+        )
+        .unwrap();
+        assert_eq!(key.item.as_deref(), Some("output"));
+        assert_eq!(key.encoding, Some(ContentEncoding::Gzip));
+        assert!(ArweaveKey::parse("hello").is_none());
+
+        let bundle = ContentEncoding::Gzip
+            .encode(br#"{"output":"hello","metadata":"{}"}"#)
+            .unwrap();
+        assert_eq!(key.decode(&bundle).unwrap(), b"hello");
+
+        let key = ArweaveKey {
+            item: Some("missing".to_string()),
+            ..key
+        };
+        assert!(key.decode(&bundle).is_err());
+    }
+}
@@ -1,5 +1,25 @@
+//! External storages of the Dria Oracle, i.e. Arweave.
+//!
+//! The keys & encodings of the stored values are always available, so that the values can be read
+//! on `wasm32` as well. The storage itself requires the `arweave` feature, which is enabled by default.
+
+#[cfg(feature = "arweave")]
 mod arweave;
-pub use arweave::ArweaveStorage;
+#[cfg(feature = "arweave")]
+pub use arweave::{format_winston, parse_winston, ArweaveReceipt, ArweaveStorage};
+
+mod key;
+pub use key::{ArweaveKey, EncryptionScheme};
+
+mod encoding;
+pub use encoding::ContentEncoding;
+
+#[cfg(feature = "arweave")]
+mod encryption;
+#[cfg(feature = "arweave")]
+pub use encryption::StorageCipher;
 
+#[cfg(feature = "arweave")]
 mod traits;
+#[cfg(feature = "arweave")]
 pub use traits::IsExternalStorage;
@@ -0,0 +1,111 @@
+//! A local devnet for developing against the oracle, see `dria-oracle-devnet --help`.
+
+use alloy::hex;
+use alloy::node_bindings::Anvil;
+use alloy::primitives::Address;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::http::reqwest::Url;
+use clap::{Parser, Subcommand};
+use dria_oracle::DriaOracleConfig;
+use dria_oracle_contracts::OracleKind;
+use dria_oracle_testing::TestCoordinator;
+use eyre::Result;
+
+#[derive(Parser)]
+#[command(name = "dria-oracle-devnet", version)]
+#[command(about = "Local devnet for the Dria Oracle, on an Anvil fork.", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Fork the chain with Anvil, fund a requester, a generator & a validator, and print their env.
+    ///
+    /// The devnet is kept up until Ctrl+C.
+    Up {
+        #[arg(
+            long,
+            env = "RPC_URL",
+            help = "RPC URL of the chain to fork, where the coordinator is deployed."
+        )]
+        fork_url: Url,
+        #[arg(long, default_value_t = 8545, help = "Port of the Anvil fork.")]
+        port: u16,
+        #[arg(
+            long,
+            env = "COORDINATOR_ADDRESS",
+            help = "Coordinator to use, instead of the known deployment of the forked chain."
+        )]
+        coordinator: Option<Address>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenvy::dotenv();
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Off)
+        .filter_module("dria_oracle", log::LevelFilter::Info)
+        .filter_module("dria_oracle_testing", log::LevelFilter::Info)
+        .filter_module("dria_oracle_devnet", log::LevelFilter::Info)
+        .parse_default_env()
+        .init();
+
+    match Cli::parse().command {
+        Commands::Up {
+            fork_url,
+            port,
+            coordinator,
+        } => up(fork_url, port, coordinator).await,
+    }
+}
+
+async fn up(fork_url: Url, port: u16, coordinator_address: Option<Address>) -> Result<()> {
+    // the devnet only reads with its own wallet, the others are funded explicitly
+    let mut config = DriaOracleConfig::new(&PrivateKeySigner::random().to_bytes(), fork_url)?;
+    if let Some(coordinator_address) = coordinator_address {
+        config = config.with_coordinator_address(coordinator_address);
+    }
+    let devnet = TestCoordinator::with_anvil(config, Anvil::new().port(port)).await?;
+
+    let requester_signer = PrivateKeySigner::random();
+    devnet.fund(requester_signer.clone()).await?;
+    log::info!("Funded requester {}", requester_signer.address());
+
+    let generator_signer = PrivateKeySigner::random();
+    let generator = devnet.fund(generator_signer.clone()).await?;
+    generator.register(OracleKind::Generator).await?;
+    log::info!("Registered generator {}", generator_signer.address());
+
+    let validator_signer = PrivateKeySigner::random();
+    let validator = devnet.fund(validator_signer.clone()).await?;
+    devnet.whitelist(validator_signer.address()).await?;
+    validator.register(OracleKind::Validator).await?;
+    log::info!("Registered validator {}", validator_signer.address());
+
+    let coordinator = devnet.node.coordinator.address();
+    for (role, signer) in [
+        ("requester", &requester_signer),
+        ("generator", &generator_signer),
+        ("validator", &validator_signer),
+    ] {
+        println!("\n# {} ({})", role, signer.address());
+        println!("RPC_URL={}", devnet.anvil_url());
+        println!("COORDINATOR_ADDRESS={}", coordinator);
+        println!("SECRET_KEY={}", hex::encode(signer.to_bytes()));
+    }
+    println!(
+        "\nSave each of them to a file and use it with e.g. `dria-oracle --env=.env.generator serve`."
+    );
+
+    log::info!(
+        "Devnet is up at {}, press Ctrl+C to stop.",
+        devnet.anvil_url()
+    );
+    tokio::signal::ctrl_c().await?;
+    log::info!("Stopping the devnet.");
+
+    Ok(())
+}
@@ -0,0 +1,256 @@
+//! Fault injection for soak tests, with an HTTP proxy that sits between a node and its RPC (or storage)
+//! and misbehaves on demand.
+//!
+//! Faults are given as hooks, which are asked in order for each call through the proxy, where the
+//! first [`FaultAction`] returned is applied and calls without one are forwarded as is. The common faults
+//! have helpers, e.g. [`FaultyProxy::drop_requests`], while new resilience features can add their own:
+//!
+//! ```rust,ignore
+//! let proxy = FaultyProxy::spawn(coordinator.anvil_url()).await?;
+//! proxy.add_hook(|call| {
+//!     (call.rpc_method.as_deref() == Some("eth_sendRawTransaction"))
+//!         .then_some(FaultAction::Status(503))
+//! });
+//! ```
+
+use alloy::transports::http::reqwest::{self, Url};
+use eyre::{Context, Result};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::CONTENT_TYPE;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// A call through the proxy, as seen by the hooks.
+#[derive(Debug, Clone)]
+pub struct ProxyCall {
+    /// Path of the HTTP request, e.g. `/info` for the bundler.
+    pub path: String,
+    /// Method of the JSON-RPC request, if the body is one.
+    pub rpc_method: Option<String>,
+    /// Id of the JSON-RPC request, if the body is one.
+    rpc_id: Option<serde_json::Value>,
+}
+
+/// What to do with a call instead of forwarding it as is.
+#[derive(Debug, Clone)]
+pub enum FaultAction {
+    /// Close the connection without a response, like a dropped RPC.
+    Drop,
+    /// Respond with the given HTTP status & an empty body, like an outage.
+    Status(u16),
+    /// Respond with a `null` JSON-RPC result, e.g. a receipt that is not available yet.
+    NullResult,
+    /// Forward the call after the given delay.
+    Delay(Duration),
+    /// Respond with a JSON-RPC error of the given message, e.g. `replacement transaction underpriced`.
+    RpcError(String),
+}
+
+/// A hook that decides the fault of a call, if any.
+pub type FaultHook = Arc<dyn Fn(&ProxyCall) -> Option<FaultAction> + Send + Sync>;
+
+struct ProxyState {
+    upstream: Url,
+    client: reqwest::Client,
+    hooks: RwLock<Vec<FaultHook>>,
+}
+
+/// An HTTP proxy in front of an upstream, which injects the faults given by its hooks.
+///
+/// Point a node to [`FaultyProxy::url`] instead of the upstream, e.g. with
+/// [`TestCoordinator::fund_with_rpc`](crate::TestCoordinator::fund_with_rpc) for the RPC,
+/// or with `ARWEAVE_BASE_URL` for the bundler. The proxy is stopped when dropped.
+pub struct FaultyProxy {
+    url: Url,
+    state: Arc<ProxyState>,
+    handle: JoinHandle<()>,
+}
+
+impl FaultyProxy {
+    /// Starts a proxy to the given upstream at a free local port.
+    pub async fn spawn(upstream: Url) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .wrap_err("could not bind the proxy")?;
+        let url = Url::parse(&format!("http://{}", listener.local_addr()?))?;
+        let state = Arc::new(ProxyState {
+            upstream,
+            client: reqwest::Client::new(),
+            hooks: RwLock::new(Vec::new()),
+        });
+
+        let handle = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        let service = service_fn(|req| handle_call(state.clone(), req));
+                        // dropped calls end the connection with an error, which is expected
+                        let _ = http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), service)
+                            .await;
+                    });
+                }
+            }
+        });
+        log::info!("Proxying {} at {}", state.upstream, url);
+
+        Ok(Self { url, state, handle })
+    }
+
+    /// URL of the proxy.
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// Adds a hook, which is asked after the existing ones.
+    pub fn add_hook(
+        &self,
+        hook: impl Fn(&ProxyCall) -> Option<FaultAction> + Send + Sync + 'static,
+    ) {
+        self.state.hooks.write().unwrap().push(Arc::new(hook));
+    }
+
+    /// Removes all hooks, so that the calls are forwarded as is again.
+    pub fn clear_faults(&self) {
+        self.state.hooks.write().unwrap().clear();
+    }
+
+    /// Drops the next `count` calls.
+    pub fn drop_requests(&self, count: usize) {
+        let remaining = AtomicUsize::new(count);
+        self.add_hook(move |_| {
+            remaining
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .ok()
+                .map(|_| FaultAction::Drop)
+        });
+    }
+
+    /// Responds to all calls with the given status until the faults are cleared, e.g. `503` for a storage outage.
+    pub fn fail_all(&self, status: u16) {
+        self.add_hook(move |_| Some(FaultAction::Status(status)));
+    }
+
+    /// Responds to the receipt queries with `null` for the given duration, as if the transactions are
+    /// taking long to be included.
+    pub fn delay_receipts(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        self.add_hook(move |call| {
+            (call.rpc_method.as_deref() == Some("eth_getTransactionReceipt")
+                && Instant::now() < until)
+                .then_some(FaultAction::NullResult)
+        });
+    }
+
+    /// Delays the calls of the given JSON-RPC method.
+    pub fn delay_method(&self, method: impl Into<String>, delay: Duration) {
+        let method = method.into();
+        self.add_hook(move |call| {
+            (call.rpc_method.as_ref() == Some(&method)).then_some(FaultAction::Delay(delay))
+        });
+    }
+}
+
+impl Drop for FaultyProxy {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Applies the fault of the call if there is one, and forwards it to the upstream otherwise.
+async fn handle_call(
+    state: Arc<ProxyState>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, io::Error> {
+    let (parts, body) = req.into_parts();
+    let body = body.collect().await.map_err(io::Error::other)?.to_bytes();
+    let rpc = serde_json::from_slice::<serde_json::Value>(&body).ok();
+    let call = ProxyCall {
+        path: parts.uri.path().to_string(),
+        rpc_method: rpc
+            .as_ref()
+            .and_then(|rpc| rpc.get("method")?.as_str().map(String::from)),
+        rpc_id: rpc.as_ref().and_then(|rpc| rpc.get("id").cloned()),
+    };
+
+    // the lock is not held across the await points below
+    let action = state
+        .hooks
+        .read()
+        .unwrap()
+        .iter()
+        .find_map(|hook| hook(&call));
+    match action {
+        Some(FaultAction::Drop) => {
+            log::debug!(
+                "Dropping {:?}",
+                call.rpc_method.as_ref().unwrap_or(&call.path)
+            );
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "dropped by the proxy",
+            ));
+        }
+        Some(FaultAction::Status(status)) => {
+            return Ok(Response::builder()
+                .status(status)
+                .body(Full::default())
+                .map_err(io::Error::other)?);
+        }
+        Some(FaultAction::NullResult) => {
+            let body = serde_json::json!({ "jsonrpc": "2.0", "id": call.rpc_id, "result": null });
+            return Ok(Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .body(Full::new(Bytes::from(body.to_string())))
+                .map_err(io::Error::other)?);
+        }
+        Some(FaultAction::RpcError(message)) => {
+            let body = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": call.rpc_id,
+                "error": { "code": -32000, "message": message },
+            });
+            return Ok(Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .body(Full::new(Bytes::from(body.to_string())))
+                .map_err(io::Error::other)?);
+        }
+        Some(FaultAction::Delay(delay)) => tokio::time::sleep(delay).await,
+        None => {}
+    }
+
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let url = state
+        .upstream
+        .join(path.trim_start_matches('/'))
+        .map_err(io::Error::other)?;
+    let mut request = state.client.request(parts.method, url).body(body);
+    if let Some(content_type) = parts.headers.get(CONTENT_TYPE) {
+        request = request.header(CONTENT_TYPE, content_type);
+    }
+    let response = request.send().await.map_err(io::Error::other)?;
+    let status = StatusCode::from_u16(response.status().as_u16()).map_err(io::Error::other)?;
+    let content_type = response.headers().get(CONTENT_TYPE).cloned();
+    let body = response.bytes().await.map_err(io::Error::other)?;
+
+    let mut builder = Response::builder().status(status);
+    if let Some(content_type) = content_type {
+        builder = builder.header(CONTENT_TYPE, content_type);
+    }
+    builder.body(Full::new(body)).map_err(io::Error::other)
+}
@@ -0,0 +1,262 @@
+//! A test harness for the integrations of the Dria Oracle, so that protocols can test their requests
+//! end-to-end against real oracle nodes without deploying anything.
+//!
+//! A [`TestCoordinator`] spawns an Anvil fork of a chain where the coordinator is deployed, along with
+//! a funded requester. Generators & validators are added with funded wallets, registered & whitelisted
+//! as needed, and each of them handles the tasks just like a served node would:
+//!
+//! ```rust,ignore
+//! let mut coordinator = TestCoordinator::from_env().await?;
+//! coordinator.add_generator(vec![Model::GPT4oMini]).await?;
+//! coordinator.add_validator().await?;
+//!
+//! let request = ChatRequestBuilder::new("What is 2+2?")
+//!     .with_model(Model::GPT4oMini)
+//!     .build()?;
+//! let task = coordinator.run_cycle(request).await?;
+//! assert!(task.responses[0].output.content().contains('4'));
+//! ```
+//!
+//! Anvil must be installed, see <https://book.getfoundry.sh/getting-started/installation>. The nodes connect
+//! to the spawned fork over HTTP, so the `anvil` feature of `dria-oracle` is not needed (nor supported);
+//! the funding, whitelisting & reorgs are made with the helpers of its `testing` feature instead.
+
+mod faults;
+pub use faults::{FaultAction, FaultHook, FaultyProxy, ProxyCall};
+
+use alloy::network::EthereumWallet;
+use alloy::node_bindings::{Anvil, AnvilInstance};
+use alloy::primitives::{utils::parse_ether, Address, U256};
+use alloy::rpc::types::TransactionReceipt;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::http::reqwest::Url;
+use clap::Parser;
+use dkn_workflows::Model;
+use dria_oracle::{
+    handle_command, handle_request, Cli, DriaOracle, DriaOracleConfig, OracleRequest, TaskView,
+};
+use dria_oracle_contracts::{OracleKind, TaskStatus};
+use eyre::{eyre, Context, Result};
+use tokio::task::JoinHandle;
+
+/// Fee tokens deposited to each of the wallets created by the harness, for stakes & fees.
+const FUND_TOKENS: &str = "100";
+
+/// A coordinator on a local Anvil fork, with the oracles to handle its tasks.
+pub struct TestCoordinator {
+    /// Node with the wallet of the given config, used to read from the coordinator.
+    pub node: DriaOracle,
+    /// Node that makes the requests, funded with fee tokens.
+    pub requester: DriaOracle,
+    /// Registered generators, in the order they are added.
+    pub generators: Vec<DriaOracle>,
+    /// Registered & whitelisted validators, in the order they are added.
+    pub validators: Vec<DriaOracle>,
+    /// The fork, which is killed when the coordinator is dropped.
+    anvil: AnvilInstance,
+}
+
+impl TestCoordinator {
+    /// Forks the chain at the RPC of the given config, and creates a funded requester.
+    ///
+    /// The local database of the nodes is kept under a temporary directory, so that the tests
+    /// do not interfere with a node that is served from the same directory.
+    pub async fn new(config: DriaOracleConfig) -> Result<Self> {
+        Self::with_anvil(config, Anvil::new()).await
+    }
+
+    /// Same as [`Self::new`], with the given Anvil builder, e.g. to spawn it at a certain port.
+    pub async fn with_anvil(config: DriaOracleConfig, anvil: Anvil) -> Result<Self> {
+        let anvil = anvil
+            .fork(config.rpc_url.to_string())
+            .try_spawn()
+            .wrap_err("could not spawn Anvil, is it installed?")?;
+        log::info!(
+            "Spawned Anvil fork of {} at {}",
+            config.rpc_url,
+            anvil.endpoint()
+        );
+
+        let mut config = config.with_data_dir(
+            std::env::temp_dir().join(format!("dria-oracle-testing-{}", anvil.port())),
+        );
+        config.rpc_url = anvil.endpoint_url();
+        let node = DriaOracle::new(config).await?;
+        let requester = node
+            .anvil_new_funded_oracle(parse_ether(FUND_TOKENS)?)
+            .await?;
+
+        Ok(Self {
+            node,
+            requester,
+            generators: Vec::new(),
+            validators: Vec::new(),
+            anvil,
+        })
+    }
+
+    /// Forks the chain with the config from the environment, see [`DriaOracleConfig::new_from_env`].
+    pub async fn from_env() -> Result<Self> {
+        Self::new(DriaOracleConfig::new_from_env()?).await
+    }
+
+    /// URL of the fork, e.g. to connect the contracts of a protocol to it.
+    pub fn anvil_url(&self) -> Url {
+        self.anvil.endpoint_url()
+    }
+
+    /// Creates a node with a new wallet, funded with ETH and fee tokens.
+    pub async fn new_funded_oracle(&self) -> Result<DriaOracle> {
+        self.fund(PrivateKeySigner::random()).await
+    }
+
+    /// Funds the given signer with ETH and fee tokens, returns a node with it as its wallet.
+    pub async fn fund(&self, signer: PrivateKeySigner) -> Result<DriaOracle> {
+        self.node
+            .anvil_funded_oracle(signer, parse_ether(FUND_TOKENS)?)
+            .await
+    }
+
+    /// Funds the given signer like [`Self::fund`], returns a node that connects to the fork through the
+    /// given RPC URL instead, e.g. a [`FaultyProxy`] in front of the fork.
+    pub async fn fund_with_rpc(
+        &self,
+        signer: PrivateKeySigner,
+        rpc_url: Url,
+    ) -> Result<DriaOracle> {
+        self.fund(signer.clone()).await?;
+
+        let mut config = self.node.config.clone();
+        config.wallet = EthereumWallet::from(signer);
+        config.rpc_url = rpc_url;
+        DriaOracle::new(config).await
+    }
+
+    /// Takes a snapshot of the fork, e.g. after the generators & validators are added, returns its id.
+    ///
+    /// The cases that share an expensive setup can then start from the same state with [`Self::restore`]:
+    ///
+    /// ```rust,ignore
+    /// let mut snapshot = coordinator.snapshot().await?;
+    /// for request in requests {
+    ///     coordinator.run_cycle(request).await?;
+    ///     coordinator.restore(&mut snapshot).await?;
+    /// }
+    /// ```
+    pub async fn snapshot(&self) -> Result<U256> {
+        self.node.anvil_snapshot().await
+    }
+
+    /// Reverts the fork to the given snapshot, which is taken again so that it can be restored later as well.
+    pub async fn restore(&self, snapshot: &mut U256) -> Result<()> {
+        self.node.anvil_restore(snapshot).await
+    }
+
+    /// Replaces the latest `depth` blocks of the fork with empty ones, dropping their transactions.
+    pub async fn reorg(&self, depth: u64) -> Result<()> {
+        self.node.anvil_reorg(depth).await
+    }
+
+    /// Serves the given node in the background with the given arguments of the `serve` command,
+    /// e.g. `["generator", "-m=gpt-4o-mini"]`. The node is stopped when the handle is aborted.
+    ///
+    /// The registrations are not made by the node, see [`DriaOracle::register`].
+    pub fn serve(&self, node: DriaOracle, args: &[&str]) -> Result<JoinHandle<Result<()>>> {
+        let cli = Cli::try_parse_from(
+            ["dria-oracle", "serve"]
+                .into_iter()
+                .chain(args.iter().copied()),
+        )?;
+
+        Ok(tokio::spawn(handle_command(cli, node)))
+    }
+
+    /// Whitelists the given address as a validator, by impersonating the owner of the registry.
+    pub async fn whitelist(&self, address: Address) -> Result<TransactionReceipt> {
+        self.node.anvil_whitelist_registry(address).await
+    }
+
+    /// Adds a registered generator that serves the given models, returns its address.
+    pub async fn add_generator(&mut self, models: Vec<Model>) -> Result<Address> {
+        let mut generator = self.new_funded_oracle().await?;
+        generator.register(OracleKind::Generator).await?;
+        generator
+            .prepare_oracle(vec![OracleKind::Generator], models)
+            .await?;
+
+        let address = generator.address();
+        self.generators.push(generator);
+        Ok(address)
+    }
+
+    /// Adds a registered & whitelisted validator, returns its address.
+    ///
+    /// Validations always use `gpt-4o`, so this requires an OpenAI API key.
+    pub async fn add_validator(&mut self) -> Result<Address> {
+        let mut validator = self.new_funded_oracle().await?;
+        self.whitelist(validator.address()).await?;
+        validator.register(OracleKind::Validator).await?;
+        validator
+            .prepare_oracle(vec![OracleKind::Validator], vec![Model::GPT4o])
+            .await?;
+
+        let address = validator.address();
+        self.validators.push(validator);
+        Ok(address)
+    }
+
+    /// Makes the given request from the requester, returns the id of the created task.
+    pub async fn request(&self, request: OracleRequest) -> Result<U256> {
+        self.requester.send_request(request).await
+    }
+
+    /// Has the generators (for [`TaskStatus::PendingGeneration`]) or the validators
+    /// (for [`TaskStatus::PendingValidation`]) handle the task, until it is no longer in that status.
+    ///
+    /// Returns the receipts of the responses, where the oracles that skip the task have none.
+    pub async fn respond(
+        &self,
+        task_id: U256,
+        status: TaskStatus,
+    ) -> Result<Vec<TransactionReceipt>> {
+        let oracles = match status {
+            TaskStatus::PendingGeneration => &self.generators,
+            TaskStatus::PendingValidation => &self.validators,
+            _ => return Err(eyre!("Task {} can not be responded as {}", task_id, status)),
+        };
+
+        let mut receipts = Vec::new();
+        for oracle in oracles {
+            let request = self.node.coordinator.requests(task_id).call().await?;
+            if request.status != u8::from(status) {
+                break;
+            }
+
+            if let Some(receipt) = handle_request(oracle, status, task_id, request.protocol).await?
+            {
+                receipts.push(receipt);
+            }
+        }
+
+        Ok(receipts)
+    }
+
+    /// Runs the full cycle of a task, i.e. makes the request, has it generated & validated,
+    /// and returns the completed task.
+    pub async fn run_cycle(&self, request: OracleRequest) -> Result<TaskView> {
+        let task_id = self.request(request).await?;
+        self.respond(task_id, TaskStatus::PendingGeneration).await?;
+        self.respond(task_id, TaskStatus::PendingValidation).await?;
+
+        let task = self.node.get_task(task_id).await?;
+        if !matches!(task.request.status, TaskStatus::Completed) {
+            return Err(eyre!(
+                "Task {} is not completed, it is {}; are there enough generators & validators?",
+                task_id,
+                task.request.status
+            ));
+        }
+
+        Ok(task)
+    }
+}
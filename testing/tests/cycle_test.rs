@@ -0,0 +1,112 @@
+//! Test the harness with a simple 2 + 2 question.
+//!
+//! ```sh
+//! cargo test --package dria-oracle-testing --test cycle_test -- test_cycle_two_plus_two --exact --show-output --ignored
+//! ```
+
+use dkn_workflows::Model;
+use dria_oracle::ChatRequestBuilder;
+use dria_oracle_testing::TestCoordinator;
+use eyre::Result;
+
+#[tokio::test]
+#[ignore = "requires Anvil, an RPC and an OpenAI API key"]
+async fn test_cycle_two_plus_two() -> Result<()> {
+    dotenvy::dotenv().unwrap();
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Off)
+        .filter_module("dria_oracle", log::LevelFilter::Debug)
+        .is_test(true)
+        .try_init();
+
+    let mut coordinator = TestCoordinator::from_env().await?;
+    coordinator.add_generator(vec![Model::GPT4o]).await?;
+    coordinator.add_validator().await?;
+
+    let request = ChatRequestBuilder::new("What is the result of 2 + 2?")
+        .with_model(Model::GPT4o)
+        .with_protocol(format!("test/{}", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let task = coordinator.run_cycle(request).await?;
+
+    assert_eq!(task.responses.len(), 1);
+    assert_eq!(task.validations.len(), 1);
+    assert!(task.responses[0].output.content().contains('4'));
+    assert!(!task.responses[0].score.is_zero(), "score must be non-zero");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Anvil, an RPC and an OpenAI API key"]
+async fn test_cycle_chat_history() -> Result<()> {
+    dotenvy::dotenv().unwrap();
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Off)
+        .filter_module("dria_oracle", log::LevelFilter::Debug)
+        .is_test(true)
+        .try_init();
+
+    let mut coordinator = TestCoordinator::from_env().await?;
+    coordinator.add_generator(vec![Model::GPT4o]).await?;
+    coordinator.add_validator().await?;
+    let mut snapshot = coordinator.snapshot().await?;
+
+    let request = ChatRequestBuilder::new("What is the result of 2 + 2?")
+        .with_model(Model::GPT4o)
+        .with_protocol(format!("test/{}", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let task = coordinator.run_cycle(request).await?;
+
+    // a completed task can be continued, while the ones that do not exist can not
+    let history_id = task.task_id.to::<usize>();
+    coordinator.node.check_chat_history(history_id).await?;
+    assert!(coordinator.node.check_chat_history(0).await.is_err());
+    assert!(coordinator
+        .node
+        .check_chat_history(history_id + 1)
+        .await
+        .is_err());
+
+    // the task is gone once the fork is restored to before the request
+    coordinator.restore(&mut snapshot).await?;
+    assert!(coordinator
+        .node
+        .check_chat_history(history_id)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Anvil, an RPC and an OpenAI API key"]
+async fn test_cycle_two_generators() -> Result<()> {
+    dotenvy::dotenv().unwrap();
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Off)
+        .filter_module("dria_oracle", log::LevelFilter::Debug)
+        .is_test(true)
+        .try_init();
+
+    // the generators share the database of the coordinator node, so each one must see
+    // only its own responses in there to respond to the same task
+    let mut coordinator = TestCoordinator::from_env().await?;
+    let first = coordinator.add_generator(vec![Model::GPT4o]).await?;
+    let second = coordinator.add_generator(vec![Model::GPT4o]).await?;
+    coordinator.add_validator().await?;
+
+    let request = ChatRequestBuilder::new("What is the result of 2 + 2?")
+        .with_model(Model::GPT4o)
+        .with_protocol(format!("test/{}", env!("CARGO_PKG_VERSION")))
+        .build()?
+        .with_generations(2);
+    let task = coordinator.run_cycle(request).await?;
+
+    assert_eq!(task.responses.len(), 2);
+    assert_eq!(task.validations.len(), 1);
+    assert_eq!(task.responses[0].responder, first);
+    assert_eq!(task.responses[1].responder, second);
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+//! Soak test of the serve loop of a generator, with faults injected between it and the fork, and between
+//! it and the Arweave bundler.
+//!
+//! The generator uses the mock model, so no API keys are required. Arweave must be configured with
+//! `ARWEAVE_WALLET_PATH`, as the storage faults are injected through `ARWEAVE_BASE_URL`.
+//!
+//! ```sh
+//! cargo test --package dria-oracle-testing --test soak_test -- --show-output --ignored
+//! ```
+
+use alloy::primitives::U256;
+use alloy::signers::local::PrivateKeySigner;
+use dkn_workflows::Model;
+use dria_oracle::{ChatRequestBuilder, WalDecision, WalEntry};
+use dria_oracle_contracts::{OracleKind, TaskStatus};
+use dria_oracle_testing::{FaultAction, FaultyProxy, TestCoordinator};
+use eyre::{eyre, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Bundler that the storage proxy forwards to, if `ARWEAVE_BASE_URL` is not set.
+const DEFAULT_BUNDLER_URL: &str = "https://node1.bundlr.network";
+
+/// Returns the status of the given task.
+async fn task_status(coordinator: &TestCoordinator, task_id: U256) -> Result<u8> {
+    let request = coordinator
+        .node
+        .coordinator
+        .requests(task_id)
+        .call()
+        .await?;
+
+    Ok(request.status)
+}
+
+/// Waits until the task has left the generation stage, i.e. the generator has responded.
+async fn wait_for_generation(coordinator: &TestCoordinator, task_id: U256) -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(120) {
+        if task_status(coordinator, task_id).await? != u8::from(TaskStatus::PendingGeneration) {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    Err(eyre!("Task {} is not generated in time", task_id))
+}
+
+/// Returns the write-ahead log entries of the generator for the given task.
+///
+/// The generator shares the data directory of the coordinator, so its log can be read from there.
+fn wal_entries(coordinator: &TestCoordinator, task_id: U256) -> Result<Vec<WalEntry>> {
+    let kind = OracleKind::Generator.to_string();
+    Ok(coordinator
+        .node
+        .db
+        .get_wal_entries(0)?
+        .into_iter()
+        .filter(|entry| entry.task_id == task_id && entry.kind == kind)
+        .collect())
+}
+
+/// Waits until the generator has sent its response to the given task.
+async fn wait_for_submission(coordinator: &TestCoordinator, task_id: U256) -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(120) {
+        if wal_entries(coordinator, task_id)?
+            .iter()
+            .any(|entry| matches!(entry.decision, WalDecision::Submitted { .. }))
+        {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    Err(eyre!("Task {} is not responded in time", task_id))
+}
+
+#[tokio::test]
+#[ignore = "requires Anvil, an RPC and an Arweave wallet"]
+async fn test_soak_with_faults() -> Result<()> {
+    dotenvy::dotenv().unwrap();
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Off)
+        .filter_module("dria_oracle", log::LevelFilter::Info)
+        .filter_module("dria_oracle_testing", log::LevelFilter::Debug)
+        .is_test(true)
+        .try_init();
+
+    let coordinator = TestCoordinator::from_env().await?;
+    let proxy = FaultyProxy::spawn(coordinator.anvil_url()).await?;
+    let bundler = std::env::var("ARWEAVE_BASE_URL")
+        .unwrap_or_else(|_| DEFAULT_BUNDLER_URL.to_string())
+        .parse()?;
+    let storage_proxy = FaultyProxy::spawn(bundler).await?;
+    std::env::set_var("ARWEAVE_BASE_URL", storage_proxy.url().as_str());
+
+    let signer = PrivateKeySigner::random();
+    let generator = coordinator
+        .fund_with_rpc(signer.clone(), proxy.url())
+        .await?;
+    generator.register(OracleKind::Generator).await?;
+
+    let fixtures = std::env::temp_dir().join(format!(
+        "dria-oracle-soak-fixtures-{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&fixtures, r#"{ "fixtures": [], "default": "soak" }"#)?;
+    let mock_model = format!("--mock-model={}", fixtures.display());
+    let args = ["generator", "-m=gpt-4o-mini", mock_model.as_str()];
+    let request = |n: usize| {
+        ChatRequestBuilder::new(format!("Soak test #{}", n))
+            .with_model(Model::GPT4oMini)
+            .build()
+    };
+
+    // a storage outage at start, during which the tasks are paused until the next health check
+    storage_proxy.fail_all(503);
+    let serve = coordinator.serve(generator, &args)?;
+    let task_id = coordinator.request(request(0)?).await?;
+    tokio::time::sleep(Duration::from_secs(15)).await;
+    assert_eq!(
+        task_status(&coordinator, task_id).await?,
+        u8::from(TaskStatus::PendingGeneration),
+        "tasks must be paused while the storage is down"
+    );
+    storage_proxy.clear_faults();
+    wait_for_generation(&coordinator, task_id).await?;
+
+    // dropped RPC calls
+    proxy.drop_requests(5);
+    let task_id = coordinator.request(request(1)?).await?;
+    wait_for_generation(&coordinator, task_id).await?;
+    proxy.clear_faults();
+
+    // underpriced transactions, which are sent again with a higher gas price
+    let sent = Arc::new(AtomicUsize::new(0));
+    proxy.add_hook({
+        let sent = sent.clone();
+        move |call| {
+            (call.rpc_method.as_deref() == Some("eth_sendRawTransaction")
+                && sent.fetch_add(1, Ordering::Relaxed) < 2)
+                .then(|| FaultAction::RpcError("replacement transaction underpriced".into()))
+        }
+    });
+    let task_id = coordinator.request(request(2)?).await?;
+    wait_for_generation(&coordinator, task_id).await?;
+    proxy.clear_faults();
+    assert!(
+        sent.load(Ordering::Relaxed) > 2,
+        "response must be sent again with gas hikes"
+    );
+
+    // a reorg that drops the response while its receipt is awaited, after which the node is restarted
+    proxy.delay_receipts(Duration::from_secs(120));
+    let task_id = coordinator.request(request(3)?).await?;
+    wait_for_submission(&coordinator, task_id).await?;
+    coordinator.reorg(1).await?;
+    assert_eq!(
+        task_status(&coordinator, task_id).await?,
+        u8::from(TaskStatus::PendingGeneration),
+        "response must be dropped by the reorg"
+    );
+    serve.abort();
+    let _ = serve.await;
+    proxy.clear_faults();
+
+    let generator = coordinator.fund_with_rpc(signer, proxy.url()).await?;
+    let serve = coordinator.serve(generator, &args)?;
+    wait_for_generation(&coordinator, task_id).await?;
+    let entries = wal_entries(&coordinator, task_id)?;
+    assert!(
+        entries
+            .iter()
+            .any(|entry| entry.decision == WalDecision::Resumed),
+        "interrupted task must be resumed"
+    );
+    assert!(
+        entries
+            .iter()
+            .filter(|entry| matches!(entry.decision, WalDecision::Submitting { .. }))
+            .count()
+            >= 2,
+        "dropped response must be retried"
+    );
+
+    // a reorg between tasks, after which the node keeps serving
+    coordinator.reorg(2).await?;
+    let task_id = coordinator.request(request(4)?).await?;
+    wait_for_generation(&coordinator, task_id).await?;
+
+    assert!(!serve.is_finished(), "serve loop must survive the faults");
+    serve.abort();
+    std::fs::remove_file(&fixtures)?;
+
+    Ok(())
+}